@@ -1,6 +1,10 @@
-use glam::{Mat4, Vec3};
+use std::cell::Cell;
+
+use glam::{Mat3, Mat4, Quat, Vec3, Vec4};
 use thiserror::Error;
 
+use crate::math_utils::{Aabb, Frustum, Ray};
+
 /// Camera assumes a right-handed system with the +Z axis going _out_ of the
 /// screen rather than in. This is an arbitrary choice and I decided to use RH
 /// because the abundance of OpenGL tutorials which typically assume RH over LH.
@@ -14,11 +18,17 @@ use thiserror::Error;
 /// WebGPU defines clip space to be a unit cube with values with the front bottom
 /// left corner as (-1, -1, -1) and the back top right corner (1, 1, 1).
 /// +X faces right, +Y is up and +Z is into the screen.
+#[derive(Clone, Debug)]
 pub struct Camera {
+    /// How the camera's view volume is projected onto the screen.
+    projection: Projection,
     /// The position of the camera in world space.
     eye: Vec3,
     /// The target position the camera should look at.
     target: Vec3,
+    /// Rotation from the camera's local space to world space. The forward,
+    /// right and up axes are derived from this rotation.
+    orientation: Quat,
     /// The camera's forward axis.
     forward: Vec3,
     /// The camera's right axis.
@@ -41,9 +51,43 @@ pub struct Camera {
     z_far: f32,
     viewport_width: f32,
     viewport_height: f32,
+    /// Matrices and frustum derived from the fields above, or `None` when a
+    /// field changed since they were last computed.
+    matrices: Cell<Option<CameraMatrices>>,
+}
+
+/// Matrices and frustum that are computed from a camera's position,
+/// orientation and projection when they are first needed, and then reused
+/// until the camera changes.
+#[derive(Clone, Copy, Debug)]
+struct CameraMatrices {
+    view: Mat4,
+    projection: Mat4,
+    view_projection: Mat4,
+    inverse_view: Mat4,
+    inverse_projection: Mat4,
+    inverse_view_projection: Mat4,
+    frustum: Frustum,
+}
+
+/// How a camera projects the world onto the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Objects farther from the camera appear smaller. The view widens with
+    /// distance from the camera according to the camera's vertical field of
+    /// view.
+    Perspective,
+    /// Objects appear the same size at any distance from the camera. The view
+    /// is a box `height` world units tall, and as wide as the aspect ratio
+    /// allows. Used for 2D scenes and for lights that shine in one direction.
+    Orthographic { height: f32 },
 }
 
 impl Camera {
+    /// The widest vertical field of view a camera can have, in radians. The
+    /// projection breaks down as the field of view approaches 180 degrees.
+    pub const MAX_FOV_Y: f32 = 179.0 * std::f32::consts::PI / 180.0;
+
     /// Create a new camera centered at `eye` with the center of the view
     /// aiming at `target` with `up` as the camera's upward direction.
     ///
@@ -60,7 +104,7 @@ impl Camera {
         viewport_width: u32,
         viewport_height: u32,
     ) -> Self {
-        assert!(fov_y > 0.0);
+        assert!(fov_y > 0.0 && fov_y <= Self::MAX_FOV_Y);
         assert!(z_near >= 0.0);
         assert!(z_far > z_near);
         assert!(eye != target);
@@ -68,8 +112,10 @@ impl Camera {
         let up = up.normalize();
 
         let mut camera = Self {
+            projection: Projection::Perspective,
             eye: Default::default(),
             target: Default::default(),
+            orientation: Default::default(),
             forward: Default::default(),
             right: Default::default(),
             up: Default::default(),
@@ -84,6 +130,7 @@ impl Camera {
             z_far,
             viewport_width: viewport_width as f32,
             viewport_height: viewport_height as f32,
+            matrices: Cell::new(None),
         };
 
         camera.reorient(eye, target);
@@ -109,15 +156,210 @@ impl Camera {
         let new_right = Vec3::cross(self.world_up, new_direction).normalize();
         let new_up = Vec3::cross(new_direction, new_right);
 
-        self.forward = -new_direction;
-        self.right = new_right;
-        self.up = new_up;
+        self.set_rotation(Quat::from_mat3(&Mat3::from_cols(
+            new_right,
+            new_up,
+            new_direction,
+        )));
+    }
+
+    /// Move the camera to `position` and rotate it by `rotation`. The camera
+    /// looks down its local -Z axis with +Y up, so a rotation around the
+    /// camera's forward axis rolls (banks) the view.
+    ///
+    /// The target stays the same distance in front of the camera.
+    pub fn set_orientation(&mut self, position: Vec3, rotation: Quat) {
+        let target_distance = self.eye.distance(self.target).max(f32::EPSILON);
+
+        self.eye = position;
+        self.set_rotation(rotation.normalize());
+        self.target = self.eye + self.forward * target_distance;
+    }
+
+    /// Get the rotation from the camera's local space to world space.
+    pub fn orientation(&self) -> Quat {
+        self.orientation
+    }
+
+    /// Set the camera's rotation and update its local axes to match.
+    fn set_rotation(&mut self, rotation: Quat) {
+        self.invalidate_matrices();
+        self.orientation = rotation;
+        self.forward = rotation * Vec3::NEG_Z;
+        self.right = rotation * Vec3::X;
+        self.up = rotation * Vec3::Y;
+    }
+
+    /// Direction from a framed bounding box's center towards the eye that is
+    /// used by `frame_bounds`. Looks slightly down and to the side of the box.
+    const FRAME_BOUNDS_DIRECTION: Vec3 = Vec3::new(0.5, 0.5, 1.0);
+    /// Extra space left between the framed bounding box and the edges of the
+    /// view, as a fraction of the distance to the box.
+    const FRAME_BOUNDS_MARGIN: f32 = 0.1;
+
+    /// Move the camera so that all of `bounds` is visible, and adjust the near
+    /// and far planes to enclose it.
+    ///
+    /// The camera looks at the center of the box from a fixed default
+    /// direction. The distance to the box is chosen using the current field
+    /// of view and aspect ratio, and orthographic cameras have their view
+    /// height set to fit the box.
+    pub fn frame_bounds(&mut self, bounds: Aabb) {
+        let center = bounds.center();
+        let radius = (bounds.size().length() * 0.5).max(f32::EPSILON);
+
+        // A bounding sphere fits in the view when the distance to its center is
+        // larger than `radius / sin(half_fov)`. Use the narrower of the vertical
+        // and horizontal fields of view.
+        let half_fov_y = self.fov_y * 0.5;
+        let half_fov = if self.aspect > 0.0 {
+            half_fov_y.min(f32::atan(f32::tan(half_fov_y) * self.aspect))
+        } else {
+            half_fov_y
+        };
+
+        let distance = radius / f32::sin(half_fov) * (1.0 + Self::FRAME_BOUNDS_MARGIN);
+        let mut direction = Self::FRAME_BOUNDS_DIRECTION.normalize();
+
+        if direction.cross(self.world_up).length_squared() < f32::EPSILON {
+            direction = self.world_up.any_orthonormal_vector();
+        }
+
+        self.reorient(center + direction * distance, center);
+        self.z_near = (distance - radius) * (1.0 - Self::FRAME_BOUNDS_MARGIN);
+        self.z_far = (distance + radius) * (1.0 + Self::FRAME_BOUNDS_MARGIN);
+
+        // Orthographic views do not widen with distance, so they are resized to
+        // fit the box instead.
+        if let Projection::Orthographic { height } = &mut self.projection {
+            let aspect = if self.aspect > 0.0 { self.aspect } else { 1.0 };
+            *height = 2.0 * radius * (1.0 + Self::FRAME_BOUNDS_MARGIN) / aspect.min(1.0);
+        }
+
+        self.invalidate_matrices();
     }
 
-    /// Set the camera's vertical field of view.
+    /// Set the camera's vertical field of view in radians. The field of view
+    /// must be larger than zero and no larger than `Camera::MAX_FOV_Y`.
     pub fn set_fov_y(&mut self, fov_y: f32) {
-        assert!(fov_y > 0.0);
+        assert!(
+            fov_y > 0.0 && fov_y <= Self::MAX_FOV_Y,
+            "invalid vertical field of view {fov_y} radians"
+        );
         self.fov_y = fov_y;
+        self.invalidate_matrices();
+    }
+
+    /// Get how the camera projects the world onto the screen.
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Set how the camera projects the world onto the screen. Orthographic
+    /// projections must have a height larger than zero.
+    pub fn set_projection(&mut self, projection: Projection) {
+        if let Projection::Orthographic { height } = projection {
+            assert!(height > 0.0, "invalid orthographic view height {height}");
+        }
+
+        self.projection = projection;
+        self.invalidate_matrices();
+    }
+
+    /// Get the height of the view in world units at `distance` in front of
+    /// the camera. Orthographic views are the same height at every distance.
+    pub fn view_height_at(&self, distance: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective => 2.0 * distance * f32::tan(self.fov_y * 0.5),
+            Projection::Orthographic { height } => height,
+        }
+    }
+
+    /// Change the vertical field of view to `target_fov_y` while moving the
+    /// camera along its forward axis so that a subject `subject_distance` in
+    /// front of the camera stays the same size on screen (a "dolly zoom").
+    ///
+    /// The height of the view at distance `d` is `2 * d * tan(fov_y / 2)`. To
+    /// keep the subject the same size this height must not change, so the new
+    /// distance to the subject is:
+    ///
+    ///   `d' = d * tan(fov_y / 2) / tan(target_fov_y / 2)`
+    ///
+    /// The camera and its target are both moved by `d - d'` along the forward
+    /// axis, which leaves the camera's orientation unchanged. Orthographic
+    /// cameras keep subjects the same size at any distance, so they only have
+    /// their field of view changed.
+    ///
+    /// The camera is left unchanged unless `subject_distance > 0` and
+    /// `0 < target_fov_y <= Camera::MAX_FOV_Y`.
+    pub fn dolly_zoom_to(
+        &mut self,
+        subject_distance: f32,
+        target_fov_y: f32,
+    ) -> Result<(), InvalidDollyZoom> {
+        if !(subject_distance > 0.0 && target_fov_y > 0.0 && target_fov_y <= Self::MAX_FOV_Y) {
+            return Err(InvalidDollyZoom(subject_distance, target_fov_y));
+        }
+
+        let new_subject_distance =
+            subject_distance * f32::tan(self.fov_y * 0.5) / f32::tan(target_fov_y * 0.5);
+        let offset = self.forward * (subject_distance - new_subject_distance);
+
+        if self.projection == Projection::Perspective {
+            self.eye += offset;
+            self.target += offset;
+        }
+
+        self.fov_y = target_fov_y;
+        self.invalidate_matrices();
+
+        Ok(())
+    }
+
+    /// Get a camera that is `t` of the way between `a` and `b`, where `t` is
+    /// in the range `[0, 1]`.
+    ///
+    /// The eye position, field of view, clip planes and viewport are linearly
+    /// interpolated and the orientation is spherically interpolated. The target
+    /// is placed in front of the interpolated camera at the interpolated target
+    /// distance. `t` values of 0 and 1 return exact copies of `a` and `b`.
+    ///
+    /// Orthographic view heights are interpolated when both cameras are
+    /// orthographic. Otherwise the camera uses `a`'s projection until `t`
+    /// reaches 1.
+    pub fn lerp(a: &Camera, b: &Camera, t: f32) -> Camera {
+        if t <= 0.0 {
+            return a.clone();
+        } else if t >= 1.0 {
+            return b.clone();
+        }
+
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        let target_distance = lerp(a.eye.distance(a.target), b.eye.distance(b.target));
+
+        let projection = match (a.projection, b.projection) {
+            (Projection::Orthographic { height: a }, Projection::Orthographic { height: b }) => {
+                Projection::Orthographic { height: lerp(a, b) }
+            }
+            (projection, _) => projection,
+        };
+
+        let mut camera = Camera {
+            projection,
+            eye: a.eye.lerp(b.eye, t),
+            world_up: a.world_up.lerp(b.world_up, t).normalize_or(a.world_up),
+            aspect: lerp(a.aspect, b.aspect),
+            fov_y: lerp(a.fov_y, b.fov_y),
+            z_near: lerp(a.z_near, b.z_near),
+            z_far: lerp(a.z_far, b.z_far),
+            viewport_width: lerp(a.viewport_width, b.viewport_width),
+            viewport_height: lerp(a.viewport_height, b.viewport_height),
+            ..a.clone()
+        };
+
+        camera.set_rotation(a.orientation.slerp(b.orientation, t));
+        camera.target = camera.eye + camera.forward * target_distance;
+        camera
     }
 
     /// Get the camera's view matrix.
@@ -131,23 +373,128 @@ impl Camera {
     /// space transform. For instance if a camera is moved backwards 3 units
     /// then it is the same as moving the scene forward 3 units!
     pub fn view_matrix(&self) -> Mat4 {
-        Mat4::look_at_rh(self.eye, self.target, self.up)
+        self.matrices().view
     }
 
     /// Get the camera's projection matrix.
     ///
     /// A projection matrix transforms coordinates from view space to clip space.
-    /// This camera applies a perspective projection to make objects farther from
-    /// the camera appear smaller. Any fragment outside of the viewing frustum
-    /// will not be rendered to the screen.
+    /// A perspective projection makes objects farther from the camera appear
+    /// smaller, while an orthographic projection keeps them the same size. Any
+    /// fragment outside of the viewing volume will not be rendered to the
+    /// screen.
     pub fn projection_matrix(&self) -> Mat4 {
-        Mat4::perspective_rh(self.fov_y, self.aspect, self.z_near, self.z_far)
+        self.matrices().projection
     }
 
     /// Get the camera's view projection matrix. The view projection matrix will
     /// transform points from world space to clip space.
     pub fn view_projection_matrix(&self) -> Mat4 {
-        self.projection_matrix() * self.view_matrix()
+        self.matrices().view_projection
+    }
+
+    /// Get the inverse of the view matrix, which transforms coordinates from
+    /// view space to world space.
+    #[allow(dead_code)]
+    pub fn inverse_view_matrix(&self) -> Mat4 {
+        self.matrices().inverse_view
+    }
+
+    /// Get the inverse of the projection matrix, which transforms coordinates
+    /// from clip space to view space.
+    pub fn inverse_projection_matrix(&self) -> Mat4 {
+        self.matrices().inverse_projection
+    }
+
+    /// Get the inverse of the view projection matrix, which transforms
+    /// coordinates from clip space to world space.
+    pub fn inverse_view_projection_matrix(&self) -> Mat4 {
+        self.matrices().inverse_view_projection
+    }
+
+    /// Get the volume of the world visible to the camera.
+    pub fn frustum(&self) -> Frustum {
+        self.matrices().frustum
+    }
+
+    /// Get the six planes bounding the volume of the world visible to the
+    /// camera, in the order described by `Frustum::planes`.
+    #[allow(dead_code)]
+    pub fn frustum_planes(&self) -> [Vec4; 6] {
+        *self.matrices().frustum.planes()
+    }
+
+    /// Get the camera's matrices, computing them first if the camera changed
+    /// since they were last computed.
+    fn matrices(&self) -> CameraMatrices {
+        if let Some(matrices) = self.matrices.get() {
+            return matrices;
+        }
+
+        let view = Mat4::look_at_rh(self.eye, self.target, self.up);
+        let projection = match self.projection {
+            Projection::Perspective => {
+                Mat4::perspective_rh(self.fov_y, self.aspect, self.z_near, self.z_far)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * self.aspect;
+
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.z_near,
+                    self.z_far,
+                )
+            }
+        };
+        let view_projection = projection * view;
+
+        let matrices = CameraMatrices {
+            view,
+            projection,
+            view_projection,
+            inverse_view: view.inverse(),
+            inverse_projection: projection.inverse(),
+            inverse_view_projection: view_projection.inverse(),
+            frustum: Frustum::from_view_projection(&view_projection),
+        };
+
+        self.matrices.set(Some(matrices));
+        matrices
+    }
+
+    /// Mark the camera's matrices as needing to be computed again. Called
+    /// whenever a field they depend on changes.
+    fn invalidate_matrices(&mut self) {
+        self.matrices.set(None);
+    }
+
+    /// Get the ray from the camera's eye through the pixel at (`x`, `y`) of
+    /// its viewport, eg to find what is under the mouse cursor. Pixels are
+    /// counted from the top left of the viewport. The ray's direction is
+    /// normalized, so distances along it are in world units.
+    ///
+    /// Rays from orthographic cameras all point in the camera's forward
+    /// direction, and start at the pixel on the camera's plane rather than at
+    /// the eye.
+    pub fn screen_to_ray(&self, x: f32, y: f32) -> Ray {
+        let ndc_x = x / self.viewport_width * 2.0 - 1.0;
+        let ndc_y = 1.0 - y / self.viewport_height * 2.0;
+        let clip_to_world = self.inverse_view_projection_matrix();
+
+        match self.projection {
+            Projection::Perspective => {
+                let far_point = clip_to_world.project_point3(Vec3::new(ndc_x, ndc_y, 1.0));
+                Ray::new(self.eye, (far_point - self.eye).normalize())
+            }
+            Projection::Orthographic { .. } => {
+                let near_point = clip_to_world.project_point3(Vec3::new(ndc_x, ndc_y, 0.0));
+                Ray::new(near_point - self.forward * self.z_near, self.forward)
+            }
+        }
     }
 
     /// Resize the camera's viewport.
@@ -160,6 +507,7 @@ impl Camera {
             self.aspect = new_width as f32 / new_height as f32;
             self.viewport_width = new_width as f32;
             self.viewport_height = new_height as f32;
+            self.invalidate_matrices();
             Ok(())
         } else {
             Err(InvalidCameraSize(new_width, new_height))
@@ -182,7 +530,6 @@ impl Camera {
     }
 
     /// Get the camera's right axis.
-    #[allow(dead_code)]
     pub fn right(&self) -> Vec3 {
         self.right
     }
@@ -192,6 +539,11 @@ impl Camera {
         self.up
     }
 
+    /// Get the camera's vertical field of view.
+    pub fn fov_y(&self) -> f32 {
+        self.fov_y
+    }
+
     /// Get the camera viewport width in pixels.
     pub fn viewport_width(&self) -> f32 {
         self.viewport_width
@@ -202,6 +554,41 @@ impl Camera {
         self.viewport_height
     }
 
+    /// Get the minimum camera view distance.
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    /// Get the maximum camera view distance.
+    pub fn z_far(&self) -> f32 {
+        self.z_far
+    }
+
+    /// Set the minimum camera view distance. Must be smaller than `z_far`.
+    #[allow(dead_code)]
+    pub fn set_z_near(&mut self, z_near: f32) -> Result<(), InvalidClipPlanes> {
+        self.set_clip_planes(z_near, self.z_far)
+    }
+
+    /// Set the maximum camera view distance. Must be larger than `z_near`.
+    #[allow(dead_code)]
+    pub fn set_z_far(&mut self, z_far: f32) -> Result<(), InvalidClipPlanes> {
+        self.set_clip_planes(self.z_near, z_far)
+    }
+
+    /// Set the minimum and maximum camera view distances. The clip planes are
+    /// left unchanged unless `0 <= z_near < z_far`.
+    pub fn set_clip_planes(&mut self, z_near: f32, z_far: f32) -> Result<(), InvalidClipPlanes> {
+        if 0.0 <= z_near && z_near < z_far {
+            self.z_near = z_near;
+            self.z_far = z_far;
+            self.invalidate_matrices();
+            Ok(())
+        } else {
+            Err(InvalidClipPlanes(z_near, z_far))
+        }
+    }
+
     /// Get the world up axis (not the camera's up axis).
     pub fn world_up(&self) -> Vec3 {
         self.world_up
@@ -212,6 +599,14 @@ impl Camera {
 #[error("camera viewport width and height must be larger than zero but width was {} and height was {}", .0, .1)]
 pub struct InvalidCameraSize(u32, u32);
 
+#[derive(Debug, Error, PartialEq)]
+#[error("camera clip planes must satisfy 0 <= near < far but near was {} and far was {}", .0, .1)]
+pub struct InvalidClipPlanes(f32, f32);
+
+#[derive(Debug, Error, PartialEq)]
+#[error("dolly zoom must satisfy distance > 0 and 0 < fov <= 179 degrees but distance was {} and fov was {}", .0, .1)]
+pub struct InvalidDollyZoom(f32, f32);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,6 +630,35 @@ mod tests {
         assert_eq!(2.0, camera.aspect);
     }
 
+    #[test]
+    fn framed_unit_cube_is_inside_ndc() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            200,
+        );
+
+        let bounds = Aabb::new(Vec3::new(4.5, -0.5, -0.5), Vec3::new(5.5, 0.5, 0.5));
+        camera.frame_bounds(bounds);
+
+        assert_eq!(bounds.center(), camera.target());
+
+        let view_projection = camera.view_projection_matrix();
+
+        for corner in bounds.corners() {
+            let ndc = view_projection.project_point3(corner);
+
+            assert!((-1.0..=1.0).contains(&ndc.x), "{corner} -> {ndc}");
+            assert!((-1.0..=1.0).contains(&ndc.y), "{corner} -> {ndc}");
+            assert!((0.0..=1.0).contains(&ndc.z), "{corner} -> {ndc}");
+        }
+    }
+
     #[test]
     fn set_invalid_viewport_size() {
         let mut camera = Camera::new(
@@ -266,4 +690,332 @@ mod tests {
         assert_eq!(0, err.0);
         assert_eq!(0, err.1);
     }
+
+    #[test]
+    fn set_valid_clip_planes() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+
+        assert_eq!(camera.set_clip_planes(0.5, 50.0), Ok(()));
+        assert_eq!((camera.z_near(), camera.z_far()), (0.5, 50.0));
+
+        assert_eq!(camera.set_z_near(0.0), Ok(()));
+        assert_eq!(camera.set_z_far(1000.0), Ok(()));
+        assert_eq!((camera.z_near(), camera.z_far()), (0.0, 1000.0));
+    }
+
+    #[test]
+    fn set_invalid_clip_planes() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+
+        assert_eq!(
+            camera.set_clip_planes(-1.0, 10.0),
+            Err(InvalidClipPlanes(-1.0, 10.0))
+        );
+        assert!(camera.set_clip_planes(10.0, 10.0).is_err());
+        assert!(camera.set_z_near(100.0).is_err());
+        assert!(camera.set_z_far(0.05).is_err());
+
+        // Rejected clip planes leave the camera unchanged.
+        assert_eq!((camera.z_near(), camera.z_far()), (0.1, 100.0));
+    }
+
+    #[test]
+    fn orientation_round_trip_is_stable() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+
+        let rotation = Quat::from_euler(glam::EulerRot::YXZ, 0.7, -0.3, 0.4);
+        camera.set_orientation(Vec3::new(1.0, 2.0, 3.0), rotation);
+
+        assert!(camera.orientation().abs_diff_eq(rotation, 1e-6));
+        assert!(camera.forward().abs_diff_eq(rotation * Vec3::NEG_Z, 1e-6));
+        assert!(camera.up().abs_diff_eq(rotation * Vec3::Y, 1e-6));
+
+        // Setting the same orientation again does not drift.
+        camera.set_orientation(camera.eye(), camera.orientation());
+        assert!(camera.orientation().abs_diff_eq(rotation, 1e-6));
+
+        // `reorient` goes through the same rotation and gives the expected axes.
+        camera.reorient(Vec3::new(0.0, 0.0, 3.0), Vec3::ZERO);
+        assert!(camera.forward().abs_diff_eq(Vec3::NEG_Z, 1e-6));
+        assert!(camera.right().abs_diff_eq(Vec3::X, 1e-6));
+        assert!(camera.up().abs_diff_eq(Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn rolled_camera_rotates_up_axis() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+
+        // Roll 90 degrees counterclockwise around the forward (-Z) axis.
+        let roll = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        camera.set_orientation(camera.eye(), roll);
+
+        assert!(camera.up().abs_diff_eq(Vec3::NEG_X, 1e-6));
+        assert!(camera.target().abs_diff_eq(Vec3::ZERO, 1e-6));
+
+        // Points to the camera's left now appear at the top of the screen.
+        let clip = camera.view_projection_matrix() * Vec3::new(-1.0, 0.0, 0.0).extend(1.0);
+        assert!(clip.y / clip.w > 0.0);
+    }
+
+    #[test]
+    fn dolly_zoom_keeps_subject_size() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+
+        // The subject is a unit tall object at the target, 5 units away.
+        let subject_top = Vec3::new(0.0, 1.0, 0.0);
+        let ndc_y = |camera: &Camera| {
+            let clip = camera.view_projection_matrix() * subject_top.extend(1.0);
+            clip.y / clip.w
+        };
+
+        let before = ndc_y(&camera);
+
+        camera.dolly_zoom_to(5.0, f32::to_radians(90.0)).unwrap();
+
+        assert!((ndc_y(&camera) - before).abs() < 1e-5);
+        assert_eq!(camera.fov_y(), f32::to_radians(90.0));
+        assert!(camera.eye().z < 5.0);
+
+        // Zooming back out returns to the original position.
+        let subject_distance = camera.eye().z;
+        camera
+            .dolly_zoom_to(subject_distance, f32::to_radians(45.0))
+            .unwrap();
+
+        assert!((ndc_y(&camera) - before).abs() < 1e-5);
+        assert!(camera.eye().abs_diff_eq(Vec3::new(0.0, 0.0, 5.0), 1e-4));
+
+        // Invalid distances and fields of view leave the camera unchanged.
+        assert_eq!(
+            Err(InvalidDollyZoom(0.0, 1.0)),
+            camera.dolly_zoom_to(0.0, 1.0)
+        );
+        assert_eq!(
+            Err(InvalidDollyZoom(5.0, std::f32::consts::PI)),
+            camera.dolly_zoom_to(5.0, std::f32::consts::PI)
+        );
+        assert!(camera.dolly_zoom_to(5.0, f32::to_radians(179.5)).is_err());
+        assert_eq!(camera.fov_y(), f32::to_radians(45.0));
+        assert!(camera.eye().abs_diff_eq(Vec3::new(0.0, 0.0, 5.0), 1e-4));
+    }
+
+    #[test]
+    fn lerp_between_cameras() {
+        let a = Camera::new(
+            Vec3::new(0.0, 0.0, 5.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+        let b = Camera::new(
+            Vec3::new(4.0, 2.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(60.0),
+            0.5,
+            50.0,
+            100,
+            100,
+        );
+
+        // The endpoints are reproduced exactly.
+        let start = Camera::lerp(&a, &b, 0.0);
+        assert_eq!(start.eye(), a.eye());
+        assert_eq!(start.orientation(), a.orientation());
+        assert_eq!(start.view_projection_matrix(), a.view_projection_matrix());
+
+        let end = Camera::lerp(&a, &b, 1.0);
+        assert_eq!(end.eye(), b.eye());
+        assert_eq!(end.orientation(), b.orientation());
+        assert_eq!(end.view_projection_matrix(), b.view_projection_matrix());
+
+        // Intermediate eye positions lie on the segment between the endpoints.
+        let segment = b.eye() - a.eye();
+
+        for t in [0.25, 0.5, 0.75] {
+            let camera = Camera::lerp(&a, &b, t);
+            let offset = camera.eye() - a.eye();
+
+            assert!(offset.cross(segment).length() < 1e-4);
+            assert!((offset.length() - segment.length() * t).abs() < 1e-4);
+            assert!((camera.fov_y() - f32::to_radians(45.0 + 15.0 * t)).abs() < 1e-6);
+            assert!((camera.forward().length() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn screen_rays_pass_through_the_pixel() {
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(90.0),
+            0.1,
+            100.0,
+            200,
+            100,
+        );
+
+        let center = camera.screen_to_ray(100.0, 50.0);
+        assert_eq!(camera.eye(), center.origin);
+        assert!(center.direction.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+
+        // Points along a ray through a pixel project back onto that pixel.
+        let ray = camera.screen_to_ray(150.0, 25.0);
+        let ndc = camera.view_projection_matrix().project_point3(ray.at(2.0));
+        assert!((ndc.x - 0.5).abs() < 1e-4, "{ndc:?}");
+        assert!((ndc.y - 0.5).abs() < 1e-4, "{ndc:?}");
+    }
+
+    #[test]
+    fn cached_matrices_follow_camera_changes() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            100,
+            100,
+        );
+
+        let expected = |camera: &Camera| {
+            let view = Mat4::look_at_rh(camera.eye(), camera.target(), camera.up());
+            let projection = Mat4::perspective_rh(
+                camera.fov_y(),
+                camera.viewport_width() / camera.viewport_height(),
+                camera.z_near(),
+                camera.z_far(),
+            );
+            (view, projection)
+        };
+
+        let check = |camera: &Camera| {
+            let (view, projection) = expected(camera);
+            assert_eq!(view, camera.view_matrix());
+            assert_eq!(projection, camera.projection_matrix());
+            assert_eq!(projection * view, camera.view_projection_matrix());
+            assert!(camera
+                .inverse_view_matrix()
+                .abs_diff_eq(view.inverse(), 1e-5));
+            assert!(camera
+                .inverse_view_projection_matrix()
+                .mul_mat4(&camera.view_projection_matrix())
+                .abs_diff_eq(Mat4::IDENTITY, 1e-4));
+            assert_eq!(
+                Frustum::from_view_projection(&(projection * view)),
+                camera.frustum()
+            );
+        };
+
+        check(&camera);
+
+        camera.reorient(Vec3::new(2.0, 1.0, 4.0), Vec3::new(0.0, 1.0, 0.0));
+        check(&camera);
+        camera.set_fov_y(f32::to_radians(70.0));
+        check(&camera);
+        camera.set_viewport_size(300, 100).unwrap();
+        check(&camera);
+        camera.set_clip_planes(1.0, 20.0).unwrap();
+        check(&camera);
+        camera.dolly_zoom_to(2.0, f32::to_radians(30.0)).unwrap();
+        check(&camera);
+        camera.frame_bounds(Aabb::new(Vec3::ZERO, Vec3::ONE));
+        check(&camera);
+
+        // The right vector and frustum planes agree with each other: the left
+        // plane faces to the camera's right.
+        let left_plane = camera.frustum_planes()[0];
+        assert!(left_plane.truncate().dot(camera.right()) > 0.0);
+    }
+
+    #[test]
+    fn orthographic_cameras_keep_objects_the_same_size() {
+        let mut camera = Camera::new(
+            Vec3::new(0.0, 0.0, 3.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(90.0),
+            0.1,
+            100.0,
+            200,
+            100,
+        );
+        camera.set_projection(Projection::Orthographic { height: 4.0 });
+
+        // The view is 4 units tall and 8 units wide at every distance.
+        let view_projection = camera.view_projection_matrix();
+
+        for z in [2.0, -10.0, -90.0] {
+            let ndc = view_projection.project_point3(Vec3::new(4.0, 2.0, z));
+            assert!(ndc.abs_diff_eq(Vec3::new(1.0, 1.0, ndc.z), 1e-5), "{ndc:?}");
+            assert!((0.0..=1.0).contains(&ndc.z), "{ndc:?}");
+        }
+
+        assert_eq!(4.0, camera.view_height_at(50.0));
+
+        // Screen rays are parallel, and start at the pixel on the camera plane.
+        let ray = camera.screen_to_ray(150.0, 25.0);
+        assert!(ray.origin.abs_diff_eq(Vec3::new(2.0, 1.0, 3.0), 1e-5));
+        assert!(ray.direction.abs_diff_eq(Vec3::NEG_Z, 1e-6));
+
+        // Framing a box resizes the view to fit it.
+        let bounds = Aabb::new(Vec3::splat(-3.0), Vec3::splat(3.0));
+        camera.frame_bounds(bounds);
+
+        for corner in bounds.corners() {
+            let ndc = camera.view_projection_matrix().project_point3(corner);
+            assert!(ndc.abs().cmple(Vec3::ONE).all(), "{corner} -> {ndc}");
+        }
+    }
 }
@@ -2,95 +2,310 @@
 
 use std::{cell::RefCell, collections::HashMap, path::Path, rc::Rc};
 
+use tracing::warn;
+
 use crate::{
-    platform::load_as_binary,
+    platform::{content_cache_key, load_as_binary},
     renderer::{
-        self, shaders,
+        self,
+        materials::Material,
+        render_context::RenderContext,
         textures::{self, ColorSpace},
     },
 };
 
 mod obj_model;
+pub mod scene_file;
 
-// TODO: Implement basic content loader with caching support.
 // TODO: Add ability to precompile models to a binary format that is loadable here.
 
+/// A progress update for a single asset that is being loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadProgress {
+    /// The asset at `path` started loading.
+    Started { path: String },
+    /// `bytes` were read for the asset at `path`.
+    BytesLoaded { path: String, bytes: usize },
+    /// The asset at `path` finished loading.
+    Finished { path: String },
+    /// The asset at `path` failed to load.
+    Failed { path: String, error: String },
+}
+
+/// Receives progress updates while content is loading (eg to update a loading
+/// screen).
+pub type LoadProgressSink = Rc<dyn Fn(&LoadProgress)>;
+
+/// Sends `LoadProgress` updates to an optional progress sink.
+#[derive(Clone, Default)]
+pub struct ProgressReporter {
+    sink: Option<LoadProgressSink>,
+}
+
+impl ProgressReporter {
+    pub fn new(sink: Option<LoadProgressSink>) -> Self {
+        Self { sink }
+    }
+
+    pub fn started(&self, path: &Path) {
+        self.report(LoadProgress::Started {
+            path: path.display().to_string(),
+        });
+    }
+
+    pub fn bytes_loaded(&self, path: &Path, bytes: usize) {
+        self.report(LoadProgress::BytesLoaded {
+            path: path.display().to_string(),
+            bytes,
+        });
+    }
+
+    pub fn finished(&self, path: &Path) {
+        self.report(LoadProgress::Finished {
+            path: path.display().to_string(),
+        });
+    }
+
+    pub fn failed(&self, path: &Path, error: &anyhow::Error) {
+        self.report(LoadProgress::Failed {
+            path: path.display().to_string(),
+            error: format!("{error:#}"),
+        });
+    }
+
+    /// Run `load` for the asset at `path`, reporting when it starts and if it
+    /// finished or failed.
+    pub async fn track<T, F>(&self, path: &Path, load: F) -> anyhow::Result<T>
+    where
+        F: std::future::Future<Output = anyhow::Result<T>>,
+    {
+        self.started(path);
+
+        let result = load.await;
+
+        match &result {
+            Ok(_) => self.finished(path),
+            Err(e) => self.failed(path, e),
+        }
+
+        result
+    }
+
+    fn report(&self, progress: LoadProgress) {
+        if let Some(sink) = &self.sink {
+            sink(&progress);
+        }
+    }
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("has_sink", &self.sink.is_some())
+            .finish()
+    }
+}
+
 pub struct ContentManager {
-    default_textures: DefaultTextures,
-    _loaded_textures: RefCell<HashMap<String, Rc<wgpu::Texture>>>,
+    context: RenderContext,
+    progress: ProgressReporter,
+    /// Stop loading a batch of assets after the first one fails.
+    abort_batch_on_error: bool,
+    /// Textures that were already loaded, keyed by their normalized file path
+    /// and the color space they were loaded with.
+    loaded_textures: RefCell<HashMap<(String, ColorSpace), Rc<textures::Texture>>>,
+    /// Materials created from MTL files, keyed by the MTL material's values so
+    /// identical materials are shared.
+    loaded_materials: RefCell<HashMap<obj_model::MtlMaterialKey, Rc<Material>>>,
 }
 
 impl ContentManager {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    pub fn new(context: RenderContext) -> Self {
         Self {
-            default_textures: DefaultTextures::new(device, queue),
-            _loaded_textures: RefCell::new(HashMap::new()),
+            context,
+            progress: ProgressReporter::default(),
+            abort_batch_on_error: false,
+            loaded_textures: RefCell::new(HashMap::new()),
+            loaded_materials: RefCell::new(HashMap::new()),
         }
     }
 
-    pub async fn load_obj_mesh<P>(
+    /// Report loading progress for each asset to `sink`.
+    pub fn with_progress_sink(mut self, sink: Option<LoadProgressSink>) -> Self {
+        self.progress = ProgressReporter::new(sink);
+        self
+    }
+
+    /// Stop loading a batch after the first asset that fails to load instead
+    /// of skipping it and continuing with the rest of the batch.
+    pub fn with_abort_batch_on_error(mut self, abort: bool) -> Self {
+        self.abort_batch_on_error = abort;
+        self
+    }
+
+    /// Get the number of distinct materials loaded from MTL files.
+    pub fn loaded_material_count(&self) -> usize {
+        self.loaded_materials.borrow().len()
+    }
+
+    /// Get the textures used when a material does not specify its own.
+    pub fn default_textures(&self) -> &DefaultTextures {
+        self.context.default_textures()
+    }
+
+    pub async fn load_obj_mesh<P>(&self, obj_file_path: P) -> anyhow::Result<renderer::models::Mesh>
+    where
+        P: AsRef<Path> + std::fmt::Debug,
+    {
+        obj_model::load_obj_mesh(self, obj_file_path).await
+    }
+
+    /// Load a batch of obj meshes. A mesh that fails to load is reported to the
+    /// progress sink and returned as `None`, unless the content manager was
+    /// asked to abort the batch on the first error.
+    pub async fn load_obj_meshes<P>(
         &self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        layouts: &shaders::BindGroupLayouts,
-        obj_file_path: P,
-    ) -> anyhow::Result<renderer::models::Mesh>
+        obj_file_paths: &[P],
+    ) -> anyhow::Result<Vec<Option<renderer::models::Mesh>>>
     where
         P: AsRef<Path> + std::fmt::Debug,
     {
-        obj_model::load_obj_mesh(
-            device,
-            queue,
-            layouts,
-            &self.default_textures,
-            obj_file_path,
-        )
-        .await
+        let mut meshes = Vec::with_capacity(obj_file_paths.len());
+
+        for obj_file_path in obj_file_paths {
+            match self.load_obj_mesh(obj_file_path).await {
+                Ok(mesh) => meshes.push(Some(mesh)),
+                Err(e) if self.abort_batch_on_error => return Err(e),
+                Err(e) => {
+                    warn!("skipping obj mesh {obj_file_path:?} that failed to load: {e:#}");
+                    meshes.push(None);
+                }
+            }
+        }
+
+        Ok(meshes)
     }
 
-    // TODO: Implement cached texture loading.
-    /*
+    /// Load a texture file, or return the already loaded texture if the same
+    /// file was previously loaded with the same color space.
     pub async fn load_texture<P>(
         &self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
         file_path: P,
-    ) -> anyhow::Result<Rc<wgpu::Texture>>
+        color_space: ColorSpace,
+    ) -> anyhow::Result<Rc<textures::Texture>>
     where
         P: AsRef<Path> + std::fmt::Debug,
     {
-        // Resolve the texture file path to an unambiguous absolute file path
-        // and use this value as the shared key.
-        let file_path = std::fs::canonicalize(file_path.as_ref())?;
-        let cache_key = file_path.to_string_lossy();
+        // Resolve the texture file path to an unambiguous path and use this
+        // value as the shared key.
+        let cache_key = (content_cache_key(file_path.as_ref()), color_space);
 
         // Return a copy of the already loaded texture if it exists in the
         // texture cache.
-        if let Some(texture) = self.loaded_textures.borrow().get(cache_key.as_ref()) {
+        if let Some(texture) = self.loaded_textures.borrow().get(&cache_key) {
             return Ok(texture.clone());
         }
 
-        // The texture was not already in the cache. Load it from disk and add
-        // it to the cache before returning the texture to the caller.
-        Ok({
-            let cache_key = cache_key.into_owned();
-            let texture = Rc::new(load_texture_file(device, queue, file_path).await?);
+        // The texture was not already in the cache. Load it and add it to the
+        // cache before returning the texture to the caller.
+        let texture = Rc::new(
+            load_texture_file(
+                self.context.device(),
+                self.context.queue(),
+                &self.progress,
+                file_path,
+                color_space,
+            )
+            .await?,
+        );
 
-            self.loaded_textures
-                .borrow_mut()
-                .insert(cache_key, texture.clone());
+        self.loaded_textures
+            .borrow_mut()
+            .insert(cache_key, texture.clone());
 
-            texture
-        })
+        Ok(texture)
+    }
+
+    /// Load a cube map (eg for `Scene::skybox`) from six square face images
+    /// of the same size, in +X, -X, +Y, -Y, +Z, -Z order.
+    pub async fn load_cubemap<P>(
+        &self,
+        face_file_paths: &[P],
+        color_space: ColorSpace,
+    ) -> anyhow::Result<Rc<textures::Texture>>
+    where
+        P: AsRef<Path> + std::fmt::Debug,
+    {
+        let mut faces = Vec::with_capacity(face_file_paths.len());
+
+        for face_file_path in face_file_paths {
+            let face_file_path = face_file_path.as_ref();
+
+            faces.push(
+                self.progress
+                    .track(face_file_path, async {
+                        let file_bytes = load_as_binary(face_file_path).await?;
+                        self.progress.bytes_loaded(face_file_path, file_bytes.len());
+
+                        Ok(image::load_from_memory(&file_bytes)?)
+                    })
+                    .await?,
+            );
+        }
+
+        Ok(Rc::new(textures::cube_from_images(
+            self.context.device(),
+            self.context.queue(),
+            &faces,
+            color_space,
+            face_file_paths
+                .first()
+                .and_then(|p| p.as_ref().to_str())
+                .or(Some("cube map")),
+        )?))
+    }
+
+    /// Load a cube map from a single equirectangular panorama, such as an HDR
+    /// environment map. The center of the panorama faces -Z.
+    pub async fn load_equirect_cubemap<P>(
+        &self,
+        file_path: P,
+    ) -> anyhow::Result<Rc<textures::Texture>>
+    where
+        P: AsRef<Path> + std::fmt::Debug,
+    {
+        let file_path = file_path.as_ref();
+
+        self.progress
+            .track(file_path, async {
+                let file_bytes = load_as_binary(file_path).await?;
+                self.progress.bytes_loaded(file_path, file_bytes.len());
+
+                let panorama = image::load_from_memory(&file_bytes)?;
+
+                Ok(Rc::new(textures::cube_from_equirect(
+                    self.context.device(),
+                    self.context.queue(),
+                    &panorama,
+                    Some(
+                        file_path
+                            .to_str()
+                            .unwrap_or("invalid utf8 chars in cube map filename"),
+                    ),
+                )))
+            })
+            .await
     }
-    */
 }
 
 #[derive(Debug)]
 pub struct DefaultTextures {
-    pub diffuse_map: Rc<wgpu::Texture>,
-    pub specular_map: Rc<wgpu::Texture>,
-    pub emissive_map: Rc<wgpu::Texture>,
+    pub diffuse_map: Rc<textures::Texture>,
+    pub specular_map: Rc<textures::Texture>,
+    pub emissive_map: Rc<textures::Texture>,
+    pub metallic_map: Rc<textures::Texture>,
+    pub roughness_map: Rc<textures::Texture>,
+    pub ao_map: Rc<textures::Texture>,
 }
 
 impl DefaultTextures {
@@ -113,10 +328,31 @@ impl DefaultTextures {
             emissive_map: Rc::new(textures::new_1x1(
                 device,
                 queue,
-                [0, 0, 0],
+                [255, 255, 255],
                 textures::ColorSpace::Linear,
                 Some("default emissive texture"),
             )),
+            metallic_map: Rc::new(textures::new_1x1(
+                device,
+                queue,
+                [255, 255, 255],
+                textures::ColorSpace::Linear,
+                Some("default metallic texture"),
+            )),
+            roughness_map: Rc::new(textures::new_1x1(
+                device,
+                queue,
+                [255, 255, 255],
+                textures::ColorSpace::Linear,
+                Some("default roughness texture"),
+            )),
+            ao_map: Rc::new(textures::new_1x1(
+                device,
+                queue,
+                [255, 255, 255],
+                textures::ColorSpace::Linear,
+                Some("default ambient occlusion texture"),
+            )),
         }
     }
 }
@@ -125,23 +361,103 @@ impl DefaultTextures {
 pub async fn load_texture_file<P>(
     device: &wgpu::Device,
     queue: &wgpu::Queue,
+    progress: &ProgressReporter,
     file_path: P,
     color_space: ColorSpace,
-) -> anyhow::Result<wgpu::Texture>
+) -> anyhow::Result<textures::Texture>
 where
     P: AsRef<Path> + std::fmt::Debug,
 {
-    let file_bytes = load_as_binary(file_path.as_ref()).await?;
-    renderer::textures::from_image_bytes(
-        device,
-        queue,
-        &file_bytes,
-        color_space,
-        Some(
-            file_path
-                .as_ref()
-                .to_str()
-                .unwrap_or("invalid utf8 chars in texture filename"),
-        ),
-    )
+    let file_path = file_path.as_ref();
+
+    progress
+        .track(file_path, async {
+            let file_bytes = load_as_binary(file_path).await?;
+            progress.bytes_loaded(file_path, file_bytes.len());
+
+            renderer::textures::from_image_bytes(
+                device,
+                queue,
+                &file_bytes,
+                color_space,
+                Some(
+                    file_path
+                        .to_str()
+                        .unwrap_or("invalid utf8 chars in texture filename"),
+                ),
+            )
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_renderer;
+
+    #[test]
+    fn failed_mesh_is_reported_without_aborting_batch() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink_events = events.clone();
+        let sink: LoadProgressSink = Rc::new(move |p: &LoadProgress| {
+            sink_events.borrow_mut().push(p.clone());
+        });
+
+        let content =
+            ContentManager::new(renderer.context().clone()).with_progress_sink(Some(sink));
+        let meshes = pollster::block_on(content.load_obj_meshes(&["missing.obj"])).unwrap();
+
+        assert!(meshes[0].is_none());
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0],
+            LoadProgress::Started {
+                path: "missing.obj".to_string()
+            }
+        );
+        assert!(matches!(&events[1], LoadProgress::Failed { path, .. } if path == "missing.obj"));
+
+        // The same failure stops the batch when asked to.
+        let content =
+            ContentManager::new(renderer.context().clone()).with_abort_batch_on_error(true);
+        assert!(pollster::block_on(content.load_obj_meshes(&["missing.obj"])).is_err());
+    }
+
+    #[test]
+    fn loading_same_texture_twice_shares_it() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let first = pollster::block_on(content.load_texture("test.png", ColorSpace::Srgb)).unwrap();
+        let second =
+            pollster::block_on(content.load_texture("./test.png", ColorSpace::Srgb)).unwrap();
+        let linear =
+            pollster::block_on(content.load_texture("test.png", ColorSpace::Linear)).unwrap();
+
+        assert!(Rc::ptr_eq(&first, &second));
+        assert!(!Rc::ptr_eq(&first, &linear));
+    }
+
+    #[test]
+    fn identical_mtl_materials_share_one_material() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        // The obj file has three groups referencing two identically defined
+        // materials.
+        let content = ContentManager::new(renderer.context().clone());
+        let mesh = pollster::block_on(content.load_obj_mesh("shared_material.obj")).unwrap();
+
+        assert_eq!(mesh.submeshes().len(), 3);
+        assert_eq!(content.loaded_material_count(), 1);
+    }
 }
@@ -1,79 +1,174 @@
 use std::{path::Path, rc::Rc};
 
 use glam::Vec3;
-use wgpu::util::DeviceExt;
+use tracing::debug;
 
 use crate::{
-    content::load_texture_file,
-    platform::load_as_string,
+    platform::{content_cache_key, load_as_string},
     renderer::{self, materials, models, shaders, textures::ColorSpace},
 };
 
-use super::DefaultTextures;
-
-// TODO: Support loading emissive maps from mtl files.
+use super::ContentManager;
 
 /// Creates a new `Mesh` from an obj model.
-#[tracing::instrument(level = "info")]
+#[tracing::instrument(level = "info", skip(content))]
 pub async fn load_obj_mesh<P>(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
-    layouts: &shaders::BindGroupLayouts,
-    default_textures: &DefaultTextures,
+    content: &ContentManager,
     obj_file_path: P,
 ) -> anyhow::Result<renderer::models::Mesh>
 where
     P: AsRef<Path> + std::fmt::Debug,
 {
-    let obj_text = load_as_string(obj_file_path.as_ref()).await?;
-    let obj_cursor = std::io::Cursor::new(obj_text); // TODO: move inline?
-    let mut obj_buf_reader = std::io::BufReader::new(obj_cursor);
-
-    // Parse the .obj file to get a list of models (actually meshes) and materials
-    // definitions.
-    let (obj_models, obj_materials) = tobj::load_obj_buf_async(
-        &mut obj_buf_reader,
-        &tobj::LoadOptions {
-            triangulate: true,
-            single_index: true,
-            ..Default::default()
-        },
-        |mtl_file_path| async move {
-            // TODO: Break this out - can caching be supported?
-            // TODO: Convert the unwrap to a returnable error.
-            let mtl_text = load_as_string(&mtl_file_path).await.unwrap();
-            tobj::load_mtl_buf(&mut std::io::BufReader::new(std::io::Cursor::new(mtl_text)))
-        },
-    )
-    .await?;
-
-    // Create materials for each of the MTL material definitions.
-    let obj_materials = obj_materials?;
-    let mut materials = Vec::with_capacity(obj_materials.len());
-
-    for obj_mtl in obj_materials.into_iter() {
-        materials.push(create_material(device, queue, obj_mtl, default_textures).await?);
+    let obj_file_path = obj_file_path.as_ref();
+    let progress = &content.progress;
+
+    // MTL files and the textures they use are found next to the obj file.
+    let base_dir = obj_file_path.parent().unwrap_or(Path::new(""));
+
+    progress
+        .track(obj_file_path, async {
+            let obj_text = load_as_string(obj_file_path).await?;
+            progress.bytes_loaded(obj_file_path, obj_text.len());
+
+            let obj_cursor = std::io::Cursor::new(obj_text); // TODO: move inline?
+            let mut obj_buf_reader = std::io::BufReader::new(obj_cursor);
+
+            // Parse the .obj file to get a list of models (actually meshes) and
+            // materials definitions.
+            let (obj_models, obj_materials) = tobj::load_obj_buf_async(
+                &mut obj_buf_reader,
+                &tobj::LoadOptions {
+                    triangulate: true,
+                    single_index: true,
+                    ..Default::default()
+                },
+                |mtl_file_path| async move {
+                    // NOTE: The MTL text is not cached, but the materials it
+                    //       defines are (see `load_material`).
+                    let mtl_file_path = base_dir.join(mtl_file_path);
+                    let mtl_file_path = mtl_file_path.as_path();
+                    progress.started(mtl_file_path);
+
+                    match load_as_string(mtl_file_path).await {
+                        Ok(mtl_text) => {
+                            progress.bytes_loaded(mtl_file_path, mtl_text.len());
+                            progress.finished(mtl_file_path);
+
+                            tobj::load_mtl_buf(&mut std::io::BufReader::new(std::io::Cursor::new(
+                                mtl_text,
+                            )))
+                        }
+                        Err(e) => {
+                            progress.failed(mtl_file_path, &e);
+                            Err(tobj::LoadError::OpenFileFailed)
+                        }
+                    }
+                },
+            )
+            .await?;
+
+            // Create materials for each of the MTL material definitions. MTL
+            // entries that are identical share the same cached material.
+            let obj_materials = obj_materials?;
+            let mut materials = Vec::with_capacity(obj_materials.len());
+
+            for mut obj_mtl in obj_materials.into_iter() {
+                resolve_texture_paths(&mut obj_mtl, base_dir);
+                materials.push(load_material(content, obj_mtl).await?);
+            }
+
+            // Creates meshes for each of the obj models.
+            create_mesh(
+                content.context.device(),
+                content.context.bind_group_layouts(),
+                &obj_models,
+                &materials,
+                obj_file_path
+                    .to_str()
+                    .unwrap_or("invalid utf8 chars in obj file path"),
+            )
+        })
+        .await
+}
+
+/// Texture map parameters of MTL materials that tobj does not parse. Besides
+/// the emissive map these are from the PBR extension to MTL.
+const TEXTURE_PARAMS: [&str; 4] = ["map_Ke", "map_Pm", "map_Pr", "map_ao"];
+
+/// Parameters from the PBR extension to MTL.
+const PBR_PARAMS: [&str; 5] = ["Pm", "Pr", "map_Pm", "map_Pr", "map_ao"];
+
+/// Identifies an MTL material by its constants and texture maps, ignoring its
+/// name. MTL materials with the same key can share one `Material`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MtlMaterialKey {
+    ambient: Option<[u32; 3]>,
+    diffuse: Option<[u32; 3]>,
+    specular: Option<[u32; 3]>,
+    shininess: Option<u32>,
+    emissive: Option<[u32; 3]>,
+    metallic: Option<u32>,
+    roughness: Option<u32>,
+    diffuse_texture: Option<String>,
+    specular_texture: Option<String>,
+    emissive_texture: Option<String>,
+    metallic_texture: Option<String>,
+    roughness_texture: Option<String>,
+    ao_texture: Option<String>,
+}
+
+impl MtlMaterialKey {
+    pub fn new(mat: &tobj::Material) -> Self {
+        let color_bits = |c: [f32; 3]| c.map(f32::to_bits);
+
+        Self {
+            ambient: mat.ambient.map(color_bits),
+            diffuse: mat.diffuse.map(color_bits),
+            specular: mat.specular.map(color_bits),
+            shininess: mat.shininess.map(f32::to_bits),
+            emissive: emissive_color(mat).map(color_bits),
+            metallic: scalar_param(mat, "Pm").map(f32::to_bits),
+            roughness: scalar_param(mat, "Pr").map(f32::to_bits),
+            diffuse_texture: mat.diffuse_texture.as_ref().map(content_cache_key),
+            specular_texture: mat.specular_texture.as_ref().map(content_cache_key),
+            emissive_texture: texture_param(mat, "map_Ke").map(content_cache_key),
+            metallic_texture: texture_param(mat, "map_Pm").map(content_cache_key),
+            roughness_texture: texture_param(mat, "map_Pr").map(content_cache_key),
+            ao_texture: texture_param(mat, "map_ao").map(content_cache_key),
+        }
     }
+}
 
-    // Creates meshes for each of the obj models.
-    create_mesh(
-        device,
-        layouts,
-        &obj_models,
-        &materials,
-        obj_file_path
-            .as_ref()
-            .to_str()
-            .unwrap_or("invalid utf8 chars in obj file path"),
-    )
+/// Get the `Material` for an obj model's .mtl material, creating it only if an
+/// identical material has not already been loaded by `content`.
+pub async fn load_material(
+    content: &ContentManager,
+    mat: tobj::Material,
+) -> anyhow::Result<Rc<materials::Material>> {
+    let cache_key = MtlMaterialKey::new(&mat);
+
+    if let Some(material) = content.loaded_materials.borrow().get(&cache_key) {
+        debug!("reusing cached material for mtl material {:?}", mat.name);
+        return Ok(material.clone());
+    }
+
+    let material = Rc::new(create_material(content, mat).await?);
+
+    content
+        .loaded_materials
+        .borrow_mut()
+        .insert(cache_key, material.clone());
+
+    Ok(material)
 }
 
-/// Creates a `shading::Material` object from a given obj model's .mtl material.
+/// Creates a `Material` object from a given obj model's .mtl material.
+///
+/// Textures referenced by the material are loaded through `content` so they are
+/// shared with any other material using the same texture file.
 pub async fn create_material(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
+    content: &ContentManager,
     mat: tobj::Material,
-    default_textures: &DefaultTextures,
 ) -> anyhow::Result<materials::Material> {
     let mut material = materials::MaterialBuilder::new();
 
@@ -93,19 +188,122 @@ pub async fn create_material(
         material = material.specular_power(power);
     }
 
-    if let Some(file_path) = mat.diffuse_texture {
-        material = material.diffuse_map(Rc::new(
-            load_texture_file(device, queue, &file_path, ColorSpace::Srgb).await?,
-        ));
+    // Materials that are partially dissolved are drawn with alpha blending.
+    if let Some(dissolve) = mat.dissolve.filter(|dissolve| *dissolve < 1.0) {
+        material = material
+            .alpha(dissolve)
+            .blend_mode(materials::BlendMode::AlphaBlend);
     }
 
-    if let Some(file_path) = mat.specular_texture {
-        material = material.specular_map(Rc::new(
-            load_texture_file(device, queue, &file_path, ColorSpace::Srgb).await?,
-        ));
+    if let Some(file_path) = &mat.diffuse_texture {
+        material = material.diffuse_map(content.load_texture(file_path, ColorSpace::Srgb).await?);
+    }
+
+    if let Some(file_path) = &mat.specular_texture {
+        material = material.specular_map(content.load_texture(file_path, ColorSpace::Srgb).await?);
     }
 
-    Ok(material.build(default_textures))
+    if let Some(color) = emissive_color(&mat) {
+        material = material.emissive_color(Vec3::new(color[0], color[1], color[2]));
+    }
+
+    if let Some(file_path) = texture_param(&mat, "map_Ke") {
+        material = material.emissive_map(content.load_texture(file_path, ColorSpace::Srgb).await?);
+    }
+
+    // Materials using the PBR extension to MTL are shaded with PBR lighting.
+    if PBR_PARAMS
+        .iter()
+        .any(|key| mat.unknown_param.contains_key(*key))
+    {
+        material = material.shading(materials::ShadingModel::Pbr);
+    }
+
+    if let Some(metallic) = scalar_param(&mat, "Pm") {
+        material = material.metallic(metallic);
+    }
+
+    if let Some(roughness) = scalar_param(&mat, "Pr") {
+        material = material.roughness(roughness);
+    }
+
+    if let Some(file_path) = texture_param(&mat, "map_Pm") {
+        material =
+            material.metallic_map(content.load_texture(file_path, ColorSpace::Linear).await?);
+    }
+
+    if let Some(file_path) = texture_param(&mat, "map_Pr") {
+        material =
+            material.roughness_map(content.load_texture(file_path, ColorSpace::Linear).await?);
+    }
+
+    if let Some(file_path) = texture_param(&mat, "map_ao") {
+        material = material.ao_map(content.load_texture(file_path, ColorSpace::Linear).await?);
+    }
+
+    Ok(material.build(content.default_textures()))
+}
+
+/// Get the emissive color (`Ke`) of an MTL material. tobj does not parse this
+/// value so it is read from the material's unknown parameters.
+fn emissive_color(mat: &tobj::Material) -> Option<[f32; 3]> {
+    let values: Vec<f32> = mat
+        .unknown_param
+        .get("Ke")?
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match values[..] {
+        [r, g, b] => Some([r, g, b]),
+        _ => None,
+    }
+}
+
+/// Get a single value parameter of an MTL material that tobj does not parse,
+/// eg the roughness (`Pr`).
+fn scalar_param(mat: &tobj::Material, key: &str) -> Option<f32> {
+    mat.unknown_param.get(key)?.trim().parse().ok()
+}
+
+/// Get the path of a texture map parameter of an MTL material that tobj does
+/// not parse, eg the emissive map (`map_Ke`). Any texture options preceding the
+/// file name are ignored.
+fn texture_param<'a>(mat: &'a tobj::Material, key: &str) -> Option<&'a str> {
+    mat.unknown_param.get(key)?.split_whitespace().last()
+}
+
+/// Make the texture map paths of an MTL material relative to the content
+/// directory rather than `base_dir`, the directory holding the MTL file.
+fn resolve_texture_paths(mat: &mut tobj::Material, base_dir: &Path) {
+    if base_dir.as_os_str().is_empty() {
+        return;
+    }
+
+    let resolve = |file_path: &str| base_dir.join(file_path).to_string_lossy().into_owned();
+
+    for file_path in [&mut mat.diffuse_texture, &mut mat.specular_texture]
+        .into_iter()
+        .flatten()
+    {
+        *file_path = resolve(file_path);
+    }
+
+    // Only the file name at the end of these maps is a path, see
+    // `texture_param`.
+    for key in TEXTURE_PARAMS {
+        let Some(map) = mat.unknown_param.get_mut(key) else {
+            continue;
+        };
+
+        let mut parts: Vec<String> = map.split_whitespace().map(String::from).collect();
+
+        if let Some(file_path) = parts.last_mut() {
+            *file_path = resolve(file_path);
+            *map = parts.join(" ");
+        }
+    }
 }
 
 /// Create a mesh out of the models in an obj model file.
@@ -117,7 +315,7 @@ fn create_mesh(
     device: &wgpu::Device,
     layouts: &shaders::BindGroupLayouts,
     obj_meshes: &[tobj::Model],
-    materials: &[materials::Material],
+    materials: &[Rc<materials::Material>],
     name: &str,
 ) -> anyhow::Result<models::Mesh> {
     // Allocate a single vertex and index buffer for the entire obj mesh.
@@ -143,27 +341,9 @@ fn create_mesh(
         )?);
     }
 
-    // Copy the newly assembled vertex buffer into a hardware GPU vertex buffer.
-    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some(&format!("{name} vertex buffer")),
-        contents: bytemuck::cast_slice(&vertices),
-        usage: wgpu::BufferUsages::VERTEX,
-    });
-
-    // Create a hardware GPU index buffer using the tobj mesh's indices. No need
-    // to assemble an index buffer!
-    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some(&format!("{name} index buffer")),
-        contents: bytemuck::cast_slice(&indices),
-        usage: wgpu::BufferUsages::INDEX,
-    });
-
+    // Copy the newly assembled vertices and indices into hardware GPU buffers.
     Ok(models::Mesh::new(
-        vertex_buffer,
-        index_buffer,
-        indices.len() as u32,
-        wgpu::IndexFormat::Uint32,
-        submeshes,
+        device, name, vertices, indices, submeshes,
     ))
 }
 
@@ -181,7 +361,7 @@ fn process_obj_mesh(
     model: &tobj::Model,
     vertices: &mut Vec<models::Vertex>,
     indices: &mut Vec<u32>,
-    materials: &[materials::Material],
+    materials: &[Rc<materials::Material>],
 ) -> anyhow::Result<models::Submesh> {
     // This method assumes that `obj_model` was loaded with `triangulate = True`,
     // and `single_index = True`.
@@ -205,7 +385,7 @@ fn process_obj_mesh(
     // It's also possible for the obj file to have an empty normal buffer which
     // means the obj data didn't specify any normals.
     assert!(
-        model.mesh.positions.len() % 3 == 0,
+        model.mesh.positions.len().is_multiple_of(3),
         "expected triangulate = true"
     );
 
@@ -255,3 +435,89 @@ fn process_obj_mesh(
             .expect("TODO: Make material optional, let renderer handle empty material")],
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_renderer;
+
+    #[test]
+    fn mtl_emissive_map_and_color_are_loaded() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let mtl_text = pollster::block_on(load_as_string("emissive.mtl")).unwrap();
+        let (mut mtl_materials, _) =
+            tobj::load_mtl_buf(&mut std::io::BufReader::new(mtl_text.as_bytes())).unwrap();
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material =
+            pollster::block_on(create_material(&content, mtl_materials.remove(0))).unwrap();
+
+        assert!(!Rc::ptr_eq(
+            &material.emissive_map,
+            &content.default_textures().emissive_map
+        ));
+        assert_eq!(material.emissive_color, Vec3::new(0.5, 1.0, 0.25));
+    }
+
+    #[test]
+    fn mtl_pbr_parameters_pick_pbr_shading() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let mtl_text = "newmtl metal\nKd 0.8 0.8 0.8\nPm 1.0\nPr 0.25\n";
+        let (mut mtl_materials, _) =
+            tobj::load_mtl_buf(&mut std::io::BufReader::new(mtl_text.as_bytes())).unwrap();
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material =
+            pollster::block_on(create_material(&content, mtl_materials.remove(0))).unwrap();
+
+        assert_eq!(materials::ShadingModel::Pbr, material.shading);
+        assert_eq!(1.0, material.metallic);
+        assert_eq!(0.25, material.roughness);
+    }
+
+    #[test]
+    fn mtl_texture_paths_are_resolved_next_to_the_mtl_file() {
+        let mtl_text =
+            "newmtl tile\nmap_Kd tile.png\nmap_Ks maps/tile_spec.png\nmap_Ke -bm 1.0 glow.png\nmap_Pr rough.png\n";
+        let (mut mtl_materials, _) =
+            tobj::load_mtl_buf(&mut std::io::BufReader::new(mtl_text.as_bytes())).unwrap();
+        let mut mat = mtl_materials.remove(0);
+
+        resolve_texture_paths(&mut mat, Path::new("models/tile"));
+
+        assert_eq!(
+            Some(Path::new("models/tile/tile.png")),
+            mat.diffuse_texture.as_deref().map(Path::new)
+        );
+        assert_eq!(
+            Some(Path::new("models/tile/maps/tile_spec.png")),
+            mat.specular_texture.as_deref().map(Path::new)
+        );
+        assert_eq!(
+            Some(Path::new("models/tile/glow.png")),
+            texture_param(&mat, "map_Ke").map(Path::new)
+        );
+        assert_eq!(
+            Some(Path::new("models/tile/rough.png")),
+            texture_param(&mat, "map_Pr").map(Path::new)
+        );
+    }
+
+    #[test]
+    fn mtl_texture_paths_in_the_content_directory_are_unchanged() {
+        let mtl_text = "newmtl tile\nmap_Kd tile.png\n";
+        let (mut mtl_materials, _) =
+            tobj::load_mtl_buf(&mut std::io::BufReader::new(mtl_text.as_bytes())).unwrap();
+        let mut mat = mtl_materials.remove(0);
+
+        resolve_texture_paths(&mut mat, Path::new(""));
+
+        assert_eq!(Some("tile.png"), mat.diffuse_texture.as_deref());
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::Path;
+
+use anyhow::Context;
+use glam::{Vec2, Vec3};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::DefaultTextures;
+use crate::{
+    platform::FileWatcher,
+    renderer::{
+        materials::{CullMode, Material, MaterialBuilder, ShadingModel},
+        scene::{Entity, Light, Scene, Transform},
+    },
+};
+
+/// Lights, transforms and materials of named scene entities, read from a JSON
+/// scene file:
+///
+/// ```json
+/// {
+///   "entities": [
+///     { "name": "sun", "light": { "directional": { "direction": [0, -1, 0] } } },
+///     { "name": "crate", "transform": { "translation": [0, 1, 0] } },
+///     { "name": "lamp", "material": { "emissive_color": [1, 0.8, 0.5] } }
+///   ]
+/// }
+/// ```
+///
+/// Fields that are left out keep their default values.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub entities: Vec<EntityDescription>,
+}
+
+/// The parts of a scene entity that are set by a scene file.
+#[derive(Debug, Deserialize)]
+pub struct EntityDescription {
+    /// Name of the entity in the scene, see `Entity::with_name`.
+    pub name: String,
+    pub transform: Option<Transform>,
+    pub light: Option<Light>,
+    /// Material constants applied to every submesh of the entity's model.
+    pub material: Option<MaterialDescription>,
+}
+
+/// Material constants set by a scene file. Texture maps are not included, so
+/// the maps a model was loaded with are kept.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MaterialDescription {
+    pub ambient_color: Option<Vec3>,
+    pub diffuse_color: Option<Vec3>,
+    pub specular_color: Option<Vec3>,
+    pub specular_power: Option<f32>,
+    pub emissive_color: Option<Vec3>,
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+    pub alpha: Option<f32>,
+    pub uv_scale: Option<Vec2>,
+    pub uv_offset: Option<Vec2>,
+    pub unlit: Option<bool>,
+    /// Which triangle faces are not drawn, eg `"none"` for thin surfaces that
+    /// are seen from both sides.
+    pub cull_mode: Option<CullMode>,
+    /// The lighting model, `"phong"` or `"pbr"`.
+    pub shading: Option<ShadingModel>,
+}
+
+impl MaterialDescription {
+    /// Create a material with the described constants, and the default value
+    /// for every constant that is not described.
+    pub fn build(&self, default_textures: &DefaultTextures) -> Material {
+        let mut builder = MaterialBuilder::new();
+
+        if let Some(color) = self.ambient_color {
+            builder = builder.ambient_color(color);
+        }
+
+        if let Some(color) = self.diffuse_color {
+            builder = builder.diffuse_color(color);
+        }
+
+        if let Some(color) = self.specular_color {
+            builder = builder.specular_color(color);
+        }
+
+        if let Some(power) = self.specular_power {
+            builder = builder.specular_power(power);
+        }
+
+        if let Some(color) = self.emissive_color {
+            builder = builder.emissive_color(color);
+        }
+
+        if let Some(metallic) = self.metallic {
+            builder = builder.metallic(metallic);
+        }
+
+        if let Some(roughness) = self.roughness {
+            builder = builder.roughness(roughness);
+        }
+
+        if let Some(alpha) = self.alpha {
+            builder = builder.alpha(alpha);
+        }
+
+        if let Some(scale) = self.uv_scale {
+            builder = builder.uv_scale(scale);
+        }
+
+        if let Some(offset) = self.uv_offset {
+            builder = builder.uv_offset(offset);
+        }
+
+        if let Some(unlit) = self.unlit {
+            builder = builder.unlit(unlit);
+        }
+
+        if let Some(cull_mode) = self.cull_mode {
+            builder = builder.cull_mode(cull_mode);
+        }
+
+        if let Some(shading) = self.shading {
+            builder = builder.shading(shading);
+        }
+
+        builder.build(default_textures)
+    }
+}
+
+impl SceneFile {
+    /// Parse the contents of a scene file.
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(contents).context("failed to parse scene file")
+    }
+
+    /// Update the entities in `scene` with the same names as the described
+    /// entities. Described entities that are not in the scene are spawned,
+    /// unless they describe a material since they have no model to apply it
+    /// to. The scene's camera and any entities that are not described are left
+    /// as they are.
+    pub fn apply(&self, scene: &mut Scene, default_textures: &DefaultTextures) {
+        for description in &self.entities {
+            let id = match scene.find_entity(&description.name) {
+                Some(id) => id,
+                None if description.material.is_some() => {
+                    warn!(
+                        "cannot set the material of scene entity {:?} since it does not exist",
+                        description.name
+                    );
+                    continue;
+                }
+                None => scene.spawn(Entity::new(Transform::IDENTITY).with_name(&description.name)),
+            };
+
+            let entity = scene.entity_mut(id).expect("entity was just found");
+
+            if let Some(transform) = description.transform {
+                entity.transform = transform;
+            }
+
+            match &description.light {
+                Some(Light::Spot(spot)) if !spot.has_valid_cutoff() => warn!(
+                    "cannot set the light of scene entity {:?} since its spot light cutoff angles are not valid",
+                    description.name
+                ),
+                Some(light) => entity.light = Some(light.clone()),
+                None => {}
+            }
+
+            if let Some(material) = &description.material {
+                let Some(mesh) = entity.model.as_mut().and_then(|model| model.mesh_mut()) else {
+                    warn!(
+                        "cannot set the material of scene entity {:?} since it has no model, or its mesh is shared with other models",
+                        description.name
+                    );
+                    continue;
+                };
+
+                let material = material.build(default_textures);
+
+                for index in 0..mesh.submeshes().len() {
+                    if let Some(submesh) = mesh.submesh_mut(index) {
+                        submesh.set_material_constants(&material);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applies a scene file to a scene when the game starts, and again whenever
+/// the file is edited while the game is running.
+pub struct SceneHotReload {
+    watcher: FileWatcher,
+}
+
+impl SceneHotReload {
+    /// Watch the scene file at `file_path`, which is relative to the content
+    /// directory.
+    pub fn new<P: AsRef<Path>>(file_path: P) -> anyhow::Result<Self> {
+        Ok(Self {
+            watcher: FileWatcher::new(file_path)?,
+        })
+    }
+
+    /// Apply the scene file to `scene` if it is the first update or the file
+    /// changed since the last update, and return true if it was applied. Files
+    /// that fail to parse are logged and skipped.
+    pub fn update(&mut self, scene: &mut Scene, default_textures: &DefaultTextures) -> bool {
+        let Some(contents) = self.watcher.poll() else {
+            return false;
+        };
+
+        match SceneFile::parse(&contents) {
+            Ok(scene_file) => {
+                scene_file.apply(scene, default_textures);
+                info!("applied scene file {:?}", self.watcher.path());
+                true
+            }
+            Err(e) => {
+                warn!("skipped scene file {:?}: {e:#}", self.watcher.path());
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use glam::Quat;
+
+    use super::*;
+    use crate::{
+        content::ContentManager,
+        renderer::{
+            lighting::PointLight,
+            meshes::{builtin_mesh, BuiltinMesh},
+            models::Model,
+        },
+        test_support::{test_camera, test_renderer},
+    };
+
+    #[test]
+    fn scene_files_update_named_entities_in_place() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let mut scene = Scene::new(test_camera(4, 4));
+        let model = scene.spawn(
+            Entity::new(Transform::IDENTITY)
+                .with_name("crate")
+                .with_model(Model::new(cube, Vec3::ZERO, Quat::IDENTITY, Vec3::ONE)),
+        );
+        let light = scene.spawn(
+            Entity::new(Transform::IDENTITY)
+                .with_name("lamp")
+                .with_light(PointLight::default()),
+        );
+
+        for submesh in scene.model(model).unwrap().mesh().submeshes() {
+            submesh.update_gpu(renderer.gpu().queue());
+        }
+
+        let scene_file = SceneFile::parse(
+            r#"{
+                "entities": [
+                    { "name": "crate", "transform": { "translation": [1, 2, 3] }, "material": { "diffuse_color": [1, 0, 0], "cull_mode": "none", "shading": "pbr" } },
+                    { "name": "lamp", "light": { "point": { "position": [0, 4, 0], "intensity": 2 } } },
+                    { "name": "sun", "light": { "directional": { "direction": [0, -1, 0] } } },
+                    { "name": "torch", "light": { "spot": { "cutoff_radians": 0.5, "outer_cutoff_radians": 0.2 } } },
+                    { "name": "missing", "material": { "alpha": 0.5 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        scene_file.apply(&mut scene, content.default_textures());
+
+        assert_eq!(
+            Vec3::new(1.0, 2.0, 3.0),
+            scene.entity(model).unwrap().transform.translation
+        );
+        assert!(scene
+            .model(model)
+            .unwrap()
+            .mesh()
+            .submeshes()
+            .iter()
+            .all(|submesh| submesh.is_dirty()));
+
+        // Lights are replaced, and lights that were not in the scene are
+        // spawned. Entities with only a material need a model to exist.
+        assert_eq!(Some(light), scene.find_entity("lamp"));
+        let point_lights = scene.point_lights();
+        assert_eq!(1, point_lights.len());
+        assert_eq!(Vec3::new(0.0, 4.0, 0.0), point_lights[0].position);
+        assert_eq!(2.0, point_lights[0].intensity);
+        assert_eq!(1, scene.directional_lights().len());
+        assert!(scene.spot_lights().is_empty());
+        assert_eq!(None, scene.find_entity("missing"));
+
+        assert!(SceneFile::parse("{ \"entities\": [{}] }").is_err());
+    }
+}
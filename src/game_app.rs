@@ -1,33 +1,458 @@
+pub mod demos;
+pub mod frame_pacer;
+pub mod frame_stats;
+pub mod input_state;
+pub mod instancing_demo;
+pub mod lighting_demo;
+pub mod model_viewer;
 pub mod multi_cube_demo;
+pub mod sim_clock;
+pub mod triangle_demo;
 
 use std::time::Duration;
 
-use tracing::{debug, error, warn};
+use anyhow::bail;
+use glam::Vec3;
+use tracing::{debug, error, info, warn};
+use winit::event_loop::ControlFlow;
 
-use crate::renderer::{scene::Scene, Renderer};
+#[cfg(not(target_arch = "wasm32"))]
+use frame_pacer::EventLoopWait;
+use frame_pacer::{FramePacer, FrameWaitStrategy};
+use frame_stats::{FrameSample, FrameStats};
+use input_state::InputState;
+use sim_clock::SimClock;
+
+#[cfg(feature = "gamepad")]
+use crate::gameplay::gamepad::Gamepads;
+#[cfg(target_arch = "wasm32")]
+use crate::renderer::{NewDevice, RendererError};
+
+use crate::{
+    camera::Camera,
+    content::LoadProgressSink,
+    gameplay::{
+        gamepad::GamepadEvent,
+        gestures::{GestureDetector, MouseGesture},
+    },
+    platform::SystemTime,
+    renderer::{
+        post_process::{ColorGradingEffect, FxaaEffect, PostEffect, TonemapEffect, VignetteEffect},
+        recreated_resources::RecreatedResources,
+        render_context::RenderContext,
+        scene::Scene,
+        viewport::Viewport,
+        Renderer, Tonemapper,
+    },
+};
+
+/// Controls when the host asks the window to redraw.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderLoopMode {
+    /// Redraw every frame regardless of whether anything changed.
+    #[default]
+    Continuous,
+    /// Only redraw after input, a window resize, or when the game app reports
+    /// that it wants continuous redraws (eg it is animating).
+    OnDemand,
+}
+
+/// Receives a GPU device that is requested in the background, or the error
+/// that stopped it from being created.
+#[cfg(target_arch = "wasm32")]
+type PendingDevice = std::rc::Rc<std::cell::RefCell<Option<Result<NewDevice, RendererError>>>>;
+
+/// A game registered with the host that can be switched to.
+struct GameSlot {
+    /// The game, or `None` for the active game's slot since the active game is
+    /// held by the host while it runs.
+    game: Option<Box<dyn GameApp>>,
+    content_loaded: bool,
+}
 
 /// Dispatches events coming from the underlying platform to the game for
 /// execution.
+///
+/// The host can hold several games (eg demo scenes) and switch between them
+/// with `switch_to`. Only the active game receives events and is drawn.
 pub struct GameAppHost<'a> {
     renderer: Renderer<'a>, // TODO: Refactor so renderer does not need to be stored.
+    /// The active game.
     game: Box<dyn GameApp>,
+    /// Every game registered with the host, indexed by the order they were
+    /// added.
+    game_slots: Vec<GameSlot>,
+    active_game: usize,
+    /// Reports loading progress for games that are loaded when first switched
+    /// to.
+    progress: Option<LoadProgressSink>,
+    /// Recognizes clicks and drags from mouse events for the active game.
+    gestures: GestureDetector,
+    /// Keyboard and mouse state passed to the active game's `update_sim`.
+    input_state: InputState,
+    /// Limits the frame rate when a frame rate cap is set.
+    frame_pacer: FramePacer,
+    /// Scales, pauses and single steps the time passed to `update_sim`.
+    sim_clock: SimClock,
     mouse_captured: bool,
+    render_loop_mode: RenderLoopMode,
+    /// Builds the active game's UI each frame.
+    ui: egui::Context,
+    /// Translates window events into egui input, or `None` when the renderer
+    /// has no window.
+    ui_input: Option<egui_winit::State>,
+    /// Timings and render counts of recent frames.
+    frame_stats: FrameStats,
+    /// Draw `frame_stats` over each frame when true.
+    show_frame_stats: bool,
+    /// Draw the host's settings window over each frame when true.
+    show_settings: bool,
+    /// Game picked in the settings window, which is switched to before the
+    /// next update.
+    next_game: Option<usize>,
+    /// When the previous frame started rendering, or `None` before the first
+    /// frame.
+    last_render_start: Option<SystemTime>,
+    /// Time spent in the active game's `update_sim` since the last frame.
+    update_time: Duration,
+    /// Reads connected gamepads, or `None` if they cannot be read.
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<Gamepads>,
+    /// Receives the GPU device requested to replace a lost one, or `None` when
+    /// no device is being requested.
+    #[cfg(target_arch = "wasm32")]
+    pending_device: Option<PendingDevice>,
 }
 
 impl<'a> GameAppHost<'a> {
+    /// The longest frame delta passed to the game in on-demand mode. Frames
+    /// can be far apart when nothing is changing, and the game should not
+    /// simulate the entire idle period once it wakes up.
+    const MAX_ON_DEMAND_FRAME_DELTA: Duration = Duration::from_millis(100);
+
+    /// How often connected gamepads are checked for input while no frames are
+    /// being drawn.
+    #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+    const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    #[allow(dead_code)]
     pub fn new(renderer: Renderer<'a>, game: Box<dyn GameApp>) -> Self {
+        Self::with_games(renderer, vec![game], 0)
+    }
+
+    /// Create a host for several games that can be switched between, with the
+    /// game at `active_game` as the active game. Games keep their index in
+    /// `games` when switching to them with `switch_to`.
+    pub fn with_games(
+        renderer: Renderer<'a>,
+        mut games: Vec<Box<dyn GameApp>>,
+        active_game: usize,
+    ) -> Self {
+        assert!(active_game < games.len(), "active game is out of range");
+
+        let ui = egui::Context::default();
+        let ui_input = Self::create_ui_input(&ui, &renderer);
+
+        let game = games.remove(active_game);
+        let mut game_slots: Vec<GameSlot> = games
+            .into_iter()
+            .map(|game| GameSlot {
+                game: Some(game),
+                content_loaded: false,
+            })
+            .collect();
+        game_slots.insert(
+            active_game,
+            GameSlot {
+                game: None,
+                content_loaded: false,
+            },
+        );
+
         Self {
             renderer,
             game,
+            game_slots,
+            active_game,
+            progress: None,
+            gestures: GestureDetector::new(),
+            input_state: InputState::new(),
+            frame_pacer: FramePacer::new(),
+            sim_clock: SimClock::new(),
             mouse_captured: false,
+            render_loop_mode: RenderLoopMode::default(),
+            ui,
+            ui_input,
+            frame_stats: FrameStats::default(),
+            show_frame_stats: false,
+            show_settings: false,
+            next_game: None,
+            last_render_start: None,
+            update_time: Duration::ZERO,
+            #[cfg(feature = "gamepad")]
+            gamepads: Gamepads::new()
+                .map_err(|e| warn!("gamepad input is unavailable: {e:#}"))
+                .ok(),
+            #[cfg(target_arch = "wasm32")]
+            pending_device: None,
+        }
+    }
+
+    /// Register another game that can be switched to, and return its index.
+    /// The game's content is not loaded until the first time it is active.
+    #[allow(dead_code)]
+    pub fn add_game(&mut self, game: Box<dyn GameApp>) -> usize {
+        self.game_slots.push(GameSlot {
+            game: Some(game),
+            content_loaded: false,
+        });
+
+        self.game_slots.len() - 1
+    }
+
+    /// Get the number of games registered with the host.
+    pub fn game_count(&self) -> usize {
+        self.game_slots.len()
+    }
+
+    /// Get the index of the active game.
+    pub fn active_game(&self) -> usize {
+        self.active_game
+    }
+
+    /// Make the game at `index` the active game. The game's content is loaded
+    /// the first time it becomes active, and kept loaded when switching away
+    /// from it. The active game is not changed if loading fails.
+    pub fn switch_to(&mut self, index: usize) -> anyhow::Result<()> {
+        if index == self.active_game {
+            return Ok(());
+        }
+
+        let Some(slot) = self.game_slots.get_mut(index) else {
+            bail!("cannot switch to game {index}, there is no game with that index");
+        };
+
+        let mut next_game = slot
+            .game
+            .take()
+            .expect("only the active game's slot is empty");
+
+        if !slot.content_loaded {
+            if let Err(e) = next_game.load_content(self.renderer.context(), self.progress.clone()) {
+                slot.game = Some(next_game);
+                return Err(e);
+            }
+
+            slot.content_loaded = true;
         }
+
+        // Swap the next game in and hand the previous game back to its slot.
+        let previous_game = std::mem::replace(&mut self.game, next_game);
+        self.game_slots[self.active_game].game = Some(previous_game);
+        self.active_game = index;
+
+        info!("switched to game {index}");
+
+        // The window may have been resized while the game was inactive.
+        let window_size = self.renderer.window_size();
+        self.game
+            .render_scene_mut()
+            .set_viewport_size(window_size.width, window_size.height);
+        self.game
+            .window_resized(window_size.width, window_size.height);
+        self.request_redraw();
+
+        Ok(())
     }
 
-    pub fn load_content(&mut self) -> anyhow::Result<()> {
-        self.game.load_content(&mut self.renderer)
+    /// Get the host's render loop mode.
+    pub fn render_loop_mode(&self) -> RenderLoopMode {
+        self.render_loop_mode
     }
 
-    pub fn renderer(&self) -> &Renderer {
+    /// Set when the host should ask the window to redraw.
+    pub fn set_render_loop_mode(&mut self, mode: RenderLoopMode) {
+        self.render_loop_mode = mode;
+        self.request_redraw();
+    }
+
+    /// Get the frame rate cap, or `None` if the frame rate is not capped.
+    pub fn frame_rate_cap(&self) -> Option<u32> {
+        self.frame_pacer.target_fps()
+    }
+
+    /// Limit drawing to at most `fps` frames per second, or remove the limit
+    /// when `fps` is `None`.
+    pub fn set_frame_rate_cap(&mut self, fps: Option<u32>) {
+        self.frame_pacer.set_target_fps(fps);
+        self.request_redraw();
+    }
+
+    /// Get how the native render loop waits for the next frame when the frame
+    /// rate is capped.
+    pub fn frame_wait_strategy(&self) -> FrameWaitStrategy {
+        self.frame_pacer.wait_strategy()
+    }
+
+    /// Set how the native render loop waits for the next frame when the frame
+    /// rate is capped.
+    pub fn set_frame_wait_strategy(&mut self, wait_strategy: FrameWaitStrategy) {
+        self.frame_pacer.set_wait_strategy(wait_strategy);
+    }
+
+    /// Returns true if frames are drawn one after another rather than only
+    /// when something changed.
+    pub fn wants_continuous_redraw(&self) -> bool {
+        self.render_loop_mode == RenderLoopMode::Continuous
+            || self.game.wants_continuous_redraw()
+            || self.is_gamepad_in_use()
+    }
+
+    /// Returns true if a gamepad button is held or a stick is pushed, which
+    /// keeps changing what is drawn without sending new events.
+    fn is_gamepad_in_use(&self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "gamepad")] {
+                self.gamepads.as_ref().is_some_and(Gamepads::is_in_use)
+            } else {
+                false
+            }
+        }
+    }
+
+    /// Send pending gamepad events to the active game, and get how the event
+    /// loop should wait for events when no frames need to be drawn. Gamepads
+    /// do not wake the event loop, so it keeps waking up to check them while
+    /// any are connected.
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepads(&mut self) -> ControlFlow {
+        let Some(gamepads) = &mut self.gamepads else {
+            return ControlFlow::Wait;
+        };
+
+        let events = std::iter::from_fn(|| gamepads.next_event()).collect::<Vec<_>>();
+        let connected = gamepads.is_connected();
+
+        for event in events {
+            self.gamepad_input(&event);
+        }
+
+        if !connected {
+            return ControlFlow::Wait;
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                // Gamepads are read on each animation frame.
+                self.request_redraw();
+                ControlFlow::Wait
+            } else {
+                ControlFlow::WaitUntil(std::time::Instant::now() + Self::GAMEPAD_POLL_INTERVAL)
+            }
+        }
+    }
+
+    /// Request the next frame if it is due, and get how the event loop should
+    /// wait for events until then. Call this each time the event loop is about
+    /// to wait for new events.
+    ///
+    /// Natively the loop polls or sleeps until the next frame is due, following
+    /// the frame wait strategy. On the web a redraw is requested right away
+    /// since redraws are delivered on the browser's next animation frame, which
+    /// paces the loop, so polling would only keep the CPU busy.
+    pub fn about_to_wait(&mut self) -> ControlFlow {
+        #[cfg(feature = "gamepad")]
+        let idle = self.poll_gamepads();
+        #[cfg(not(feature = "gamepad"))]
+        let idle = ControlFlow::Wait;
+
+        if !self.wants_continuous_redraw() {
+            return idle;
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                self.request_redraw();
+                ControlFlow::Wait
+            } else {
+                match self.frame_pacer.event_loop_wait(SystemTime::now()) {
+                    EventLoopWait::Redraw => {
+                        self.request_redraw();
+                        ControlFlow::Poll
+                    }
+                    EventLoopWait::Poll => ControlFlow::Poll,
+                    EventLoopWait::Sleep(duration) => {
+                        ControlFlow::WaitUntil(std::time::Instant::now() + duration)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait until the next frame should be drawn when the frame rate is capped.
+    /// Call this when a redraw is requested, and only draw the frame if it
+    /// returns true.
+    ///
+    /// Natively this sleeps until the frame is due and always returns true. On
+    /// the web the browser paces redraws, so frames that arrive early are
+    /// skipped by returning false and asking for another redraw.
+    pub fn begin_frame(&mut self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                if !self.frame_pacer.is_frame_due(SystemTime::now()) {
+                    self.request_redraw();
+                    return false;
+                }
+            } else {
+                self.frame_pacer.wait_for_next_frame();
+            }
+        }
+
+        self.frame_pacer.frame_started(SystemTime::now());
+        self.renderer.set_frame_rate_stats(
+            self.frame_pacer.measured_fps(),
+            self.frame_pacer.target_fps(),
+        );
+
+        true
+    }
+
+    /// Ask the window to redraw the next time the event loop is idle.
+    pub fn request_redraw(&self) {
+        if let Some(window) = self.renderer.window() {
+            window.request_redraw();
+        }
+    }
+
+    /// Get the time delta to pass to the game for a frame that was drawn
+    /// `elapsed` after the previous one.
+    pub fn frame_delta(&self, elapsed: Duration) -> Duration {
+        match self.render_loop_mode {
+            RenderLoopMode::Continuous => elapsed,
+            RenderLoopMode::OnDemand => elapsed.min(Self::MAX_ON_DEMAND_FRAME_DELTA),
+        }
+    }
+
+    /// Load the active game's content. Per-asset loading progress is sent to
+    /// `progress` when it is provided (eg to draw a loading bar). Other games
+    /// report to the same `progress` when they are loaded by `switch_to`.
+    pub fn load_content(&mut self, progress: Option<LoadProgressSink>) -> anyhow::Result<()> {
+        self.progress = progress;
+        self.game
+            .load_content(self.renderer.context(), self.progress.clone())?;
+        self.game_slots[self.active_game].content_loaded = true;
+
+        // Make sure the scene's camera matches the size of the render window.
+        let window_size = self.renderer.window_size();
+        self.game
+            .render_scene_mut()
+            .set_viewport_size(window_size.width, window_size.height);
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn renderer(&self) -> &Renderer<'a> {
         &self.renderer
     }
 
@@ -36,23 +461,166 @@ impl<'a> GameAppHost<'a> {
         // If renderer.input returns false do not let game app handle input but
         // also issue a warning that it was overridden?
         self.renderer.input(event);
-        self.game.input(event)
+
+        // Events used by the UI (eg clicking a button or typing into a text
+        // field) are not passed on to the game.
+        if let (Some(ui_input), Some(window)) = (&mut self.ui_input, self.renderer.window()) {
+            let response = ui_input.on_window_event(window, event);
+
+            if response.repaint {
+                self.request_redraw();
+            }
+
+            if response.consumed {
+                self.input_state.process_ui_input(event);
+                return true;
+            }
+        }
+
+        // User input could change what is drawn.
+        if matches!(
+            event,
+            winit::event::WindowEvent::KeyboardInput { .. }
+                | winit::event::WindowEvent::ModifiersChanged(_)
+                | winit::event::WindowEvent::MouseInput { .. }
+                | winit::event::WindowEvent::MouseWheel { .. }
+                | winit::event::WindowEvent::CursorMoved { .. }
+        ) {
+            self.request_redraw();
+        }
+
+        self.input_state.process_input(event);
+        let handled = self.game.input(event);
+
+        for gesture in self.gestures.process_input(event, SystemTime::now()) {
+            self.game.mouse_gesture(&gesture);
+        }
+
+        handled
     }
 
+    /// Advance the active game's simulation for a frame drawn `delta` after
+    /// the previous one. The game is still updated while the simulation is
+    /// paused, but with no time passing, so it keeps responding to input.
     pub fn update_sim(&mut self, delta: Duration) {
-        self.game.update_sim(delta)
+        if let Some(index) = self.next_game.take() {
+            if let Err(e) = self.switch_to(index) {
+                warn!("failed to switch demo scene: {e:#}");
+            }
+        }
+
+        let delta = self.sim_clock.advance(delta);
+
+        let update_start = SystemTime::now();
+        self.game.update_sim(delta, &self.input_state);
+        self.update_time += SystemTime::now() - update_start;
+
+        self.input_state.end_update();
+    }
+
+    /// Get how much faster than real time the simulation runs.
+    pub fn time_scale(&self) -> f32 {
+        self.sim_clock.time_scale()
+    }
+
+    /// Set how much faster than real time the simulation runs, eg 0.5 for half
+    /// speed. Rendering is not affected.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.sim_clock.set_time_scale(time_scale);
+    }
+
+    /// Returns true if the simulation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.sim_clock.is_paused()
+    }
+
+    /// Stop advancing the simulation. Frames are still drawn, so cameras and
+    /// the UI keep working.
+    pub fn pause(&mut self) {
+        info!("simulation paused");
+        self.sim_clock.pause();
+    }
+
+    /// Advance the simulation again after it was paused.
+    pub fn resume(&mut self) {
+        info!("simulation resumed");
+        self.sim_clock.resume();
+    }
+
+    /// Pause the simulation if it is running, and advance it by a single
+    /// frame in the next update.
+    pub fn step(&mut self) {
+        self.sim_clock.step();
+        self.request_redraw();
+    }
+
+    /// Get the keyboard and mouse state that is passed to the next update.
+    pub fn input_state(&self) -> &InputState {
+        &self.input_state
     }
 
     pub fn render(&mut self, delta: Duration) {
+        // Browsers can discard the GPU context when a tab is hidden, and drivers
+        // can reset the GPU. A lost device stays lost, so it is replaced.
+        if self.renderer.take_device_lost() {
+            self.start_device_recovery();
+        }
+
+        // Nothing can be drawn with the lost device while the browser creates
+        // the new one.
+        #[cfg(target_arch = "wasm32")]
+        if self.poll_device_recovery() {
+            return;
+        }
+
+        let render_start = SystemTime::now();
+        let frame_time = self
+            .last_render_start
+            .map_or(Duration::ZERO, |last_start| render_start - last_start);
+        self.last_render_start = Some(render_start);
+
         self.game.prepare_render(&mut self.renderer, delta);
+        let prepare_render_time = SystemTime::now() - render_start;
+
+        self.build_ui();
+
+        if self.show_frame_stats {
+            let width = self.renderer.window_size().width;
+            self.frame_stats.draw_overlay(self.renderer.text(), width);
+        }
+
+        let scene = self.game.render_scene();
+        let result = match self.game.split_screen_camera() {
+            Some(camera) => {
+                let size = self.renderer.window_size();
+                let [left, right] = Viewport::side_by_side(size.width, size.height);
+                let views = [(scene.camera.clone(), left), (camera.clone(), right)];
 
-        match self.renderer.render(self.game.render_scene(), delta) {
+                self.renderer.render_viewports(scene, &views, delta)
+            }
+            None => self.renderer.render(scene, delta),
+        };
+
+        match result {
             Ok(_) => {}
-            // Reconfigure surface when lost:
-            Err(wgpu::SurfaceError::Lost) | Err(wgpu::SurfaceError::Outdated) => {
-                warn!("handling surface lost or outdated event by re-applying current window size");
-                let window_size = self.renderer.window_size();
-                self.renderer.resize(window_size.width, window_size.height);
+            // The device still works, so only the surface is replaced.
+            Err(wgpu::SurfaceError::Lost) => {
+                warn!("handling surface lost event by recreating the surface");
+                self.recreate_surface();
+            }
+            // The window changed (eg it was resized) since the surface was
+            // configured.
+            Err(wgpu::SurfaceError::Outdated) => {
+                warn!("handling surface outdated event by reconfiguring the surface");
+                self.renderer.reconfigure_surface();
+                info!("reconfigured the rendering surface");
+            }
+            // A lost WebGL/WebGPU context can be reported as out of memory.
+            // Surfaces cannot be used with the lost device, so the device is
+            // replaced before giving up.
+            Err(wgpu::SurfaceError::OutOfMemory) if cfg!(target_arch = "wasm32") => {
+                error!("WGPU out of memory error, attempting to replace the GPU device");
+                self.start_device_recovery();
             }
             // System is out of memory - bail out!
             Err(wgpu::SurfaceError::OutOfMemory) => {
@@ -63,45 +631,602 @@ impl<'a> GameAppHost<'a> {
                 error!("WGPU error, will skip frame and try to ignore: {e:?}");
             }
         }
+
+        let render_stats = self.renderer.stats();
+        self.frame_stats.record(FrameSample {
+            frame_time,
+            update_time: std::mem::take(&mut self.update_time),
+            prepare_render_time,
+            gpu_time: render_stats.gpu_time(),
+            draw_calls: render_stats.draw_calls,
+            triangles: render_stats.triangles,
+            uploaded_bytes: render_stats.uploaded_bytes,
+        });
+    }
+
+    /// Get the timings and render counts of recent frames.
+    #[allow(dead_code)]
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Returns true if the frame stats are drawn over each frame.
+    pub fn is_showing_frame_stats(&self) -> bool {
+        self.show_frame_stats
+    }
+
+    /// Draw the averaged frame stats in the corner of each frame when
+    /// `is_shown` is true.
+    pub fn set_show_frame_stats(&mut self, is_shown: bool) {
+        self.show_frame_stats = is_shown;
+        self.request_redraw();
+    }
+
+    /// Write the averaged frame stats to the log.
+    pub fn log_frame_stats(&self) {
+        self.frame_stats.log_summary();
+    }
+
+    /// Returns true if the host's settings window is drawn over each frame.
+    pub fn is_showing_settings(&self) -> bool {
+        self.show_settings
+    }
+
+    /// Draw a window for changing the host's settings over each frame when
+    /// `is_shown` is true.
+    pub fn set_show_settings(&mut self, is_shown: bool) {
+        self.show_settings = is_shown;
+        self.request_redraw();
+    }
+
+    /// Returns true if the mouse cursor is over the UI or the UI is being
+    /// dragged, in which case mouse clicks belong to the UI.
+    pub fn ui_wants_pointer_input(&self) -> bool {
+        self.ui.wants_pointer_input()
+    }
+
+    /// Run the active game's UI code, and the host's settings window when it
+    /// is shown, for this frame and hand the result to the renderer.
+    fn build_ui(&mut self) {
+        let raw_input = match (&mut self.ui_input, self.renderer.window()) {
+            (Some(ui_input), Some(window)) => ui_input.take_egui_input(window),
+            // Headless renderers have no window events, so the UI only knows
+            // the size of the render target.
+            _ => {
+                let size = self.renderer.window_size();
+                egui::RawInput {
+                    screen_rect: Some(egui::Rect::from_min_size(
+                        egui::Pos2::ZERO,
+                        egui::vec2(size.width as f32, size.height as f32),
+                    )),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let ui = self.ui.clone();
+        let output = ui.run(raw_input, |ctx| {
+            self.game.build_ui(ctx);
+
+            if self.show_settings {
+                self.build_settings_ui(ctx);
+            }
+        });
+
+        if let (Some(ui_input), Some(window)) = (&mut self.ui_input, self.renderer.window()) {
+            ui_input.handle_platform_output(window, output.platform_output);
+        }
+
+        // Keep drawing while the UI is animating (eg a window fading in).
+        if output
+            .viewport_output
+            .get(&egui::ViewportId::ROOT)
+            .is_some_and(|viewport| viewport.repaint_delay.is_zero())
+        {
+            self.request_redraw();
+        }
+
+        let primitives = self.ui.tessellate(output.shapes, output.pixels_per_point);
+        self.renderer
+            .set_ui(primitives, output.textures_delta, output.pixels_per_point);
+    }
+
+    /// Show a window for changing how the host draws frames.
+    fn build_settings_ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Engine settings").show(ctx, |ui| {
+            ui.heading("Scenes");
+
+            let active_game = self.active_game();
+            ui.horizontal(|ui| {
+                ui.label("Scene");
+
+                for index in 0..self.game_count() {
+                    if ui
+                        .selectable_label(index == active_game, (index + 1).to_string())
+                        .clicked()
+                    {
+                        self.next_game = Some(index);
+                    }
+                }
+            });
+
+            ui.heading("Frames");
+
+            let mut render_loop_mode = self.render_loop_mode();
+            ui.horizontal(|ui| {
+                ui.label("Redraw");
+                ui.radio_value(
+                    &mut render_loop_mode,
+                    RenderLoopMode::Continuous,
+                    "Continuously",
+                );
+                ui.radio_value(&mut render_loop_mode, RenderLoopMode::OnDemand, "On demand");
+            });
+
+            if render_loop_mode != self.render_loop_mode() {
+                self.set_render_loop_mode(render_loop_mode);
+            }
+
+            let mut capped = self.frame_rate_cap().is_some();
+            let mut fps = self.frame_rate_cap().unwrap_or(60);
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut capped, "Cap frame rate");
+                ui.add_enabled(capped, egui::Slider::new(&mut fps, 15..=240).suffix(" fps"));
+            });
+
+            let frame_rate_cap = capped.then_some(fps);
+
+            if frame_rate_cap != self.frame_rate_cap() {
+                self.set_frame_rate_cap(frame_rate_cap);
+            }
+
+            // Web frames are always paced by the browser.
+            let mut wait_strategy = self.frame_wait_strategy();
+            ui.add_enabled_ui(capped && !cfg!(target_arch = "wasm32"), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Wait for frames by");
+                    ui.radio_value(&mut wait_strategy, FrameWaitStrategy::Sleep, "Sleeping");
+                    ui.radio_value(&mut wait_strategy, FrameWaitStrategy::Spin, "Spinning");
+                    ui.radio_value(
+                        &mut wait_strategy,
+                        FrameWaitStrategy::SleepThenSpin,
+                        "Sleeping then spinning",
+                    );
+                });
+            });
+
+            if wait_strategy != self.frame_wait_strategy() {
+                self.set_frame_wait_strategy(wait_strategy);
+            }
+
+            let mut max_frame_latency = self.renderer.max_frame_latency();
+
+            if ui
+                .add(egui::Slider::new(&mut max_frame_latency, 1..=3).text("Max frame latency"))
+                .changed()
+            {
+                self.renderer.set_max_frame_latency(max_frame_latency);
+            }
+
+            ui.heading("Simulation");
+
+            let mut time_scale = self.time_scale();
+            if ui
+                .add(
+                    egui::Slider::new(&mut time_scale, 0.1..=4.0)
+                        .logarithmic(true)
+                        .text("Time scale"),
+                )
+                .changed()
+            {
+                self.set_time_scale(time_scale);
+            }
+
+            ui.horizontal(|ui| {
+                let mut paused = self.is_paused();
+                if ui.checkbox(&mut paused, "Paused (F5)").changed() {
+                    if paused {
+                        self.pause();
+                    } else {
+                        self.resume();
+                    }
+                }
+
+                if ui.button("Step (F6)").clicked() {
+                    self.step();
+                }
+            });
+
+            ui.heading("Post processing");
+            self.build_post_process_ui(ui);
+
+            ui.heading("Ambient occlusion");
+
+            let mut ssao = self.renderer.ssao_settings();
+            ui.checkbox(&mut ssao.enabled, "Screen space ambient occlusion");
+            ui.add_enabled_ui(ssao.enabled, |ui| {
+                ui.add(egui::Slider::new(&mut ssao.kernel_size, 4..=64).text("Samples"));
+                ui.add(egui::Slider::new(&mut ssao.radius, 0.05..=2.0).text("Radius"));
+                ui.add(egui::Slider::new(&mut ssao.bias, 0.0..=0.1).text("Bias"));
+                ui.add(egui::Slider::new(&mut ssao.intensity, 0.0..=1.0).text("Intensity"));
+            });
+
+            if ssao != self.renderer.ssao_settings() {
+                self.renderer.set_ssao_settings(ssao);
+            }
+
+            ui.heading("Vertex normals");
+            ui.label("Press N to draw the vertex normals of selected models.");
+
+            let mut color = self.renderer.normals_color().to_array();
+            ui.horizontal(|ui| {
+                ui.label("Color");
+                if ui.color_edit_button_rgb(&mut color).changed() {
+                    self.renderer.set_normals_color(Vec3::from_array(color));
+                }
+            });
+
+            let mut length = self.renderer.normals_length();
+            if ui
+                .add(egui::Slider::new(&mut length, 0.01..=2.0).text("Length"))
+                .changed()
+            {
+                self.renderer.set_normals_length(length);
+            }
+
+            ui.heading("Input");
+
+            let input = self.input_state();
+            let mut keys: Vec<String> = input.keys_down().map(|key| format!("{key:?}")).collect();
+            keys.sort();
+            ui.label(format!("Keys held: {}", keys.join(", ")));
+            ui.label(match input.cursor_position() {
+                Some(position) => format!("Cursor: {:.0}, {:.0}", position.x, position.y),
+                None => "Cursor: outside of the window".to_string(),
+            });
+        });
+    }
+
+    /// Build the settings window section that picks the tonemapper and turns
+    /// the optional post effects on and off.
+    ///
+    /// Color grading runs before the tonemap effect while colors are still in
+    /// HDR. The vignette and FXAA run after it, with FXAA last so it smooths
+    /// the final image.
+    fn build_post_process_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(mut tonemapper) = self.renderer.tonemapper() {
+            ui.horizontal(|ui| {
+                ui.label("Tonemapper");
+                ui.radio_value(&mut tonemapper, Tonemapper::Aces, "ACES");
+                ui.radio_value(&mut tonemapper, Tonemapper::Reinhard, "Reinhard");
+                ui.radio_value(&mut tonemapper, Tonemapper::Clamp, "Clamp");
+            });
+
+            if Some(tonemapper) != self.renderer.tonemapper() {
+                self.renderer.set_tonemapper(tonemapper);
+            }
+        }
+
+        let device = self.renderer.gpu().device();
+        let stack = self.renderer.post_process_stack();
+        let position = |name| stack.effect_names().position(|effect| effect == name);
+
+        let mut color_grading = stack.effect::<ColorGradingEffect>().is_some();
+        let mut vignette = stack.effect::<VignetteEffect>().is_some();
+        let mut fxaa = stack.effect::<FxaaEffect>().is_some();
+
+        // Effects are created before the stack is borrowed to add them.
+        let mut added: Option<(usize, Box<dyn PostEffect>)> = None;
+        let mut removed = None;
+
+        if ui.checkbox(&mut color_grading, "Color grading").changed() {
+            if color_grading {
+                let index = position(TonemapEffect::NAME).unwrap_or(0);
+                let effect = ColorGradingEffect::new(device, stack.input_layout());
+                added = Some((index, Box::new(effect)));
+            } else {
+                removed = Some(ColorGradingEffect::NAME);
+            }
+        }
+
+        if ui.checkbox(&mut vignette, "Vignette").changed() {
+            if vignette {
+                let index = position(FxaaEffect::NAME).unwrap_or(stack.effect_names().count());
+                let effect = VignetteEffect::new(device, stack.input_layout());
+                added = Some((index, Box::new(effect)));
+            } else {
+                removed = Some(VignetteEffect::NAME);
+            }
+        }
+
+        if ui.checkbox(&mut fxaa, "FXAA").changed() {
+            if fxaa {
+                let index = stack.effect_names().count();
+                let effect = FxaaEffect::new(device, stack.input_layout());
+                added = Some((index, Box::new(effect)));
+            } else {
+                removed = Some(FxaaEffect::NAME);
+            }
+        }
+
+        let stack = self.renderer.post_process_stack_mut();
+
+        if let Some((index, effect)) = added {
+            stack.insert(index, effect);
+        }
+
+        if let Some(name) = removed {
+            stack.remove(name);
+        }
+
+        if let Some(effect) = stack.effect_mut::<ColorGradingEffect>() {
+            let mut exposure = effect.exposure();
+            let mut contrast = effect.contrast();
+            let mut saturation = effect.saturation();
+            let mut color_filter = effect.color_filter().to_array();
+
+            if ui
+                .add(egui::Slider::new(&mut exposure, -4.0..=4.0).text("Exposure (stops)"))
+                .changed()
+            {
+                effect.set_exposure(exposure);
+            }
+
+            if ui
+                .add(egui::Slider::new(&mut contrast, 0.0..=2.0).text("Contrast"))
+                .changed()
+            {
+                effect.set_contrast(contrast);
+            }
+
+            if ui
+                .add(egui::Slider::new(&mut saturation, 0.0..=2.0).text("Saturation"))
+                .changed()
+            {
+                effect.set_saturation(saturation);
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Color filter");
+
+                if ui.color_edit_button_rgb(&mut color_filter).changed() {
+                    effect.set_color_filter(Vec3::from_array(color_filter));
+                }
+            });
+        }
+
+        if let Some(effect) = stack.effect_mut::<VignetteEffect>() {
+            let mut intensity = effect.intensity();
+            let (mut radius, mut softness) = effect.radius();
+
+            if ui
+                .add(egui::Slider::new(&mut intensity, 0.0..=1.0).text("Vignette intensity"))
+                .changed()
+            {
+                effect.set_intensity(intensity);
+            }
+
+            let radius_changed = ui
+                .add(egui::Slider::new(&mut radius, 0.2..=1.5).text("Vignette radius"))
+                .changed();
+            let softness_changed = ui
+                .add(egui::Slider::new(&mut softness, 0.05..=1.0).text("Vignette softness"))
+                .changed();
+
+            if radius_changed || softness_changed {
+                effect.set_radius(radius, softness);
+            }
+        }
+    }
+
+    /// Create the state that translates window events into input for `ui`, or
+    /// `None` when the renderer has no window.
+    fn create_ui_input(ui: &egui::Context, renderer: &Renderer) -> Option<egui_winit::State> {
+        renderer.window().map(|window| {
+            egui_winit::State::new(
+                ui.clone(),
+                egui::ViewportId::ROOT,
+                window,
+                Some(window.scale_factor() as f32),
+                Some(renderer.gpu().device().limits().max_texture_dimension_2d as usize),
+            )
+        })
+    }
+
+    /// Replace the renderer's lost surface with a new one created from the
+    /// window. The device must still work, see `start_device_recovery`.
+    fn recreate_surface(&mut self) {
+        match self.renderer.recreate_surface() {
+            Ok(_) => info!("recreated the rendering surface"),
+            Err(e) => error!("failed to recreate the rendering surface: {e:#}"),
+        }
+    }
+
+    /// Start replacing the renderer's lost GPU device with a new one. Natively
+    /// this blocks until the device is replaced, while on the web the device
+    /// is requested in the background and installed by `poll_device_recovery`
+    /// since browsers cannot block on the request.
+    fn start_device_recovery(&mut self) {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                if self.pending_device.is_some() {
+                    return;
+                }
+
+                warn!("recovering from a lost GPU device");
+
+                match self.renderer.request_new_device() {
+                    Ok(request) => {
+                        let pending_device: PendingDevice = Default::default();
+                        self.pending_device = Some(pending_device.clone());
+
+                        wasm_bindgen_futures::spawn_local(async move {
+                            *pending_device.borrow_mut() = Some(request.await);
+                        });
+                    }
+                    Err(e) => error!("failed to recover from the lost GPU device: {e:#}"),
+                }
+            } else {
+                if let Err(e) = self.recover_lost_device() {
+                    error!("failed to recover from the lost GPU device: {e:#}");
+                }
+            }
+        }
+    }
+
+    /// Install the GPU device requested by `start_device_recovery` if it
+    /// arrived since the last call. Returns true while still waiting for it.
+    #[cfg(target_arch = "wasm32")]
+    fn poll_device_recovery(&mut self) -> bool {
+        let Some(pending_device) = &self.pending_device else {
+            return false;
+        };
+
+        let new_device = pending_device.borrow_mut().take();
+
+        match new_device {
+            // Keep asking for frames so the device is installed once it arrives.
+            None => {
+                self.request_redraw();
+                true
+            }
+            Some(new_device) => {
+                self.pending_device = None;
+
+                let recovered = new_device
+                    .map_err(anyhow::Error::from)
+                    .and_then(|new_device| {
+                        self.renderer.install_device(new_device)?;
+                        self.recreate_gpu_resources()
+                    });
+
+                if let Err(e) = recovered {
+                    error!("failed to recover from the lost GPU device: {e:#}");
+                }
+
+                false
+            }
+        }
+    }
+
+    /// Replace the renderer's lost GPU device with a new one, and recreate the
+    /// meshes and textures of every game that was loaded on the new device
+    /// from the copies they keep on the CPU. The games keep their scenes and
+    /// the rest of their state.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn recover_lost_device(&mut self) -> anyhow::Result<()> {
+        warn!("recovering from a lost GPU device");
+        pollster::block_on(self.renderer.recreate_device())?;
+        self.recreate_gpu_resources()
+    }
+
+    /// Recreate the GPU resources of every game that was loaded, and of the UI,
+    /// after the renderer's device was replaced.
+    fn recreate_gpu_resources(&mut self) -> anyhow::Result<()> {
+        let mut resources = RecreatedResources::new(self.renderer.context().clone());
+        let window_size = self.renderer.window_size();
+
+        let inactive_games = self
+            .game_slots
+            .iter_mut()
+            .filter(|slot| slot.content_loaded)
+            .filter_map(|slot| slot.game.as_mut());
+
+        for game in std::iter::once(&mut self.game).chain(inactive_games) {
+            game.recreate_gpu_resources(&mut resources)?;
+            game.render_scene_mut()
+                .set_viewport_size(window_size.width, window_size.height);
+        }
+
+        // The UI's textures (eg its font atlas) were also on the old device, and
+        // are only uploaded again by a new UI context.
+        self.ui = egui::Context::default();
+        self.ui_input = Self::create_ui_input(&self.ui, &self.renderer);
+
+        info!("recovered from a lost GPU device");
+        self.request_redraw();
+
+        Ok(())
     }
 
     /// Handles when the game window ("rendering window") is resized.
     pub fn window_resized(&mut self, new_width: u32, new_height: u32) {
-        self.renderer.resize(new_width, new_height)
+        self.renderer.resize(new_width, new_height);
+        self.game
+            .render_scene_mut()
+            .set_viewport_size(new_width, new_height);
+        self.game.window_resized(new_width, new_height);
+        self.request_redraw();
+    }
+
+    /// Handles when the game window gains or loses focus.
+    pub fn focus_changed(&mut self, is_focused: bool) {
+        self.set_mouse_captured(is_focused);
+        self.game.focus_changed(is_focused);
+
+        // Keys released while the window is not focused are never seen.
+        if !is_focused {
+            self.input_state.release_all();
+        }
+        self.request_redraw();
     }
 
     /// Handles when Windows DPI scaling is changed.
     pub fn scale_factor_changed(&mut self) {
-        let new_size = self.renderer.window().inner_size();
-        self.renderer.resize(new_size.width, new_size.height)
+        if let Some(window) = self.renderer.window() {
+            let new_size = window.inner_size();
+            self.window_resized(new_size.width, new_size.height)
+        }
     }
 
     /// Handles when the mouse moves.
     pub fn mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
-        self.game.mouse_motion(delta_x, delta_y)
+        self.input_state.process_mouse_motion(delta_x, delta_y);
+        self.game.mouse_motion(delta_x, delta_y);
+        self.request_redraw();
     }
 
     /// Handles when the mouse wheel is scrolled up or down.
     pub fn mouse_scroll_wheel(&mut self, delta_x: f64, delta_y: f64) {
-        self.game.mouse_scroll_wheel(delta_x, delta_y)
+        self.input_state.process_mouse_wheel(delta_x, delta_y);
+        self.game.mouse_scroll_wheel(delta_x, delta_y);
+        self.request_redraw();
     }
 
+    /// Handles when a gamepad button, stick or trigger changes.
+    // Only called when gamepads are read.
+    #[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+    pub fn gamepad_input(&mut self, event: &GamepadEvent) -> bool {
+        let handled = self.game.gamepad_input(event);
+        self.request_redraw();
+        handled
+    }
+
+    /// Returns true if the mouse cursor is captured by the render window.
     pub fn is_mouse_captured(&self) -> bool {
+        // Browsers release the pointer lock on their own when escape is
+        // pressed, so check if the page still holds it.
+        #[cfg(target_arch = "wasm32")]
+        if self.mouse_captured && !crate::wasm_support::is_pointer_locked() {
+            return false;
+        }
+
         self.mouse_captured
     }
 
+    /// Capture or release the mouse cursor. Capturing hides the cursor and
+    /// keeps it inside the render window. The mouse is left uncaptured if no
+    /// way of capturing it is supported by the platform.
     pub fn set_mouse_captured(&mut self, is_captured: bool) {
-        let window = self.renderer.window();
+        let Some(window) = self.renderer.window() else {
+            return;
+        };
 
-        // Lock the cursor to the screen.
-        if let Err(_e) = window.set_cursor_grab(if is_captured {
-            winit::window::CursorGrabMode::Locked
+        let is_captured = if is_captured {
+            Self::grab_cursor(window)
         } else {
-            winit::window::CursorGrabMode::None
-        }) {
-            // TODO: Handle error by manually locking the cursor?
-            //       First check when this can fail.
-            warn!("failed to lock/unlock cursor")
+            Self::release_cursor(window);
+            false
         };
 
         // The cursor should be hidden when the mouse is captured.
@@ -111,15 +1236,69 @@ impl<'a> GameAppHost<'a> {
         debug!("mouse_captured = {is_captured}");
         self.mouse_captured = is_captured;
     }
+
+    /// Try each way of capturing the cursor that the platform might support,
+    /// and return true if one of them worked.
+    fn grab_cursor(window: &winit::window::Window) -> bool {
+        use winit::window::CursorGrabMode;
+
+        // Locking keeps the cursor in place which is best for mouse look, but
+        // not every platform supports it. Confining keeps the cursor inside of
+        // the window which is the next best thing.
+        for mode in [CursorGrabMode::Locked, CursorGrabMode::Confined] {
+            match window.set_cursor_grab(mode) {
+                Ok(_) => return true,
+                Err(e) => debug!("cursor grab mode {mode:?} is not available: {e}"),
+            }
+        }
+
+        // Ask the browser for a pointer lock on the canvas directly.
+        #[cfg(target_arch = "wasm32")]
+        if crate::wasm_support::request_pointer_lock(window) {
+            return true;
+        }
+
+        warn!("failed to capture the mouse cursor, no cursor grab mode is supported");
+        false
+    }
+
+    /// Release any cursor grab held by the window.
+    fn release_cursor(window: &winit::window::Window) {
+        if let Err(e) = window.set_cursor_grab(winit::window::CursorGrabMode::None) {
+            warn!("failed to release the mouse cursor: {e}");
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        crate::wasm_support::exit_pointer_lock();
+    }
 }
 
 /// A specific game or demo scene implementation.
 pub trait GameApp {
-    /// Loads content required by the game prior to the start of rendering
-    fn load_content(&mut self, renderer: &mut Renderer) -> anyhow::Result<()>;
+    /// Loads content required by the game prior to the start of rendering.
+    /// Loading progress should be reported to `progress` if it is provided.
+    fn load_content(
+        &mut self,
+        context: &RenderContext,
+        progress: Option<LoadProgressSink>,
+    ) -> anyhow::Result<()>;
 
-    /// Advances the game's simulation state by the given `delta`.
-    fn update_sim(&mut self, delta: Duration);
+    /// Recreates the game's meshes and textures on a new GPU device after the
+    /// old one was lost, using `resources` so resources shared with other
+    /// games stay shared.
+    ///
+    /// The default recreates everything drawn by the game's scene. Games that
+    /// keep GPU resources outside of their scene should override this to
+    /// recreate them too.
+    fn recreate_gpu_resources(&mut self, resources: &mut RecreatedResources) -> anyhow::Result<()> {
+        self.render_scene_mut().recreate_gpu_resources(resources);
+        Ok(())
+    }
+
+    /// Advances the game's simulation state by the given `delta`. `input` holds
+    /// the keys and mouse buttons that are down, and the input seen since the
+    /// previous update.
+    fn update_sim(&mut self, delta: Duration, input: &InputState);
 
     /// Prepares GPU resources for rendering in the upcoming frame.
     fn prepare_render(&mut self, renderer: &mut Renderer, delta: Duration);
@@ -127,12 +1306,285 @@ pub trait GameApp {
     /// Called anytime there is a new input even from the host.
     fn input(&mut self, event: &winit::event::WindowEvent) -> bool;
 
+    /// Called by the host each frame to build the game's UI, eg a tool window
+    /// with sliders for tweaking the game while it runs. Input used by the UI
+    /// is not sent to `input`.
+    fn build_ui(&mut self, _ctx: &egui::Context) {}
+
     /// Called by the host when the user's mouse moves.
     fn mouse_motion(&mut self, _delta_x: f64, _delta_y: f64) {}
 
     /// Called by the host when user moves the scroll wheel up or down.
     fn mouse_scroll_wheel(&mut self, _delta_x: f64, _delta_y: f64) {}
 
+    /// Called by the host when a gamepad button, stick or trigger changes.
+    /// Returns `true` if the event was used.
+    fn gamepad_input(&mut self, _event: &GamepadEvent) -> bool {
+        false
+    }
+
+    /// Called by the host when the user clicks, double clicks or drags with a
+    /// mouse button.
+    fn mouse_gesture(&mut self, _gesture: &MouseGesture) {}
+
+    /// Called by the host after the render window is resized. The renderer and
+    /// the scene's camera have already been updated to the new size.
+    fn window_resized(&mut self, _width: u32, _height: u32) {}
+
+    /// Called by the host when the render window gains or loses focus.
+    fn focus_changed(&mut self, _is_focused: bool) {}
+
+    /// Returns true if the game is animating and needs a new frame drawn even
+    /// when there is no input. Only used by `RenderLoopMode::OnDemand`.
+    fn wants_continuous_redraw(&self) -> bool {
+        false
+    }
+
+    /// Returns a second camera to draw the scene from, or `None` to only draw
+    /// it from the scene's camera. The render window is split in half when
+    /// there is a second camera, with the scene's camera drawing the left half.
+    fn split_screen_camera(&self) -> Option<&Camera> {
+        None
+    }
+
     /// Returns the render scene for the game app.
     fn render_scene(&self) -> &Scene;
+
+    /// Returns a mutable reference to the render scene for the game app.
+    fn render_scene_mut(&mut self) -> &mut Scene;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use glam::{Quat, Vec3};
+
+    use super::*;
+    use crate::{
+        renderer::{
+            materials::MaterialBuilder,
+            meshes::{builtin_mesh, BuiltinMesh},
+            models::Model,
+        },
+        test_support::{test_camera, test_renderer},
+    };
+
+    /// A game that counts how many times its content was loaded and how many
+    /// times it was simulated.
+    struct CountingGame {
+        loads: Rc<Cell<u32>>,
+        updates: Rc<Cell<u32>>,
+        scene: Scene,
+        /// Fill the window with a UI panel when true.
+        shows_panel: bool,
+    }
+
+    impl CountingGame {
+        fn new() -> (Self, Rc<Cell<u32>>, Rc<Cell<u32>>) {
+            let loads = Rc::new(Cell::new(0));
+            let updates = Rc::new(Cell::new(0));
+            let camera = test_camera(0, 0);
+
+            let game = Self {
+                loads: loads.clone(),
+                updates: updates.clone(),
+                scene: Scene::new(camera),
+                shows_panel: false,
+            };
+
+            (game, loads, updates)
+        }
+    }
+
+    impl GameApp for CountingGame {
+        fn load_content(
+            &mut self,
+            _context: &RenderContext,
+            _progress: Option<LoadProgressSink>,
+        ) -> anyhow::Result<()> {
+            self.loads.set(self.loads.get() + 1);
+            Ok(())
+        }
+
+        fn update_sim(&mut self, _delta: Duration, _input: &InputState) {
+            self.updates.set(self.updates.get() + 1);
+        }
+
+        fn prepare_render(&mut self, _renderer: &mut Renderer, _delta: Duration) {}
+
+        fn input(&mut self, _event: &winit::event::WindowEvent) -> bool {
+            false
+        }
+
+        fn build_ui(&mut self, ctx: &egui::Context) {
+            if self.shows_panel {
+                egui::CentralPanel::default().show(ctx, |ui| ui.label("panel"));
+            }
+        }
+
+        fn render_scene(&self) -> &Scene {
+            &self.scene
+        }
+
+        fn render_scene_mut(&mut self) -> &mut Scene {
+            &mut self.scene
+        }
+    }
+
+    #[test]
+    fn games_are_loaded_once_when_first_switched_to() {
+        let Some(renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let (first, first_loads, first_updates) = CountingGame::new();
+        let (second, second_loads, second_updates) = CountingGame::new();
+
+        let mut host = GameAppHost::new(renderer, Box::new(first));
+        let second_index = host.add_game(Box::new(second));
+        host.load_content(None).unwrap();
+
+        assert_eq!((1, 0), (first_loads.get(), second_loads.get()));
+
+        host.switch_to(second_index).unwrap();
+        host.update_sim(Duration::ZERO);
+        host.switch_to(0).unwrap();
+        host.switch_to(second_index).unwrap();
+
+        assert_eq!((1, 1), (first_loads.get(), second_loads.get()));
+        assert_eq!((0, 1), (first_updates.get(), second_updates.get()));
+        assert_eq!(second_index, host.active_game());
+
+        // The inactive game was sized to the window when it became active.
+        assert_eq!(32.0, host.game.render_scene().camera.viewport_height());
+
+        assert!(host.switch_to(7).is_err());
+        assert_eq!(second_index, host.active_game());
+    }
+
+    #[test]
+    fn games_keep_their_index_when_another_game_starts_active() {
+        let Some(renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let (first, first_loads, _) = CountingGame::new();
+        let (second, second_loads, _) = CountingGame::new();
+        let (third, third_loads, _) = CountingGame::new();
+
+        let mut host = GameAppHost::with_games(
+            renderer,
+            vec![Box::new(first), Box::new(second), Box::new(third)],
+            1,
+        );
+        host.load_content(None).unwrap();
+
+        assert_eq!((3, 1), (host.game_count(), host.active_game()));
+        assert_eq!(
+            (0, 1, 0),
+            (first_loads.get(), second_loads.get(), third_loads.get())
+        );
+
+        host.switch_to(0).unwrap();
+        host.switch_to(2).unwrap();
+        host.switch_to(1).unwrap();
+        assert_eq!(
+            (1, 1, 1),
+            (first_loads.get(), second_loads.get(), third_loads.get())
+        );
+    }
+
+    #[test]
+    fn loaded_games_keep_their_scene_after_the_device_is_lost() {
+        let Some(renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let (first, first_loads, _) = CountingGame::new();
+        let (second, second_loads, _) = CountingGame::new();
+        let (third, third_loads, _) = CountingGame::new();
+
+        let mut host = GameAppHost::new(renderer, Box::new(first));
+        let second_index = host.add_game(Box::new(second));
+        host.add_game(Box::new(third));
+        host.load_content(None).unwrap();
+        host.switch_to(second_index).unwrap();
+
+        let context = host.renderer().context().clone();
+        let material = MaterialBuilder::new().build(context.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            context.device(),
+            context.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+        let translation = Vec3::new(1.0, 2.0, 3.0);
+        let id = host.game.render_scene_mut().spawn_model(Model::new(
+            cube.clone(),
+            translation,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+
+        // Content is not loaded again, and the scene keeps its entities with
+        // their meshes recreated on the new device.
+        host.recover_lost_device().unwrap();
+        assert_eq!(
+            (1, 1, 0),
+            (first_loads.get(), second_loads.get(), third_loads.get())
+        );
+
+        let model = host.game.render_scene().model(id).unwrap();
+        assert!(!Rc::ptr_eq(&cube, model.mesh()));
+        assert_eq!(cube.triangles(), model.mesh().triangles());
+        assert_eq!(translation, model.translation());
+
+        host.render(Duration::ZERO);
+        assert!(host.renderer().read_pixels().is_ok());
+    }
+
+    #[test]
+    fn game_ui_is_drawn_over_the_scene() {
+        let Some(renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let (mut game, _, _) = CountingGame::new();
+        game.shows_panel = true;
+
+        let mut host = GameAppHost::new(renderer, Box::new(game));
+        host.load_content(None).unwrap();
+
+        // The empty scene is black, so any other color comes from the panel.
+        host.render(Duration::ZERO);
+        let pixels = host.renderer().read_pixels().unwrap();
+        assert_ne!([0, 0, 0, 255], pixels.get_pixel(60, 28).0);
+    }
+
+    #[test]
+    fn frame_stats_are_recorded_after_each_frame() {
+        let Some(renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let (game, _, _) = CountingGame::new();
+        let mut host = GameAppHost::new(renderer, Box::new(game));
+        host.load_content(None).unwrap();
+        assert!(host.frame_stats().latest().is_none());
+
+        host.set_show_frame_stats(true);
+        host.update_sim(Duration::ZERO);
+        host.render(Duration::ZERO);
+        host.render(Duration::ZERO);
+
+        let latest = host.frame_stats().latest().unwrap();
+        assert_eq!(0, latest.draw_calls);
+        assert_eq!(Duration::ZERO, latest.update_time);
+        assert!(latest.frame_time > Duration::ZERO);
+
+        // The overlay is yellow text over the black scene.
+        let pixels = host.renderer().read_pixels().unwrap();
+        assert!(pixels.pixels().any(|pixel| pixel.0 != [0, 0, 0, 255]));
+    }
 }
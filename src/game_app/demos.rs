@@ -0,0 +1,171 @@
+//! The demo scenes that can be run. Demos are switched to with the number keys
+//! in the order they are listed in `DEMOS`, and the demo that runs first can be
+//! picked with `--demo <name>` on the command line.
+use std::path::PathBuf;
+
+use tracing::warn;
+
+use super::{
+    instancing_demo::InstancingDemo, lighting_demo::LightingDemo, model_viewer::ModelViewer,
+    multi_cube_demo::MultiCubeDemo, triangle_demo::TriangleDemo, GameApp,
+};
+
+/// A demo scene that can be run by the host.
+pub struct Demo {
+    /// Name used to pick the demo on the command line, eg `--demo lighting`.
+    pub name: &'static str,
+    /// A short description of what the demo shows.
+    pub description: &'static str,
+    /// Create a new instance of the demo.
+    pub create: fn(&DemoArgs) -> Box<dyn GameApp>,
+}
+
+/// Every demo scene, with the demo that runs by default first.
+pub const DEMOS: &[Demo] = &[
+    Demo {
+        name: "multi-cube",
+        description: "Crates lit by point, spot and directional lights",
+        create: |_| Box::new(MultiCubeDemo::new()),
+    },
+    Demo {
+        name: Demo::MODEL_VIEWER,
+        description: "An obj model that can be orbited, picked with --model or drag and drop",
+        create: |args| Box::new(ModelViewer::new(args.model_path.clone())),
+    },
+    Demo {
+        name: "lighting",
+        description: "Moving colored point, spot and directional lights",
+        create: |_| Box::new(LightingDemo::new()),
+    },
+    Demo {
+        name: "instancing",
+        description: "Thousands of cubes drawn with instanced draw calls",
+        create: |_| Box::new(InstancingDemo::new()),
+    },
+    Demo {
+        name: "triangle",
+        description: "A single unlit spinning triangle",
+        create: |_| Box::new(TriangleDemo::new()),
+    },
+];
+
+impl Demo {
+    const MODEL_VIEWER: &'static str = "model-viewer";
+}
+
+/// Demo options passed on the command line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DemoArgs {
+    /// Name of the demo to run first, from `--demo <name>`.
+    pub demo: Option<String>,
+    /// Model shown by the model viewer, from `--model <path>`.
+    pub model_path: Option<PathBuf>,
+}
+
+impl DemoArgs {
+    /// Read the demo options from `args`, which should not include the program
+    /// name. Options are given as either `--name value` or `--name=value`, and
+    /// unknown arguments are ignored.
+    #[cfg(any(not(target_arch = "wasm32"), test))]
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut demo_args = Self::default();
+
+        while let Some(arg) = args.next() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match name.as_str() {
+                "--demo" => demo_args.demo = value.or_else(|| args.next()),
+                "--model" => {
+                    demo_args.model_path = value.or_else(|| args.next()).map(PathBuf::from)
+                }
+                _ => {}
+            }
+        }
+
+        demo_args
+    }
+
+    /// Get the index in `DEMOS` of the demo to run first. This is the demo
+    /// named by `demo`, or the model viewer when only a model was given.
+    /// Otherwise the first demo is run.
+    pub fn first_demo(&self) -> usize {
+        let name = match (&self.demo, &self.model_path) {
+            (Some(name), _) => name.as_str(),
+            (None, Some(_)) => Demo::MODEL_VIEWER,
+            (None, None) => return 0,
+        };
+
+        find_demo(name).unwrap_or_else(|| {
+            let names: Vec<_> = DEMOS.iter().map(|demo| demo.name).collect();
+            warn!("there is no demo called {name:?}, expected one of {names:?}");
+            0
+        })
+    }
+}
+
+/// Get the index of the demo called `name` in `DEMOS`. Names are not case
+/// sensitive.
+pub fn find_demo(name: &str) -> Option<usize> {
+    DEMOS
+        .iter()
+        .position(|demo| demo.name.eq_ignore_ascii_case(name))
+}
+
+/// Create every demo in `DEMOS`.
+pub fn create_demos(args: &DemoArgs) -> Vec<Box<dyn GameApp>> {
+    DEMOS.iter().map(|demo| (demo.create)(args)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> DemoArgs {
+        DemoArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn demos_are_found_by_unique_name() {
+        for (index, demo) in DEMOS.iter().enumerate() {
+            assert_eq!(Some(index), find_demo(demo.name));
+        }
+
+        assert_eq!(Some(2), find_demo("Lighting"));
+        assert_eq!(None, find_demo("missing"));
+    }
+
+    #[test]
+    fn demo_args_are_read_from_either_flag_form() {
+        assert_eq!(
+            Some("lighting"),
+            parse(&["--demo", "lighting"]).demo.as_deref()
+        );
+        assert_eq!(
+            Some("instancing"),
+            parse(&["-v", "--demo=instancing"]).demo.as_deref()
+        );
+        assert_eq!(
+            Some(PathBuf::from("models/teapot.obj")),
+            parse(&["--model=models/teapot.obj"]).model_path
+        );
+        assert_eq!(DemoArgs::default(), parse(&["--demo"]));
+        assert_eq!(DemoArgs::default(), parse(&["lighting"]));
+    }
+
+    #[test]
+    fn model_viewer_runs_first_when_only_a_model_is_given() {
+        assert_eq!(0, parse(&[]).first_demo());
+        assert_eq!(
+            find_demo(Demo::MODEL_VIEWER),
+            Some(parse(&["--model", "teapot.obj"]).first_demo())
+        );
+        assert_eq!(
+            find_demo("triangle"),
+            Some(parse(&["--model", "teapot.obj", "--demo", "triangle"]).first_demo())
+        );
+        assert_eq!(0, parse(&["--demo", "missing"]).first_demo());
+    }
+}
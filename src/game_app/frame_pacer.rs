@@ -0,0 +1,293 @@
+//! Limits how often frames are drawn and measures the frame rate that was
+//! actually achieved.
+use std::time::Duration;
+
+use crate::platform::SystemTime;
+
+/// How much of the measured frame rate comes from the most recent frame. Lower
+/// values smooth out the measurement more.
+const FPS_SMOOTHING: f32 = 0.1;
+
+/// Native sleeps are only accurate to about a millisecond, so the last part of
+/// the wait before a frame is spent spinning instead.
+#[cfg(not(target_arch = "wasm32"))]
+const SPIN_DURATION: Duration = Duration::from_millis(1);
+
+/// Browsers draw at the display's refresh rate and the timer is only accurate
+/// to a millisecond, so a frame that arrives slightly early is drawn rather
+/// than waiting an entire refresh for the next one.
+#[cfg(target_arch = "wasm32")]
+const EARLY_FRAME_TOLERANCE: Duration = Duration::from_millis(2);
+
+/// How the native render loop waits for the next frame when the frame rate is
+/// capped. Web frames are always paced by the browser.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameWaitStrategy {
+    /// Sleep until the frame is due. Uses the least power, but frames can start
+    /// late by as much as the operating system's timer resolution.
+    Sleep,
+    /// Keep processing events without sleeping until the frame is due. Frames
+    /// start on time at the cost of keeping a CPU core busy.
+    Spin,
+    /// Sleep until shortly before the frame is due, then spin for the rest.
+    #[default]
+    SleepThenSpin,
+}
+
+/// What the event loop should do while waiting for the next frame, see
+/// `FramePacer::event_loop_wait`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventLoopWait {
+    /// The next frame is due, so it should be drawn now.
+    Redraw,
+    /// Keep processing events without sleeping.
+    Poll,
+    /// Sleep for up to this long unless an event arrives.
+    Sleep(Duration),
+}
+
+/// Paces the render loop to an optional target frame rate.
+///
+/// Natively the event loop waits until the next frame is due using the pacer's
+/// `FrameWaitStrategy`. On the web frames are paced by the browser's
+/// `requestAnimationFrame` callback, so frames that arrive before the next
+/// frame is due are skipped instead.
+#[derive(Debug, Default)]
+pub struct FramePacer {
+    target_fps: Option<u32>,
+    wait_strategy: FrameWaitStrategy,
+    /// When the next frame should start if the frame rate is capped.
+    next_frame: Option<SystemTime>,
+    /// When the previous frame started.
+    last_frame: Option<SystemTime>,
+    /// Smoothed time between the start of recent frames.
+    average_frame_time: Option<Duration>,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get the frame rate the pacer is limiting frames to, or `None` if frames
+    /// are not limited.
+    pub fn target_fps(&self) -> Option<u32> {
+        self.target_fps
+    }
+
+    /// Limit frames to `target_fps` frames per second, or remove the limit
+    /// when `target_fps` is `None` or zero.
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) {
+        self.target_fps = target_fps.filter(|fps| *fps > 0);
+        self.next_frame = None;
+    }
+
+    /// Get how the native render loop waits for the next frame.
+    pub fn wait_strategy(&self) -> FrameWaitStrategy {
+        self.wait_strategy
+    }
+
+    /// Set how the native render loop waits for the next frame.
+    pub fn set_wait_strategy(&mut self, wait_strategy: FrameWaitStrategy) {
+        self.wait_strategy = wait_strategy;
+    }
+
+    /// Get the time between frames needed to hit the target frame rate.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        self.target_fps
+            .map(|fps| Duration::from_secs_f64(1.0 / fps as f64))
+    }
+
+    /// Get the frame rate measured over recent frames, or zero if not enough
+    /// frames have been drawn yet.
+    pub fn measured_fps(&self) -> f32 {
+        match self.average_frame_time {
+            Some(frame_time) if !frame_time.is_zero() => 1.0 / frame_time.as_secs_f32(),
+            _ => 0.0,
+        }
+    }
+
+    /// Get how long to wait at `now` before the next frame should start.
+    pub fn time_until_next_frame(&self, now: SystemTime) -> Duration {
+        match self.next_frame {
+            Some(next_frame) if next_frame > now => next_frame - now,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Record that a frame started at `now`, and schedule when the next frame
+    /// should start.
+    pub fn frame_started(&mut self, now: SystemTime) {
+        if let Some(last_frame) = self.last_frame {
+            let frame_time = now - last_frame;
+
+            self.average_frame_time = Some(match self.average_frame_time {
+                Some(average) => {
+                    average.mul_f32(1.0 - FPS_SMOOTHING) + frame_time.mul_f32(FPS_SMOOTHING)
+                }
+                None => frame_time,
+            });
+        }
+
+        self.last_frame = Some(now);
+
+        // Schedule frames from the previous deadline rather than from `now` so
+        // small delays do not add up and lower the frame rate. A frame that is
+        // more than an entire interval late starts a new schedule instead of
+        // rushing to catch up.
+        self.next_frame = self.frame_interval().map(|interval| match self.next_frame {
+            Some(next_frame) if now - next_frame < interval => next_frame + interval,
+            _ => now + interval,
+        });
+    }
+
+    /// Returns true if the next frame should be drawn at `now`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn is_frame_due(&self, now: SystemTime) -> bool {
+        self.time_until_next_frame(now) <= EARLY_FRAME_TOLERANCE
+    }
+
+    /// Get what the event loop should do at `now` while waiting for the next
+    /// frame, following the wait strategy.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn event_loop_wait(&self, now: SystemTime) -> EventLoopWait {
+        let remaining = self.time_until_next_frame(now);
+
+        match self.wait_strategy {
+            _ if remaining.is_zero() => EventLoopWait::Redraw,
+            FrameWaitStrategy::Sleep => EventLoopWait::Sleep(remaining),
+            FrameWaitStrategy::Spin => EventLoopWait::Poll,
+            FrameWaitStrategy::SleepThenSpin if remaining > SPIN_DURATION => {
+                EventLoopWait::Sleep(remaining - SPIN_DURATION)
+            }
+            FrameWaitStrategy::SleepThenSpin => EventLoopWait::Poll,
+        }
+    }
+
+    /// Block the current thread until the next frame should start, following
+    /// the wait strategy. Used when a frame is drawn before it is due, eg when
+    /// input asks for a redraw.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait_for_next_frame(&self) {
+        let Some(next_frame) = self.next_frame else {
+            return;
+        };
+
+        let remaining = self.time_until_next_frame(SystemTime::now());
+
+        match self.wait_strategy {
+            FrameWaitStrategy::Sleep => std::thread::sleep(remaining),
+            FrameWaitStrategy::Spin => {}
+            FrameWaitStrategy::SleepThenSpin if remaining > SPIN_DURATION => {
+                std::thread::sleep(remaining - SPIN_DURATION)
+            }
+            FrameWaitStrategy::SleepThenSpin => {}
+        }
+
+        while SystemTime::now() < next_frame {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_TIME: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn uncapped_frames_never_wait() {
+        let mut pacer = FramePacer::new();
+        let start = SystemTime::now();
+
+        pacer.frame_started(start);
+        assert_eq!(Duration::ZERO, pacer.time_until_next_frame(start));
+
+        // A zero frame rate cap is the same as no cap.
+        pacer.set_target_fps(Some(0));
+        pacer.frame_started(start + FRAME_TIME);
+        assert_eq!(None, pacer.target_fps());
+        assert_eq!(
+            Duration::ZERO,
+            pacer.time_until_next_frame(start + FRAME_TIME)
+        );
+    }
+
+    #[test]
+    fn capped_frames_are_scheduled_from_the_previous_deadline() {
+        let mut pacer = FramePacer::new();
+        pacer.set_target_fps(Some(50));
+        let start = SystemTime::now();
+
+        pacer.frame_started(start);
+        assert_eq!(FRAME_TIME, pacer.time_until_next_frame(start));
+
+        // A slightly late frame does not push back the frame after it.
+        pacer.frame_started(start + Duration::from_millis(22));
+        assert_eq!(
+            Duration::from_millis(18),
+            pacer.time_until_next_frame(start + Duration::from_millis(22))
+        );
+
+        // A very late frame starts a new schedule.
+        let late = start + Duration::from_millis(200);
+        pacer.frame_started(late);
+        assert_eq!(FRAME_TIME, pacer.time_until_next_frame(late));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn event_loop_waits_follow_the_wait_strategy() {
+        let mut pacer = FramePacer::new();
+        let start = SystemTime::now();
+        let almost_due = start + (FRAME_TIME - SPIN_DURATION / 2);
+
+        // Uncapped frames are always due.
+        pacer.frame_started(start);
+        assert_eq!(EventLoopWait::Redraw, pacer.event_loop_wait(start));
+
+        pacer.set_target_fps(Some(50));
+        pacer.frame_started(start);
+        assert_eq!(
+            EventLoopWait::Sleep(FRAME_TIME - SPIN_DURATION),
+            pacer.event_loop_wait(start)
+        );
+        assert_eq!(EventLoopWait::Poll, pacer.event_loop_wait(almost_due));
+
+        pacer.set_wait_strategy(FrameWaitStrategy::Sleep);
+        assert_eq!(
+            EventLoopWait::Sleep(FRAME_TIME),
+            pacer.event_loop_wait(start)
+        );
+
+        pacer.set_wait_strategy(FrameWaitStrategy::Spin);
+        assert_eq!(EventLoopWait::Poll, pacer.event_loop_wait(start));
+
+        for strategy in [
+            FrameWaitStrategy::Sleep,
+            FrameWaitStrategy::Spin,
+            FrameWaitStrategy::SleepThenSpin,
+        ] {
+            pacer.set_wait_strategy(strategy);
+            assert_eq!(
+                EventLoopWait::Redraw,
+                pacer.event_loop_wait(start + FRAME_TIME)
+            );
+        }
+    }
+
+    #[test]
+    fn measured_fps_follows_frame_times() {
+        let mut pacer = FramePacer::new();
+        let start = SystemTime::now();
+        assert_eq!(0.0, pacer.measured_fps());
+
+        for frame in 0..10 {
+            pacer.frame_started(start + FRAME_TIME * frame);
+        }
+
+        assert!((pacer.measured_fps() - 50.0).abs() < 0.01);
+    }
+}
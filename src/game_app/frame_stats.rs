@@ -0,0 +1,191 @@
+//! Collects how long each part of a frame took and how much the renderer drew,
+//! averaged over recent frames.
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use glam::{Vec2, Vec4};
+use tracing::info;
+
+use crate::renderer::text::TextRenderer;
+
+/// Timings and render counts measured for one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameSample {
+    /// Time since the previous frame started.
+    pub frame_time: Duration,
+    /// Time spent advancing the game's simulation.
+    pub update_time: Duration,
+    /// Time the game spent preparing to render.
+    pub prepare_render_time: Duration,
+    /// GPU time spent in the timed render passes, which is zero when the
+    /// adapter does not support timestamp queries.
+    pub gpu_time: Duration,
+    /// Number of draw calls issued for the scene's models.
+    pub draw_calls: usize,
+    /// Number of triangles drawn for the scene's models.
+    pub triangles: usize,
+    /// Bytes of shader values and instances copied to the GPU.
+    pub uploaded_bytes: u64,
+}
+
+/// Keeps the samples of the most recent frames so they can be averaged, which
+/// smooths out single slow frames.
+///
+/// The averages can be written to the log with `log_summary`, or drawn over
+/// the frame with `draw_overlay`.
+#[derive(Debug)]
+pub struct FrameStats {
+    /// Samples of recent frames, with the newest sample at the back.
+    samples: VecDeque<FrameSample>,
+    /// Maximum number of samples kept.
+    window: usize,
+}
+
+impl FrameStats {
+    /// Number of frames averaged together by default.
+    pub const DEFAULT_WINDOW: usize = 60;
+    /// Height in pixels of the lines drawn by `draw_overlay`.
+    const OVERLAY_TEXT_SIZE: f32 = 16.0;
+    /// Distance in pixels between the overlay and the edges of the screen.
+    const OVERLAY_MARGIN: f32 = 8.0;
+    const OVERLAY_COLOR: Vec4 = Vec4::new(1.0, 1.0, 0.0, 1.0);
+
+    /// Create frame stats that average the last `window` frames.
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            window: window.max(1),
+        }
+    }
+
+    /// Add the sample for the frame that just finished, dropping the oldest
+    /// sample once the window is full.
+    pub fn record(&mut self, sample: FrameSample) {
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(sample);
+    }
+
+    /// Get the sample of the most recent frame, or `None` if no frames have
+    /// been recorded.
+    #[allow(dead_code)]
+    pub fn latest(&self) -> Option<&FrameSample> {
+        self.samples.back()
+    }
+
+    /// Get the average of every sample in the window, or an empty sample if no
+    /// frames have been recorded.
+    pub fn average(&self) -> FrameSample {
+        let count = self.samples.len().max(1);
+        let sum = self
+            .samples
+            .iter()
+            .fold(FrameSample::default(), |sum, sample| FrameSample {
+                frame_time: sum.frame_time + sample.frame_time,
+                update_time: sum.update_time + sample.update_time,
+                prepare_render_time: sum.prepare_render_time + sample.prepare_render_time,
+                gpu_time: sum.gpu_time + sample.gpu_time,
+                draw_calls: sum.draw_calls + sample.draw_calls,
+                triangles: sum.triangles + sample.triangles,
+                uploaded_bytes: sum.uploaded_bytes + sample.uploaded_bytes,
+            });
+
+        FrameSample {
+            frame_time: sum.frame_time / count as u32,
+            update_time: sum.update_time / count as u32,
+            prepare_render_time: sum.prepare_render_time / count as u32,
+            gpu_time: sum.gpu_time / count as u32,
+            draw_calls: sum.draw_calls / count,
+            triangles: sum.triangles / count,
+            uploaded_bytes: sum.uploaded_bytes / count as u64,
+        }
+    }
+
+    /// Write the averages to the log.
+    pub fn log_summary(&self) {
+        info!("frame stats:\n{self}");
+    }
+
+    /// Draw the averages in the top right corner of a render target that is
+    /// `target_width` pixels wide.
+    pub fn draw_overlay(&self, text: &mut TextRenderer, target_width: u32) {
+        let summary = self.to_string();
+        let size = TextRenderer::measure_text(&summary, Self::OVERLAY_TEXT_SIZE);
+
+        text.draw_text(
+            Vec2::new(
+                target_width as f32 - size.x - Self::OVERLAY_MARGIN,
+                Self::OVERLAY_MARGIN,
+            ),
+            &summary,
+            Self::OVERLAY_TEXT_SIZE,
+            Self::OVERLAY_COLOR,
+        );
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let average = self.average();
+        let as_millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let fps = match average.frame_time.as_secs_f64() {
+            secs if secs > 0.0 => 1.0 / secs,
+            _ => 0.0,
+        };
+
+        writeln!(
+            f,
+            "frame   {:6.2} ms ({fps:.0} fps)",
+            as_millis(average.frame_time)
+        )?;
+        writeln!(f, "update  {:6.2} ms", as_millis(average.update_time))?;
+        writeln!(
+            f,
+            "prepare {:6.2} ms",
+            as_millis(average.prepare_render_time)
+        )?;
+        writeln!(f, "gpu     {:6.2} ms", as_millis(average.gpu_time))?;
+        writeln!(f, "draws   {}", average.draw_calls)?;
+        writeln!(f, "tris    {}", average.triangles)?;
+        write!(
+            f,
+            "upload  {:.1} KiB",
+            average.uploaded_bytes as f64 / 1024.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_averaged_over_the_window() {
+        let mut stats = FrameStats::new(2);
+        assert_eq!(FrameSample::default(), stats.average());
+
+        for millis in [100, 10, 20] {
+            stats.record(FrameSample {
+                frame_time: Duration::from_millis(millis),
+                gpu_time: Duration::from_millis(millis / 10),
+                draw_calls: millis as usize,
+                ..Default::default()
+            });
+        }
+
+        // The first sample no longer fits in the window.
+        let average = stats.average();
+        assert_eq!(Duration::from_millis(15), average.frame_time);
+        assert_eq!(Duration::from_micros(1500), average.gpu_time);
+        assert_eq!(15, average.draw_calls);
+        assert_eq!(20, stats.latest().unwrap().draw_calls);
+        assert!(stats.to_string().starts_with("frame    15.00 ms (67 fps)"));
+    }
+}
@@ -0,0 +1,251 @@
+//! A snapshot of the keyboard and mouse that games can poll each update rather
+//! than tracking input events themselves.
+use std::collections::HashSet;
+
+use glam::Vec2;
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+/// The keys and mouse buttons held down, and how the mouse moved, as of the
+/// current update.
+///
+/// Presses, releases and mouse movement are accumulated from the events seen
+/// since the previous update, so a key that was pressed and released between
+/// two updates is still reported as pressed and released.
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    modifiers: ModifiersState,
+    /// Cursor position in physical pixels relative to the top left of the
+    /// window, or `None` if the cursor is outside of the window.
+    cursor_position: Option<Vec2>,
+    mouse_delta: Vec2,
+    scroll_delta: Vec2,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns true if `key` is held down.
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Returns true if `key` was pressed since the previous update. Keys that
+    /// repeat while held are not pressed again.
+    pub fn was_key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Returns true if `key` was released since the previous update.
+    pub fn was_key_released(&self, key: KeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    /// Get every key that is held down.
+    pub fn keys_down(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.keys_down.iter().copied()
+    }
+
+    /// Returns true if `button` is held down.
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    /// Returns true if `button` was pressed since the previous update.
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    /// Returns true if `button` was released since the previous update.
+    #[allow(dead_code)]
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Get the modifier keys (shift, control, alt and super) that are held.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Get the cursor position in physical pixels relative to the top left of
+    /// the window, or `None` if the cursor is outside of the window.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// Get how far the mouse moved since the previous update. This is raw
+    /// mouse motion, so it keeps changing while the cursor is captured.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    /// Get how far the scroll wheel moved since the previous update.
+    pub fn scroll_delta(&self) -> Vec2 {
+        self.scroll_delta
+    }
+
+    /// Update the state with a window event.
+    pub fn process_input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    update_held(
+                        key,
+                        event.state,
+                        &mut self.keys_down,
+                        &mut self.keys_pressed,
+                        &mut self.keys_released,
+                    );
+                }
+            }
+            WindowEvent::MouseInput { button, state, .. } => update_held(
+                *button,
+                *state,
+                &mut self.buttons_down,
+                &mut self.buttons_pressed,
+                &mut self.buttons_released,
+            ),
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(Vec2::new(position.x as f32, position.y as f32));
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            _ => {}
+        }
+    }
+
+    /// Update the state with a window event that was used by the UI. Only
+    /// releases are applied so that keys and buttons released over the UI do
+    /// not stay held.
+    pub fn process_ui_input(&mut self, event: &WindowEvent) {
+        let is_release = match event {
+            WindowEvent::KeyboardInput { event, .. } => event.state == ElementState::Released,
+            WindowEvent::MouseInput { state, .. } => *state == ElementState::Released,
+            WindowEvent::ModifiersChanged(_)
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::CursorLeft { .. } => true,
+            _ => false,
+        };
+
+        if is_release {
+            self.process_input(event);
+        }
+    }
+
+    /// Accumulate raw mouse motion until the next update.
+    pub fn process_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.mouse_delta += Vec2::new(delta_x as f32, delta_y as f32);
+    }
+
+    /// Accumulate scroll wheel movement until the next update.
+    pub fn process_mouse_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.scroll_delta += Vec2::new(delta_x as f32, delta_y as f32);
+    }
+
+    /// Forget the presses, releases and mouse movement seen since the previous
+    /// update. Call this after each update.
+    pub fn end_update(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.scroll_delta = Vec2::ZERO;
+    }
+
+    /// Forget every held key and button without reporting them as released,
+    /// eg when the window loses focus and will not see them being released.
+    pub fn release_all(&mut self) {
+        self.keys_down.clear();
+        self.keys_pressed.clear();
+        self.buttons_down.clear();
+        self.buttons_pressed.clear();
+        self.modifiers = ModifiersState::empty();
+    }
+}
+
+/// Mark `input` as held or no longer held in `down`, recording the change in
+/// `pressed` or `released`.
+fn update_held<T: Copy + Eq + std::hash::Hash>(
+    input: T,
+    state: ElementState,
+    down: &mut HashSet<T>,
+    pressed: &mut HashSet<T>,
+    released: &mut HashSet<T>,
+) {
+    match state {
+        ElementState::Pressed => {
+            if down.insert(input) {
+                pressed.insert(input);
+            }
+        }
+        ElementState::Released => {
+            if down.remove(&input) {
+                released.insert(input);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_and_motion_last_until_the_end_of_the_update() {
+        let mut input = InputState::new();
+        let (w, left) = (KeyCode::KeyW, MouseButton::Left);
+
+        update_held(
+            w,
+            ElementState::Pressed,
+            &mut input.keys_down,
+            &mut input.keys_pressed,
+            &mut input.keys_released,
+        );
+        update_held(
+            left,
+            ElementState::Pressed,
+            &mut input.buttons_down,
+            &mut input.buttons_pressed,
+            &mut input.buttons_released,
+        );
+        update_held(
+            left,
+            ElementState::Released,
+            &mut input.buttons_down,
+            &mut input.buttons_pressed,
+            &mut input.buttons_released,
+        );
+        input.process_mouse_motion(3.0, -1.0);
+        input.process_mouse_motion(1.0, -1.0);
+        input.process_mouse_wheel(0.0, 2.0);
+
+        assert!(input.is_key_down(w) && input.was_key_pressed(w));
+        assert!(!input.is_button_down(left));
+        assert!(input.was_button_pressed(left) && input.was_button_released(left));
+        assert_eq!(Vec2::new(4.0, -2.0), input.mouse_delta());
+        assert_eq!(Vec2::new(0.0, 2.0), input.scroll_delta());
+
+        // Held keys stay held across updates.
+        input.end_update();
+        assert!(input.is_key_down(w) && !input.was_key_pressed(w));
+        assert!(!input.was_button_pressed(left) && !input.was_button_released(left));
+        assert_eq!(Vec2::ZERO, input.mouse_delta());
+        assert_eq!(Vec2::ZERO, input.scroll_delta());
+
+        input.release_all();
+        assert!(!input.is_key_down(w) && !input.was_key_released(w));
+        assert_eq!(0, input.keys_down().count());
+    }
+}
@@ -0,0 +1,219 @@
+use std::rc::Rc;
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    camera::Camera,
+    content::{ContentManager, LoadProgressSink},
+    gameplay::{gamepad::GamepadEvent, ArcballCameraController, CameraController},
+    renderer::{
+        lighting::DirectionalLight,
+        materials::MaterialBuilder,
+        meshes::{builtin_mesh, BuiltinMesh},
+        models::Mesh,
+        recreated_resources::RecreatedResources,
+        render_context::RenderContext,
+        scene::{EntityId, Scene},
+        Renderer,
+    },
+};
+
+use super::{input_state::InputState, GameApp};
+
+/// A stress test that fills the scene with a large grid of spinning cubes.
+/// Every cube shares one mesh, so the renderer draws them all with a single
+/// instanced draw call. Press F3 to see the frame time and draw call count.
+pub struct InstancingDemo {
+    arcball: ArcballCameraController,
+    sim_time_elapsed: std::time::Duration,
+    scene: Scene,
+    /// Mesh shared by every cube, or `None` before content is loaded.
+    cube_mesh: Option<Rc<Mesh>>,
+    /// The spawned cubes, one row at a time.
+    cubes: Vec<EntityId>,
+    /// Number of cubes along each side of the grid.
+    grid_size: usize,
+    /// Grid size picked in the UI, which replaces the cubes on the next frame
+    /// when it differs from `grid_size`.
+    requested_grid_size: usize,
+    /// Cubes spin around their own axis when true.
+    spinning: bool,
+}
+
+impl InstancingDemo {
+    const CAMERA_POS: Vec3 = Vec3::new(0.0, 60.0, 90.0);
+    const DEFAULT_GRID_SIZE: usize = 100;
+    const MAX_GRID_SIZE: usize = 300;
+    /// Distance between the centers of neighboring cubes.
+    const CUBE_SPACING: f32 = 1.5;
+    const DEGREES_PER_SECOND: f32 = 90.0;
+
+    const SUN: DirectionalLight = DirectionalLight {
+        direction: Vec3::new(-0.3, -1.0, -0.5),
+        color: Vec3::ONE,
+        intensity: 1.0,
+        ambient: 0.1,
+        specular: 0.5,
+    };
+
+    pub fn new() -> Self {
+        // The viewport size is set by the host once the render window size is
+        // known.
+        let camera = Camera::new(
+            Self::CAMERA_POS,
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            500.0,
+            0,
+            0,
+        );
+
+        let mut arcball = ArcballCameraController::new();
+        arcball
+            .set_distance_range(1.0, None)
+            .expect("zoom distance range is valid");
+
+        Self {
+            arcball,
+            sim_time_elapsed: Default::default(),
+            scene: Scene::new(camera),
+            cube_mesh: None,
+            cubes: Vec::new(),
+            grid_size: Self::DEFAULT_GRID_SIZE,
+            requested_grid_size: Self::DEFAULT_GRID_SIZE,
+            spinning: true,
+        }
+    }
+
+    /// Spawn a `grid_size` by `grid_size` grid of cubes centered on the origin.
+    fn spawn_cubes(&mut self, renderer: &mut Renderer) {
+        let Some(cube_mesh) = &self.cube_mesh else {
+            return;
+        };
+
+        for mut cube in renderer.create_model_grid(
+            cube_mesh.clone(),
+            self.grid_size,
+            self.grid_size,
+            Self::CUBE_SPACING,
+        ) {
+            cube.set_scale(Vec3::splat(0.5));
+            self.cubes.push(self.scene.spawn_model(cube));
+        }
+    }
+
+    /// Remove every cube from the scene and free its shader values.
+    fn despawn_cubes(&mut self, renderer: &mut Renderer) {
+        for id in self.cubes.drain(..) {
+            if let Some(model) = self.scene.despawn(id).and_then(|entity| entity.model) {
+                renderer.destroy_model(model);
+            }
+        }
+    }
+}
+
+impl GameApp for InstancingDemo {
+    fn load_content(
+        &mut self,
+        context: &RenderContext,
+        progress: Option<LoadProgressSink>,
+    ) -> anyhow::Result<()> {
+        let content = ContentManager::new(context.clone()).with_progress_sink(progress);
+
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::new(0.3, 0.6, 0.9))
+            .specular_color(Vec3::ONE)
+            .specular_power(32.0)
+            .build(content.default_textures());
+
+        self.cube_mesh = Some(Rc::new(builtin_mesh(
+            context.device(),
+            context.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        )));
+
+        // Cubes are spawned by the first `prepare_render`, which has the
+        // renderer that creates them.
+        self.cubes.clear();
+        self.scene.spawn_light(Self::SUN);
+
+        Ok(())
+    }
+
+    fn recreate_gpu_resources(&mut self, resources: &mut RecreatedResources) -> anyhow::Result<()> {
+        // Cubes spawned later keep sharing the mesh with the recreated cubes.
+        self.cube_mesh = self.cube_mesh.as_ref().map(|mesh| resources.mesh(mesh));
+        self.scene.recreate_gpu_resources(resources);
+
+        Ok(())
+    }
+
+    fn input(&mut self, event: &winit::event::WindowEvent) -> bool {
+        self.arcball.process_input(event)
+    }
+
+    fn gamepad_input(&mut self, event: &GamepadEvent) -> bool {
+        self.arcball.process_gamepad(event)
+    }
+
+    fn build_ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Instancing").show(ctx, |ui| {
+            ui.add(
+                egui::Slider::new(&mut self.requested_grid_size, 1..=Self::MAX_GRID_SIZE)
+                    .text("Grid size"),
+            );
+            ui.label(format!("{} cubes", self.cubes.len()));
+            ui.checkbox(&mut self.spinning, "Spin cubes");
+        });
+    }
+
+    fn update_sim(&mut self, delta: std::time::Duration, _input: &InputState) {
+        if self.spinning {
+            self.sim_time_elapsed += delta;
+        }
+    }
+
+    fn prepare_render(&mut self, renderer: &mut Renderer, delta: std::time::Duration) {
+        if self.cubes.is_empty() || self.requested_grid_size != self.grid_size {
+            self.despawn_cubes(renderer);
+            self.grid_size = self.requested_grid_size;
+            self.spawn_cubes(renderer);
+        }
+
+        self.arcball.update_camera(&mut self.scene.camera, delta);
+
+        // Offset each row's angle so a wave rolls across the grid.
+        let angle = (self.sim_time_elapsed.as_secs_f32() * Self::DEGREES_PER_SECOND).to_radians();
+
+        for (index, id) in self.cubes.iter().enumerate() {
+            let row = (index / self.grid_size.max(1)) as f32;
+
+            if let Some(cube) = self.scene.entity_mut(*id) {
+                cube.transform.rotation = Quat::from_rotation_y(angle + row * 0.1);
+            }
+        }
+    }
+
+    fn mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.arcball.process_mouse_motion(delta_x, delta_y);
+    }
+
+    fn mouse_scroll_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.arcball.process_mouse_wheel(delta_x, delta_y);
+    }
+
+    fn wants_continuous_redraw(&self) -> bool {
+        self.spinning
+    }
+
+    fn render_scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    fn render_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
@@ -0,0 +1,361 @@
+use std::rc::Rc;
+
+use glam::{Quat, Vec2, Vec3};
+
+use crate::{
+    camera::Camera,
+    content::{ContentManager, LoadProgressSink},
+    gameplay::{gamepad::GamepadEvent, ArcballCameraController, CameraController},
+    renderer::{
+        billboards::Billboard,
+        lighting::{sun_light, LightAttenuation, PointLight, SpotLight, DEFAULT_LIGHT_RANGE},
+        materials::MaterialBuilder,
+        meshes::{builtin_mesh, BuiltinMesh},
+        models::Model,
+        render_context::RenderContext,
+        scene::{Entity, EntityId, Light, Scene, Transform},
+        textures::{self, ColorSpace, Texture},
+        Renderer,
+    },
+};
+
+use super::{input_state::InputState, GameApp};
+
+/// A demo scene showing each kind of light. Colored point lights circle a
+/// ring of pillars, a spot light sweeps across the floor and the sun rises
+/// and sets overhead.
+pub struct LightingDemo {
+    arcball: ArcballCameraController,
+    sim_time_elapsed: std::time::Duration,
+    scene: Scene,
+    /// Point lights that circle the pillars, one per `POINT_LIGHT_COLORS`.
+    point_lights: Vec<EntityId>,
+    /// Spot light that sweeps back and forth across the floor.
+    spot_light: EntityId,
+    /// Directional light that follows the sun through the day.
+    sun: EntityId,
+    /// Time of day passed to `sun_light`.
+    time_of_day: f32,
+    /// Advance the time of day when true.
+    sun_moving: bool,
+}
+
+impl LightingDemo {
+    const CAMERA_POS: Vec3 = Vec3::new(0.0, 6.0, 12.0);
+    const CAMERA_LOOK_AT: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+
+    const POINT_LIGHT_COLORS: &'static [Vec3] = &[
+        Vec3::new(1.0, 0.2, 0.2),
+        Vec3::new(0.2, 1.0, 0.2),
+        Vec3::new(0.2, 0.4, 1.0),
+    ];
+    /// Distance of the orbiting point lights from the center of the scene.
+    const POINT_LIGHT_ORBIT_RADIUS: f32 = 3.0;
+    const POINT_LIGHT_HEIGHT: f32 = 1.5;
+    /// How fast the point lights circle the center of the scene.
+    const POINT_LIGHT_DEGREES_PER_SECOND: f32 = 30.0;
+    /// Width and height of the glow drawn at each point light.
+    const POINT_LIGHT_GLOW_SIZE: f32 = 0.6;
+
+    const SPOT_LIGHT_POS: Vec3 = Vec3::new(0.0, 6.0, 0.0);
+    /// How far the spot light sweeps to either side of straight down.
+    const SPOT_LIGHT_SWEEP_DEGREES: f32 = 35.0;
+    const SPOT_LIGHT_SWEEP_SECONDS: f32 = 6.0;
+
+    /// Time taken for the sun to rise and set once.
+    const DAY_LENGTH: std::time::Duration = std::time::Duration::from_secs(60);
+    /// The demo starts in the morning so the scene is lit right away.
+    const INITIAL_TIME_OF_DAY: f32 = 0.3;
+    const LATITUDE_DEGREES: f32 = 30.0;
+
+    const PILLAR_COUNT: usize = 8;
+    const PILLAR_RING_RADIUS: f32 = 5.0;
+    const FLOOR_SIZE: f32 = 16.0;
+
+    pub fn new() -> Self {
+        // The viewport size is set by the host once the render window size is
+        // known.
+        let camera = Camera::new(
+            Self::CAMERA_POS,
+            Self::CAMERA_LOOK_AT,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            0,
+            0,
+        );
+
+        Self {
+            arcball: ArcballCameraController::new(),
+            sim_time_elapsed: Default::default(),
+            scene: Scene::new(camera),
+            point_lights: Vec::new(),
+            spot_light: EntityId::default(),
+            sun: EntityId::default(),
+            time_of_day: Self::INITIAL_TIME_OF_DAY,
+            sun_moving: true,
+        }
+    }
+
+    /// Create a white dot that fades out towards its edges, which is tinted to
+    /// draw a glow at each point light.
+    fn create_glow_texture(context: &RenderContext) -> Texture {
+        const SIZE: u32 = 32;
+
+        let image = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+            let offset = (Vec2::new(x as f32, y as f32) + 0.5) / SIZE as f32 * 2.0 - 1.0;
+            let alpha = (1.0 - offset.length()).clamp(0.0, 1.0).powi(2);
+            image::Rgba([255, 255, 255, (alpha * 255.0) as u8])
+        });
+
+        textures::from_image(
+            context.device(),
+            context.queue(),
+            image.into(),
+            ColorSpace::Srgb,
+            Some("point light glow"),
+        )
+    }
+}
+
+impl GameApp for LightingDemo {
+    fn load_content(
+        &mut self,
+        context: &RenderContext,
+        progress: Option<LoadProgressSink>,
+    ) -> anyhow::Result<()> {
+        let content = ContentManager::new(context.clone()).with_progress_sink(progress);
+
+        let floor_material = MaterialBuilder::new()
+            .diffuse_color(Vec3::splat(0.6))
+            .specular_color(Vec3::splat(0.2))
+            .specular_power(16.0)
+            .build(content.default_textures());
+        let pillar_material = MaterialBuilder::new()
+            .diffuse_color(Vec3::splat(0.9))
+            .specular_color(Vec3::ONE)
+            .specular_power(64.0)
+            .build(content.default_textures());
+
+        let floor_mesh = Rc::new(builtin_mesh(
+            context.device(),
+            context.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &floor_material,
+        ));
+        let pillar_mesh = Rc::new(builtin_mesh(
+            context.device(),
+            context.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &pillar_material,
+        ));
+
+        // The floor is a flattened cube with its top face at y = 0.
+        self.scene.spawn_model(Model::new(
+            floor_mesh,
+            Vec3::new(0.0, -0.1, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(Self::FLOOR_SIZE, 0.2, Self::FLOOR_SIZE) * 0.5,
+        ));
+
+        for index in 0..Self::PILLAR_COUNT {
+            let angle = index as f32 / Self::PILLAR_COUNT as f32 * std::f32::consts::TAU;
+            self.scene.spawn_model(Model::new(
+                pillar_mesh.clone(),
+                Vec3::new(angle.sin(), 0.0, angle.cos()) * Self::PILLAR_RING_RADIUS
+                    + Vec3::new(0.0, 1.5, 0.0),
+                Quat::IDENTITY,
+                Vec3::new(0.3, 1.5, 0.3),
+            ));
+        }
+
+        // A single pillar in the middle for the orbiting lights to circle.
+        self.scene.spawn_model(Model::new(
+            pillar_mesh,
+            Vec3::new(0.0, 1.0, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(0.5, 1.0, 0.5),
+        ));
+
+        self.point_lights = Self::POINT_LIGHT_COLORS
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                self.scene.spawn(
+                    Entity::new(Transform::IDENTITY)
+                        .with_name(format!("point light {index}"))
+                        .with_light(PointLight {
+                            color: *color,
+                            attenuation: LightAttenuation {
+                                constant: 1.0,
+                                linear: 0.14,
+                                quadratic: 0.07,
+                            },
+                            range: 12.0,
+                            specular: 1.0,
+                            casts_shadows: index == 0,
+                            ..Default::default()
+                        }),
+                )
+            })
+            .collect();
+
+        // Each point light is drawn as a glow in its color, in the same order
+        // as `point_lights`.
+        let glow_texture = Rc::new(Self::create_glow_texture(context));
+        self.scene.billboards = Self::POINT_LIGHT_COLORS
+            .iter()
+            .map(|color| {
+                let mut glow = Billboard::new(
+                    Vec3::ZERO,
+                    Vec2::splat(Self::POINT_LIGHT_GLOW_SIZE),
+                    glow_texture.clone(),
+                );
+                glow.color = color.extend(1.0);
+                glow
+            })
+            .collect();
+
+        let mut spot_light = SpotLight::from_degrees(15.0, 22.5)?;
+        spot_light.position = Self::SPOT_LIGHT_POS;
+        spot_light.direction = Vec3::NEG_Y;
+        spot_light.color = Vec3::new(1.0, 0.9, 0.6);
+        spot_light.attenuation = LightAttenuation {
+            constant: 1.0,
+            linear: 0.045,
+            quadratic: 0.0075,
+        };
+        spot_light.range = DEFAULT_LIGHT_RANGE;
+        spot_light.specular = 1.0;
+
+        self.spot_light = self.scene.spawn(
+            Entity::new(Transform::IDENTITY)
+                .with_name("spot light")
+                .with_light(spot_light),
+        );
+        self.sun = self.scene.spawn(
+            Entity::new(Transform::IDENTITY)
+                .with_name("sun")
+                .with_light(sun_light(self.time_of_day, Self::LATITUDE_DEGREES)),
+        );
+
+        Ok(())
+    }
+
+    fn input(&mut self, event: &winit::event::WindowEvent) -> bool {
+        self.arcball.process_input(event)
+    }
+
+    fn gamepad_input(&mut self, event: &GamepadEvent) -> bool {
+        self.arcball.process_gamepad(event)
+    }
+
+    fn build_ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Lighting").show(ctx, |ui| {
+            ui.checkbox(&mut self.sun_moving, "Move the sun");
+
+            for (index, id) in self.point_lights.iter().enumerate() {
+                if let Some(light) = self.scene.light_mut(*id).and_then(Light::as_point_mut) {
+                    ui.add(
+                        egui::Slider::new(&mut light.intensity, 0.0..=5.0)
+                            .text(format!("Point light {index} intensity")),
+                    );
+                }
+            }
+
+            if let Some(spot_light) = self
+                .scene
+                .light_mut(self.spot_light)
+                .and_then(Light::as_spot_mut)
+            {
+                ui.add(
+                    egui::Slider::new(&mut spot_light.intensity, 0.0..=5.0)
+                        .text("Spot light intensity"),
+                );
+            }
+        });
+    }
+
+    fn update_sim(&mut self, delta: std::time::Duration, _input: &InputState) {
+        self.sim_time_elapsed += delta;
+
+        if self.sun_moving {
+            self.time_of_day += delta.as_secs_f32() / Self::DAY_LENGTH.as_secs_f32();
+        }
+    }
+
+    fn prepare_render(&mut self, _renderer: &mut Renderer, delta: std::time::Duration) {
+        self.arcball.update_camera(&mut self.scene.camera, delta);
+
+        let secs = self.sim_time_elapsed.as_secs_f32();
+
+        // Spread the point lights evenly around their orbit.
+        let light_count = self.point_lights.len() as f32;
+
+        for (index, id) in self.point_lights.iter().enumerate() {
+            let angle = (secs * Self::POINT_LIGHT_DEGREES_PER_SECOND).to_radians()
+                + index as f32 / light_count * std::f32::consts::TAU;
+
+            let position = Vec3::new(
+                angle.sin() * Self::POINT_LIGHT_ORBIT_RADIUS,
+                Self::POINT_LIGHT_HEIGHT,
+                angle.cos() * Self::POINT_LIGHT_ORBIT_RADIUS,
+            );
+
+            let Some(light) = self.scene.light_mut(*id).and_then(Light::as_point_mut) else {
+                continue;
+            };
+            light.position = position;
+
+            // Dim lights fade out their glow.
+            let opacity = light.intensity.min(1.0);
+
+            if let Some(glow) = self.scene.billboards.get_mut(index) {
+                glow.position = position;
+                glow.color.w = opacity;
+            }
+        }
+
+        // Swing the spot light back and forth around the Z axis.
+        let sweep = (secs / Self::SPOT_LIGHT_SWEEP_SECONDS * std::f32::consts::TAU).sin()
+            * Self::SPOT_LIGHT_SWEEP_DEGREES.to_radians();
+
+        if let Some(spot_light) = self
+            .scene
+            .light_mut(self.spot_light)
+            .and_then(Light::as_spot_mut)
+        {
+            spot_light.direction = Quat::from_rotation_z(sweep) * Vec3::NEG_Y;
+        }
+
+        if let Some(sun) = self
+            .scene
+            .light_mut(self.sun)
+            .and_then(Light::as_directional_mut)
+        {
+            *sun = sun_light(self.time_of_day, Self::LATITUDE_DEGREES);
+        }
+    }
+
+    fn mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.arcball.process_mouse_motion(delta_x, delta_y);
+    }
+
+    fn mouse_scroll_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.arcball.process_mouse_wheel(delta_x, delta_y);
+    }
+
+    fn wants_continuous_redraw(&self) -> bool {
+        // The lights are always moving.
+        true
+    }
+
+    fn render_scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    fn render_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    camera::Camera,
+    content::{ContentManager, LoadProgressSink},
+    gameplay::{gamepad::GamepadEvent, ArcballCameraController, CameraController},
+    math_utils::Aabb,
+    renderer::{
+        lighting::DirectionalLight,
+        models::{Mesh, Model},
+        recreated_resources::RecreatedResources,
+        render_context::RenderContext,
+        scene::{EntityId, Scene},
+        Renderer,
+    },
+};
+
+use tracing::{error, info, warn};
+use winit::event::WindowEvent;
+
+use super::{input_state::InputState, GameApp};
+
+/// A model that finished loading, or the error that stopped it loading.
+type LoadedMesh = Rc<RefCell<Option<anyhow::Result<Mesh>>>>;
+
+/// Moves the camera from where it was to where it frames a newly shown model.
+struct Framing {
+    from: Camera,
+    to: Camera,
+    elapsed: Duration,
+}
+
+/// Shows a single model that can be orbited with the arcball camera. The model
+/// is picked on the command line with `--model <path>`, or by dropping a model
+/// file onto the window. Only obj models are supported.
+pub struct ModelViewer {
+    arcball: ArcballCameraController,
+    scene: Scene,
+    /// Loads models and the textures they use, or `None` before content is
+    /// loaded.
+    content: Option<Rc<ContentManager>>,
+    /// Receives the loading progress of models.
+    progress: Option<LoadProgressSink>,
+    /// Path of the model to show. Relative paths are in the content directory.
+    model_path: PathBuf,
+    /// Receives the model's mesh once it is loaded.
+    loaded_mesh: LoadedMesh,
+    /// True while a model is being loaded.
+    loading: bool,
+    /// The model being shown, which is replaced when another model is loaded.
+    model: Option<EntityId>,
+    /// The camera move to a newly shown model, or `None` once the camera has
+    /// arrived.
+    framing: Option<Framing>,
+}
+
+impl ModelViewer {
+    /// The model shown when no other model is picked.
+    const DEFAULT_MODEL: &'static str = "demo_cube.obj";
+    const CAMERA_POS: Vec3 = Vec3::new(2.0, 2.0, 4.0);
+
+    /// How close to the model the camera can zoom in, relative to the radius
+    /// of the model's bounding box.
+    const MIN_ZOOM_DISTANCE: f32 = 0.5;
+    /// How far from the model the camera can zoom out, relative to the radius
+    /// of the model's bounding box.
+    const MAX_ZOOM_DISTANCE: f32 = 20.0;
+    /// Distance zoomed by each scroll unit, relative to the radius of the
+    /// model's bounding box.
+    const ZOOM_SPEED: f32 = 0.2;
+    /// How long the camera takes to move to a newly shown model.
+    const FRAMING_DURATION: Duration = Duration::from_millis(600);
+
+    const KEY_LIGHT: DirectionalLight = DirectionalLight {
+        direction: Vec3::new(-0.5, -1.0, -0.8),
+        color: Vec3::ONE,
+        intensity: 1.0,
+        ambient: 0.15,
+        specular: 0.8,
+    };
+    const FILL_LIGHT: DirectionalLight = DirectionalLight {
+        direction: Vec3::new(0.8, -0.2, 0.5),
+        color: Vec3::new(0.6, 0.7, 0.9),
+        intensity: 0.4,
+        ambient: 0.0,
+        specular: 0.1,
+    };
+
+    /// Create a model viewer that shows the model at `model_path`, or the
+    /// default model if `None`.
+    pub fn new(model_path: Option<PathBuf>) -> Self {
+        // The viewport size is set by the host once the render window size is
+        // known.
+        let camera = Camera::new(
+            Self::CAMERA_POS,
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            0,
+            0,
+        );
+
+        Self {
+            arcball: ArcballCameraController::new(),
+            scene: Scene::new(camera),
+            content: None,
+            progress: None,
+            model_path: model_path.unwrap_or_else(|| PathBuf::from(Self::DEFAULT_MODEL)),
+            loaded_mesh: Default::default(),
+            loading: false,
+            model: None,
+            framing: None,
+        }
+    }
+
+    /// Returns true if the file at `path` is a model that can be shown.
+    fn is_supported_model(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("obj"))
+    }
+
+    /// Start loading the model at `model_path`. Natively the model is loaded
+    /// right away, while on the web it is fetched in the background and shown
+    /// once it arrives.
+    fn start_loading(&mut self) {
+        let Some(content) = self.content.clone() else {
+            return;
+        };
+
+        self.loading = true;
+
+        let (path, loaded_mesh) = (self.model_path.clone(), self.loaded_mesh.clone());
+        let load = async move {
+            let mesh = content.load_obj_mesh(&path).await;
+            *loaded_mesh.borrow_mut() = Some(mesh);
+        };
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                wasm_bindgen_futures::spawn_local(load);
+            } else {
+                pollster::block_on(load);
+            }
+        }
+    }
+
+    /// Show the model in place of the previous one if it finished loading since
+    /// the last call, and point the camera at it.
+    fn show_loaded_mesh(&mut self, renderer: &mut Renderer) {
+        let Some(loaded_mesh) = self.loaded_mesh.borrow_mut().take() else {
+            return;
+        };
+
+        self.loading = false;
+
+        let mesh = match loaded_mesh {
+            Ok(mesh) => mesh,
+            Err(e) => {
+                error!("failed to load model {:?}: {e:#}", self.model_path);
+                return;
+            }
+        };
+
+        info!("showing model {:?}", self.model_path);
+
+        if let Some(model) = self
+            .model
+            .take()
+            .and_then(|id| self.scene.despawn(id))
+            .and_then(|entity| entity.model)
+        {
+            renderer.destroy_model(model);
+        }
+
+        let bounds = mesh.bounds();
+        self.model = Some(self.scene.spawn_model(Model::new(
+            Rc::new(mesh),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        )));
+
+        self.frame_model(bounds);
+    }
+
+    /// Move the camera to point at the center of `bounds` from far enough away
+    /// to see all of it, and scale zooming to the size of the model.
+    fn frame_model(&mut self, bounds: Aabb) {
+        let radius = (bounds.size().length() * 0.5).max(0.01);
+        let mut camera = self.scene.camera.clone();
+
+        camera.frame_bounds(bounds);
+
+        // Leave room to zoom out without the model being clipped.
+        if let Err(e) = camera.set_clip_planes(
+            radius * Self::MIN_ZOOM_DISTANCE * 0.1,
+            radius * (Self::MAX_ZOOM_DISTANCE + 1.0),
+        ) {
+            warn!("model clip planes were not changed: {e}");
+        }
+
+        if let Err(e) = self.arcball.set_distance_range(
+            radius * Self::MIN_ZOOM_DISTANCE,
+            Some(radius * Self::MAX_ZOOM_DISTANCE),
+        ) {
+            warn!("model zoom distance range was not changed: {e}");
+        }
+
+        self.arcball.set_scroll_speed(radius * Self::ZOOM_SPEED);
+
+        self.framing = Some(Framing {
+            from: self.scene.camera.clone(),
+            to: camera,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Move the camera along the framing move, returning false once there is
+    /// no move left to make.
+    fn update_framing(&mut self, delta: Duration) -> bool {
+        let Some(framing) = &mut self.framing else {
+            return false;
+        };
+
+        framing.elapsed += delta;
+
+        // Ease in and out so the camera doesn't start or stop abruptly.
+        let t = (framing.elapsed.as_secs_f32() / Self::FRAMING_DURATION.as_secs_f32()).min(1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+
+        // Keep the current viewport in case the window was resized during the
+        // move.
+        let camera = &mut self.scene.camera;
+        let (width, height) = (camera.viewport_width(), camera.viewport_height());
+        *camera = Camera::lerp(&framing.from, &framing.to, t);
+        camera
+            .set_viewport_size(width as u32, height as u32)
+            .unwrap_or_else(|e| warn!("{e}"));
+
+        if t >= 1.0 {
+            self.framing = None;
+        }
+
+        true
+    }
+}
+
+impl GameApp for ModelViewer {
+    fn load_content(
+        &mut self,
+        context: &RenderContext,
+        progress: Option<LoadProgressSink>,
+    ) -> anyhow::Result<()> {
+        self.model = None;
+        self.scene.spawn_light(Self::KEY_LIGHT);
+        self.scene.spawn_light(Self::FILL_LIGHT);
+
+        self.progress = progress;
+        self.content = Some(Rc::new(
+            ContentManager::new(context.clone()).with_progress_sink(self.progress.clone()),
+        ));
+        self.start_loading();
+
+        Ok(())
+    }
+
+    fn recreate_gpu_resources(&mut self, resources: &mut RecreatedResources) -> anyhow::Result<()> {
+        self.scene.recreate_gpu_resources(resources);
+
+        // Content loaded from now on is created on the new device. A model that
+        // was still loading was created on the old device, so it is loaded
+        // again.
+        if self.content.is_some() {
+            self.content = Some(Rc::new(
+                ContentManager::new(resources.context().clone())
+                    .with_progress_sink(self.progress.clone()),
+            ));
+        }
+
+        if self.loading {
+            self.loaded_mesh = Default::default();
+            self.start_loading();
+        }
+
+        Ok(())
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::DroppedFile(path) = event {
+            if Self::is_supported_model(path) {
+                self.model_path = path.clone();
+                self.start_loading();
+            } else {
+                warn!("cannot show {path:?}, only obj models are supported");
+            }
+
+            return true;
+        }
+
+        self.arcball.process_input(event)
+    }
+
+    fn gamepad_input(&mut self, event: &GamepadEvent) -> bool {
+        self.arcball.process_gamepad(event)
+    }
+
+    fn update_sim(&mut self, _delta: Duration, _input: &InputState) {}
+
+    fn prepare_render(&mut self, renderer: &mut Renderer, delta: Duration) {
+        self.show_loaded_mesh(renderer);
+
+        if !self.update_framing(delta) {
+            self.arcball.update_camera(&mut self.scene.camera, delta);
+        }
+    }
+
+    fn mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.arcball.process_mouse_motion(delta_x, delta_y);
+    }
+
+    fn mouse_scroll_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.arcball.process_mouse_wheel(delta_x, delta_y);
+    }
+
+    fn wants_continuous_redraw(&self) -> bool {
+        // Keep drawing until the model arrives and the camera has moved to it so
+        // both are shown without waiting for input.
+        self.loading || self.framing.is_some()
+    }
+
+    fn render_scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    fn render_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_obj_files_are_supported_models() {
+        assert!(ModelViewer::is_supported_model(Path::new("crate.obj")));
+        assert!(ModelViewer::is_supported_model(Path::new(
+            "/models/CRATE.OBJ"
+        )));
+        assert!(!ModelViewer::is_supported_model(Path::new("crate.mtl")));
+        assert!(!ModelViewer::is_supported_model(Path::new("crate")));
+    }
+}
@@ -1,33 +1,108 @@
 use std::rc::Rc;
 
-use glam::{Quat, Vec2, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3};
 
 use crate::{
-    gameplay::{ArcballCameraController, CameraController, FreeLookCameraController},
-    math_utils::rotate_around_pivot,
+    camera::{Camera, Projection},
+    content::{scene_file::SceneHotReload, ContentManager, LoadProgressSink},
+    gameplay::{
+        camera_path::{CameraKeyframe, CameraPath, PathInterpolation},
+        gamepad::{GamepadButton, GamepadEvent},
+        gestures::MouseGesture,
+        input_map::{Binding, InputMap},
+        ArcballCameraController, CameraController, FollowCameraController,
+        FreeLookCameraController,
+    },
+    math_utils::{rotate_around_pivot, Ray},
     renderer::{
-        lighting::{DirectionalLight, LightAttenuation, PointLight, SpotLight},
+        lighting::{
+            DirectionalLight, LightAttenuation, PointLight, SpotLight, DEFAULT_LIGHT_INTENSITY,
+            DEFAULT_LIGHT_RANGE,
+        },
         materials::MaterialBuilder,
         meshes::{builtin_mesh, BuiltinMesh},
-        scene::Scene,
-        textures::{self, ColorSpace},
+        models::{DebugBounds, Model},
+        render_context::RenderContext,
+        render_target::RenderTarget,
+        scene::{Entity, EntityId, Light, Scene, Transform},
+        sprites::Sprite,
+        textures::{self, ColorSpace, Texture},
         Renderer,
     },
 };
 
-use super::GameApp;
+use tracing::warn;
+use winit::{
+    event::{ElementState, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use super::{input_state::InputState, GameApp};
 
 enum CameraControllerType {
     Arcball,
     Freelook,
+    Flythrough,
+    /// Follows the cube orbiting the first cube.
+    Follow,
 }
 
 pub struct MultiCubeDemo {
     arcball: ArcballCameraController,
     freelook: FreeLookCameraController,
+    /// Camera path that circles the cubes.
+    flythrough: CameraPath,
+    /// Chases the cube orbiting the first cube.
+    follow: FollowCameraController,
     camera_type: CameraControllerType,
+    /// Maps keys to the demo's camera toggles.
+    input: InputMap,
+    /// Camera toggle that the next released key is bound to, picked in the
+    /// demo settings.
+    rebinding: Option<&'static str>,
     sim_time_elapsed: std::time::Duration,
+    /// The simulation stops advancing while the window is not focused.
+    paused: bool,
     scene: Scene,
+    /// Point light that circles the scene.
+    orbiting_light: EntityId,
+    /// Directional light acting as the sun.
+    sun: EntityId,
+    /// Spot light that follows the camera.
+    flashlight: EntityId,
+    /// Attached to the first cube and spun to make a smaller cube attached to
+    /// it orbit the first cube.
+    moon_pivot: EntityId,
+    /// The cube orbiting the first cube, which the follow camera chases.
+    moon: EntityId,
+    /// Applies `SCENE_FILE` to the scene when it is edited.
+    scene_file: Option<SceneHotReload>,
+    /// The cube that was last clicked on, which is highlighted, outlined and
+    /// drawn with its bounding sphere.
+    picked: Option<EntityId>,
+    /// Where the click that picked the cube hit it, which is marked with a
+    /// small debug cube.
+    picked_point: Option<Vec3>,
+    /// The ray cast by the click that picked the cube.
+    pick_ray: Option<Ray>,
+    /// Pick cubes by their bounding boxes rather than their triangles.
+    pick_bounds: bool,
+    /// Draw a ground grid, the world axes, the bounds of every cube, the
+    /// flythrough's keyframes, lines between attached entities and the last
+    /// pick ray as debug lines.
+    debug_lines: bool,
+    /// Drawn in the middle of the scene's view while the free look camera is
+    /// active.
+    crosshair: Option<Rc<Texture>>,
+    /// Top down camera drawn beside the scene's camera, or `None` when the
+    /// screen is not split.
+    overview: Option<Camera>,
+    /// Draw the top down view into a small texture shown in the corner of the
+    /// window.
+    show_minimap: bool,
+    /// Texture the minimap is drawn into, created the first frame it is
+    /// shown.
+    minimap: Option<RenderTarget>,
 }
 
 impl MultiCubeDemo {
@@ -40,8 +115,11 @@ impl MultiCubeDemo {
                 quadratic: 0.01,
             },
             color: Vec3::new(0.8, 0.8, 0.8),
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            range: DEFAULT_LIGHT_RANGE,
             ambient: 0.0425,
             specular: 1.0,
+            casts_shadows: false,
         },
         PointLight {
             position: Vec3::new(-4.0, 2.0, -12.0),
@@ -51,8 +129,11 @@ impl MultiCubeDemo {
                 quadratic: 0.03,
             },
             color: Vec3::new(1.0, 0.0, 0.0),
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            range: DEFAULT_LIGHT_RANGE,
             ambient: 0.0,
             specular: 1.0,
+            casts_shadows: false,
         },
         PointLight {
             position: Vec3::new(0.7, 0.2, 2.0),
@@ -62,8 +143,11 @@ impl MultiCubeDemo {
                 quadratic: 0.03,
             },
             color: Vec3::new(1.0, 0.5, 0.0),
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            range: DEFAULT_LIGHT_RANGE,
             ambient: 0.0,
             specular: 1.0,
+            casts_shadows: false,
         },
         PointLight {
             position: Vec3::new(2.3, -3.3, -4.0),
@@ -73,13 +157,17 @@ impl MultiCubeDemo {
                 quadratic: 0.03,
             },
             color: Vec3::new(0.0, 0.0, 1.0),
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            range: DEFAULT_LIGHT_RANGE,
             ambient: 0.0,
             specular: 1.0,
+            casts_shadows: false,
         },
     ];
     const DIRECTIONAL_LIGHT: DirectionalLight = DirectionalLight {
         direction: Vec3::new(0.0, -1.0, 0.0),
         color: Vec3::new(0.3, 0.3, 0.3),
+        intensity: DEFAULT_LIGHT_INTENSITY,
         ambient: 0.01,
         specular: 0.2,
     };
@@ -89,11 +177,13 @@ impl MultiCubeDemo {
         cutoff_radians: 0.2181662,       // 12.5 degree.
         outer_cutoff_radians: 0.3054326, // 17.5 degree.
         color: Vec3::new(0.8, 0.8, 0.8),
+        intensity: DEFAULT_LIGHT_INTENSITY,
         attenuation: LightAttenuation {
             constant: 1.0,
             linear: 0.09,
             quadratic: 0.032,
         },
+        range: DEFAULT_LIGHT_RANGE,
         ambient: 0.01,
         specular: 1.0,
     };
@@ -111,23 +201,332 @@ impl MultiCubeDemo {
         Vec3::new(-1.3, 1.0, -1.5),
     ];
 
+    /// Lights, transforms and materials that can be edited while the demo is
+    /// running.
+    const SCENE_FILE: &'static str = "multi_cube_demo.scene.json";
+
+    const CAMERA_POS: Vec3 = Vec3::new(1.5, 1.0, 5.0);
+    const CAMERA_LOOK_AT: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    /// The overview camera looks straight down at the middle of the cubes.
+    const OVERVIEW_POS: Vec3 = Vec3::new(-1.0, 30.0, -4.0);
+    const OVERVIEW_LOOK_AT: Vec3 = Vec3::new(-1.0, 0.0, -4.0);
+    /// Distance between the first cube and the cube orbiting it.
+    const MOON_DISTANCE: f32 = 1.5;
+    /// Width and height of the crosshair in pixels.
+    const CROSSHAIR_SIZE: u32 = 15;
+    /// Width and height of the minimap in pixels.
+    const MINIMAP_SIZE: u32 = 192;
+    /// Gap between the minimap and the edges of the window in pixels.
+    const MINIMAP_MARGIN: f32 = 10.0;
+    /// The debug grid is drawn just below the lowest cube.
+    const DEBUG_GRID_CENTER: Vec3 = Vec3::new(0.0, -4.0, 0.0);
+
+    /// Time taken to circle the cubes once during the flythrough.
+    const FLYTHROUGH_DURATION: std::time::Duration = std::time::Duration::from_secs(16);
+
+    const TOGGLE_CAMERA: &'static str = "toggle_camera";
+    const TOGGLE_PROJECTION: &'static str = "toggle_projection";
+    const TOGGLE_FLYTHROUGH: &'static str = "toggle_flythrough";
+    const TOGGLE_FOLLOW: &'static str = "toggle_follow";
+
     pub fn new() -> Self {
+        // Initialize the scene's camera.
+        // Position it one unit up, and five units back from world origin and
+        // have it look at the origin. The viewport size is set by the host once
+        // the render window size is known.
+        // +y is up
+        // +z is out of the screen.
+        let camera = Camera::new(
+            Self::CAMERA_POS,
+            Self::CAMERA_LOOK_AT,
+            Vec3::new(0.0, 1.0, 0.0),
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            0,
+            0,
+        );
+
         Self {
             arcball: ArcballCameraController::new(),
             freelook: FreeLookCameraController::new(),
+            flythrough: Self::flythrough_path(),
+            follow: Self::follow_camera(),
             camera_type: CameraControllerType::Arcball,
+            input: InputMap::new()
+                .with_binding(Self::TOGGLE_CAMERA, Binding::Key(KeyCode::KeyC))
+                .with_binding(Self::TOGGLE_PROJECTION, Binding::Key(KeyCode::KeyO))
+                .with_binding(Self::TOGGLE_FLYTHROUGH, Binding::Key(KeyCode::KeyP))
+                .with_binding(Self::TOGGLE_FOLLOW, Binding::Key(KeyCode::KeyF))
+                .with_binding(
+                    Self::TOGGLE_CAMERA,
+                    Binding::GamepadButton(GamepadButton::Select),
+                )
+                .with_binding(
+                    Self::TOGGLE_PROJECTION,
+                    Binding::GamepadButton(GamepadButton::North),
+                )
+                .with_binding(
+                    Self::TOGGLE_FLYTHROUGH,
+                    Binding::GamepadButton(GamepadButton::Start),
+                )
+                .with_binding(
+                    Self::TOGGLE_FOLLOW,
+                    Binding::GamepadButton(GamepadButton::West),
+                ),
+            rebinding: None,
             sim_time_elapsed: Default::default(),
-            scene: Default::default(),
+            paused: false,
+            scene: Scene::new(camera),
+            orbiting_light: EntityId::default(),
+            sun: EntityId::default(),
+            flashlight: EntityId::default(),
+            moon_pivot: EntityId::default(),
+            moon: EntityId::default(),
+            scene_file: None,
+            picked: None,
+            picked_point: None,
+            pick_ray: None,
+            pick_bounds: false,
+            debug_lines: false,
+            crosshair: None,
+            overview: None,
+            show_minimap: false,
+            minimap: None,
+        }
+    }
+
+    /// Create the controller that chases the cube orbiting the first cube,
+    /// which is smaller than the other cubes so it is followed more closely.
+    fn follow_camera() -> FollowCameraController {
+        let mut follow = FollowCameraController::new();
+        follow
+            .set_distance(2.5, 1.0, 10.0)
+            .unwrap_or_else(|e| warn!("{e}"));
+        follow.set_height(1.0, 0.0);
+        follow
+    }
+
+    /// Create the top down camera shown when the screen is split.
+    fn overview_camera() -> Camera {
+        // Looking straight down, so -z is up on screen. The viewport size is
+        // set by the renderer when drawing.
+        Camera::new(
+            Self::OVERVIEW_POS,
+            Self::OVERVIEW_LOOK_AT,
+            Vec3::NEG_Z,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            0,
+            0,
+        )
+    }
+
+    /// Create a looping path that circles the cubes, rising and falling and
+    /// zooming in on the way around.
+    fn flythrough_path() -> CameraPath {
+        const KEYFRAME_COUNT: u32 = 8;
+
+        let keyframes = (0..=KEYFRAME_COUNT)
+            .map(|index| {
+                let angle = index as f32 / KEYFRAME_COUNT as f32 * std::f32::consts::TAU;
+                let (near, low) = (index % 2 == 1, index % 4 == 2);
+
+                CameraKeyframe::new(
+                    Self::FLYTHROUGH_DURATION * index / KEYFRAME_COUNT,
+                    Vec3::new(
+                        angle.sin() * if near { 4.0 } else { 6.0 },
+                        if low { 0.5 } else { 2.0 },
+                        angle.cos() * if near { 4.0 } else { 6.0 },
+                    ),
+                    Self::CAMERA_LOOK_AT,
+                    f32::to_radians(if near { 40.0 } else { 50.0 }),
+                )
+            })
+            .collect();
+
+        let mut path = CameraPath::new(keyframes, PathInterpolation::CatmullRom)
+            .expect("flythrough keyframes are valid");
+        path.set_looping(true);
+        path
+    }
+
+    /// Create a white plus shaped crosshair with a dark outline.
+    fn create_crosshair_texture(context: &RenderContext) -> Texture {
+        const SIZE: u32 = MultiCubeDemo::CROSSHAIR_SIZE;
+        const CENTER: i32 = SIZE as i32 / 2;
+
+        let image = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+            let (dx, dy) = ((x as i32 - CENTER).abs(), (y as i32 - CENTER).abs());
+
+            match dx.min(dy) {
+                0 => image::Rgba([255, 255, 255, 255]),
+                1 => image::Rgba([0, 0, 0, 160]),
+                _ => image::Rgba([0, 0, 0, 0]),
+            }
+        });
+
+        textures::from_image(
+            context.device(),
+            context.queue(),
+            image.into(),
+            ColorSpace::Srgb,
+            Some("crosshair"),
+        )
+    }
+
+    /// Render the top down view into the minimap and show it in the top right
+    /// corner of the window. The minimap's texture is freed while it is
+    /// hidden.
+    fn draw_minimap(&mut self, renderer: &mut Renderer) {
+        if !self.show_minimap {
+            self.minimap = None;
+            return;
         }
+
+        let minimap = self.minimap.get_or_insert_with(|| {
+            renderer.create_render_target(Self::MINIMAP_SIZE, Self::MINIMAP_SIZE)
+        });
+        renderer.render_to_target(&self.scene, &Self::overview_camera(), minimap);
+
+        let size = Vec2::new(minimap.width() as f32, minimap.height() as f32);
+        let window_width = renderer.window_size().width as f32;
+        self.scene.sprites.push(Sprite::new(
+            Vec2::new(
+                window_width - size.x - Self::MINIMAP_MARGIN,
+                Self::MINIMAP_MARGIN,
+            ),
+            size,
+            minimap.texture().clone(),
+        ));
+    }
+
+    /// Draw a grid below the cubes, the world axes, a box around all of the
+    /// cubes, the flythrough's keyframes, lines between attached entities and
+    /// the ray from the last click to the cube it picked.
+    fn draw_debug_lines(&self, renderer: &mut Renderer) {
+        let debug_draw = renderer.debug_draw();
+        debug_draw.grid(Self::DEBUG_GRID_CENTER, 30.0, 30, Vec3::splat(0.3));
+        debug_draw.axes(Mat4::IDENTITY, 1.0);
+
+        if let Some(bounds) = self
+            .scene
+            .models()
+            .map(Model::world_bounds)
+            .reduce(|a, b| a.union(&b))
+        {
+            debug_draw.aabb(&bounds, Vec3::new(0.0, 1.0, 1.0));
+        }
+
+        // Show where the flythrough passes through or near.
+        for keyframes in self.flythrough.keyframes().windows(2) {
+            debug_draw.line(keyframes[0].eye, keyframes[1].eye, Vec3::new(1.0, 0.5, 0.0));
+        }
+
+        // Connect each entity to the entities attached to it.
+        for (id, _) in self.scene.entities() {
+            let origin = |id| {
+                self.scene
+                    .world_transform(id)
+                    .map(|world| world.transform_point3(Vec3::ZERO))
+            };
+
+            for child in self.scene.children(id) {
+                if let (Some(a), Some(b)) = (origin(id), origin(child)) {
+                    debug_draw.line(a, b, Vec3::new(1.0, 0.0, 1.0));
+                }
+            }
+        }
+
+        if let (Some(ray), Some(point)) = (self.pick_ray, self.picked_point) {
+            debug_draw.ray(ray.origin, point - ray.origin, Vec3::new(1.0, 1.0, 0.0));
+        }
+    }
+
+    /// Replace the keys bound to the camera toggle `action` with `key`.
+    /// Gamepad buttons bound to the toggle are kept.
+    fn rebind_toggle(&mut self, action: &str, key: KeyCode) {
+        let bindings: Vec<Binding> = self
+            .input
+            .bindings(action)
+            .iter()
+            .copied()
+            .filter(|binding| !matches!(binding, Binding::Key(_)))
+            .chain([Binding::Key(key)])
+            .collect();
+
+        self.input.rebind(action, bindings);
+    }
+
+    /// Apply the demo's camera toggles when their keys are released. The keys
+    /// can be changed in the demo settings, and default to:
+    ///  `c` -> Toggle between arcball and freelook camera.
+    ///  `o` -> Toggle between perspective and orthographic projection.
+    ///  `p` -> Start or stop flying around the cubes.
+    ///  `f` -> Start or stop following the cube orbiting the first cube.
+    fn apply_toggles(&mut self) {
+        if self.input.was_released(Self::TOGGLE_CAMERA) {
+            self.camera_type = match self.camera_type {
+                CameraControllerType::Arcball => CameraControllerType::Freelook,
+                CameraControllerType::Freelook
+                | CameraControllerType::Flythrough
+                | CameraControllerType::Follow => CameraControllerType::Arcball,
+            };
+        }
+
+        if self.input.was_released(Self::TOGGLE_FOLLOW) {
+            self.camera_type = match self.camera_type {
+                CameraControllerType::Follow => CameraControllerType::Arcball,
+                _ => {
+                    self.follow.snap();
+                    CameraControllerType::Follow
+                }
+            };
+        }
+
+        if self.input.was_released(Self::TOGGLE_FLYTHROUGH) {
+            self.camera_type = match self.camera_type {
+                CameraControllerType::Flythrough => {
+                    self.flythrough.pause();
+                    CameraControllerType::Arcball
+                }
+                _ => {
+                    self.flythrough.play();
+                    CameraControllerType::Flythrough
+                }
+            };
+        }
+
+        if self.input.was_released(Self::TOGGLE_PROJECTION) {
+            // Keep the target the same size on screen when switching.
+            let camera = &mut self.scene.camera;
+            let projection = match camera.projection() {
+                Projection::Perspective => Projection::Orthographic {
+                    height: camera.view_height_at(camera.eye().distance(camera.target())),
+                },
+                Projection::Orthographic { .. } => Projection::Perspective,
+            };
+
+            camera.set_projection(projection);
+        }
+
+        self.input.end_frame();
     }
 }
 
 impl GameApp for MultiCubeDemo {
-    fn load_content(&mut self, renderer: &mut Renderer) -> anyhow::Result<()> {
-        // TODO: Pass these values as raw parameters.
-        let device = &renderer.device;
-        let queue = &renderer.queue;
-        let default_textures = &renderer.default_textures;
+    fn load_content(
+        &mut self,
+        context: &RenderContext,
+        progress: Option<LoadProgressSink>,
+    ) -> anyhow::Result<()> {
+        let device = context.device();
+        let queue = context.queue();
+        let layouts = context.bind_group_layouts();
+        let content = ContentManager::new(context.clone()).with_progress_sink(progress);
+
+        self.crosshair = Some(Rc::new(Self::create_crosshair_texture(context)));
 
         // Create the crate model.
         let diffuse_map = Rc::new(textures::from_image_bytes(
@@ -151,11 +550,11 @@ impl GameApp for MultiCubeDemo {
             .specular_power(64.0)
             .diffuse_map(diffuse_map)
             .specular_map(specular_map)
-            .build(default_textures);
+            .build(content.default_textures());
 
         let cube_mesh = Rc::new(builtin_mesh(
-            &renderer.device,
-            &renderer.bind_group_layouts,
+            device,
+            layouts,
             BuiltinMesh::Cube,
             &crate_material,
         ));
@@ -163,79 +562,451 @@ impl GameApp for MultiCubeDemo {
         // Spawn a buch of copies of the crate model.
 
         // Set up scene.
-        self.scene.models.reserve(Self::INITIAL_CUBE_POS.len());
+        let cubes = Self::INITIAL_CUBE_POS
+            .iter()
+            .map(|initial_pos| {
+                self.scene.spawn_model(Model::new(
+                    cube_mesh.clone(),
+                    *initial_pos,
+                    Quat::IDENTITY,
+                    Vec3::ONE,
+                ))
+            })
+            .collect::<Vec<_>>();
 
-        for initial_pos in Self::INITIAL_CUBE_POS {
-            self.scene.models.push(renderer.create_model(
+        // A small cube orbits the first cube by being attached to a pivot that
+        // spins on the first cube.
+        self.moon_pivot = self
+            .scene
+            .spawn(Entity::new(Transform::IDENTITY).with_name("moon pivot"));
+        self.moon = self.scene.spawn(
+            Entity::new(Transform {
+                scale: Vec3::splat(0.3),
+                ..Transform::from_translation(Vec3::new(Self::MOON_DISTANCE, 0.0, 0.0))
+            })
+            .with_name("moon")
+            .with_model(Model::new(
                 cube_mesh.clone(),
-                *initial_pos,
+                Vec3::ZERO,
                 Quat::IDENTITY,
                 Vec3::ONE,
-            ));
-        }
+            )),
+        );
+
+        self.scene.attach(self.moon_pivot, cubes[0])?;
+        self.scene.attach(self.moon, self.moon_pivot)?;
 
         // This demo has one directional, one spot and three point lights.
-        self.scene.directional_lights.push(Self::DIRECTIONAL_LIGHT);
-        self.scene.spot_lights.push(Self::SPOT_LIGHT);
+        // Lights are named so the scene file can find them.
+        self.sun = self.scene.spawn(
+            Entity::new(Transform::IDENTITY)
+                .with_name("sun")
+                .with_light(Self::DIRECTIONAL_LIGHT),
+        );
+        self.flashlight = self.scene.spawn(
+            Entity::new(Transform::IDENTITY)
+                .with_name("flashlight")
+                .with_light(Self::SPOT_LIGHT),
+        );
+
+        for (index, light) in Self::POINT_LIGHTS.iter().enumerate() {
+            let id = self.scene.spawn(
+                Entity::new(Transform::IDENTITY)
+                    .with_name(format!("point light {index}"))
+                    .with_light(light.clone()),
+            );
 
-        for light in Self::POINT_LIGHTS.iter() {
-            self.scene.point_lights.push(light.clone());
+            if index == 0 {
+                self.orbiting_light = id;
+            }
         }
 
+        self.scene_file = SceneHotReload::new(Self::SCENE_FILE)
+            .map_err(|e| warn!("scene file changes will not be applied: {e:#}"))
+            .ok();
+
         Ok(())
     }
 
     fn input(&mut self, event: &winit::event::WindowEvent) -> bool {
-        use winit::{
-            event::{ElementState, WindowEvent},
-            keyboard::{KeyCode, PhysicalKey},
-        };
-
-        // Handle keyboard input events specific to this demo scene:
-        //  `c` -> Toggle between arcball and freelook camera.
-        if let WindowEvent::KeyboardInput {
-            event: keyboard_input_event,
-            ..
-        } = event
-        {
-            match keyboard_input_event.physical_key {
-                PhysicalKey::Code(KeyCode::KeyC)
-                    if keyboard_input_event.state == ElementState::Released =>
-                {
-                    self.camera_type = match self.camera_type {
-                        CameraControllerType::Arcball => CameraControllerType::Freelook,
-                        CameraControllerType::Freelook => CameraControllerType::Arcball,
-                    };
+        // The key released while a toggle is being rebound replaces the
+        // toggle's keys, or cancels rebinding if it is escape. It is not
+        // passed on, so it does not also toggle.
+        if let (Some(action), WindowEvent::KeyboardInput { event, .. }) = (self.rebinding, event) {
+            if let (PhysicalKey::Code(key), ElementState::Released) =
+                (event.physical_key, event.state)
+            {
+                if key != KeyCode::Escape {
+                    self.rebind_toggle(action, key);
                 }
-                _ => {}
+
+                self.rebinding = None;
+                return true;
             }
         }
 
+        // Demo toggles are applied in the next update.
+        let toggled = self.input.process_input(event);
+
         // Forward input to the active camera controller.
-        match self.camera_type {
+        let used = match self.camera_type {
             CameraControllerType::Arcball => self.arcball.process_input(event),
             CameraControllerType::Freelook => self.freelook.process_input(event),
-        }
+            CameraControllerType::Flythrough => self.flythrough.process_input(event),
+            CameraControllerType::Follow => self.follow.process_input(event),
+        };
+
+        toggled || used
     }
 
-    fn update_sim(&mut self, delta: std::time::Duration) {
-        self.sim_time_elapsed += delta;
+    fn gamepad_input(&mut self, event: &GamepadEvent) -> bool {
+        let toggled = self.input.process_gamepad(event);
+
+        // Forward input to the active camera controller.
+        let used = match self.camera_type {
+            CameraControllerType::Arcball => self.arcball.process_gamepad(event),
+            CameraControllerType::Freelook => self.freelook.process_gamepad(event),
+            CameraControllerType::Flythrough => self.flythrough.process_gamepad(event),
+            CameraControllerType::Follow => self.follow.process_gamepad(event),
+        };
+
+        toggled || used
+    }
+
+    fn build_ui(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Demo settings").show(ctx, |ui| {
+            ui.heading("Lights");
+
+            let Some(orbiting_light) = self
+                .scene
+                .light_mut(self.orbiting_light)
+                .and_then(Light::as_point_mut)
+            else {
+                return;
+            };
+            let mut color = orbiting_light.color.to_array();
+            ui.horizontal(|ui| {
+                ui.label("Orbiting light color");
+                ui.color_edit_button_rgb(&mut color);
+            });
+            orbiting_light.color = Vec3::from_array(color);
+            ui.add(
+                egui::Slider::new(&mut orbiting_light.intensity, 0.0..=5.0)
+                    .text("Orbiting light intensity"),
+            );
+
+            if let Some(sun) = self
+                .scene
+                .light_mut(self.sun)
+                .and_then(Light::as_directional_mut)
+            {
+                ui.add(egui::Slider::new(&mut sun.intensity, 0.0..=5.0).text("Sun intensity"));
+            }
+
+            if let Some(flashlight) = self
+                .scene
+                .light_mut(self.flashlight)
+                .and_then(Light::as_spot_mut)
+            {
+                ui.add(
+                    egui::Slider::new(&mut flashlight.intensity, 0.0..=5.0)
+                        .text("Flashlight intensity"),
+                );
+            }
+
+            ui.heading("Camera");
+
+            let mut split_screen = self.overview.is_some();
+            ui.checkbox(&mut split_screen, "Split screen overview");
+
+            if split_screen != self.overview.is_some() {
+                self.overview = split_screen.then(Self::overview_camera);
+            }
+
+            ui.checkbox(&mut self.show_minimap, "Minimap");
+
+            let mut move_speed = self.freelook.move_speed();
+            ui.add(egui::Slider::new(&mut move_speed, 0.5..=20.0).text("Free look move speed"));
+            self.freelook.set_move_speed(move_speed);
+
+            // Zooming out past the default 60 degrees gives an ultra-wide view.
+            let (min_fov, mut max_fov) = self.freelook.fov_range();
+
+            if ui
+                .add(
+                    egui::Slider::new(&mut max_fov, min_fov.max(30.0)..=150.0)
+                        .text("Free look widest zoom (degrees)"),
+                )
+                .changed()
+            {
+                self.freelook
+                    .set_fov_range(min_fov, max_fov)
+                    .unwrap_or_else(|e| warn!("{e}"));
+            }
+
+            let mut pan_speed = self.arcball.pan_speed();
+            ui.add(egui::Slider::new(&mut pan_speed, 0.1..=4.0).text("Arcball pan speed"));
+            self.arcball.set_pan_speed(pan_speed);
+
+            // Dolly zooming keeps the arcball pivot the same size on screen
+            // while the field of view changes. The free look controller zooms
+            // by changing the field of view itself.
+            if let CameraControllerType::Arcball = self.camera_type {
+                let camera = &mut self.scene.camera;
+                let mut fov_degrees = camera.fov_y().to_degrees();
+
+                if ui
+                    .add(
+                        egui::Slider::new(&mut fov_degrees, 20.0..=90.0)
+                            .text("Dolly zoom (degrees)"),
+                    )
+                    .changed()
+                {
+                    let pivot = camera.target();
+
+                    match camera
+                        .dolly_zoom_to(camera.eye().distance(pivot), fov_degrees.to_radians())
+                    {
+                        // The target moves with the camera, so look back at
+                        // the pivot to keep orbiting around it.
+                        Ok(()) => camera.reorient(camera.eye(), pivot),
+                        Err(e) => warn!("{e}"),
+                    }
+                }
+            }
+
+            // Both controllers share a mouse sensitivity.
+            let mut mouse_sensitivity = self.freelook.mouse_sensitivity();
+
+            if ui
+                .add(egui::Slider::new(&mut mouse_sensitivity, 0.1..=4.0).text("Mouse sensitivity"))
+                .changed()
+            {
+                self.freelook.set_mouse_sensitivity(mouse_sensitivity);
+                self.arcball.set_mouse_sensitivity(mouse_sensitivity);
+            }
+
+            // Arrow keys move the free look camera alongside WASD unless they
+            // are unbound, eg to keep them free for a game's own controls.
+            let arrows = [
+                (FreeLookCameraController::MOVE_FORWARD, KeyCode::ArrowUp),
+                (FreeLookCameraController::MOVE_BACKWARD, KeyCode::ArrowDown),
+                (FreeLookCameraController::MOVE_LEFT, KeyCode::ArrowLeft),
+                (FreeLookCameraController::MOVE_RIGHT, KeyCode::ArrowRight),
+            ];
+            let mut arrow_keys = arrows.iter().all(|(action, key)| {
+                self.freelook
+                    .input_map()
+                    .bindings(action)
+                    .contains(&Binding::Key(*key))
+            });
+
+            if ui
+                .checkbox(&mut arrow_keys, "Move free look with arrow keys")
+                .changed()
+            {
+                let input = self.freelook.input_map_mut();
+
+                for (action, key) in arrows {
+                    if arrow_keys {
+                        input.bind(action, Binding::Key(key));
+                    } else {
+                        input.unbind(action, Binding::Key(key));
+                    }
+                }
+            }
+
+            // Both controllers share inverting vertical mouse movement too.
+            let mut invert_y = self.freelook.invert_y();
+
+            if ui.checkbox(&mut invert_y, "Invert Y").changed() {
+                self.freelook.set_invert_y(invert_y);
+                self.arcball.set_invert_y(invert_y);
+            }
+
+            // Both controllers share a smoothing setting, and zero turns it off.
+            let mut smoothing_ms = self
+                .arcball
+                .smoothing()
+                .map_or(0.0, |half_life| half_life.as_secs_f32() * 1000.0);
+
+            if ui
+                .add(
+                    egui::Slider::new(&mut smoothing_ms, 0.0..=250.0).text("Camera smoothing (ms)"),
+                )
+                .changed()
+            {
+                let half_life = (smoothing_ms > 0.0)
+                    .then(|| std::time::Duration::from_secs_f32(smoothing_ms / 1000.0));
+                self.arcball.set_smoothing(half_life);
+                self.freelook.set_smoothing(half_life);
+            }
+
+            // Gamepad settings are also shared by both controllers.
+            let mut dead_zone = self.arcball.input_map().gamepad_dead_zone();
+            let mut sensitivity = self.arcball.input_map().gamepad_sensitivity();
+            ui.add(egui::Slider::new(&mut dead_zone, 0.0..=0.9).text("Gamepad dead zone"));
+            ui.add(egui::Slider::new(&mut sensitivity, 0.1..=4.0).text("Gamepad sensitivity"));
+
+            for input in [self.arcball.input_map_mut(), self.freelook.input_map_mut()] {
+                input.set_gamepad_dead_zone(dead_zone);
+                input.set_gamepad_sensitivity(sensitivity);
+            }
+
+            ui.heading("Flythrough");
+
+            // Scrubbing the path shows where the camera is at that time, and
+            // is kept when the path plays again.
+            let duration = self.flythrough.duration().as_secs_f32();
+            let mut time = self.flythrough.time().as_secs_f32();
+
+            if ui
+                .add(egui::Slider::new(&mut time, 0.0..=duration).text("Time (seconds)"))
+                .changed()
+            {
+                self.flythrough
+                    .seek(std::time::Duration::from_secs_f32(time));
+                self.camera_type = CameraControllerType::Flythrough;
+            }
+
+            let label = if self.flythrough.is_playing() {
+                "Pause"
+            } else {
+                "Play"
+            };
+
+            if ui.button(label).clicked() {
+                if self.flythrough.is_playing() {
+                    self.flythrough.pause();
+                } else {
+                    self.flythrough.play();
+                    self.camera_type = CameraControllerType::Flythrough;
+                }
+            }
+
+            ui.heading("Key bindings");
+
+            for (label, action) in [
+                ("Switch camera", Self::TOGGLE_CAMERA),
+                ("Switch projection", Self::TOGGLE_PROJECTION),
+                ("Fly around the cubes", Self::TOGGLE_FLYTHROUGH),
+                ("Follow the orbiting cube", Self::TOGGLE_FOLLOW),
+            ] {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+
+                    if self.rebinding == Some(action) {
+                        ui.label("Press a key...");
+                        return;
+                    }
+
+                    let keys: Vec<String> = self
+                        .input
+                        .bindings(action)
+                        .iter()
+                        .filter_map(|binding| match binding {
+                            Binding::Key(key) => Some(format!("{key:?}")),
+                            _ => None,
+                        })
+                        .collect();
+
+                    if ui.button(keys.join(", ")).clicked() {
+                        self.rebinding = Some(action);
+                    }
+                });
+            }
+
+            ui.heading("Debug");
+            ui.checkbox(&mut self.debug_lines, "Debug lines");
+            ui.checkbox(&mut self.pick_bounds, "Pick by bounding box");
+        });
+    }
+
+    fn update_sim(&mut self, delta: std::time::Duration, _input: &InputState) {
+        self.apply_toggles();
+
+        if !self.paused {
+            self.sim_time_elapsed += delta;
+        }
     }
 
     fn prepare_render(&mut self, renderer: &mut Renderer, delta: std::time::Duration) {
+        // The picked cube is outlined, and its bounds are drawn.
+        let picked = self
+            .picked
+            .and_then(|id| self.scene.model(id))
+            .and_then(Model::model_sv_key);
+        renderer.set_highlighted(picked);
+        renderer.set_selected(picked.as_slice());
+
+        if let Some(point) = self.picked_point.filter(|_| picked.is_some()) {
+            renderer.add_debug_cube(
+                Mat4::from_scale_rotation_translation(Vec3::splat(0.05), Quat::IDENTITY, point),
+                Vec3::new(1.0, 1.0, 0.0),
+            );
+        }
+
+        if self.debug_lines {
+            self.draw_debug_lines(renderer);
+        }
+
+        self.scene.sprites.clear();
+        self.draw_minimap(renderer);
+
+        // Center the crosshair in the scene's view, which is the left half of
+        // the window when the screen is split.
+        if let Some(crosshair) = self
+            .crosshair
+            .as_ref()
+            .filter(|_| matches!(self.camera_type, CameraControllerType::Freelook))
+        {
+            let window_size = renderer.window_size();
+            let mut view_size = Vec2::new(window_size.width as f32, window_size.height as f32);
+
+            if self.overview.is_some() {
+                view_size.x = (window_size.width / 2) as f32;
+            }
+
+            let size = Vec2::splat(Self::CROSSHAIR_SIZE as f32);
+            self.scene.sprites.push(Sprite::new(
+                ((view_size - size) * 0.5).round(),
+                size,
+                crosshair.clone(),
+            ));
+        }
+
+        if let Some(scene_file) = &mut self.scene_file {
+            scene_file.update(&mut self.scene, renderer.context().default_textures());
+        }
+
         // Allow camera controller to control the scene's camera.
         match self.camera_type {
             CameraControllerType::Arcball => {
-                self.arcball.update_camera(&mut renderer.camera, delta)
+                self.arcball.update_camera(&mut self.scene.camera, delta)
             }
             CameraControllerType::Freelook => {
-                self.freelook.update_camera(&mut renderer.camera, delta)
+                self.freelook.update_camera(&mut self.scene.camera, delta)
+            }
+            CameraControllerType::Flythrough => {
+                self.flythrough.update_camera(&mut self.scene.camera, delta)
+            }
+            CameraControllerType::Follow => {
+                self.follow.track(&self.scene, self.moon);
+                self.follow.update_camera(&mut self.scene.camera, delta)
             }
         }
 
         // Spot light follows the camera.
-        self.scene.spot_lights[0].position = renderer.camera.eye();
-        self.scene.spot_lights[0].direction = renderer.camera.forward();
+        let (eye, forward) = (self.scene.camera.eye(), self.scene.camera.forward());
+
+        if let Some(flashlight) = self
+            .scene
+            .light_mut(self.flashlight)
+            .and_then(Light::as_spot_mut)
+        {
+            flashlight.position = eye;
+            flashlight.direction = forward;
+        }
 
         // Make the primary light orbit around the scene.
         let sys_time_secs: f32 = self.sim_time_elapsed.as_secs_f32();
@@ -246,13 +1017,76 @@ impl GameApp for MultiCubeDemo {
             (sys_time_secs * 24.0).to_radians(),
         );
 
-        self.scene.point_lights[0].position = Vec3::new(light_xy.x, light_xy.y, light_xy.y);
+        if let Some(orbiting_light) = self
+            .scene
+            .light_mut(self.orbiting_light)
+            .and_then(Light::as_point_mut)
+        {
+            orbiting_light.position = Vec3::new(light_xy.x, light_xy.y, light_xy.y);
+        }
+
+        if let Some(moon_pivot) = self.scene.entity_mut(self.moon_pivot) {
+            moon_pivot.transform.rotation =
+                Quat::from_rotation_y((sys_time_secs * 45.0).to_radians());
+        }
     }
 
     fn mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
         match self.camera_type {
             CameraControllerType::Arcball => self.arcball.process_mouse_motion(delta_x, delta_y),
             CameraControllerType::Freelook => self.freelook.process_mouse_motion(delta_x, delta_y),
+            CameraControllerType::Flythrough => {
+                self.flythrough.process_mouse_motion(delta_x, delta_y)
+            }
+            CameraControllerType::Follow => self.follow.process_mouse_motion(delta_x, delta_y),
+        }
+    }
+
+    fn mouse_gesture(&mut self, gesture: &MouseGesture) {
+        // Clicking a cube selects it, and clicking empty space clears the
+        // selection.
+        if let MouseGesture::Click {
+            button: winit::event::MouseButton::Left,
+            position,
+        } = gesture
+        {
+            // The scene's camera only draws the left half of a split screen.
+            let mut camera = self.scene.camera.clone();
+
+            if self.overview.is_some() {
+                let (width, height) = (camera.viewport_width(), camera.viewport_height());
+                let left_width = (width as u32 / 2) as f32;
+
+                if position.x >= left_width {
+                    return;
+                }
+
+                camera
+                    .set_viewport_size(left_width as u32, height as u32)
+                    .unwrap_or_else(|e| warn!("{e}"));
+            }
+
+            let ray = camera.screen_to_ray(position.x, position.y);
+            let hit = if self.pick_bounds {
+                self.scene.pick(&ray)
+            } else {
+                self.scene.pick_triangles(&ray)
+            };
+
+            // Draw a bounding sphere around the picked cube as well as the box
+            // that highlights it.
+            for (id, sphere) in [(self.picked, false), (hit.map(|hit| hit.entity), true)] {
+                if let Some(model) = id.and_then(|id| self.scene.model_mut(id)) {
+                    model.set_debug_bounds(DebugBounds {
+                        aabb: false,
+                        sphere,
+                    });
+                }
+            }
+
+            self.picked = hit.map(|hit| hit.entity);
+            self.picked_point = hit.map(|hit| ray.at(hit.distance));
+            self.pick_ray = hit.map(|_| ray);
         }
     }
 
@@ -260,10 +1094,37 @@ impl GameApp for MultiCubeDemo {
         match self.camera_type {
             CameraControllerType::Arcball => self.arcball.process_mouse_wheel(delta_x, delta_y),
             CameraControllerType::Freelook => self.freelook.process_mouse_wheel(delta_x, delta_y),
+            CameraControllerType::Flythrough => {
+                self.flythrough.process_mouse_wheel(delta_x, delta_y)
+            }
+            CameraControllerType::Follow => self.follow.process_mouse_wheel(delta_x, delta_y),
+        }
+    }
+
+    fn focus_changed(&mut self, is_focused: bool) {
+        // Pause the demo while the user is doing something else. Keys released
+        // while the window is not focused are never seen, so forget them.
+        self.paused = !is_focused;
+
+        if !is_focused {
+            self.input.release_all();
         }
     }
 
+    fn wants_continuous_redraw(&self) -> bool {
+        // The primary light is always orbiting the scene unless paused.
+        !self.paused
+    }
+
+    fn split_screen_camera(&self) -> Option<&Camera> {
+        self.overview.as_ref()
+    }
+
     fn render_scene(&self) -> &Scene {
         &self.scene
     }
+
+    fn render_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
 }
@@ -0,0 +1,125 @@
+//! Scales, pauses and single steps the time passed to a game's simulation.
+use std::time::Duration;
+
+/// Time the simulation advances by for each single step while paused, which is
+/// one frame at 60 frames per second.
+pub const STEP_DELTA: Duration = Duration::from_nanos(16_666_667);
+
+/// Turns the time between frames into the time the simulation advances by.
+///
+/// The simulation can run faster or slower than real time, or be paused. While
+/// paused it can be stepped forward one frame at a time to debug update logic.
+#[derive(Debug)]
+pub struct SimClock {
+    time_scale: f32,
+    paused: bool,
+    /// Number of steps requested while paused that have not been taken.
+    pending_steps: u32,
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        Self {
+            time_scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+        }
+    }
+
+    /// Get how much faster than real time the simulation runs.
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Set how much faster than real time the simulation runs, eg 0.5 for half
+    /// speed. The scale cannot be negative.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        assert!(time_scale >= 0.0 && time_scale.is_finite());
+        self.time_scale = time_scale;
+    }
+
+    /// Returns true if the simulation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stop advancing the simulation.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Advance the simulation again after being paused. Steps that were not
+    /// taken yet are dropped.
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    /// Pause the simulation if it is running, and advance it by one step of
+    /// `STEP_DELTA` the next time it is updated. Steps are not scaled by the
+    /// time scale.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.pending_steps += 1;
+    }
+
+    /// Get how far to advance the simulation for a frame that was drawn
+    /// `delta` after the previous one.
+    pub fn advance(&mut self, delta: Duration) -> Duration {
+        if !self.paused {
+            delta.mul_f64(self.time_scale as f64)
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            STEP_DELTA
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn time_is_scaled_until_paused() {
+        let mut clock = SimClock::new();
+        assert_eq!(FRAME, clock.advance(FRAME));
+
+        clock.set_time_scale(0.5);
+        assert_eq!(FRAME / 2, clock.advance(FRAME));
+
+        clock.pause();
+        assert_eq!(Duration::ZERO, clock.advance(FRAME));
+
+        clock.resume();
+        assert_eq!(FRAME / 2, clock.advance(FRAME));
+    }
+
+    #[test]
+    fn steps_advance_one_frame_at_a_time() {
+        let mut clock = SimClock::new();
+        clock.set_time_scale(3.0);
+
+        // Stepping pauses a running simulation.
+        clock.step();
+        clock.step();
+        assert!(clock.is_paused());
+        assert_eq!(STEP_DELTA, clock.advance(FRAME));
+        assert_eq!(STEP_DELTA, clock.advance(FRAME));
+        assert_eq!(Duration::ZERO, clock.advance(FRAME));
+
+        // Resuming drops steps that were not taken.
+        clock.step();
+        clock.resume();
+        assert!(clock.advance(FRAME).abs_diff(FRAME * 3) < Duration::from_micros(1));
+    }
+}
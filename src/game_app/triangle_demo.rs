@@ -0,0 +1,174 @@
+use std::rc::Rc;
+
+use glam::{Quat, Vec3};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::{
+    camera::Camera,
+    content::{ContentManager, LoadProgressSink},
+    renderer::{
+        materials::MaterialBuilder,
+        meshes::{builtin_mesh, BuiltinMesh},
+        models::Model,
+        render_context::RenderContext,
+        scene::{EntityId, Scene},
+        Renderer,
+    },
+};
+
+use super::{input_state::InputState, GameApp};
+
+/// A minimal demo scene with a single unlit triangle spinning in front of the
+/// camera, controlled by polling the host's input state each update:
+///  `space` -> Reverse the spin. Hold `shift` to spin twice as fast.
+///  `up` / `down` or dragging with the left mouse button -> Tilt the triangle.
+///  Scroll wheel -> Move the triangle closer or further away.
+///  `r` or the right mouse button -> Reset the tilt and distance.
+pub struct TriangleDemo {
+    scene: Scene,
+    triangle: EntityId,
+    /// Rotation around the vertical axis in degrees.
+    spin: f32,
+    /// One when spinning counter clockwise seen from above, and minus one when
+    /// spinning clockwise.
+    spin_direction: f32,
+    /// Rotation around the horizontal axis in degrees.
+    tilt: f32,
+    /// How far the triangle was moved towards the camera.
+    offset: f32,
+}
+
+impl TriangleDemo {
+    const CAMERA_POS: Vec3 = Vec3::new(0.0, 0.0, 3.0);
+    const TRIANGLE_COLOR: Vec3 = Vec3::new(1.0, 0.5, 0.1);
+    /// How fast the triangle spins around the vertical axis.
+    const DEGREES_PER_SECOND: f32 = 45.0;
+    /// How fast holding the up or down arrow tilts the triangle.
+    const TILT_DEGREES_PER_SECOND: f32 = 90.0;
+    /// How much dragging the mouse by a pixel tilts the triangle.
+    const TILT_DEGREES_PER_PIXEL: f32 = 0.5;
+    /// How far a line of scrolling moves the triangle.
+    const OFFSET_PER_SCROLL_LINE: f32 = 0.25;
+    /// The triangle stays between the camera's near plane and this far behind
+    /// the origin.
+    const OFFSET_RANGE: std::ops::RangeInclusive<f32> = -10.0..=2.5;
+
+    pub fn new() -> Self {
+        // The viewport size is set by the host once the render window size is
+        // known.
+        let camera = Camera::new(
+            Self::CAMERA_POS,
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            0,
+            0,
+        );
+
+        Self {
+            scene: Scene::new(camera),
+            triangle: EntityId::default(),
+            spin: 0.0,
+            spin_direction: 1.0,
+            tilt: 0.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl GameApp for TriangleDemo {
+    fn load_content(
+        &mut self,
+        context: &RenderContext,
+        progress: Option<LoadProgressSink>,
+    ) -> anyhow::Result<()> {
+        let content = ContentManager::new(context.clone()).with_progress_sink(progress);
+
+        // The builtin triangle has a back face, so it stays visible as it
+        // spins all the way around.
+        let material = MaterialBuilder::new()
+            .diffuse_color(Self::TRIANGLE_COLOR)
+            .unlit(true)
+            .build(content.default_textures());
+
+        let triangle_mesh = Rc::new(builtin_mesh(
+            context.device(),
+            context.bind_group_layouts(),
+            BuiltinMesh::Triangle,
+            &material,
+        ));
+
+        self.triangle = self.scene.spawn_model(Model::new(
+            triangle_mesh,
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+
+        Ok(())
+    }
+
+    fn input(&mut self, _event: &winit::event::WindowEvent) -> bool {
+        false
+    }
+
+    fn update_sim(&mut self, delta: std::time::Duration, input: &InputState) {
+        let delta = delta.as_secs_f32();
+
+        if input.was_key_pressed(KeyCode::Space) {
+            self.spin_direction = -self.spin_direction;
+        }
+
+        let speed = if input.modifiers().shift_key() {
+            Self::DEGREES_PER_SECOND * 2.0
+        } else {
+            Self::DEGREES_PER_SECOND
+        };
+
+        self.spin = (self.spin + self.spin_direction * speed * delta) % 360.0;
+
+        if input.is_key_down(KeyCode::ArrowUp) {
+            self.tilt -= Self::TILT_DEGREES_PER_SECOND * delta;
+        }
+
+        if input.is_key_down(KeyCode::ArrowDown) {
+            self.tilt += Self::TILT_DEGREES_PER_SECOND * delta;
+        }
+
+        if input.is_button_down(MouseButton::Left) {
+            self.tilt += input.mouse_delta().y * Self::TILT_DEGREES_PER_PIXEL;
+        }
+
+        self.tilt = self.tilt.clamp(-90.0, 90.0);
+        self.offset = (self.offset + input.scroll_delta().y * Self::OFFSET_PER_SCROLL_LINE)
+            .clamp(*Self::OFFSET_RANGE.start(), *Self::OFFSET_RANGE.end());
+
+        if input.was_key_released(KeyCode::KeyR) || input.was_button_pressed(MouseButton::Right) {
+            self.tilt = 0.0;
+            self.offset = 0.0;
+        }
+    }
+
+    fn prepare_render(&mut self, _renderer: &mut Renderer, _delta: std::time::Duration) {
+        if let Some(triangle) = self.scene.entity_mut(self.triangle) {
+            triangle.transform.rotation = Quat::from_rotation_x(self.tilt.to_radians())
+                * Quat::from_rotation_y(self.spin.to_radians());
+            triangle.transform.translation = Vec3::new(0.0, 0.0, self.offset);
+        }
+    }
+
+    fn wants_continuous_redraw(&self) -> bool {
+        // The triangle is always spinning.
+        true
+    }
+
+    fn render_scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    fn render_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
@@ -1,12 +1,25 @@
+pub mod camera_path;
+pub mod gamepad;
+pub mod gestures;
+pub mod input_map;
+
 use std::time::Duration;
 
-use glam::{Quat, Vec2, Vec3};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use thiserror::Error;
 use winit::{
-    event::{ElementState, WindowEvent},
-    keyboard::{KeyCode, PhysicalKey},
+    event::{MouseButton, WindowEvent},
+    keyboard::KeyCode,
 };
 
-use crate::camera::Camera;
+use gamepad::{GamepadAxis, GamepadEvent};
+use input_map::{Binding, InputMap, MouseAxis};
+
+use crate::{
+    camera::Camera,
+    math_utils::Ray,
+    renderer::scene::{EntityId, Scene},
+};
 
 // NOTE: The camera can be janky when trying to scroll past min/max forward. It
 //       is also prone to weird behavior when vertically panning near to parallel
@@ -14,10 +27,6 @@ use crate::camera::Camera;
 //       mechanics and limits, but clearly I need to sit down again to work out
 //       why these behaviors are emerging near the limits despite being clamped.
 
-// TODO(scott): Simple tests for camera controller.
-//  1. Move forward/backward/left/right: is new position, eye expected?
-//  2. Does camera clamp the minimum/maximum forward?
-
 pub trait CameraController {
     /// Updates the camera controller state with the given input event. This
     /// method returns `true` if `event` was used by this update method, other
@@ -32,6 +41,10 @@ pub trait CameraController {
     /// `update_camera`.
     fn process_mouse_wheel(&mut self, delta_x: f64, delta_y: f64);
 
+    /// Updates the camera controller state with the given gamepad event. This
+    /// method returns `true` if `event` was used by this controller.
+    fn process_gamepad(&mut self, event: &GamepadEvent) -> bool;
+
     /// Applies updates to the camera that reflect the current state of this
     /// controller.
     fn update_camera(&mut self, camera: &mut Camera, delta: Duration);
@@ -39,116 +52,213 @@ pub trait CameraController {
 
 /// A first person camera that moves in the direction the mouse is looking.
 pub struct FreeLookCameraController {
-    /// A movement speed modifier.
+    /// Movement speed in world units per second.
     move_speed: f32,
+    /// Degrees of rotation per unit of mouse motion. Mouse motion is already
+    /// accumulated over the frame so this is not scaled by the frame time.
     look_speed: f32,
-    move_forward: bool,
-    move_backward: bool,
-    move_left: bool,
-    move_right: bool,
-    mouse_delta: Option<Vec2>,
+    /// Maps keys and mouse axes to movement, looking and zooming.
+    input: InputMap,
     pitch_deg: f32,
     yaw_deg: f32,
-    scroll_wheel_delta: Option<f32>,
+    /// Vertical field of view in degrees.
     fov_y: f32,
+    /// The narrowest vertical field of view in degrees that zooming can reach.
+    min_fov: f32,
+    /// The widest vertical field of view in degrees that zooming can reach.
+    max_fov: f32,
+    /// Scales mouse motion before it is applied as camera rotation.
+    mouse_sensitivity: f32,
+    /// Flips the direction of vertical mouse look when true.
+    invert_y: bool,
+    /// Time taken for the camera to cover half of the remaining distance to
+    /// where input moved it, or `None` to move the camera immediately.
+    smoothing_half_life: Option<Duration>,
+    /// Where input has moved the camera to when smoothing is enabled.
+    goal: Option<SmoothedGoal>,
 }
 
 impl FreeLookCameraController {
+    pub const MOVE_FORWARD: &'static str = "move_forward";
+    pub const MOVE_BACKWARD: &'static str = "move_backward";
+    pub const MOVE_LEFT: &'static str = "move_left";
+    pub const MOVE_RIGHT: &'static str = "move_right";
+    pub const LOOK_X: &'static str = "look_x";
+    pub const LOOK_Y: &'static str = "look_y";
+    pub const ZOOM: &'static str = "zoom";
+    pub const ZOOM_IN: &'static str = "zoom_in";
+    pub const ZOOM_OUT: &'static str = "zoom_out";
+
+    /// Degrees per second the camera turns with a gamepad stick fully pushed.
+    const GAMEPAD_LOOK_SPEED: f32 = 120.0;
+    /// Degrees per second the field of view changes with a trigger fully
+    /// pressed.
+    const GAMEPAD_ZOOM_SPEED: f32 = 30.0;
+
     #[allow(dead_code)]
     pub fn new() -> Self {
         Self {
             move_speed: 4.0,
-            look_speed: 4.0,
-            move_forward: false,
-            move_backward: false,
-            move_left: false,
-            move_right: false,
-            mouse_delta: None,
+            look_speed: 0.07,
+            input: Self::default_input_map(),
             pitch_deg: 0.0,
             yaw_deg: -90.0,
-            scroll_wheel_delta: None,
             fov_y: 45.0,
+            min_fov: 1.0,
+            max_fov: 60.0,
+            mouse_sensitivity: 1.0,
+            invert_y: false,
+            smoothing_half_life: None,
+            goal: None,
         }
     }
-}
 
-impl CameraController for FreeLookCameraController {
-    fn process_input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            // Keyboard input.
-            WindowEvent::KeyboardInput {
-                event: keyboard_input_event,
-                ..
-            } => {
-                // Is the button pushed down or no longer down?
-                let is_pressed = keyboard_input_event.state == ElementState::Pressed;
-
-                match keyboard_input_event.physical_key {
-                    PhysicalKey::Code(KeyCode::ArrowUp) | PhysicalKey::Code(KeyCode::KeyW) => {
-                        self.move_forward = is_pressed;
-                        true
-                    }
-                    PhysicalKey::Code(KeyCode::ArrowDown) | PhysicalKey::Code(KeyCode::KeyS) => {
-                        self.move_backward = is_pressed;
-                        true
-                    }
-                    PhysicalKey::Code(KeyCode::ArrowLeft) | PhysicalKey::Code(KeyCode::KeyA) => {
-                        self.move_left = is_pressed;
-                        true
-                    }
-                    PhysicalKey::Code(KeyCode::ArrowRight) | PhysicalKey::Code(KeyCode::KeyD) => {
-                        self.move_right = is_pressed;
-                        true
-                    }
-                    _ => false,
-                }
-            }
-            _ => false,
-        }
+    /// Get the bindings for moving with WASD, the arrow keys or the left
+    /// gamepad stick, looking with the mouse or the right stick, and zooming
+    /// with the scroll wheel or the triggers.
+    pub fn default_input_map() -> InputMap {
+        InputMap::new()
+            .with_binding(Self::MOVE_FORWARD, Binding::Key(KeyCode::KeyW))
+            .with_binding(Self::MOVE_FORWARD, Binding::Key(KeyCode::ArrowUp))
+            .with_binding(Self::MOVE_BACKWARD, Binding::Key(KeyCode::KeyS))
+            .with_binding(Self::MOVE_BACKWARD, Binding::Key(KeyCode::ArrowDown))
+            .with_binding(Self::MOVE_LEFT, Binding::Key(KeyCode::KeyA))
+            .with_binding(Self::MOVE_LEFT, Binding::Key(KeyCode::ArrowLeft))
+            .with_binding(Self::MOVE_RIGHT, Binding::Key(KeyCode::KeyD))
+            .with_binding(Self::MOVE_RIGHT, Binding::Key(KeyCode::ArrowRight))
+            .with_binding(Self::LOOK_X, Binding::Axis(MouseAxis::MotionX))
+            .with_binding(Self::LOOK_Y, Binding::Axis(MouseAxis::MotionY))
+            .with_binding(Self::ZOOM, Binding::Axis(MouseAxis::WheelX))
+            .with_binding(
+                Self::MOVE_FORWARD,
+                Binding::GamepadAxis(GamepadAxis::LeftStickY),
+            )
+            .with_binding(
+                Self::MOVE_RIGHT,
+                Binding::GamepadAxis(GamepadAxis::LeftStickX),
+            )
+            .with_binding(Self::LOOK_X, Binding::GamepadAxis(GamepadAxis::RightStickX))
+            .with_binding(Self::LOOK_Y, Binding::GamepadAxis(GamepadAxis::RightStickY))
+            .with_binding(
+                Self::ZOOM_IN,
+                Binding::GamepadAxis(GamepadAxis::RightTrigger),
+            )
+            .with_binding(
+                Self::ZOOM_OUT,
+                Binding::GamepadAxis(GamepadAxis::LeftTrigger),
+            )
     }
 
-    fn process_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
-        self.mouse_delta =
-            Some(self.mouse_delta.unwrap_or_default() + Vec2::new(delta_x as f32, delta_y as f32));
+    /// Get the bindings used by this controller.
+    pub fn input_map(&self) -> &InputMap {
+        &self.input
     }
 
-    fn process_mouse_wheel(&mut self, delta_x: f64, _delta_y: f64) {
-        self.scroll_wheel_delta =
-            Some(self.scroll_wheel_delta.unwrap_or_default() + delta_x as f32);
+    /// Get the bindings used by this controller so they can be changed.
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input
     }
 
-    fn update_camera(&mut self, camera: &mut Camera, delta: Duration) {
+    /// Get the amount mouse motion is scaled by before it rotates the camera.
+    pub fn mouse_sensitivity(&self) -> f32 {
+        self.mouse_sensitivity
+    }
+
+    /// Set the amount mouse motion is scaled by before it rotates the camera.
+    pub fn set_mouse_sensitivity(&mut self, mouse_sensitivity: f32) {
+        self.mouse_sensitivity = mouse_sensitivity;
+    }
+
+    /// Ease the camera towards where input moves it rather than moving it
+    /// immediately. The camera covers half of the remaining distance every
+    /// `half_life`. Pass `None` to turn smoothing off.
+    pub fn set_smoothing(&mut self, half_life: Option<Duration>) {
+        self.smoothing_half_life = half_life;
+        self.goal = None;
+    }
+
+    /// Get the smoothing half life, or `None` if smoothing is off.
+    #[allow(dead_code)]
+    pub fn smoothing(&self) -> Option<Duration> {
+        self.smoothing_half_life
+    }
+
+    /// Returns true if vertical mouse look is inverted.
+    pub fn invert_y(&self) -> bool {
+        self.invert_y
+    }
+
+    /// Invert vertical mouse look when `invert_y` is true.
+    pub fn set_invert_y(&mut self, invert_y: bool) {
+        self.invert_y = invert_y;
+    }
+
+    /// Get the movement speed in world units per second.
+    pub fn move_speed(&self) -> f32 {
+        self.move_speed
+    }
+
+    /// Set the movement speed in world units per second.
+    pub fn set_move_speed(&mut self, move_speed: f32) {
+        self.move_speed = move_speed;
+    }
+
+    /// Get the narrowest and widest vertical field of view in degrees that
+    /// zooming can reach.
+    pub fn fov_range(&self) -> (f32, f32) {
+        (self.min_fov, self.max_fov)
+    }
+
+    /// Set the narrowest and widest vertical field of view in degrees that
+    /// zooming can reach. The widest field of view can be at most 179 degrees.
+    /// The range is left unchanged if it is not valid.
+    pub fn set_fov_range(&mut self, min_fov: f32, max_fov: f32) -> Result<(), InvalidFovRange> {
+        if !(0.0 < min_fov && min_fov <= max_fov && max_fov <= Camera::MAX_FOV_Y.to_degrees()) {
+            return Err(InvalidFovRange(min_fov, max_fov));
+        }
+
+        self.min_fov = min_fov;
+        self.max_fov = max_fov;
+        self.fov_y = self.fov_y.clamp(min_fov, max_fov);
+
+        Ok(())
+    }
+
+    /// Move `camera` to where input since the last update takes it.
+    fn apply_input(&mut self, camera: &mut Camera, delta: Duration) {
         let mut camera_pos = camera.eye();
         let delta_secs = delta.as_secs_f32();
         let move_speed = self.move_speed * delta_secs;
 
-        // Respond to keyboard forward/backward/left/right movement.
-        if self.move_forward {
-            camera_pos += move_speed * camera.forward();
-        }
+        // Respond to forward/backward/left/right movement. Gamepad sticks
+        // move slower when partially pushed.
+        let forward =
+            self.input.analog(Self::MOVE_FORWARD) - self.input.analog(Self::MOVE_BACKWARD);
+        let right = self.input.analog(Self::MOVE_RIGHT) - self.input.analog(Self::MOVE_LEFT);
 
-        if self.move_backward {
-            camera_pos -= move_speed * camera.forward();
-        }
+        camera_pos += move_speed * (forward * camera.forward() + right * camera.right());
 
-        if self.move_left {
-            camera_pos -= move_speed * (Vec3::cross(camera.forward(), camera.up()));
-        }
+        // Handle mouse look.
+        let mouse_delta = apply_mouse_look_options(
+            Vec2::new(self.input.axis(Self::LOOK_X), self.input.axis(Self::LOOK_Y)),
+            self.mouse_sensitivity,
+            self.invert_y,
+        );
 
-        if self.move_right {
-            camera_pos += move_speed * (Vec3::cross(camera.forward(), camera.up()));
-        }
+        self.yaw_deg += self.look_speed * mouse_delta.x;
+        self.pitch_deg -= self.look_speed * mouse_delta.y;
 
-        // Handle mouse look.
-        let look_speed = self.look_speed * delta_secs;
-        self.yaw_deg += look_speed * self.mouse_delta.unwrap_or_default().x;
-        self.pitch_deg -= look_speed * self.mouse_delta.unwrap_or_default().y;
-
-        if self.yaw_deg > 89.0 {
-            self.yaw_deg = 89.0;
-        } else if self.yaw_deg < -90.0 {
-            self.yaw_deg = -89.0;
-        }
+        // Gamepad sticks turn the camera at a rate rather than by an amount,
+        // and pushing up looks up.
+        let look_speed = Self::GAMEPAD_LOOK_SPEED * delta_secs;
+        let invert_y = if self.invert_y { -1.0 } else { 1.0 };
+
+        self.yaw_deg += look_speed * self.input.analog(Self::LOOK_X);
+        self.pitch_deg += look_speed * self.input.analog(Self::LOOK_Y) * invert_y;
+
+        // Looking straight up or down makes the look direction parallel with
+        // the up vector, so stop just short of it.
+        self.pitch_deg = self.pitch_deg.clamp(-89.0, 89.0);
 
         let yaw = self.yaw_deg.to_radians();
         let pitch = self.pitch_deg.to_radians();
@@ -163,116 +273,277 @@ impl CameraController for FreeLookCameraController {
         camera.reorient(camera_pos, camera_pos + look_dir);
 
         // Handle zoom in/out by adjusting the field of view.
-        // TODO: Add speed modifier and adjust by time delta.
-        self.fov_y += self.scroll_wheel_delta.unwrap_or_default();
+        // TODO: Add speed modifier.
+        self.fov_y += self.input.axis(Self::ZOOM);
+        self.fov_y += (self.input.analog(Self::ZOOM_OUT) - self.input.analog(Self::ZOOM_IN))
+            * Self::GAMEPAD_ZOOM_SPEED
+            * delta_secs;
 
-        self.fov_y = self.fov_y.clamp(1.0, 60.0);
+        self.fov_y = self.fov_y.clamp(self.min_fov, self.max_fov);
 
         camera.set_fov_y(self.fov_y.to_radians());
 
         // Reset mouse state.
-        self.mouse_delta = None;
-        self.scroll_wheel_delta = None;
+        self.input.end_frame();
+    }
+}
+
+impl CameraController for FreeLookCameraController {
+    fn process_input(&mut self, event: &WindowEvent) -> bool {
+        self.input.process_input(event)
+    }
+
+    fn process_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.input.process_mouse_motion(delta_x, delta_y);
+    }
+
+    fn process_mouse_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.input.process_mouse_wheel(delta_x, delta_y);
+    }
+
+    fn process_gamepad(&mut self, event: &GamepadEvent) -> bool {
+        self.input.process_gamepad(event)
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, delta: Duration) {
+        let Some(half_life) = self.smoothing_half_life else {
+            self.apply_input(camera, delta);
+            return;
+        };
+
+        let mut goal = SmoothedGoal::resume(self.goal.take(), camera);
+        self.apply_input(&mut goal, delta);
+
+        let t = smoothing_factor(half_life, delta);
+        let eye = camera.eye().lerp(goal.eye(), t);
+        let orientation = camera.orientation().slerp(goal.orientation(), t);
+
+        camera.set_orientation(eye, orientation);
+        camera.set_fov_y(camera.fov_y() + (goal.fov_y() - camera.fov_y()) * t);
+
+        self.goal = Some(SmoothedGoal::new(&goal, camera));
     }
 }
 
 /// Experimental arc-ball camera controller. This controller uses the camera's
-/// target as the pivot point, and allows both rotation and zooming. Zooming is
-/// accomplished with the mouse wheel. Rotation is done by holding the mouse
-/// button down and panning in the direction you wish to rotate.
+/// target as the pivot point, and allows rotation, panning and zooming. Zooming
+/// is accomplished with the mouse wheel. Rotation is done by holding the left
+/// mouse button down and moving in the direction you wish to rotate. Panning
+/// moves the pivot and is done by dragging with the middle mouse button, or
+/// with the left mouse button while shift is held.
 pub struct ArcballCameraController {
-    /// Horizontal panning speed modifier.
+    /// Horizontal rotation speed modifier. Mouse motion is already accumulated
+    /// over the frame so this is not scaled by the frame time.
     horizontal_speed: f32,
-    /// Vertical panning speed modifier.
+    /// Vertical rotation speed modifier.
     vertical_speed: f32,
-    /// Allows mouse motion to contribute to the camera controller when set to
-    /// true, otherwise mouse motion is ignored.
-    allow_mouse_look: bool,
+    /// Maps mouse buttons and keys to orbiting and panning.
+    input: InputMap,
+    /// True if the pan modifier was held when orbiting started, which makes
+    /// the orbit button pan instead until it is released.
+    orbit_pans: bool,
     /// Amount of mouse motion this frame encoded as a delta from the last call
     /// to update.
     mouse_motion: Option<Vec2>,
+    /// Amount of mouse motion used for panning since the last call to update.
+    mouse_pan: Option<Vec2>,
+    /// Pan speed modifier. At 1.0 the pivot moves at the same rate as the
+    /// mouse cursor.
+    pan_speed: f32,
     /// The amount of scroll units that the mouse has moved since the last call
     /// to update.
     mouse_scroll: Option<Vec2>,
     /// A direction modifier to apply to mouse scroll actions. This value should
     /// be 1.0 or -1.0.
     scroll_direction_modifier: f32,
-    /// Adjusts the mouse wheel scroll speed by the given amount.
+    /// World units moved toward or away from the target per scroll unit.
     scroll_speed_modifier: f32,
     /// Minimum view distance from target.
     min_distance: f32,
     /// Maximum view distance from target.
     max_distance: Option<f32>,
+    /// Scales mouse motion before it is applied as camera rotation.
+    mouse_sensitivity: f32,
+    /// Flips the direction of vertical rotation when true.
+    invert_y: bool,
+    /// Time taken for the camera to cover half of the remaining distance to
+    /// where input moved it, or `None` to move the camera immediately.
+    smoothing_half_life: Option<Duration>,
+    /// Where input has moved the camera to when smoothing is enabled.
+    goal: Option<SmoothedGoal>,
 }
 
 impl ArcballCameraController {
+    pub const ORBIT: &'static str = "orbit";
+    pub const PAN: &'static str = "pan";
+    pub const PAN_MODIFIER: &'static str = "pan_modifier";
+    pub const ORBIT_X: &'static str = "orbit_x";
+    pub const ORBIT_Y: &'static str = "orbit_y";
+    pub const PAN_X: &'static str = "pan_x";
+    pub const PAN_Y: &'static str = "pan_y";
+    pub const ZOOM_IN: &'static str = "zoom_in";
+    pub const ZOOM_OUT: &'static str = "zoom_out";
+
+    /// Pixels per second of mouse motion that a fully pushed gamepad stick
+    /// orbits or pans the camera by.
+    const GAMEPAD_MOUSE_SPEED: f32 = 500.0;
+    /// Scroll units per second that a fully pressed trigger zooms by.
+    const GAMEPAD_SCROLL_SPEED: f32 = 10.0;
+
     /// Create a new camera controller that lets users pan and zoom on a pivot
     /// point.
     pub fn new() -> Self {
         Self {
-            horizontal_speed: 25.0,
-            vertical_speed: 25.0,
-            allow_mouse_look: false,
+            horizontal_speed: 0.4,
+            vertical_speed: 0.4,
+            input: Self::default_input_map(),
+            orbit_pans: false,
             mouse_motion: None,
+            mouse_pan: None,
+            pan_speed: 1.0,
             mouse_scroll: None,
             scroll_direction_modifier: -1.0,
-            scroll_speed_modifier: 25.0,
+            scroll_speed_modifier: 0.4,
             min_distance: 1.0,
             max_distance: Some(20.0),
+            mouse_sensitivity: 1.0,
+            invert_y: false,
+            smoothing_half_life: None,
+            goal: None,
         }
     }
-}
 
-impl CameraController for ArcballCameraController {
-    fn process_input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            // Capture mouse input.
-            WindowEvent::MouseInput {
-                button: winit::event::MouseButton::Left,
-                state,
-                ..
-            } => {
-                self.allow_mouse_look = state == &ElementState::Pressed;
-                true
-            }
-            _ => false,
-        }
+    /// Get the bindings for orbiting with the left mouse button or the right
+    /// gamepad stick, panning with the middle mouse button, with shift and the
+    /// left mouse button or with the left stick, and zooming with the
+    /// triggers.
+    pub fn default_input_map() -> InputMap {
+        InputMap::new()
+            .with_binding(Self::ORBIT, Binding::Mouse(MouseButton::Left))
+            .with_binding(Self::PAN, Binding::Mouse(MouseButton::Middle))
+            .with_binding(Self::PAN_MODIFIER, Binding::Key(KeyCode::ShiftLeft))
+            .with_binding(Self::PAN_MODIFIER, Binding::Key(KeyCode::ShiftRight))
+            .with_binding(
+                Self::ORBIT_X,
+                Binding::GamepadAxis(GamepadAxis::RightStickX),
+            )
+            .with_binding(
+                Self::ORBIT_Y,
+                Binding::GamepadAxis(GamepadAxis::RightStickY),
+            )
+            .with_binding(Self::PAN_X, Binding::GamepadAxis(GamepadAxis::LeftStickX))
+            .with_binding(Self::PAN_Y, Binding::GamepadAxis(GamepadAxis::LeftStickY))
+            .with_binding(
+                Self::ZOOM_IN,
+                Binding::GamepadAxis(GamepadAxis::RightTrigger),
+            )
+            .with_binding(
+                Self::ZOOM_OUT,
+                Binding::GamepadAxis(GamepadAxis::LeftTrigger),
+            )
     }
 
-    fn process_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
-        if self.allow_mouse_look {
-            self.mouse_motion = Some(
-                self.mouse_motion.unwrap_or_default() + Vec2::new(delta_x as f32, delta_y as f32),
-            );
+    /// Add gamepad stick and trigger positions to the mouse input accumulated
+    /// since the last update, as the mouse motion that would move the camera
+    /// the same amount over `delta`.
+    fn apply_gamepad(&mut self, delta: Duration) {
+        let mouse_speed = Self::GAMEPAD_MOUSE_SPEED * delta.as_secs_f32();
+
+        // Pushing a stick up raises the camera, which is the same as dragging
+        // the mouse up when orbiting but down when panning.
+        let orbit = Vec2::new(
+            self.input.analog(Self::ORBIT_X),
+            -self.input.analog(Self::ORBIT_Y),
+        );
+        let pan = Vec2::new(
+            -self.input.analog(Self::PAN_X),
+            self.input.analog(Self::PAN_Y),
+        );
+        let zoom = self.input.analog(Self::ZOOM_IN) - self.input.analog(Self::ZOOM_OUT);
+
+        if orbit != Vec2::ZERO {
+            self.mouse_motion = Some(self.mouse_motion.unwrap_or_default() + orbit * mouse_speed);
+        }
+
+        if pan != Vec2::ZERO {
+            self.mouse_pan = Some(self.mouse_pan.unwrap_or_default() + pan * mouse_speed);
+        }
+
+        if zoom != 0.0 {
+            let scroll = Vec2::new(zoom * Self::GAMEPAD_SCROLL_SPEED * delta.as_secs_f32(), 0.0);
+            self.mouse_scroll = Some(self.mouse_scroll.unwrap_or_default() + scroll);
         }
     }
 
-    fn process_mouse_wheel(&mut self, delta_x: f64, delta_y: f64) {
-        self.mouse_scroll =
-            Some(self.mouse_scroll.unwrap_or_default() + Vec2::new(delta_x as f32, delta_y as f32));
+    /// Get the bindings used by this controller.
+    pub fn input_map(&self) -> &InputMap {
+        &self.input
     }
 
-    fn update_camera(&mut self, camera: &mut Camera, delta: Duration) {
-        let pivot = camera.target();
-        let delta_secs = delta.as_secs_f32();
+    /// Get the bindings used by this controller so they can be changed.
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input
+    }
+
+    /// Set the amount mouse motion is scaled by before it rotates the camera.
+    pub fn set_mouse_sensitivity(&mut self, mouse_sensitivity: f32) {
+        self.mouse_sensitivity = mouse_sensitivity;
+    }
+
+    /// Ease the camera towards where input moves it rather than moving it
+    /// immediately. The camera covers half of the remaining distance every
+    /// `half_life`, and orbits around the pivot rather than cutting across.
+    /// Pass `None` to turn smoothing off.
+    pub fn set_smoothing(&mut self, half_life: Option<Duration>) {
+        self.smoothing_half_life = half_life;
+        self.goal = None;
+    }
+
+    /// Get the smoothing half life, or `None` if smoothing is off.
+    pub fn smoothing(&self) -> Option<Duration> {
+        self.smoothing_half_life
+    }
+
+    /// Invert vertical rotation when `invert_y` is true.
+    pub fn set_invert_y(&mut self, invert_y: bool) {
+        self.invert_y = invert_y;
+    }
+
+    /// Move `camera` to where input since the last update takes it.
+    fn apply_input(&mut self, camera: &mut Camera) {
+        // All arcball movement is driven by mouse input that has been
+        // accumulated since the last update, so none of it is scaled by the
+        // frame time.
+
+        // Move the eye and pivot along the camera's right and up axes. The
+        // amount of world space covered by one pixel grows with the distance to
+        // the pivot, so scale by the view height at the pivot's distance.
+        let pan_offset = self.mouse_pan.map_or(Vec3::ZERO, |pan| {
+            let pixel_size = camera.view_height_at((camera.eye() - camera.target()).length())
+                / camera.viewport_height();
+            (camera.up() * pan.y - camera.right() * pan.x) * pixel_size * self.pan_speed
+        });
+
+        let pivot = camera.target() + pan_offset;
+        let eye = camera.eye() + pan_offset;
 
         // Convert the mouse motion to an amount of rotation. The height of the
         // viewport is 180 degrees, and the width of the viewport is 360 degrees.
         let x_view_angles = 2.0 * std::f32::consts::PI / camera.viewport_width();
         let y_view_angles = std::f32::consts::PI / camera.viewport_height();
 
-        let x_angle = self.mouse_motion.unwrap_or_default().x
-            * x_view_angles
-            * self.horizontal_speed
-            * delta_secs;
-        let y_angle = self.mouse_motion.unwrap_or_default().y
-            * y_view_angles
-            * self.vertical_speed
-            * delta_secs;
+        let mouse_motion = apply_mouse_look_options(
+            self.mouse_motion.unwrap_or_default(),
+            self.mouse_sensitivity,
+            self.invert_y,
+        );
+
+        let x_angle = mouse_motion.x * x_view_angles * self.horizontal_speed;
+        let y_angle = mouse_motion.y * y_view_angles * self.vertical_speed;
 
         // Rotate camera around the Y axis. (horizontal mouse movement).
         let x_rotation = Quat::from_axis_angle(camera.up(), x_angle);
-        let camera_pos_1 = x_rotation * (camera.eye() - pivot) + pivot;
+        let camera_pos_1 = x_rotation * (eye - pivot) + pivot;
 
         // Regenerate the forward and right vectors after moving the camera.
         let forward = pivot - camera_pos_1;
@@ -298,20 +569,19 @@ impl CameraController for ArcballCameraController {
         // Move closer or further away from the target if requested by input.
         let scroll_amount =
             self.mouse_scroll.unwrap_or_default().x * self.scroll_direction_modifier;
-        let camera_pos =
-            camera_pos - forward * scroll_amount * self.scroll_speed_modifier * delta_secs;
+        let camera_pos = camera_pos - forward * scroll_amount * self.scroll_speed_modifier;
 
         // Don't scroll too close or too far from the target.
         let pivot_to_camera = camera_pos - pivot;
         let distance = pivot_to_camera.length();
 
         let camera_pos = if distance <= self.min_distance {
-            pivot_to_camera.normalize() * self.min_distance
+            pivot + pivot_to_camera.normalize() * self.min_distance
         } else if self
             .max_distance
             .map_or_else(|| false, |max_distance| distance >= max_distance)
         {
-            pivot_to_camera.normalize() * self.max_distance.unwrap()
+            pivot + pivot_to_camera.normalize() * self.max_distance.unwrap()
         } else {
             camera_pos
         };
@@ -321,6 +591,787 @@ impl CameraController for ArcballCameraController {
 
         // Reset update state.
         self.mouse_motion = None;
+        self.mouse_pan = None;
         self.mouse_scroll = None;
     }
+
+    /// Get the pan speed modifier.
+    pub fn pan_speed(&self) -> f32 {
+        self.pan_speed
+    }
+
+    /// Set the pan speed modifier.
+    pub fn set_pan_speed(&mut self, pan_speed: f32) {
+        self.pan_speed = pan_speed;
+    }
+
+    /// Set how many world units the camera moves towards or away from the
+    /// pivot per scroll unit.
+    pub fn set_scroll_speed(&mut self, scroll_speed: f32) {
+        self.scroll_speed_modifier = scroll_speed;
+    }
+
+    /// Set how close the camera can zoom in to the pivot, and how far it can
+    /// zoom out. `None` lets the camera zoom out without limit. The range is
+    /// left unchanged if it is not valid.
+    pub fn set_distance_range(
+        &mut self,
+        min_distance: f32,
+        max_distance: Option<f32>,
+    ) -> Result<(), InvalidDistanceRange> {
+        if !(0.0 <= min_distance && max_distance.is_none_or(|max| min_distance <= max)) {
+            return Err(InvalidDistanceRange(min_distance, max_distance));
+        }
+
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+
+        Ok(())
+    }
+}
+
+impl CameraController for ArcballCameraController {
+    fn process_input(&mut self, event: &WindowEvent) -> bool {
+        let was_orbiting = self.input.is_pressed(Self::ORBIT);
+        let used = self.input.process_input(event);
+
+        // Orbiting while the pan modifier is held pans until the orbit button
+        // is released.
+        if self.input.is_pressed(Self::ORBIT) && !was_orbiting {
+            self.orbit_pans = self.input.is_pressed(Self::PAN_MODIFIER);
+        }
+
+        used
+    }
+
+    fn process_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        let orbiting = self.input.is_pressed(Self::ORBIT);
+
+        if self.input.is_pressed(Self::PAN) || (orbiting && self.orbit_pans) {
+            self.mouse_pan = Some(
+                self.mouse_pan.unwrap_or_default() + Vec2::new(delta_x as f32, delta_y as f32),
+            );
+        } else if orbiting {
+            self.mouse_motion = Some(
+                self.mouse_motion.unwrap_or_default() + Vec2::new(delta_x as f32, delta_y as f32),
+            );
+        }
+    }
+
+    fn process_mouse_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.mouse_scroll =
+            Some(self.mouse_scroll.unwrap_or_default() + Vec2::new(delta_x as f32, delta_y as f32));
+    }
+
+    fn process_gamepad(&mut self, event: &GamepadEvent) -> bool {
+        self.input.process_gamepad(event)
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, delta: Duration) {
+        let Some(half_life) = self.smoothing_half_life else {
+            self.apply_gamepad(delta);
+            self.apply_input(camera);
+            return;
+        };
+
+        let mut goal = SmoothedGoal::resume(self.goal.take(), camera);
+        self.apply_gamepad(delta);
+        self.apply_input(&mut goal);
+
+        // Interpolate the pivot, the distance to it and the rotation around it
+        // separately so the camera swings around the pivot.
+        let t = smoothing_factor(half_life, delta);
+        let pivot = camera.target().lerp(goal.target(), t);
+        let distance = camera.eye().distance(camera.target());
+        let distance = distance + (goal.eye().distance(goal.target()) - distance) * t;
+        let orientation = camera.orientation().slerp(goal.orientation(), t);
+
+        camera.reorient(pivot - orientation * Vec3::NEG_Z * distance, pivot);
+
+        self.goal = Some(SmoothedGoal::new(&goal, camera));
+    }
+}
+
+/// A third person camera that stays behind and above a moving target, such as
+/// the player's character. The camera eases towards its place behind the
+/// target rather than moving with it rigidly, and the mouse wheel zooms in and
+/// out.
+///
+/// Call `track` each frame before `update_camera` to follow an entity in a
+/// scene. Models between the target and the camera pull the camera in front
+/// of them so the target is never hidden.
+pub struct FollowCameraController {
+    /// World transform of the followed target. The camera stays behind the
+    /// target's forward (-Z) axis.
+    target: Mat4,
+    /// Horizontal distance from the target to the camera.
+    distance: f32,
+    /// Closest that zooming can bring the camera to the target.
+    min_distance: f32,
+    /// Farthest that zooming can take the camera from the target.
+    max_distance: f32,
+    /// Height of the camera above the target's origin.
+    height: f32,
+    /// Height above the target's origin that the camera looks at.
+    look_height: f32,
+    /// How quickly the camera catches up to where it should be, as the
+    /// fraction of the remaining distance per second on a log scale. Larger
+    /// values follow more tightly, and infinity follows rigidly.
+    damping: f32,
+    /// World units moved toward or away from the target per scroll unit.
+    zoom_speed: f32,
+    /// Space kept between the camera and models that block its view.
+    collision_margin: f32,
+    /// Distance from the point the camera looks at to the closest model
+    /// between it and the camera, as of the last call to `track`.
+    obstruction: Option<f32>,
+    /// The amount of scroll units that the mouse has moved since the last call
+    /// to update.
+    mouse_scroll: Option<f32>,
+    /// Moves the camera straight to its place behind the target on the next
+    /// update rather than easing towards it.
+    snap: bool,
+}
+
+impl FollowCameraController {
+    /// Closest the camera gets to the point it looks at when it is pulled in
+    /// front of a model.
+    const MIN_OBSTRUCTED_DISTANCE: f32 = 0.1;
+
+    /// Create a new camera controller that follows a target at the origin.
+    pub fn new() -> Self {
+        Self {
+            target: Mat4::IDENTITY,
+            distance: 6.0,
+            min_distance: 2.0,
+            max_distance: 20.0,
+            height: 2.5,
+            look_height: 1.0,
+            damping: 6.0,
+            zoom_speed: 0.4,
+            collision_margin: 0.2,
+            obstruction: None,
+            mouse_scroll: None,
+            snap: true,
+        }
+    }
+
+    /// Set the horizontal distance from the target to the camera, and the
+    /// range that zooming can change it in. The minimum distance must be
+    /// larger than zero, and the distances are left unchanged if the range is
+    /// not valid.
+    pub fn set_distance(
+        &mut self,
+        distance: f32,
+        min_distance: f32,
+        max_distance: f32,
+    ) -> Result<(), InvalidDistanceRange> {
+        if !(0.0 < min_distance && min_distance <= max_distance) {
+            return Err(InvalidDistanceRange(min_distance, Some(max_distance)));
+        }
+
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self.distance = distance.clamp(min_distance, max_distance);
+
+        Ok(())
+    }
+
+    /// Set the height of the camera, and of the point it looks at, above the
+    /// target's origin.
+    pub fn set_height(&mut self, height: f32, look_height: f32) {
+        self.height = height;
+        self.look_height = look_height;
+    }
+
+    /// Set how quickly the camera catches up to the target. Zero leaves the
+    /// camera where it is, and `f32::INFINITY` follows the target rigidly.
+    #[allow(dead_code)]
+    pub fn set_damping(&mut self, damping: f32) {
+        assert!(damping >= 0.0);
+        self.damping = damping;
+    }
+
+    /// Set the world transform of the target to follow.
+    #[allow(dead_code)]
+    pub fn set_target(&mut self, target: Mat4) {
+        self.target = target;
+    }
+
+    /// Move the camera straight to its place behind the target on the next
+    /// update, eg after the target teleports.
+    pub fn snap(&mut self) {
+        self.snap = true;
+    }
+
+    /// Follow entity `target` in `scene`, and find the closest model between
+    /// the target and where the camera should be. The target, and any entity
+    /// attached to it, do not block the camera.
+    pub fn track(&mut self, scene: &Scene, target: EntityId) {
+        let Some(world) = scene.world_transform(target) else {
+            return;
+        };
+
+        self.target = world;
+
+        let (eye, look_at) = self.desired_eye_and_look_at(scene.camera.world_up(), None);
+        let ray = Ray::new(look_at, (eye - look_at).normalize_or_zero());
+        let is_target = |mut id: EntityId| loop {
+            if id == target {
+                break true;
+            }
+
+            match scene.entity(id).and_then(|entity| entity.parent()) {
+                Some(parent) => id = parent,
+                None => break false,
+            }
+        };
+
+        self.obstruction = scene
+            .pick_triangles_filtered(&ray, |id| !is_target(id))
+            .map(|hit| hit.distance)
+            .filter(|distance| *distance < look_at.distance(eye));
+    }
+
+    /// Get where the camera should be and the point it should look at. The
+    /// camera stays behind the target's forward axis, or behind `fallback`
+    /// when the target faces straight up or down.
+    fn desired_eye_and_look_at(&self, world_up: Vec3, fallback: Option<Vec3>) -> (Vec3, Vec3) {
+        let origin = self.target.transform_point3(Vec3::ZERO);
+        let forward = self.target.transform_vector3(Vec3::NEG_Z);
+        let flat_forward = forward - world_up * forward.dot(world_up);
+
+        let behind = (-flat_forward)
+            .try_normalize()
+            .or(fallback)
+            .unwrap_or_else(|| world_up.any_orthonormal_vector());
+
+        (
+            origin + behind * self.distance + world_up * self.height,
+            origin + world_up * self.look_height,
+        )
+    }
+}
+
+impl CameraController for FollowCameraController {
+    fn process_input(&mut self, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    fn process_mouse_motion(&mut self, _delta_x: f64, _delta_y: f64) {}
+
+    fn process_mouse_wheel(&mut self, delta_x: f64, _delta_y: f64) {
+        self.mouse_scroll = Some(self.mouse_scroll.unwrap_or_default() + delta_x as f32);
+    }
+
+    fn process_gamepad(&mut self, _event: &GamepadEvent) -> bool {
+        false
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, delta: Duration) {
+        // Scrolling up zooms in, like the arcball camera.
+        if let Some(scroll) = self.mouse_scroll.take() {
+            self.distance = (self.distance - scroll * self.zoom_speed)
+                .clamp(self.min_distance, self.max_distance);
+        }
+
+        let world_up = camera.world_up();
+        let current_behind = camera.eye() - camera.target();
+        let (desired_eye, look_at) = self.desired_eye_and_look_at(
+            world_up,
+            (current_behind - world_up * current_behind.dot(world_up)).try_normalize(),
+        );
+
+        // Ease towards the desired position by the same fraction of the
+        // remaining distance every second, whatever the frame rate.
+        let eye = if self.snap {
+            self.snap = false;
+            desired_eye
+        } else {
+            let t = 1.0 - f32::exp(-self.damping * delta.as_secs_f32());
+            camera.eye().lerp(desired_eye, t)
+        };
+
+        // Pull the camera in front of anything blocking the view right away
+        // rather than easing, so the target is never hidden.
+        let offset = eye - look_at;
+        let max_offset = self.obstruction.map_or(f32::INFINITY, |distance| {
+            (distance - self.collision_margin).max(Self::MIN_OBSTRUCTED_DISTANCE)
+        });
+
+        let eye = if offset.length() > max_offset {
+            look_at + offset.normalize() * max_offset
+        } else {
+            eye
+        };
+
+        if eye != look_at {
+            camera.reorient(eye, look_at);
+        }
+    }
+}
+
+/// Where a smoothed camera controller's input has moved the camera to, which
+/// the camera eases towards.
+struct SmoothedGoal {
+    eye: Vec3,
+    target: Vec3,
+    /// Where the camera was left by the last update. Anything else moving the
+    /// camera, such as another controller, replaces the goal.
+    left_at: (Vec3, Vec3),
+}
+
+impl SmoothedGoal {
+    /// Remember `goal` as where the camera is heading, after `camera` was
+    /// moved part of the way there.
+    fn new(goal: &Camera, camera: &Camera) -> Self {
+        Self {
+            eye: goal.eye(),
+            target: goal.target(),
+            left_at: (camera.eye(), camera.target()),
+        }
+    }
+
+    /// Get a copy of `camera` placed at `goal`, or at the camera itself if
+    /// there is no goal or the camera was moved since the goal was set.
+    fn resume(goal: Option<Self>, camera: &Camera) -> Camera {
+        let mut goal_camera = camera.clone();
+
+        if let Some(goal) = goal.filter(|goal| goal.left_at == (camera.eye(), camera.target())) {
+            goal_camera.reorient(goal.eye, goal.target);
+        }
+
+        goal_camera
+    }
+}
+
+/// Get the fraction of the remaining distance to cover in `delta` when half
+/// of it is covered every `half_life`. Covering the same fraction per unit of
+/// time makes the motion the same at any frame rate.
+fn smoothing_factor(half_life: Duration, delta: Duration) -> f32 {
+    if half_life.is_zero() {
+        1.0
+    } else {
+        1.0 - f32::powf(0.5, delta.as_secs_f32() / half_life.as_secs_f32())
+    }
+}
+
+/// Scale `mouse_delta` by `sensitivity`, and flip its vertical component when
+/// `invert_y` is true.
+fn apply_mouse_look_options(mouse_delta: Vec2, sensitivity: f32, invert_y: bool) -> Vec2 {
+    let y_direction = if invert_y { -1.0 } else { 1.0 };
+    Vec2::new(mouse_delta.x, mouse_delta.y * y_direction) * sensitivity
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("invalid camera distance range: min was {} and max was {:?}", .0, .1)]
+pub struct InvalidDistanceRange(f32, Option<f32>);
+
+#[derive(Debug, Error, PartialEq)]
+#[error("field of view range must satisfy 0 < min <= max <= 179 degrees but min was {0} and max was {1}")]
+pub struct InvalidFovRange(f32, f32);
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use winit::event::ElementState;
+
+    use super::*;
+    use crate::{
+        content::ContentManager,
+        renderer::{
+            materials::MaterialBuilder,
+            meshes::{builtin_mesh, BuiltinMesh},
+            models::Model,
+        },
+        test_support::{test_camera, test_renderer},
+    };
+
+    #[test]
+    fn invert_y_only_flips_vertical_mouse_look() {
+        let delta = Vec2::new(3.0, -2.0);
+
+        assert_eq!(delta, apply_mouse_look_options(delta, 1.0, false));
+        assert_eq!(
+            Vec2::new(6.0, 4.0),
+            apply_mouse_look_options(delta, 2.0, true)
+        );
+    }
+
+    /// Hold `key` down for `duration` and return where the camera ends up.
+    fn free_look_eye_after_key(key: KeyCode, duration: Duration) -> Vec3 {
+        let mut camera = test_camera(800, 600);
+        let mut controller = FreeLookCameraController::new();
+
+        assert!(controller
+            .input
+            .process_button(Binding::Key(key), ElementState::Pressed));
+        controller.update_camera(&mut camera, duration);
+
+        camera.eye()
+    }
+
+    #[test]
+    fn free_look_moves_along_camera_axes() {
+        let half_second = Duration::from_millis(500);
+        let start = test_camera(800, 600).eye();
+
+        // Moving for half a second at four units per second covers two units.
+        let moved = |eye: Vec3| eye - start;
+        assert!(moved(free_look_eye_after_key(KeyCode::KeyW, half_second))
+            .abs_diff_eq(Vec3::new(0.0, 0.0, -2.0), 1e-5));
+        assert!(moved(free_look_eye_after_key(KeyCode::KeyS, half_second))
+            .abs_diff_eq(Vec3::new(0.0, 0.0, 2.0), 1e-5));
+        assert!(moved(free_look_eye_after_key(KeyCode::KeyA, half_second))
+            .abs_diff_eq(Vec3::new(-2.0, 0.0, 0.0), 1e-5));
+        assert!(moved(free_look_eye_after_key(KeyCode::KeyD, half_second))
+            .abs_diff_eq(Vec3::new(2.0, 0.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn free_look_moves_slower_with_gamepad_stick_partially_pushed() {
+        let mut camera = test_camera(800, 600);
+        let mut controller = FreeLookCameraController::new();
+        controller.input.set_gamepad_dead_zone(0.0);
+
+        // Pushing the stick halfway up and all the way left moves forward at
+        // half speed and left at full speed.
+        controller.process_gamepad(&GamepadEvent::Axis(GamepadAxis::LeftStickY, 0.5));
+        controller.process_gamepad(&GamepadEvent::Axis(GamepadAxis::LeftStickX, -1.0));
+        controller.update_camera(&mut camera, Duration::from_millis(500));
+
+        assert!((camera.eye() - test_camera(800, 600).eye())
+            .abs_diff_eq(Vec3::new(-2.0, 0.0, -1.0), 1e-5));
+
+        // The stick keeps moving the camera until it is released.
+        controller.process_gamepad(&GamepadEvent::Axis(GamepadAxis::LeftStickX, 0.0));
+        controller.update_camera(&mut camera, Duration::from_millis(500));
+
+        assert!((camera.eye() - test_camera(800, 600).eye())
+            .abs_diff_eq(Vec3::new(-2.0, 0.0, -2.0), 1e-5));
+    }
+
+    #[test]
+    fn free_look_stops_moving_when_key_released() {
+        let mut camera = test_camera(800, 600);
+        let mut controller = FreeLookCameraController::new();
+        let key = Binding::Key(KeyCode::ArrowUp);
+
+        controller.input.process_button(key, ElementState::Pressed);
+        controller.input.process_button(key, ElementState::Released);
+        controller.update_camera(&mut camera, Duration::from_secs(1));
+
+        assert!(camera.eye().abs_diff_eq(test_camera(800, 600).eye(), 1e-5));
+    }
+
+    #[test]
+    fn free_look_zoom_is_clamped_to_fov_range() {
+        let mut camera = test_camera(800, 600);
+        let mut controller = FreeLookCameraController::new();
+        controller.set_fov_range(30.0, 120.0).unwrap();
+
+        let zoom = |controller: &mut FreeLookCameraController, camera: &mut Camera, amount| {
+            controller.process_mouse_wheel(amount, 0.0);
+            controller.update_camera(camera, Duration::ZERO);
+            camera.fov_y().to_degrees()
+        };
+
+        // Zooming out can go past the default 60 degree limit.
+        assert!((zoom(&mut controller, &mut camera, 50.0) - 95.0).abs() < 1e-3);
+        assert!((zoom(&mut controller, &mut camera, 500.0) - 120.0).abs() < 1e-3);
+        assert!((zoom(&mut controller, &mut camera, -500.0) - 30.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn free_look_fov_range_cannot_exceed_camera_limit() {
+        let mut controller = FreeLookCameraController::new();
+
+        assert_eq!(
+            Err(InvalidFovRange(30.0, 180.0)),
+            controller.set_fov_range(30.0, 180.0)
+        );
+        assert_eq!(
+            Err(InvalidFovRange(0.0, 60.0)),
+            controller.set_fov_range(0.0, 60.0)
+        );
+        assert_eq!(
+            Err(InvalidFovRange(90.0, 60.0)),
+            controller.set_fov_range(90.0, 60.0)
+        );
+        assert_eq!((1.0, 60.0), controller.fov_range());
+    }
+
+    #[test]
+    fn free_look_mouse_look_ignores_frame_time() {
+        let look = |delta: Duration| {
+            let mut camera = test_camera(800, 600);
+            let mut controller = FreeLookCameraController::new();
+
+            controller.process_mouse_motion(100.0, 0.0);
+            controller.update_camera(&mut camera, delta);
+            camera.forward()
+        };
+
+        assert!(look(Duration::from_millis(8)).abs_diff_eq(look(Duration::from_millis(33)), 1e-5));
+    }
+
+    #[test]
+    fn arcball_clamps_distance_to_target() {
+        let mut camera = test_camera(800, 600);
+        let mut controller = ArcballCameraController::new();
+
+        // Scroll towards the target, stopping short of passing through it.
+        controller.process_mouse_wheel(10.0, 0.0);
+        controller.update_camera(&mut camera, Duration::from_millis(16));
+
+        assert!((camera.eye().distance(camera.target()) - 1.0).abs() < 1e-5);
+
+        // Scroll far away from the target.
+        controller.process_mouse_wheel(-1000.0, 0.0);
+        controller.update_camera(&mut camera, Duration::from_millis(16));
+
+        assert!((camera.eye().distance(camera.target()) - 20.0).abs() < 1e-4);
+        assert_eq!(Vec3::ZERO, camera.target());
+    }
+
+    #[test]
+    fn arcball_rejects_invalid_distance_range() {
+        let mut controller = ArcballCameraController::new();
+
+        assert_eq!(
+            Err(InvalidDistanceRange(5.0, Some(2.0))),
+            controller.set_distance_range(5.0, Some(2.0))
+        );
+        assert_eq!(
+            Err(InvalidDistanceRange(-1.0, None)),
+            controller.set_distance_range(-1.0, None)
+        );
+        assert_eq!(
+            (1.0, Some(20.0)),
+            (controller.min_distance, controller.max_distance)
+        );
+
+        assert_eq!(Ok(()), controller.set_distance_range(2.0, None));
+        assert_eq!(
+            (2.0, None),
+            (controller.min_distance, controller.max_distance)
+        );
+    }
+
+    #[test]
+    fn follow_camera_rejects_invalid_distance_range() {
+        let mut controller = FollowCameraController::new();
+        controller.set_distance(4.0, 1.0, 10.0).unwrap();
+
+        assert_eq!(
+            Err(InvalidDistanceRange(5.0, Some(2.0))),
+            controller.set_distance(3.0, 5.0, 2.0)
+        );
+        assert_eq!(
+            Err(InvalidDistanceRange(0.0, Some(2.0))),
+            controller.set_distance(1.0, 0.0, 2.0)
+        );
+        assert_eq!(
+            (4.0, 1.0, 10.0),
+            (
+                controller.distance,
+                controller.min_distance,
+                controller.max_distance
+            )
+        );
+    }
+
+    #[test]
+    fn follow_camera_eases_behind_moving_target() {
+        let mut camera = test_camera(800, 600);
+        let mut controller = FollowCameraController::new();
+        controller.set_distance(4.0, 1.0, 10.0).unwrap();
+        controller.set_height(3.0, 1.0);
+
+        // The first update moves straight behind the target, which faces -z.
+        controller.update_camera(&mut camera, Duration::from_millis(16));
+        assert!(camera.eye().abs_diff_eq(Vec3::new(0.0, 3.0, 4.0), 1e-5));
+        assert!(camera.target().abs_diff_eq(Vec3::new(0.0, 1.0, 0.0), 1e-5));
+
+        // Turn the target to face +x and move it. The camera eases towards its
+        // new place behind the target, and catches up over time.
+        controller.set_target(Mat4::from_rotation_translation(
+            Quat::from_rotation_y(-std::f32::consts::FRAC_PI_2),
+            Vec3::new(10.0, 0.0, 0.0),
+        ));
+        let desired_eye = Vec3::new(6.0, 3.0, 0.0);
+
+        controller.update_camera(&mut camera, Duration::from_millis(16));
+        let first_distance = camera.eye().distance(desired_eye);
+        assert!(first_distance > 1.0);
+        assert!(camera.target().abs_diff_eq(Vec3::new(10.0, 1.0, 0.0), 1e-5));
+
+        for _ in 0..120 {
+            controller.update_camera(&mut camera, Duration::from_millis(16));
+        }
+
+        assert!(
+            camera.eye().abs_diff_eq(desired_eye, 1e-3),
+            "{}",
+            camera.eye()
+        );
+
+        // Zooming changes the distance, within its range.
+        controller.process_mouse_wheel(100.0, 0.0);
+        controller.snap();
+        controller.update_camera(&mut camera, Duration::from_millis(16));
+        assert!(camera.eye().abs_diff_eq(Vec3::new(9.0, 3.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn follow_camera_damping_ignores_frame_rate() {
+        let follow = |frames: u32| {
+            let mut camera = test_camera(800, 600);
+            let mut controller = FollowCameraController::new();
+            controller.update_camera(&mut camera, Duration::ZERO);
+            controller.set_target(Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0)));
+
+            for _ in 0..frames {
+                controller.update_camera(&mut camera, Duration::from_secs(1) / frames);
+            }
+
+            camera.eye()
+        };
+
+        assert!(follow(10).abs_diff_eq(follow(100), 1e-4));
+    }
+
+    #[test]
+    fn follow_camera_moves_in_front_of_obstacles() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+        let cube_at = |position| Model::new(cube.clone(), position, Quat::IDENTITY, Vec3::ONE);
+
+        let mut scene = Scene::new(test_camera(800, 600));
+        let player = scene.spawn_model(cube_at(Vec3::ZERO));
+        let hat = scene.spawn_model(cube_at(Vec3::new(0.0, 1.0, 0.0)));
+        scene.attach(hat, player).unwrap();
+
+        let mut controller = FollowCameraController::new();
+        controller.set_distance(4.0, 1.0, 10.0).unwrap();
+        controller.set_height(3.0, 1.0);
+
+        // Nothing but the player and what is attached to it is near the
+        // camera, so the camera stays where it should be.
+        let look_at = Vec3::new(0.0, 1.0, 0.0);
+        let unobstructed = Vec3::new(0.0, 3.0, 4.0);
+
+        controller.track(&scene, player);
+        controller.update_camera(&mut scene.camera, Duration::from_millis(16));
+        assert!(scene.camera.eye().abs_diff_eq(unobstructed, 1e-5));
+
+        // A wall between the player and the camera pulls the camera in front
+        // of the wall right away.
+        scene.spawn_model(cube_at(Vec3::new(0.0, 2.0, 2.0)));
+
+        controller.track(&scene, player);
+        controller.update_camera(&mut scene.camera, Duration::from_millis(16));
+
+        let eye = scene.camera.eye();
+        assert!(eye.distance(look_at) < 2.0, "{eye}");
+        assert!((eye - look_at)
+            .normalize()
+            .abs_diff_eq((unobstructed - look_at).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn free_look_smoothing_eases_towards_input() {
+        let eye_after = |frames: u32| {
+            let mut camera = test_camera(800, 600);
+            let mut controller = FreeLookCameraController::new();
+            controller.set_smoothing(Some(Duration::from_millis(100)));
+            controller.update_camera(&mut camera, Duration::ZERO);
+
+            // Move forward for a tenth of a second.
+            controller
+                .input
+                .process_button(Binding::Key(KeyCode::KeyW), ElementState::Pressed);
+            controller.update_camera(&mut camera, Duration::from_millis(100));
+            controller
+                .input
+                .process_button(Binding::Key(KeyCode::KeyW), ElementState::Released);
+
+            for _ in 0..frames {
+                controller.update_camera(&mut camera, Duration::from_secs(1) / frames);
+            }
+
+            camera.eye()
+        };
+
+        // After one half life the camera has covered half of the distance the
+        // input moved it, and it keeps going after the key is released.
+        let goal = Vec3::new(0.0, 0.0, 4.6);
+        assert!(eye_after(0).abs_diff_eq(Vec3::new(0.0, 0.0, 4.8), 1e-4));
+        assert!(eye_after(10).abs_diff_eq(goal, 1e-3));
+        assert!(eye_after(10).abs_diff_eq(eye_after(100), 1e-4));
+    }
+
+    #[test]
+    fn arcball_smoothing_orbits_around_pivot() {
+        let mut camera = test_camera(800, 600);
+        let mut controller = ArcballCameraController::new();
+        controller.set_smoothing(Some(Duration::from_millis(50)));
+
+        // Drag to swing the camera around the pivot.
+        controller
+            .input
+            .process_button(Binding::Mouse(MouseButton::Left), ElementState::Pressed);
+        controller.process_mouse_motion(200.0, 0.0);
+        controller.update_camera(&mut camera, Duration::from_millis(16));
+
+        let first = camera.eye();
+        assert!(first.x.abs() > 1e-3);
+
+        for _ in 0..60 {
+            controller.update_camera(&mut camera, Duration::from_millis(16));
+
+            // The camera stays on the orbit rather than cutting across it.
+            assert!((camera.eye().length() - 5.0).abs() < 1e-3);
+            assert_eq!(Vec3::ZERO, camera.target());
+        }
+
+        assert!(camera.eye().x.abs() > first.x.abs());
+
+        // Without smoothing the same drag moves the camera to the goal at once.
+        let mut unsmoothed = test_camera(800, 600);
+        let mut controller = ArcballCameraController::new();
+        controller
+            .input
+            .process_button(Binding::Mouse(MouseButton::Left), ElementState::Pressed);
+        controller.process_mouse_motion(200.0, 0.0);
+        controller.update_camera(&mut unsmoothed, Duration::from_millis(16));
+
+        assert!(camera.eye().abs_diff_eq(unsmoothed.eye(), 1e-3));
+    }
+
+    #[test]
+    fn arcball_scrolling_ignores_frame_time() {
+        let scroll = |delta: Duration| {
+            let mut camera = test_camera(800, 600);
+            let mut controller = ArcballCameraController::new();
+
+            controller.process_mouse_wheel(2.0, 0.0);
+            controller.update_camera(&mut camera, delta);
+            camera.eye()
+        };
+
+        assert!(
+            scroll(Duration::from_millis(8)).abs_diff_eq(scroll(Duration::from_millis(33)), 1e-5)
+        );
+    }
 }
@@ -0,0 +1,405 @@
+//! Moves the camera along a smooth path through keyframes over time, for
+//! flythroughs and repeatable benchmark runs.
+use std::time::Duration;
+
+use glam::Vec3;
+use thiserror::Error;
+use winit::event::WindowEvent;
+
+use super::{gamepad::GamepadEvent, CameraController};
+use crate::camera::Camera;
+
+/// Where the camera is, what it looks at and how wide its view is at a point
+/// in time along a `CameraPath`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    /// Time since the start of the path.
+    pub time: Duration,
+    pub eye: Vec3,
+    pub target: Vec3,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+}
+
+impl CameraKeyframe {
+    pub fn new(time: Duration, eye: Vec3, target: Vec3, fov_y: f32) -> Self {
+        Self {
+            time,
+            eye,
+            target,
+            fov_y,
+        }
+    }
+
+    /// Point `camera` at this keyframe.
+    pub fn apply(&self, camera: &mut Camera) {
+        if self.eye != self.target {
+            camera.reorient(self.eye, self.target);
+        }
+
+        camera.set_fov_y(self.fov_y.clamp(f32::EPSILON, Camera::MAX_FOV_Y));
+    }
+}
+
+/// How a `CameraPath` curves between its keyframes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathInterpolation {
+    /// A Catmull-Rom spline, which passes through every keyframe.
+    CatmullRom,
+    /// Cubic Bézier curves joined end to end. The path passes through the
+    /// first keyframe and every third keyframe after it, and is pulled towards
+    /// the two keyframes in between, so there must be `3n + 1` keyframes. The
+    /// times of the keyframes that are not passed through are ignored.
+    Bezier,
+}
+
+impl PathInterpolation {
+    /// Get the number of keyframes from the start of one curve segment to the
+    /// start of the next.
+    fn segment_step(self) -> usize {
+        match self {
+            PathInterpolation::CatmullRom => 1,
+            PathInterpolation::Bezier => 3,
+        }
+    }
+}
+
+/// Plays a camera move along a curve through keyframes. Implements
+/// `CameraController`, so the path can drive the camera like any other
+/// controller: each update advances the path by the frame time and moves the
+/// camera to match.
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+    interpolation: PathInterpolation,
+    /// Time since the start of the path.
+    time: Duration,
+    playing: bool,
+    /// Start again from the beginning after reaching the end when true,
+    /// otherwise stop at the end.
+    looping: bool,
+}
+
+impl CameraPath {
+    /// Create a paused path through `keyframes`, which must be in order of
+    /// increasing time.
+    pub fn new(
+        keyframes: Vec<CameraKeyframe>,
+        interpolation: PathInterpolation,
+    ) -> Result<Self, CameraPathError> {
+        if keyframes.len() < 2 {
+            return Err(CameraPathError::TooFewKeyframes(keyframes.len()));
+        }
+
+        if interpolation == PathInterpolation::Bezier && keyframes.len() % 3 != 1 {
+            return Err(CameraPathError::BezierKeyframeCount(keyframes.len()));
+        }
+
+        let step = interpolation.segment_step();
+
+        for (index, pair) in keyframes
+            .iter()
+            .step_by(step)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .enumerate()
+        {
+            if pair[1].time <= pair[0].time {
+                return Err(CameraPathError::OutOfOrder((index + 1) * step));
+            }
+        }
+
+        Ok(Self {
+            keyframes,
+            interpolation,
+            time: Duration::ZERO,
+            playing: false,
+            looping: false,
+        })
+    }
+
+    /// Get the keyframes the path passes through or near.
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    /// Get the time from the start to the end of the path.
+    pub fn duration(&self) -> Duration {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// Get the current time along the path.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Jump to `time` along the path, which is clamped to the path's duration.
+    pub fn seek(&mut self, time: Duration) {
+        self.time = time.min(self.duration());
+    }
+
+    /// Start or resume moving along the path. Playing a path that stopped at
+    /// its end starts it again from the beginning.
+    pub fn play(&mut self) {
+        if self.is_finished() {
+            self.time = Duration::ZERO;
+        }
+
+        self.playing = true;
+    }
+
+    /// Stop moving along the path, keeping the current time.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Returns true if the path is moving.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Start again from the beginning after reaching the end when `looping` is
+    /// true, otherwise stop at the end. Paths look seamless when looped if the
+    /// last keyframe matches the first.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Returns true if the path reached its end and is not looping.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.time >= self.duration()
+    }
+
+    /// Move the current time forward by `delta` if the path is playing.
+    pub fn advance(&mut self, delta: Duration) {
+        if !self.playing {
+            return;
+        }
+
+        let duration = self.duration();
+        self.time += delta;
+
+        if self.time >= duration {
+            if self.looping && !duration.is_zero() {
+                self.time =
+                    Duration::from_nanos((self.time.as_nanos() % duration.as_nanos()) as u64);
+            } else {
+                self.time = duration;
+                self.playing = false;
+            }
+        }
+    }
+
+    /// Get the camera's keyframe at the current time.
+    pub fn current(&self) -> CameraKeyframe {
+        self.sample(self.time)
+    }
+
+    /// Get the camera's keyframe at `time` along the path. Times past the end
+    /// of the path give the last keyframe.
+    pub fn sample(&self, time: Duration) -> CameraKeyframe {
+        let (first, last) = (
+            &self.keyframes[0],
+            &self.keyframes[self.keyframes.len() - 1],
+        );
+
+        if time <= first.time {
+            return CameraKeyframe { time, ..*first };
+        } else if time >= last.time {
+            return CameraKeyframe { time, ..*last };
+        }
+
+        let step = self.interpolation.segment_step();
+
+        // Find the segment containing `time`, and how far through it `time` is.
+        let start = (0..self.keyframes.len() - step)
+            .step_by(step)
+            .rfind(|&index| self.keyframes[index].time <= time)
+            .unwrap_or(0);
+        let (start_time, end_time) = (
+            self.keyframes[start].time,
+            self.keyframes[start + step].time,
+        );
+        let t = (time - start_time).as_secs_f32() / (end_time - start_time).as_secs_f32();
+
+        let points = match self.interpolation {
+            PathInterpolation::CatmullRom => {
+                // The ends of the path are treated as if their keyframe was
+                // repeated.
+                let before = start.saturating_sub(1);
+                let after = (start + 2).min(self.keyframes.len() - 1);
+                [before, start, start + 1, after].map(|index| &self.keyframes[index])
+            }
+            PathInterpolation::Bezier => {
+                [start, start + 1, start + 2, start + 3].map(|index| &self.keyframes[index])
+            }
+        };
+
+        let curve = |value: fn(&CameraKeyframe) -> Vec3| {
+            let [p0, p1, p2, p3] = points.map(value);
+
+            match self.interpolation {
+                PathInterpolation::CatmullRom => catmull_rom(p0, p1, p2, p3, t),
+                PathInterpolation::Bezier => bezier(p0, p1, p2, p3, t),
+            }
+        };
+
+        CameraKeyframe {
+            time,
+            eye: curve(|keyframe| keyframe.eye),
+            target: curve(|keyframe| keyframe.target),
+            fov_y: curve(|keyframe| Vec3::splat(keyframe.fov_y)).x,
+        }
+    }
+}
+
+impl CameraController for CameraPath {
+    fn process_input(&mut self, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    fn process_mouse_motion(&mut self, _delta_x: f64, _delta_y: f64) {}
+
+    fn process_mouse_wheel(&mut self, _delta_x: f64, _delta_y: f64) {}
+
+    fn process_gamepad(&mut self, _event: &GamepadEvent) -> bool {
+        false
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, delta: Duration) {
+        self.advance(delta);
+        self.current().apply(camera);
+    }
+}
+
+/// Get the point `t` of the way from `p1` to `p2` along a Catmull-Rom spline
+/// through `p0`, `p1`, `p2` and `p3`.
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// Get the point `t` of the way along the cubic Bézier curve from `p0` to `p3`
+/// with control points `p1` and `p2`.
+fn bezier(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let u = 1.0 - t;
+
+    p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CameraPathError {
+    #[error("camera paths need at least two keyframes but {0} were given")]
+    TooFewKeyframes(usize),
+    #[error("Bézier camera paths need 3n + 1 keyframes but {0} were given")]
+    BezierKeyframeCount(usize),
+    #[error("camera path keyframe {0} is not later than the keyframe before it")]
+    OutOfOrder(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_camera;
+
+    fn keyframe(seconds: u64, x: f32, fov_y: f32) -> CameraKeyframe {
+        CameraKeyframe::new(
+            Duration::from_secs(seconds),
+            Vec3::new(x, 1.0, 5.0),
+            Vec3::new(x, 0.0, 0.0),
+            fov_y,
+        )
+    }
+
+    #[test]
+    fn catmull_rom_paths_pass_through_every_keyframe() {
+        let keyframes = vec![
+            keyframe(0, 0.0, 0.5),
+            keyframe(1, 2.0, 0.5),
+            keyframe(3, 2.0, 1.0),
+            keyframe(4, -1.0, 0.5),
+        ];
+        let path = CameraPath::new(keyframes.clone(), PathInterpolation::CatmullRom).unwrap();
+
+        for keyframe in &keyframes {
+            let sample = path.sample(keyframe.time);
+            assert!(sample.eye.abs_diff_eq(keyframe.eye, 1e-5));
+            assert!(sample.target.abs_diff_eq(keyframe.target, 1e-5));
+            assert!((sample.fov_y - keyframe.fov_y).abs() < 1e-5);
+        }
+
+        // Between two equal keyframes the spline overshoots rather than
+        // stopping, which keeps the motion smooth.
+        assert!(path.sample(Duration::from_secs(2)).eye.x > 2.0);
+        assert_eq!(path.sample(Duration::from_secs(10)).eye, keyframes[3].eye);
+    }
+
+    #[test]
+    fn bezier_paths_pass_through_every_third_keyframe() {
+        let keyframes = vec![
+            keyframe(0, 0.0, 0.5),
+            keyframe(0, 0.0, 0.5),
+            keyframe(0, 4.0, 0.5),
+            keyframe(2, 4.0, 1.0),
+        ];
+        let path = CameraPath::new(keyframes.clone(), PathInterpolation::Bezier).unwrap();
+
+        assert_eq!(keyframes[0].eye, path.sample(Duration::ZERO).eye);
+        assert!(path
+            .sample(Duration::from_secs(2))
+            .eye
+            .abs_diff_eq(keyframes[3].eye, 1e-5));
+        assert!((path.sample(Duration::from_secs(1)).eye.x - 2.0).abs() < 1e-5);
+
+        assert_eq!(
+            Some(CameraPathError::BezierKeyframeCount(3)),
+            CameraPath::new(keyframes[..3].to_vec(), PathInterpolation::Bezier).err()
+        );
+    }
+
+    #[test]
+    fn paths_play_pause_and_loop() {
+        let keyframes = vec![keyframe(0, 0.0, 0.5), keyframe(2, 4.0, 0.5)];
+        let mut path = CameraPath::new(keyframes, PathInterpolation::CatmullRom).unwrap();
+        let mut camera = test_camera(100, 100);
+
+        // Paths start paused.
+        path.update_camera(&mut camera, Duration::from_secs(1));
+        assert_eq!(Duration::ZERO, path.time());
+        assert_eq!(Vec3::new(0.0, 1.0, 5.0), camera.eye());
+
+        path.play();
+        path.update_camera(&mut camera, Duration::from_secs(1));
+        assert_eq!(Duration::from_secs(1), path.time());
+        assert!((camera.eye().x - 2.0).abs() < 1e-5);
+
+        path.pause();
+        path.update_camera(&mut camera, Duration::from_secs(1));
+        assert_eq!(Duration::from_secs(1), path.time());
+
+        // Without looping the path stops at its end.
+        path.play();
+        path.advance(Duration::from_secs(5));
+        assert!(path.is_finished() && !path.is_playing());
+        assert_eq!(Duration::from_secs(2), path.time());
+
+        // Looping paths wrap around to the start.
+        path.set_looping(true);
+        path.play();
+        path.advance(Duration::from_millis(2500));
+        assert!(path.is_playing());
+        assert_eq!(Duration::from_millis(500), path.time());
+
+        assert_eq!(
+            Some(CameraPathError::OutOfOrder(1)),
+            CameraPath::new(
+                vec![keyframe(1, 0.0, 0.5), keyframe(1, 1.0, 0.5)],
+                PathInterpolation::CatmullRom
+            )
+            .err()
+        );
+    }
+}
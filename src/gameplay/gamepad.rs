@@ -0,0 +1,194 @@
+//! Gamepad buttons, sticks and triggers, and reading them from connected
+//! gamepads when the `gamepad` feature is enabled.
+use winit::event::ElementState;
+
+/// A gamepad button, named after its position on the gamepad. Triggers are
+/// reported as axes since they can be partially pressed.
+// Only constructed when gamepads are read.
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// The bottom face button, eg A on an Xbox gamepad.
+    South,
+    /// The right face button, eg B on an Xbox gamepad.
+    East,
+    /// The top face button, eg Y on an Xbox gamepad.
+    North,
+    /// The left face button, eg X on an Xbox gamepad.
+    West,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    /// Pressing down on the left stick.
+    LeftStick,
+    /// Pressing down on the right stick.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// A gamepad input with a position rather than being pressed or released.
+/// Stick axes range from -1 to 1 with up and right being positive, and
+/// triggers range from 0 when released to 1 when fully pressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// A change to a gamepad's state. Events from all connected gamepads are
+/// reported together.
+// Only constructed when gamepads are read.
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEvent {
+    Button(GamepadButton, ElementState),
+    Axis(GamepadAxis, f32),
+    /// A gamepad was disconnected, so any of its buttons that were held and
+    /// sticks that were pushed are now released.
+    Disconnected,
+}
+
+#[cfg(feature = "gamepad")]
+pub use gilrs_gamepads::Gamepads;
+
+#[cfg(feature = "gamepad")]
+mod gilrs_gamepads {
+    use std::collections::{HashMap, HashSet};
+
+    use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+    use tracing::info;
+    use winit::event::ElementState;
+
+    use super::{GamepadAxis, GamepadButton, GamepadEvent};
+
+    /// Axes closer than this to their resting position do not count as in use.
+    const AXIS_AT_REST: f32 = 0.05;
+
+    /// Reads events from connected gamepads.
+    pub struct Gamepads {
+        gilrs: Gilrs,
+        /// Buttons held on each gamepad.
+        held: HashSet<(GamepadId, GamepadButton)>,
+        /// Axes pushed away from their resting position on each gamepad.
+        pushed: HashMap<(GamepadId, GamepadAxis), f32>,
+    }
+
+    impl Gamepads {
+        pub fn new() -> anyhow::Result<Self> {
+            let gilrs = Gilrs::new()
+                .map_err(|e| anyhow::anyhow!("failed to start reading gamepads: {e}"))?;
+
+            for (_, gamepad) in gilrs.gamepads() {
+                info!("found gamepad {}", gamepad.name());
+            }
+
+            Ok(Self {
+                gilrs,
+                held: HashSet::new(),
+                pushed: HashMap::new(),
+            })
+        }
+
+        /// Get the next gamepad event, or `None` once every pending event was
+        /// read.
+        pub fn next_event(&mut self) -> Option<GamepadEvent> {
+            while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+                let event = match event {
+                    EventType::Connected => {
+                        info!("gamepad {} connected", self.gilrs.gamepad(id).name());
+                        None
+                    }
+                    EventType::Disconnected => {
+                        info!("gamepad {} disconnected", self.gilrs.gamepad(id).name());
+                        self.held.retain(|(gamepad, _)| *gamepad != id);
+                        self.pushed.retain(|(gamepad, _), _| *gamepad != id);
+                        Some(GamepadEvent::Disconnected)
+                    }
+                    EventType::ButtonPressed(button, _) => to_button(button).map(|button| {
+                        self.held.insert((id, button));
+                        GamepadEvent::Button(button, ElementState::Pressed)
+                    }),
+                    EventType::ButtonReleased(button, _) => to_button(button).map(|button| {
+                        self.held.remove(&(id, button));
+                        GamepadEvent::Button(button, ElementState::Released)
+                    }),
+                    EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                        Some(self.axis_changed(id, GamepadAxis::LeftTrigger, value))
+                    }
+                    EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                        Some(self.axis_changed(id, GamepadAxis::RightTrigger, value))
+                    }
+                    EventType::AxisChanged(axis, value, _) => {
+                        to_axis(axis).map(|axis| self.axis_changed(id, axis, value))
+                    }
+                    _ => None,
+                };
+
+                if event.is_some() {
+                    return event;
+                }
+            }
+
+            None
+        }
+
+        fn axis_changed(&mut self, id: GamepadId, axis: GamepadAxis, value: f32) -> GamepadEvent {
+            if value.abs() > AXIS_AT_REST {
+                self.pushed.insert((id, axis), value);
+            } else {
+                self.pushed.remove(&(id, axis));
+            }
+
+            GamepadEvent::Axis(axis, value)
+        }
+
+        /// Returns true if any gamepads are connected.
+        pub fn is_connected(&self) -> bool {
+            self.gilrs.gamepads().next().is_some()
+        }
+
+        /// Returns true if a button is held or an axis is pushed on any
+        /// gamepad, which can keep moving things without sending new events.
+        pub fn is_in_use(&self) -> bool {
+            !self.held.is_empty() || !self.pushed.is_empty()
+        }
+    }
+
+    fn to_button(button: Button) -> Option<GamepadButton> {
+        match button {
+            Button::South => Some(GamepadButton::South),
+            Button::East => Some(GamepadButton::East),
+            Button::North => Some(GamepadButton::North),
+            Button::West => Some(GamepadButton::West),
+            Button::LeftTrigger => Some(GamepadButton::LeftBumper),
+            Button::RightTrigger => Some(GamepadButton::RightBumper),
+            Button::Select => Some(GamepadButton::Select),
+            Button::Start => Some(GamepadButton::Start),
+            Button::LeftThumb => Some(GamepadButton::LeftStick),
+            Button::RightThumb => Some(GamepadButton::RightStick),
+            Button::DPadUp => Some(GamepadButton::DPadUp),
+            Button::DPadDown => Some(GamepadButton::DPadDown),
+            Button::DPadLeft => Some(GamepadButton::DPadLeft),
+            Button::DPadRight => Some(GamepadButton::DPadRight),
+            _ => None,
+        }
+    }
+
+    fn to_axis(axis: Axis) -> Option<GamepadAxis> {
+        match axis {
+            Axis::LeftStickX => Some(GamepadAxis::LeftStickX),
+            Axis::LeftStickY => Some(GamepadAxis::LeftStickY),
+            Axis::RightStickX => Some(GamepadAxis::RightStickX),
+            Axis::RightStickY => Some(GamepadAxis::RightStickY),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Turns raw mouse button and cursor events into clicks, double clicks and
+//! drags.
+use std::{collections::HashMap, time::Duration};
+
+use glam::Vec2;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+use crate::platform::SystemTime;
+
+/// A button must be released within this long after being pressed to count as
+/// a click.
+pub const CLICK_MAX_DURATION: Duration = Duration::from_millis(300);
+/// Two clicks must happen within this long of each other to count as a double
+/// click.
+pub const DOUBLE_CLICK_MAX_INTERVAL: Duration = Duration::from_millis(400);
+/// Moving the cursor further than this many pixels while a button is held
+/// starts a drag. Clicks and double clicks must also stay within this distance.
+pub const DRAG_THRESHOLD_PIXELS: f32 = 4.0;
+
+/// A mouse gesture recognized by `GestureDetector`. Positions are in physical
+/// pixels relative to the top left of the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseGesture {
+    /// A button was pressed and quickly released without moving.
+    Click { button: MouseButton, position: Vec2 },
+    /// A second click of the same button quickly followed a click. This is
+    /// reported instead of a second `Click`.
+    DoubleClick { button: MouseButton, position: Vec2 },
+    /// The cursor moved past the drag threshold while a button was held.
+    DragStart {
+        button: MouseButton,
+        start: Vec2,
+        position: Vec2,
+    },
+    /// The button held during a drag was released.
+    DragEnd {
+        button: MouseButton,
+        start: Vec2,
+        position: Vec2,
+    },
+}
+
+/// A mouse button that is currently held down.
+#[derive(Debug)]
+struct Press {
+    time: SystemTime,
+    position: Vec2,
+    dragging: bool,
+}
+
+/// Tracks mouse button presses and cursor movement to recognize gestures.
+#[derive(Debug, Default)]
+pub struct GestureDetector {
+    cursor_position: Vec2,
+    pressed: HashMap<MouseButton, Press>,
+    /// Time and position of the most recent click for each button that could
+    /// still become a double click.
+    last_click: HashMap<MouseButton, (SystemTime, Vec2)>,
+}
+
+impl GestureDetector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Update the detector with a window event that happened at `now`, and
+    /// return any gestures that were completed by the event.
+    pub fn process_input(&mut self, event: &WindowEvent, now: SystemTime) -> Vec<MouseGesture> {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_moved(Vec2::new(position.x as f32, position.y as f32))
+            }
+            WindowEvent::MouseInput {
+                button,
+                state: ElementState::Pressed,
+                ..
+            } => {
+                self.button_pressed(*button, now);
+                Vec::new()
+            }
+            WindowEvent::MouseInput {
+                button,
+                state: ElementState::Released,
+                ..
+            } => self.button_released(*button, now).into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Move the cursor to `position`, and start dragging any held buttons that
+    /// moved far enough.
+    pub fn cursor_moved(&mut self, position: Vec2) -> Vec<MouseGesture> {
+        self.cursor_position = position;
+
+        self.pressed
+            .iter_mut()
+            .filter(|(_, press)| {
+                !press.dragging && press.position.distance(position) > DRAG_THRESHOLD_PIXELS
+            })
+            .map(|(button, press)| {
+                press.dragging = true;
+                MouseGesture::DragStart {
+                    button: *button,
+                    start: press.position,
+                    position,
+                }
+            })
+            .collect()
+    }
+
+    /// Start tracking a press of `button` at the current cursor position.
+    pub fn button_pressed(&mut self, button: MouseButton, now: SystemTime) {
+        self.pressed.insert(
+            button,
+            Press {
+                time: now,
+                position: self.cursor_position,
+                dragging: false,
+            },
+        );
+    }
+
+    /// Finish a press of `button`, returning the gesture it completed if any.
+    /// Holding a button too long without moving is not a gesture.
+    pub fn button_released(
+        &mut self,
+        button: MouseButton,
+        now: SystemTime,
+    ) -> Option<MouseGesture> {
+        let press = self.pressed.remove(&button)?;
+        let position = self.cursor_position;
+
+        if press.dragging {
+            return Some(MouseGesture::DragEnd {
+                button,
+                start: press.position,
+                position,
+            });
+        }
+
+        if now - press.time > CLICK_MAX_DURATION {
+            return None;
+        }
+
+        // A click that follows closely after another click of the same button
+        // is a double click. The pair is forgotten afterwards so a third click
+        // starts over.
+        match self.last_click.remove(&button) {
+            Some((last_time, last_position))
+                if now - last_time <= DOUBLE_CLICK_MAX_INTERVAL
+                    && last_position.distance(position) <= DRAG_THRESHOLD_PIXELS =>
+            {
+                Some(MouseGesture::DoubleClick { button, position })
+            }
+            _ => {
+                self.last_click.insert(button, (now, position));
+                Some(MouseGesture::Click { button, position })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: MouseButton = MouseButton::Left;
+
+    fn click(detector: &mut GestureDetector, at: SystemTime) -> Option<MouseGesture> {
+        detector.button_pressed(LEFT, at);
+        detector.button_released(LEFT, at + Duration::from_millis(50))
+    }
+
+    #[test]
+    fn quick_clicks_become_a_double_click() {
+        let mut detector = GestureDetector::new();
+        let start = SystemTime::now();
+        let position = Vec2::new(10.0, 20.0);
+        detector.cursor_moved(position);
+
+        assert_eq!(
+            Some(MouseGesture::Click {
+                button: LEFT,
+                position
+            }),
+            click(&mut detector, start)
+        );
+        assert_eq!(
+            Some(MouseGesture::DoubleClick {
+                button: LEFT,
+                position
+            }),
+            click(&mut detector, start + Duration::from_millis(200))
+        );
+
+        // A third click starts a new pair, and a slow click is not a double.
+        assert!(matches!(
+            click(&mut detector, start + Duration::from_millis(400)),
+            Some(MouseGesture::Click { .. })
+        ));
+        assert!(matches!(
+            click(&mut detector, start + Duration::from_secs(2)),
+            Some(MouseGesture::Click { .. })
+        ));
+    }
+
+    #[test]
+    fn long_press_is_not_a_click() {
+        let mut detector = GestureDetector::new();
+        let start = SystemTime::now();
+
+        detector.button_pressed(LEFT, start);
+        assert_eq!(
+            None,
+            detector.button_released(LEFT, start + Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn moving_while_held_drags() {
+        let mut detector = GestureDetector::new();
+        let start = SystemTime::now();
+
+        detector.button_pressed(LEFT, start);
+        assert!(detector.cursor_moved(Vec2::new(2.0, 2.0)).is_empty());
+        assert_eq!(
+            vec![MouseGesture::DragStart {
+                button: LEFT,
+                start: Vec2::ZERO,
+                position: Vec2::new(10.0, 0.0)
+            }],
+            detector.cursor_moved(Vec2::new(10.0, 0.0))
+        );
+
+        // The drag only starts once.
+        assert!(detector.cursor_moved(Vec2::new(20.0, 0.0)).is_empty());
+
+        assert_eq!(
+            Some(MouseGesture::DragEnd {
+                button: LEFT,
+                start: Vec2::ZERO,
+                position: Vec2::new(20.0, 0.0)
+            }),
+            detector.button_released(LEFT, start + Duration::from_millis(100))
+        );
+    }
+}
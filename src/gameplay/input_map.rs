@@ -0,0 +1,394 @@
+//! Maps physical keys, mouse buttons, mouse axes and gamepad inputs to named
+//! actions so that games ask whether "move_forward" is held rather than
+//! checking for `W`.
+use std::collections::{HashMap, HashSet};
+
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use super::gamepad::{GamepadAxis, GamepadButton, GamepadEvent};
+
+/// A physical input that can be bound to an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Axis(MouseAxis),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis),
+}
+
+/// A mouse input that moves by an amount rather than being pressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseAxis {
+    MotionX,
+    MotionY,
+    WheelX,
+    WheelY,
+}
+
+/// Translates input into named actions, and tracks which actions are held and
+/// how far their axes moved.
+///
+/// Button presses and releases, and mouse axis movement, are accumulated until
+/// `end_frame` is called. Call it once the frame's input has been used.
+/// Gamepad sticks and triggers instead hold their position until they move.
+#[derive(Clone, Debug)]
+pub struct InputMap {
+    bindings: HashMap<String, Vec<Binding>>,
+    /// Bound keys and buttons that are held down.
+    held: HashSet<Binding>,
+    /// Bound keys and buttons that were pressed since the last frame.
+    pressed: HashSet<Binding>,
+    /// Bound keys and buttons that were released since the last frame.
+    released: HashSet<Binding>,
+    /// Amount each mouse axis moved since the last frame.
+    axes: HashMap<MouseAxis, f32>,
+    /// Position of each bound gamepad stick and trigger.
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+    /// Gamepad axes closer than this to their resting position are treated as
+    /// resting.
+    gamepad_dead_zone: f32,
+    /// Scales gamepad axis positions once the dead zone is removed.
+    gamepad_sensitivity: f32,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputMap {
+    pub const DEFAULT_GAMEPAD_DEAD_ZONE: f32 = 0.15;
+
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            axes: HashMap::new(),
+            gamepad_axes: HashMap::new(),
+            gamepad_dead_zone: Self::DEFAULT_GAMEPAD_DEAD_ZONE,
+            gamepad_sensitivity: 1.0,
+        }
+    }
+
+    /// Bind `binding` to `action` and return the map.
+    pub fn with_binding(mut self, action: &str, binding: Binding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// Bind `binding` to `action` in addition to any existing bindings. An
+    /// input can be bound to more than one action.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.bindings.entry(action.to_string()).or_default();
+
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Remove `binding` from `action`.
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.bindings.get_mut(action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Replace all of the bindings for `action` with `bindings`.
+    pub fn rebind(&mut self, action: &str, bindings: impl IntoIterator<Item = Binding>) {
+        self.bindings.remove(action);
+
+        for binding in bindings {
+            self.bind(action, binding);
+        }
+    }
+
+    /// Get the inputs bound to `action`.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get how far from their resting position gamepad sticks and triggers
+    /// must move before they count as moved.
+    pub fn gamepad_dead_zone(&self) -> f32 {
+        self.gamepad_dead_zone
+    }
+
+    /// Set how far from their resting position gamepad sticks and triggers
+    /// must move before they count as moved, which must be at least zero and
+    /// less than one. Positions past the dead zone are rescaled to start from
+    /// zero so that movement stays smooth.
+    pub fn set_gamepad_dead_zone(&mut self, dead_zone: f32) {
+        assert!((0.0..1.0).contains(&dead_zone));
+        self.gamepad_dead_zone = dead_zone;
+    }
+
+    /// Get the amount gamepad stick and trigger positions are scaled by.
+    pub fn gamepad_sensitivity(&self) -> f32 {
+        self.gamepad_sensitivity
+    }
+
+    /// Set the amount gamepad stick and trigger positions are scaled by.
+    pub fn set_gamepad_sensitivity(&mut self, sensitivity: f32) {
+        self.gamepad_sensitivity = sensitivity;
+    }
+
+    /// Returns true if `binding` is bound to any action.
+    fn is_bound(&self, binding: Binding) -> bool {
+        self.bindings
+            .values()
+            .any(|bindings| bindings.contains(&binding))
+    }
+
+    /// Update the map with a keyboard or mouse button event. Returns `true` if
+    /// the event's key or button is bound to an action.
+    pub fn process_input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
+                PhysicalKey::Code(key_code) => {
+                    self.process_button(Binding::Key(key_code), event.state)
+                }
+                PhysicalKey::Unidentified(_) => false,
+            },
+            WindowEvent::MouseInput { button, state, .. } => {
+                self.process_button(Binding::Mouse(*button), *state)
+            }
+            _ => false,
+        }
+    }
+
+    /// Update the map when a key or mouse button is pressed or released.
+    /// Returns `true` if `binding` is bound to an action.
+    pub fn process_button(&mut self, binding: Binding, state: ElementState) -> bool {
+        if !self.is_bound(binding) {
+            return false;
+        }
+
+        match state {
+            // Held keys repeat their pressed events, which are not new presses.
+            ElementState::Pressed => {
+                if self.held.insert(binding) {
+                    self.pressed.insert(binding);
+                }
+            }
+            ElementState::Released => {
+                if self.held.remove(&binding) {
+                    self.released.insert(binding);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Update the map with a gamepad event. Returns `true` if the event's
+    /// button or axis is bound to an action.
+    pub fn process_gamepad(&mut self, event: &GamepadEvent) -> bool {
+        match *event {
+            GamepadEvent::Button(button, state) => {
+                self.process_button(Binding::GamepadButton(button), state)
+            }
+            GamepadEvent::Axis(axis, value) => {
+                if !self.is_bound(Binding::GamepadAxis(axis)) {
+                    return false;
+                }
+
+                self.gamepad_axes.insert(axis, value);
+                true
+            }
+            GamepadEvent::Disconnected => {
+                self.held
+                    .retain(|binding| !matches!(binding, Binding::GamepadButton(_)));
+                self.gamepad_axes.clear();
+                false
+            }
+        }
+    }
+
+    /// Accumulate mouse motion until the end of the frame.
+    pub fn process_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        self.move_axis(MouseAxis::MotionX, delta_x as f32);
+        self.move_axis(MouseAxis::MotionY, delta_y as f32);
+    }
+
+    /// Accumulate mouse scroll wheel movement until the end of the frame.
+    pub fn process_mouse_wheel(&mut self, delta_x: f64, delta_y: f64) {
+        self.move_axis(MouseAxis::WheelX, delta_x as f32);
+        self.move_axis(MouseAxis::WheelY, delta_y as f32);
+    }
+
+    fn move_axis(&mut self, axis: MouseAxis, delta: f32) {
+        *self.axes.entry(axis).or_default() += delta;
+    }
+
+    /// Returns true if any key or button bound to `action` is held down.
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.bindings(action).iter().any(|b| self.held.contains(b))
+    }
+
+    /// Returns true if a key or button bound to `action` was pressed since the
+    /// last frame.
+    #[allow(dead_code)]
+    pub fn was_pressed(&self, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| self.pressed.contains(b))
+    }
+
+    /// Returns true if a key or button bound to `action` was released since
+    /// the last frame.
+    pub fn was_released(&self, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|b| self.released.contains(b))
+    }
+
+    /// Get how far the mouse axes bound to `action` moved since the last frame.
+    /// Keys and buttons bound to the action do not move it.
+    pub fn axis(&self, action: &str) -> f32 {
+        self.bindings(action)
+            .iter()
+            .filter_map(|binding| match binding {
+                Binding::Axis(axis) => self.axes.get(axis),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Get how strongly `action` is held, which is 1 while a key or button
+    /// bound to it is held plus the position of the gamepad sticks and
+    /// triggers bound to it. Stick positions are negative when pushed down or
+    /// to the left.
+    pub fn analog(&self, action: &str) -> f32 {
+        let buttons = if self.is_pressed(action) { 1.0 } else { 0.0 };
+
+        buttons
+            + self
+                .bindings(action)
+                .iter()
+                .filter_map(|binding| match binding {
+                    Binding::GamepadAxis(axis) => self.gamepad_axes.get(axis),
+                    _ => None,
+                })
+                .map(|value| self.apply_dead_zone(*value) * self.gamepad_sensitivity)
+                .sum::<f32>()
+    }
+
+    /// Remove the dead zone from a gamepad axis position, and rescale what is
+    /// left to cover the full range of the axis.
+    fn apply_dead_zone(&self, value: f32) -> f32 {
+        let distance = (value.abs() - self.gamepad_dead_zone).max(0.0);
+        value.signum() * distance / (1.0 - self.gamepad_dead_zone)
+    }
+
+    /// Forget the presses, releases and axis movement of the current frame.
+    /// Keys and buttons that are held down stay held.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.axes.clear();
+    }
+
+    /// Forget every held key and button without reporting them as released,
+    /// eg when the window loses focus and will not see them being released.
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.pressed.clear();
+        self.gamepad_axes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JUMP: &str = "jump";
+    const LOOK: &str = "look";
+
+    #[test]
+    fn actions_follow_any_of_their_bindings() {
+        let space = Binding::Key(KeyCode::Space);
+        let click = Binding::Mouse(MouseButton::Left);
+        let mut input = InputMap::new()
+            .with_binding(JUMP, space)
+            .with_binding(JUMP, click);
+
+        assert!(!input.process_button(Binding::Key(KeyCode::KeyQ), ElementState::Pressed));
+        assert!(input.process_button(space, ElementState::Pressed));
+        assert!(input.process_button(click, ElementState::Pressed));
+        assert!(input.process_button(space, ElementState::Released));
+        assert!(input.is_pressed(JUMP) && input.was_pressed(JUMP) && input.was_released(JUMP));
+
+        // Presses and releases only last until the end of the frame.
+        input.end_frame();
+        assert!(input.is_pressed(JUMP) && !input.was_pressed(JUMP) && !input.was_released(JUMP));
+
+        input.process_button(click, ElementState::Released);
+        assert!(!input.is_pressed(JUMP) && input.was_released(JUMP));
+
+        // Rebinding moves the action to the new key.
+        input.rebind(JUMP, [Binding::Key(KeyCode::KeyE)]);
+        assert_eq!(&[Binding::Key(KeyCode::KeyE)], input.bindings(JUMP));
+        assert!(!input.process_button(space, ElementState::Pressed));
+        assert!(input.process_button(Binding::Key(KeyCode::KeyE), ElementState::Pressed));
+        assert!(input.is_pressed(JUMP));
+
+        input.release_all();
+        assert!(!input.is_pressed(JUMP) && !input.was_pressed(JUMP));
+    }
+
+    #[test]
+    fn axes_accumulate_until_the_end_of_the_frame() {
+        let mut input = InputMap::new()
+            .with_binding(LOOK, Binding::Axis(MouseAxis::MotionX))
+            .with_binding(LOOK, Binding::Axis(MouseAxis::WheelY))
+            .with_binding(JUMP, Binding::Key(KeyCode::Space));
+
+        input.process_mouse_motion(2.0, 5.0);
+        input.process_mouse_motion(1.5, 5.0);
+        input.process_mouse_wheel(10.0, -1.0);
+        input.process_button(Binding::Key(KeyCode::Space), ElementState::Pressed);
+
+        assert_eq!(2.5, input.axis(LOOK));
+        assert_eq!(0.0, input.axis(JUMP));
+
+        input.end_frame();
+        assert_eq!(0.0, input.axis(LOOK));
+    }
+
+    #[test]
+    fn gamepad_axes_ignore_the_dead_zone_and_keep_their_position() {
+        let stick = GamepadAxis::LeftStickY;
+        let mut input = InputMap::new()
+            .with_binding(JUMP, Binding::GamepadAxis(stick))
+            .with_binding(JUMP, Binding::GamepadButton(GamepadButton::South));
+        input.set_gamepad_dead_zone(0.2);
+
+        assert!(!input.process_gamepad(&GamepadEvent::Axis(GamepadAxis::RightStickX, 1.0)));
+        assert!(input.process_gamepad(&GamepadEvent::Axis(stick, 0.1)));
+        assert_eq!(0.0, input.analog(JUMP));
+
+        // Positions past the dead zone are rescaled, and stay until they move.
+        input.process_gamepad(&GamepadEvent::Axis(stick, -0.6));
+        input.end_frame();
+        assert!((input.analog(JUMP) + 0.5).abs() < 1e-6);
+
+        input.set_gamepad_sensitivity(2.0);
+        input.process_gamepad(&GamepadEvent::Button(
+            GamepadButton::South,
+            ElementState::Pressed,
+        ));
+        assert!(input.is_pressed(JUMP));
+        assert!(input.analog(JUMP).abs() < 1e-6);
+
+        input.process_gamepad(&GamepadEvent::Disconnected);
+        assert!(!input.is_pressed(JUMP));
+        assert_eq!(0.0, input.analog(JUMP));
+    }
+}
@@ -5,20 +5,23 @@ mod camera;
 mod content;
 mod game_app;
 mod gameplay;
+mod logging;
 mod math_utils;
 mod platform;
 mod renderer;
+#[cfg(test)]
+mod test_support;
 
-use game_app::multi_cube_demo::MultiCubeDemo;
-use game_app::GameAppHost;
+use game_app::demos::{self, DemoArgs, DEMOS};
+use game_app::{GameAppHost, RenderLoopMode};
 use platform::SystemTime;
-use renderer::Renderer;
-use tracing::{info, warn};
+use renderer::builder::RendererBuilder;
+use tracing::{error, info, warn};
 use tracing_log::log::{self};
 use winit::{
     event::*,
     event_loop::EventLoop,
-    keyboard::{Key, NamedKey},
+    keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
     window::WindowBuilder,
 };
 
@@ -27,15 +30,17 @@ use wasm_bindgen::prelude::*;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub async fn run_main() {
-    // Initialize logging before doing anything else.
-    // TODO: Configure tracing to emit INFO+ for wgpu, and DEBUG+ for squirrel
+    // Initialize logging before doing anything else. Log levels can be
+    // overridden with `RUST_LOG` natively, or the `log` URL query parameter on
+    // the web.
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
             wasm_support::logging_init();
         } else {
-            let stdout_subscriber = tracing_subscriber::fmt().pretty().finish();
-            tracing::subscriber::set_global_default(stdout_subscriber)
-                .expect("failed to install stdout global tracing subscriber");
+            logging::init(
+                logging::fmt_subscriber().finish(),
+                std::env::var("RUST_LOG").ok().as_deref(),
+            );
         }
     }
 
@@ -59,15 +64,69 @@ pub async fn run_main() {
     #[cfg(target_arch = "wasm32")]
     wasm_support::create_canvas(&main_window);
 
+    // Resize the canvas whenever its container element is resized, since the
+    // browser does not send window resize events for page layout changes.
+    #[cfg(target_arch = "wasm32")]
+    let canvas_resize_observer =
+        wasm_support::CanvasResizeObserver::new(&main_window, event_loop.create_proxy());
+
     // Initialize the renderer.
     log::info!("creating render window");
 
-    let mut game_host = GameAppHost::new(
-        Renderer::new(&main_window).await,
-        Box::new(MultiCubeDemo::new()),
+    // The graphics adapter can be picked with the WGPU_BACKEND, WGPU_POWER_PREF
+    // and WGPU_ADAPTER_NAME environment variables, eg on laptops with two GPUs.
+    let builder = RendererBuilder::from_env();
+    let renderer = match builder.clone().build(&main_window).await {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            error!("failed to create the renderer: {e}");
+
+            for adapter in builder.enumerate_adapters() {
+                log::info!(
+                    "available adapter: {} ({:?}, {:?})",
+                    adapter.name,
+                    adapter.backend,
+                    adapter.device_type
+                );
+            }
+
+            // Tell the player why nothing is drawn rather than leaving an
+            // empty canvas on the page.
+            #[cfg(target_arch = "wasm32")]
+            wasm_support::show_error(&format!("Unable to start rendering: {e}"));
+
+            return;
+        }
+    };
+
+    // Every demo scene is registered with the host, and the number keys switch
+    // between them. The demo that runs first is picked with `--demo <name>`
+    // natively, or the `demo` URL query parameter on the web. The model viewer
+    // shows the model given with `--model <path>`.
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let demo_args = DemoArgs {
+                demo: wasm_support::demo_name(),
+                ..Default::default()
+            };
+        } else {
+            let demo_args = DemoArgs::parse(std::env::args().skip(1));
+        }
+    }
+
+    let first_demo = demo_args.first_demo();
+    info!(
+        "running demo {}: {}",
+        DEMOS[first_demo].name, DEMOS[first_demo].description
     );
 
-    game_host.load_content().unwrap();
+    let mut game_host =
+        GameAppHost::with_games(renderer, demos::create_demos(&demo_args), first_demo);
+    game_host.load_content(None).unwrap();
+
+    // Only draw frames when something changed. Games that animate on their own
+    // will still be redrawn every frame.
+    game_host.set_render_loop_mode(RenderLoopMode::OnDemand);
 
     // Main window event loop.
     //
@@ -76,18 +135,27 @@ pub async fn run_main() {
     //       event dispatcher below.
     log::info!("starting main window event loop");
     let mut last_redraw = SystemTime::now();
-    let mut capture_mouse = false;
+    let capture_mouse = false;
+    let main_window = &main_window;
 
     let mut surface_configured = false;
 
     event_loop
         .run(move |event, control_flow| {
-            let renderer_window_id = game_host.renderer().window().id();
+            let renderer_window_id = main_window.id();
 
             match event {
                 Event::Resumed => {
                     info!("resumed event received, rendering can start");
                     surface_configured = true;
+                    game_host.request_redraw();
+                }
+                // The canvas container was resized (web only):
+                #[cfg(target_arch = "wasm32")]
+                Event::UserEvent(()) => {
+                    if let Some(new_size) = canvas_resize_observer.take_resize() {
+                        game_host.window_resized(new_size.width, new_size.height);
+                    }
                 }
                 Event::WindowEvent { event, window_id } if window_id == renderer_window_id => {
                     // Allow the renderer to consume input events prior to
@@ -98,12 +166,15 @@ pub async fn run_main() {
                     match event {
                         // Redraw window:
                         WindowEvent::RedrawRequested => {
-                            // Request a redraw.
-                            // TODO(scott): Switch to continuous event loop.
-                            game_host.renderer().window.request_redraw();
+                            // Wait for the next frame when the frame rate
+                            // is capped, or skip this one if it came early.
+                            if !game_host.begin_frame() {
+                                return;
+                            }
 
                             // Measure amount of time elapsed.
-                            let time_since_last_redraw = SystemTime::now() - last_redraw;
+                            let time_since_last_redraw =
+                                game_host.frame_delta(SystemTime::now() - last_redraw);
                             last_redraw = SystemTime::now();
 
                             // Don't try rendering until the window surface
@@ -121,6 +192,33 @@ pub async fn run_main() {
                         WindowEvent::CloseRequested => control_flow.exit(),
                         // Keyboard input:
                         WindowEvent::KeyboardInput { event, .. } => {
+                            // Switch demo scenes with the number keys.
+                            if let (PhysicalKey::Code(key_code), ElementState::Released) =
+                                (event.physical_key, event.state)
+                            {
+                                if let Some(index) = game_index_for_key(key_code) {
+                                    if let Err(e) = game_host.switch_to(index) {
+                                        warn!("failed to switch demo scene: {e:#}");
+                                    }
+                                }
+
+                                // Show the engine settings with F2. Show frame
+                                // stats with F3, or log them with F4. Pause the
+                                // simulation with F5, and step it one frame at a
+                                // time with F6.
+                                match key_code {
+                                    KeyCode::F2 => game_host
+                                        .set_show_settings(!game_host.is_showing_settings()),
+                                    KeyCode::F3 => game_host
+                                        .set_show_frame_stats(!game_host.is_showing_frame_stats()),
+                                    KeyCode::F4 => game_host.log_frame_stats(),
+                                    KeyCode::F5 if game_host.is_paused() => game_host.resume(),
+                                    KeyCode::F5 => game_host.pause(),
+                                    KeyCode::F6 => game_host.step(),
+                                    _ => {}
+                                }
+                            }
+
                             // Stop capturing the mouse when escape pressed
                             // otherwise if not captured exit the program.
                             if let (Key::Named(NamedKey::Escape), ElementState::Released) =
@@ -128,6 +226,11 @@ pub async fn run_main() {
                             {
                                 if game_host.is_mouse_captured() {
                                     game_host.set_mouse_captured(false);
+                                } else if cfg!(target_arch = "wasm32") {
+                                    // The browser already released the pointer
+                                    // lock, so only the cursor needs updating.
+                                    // Pages are not exited with escape.
+                                    game_host.set_mouse_captured(false);
                                 } else {
                                     control_flow.exit()
                                 }
@@ -135,20 +238,18 @@ pub async fn run_main() {
                         }
                         // Mouse button:
                         WindowEvent::MouseInput {
-                            state: mouse_button_state,
+                            state: ElementState::Pressed,
                             ..
-                        } => {
-                            // Any click will recapture the mouse if it was not
-                            // already captured.
-                            if mouse_button_state == ElementState::Pressed
-                                && !game_host.is_mouse_captured()
-                            {
-                                game_host.set_mouse_captured(true);
-                            }
+                        } if !game_host.is_mouse_captured()
+                            && !game_host.ui_wants_pointer_input() =>
+                        {
+                            // Any click outside of the UI will recapture the
+                            // mouse if it was not already captured.
+                            game_host.set_mouse_captured(true);
                         }
                         // Window focus gained or lost:
                         WindowEvent::Focused(is_focused) => {
-                            game_host.set_mouse_captured(is_focused);
+                            game_host.focus_changed(is_focused);
                         }
                         // Window resized:
                         WindowEvent::Resized(physical_size) => {
@@ -173,7 +274,11 @@ pub async fn run_main() {
                     } => game_host.mouse_scroll_wheel(delta_x as f64, delta_y as f64),
                     _ => {}
                 },
-
+                // All pending events were handled, so decide when the next
+                // frame is drawn and how to wait until then.
+                Event::AboutToWait if surface_configured => {
+                    control_flow.set_control_flow(game_host.about_to_wait());
+                }
                 _ => {}
             }
         })
@@ -182,3 +287,19 @@ pub async fn run_main() {
     // All done.
     log::info!("exiting main window loop");
 }
+
+/// Get the index of the game that pressing `key_code` switches to.
+fn game_index_for_key(key_code: KeyCode) -> Option<usize> {
+    match key_code {
+        KeyCode::Digit1 => Some(0),
+        KeyCode::Digit2 => Some(1),
+        KeyCode::Digit3 => Some(2),
+        KeyCode::Digit4 => Some(3),
+        KeyCode::Digit5 => Some(4),
+        KeyCode::Digit6 => Some(5),
+        KeyCode::Digit7 => Some(6),
+        KeyCode::Digit8 => Some(7),
+        KeyCode::Digit9 => Some(8),
+        _ => None,
+    }
+}
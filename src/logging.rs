@@ -0,0 +1,141 @@
+//! Tracing filter configuration shared by the native and wasm logging setup.
+use tracing::{level_filters::LevelFilter, warn, Subscriber};
+use tracing_subscriber::{
+    filter::{ParseError, Targets},
+    fmt::{
+        format::{Format, Pretty},
+        SubscriberBuilder,
+    },
+    layer::SubscriberExt,
+};
+
+/// Log levels used when no override is given. wgpu is very chatty at debug and
+/// trace levels so it is limited to info messages.
+pub const DEFAULT_LOG_FILTER: &str = "warn,wgpu=info,naga=info,squirrel=debug";
+
+/// Install `subscriber` as the global default tracing subscriber, filtered by
+/// `EnvFilter` style `directives` (eg `warn,wgpu=info,squirrel=debug`). The
+/// default filter is used when `directives` is missing or invalid.
+pub fn init<S>(subscriber: S, directives: Option<&str>)
+where
+    S: Subscriber + Send + Sync + 'static,
+{
+    let (filter, parse_error) = match log_filter(directives) {
+        Ok(filter) => (filter, None),
+        Err(e) => (default_log_filter(), Some(e)),
+    };
+
+    tracing::subscriber::set_global_default(subscriber.with(filter))
+        .expect("failed to install global tracing subscriber");
+
+    // Report a bad override once there is a subscriber to report it to.
+    if let Some(e) = parse_error {
+        warn!("ignoring invalid log filter {directives:?}: {e}");
+    }
+}
+
+/// Start building the subscriber that prints log messages natively. The
+/// builder's own max level is raised to trace, since it otherwise drops debug
+/// and trace messages before the filter passed to `init` sees them.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub fn fmt_subscriber() -> SubscriberBuilder<Pretty, Format<Pretty>, LevelFilter> {
+    tracing_subscriber::fmt()
+        .pretty()
+        .with_max_level(LevelFilter::TRACE)
+}
+
+/// The filter described by `DEFAULT_LOG_FILTER`.
+pub fn default_log_filter() -> Targets {
+    DEFAULT_LOG_FILTER
+        .parse()
+        .expect("default log filter must be valid")
+}
+
+/// Parse `EnvFilter` style filter directives. Missing or blank directives are
+/// replaced with the default filter.
+pub fn log_filter(directives: Option<&str>) -> Result<Targets, ParseError> {
+    match directives.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(directives) => directives.parse(),
+        None => Ok(default_log_filter()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tracing::Level;
+
+    /// Collects everything written by a subscriber.
+    #[derive(Clone, Default)]
+    struct CapturedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedOutput {
+        fn text(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    /// Log a debug and a trace message through the native subscriber filtered
+    /// by `directives`, and return what was printed.
+    fn log_through_fmt_subscriber(directives: Option<&str>) -> String {
+        let output = CapturedOutput::default();
+        let writer = output.clone();
+        let subscriber = fmt_subscriber()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish()
+            .with(log_filter(directives).unwrap());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "squirrel::renderer", "debug message");
+            tracing::trace!(target: "squirrel::renderer", "trace message");
+        });
+
+        output.text()
+    }
+
+    #[test]
+    fn default_filter_quiets_wgpu() {
+        let filter = log_filter(None).unwrap();
+
+        assert!(filter.would_enable("squirrel::renderer", &Level::DEBUG));
+        assert!(filter.would_enable("wgpu_core::device", &Level::INFO));
+        assert!(!filter.would_enable("wgpu_core::device", &Level::DEBUG));
+        assert!(!filter.would_enable("winit", &Level::INFO));
+    }
+
+    #[test]
+    fn override_replaces_default_filter() {
+        let filter = log_filter(Some("wgpu=trace")).unwrap();
+        assert!(filter.would_enable("wgpu_hal::vulkan", &Level::TRACE));
+        assert!(!filter.would_enable("squirrel", &Level::ERROR));
+
+        assert!(log_filter(Some("squirrel=loud")).is_err());
+        assert!(log_filter(Some("  ")).is_ok());
+    }
+
+    #[test]
+    fn fmt_subscriber_is_only_limited_by_the_filter() {
+        let output = log_through_fmt_subscriber(None);
+        assert!(output.contains("debug message"));
+        assert!(!output.contains("trace message"));
+
+        let output = log_through_fmt_subscriber(Some("squirrel=trace"));
+        assert!(output.contains("debug message"));
+        assert!(output.contains("trace message"));
+    }
+}
@@ -1,4 +1,4 @@
-use glam::Vec2;
+use glam::{Mat4, Vec2, Vec3, Vec4};
 
 /// Calculates the (x, y) position that results from orbiting around `pivot` at
 /// a distance of `radius`.
@@ -8,3 +8,314 @@ pub fn rotate_around_pivot(pivot: Vec2, radius: f32, angle_radian: f32) -> Vec2
         y: pivot.y + radius * f32::sin(angle_radian),
     }
 }
+
+/// An axis aligned bounding box.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aabb {
+    /// The corner of the box with the smallest value on each axis.
+    pub min: Vec3,
+    /// The corner of the box with the largest value on each axis.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Create a new bounding box from its `min` and `max` corners.
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        debug_assert!(min.cmple(max).all());
+        Self { min, max }
+    }
+
+    /// Create the smallest bounding box that contains all of the given points.
+    /// An empty box at the origin is returned when there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut points = points.into_iter();
+
+        let Some(first) = points.next() else {
+            return Self::default();
+        };
+
+        points.fold(Self::new(first, first), |aabb, p| {
+            Self::new(aabb.min.min(p), aabb.max.max(p))
+        })
+    }
+
+    /// Get the center point of the box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Get the width, height and depth of the box.
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Get the smallest box that contains both this box and `other`.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    /// Get the eight corners of the box.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Get a new bounding box that contains this box after it has been
+    /// transformed by `transform`.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        Self::from_points(
+            self.corners()
+                .iter()
+                .map(|c| transform.transform_point3(*c)),
+        )
+    }
+}
+
+/// The six planes bounding the volume a camera can see.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// The left, right, bottom, top, near and far planes. Each plane is stored
+    /// as a normal pointing into the frustum (`xyz`) and a distance (`w`), so a
+    /// point `p` is inside of the plane when `dot(plane.xyz, p) + plane.w >= 0`.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the frustum planes from a view projection matrix that maps
+    /// depth to the 0 to 1 range used by wgpu.
+    pub fn from_view_projection(view_projection: &Mat4) -> Self {
+        let (x, y, z, w) = (
+            view_projection.row(0),
+            view_projection.row(1),
+            view_projection.row(2),
+            view_projection.row(3),
+        );
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+                // Normalize the planes so `w` is a distance in world units.
+                plane / plane.truncate().length().max(f32::EPSILON)
+            }),
+        }
+    }
+
+    /// Get the left, right, bottom, top, near and far planes. Each plane is a
+    /// normal pointing into the frustum (`xyz`) and a distance (`w`).
+    pub fn planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+    /// Returns true if any part of `aabb` may be inside of the frustum.
+    ///
+    /// Boxes that are outside of the frustum but straddle more than one plane
+    /// near a corner can be reported as intersecting, which is fine for
+    /// culling.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Test the corner of the box furthest along the plane's normal.
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// A half line starting at `origin` and extending along `direction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Direction of the ray. Distances returned by intersection tests are in
+    /// multiples of this vector's length.
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Create a ray starting at `origin` and pointing along `direction`.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self { origin, direction }
+    }
+
+    /// Get the point `distance` along the ray.
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Get a copy of the ray transformed by `transform`. Distances along the
+    /// transformed ray match distances along this ray, since the direction is
+    /// not normalized again.
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        Self::new(
+            transform.transform_point3(self.origin),
+            transform.transform_vector3(self.direction),
+        )
+    }
+
+    /// Get the distance along the ray to where it enters `aabb`, zero if the
+    /// ray starts inside of the box, or `None` if the ray misses the box.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        // Clip the ray against the pair of planes bounding each axis. Axes the
+        // ray is parallel to divide by zero, giving infinite distances that
+        // either keep or reject the whole ray.
+        let inverse_direction = self.direction.recip();
+        let t0 = (aabb.min - self.origin) * inverse_direction;
+        let t1 = (aabb.max - self.origin) * inverse_direction;
+
+        let enter = t0.min(t1).max_element().max(0.0);
+        let exit = t0.max(t1).min_element();
+
+        (enter <= exit).then_some(enter)
+    }
+
+    /// Get the distance along the ray to where it hits the triangle `a`, `b`,
+    /// `c` from either side, or `None` if it misses the triangle.
+    pub fn intersect_triangle(&self, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+        // Möller–Trumbore ray triangle intersection.
+        let (edge1, edge2) = (b - a, c - a);
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let to_origin = self.origin - a;
+        let u = to_origin.dot(p) * inverse_determinant;
+
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = to_origin.cross(edge1);
+        let v = self.direction.dot(q) * inverse_determinant;
+
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse_determinant;
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+
+    #[test]
+    fn aabb_from_points_contains_all_points() {
+        let aabb = Aabb::from_points([
+            Vec3::new(1.0, -2.0, 0.5),
+            Vec3::new(-3.0, 4.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ]);
+
+        assert_eq!(aabb.min, Vec3::new(-3.0, -2.0, -1.0));
+        assert_eq!(aabb.max, Vec3::new(1.0, 4.0, 0.5));
+    }
+
+    #[test]
+    fn aabb_transformed_by_rotation_and_translation() {
+        let aabb = Aabb::new(Vec3::new(-1.0, -2.0, -3.0), Vec3::new(1.0, 2.0, 3.0));
+        let transform = Mat4::from_rotation_translation(
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            Vec3::new(10.0, 0.0, 0.0),
+        );
+
+        let transformed = aabb.transformed(&transform);
+
+        assert!(transformed
+            .min
+            .abs_diff_eq(Vec3::new(7.0, -2.0, -1.0), 0.0001));
+        assert!(transformed
+            .max
+            .abs_diff_eq(Vec3::new(13.0, 2.0, 1.0), 0.0001));
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_touching_the_view_volume() {
+        let view_projection = Mat4::perspective_rh(f32::to_radians(90.0), 1.0, 1.0, 10.0)
+            * Mat4::look_at_rh(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        let frustum = Frustum::from_view_projection(&view_projection);
+        let unit_box_at = |center: Vec3| Aabb::new(center - 0.5, center + 0.5);
+
+        // Inside, and partially inside the near and far planes.
+        assert!(frustum.intersects_aabb(&unit_box_at(Vec3::new(0.0, 0.0, -5.0))));
+        assert!(frustum.intersects_aabb(&unit_box_at(Vec3::new(0.0, 0.0, -0.75))));
+        assert!(frustum.intersects_aabb(&unit_box_at(Vec3::new(0.0, 0.0, -10.25))));
+        assert!(frustum.intersects_aabb(&unit_box_at(Vec3::new(5.25, 0.0, -5.0))));
+
+        // Behind the camera, past the far plane and off to each side.
+        assert!(!frustum.intersects_aabb(&unit_box_at(Vec3::new(0.0, 0.0, 5.0))));
+        assert!(!frustum.intersects_aabb(&unit_box_at(Vec3::new(0.0, 0.0, -11.0))));
+        assert!(!frustum.intersects_aabb(&unit_box_at(Vec3::new(7.0, 0.0, -5.0))));
+        assert!(!frustum.intersects_aabb(&unit_box_at(Vec3::new(0.0, -7.0, -5.0))));
+    }
+
+    #[test]
+    fn rays_hit_the_near_side_of_boxes() {
+        let aabb = Aabb::new(Vec3::splat(-1.0), Vec3::splat(1.0));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert_eq!(Some(4.0), ray.intersect_aabb(&aabb));
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), ray.at(4.0));
+
+        // Starting inside, pointing away, and passing beside the box.
+        let inside = Ray::new(Vec3::ZERO, Vec3::X);
+        assert_eq!(Some(0.0), inside.intersect_aabb(&aabb));
+        assert_eq!(
+            None,
+            Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::Z).intersect_aabb(&aabb)
+        );
+        assert_eq!(
+            None,
+            Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::NEG_Z).intersect_aabb(&aabb)
+        );
+    }
+
+    #[test]
+    fn rays_hit_triangles_from_either_side() {
+        let triangle = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ];
+
+        let front = Ray::new(Vec3::new(0.0, 0.0, 2.0), Vec3::NEG_Z);
+        assert_eq!(Some(2.0), front.intersect_triangle(triangle));
+
+        let back = Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::Z);
+        assert_eq!(Some(3.0), back.intersect_triangle(triangle));
+
+        // Beside the triangle, parallel to it, and pointing away from it.
+        let beside = Ray::new(Vec3::new(0.9, 0.9, 2.0), Vec3::NEG_Z);
+        assert_eq!(None, beside.intersect_triangle(triangle));
+        assert_eq!(
+            None,
+            Ray::new(Vec3::new(0.0, 0.0, 2.0), Vec3::X).intersect_triangle(triangle)
+        );
+        assert_eq!(
+            None,
+            Ray::new(Vec3::new(0.0, 0.0, 2.0), Vec3::Z).intersect_triangle(triangle)
+        );
+
+        // Distances are kept when the ray is transformed.
+        let moved = front.transformed(&Mat4::from_scale(Vec3::splat(2.0)));
+        assert_eq!(
+            Some(2.0),
+            moved.intersect_triangle(triangle.map(|p| p * 2.0))
+        );
+    }
+}
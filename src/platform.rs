@@ -1,7 +1,9 @@
 //! Functions and structs that model common platform functionality regardless
 //! if running in regular std Rust or wasm Rust.
+mod file_watcher;
 mod fileio;
 mod time;
 
+pub use file_watcher::*;
 pub use fileio::*;
 pub use time::*;
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+/// Reports the contents of a content file whenever it changes, so content can
+/// be reloaded while the game is running.
+///
+/// Natively the file is watched in the `content/` directory of the source tree
+/// rather than the copy made by the build, so edits are seen without
+/// rebuilding. Browsers cannot watch files, so on the web the file is fetched
+/// from the server every `POLL_INTERVAL` instead.
+pub struct FileWatcher {
+    path: PathBuf,
+    /// The contents last returned by `poll`, or `None` before the file was
+    /// first read.
+    contents: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    changes: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    /// Keeps the watcher alive for as long as changes are received.
+    #[cfg(not(target_arch = "wasm32"))]
+    _watcher: notify::RecommendedWatcher,
+    /// Contents of the last completed fetch, or `None` while a fetch has not
+    /// finished.
+    #[cfg(target_arch = "wasm32")]
+    fetched: std::rc::Rc<std::cell::RefCell<Option<String>>>,
+    #[cfg(target_arch = "wasm32")]
+    last_fetch: Option<super::SystemTime>,
+}
+
+impl FileWatcher {
+    /// How often the file is fetched on the web.
+    #[cfg(target_arch = "wasm32")]
+    pub const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+    /// Watch the content file at `file_path`, which is relative to the
+    /// `content/` directory like the paths passed to `load_as_string`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new<P: AsRef<Path>>(file_path: P) -> anyhow::Result<Self> {
+        use notify::Watcher;
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("content")
+            .join(file_path);
+
+        // Editors often save by replacing the file, which stops a watch on the
+        // file itself. Watch its directory instead and pick out its events.
+        let (sender, changes) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(
+            path.parent().unwrap_or(Path::new(".")),
+            notify::RecursiveMode::NonRecursive,
+        )?;
+
+        Ok(Self {
+            path,
+            contents: None,
+            changes,
+            _watcher: watcher,
+        })
+    }
+
+    /// Watch the content file at `file_path`, which is relative to the
+    /// `content/` directory like the paths passed to `load_as_string`.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new<P: AsRef<Path>>(file_path: P) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: file_path.as_ref().to_path_buf(),
+            contents: None,
+            fetched: Default::default(),
+            last_fetch: None,
+        })
+    }
+
+    /// Get the path of the watched file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the new contents of the file if it changed since the last call, or
+    /// its current contents on the first call. Returns `None` if the file did
+    /// not change or could not be read.
+    pub fn poll(&mut self) -> Option<String> {
+        let contents = self.read_if_changed()?;
+
+        if self.contents.as_ref() == Some(&contents) {
+            return None;
+        }
+
+        self.contents = Some(contents.clone());
+        Some(contents)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_if_changed(&mut self) -> Option<String> {
+        let file_name = self.path.file_name();
+        let mut changed = self.contents.is_none();
+
+        for event in self.changes.try_iter() {
+            match event {
+                Ok(event) => {
+                    changed |= !event.kind.is_access()
+                        && event.paths.iter().any(|path| path.file_name() == file_name)
+                }
+                Err(e) => warn!("error while watching {:?}: {e}", self.path),
+            }
+        }
+
+        if !changed {
+            return None;
+        }
+
+        std::fs::read_to_string(&self.path)
+            .map_err(|e| warn!("failed to read watched file {:?}: {e}", self.path))
+            .ok()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read_if_changed(&mut self) -> Option<String> {
+        let now = super::SystemTime::now();
+        let fetch_due = !matches!(
+            self.last_fetch,
+            Some(last_fetch) if now - last_fetch < Self::POLL_INTERVAL
+        );
+
+        if fetch_due {
+            self.last_fetch = Some(now);
+
+            let (path, fetched) = (self.path.clone(), self.fetched.clone());
+            wasm_bindgen_futures::spawn_local(async move {
+                match super::load_as_string(&path).await {
+                    Ok(contents) => *fetched.borrow_mut() = Some(contents),
+                    Err(e) => warn!("failed to fetch watched file {path:?}: {e:#}"),
+                }
+            });
+        }
+
+        self.fetched.borrow_mut().take()
+    }
+}
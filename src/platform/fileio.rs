@@ -18,6 +18,31 @@ where
     Ok(final_url)
 }
 
+/// Returns a normalized name for the content file at `file_path` that can be
+/// used as a cache key. Different relative paths to the same file produce the
+/// same key.
+pub fn content_cache_key<P>(file_path: P) -> String
+where
+    P: AsRef<Path> + std::fmt::Debug,
+{
+    cfg_if! {
+      if #[cfg(target_arch = "wasm32")] {
+        // Joining the path to the origin URL resolves any `.` and `..` parts.
+        format_url(file_path.as_ref())
+            .map(|url| url.to_string())
+            .unwrap_or_else(|_| file_path.as_ref().to_string_lossy().into_owned())
+      } else {
+        // Canonicalizing fails if the file does not exist, in which case the
+        // load will fail anyway and the key does not matter.
+        let full_path = Path::new(env!("OUT_DIR")).join("content").join(file_path);
+        std::fs::canonicalize(&full_path)
+            .unwrap_or(full_path)
+            .to_string_lossy()
+            .into_owned()
+      }
+    }
+}
+
 /// Loads a file relative to the current directory, and returns it as a string.
 /// `file_path` should be relative to the content\ directory.
 pub async fn load_as_string<P>(file_path: P) -> anyhow::Result<String>
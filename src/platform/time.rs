@@ -15,7 +15,6 @@ pub struct SystemTime {
 }
 
 // TODO(scott): Implement other useful methods
-//  - Add<Duration> -> SystemTime
 //  - Sub<Duration> -> SystemTime
 //  - Display/ToString
 //  - Hash
@@ -51,3 +50,21 @@ impl std::ops::Sub<SystemTime> for SystemTime {
         }
     }
 }
+
+impl std::ops::Add<std::time::Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn add(self, rhs: std::time::Duration) -> Self::Output {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Self {
+                    millis_since_epoch: self.millis_since_epoch + rhs.as_secs_f64() * 1000.0
+                }
+            } else {
+                Self {
+                    instant: self.instant + rhs
+                }
+            }
+        }
+    }
+}
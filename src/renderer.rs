@@ -1,127 +1,290 @@
+pub mod billboards;
+pub mod builder;
+mod bvh;
 mod debug;
+pub mod debug_draw;
 mod gpu_buffers;
+pub mod gpu_context;
+mod gpu_timer;
+pub mod image_compare;
 mod instancing;
 pub mod lighting;
 pub mod materials;
 pub mod meshes;
 pub mod models;
 mod passes;
+pub mod recreated_resources;
+pub mod render_context;
+mod render_graph;
+pub mod render_target;
 pub mod scene;
 pub mod shaders;
+pub mod sprites;
+pub mod stats;
+pub mod text;
 pub mod textures;
+pub mod viewport;
 
-use std::{rc::Rc, time::Duration};
+pub use passes::{
+    post_process::{self, Tonemapper},
+    SsaoSettings,
+};
 
+use std::{
+    collections::HashMap,
+    ops::Range,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::camera::Camera;
+use builder::RendererBuilder;
 use debug::DebugState;
+use debug_draw::DebugDraw;
 use glam::{Mat4, Quat, Vec3};
-use gpu_buffers::{DynamicGpuBuffer, UniformBindGroup};
-use models::{DrawModel, Mesh, Model};
+use gpu_buffers::{DynamicGpuBuffer, InstanceBuffer, UniformBindGroup};
+use gpu_context::GpuContext;
+use gpu_timer::{GpuTimer, TimedPass};
+use materials::{BlendMode, MaterialPipelines, ShadingModel};
+use models::{DrawModel, Mesh, Model, ModelInstance, Submesh};
+use render_context::RenderContext;
+use render_graph::{RenderGraph, TransientTexturePool};
+use render_target::RenderTarget;
 use scene::Scene;
-use shaders::{lit_shader, BindGroupLayouts, PerFrameShaderVals, PerModelShaderVals, VertexLayout};
-use slotmap::{new_key_type, SlotMap};
+use shaders::{
+    light_lookup, lit_shader, pbr_shader, BindGroupLayouts, LightingResources,
+    PackedDirectionalLight, PackedPointLight, PackedSpotLight, PerFrameShaderVals, PerModelBinding,
+    PerModelShaderVals, VertexLayout,
+};
+use slotmap::{new_key_type, SecondaryMap, SlotMap};
+use stats::RenderStats;
+use text::TextRenderer;
+use thiserror::Error;
 use tracing::{info, warn};
+use viewport::Viewport;
 use winit::window::Window;
 
-use crate::{camera::Camera, content::DefaultTextures};
-
-// TODO: Need to move wgpu device, queue and other values out of the renderer
-//       to allow for code to create and update GPU resources w/out reading pub
-//       properties from the renderer instance. This is especially needed to
-//       split content loading away from the renderer.
-//
-//       This strongly affects how GameApp::load_content(...) works!
-
-// TODO: Move camera out of the renderer.
-
-// TODO: Consider moving things like camera, lights, models to a scene container.
-// Doesn't have to be anything fancy since I'm not sure where all of this info
-// should live yet, eg does renderer own the scene or the game?
+new_key_type! { pub struct ModelShaderValsKey; }
 
-// TODO: Remove pub access to renderer props like device, queue, bind group etc.
-// I'm deferring these decisions right now because I think this will need a lot
-// of working and involve figuring out how to do asset loading and shader swaps.
+/// The destination that the renderer draws frames into.
+enum RenderOutput<'a> {
+    /// Frames are presented to a window's rendering surface.
+    Window {
+        surface: wgpu::Surface<'a>,
+        // XXX(scott): `window` must be declared after `surface` because it needs
+        // to be dropped after `surface`, because the surface contains unsafe
+        // references to `window`.
+        window: &'a Window,
+    },
+    /// Frames are drawn into an offscreen texture that can be read back with
+    /// `Renderer::read_pixels`.
+    Offscreen { texture: wgpu::Texture },
+}
 
-// TODO: Renderer::new() should return Result<Self> and remove .unwrap().
+/// A GPU device requested to replace a lost one, see
+/// `Renderer::install_device`.
+pub struct NewDevice {
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    /// A new surface for the window to use with the device, or `None` to keep
+    /// the renderer's surface.
+    surface: Option<wgpu::Surface<'static>>,
+}
 
-new_key_type! { pub struct ModelShaderValsKey; }
+/// Reasons that a renderer could not be created.
+#[derive(Debug, Error)]
+pub enum RendererError {
+    #[error("no graphics adapter was found for backends {0:?}")]
+    NoAdapter(wgpu::Backends),
+    #[error(
+        "no graphics adapter named like {name:?} was found, available adapters are {available:?}"
+    )]
+    NoAdapterNamed {
+        name: String,
+        available: Vec<String>,
+    },
+    #[error("failed to create the rendering surface: {0}")]
+    SurfaceCreationFailed(#[from] wgpu::CreateSurfaceError),
+    #[error("failed to create the graphics device: {0}")]
+    DeviceRequestFailed(#[from] wgpu::RequestDeviceError),
+    #[error(
+        "the rendering surface supports no texture formats that the graphics adapter can draw to"
+    )]
+    UnsupportedSurfaceFormat,
+    #[error(
+        "renderer width and height must be larger than zero but width was {0} and height was {1}"
+    )]
+    InvalidSize(u32, u32),
+}
 
 /// The renderer is pretty much everything right now while I ramp up on WGPU
 /// and other graphics tutorials to get a basic 2d/3d prototype up.
 pub struct Renderer<'a> {
-    surface: wgpu::Surface<'a>,
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
-    pub default_textures: DefaultTextures,
-    pub bind_group_layouts: BindGroupLayouts,
+    /// Instance that created the graphics adapter and the window surface, kept
+    /// so the surface can be recreated if it is lost and a new device can be
+    /// requested if the device is lost.
+    instance: Rc<wgpu::Instance>,
+    target: RenderOutput<'a>,
+    context: RenderContext,
     surface_config: wgpu::SurfaceConfiguration,
     window_size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    per_frame_uniforms: PerFrameShaderVals,
+    render_pipelines: MaterialPipelines,
+    /// Per-frame shader values for each viewport drawn in a frame. The first
+    /// entry is always present and more are created when rendering into
+    /// several viewports.
+    per_frame_uniforms: Vec<PerFrameShaderVals>,
     depth_pass: passes::DepthPass,
     light_debug_pass: passes::LightDebugPass,
+    outline_pass: passes::OutlinePass,
+    normals_pass: passes::NormalsPass,
+    billboard_pass: passes::BillboardPass,
+    point_shadow_pass: passes::PointShadowPass,
+    skybox_pass: passes::SkyboxPass,
+    sprite_pass: passes::SpritePass,
+    ssao_pass: passes::SsaoPass,
+    text_pass: passes::TextPass,
+    ui_pass: passes::UiPass,
+    /// Bins point lights into clusters for the lit shaders, or `None` if the
+    /// device does not support light clusters.
+    light_cluster_pass: Option<passes::LightClusterPass>,
+    /// Shadow maps, ambient occlusion and light clusters read by the lit
+    /// shaders.
+    lighting_resources: LightingResources,
+    /// Set once the renderer has warned that lights were ignored because the
+    /// device does not support storage buffers.
+    warned_light_limit: bool,
+    post_process_stack: passes::PostProcessStack,
+    /// Textures allocated by each frame's render graph.
+    transient_textures: TransientTexturePool,
     sys_time_elapsed: std::time::Duration,
     debug_state: DebugState,
-    pub camera: Camera,
-    pub model_shader_vals: SlotMap<ModelShaderValsKey, PerModelShaderVals>,
-    // XXX(scott): `window` must be the last field in the struct because it needs
-    // to be dropped after `surface`, because the surface contains unsafe
-    // references to `window`.
-    pub window: &'a Window,
+    /// Lines added by the game this frame.
+    debug_draw: DebugDraw,
+    /// Screen space text added by the game this frame.
+    text: TextRenderer,
+    gpu_timer: GpuTimer,
+    stats: RenderStats,
+    /// Optional features that were enabled when creating the GPU device.
+    enabled_features: wgpu::Features,
+    /// Slot in `per_model_uniforms` holding each model's shader values.
+    model_shader_vals: SlotMap<ModelShaderValsKey, usize>,
+    /// Number of models using each slot in `per_model_uniforms` that was
+    /// created by `create_shared_models` and is still used by more than one
+    /// model.
+    shared_slots: HashMap<usize, usize>,
+    /// Shader values of every model, stored in one uniform buffer.
+    per_model_uniforms: PerModelShaderVals,
+    /// Models that are drawn with a selection outline.
+    selected: Vec<ModelShaderValsKey>,
+    /// Model that has its bounding box drawn each frame.
+    highlighted: Option<ModelShaderValsKey>,
+    /// The scene models drawn in each viewport, refilled every frame.
+    visible_models: Vec<VisibleModels>,
+    /// Transforms of every model drawn this frame, grouped by viewport.
+    model_instances: InstanceBuffer<ModelInstance>,
+    /// Set by the device lost callback when the GPU device is lost (eg a GPU
+    /// reset or a browser discarding the WebGPU context).
+    device_lost: Arc<AtomicBool>,
+    /// How the graphics adapter was picked, kept so a lost device can be
+    /// replaced by one from the same kind of adapter.
+    builder: RendererBuilder,
 }
 
 impl<'a> Renderer<'a> {
-    const CAMERA_POS: Vec3 = Vec3::new(1.5, 1.0, 5.0);
-    const CAMERA_LOOK_AT: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    const STENCIL_WRITE_FACE: wgpu::StencilFaceState = wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::Always,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::Replace,
+    };
+    /// Color of the bounding box drawn around the highlighted model.
+    const HIGHLIGHT_BOUNDS_COLOR: Vec3 = Vec3::new(1.0, 1.0, 0.0);
+    /// Color of the bounding boxes drawn around models for debugging.
+    const DEBUG_BOUNDS_COLOR: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    /// Color of the bounding spheres drawn around models for debugging.
+    const DEBUG_BOUNDING_SPHERE_COLOR: Vec3 = Vec3::new(0.0, 1.0, 1.0);
+    /// How much larger bounding boxes are drawn than their actual size, so that
+    /// box edges lying on the model's faces are not hidden by the model.
+    const BOUNDS_MARGIN: f32 = 0.02;
+    /// Features the renderer uses when the adapter supports them. Timestamp
+    /// queries measure how long each pass takes on the GPU, texture compression
+    /// lets compressed textures stay compressed on the GPU, and line polygon
+    /// mode allows drawing meshes as wireframes.
+    const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY
+        .union(wgpu::Features::TEXTURE_COMPRESSION_BC)
+        .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+        .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC)
+        .union(wgpu::Features::POLYGON_MODE_LINE);
+    /// Color format of the offscreen texture used by headless renderers.
+    const OFFSCREEN_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Create a renderer that draws into `window` with the default graphics
+    /// adapter. Use `RendererBuilder` to pick a different adapter.
+    #[allow(dead_code)]
+    pub async fn new(window: &'a Window) -> Result<Self, RendererError> {
+        RendererBuilder::new().build(window).await
+    }
 
-    pub async fn new(window: &'a Window) -> Self {
+    /// Create a renderer that is not attached to a window. Frames are drawn into
+    /// an offscreen texture of the given size and can be copied back to the CPU
+    /// with `read_pixels`.
+    ///
+    /// Headless renderers are useful for tests and for rendering on machines
+    /// without a display.
+    #[allow(dead_code)]
+    pub async fn new_headless(width: u32, height: u32) -> Result<Renderer<'static>, RendererError> {
+        RendererBuilder::new().build_headless(width, height).await
+    }
+
+    /// Create a renderer that presents to `surface` using `adapter`, which must
+    /// be compatible with the surface.
+    async fn from_surface(
+        instance: wgpu::Instance,
+        surface: wgpu::Surface<'a>,
+        window: &'a Window,
+        adapter: wgpu::Adapter,
+    ) -> Result<Self, RendererError> {
         let window_size = window.inner_size();
         info!("initial renderer size: {:?}", window_size);
 
-        // Create a WGPU instance that can use any supported graphics API.
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-
-        // Create the main rendering surface and then get an adapter that acts
-        // as the handle to one of the machine's physical GPU(s).
-        let surface = instance.create_surface(window).unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let (device, queue) = Self::request_device(&adapter).await?;
+        let surface_config = Self::surface_config(&surface, &adapter, window_size)?;
+        surface.configure(&device, &surface_config);
 
-        // Get a communication channel to the graphics card and a queue for
-        // submitting commands to.
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        Ok(Self::from_device(
+            Rc::new(instance),
+            device,
+            queue,
+            surface_config,
+            RenderOutput::Window { surface, window },
+        ))
+    }
 
-        // Set the main rendering surface to use an sRGB texture, and then allow
-        // all shaders to assume they are writing to an sRGB back buffer.
-        let surface_caps = surface.get_capabilities(&adapter);
+    /// Describe how a `size` sized `surface` should be configured for drawing
+    /// with `adapter`.
+    fn surface_config(
+        surface: &wgpu::Surface,
+        adapter: &wgpu::Adapter,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Result<wgpu::SurfaceConfiguration, RendererError> {
+        // Prefer an sRGB surface so the tonemap pass can leave converting its
+        // output to sRGB to the GPU. A surface without any formats cannot be
+        // drawn to by the adapter at all.
+        let surface_caps = surface.get_capabilities(adapter);
         let surface_format = surface_caps
             .formats
             .iter()
             .copied()
             .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+            .or_else(|| surface_caps.formats.first().copied())
+            .ok_or(RendererError::UnsupportedSurfaceFormat)?;
 
         if surface_format.is_srgb() {
             info!("rendering surface supports sRGB");
@@ -129,51 +292,175 @@ impl<'a> Renderer<'a> {
             info!("no sRGB support found for the main rendering surface, defaulting to first available");
         }
 
-        let surface_config = wgpu::SurfaceConfiguration {
+        Ok(wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
-            width: window_size.width,
-            height: window_size.height,
+            width: size.width,
+            height: size.height,
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
+        })
+    }
+
+    /// Create a renderer that draws into a `width` by `height` offscreen
+    /// texture using `adapter`.
+    async fn from_headless_adapter(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        width: u32,
+        height: u32,
+    ) -> Result<Renderer<'static>, RendererError> {
+        if width == 0 || height == 0 {
+            return Err(RendererError::InvalidSize(width, height));
+        }
+
+        let (device, queue) = Renderer::request_device(&adapter).await?;
+
+        // The offscreen texture takes the place of the window surface, so
+        // describe it with a surface configuration to let the render passes
+        // treat both targets the same way.
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: Self::OFFSCREEN_TEXTURE_FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
         };
 
-        surface.configure(&device, &surface_config);
+        let texture = Renderer::create_offscreen_texture(&device, &surface_config);
 
-        // Create the registry of common bind group layouts that must be reused
-        // each time an instance of that bind group is created.
-        let bind_group_layouts = BindGroupLayouts::new(&device);
+        Ok(Renderer::from_device(
+            Rc::new(instance),
+            device,
+            queue,
+            surface_config,
+            RenderOutput::Offscreen { texture },
+        ))
+    }
 
-        // Initialize a default camera.
-        // Position it one unit up, and two units back from world origin and
-        // have it look at the origin.
-        // +y is up
-        // +z is out of the screen.
-        let camera = Camera::new(
-            Self::CAMERA_POS,
-            Self::CAMERA_LOOK_AT,
-            Vec3::new(0.0, 1.0, 0.0),
-            f32::to_radians(45.0),
-            0.1,
-            100.0,
-            surface_config.width,
-            surface_config.height,
+    /// Get a communication channel to the graphics card and a queue for
+    /// submitting commands to. Every optional feature the renderer can use is
+    /// enabled when the adapter supports it, see `negotiate_features`.
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+    ) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+        let adapter_info = adapter.get_info();
+        info!(
+            "using graphics adapter {:?} ({:?}, {:?})",
+            adapter_info.name, adapter_info.backend, adapter_info.device_type
         );
 
+        let required_features = Self::negotiate_features(adapter.features());
+
+        adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features,
+                    required_limits: if cfg!(target_arch = "wasm32") {
+                        wgpu::Limits::downlevel_webgl2_defaults()
+                    } else {
+                        wgpu::Limits::default()
+                    },
+                    label: None,
+                },
+                None,
+            )
+            .await
+    }
+
+    /// Get the optional features that the renderer will enable from the
+    /// features supported by an adapter, and log which were not available.
+    ///
+    /// Missing features are not an error. Passes check `enabled_features` and
+    /// fall back when a feature is missing, eg GPU timings are reported as
+    /// zero without timestamp queries. WebGL2 supports none of them.
+    fn negotiate_features(adapter_features: wgpu::Features) -> wgpu::Features {
+        let enabled_features = adapter_features & Self::OPTIONAL_FEATURES;
+        let missing_features = Self::OPTIONAL_FEATURES - enabled_features;
+
+        info!("enabling optional graphics features: {enabled_features:?}");
+
+        if !missing_features.is_empty() {
+            info!("optional graphics features not supported by the adapter: {missing_features:?}");
+        }
+
+        enabled_features
+    }
+
+    /// Create the texture that headless renderers draw into.
+    fn create_offscreen_texture(
+        device: &wgpu::Device,
+        surface_config: &wgpu::SurfaceConfiguration,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen render target"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_config.format,
+            usage: surface_config.usage,
+            view_formats: &[],
+        })
+    }
+
+    /// Create the pipelines and render passes shared by windowed and headless
+    /// renderers.
+    fn from_device(
+        instance: Rc<wgpu::Instance>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_config: wgpu::SurfaceConfiguration,
+        target: RenderOutput<'a>,
+    ) -> Self {
+        let window_size =
+            winit::dpi::PhysicalSize::new(surface_config.width, surface_config.height);
+
+        // The context holds the registry of common bind group layouts that must
+        // be reused each time an instance of that bind group is created, and
+        // the default textures shared by materials.
+        let context = RenderContext::new(GpuContext::new(device, queue));
+        let device = context.device();
+        let queue = context.queue();
+        let bind_group_layouts = context.bind_group_layouts();
+
         // Create a uniform per-frame buffer to store shader values such as
         // the camera projection matrix.
-        let mut per_frame_uniforms = PerFrameShaderVals::new(&device, &bind_group_layouts);
-        per_frame_uniforms.set_output_is_srgb(surface_format.is_srgb());
+        let per_frame_uniforms = PerFrameShaderVals::new(device, bind_group_layouts);
+
+        // Lights are read from storage buffers and point lights are binned
+        // into clusters when the device supports compute shaders, otherwise
+        // the lit shaders read a few lights from the per-frame uniforms.
+        let light_cluster_pass = passes::LightClusterPass::is_supported(device)
+            .then(|| passes::LightClusterPass::new(device));
+
+        if light_cluster_pass.is_none() {
+            info!("light clusters are not supported, the number of scene lights is limited");
+        }
 
         // Load the default shader and associated resources.
+        let storage_lights = light_cluster_pass.is_some();
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(lit_shader::SHADER_CODE.into()),
+            source: wgpu::ShaderSource::Wgsl(
+                light_lookup::shader_code(lit_shader::SHADER_CODE, storage_lights).into(),
+            ),
+        });
+        let pbr_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("PBR shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                light_lookup::shader_code(pbr_shader::SHADER_CODE, storage_lights).into(),
+            ),
         });
-
-        let default_textures = DefaultTextures::new(&device, &queue);
 
         // Create the default render pipeline layout and render pipeline objects.
         let render_pipeline_layout =
@@ -181,81 +468,179 @@ impl<'a> Renderer<'a> {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
                     &bind_group_layouts.per_frame_layout,
-                    &bind_group_layouts.per_model_layout,
                     &bind_group_layouts.per_submesh_layout,
+                    &bind_group_layouts.lighting_resources_layout,
                 ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[models::Vertex::vertex_buffer_layout()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: passes::DepthPass::DEPTH_TEXTURE_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less, // Fragments drawn front to back.
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
+        // Pipelines are created for each shading model, cull mode and blend
+        // mode because materials can choose how they are lit, which faces are
+        // culled and if they are transparent.
+        let render_pipelines = MaterialPipelines::new(|shading, cull_mode, blend_mode| {
+            let shader = match shading {
+                ShadingModel::Phong => &shader,
+                ShadingModel::Pbr => &pbr_shader,
+            };
+
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vs_main",
+                    buffers: &[
+                        models::Vertex::vertex_buffer_layout(),
+                        models::ModelInstance::vertex_buffer_layout(),
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: passes::PostProcessStack::HDR_TEXTURE_FORMAT,
+                        blend: Some(match blend_mode {
+                            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+                            BlendMode::AlphaBlend => wgpu::BlendState::ALPHA_BLENDING,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: cull_mode.to_wgpu_face(),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: passes::DepthPass::DEPTH_TEXTURE_FORMAT,
+                    // Transparent submeshes are tested against the depth buffer
+                    // but do not hide what is drawn behind them afterwards.
+                    depth_write_enabled: !blend_mode.is_transparent(),
+                    depth_compare: wgpu::CompareFunction::Less, // Fragments drawn front to back.
+                    // Every drawn fragment writes the stencil reference value, which
+                    // lets selected models mark their footprint for the outline pass.
+                    stencil: wgpu::StencilState {
+                        front: Self::STENCIL_WRITE_FACE,
+                        back: Self::STENCIL_WRITE_FACE,
+                        read_mask: 0xff,
+                        write_mask: 0xff,
+                    },
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
         });
 
         // Set up additional render passes.
-        let depth_pass = passes::DepthPass::new(&device, &surface_config);
-        let light_debug_pass =
-            passes::LightDebugPass::new(&device, &surface_config, &bind_group_layouts);
+        let depth_pass = passes::DepthPass::new(device, &surface_config);
+        let light_debug_pass = passes::LightDebugPass::new(device);
+        let outline_pass = passes::OutlinePass::new(device, bind_group_layouts);
+        let normals_pass = passes::NormalsPass::new(device, bind_group_layouts);
+        let billboard_pass = passes::BillboardPass::new(device, bind_group_layouts);
+        let point_shadow_pass = passes::PointShadowPass::new(device, bind_group_layouts);
+        let skybox_pass = passes::SkyboxPass::new(device);
+        let sprite_pass = passes::SpritePass::new(device, surface_config.format);
+        let ssao_pass = passes::SsaoPass::new(device, queue, bind_group_layouts);
+        let text_pass = passes::TextPass::new(device, queue, surface_config.format);
+        let ui_pass = passes::UiPass::new(device, surface_config.format);
+        let lighting_resources = LightingResources::new(
+            device,
+            bind_group_layouts,
+            &point_shadow_pass,
+            &ssao_pass,
+            light_cluster_pass.as_ref(),
+        );
+
+        let per_model_uniforms = PerModelShaderVals::new(device, bind_group_layouts);
+        let model_instances = InstanceBuffer::new(
+            device,
+            Some("model instance buffer"),
+            vec![ModelInstance::default()],
+        );
+
+        // Scenes are drawn in HDR and need to be tonemapped before they can be
+        // displayed.
+        let mut post_process_stack = passes::PostProcessStack::new(device, &surface_config);
+        post_process_stack.push(Box::new(post_process::TonemapEffect::new(
+            device,
+            post_process_stack.input_layout(),
+        )));
+        let gpu_timer = GpuTimer::new(device, queue);
+
+        // Track device loss so the owner of the renderer can try to recover. The
+        // callback also fires when the device is dropped, which is not a loss.
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let device_lost_flag = device_lost.clone();
+
+        device.set_device_lost_callback(move |reason, message| {
+            if matches!(reason, wgpu::DeviceLostReason::Unknown) {
+                warn!("GPU device lost: {message}");
+                device_lost_flag.store(true, Ordering::Release);
+            }
+        });
+
+        let enabled_features = device.features();
 
         // Initialization (hopefully) complete!
         Self {
-            surface,
-            device,
-            queue,
-            default_textures,
-            bind_group_layouts,
+            instance,
+            target,
+            context,
             surface_config,
             window_size,
-            render_pipeline,
-            camera,
+            render_pipelines,
+            enabled_features,
             model_shader_vals: SlotMap::with_key(),
+            shared_slots: HashMap::new(),
+            per_model_uniforms,
+            selected: Vec::new(),
+            highlighted: None,
+            visible_models: Vec::new(),
+            model_instances,
             sys_time_elapsed: Default::default(),
-            per_frame_uniforms,
+            per_frame_uniforms: vec![per_frame_uniforms],
             depth_pass,
             light_debug_pass,
+            outline_pass,
+            normals_pass,
+            billboard_pass,
+            point_shadow_pass,
+            skybox_pass,
+            sprite_pass,
+            ssao_pass,
+            text_pass,
+            ui_pass,
+            light_cluster_pass,
+            lighting_resources,
+            warned_light_limit: false,
+            post_process_stack,
+            transient_textures: Default::default(),
             debug_state: Default::default(),
-            window,
+            debug_draw: Default::default(),
+            text: Default::default(),
+            gpu_timer,
+            stats: Default::default(),
+            device_lost,
+            builder: Default::default(),
         }
     }
 
-    pub fn window(&self) -> &Window {
-        self.window
+    /// Get the window this renderer presents to, or `None` if the renderer is
+    /// headless.
+    pub fn window(&self) -> Option<&Window> {
+        match &self.target {
+            RenderOutput::Window { window, .. } => Some(window),
+            RenderOutput::Offscreen { .. } => None,
+        }
     }
 
     pub fn resize(&mut self, new_width: u32, new_height: u32) {
@@ -269,176 +654,3331 @@ impl<'a> Renderer<'a> {
             self.window_size = winit::dpi::PhysicalSize::new(new_width, new_height);
             self.surface_config.width = new_width;
             self.surface_config.height = new_height;
-            self.surface.configure(&self.device, &self.surface_config);
+            match &mut self.target {
+                RenderOutput::Window { surface, .. } => {
+                    surface.configure(self.context.device(), &self.surface_config)
+                }
+                RenderOutput::Offscreen { texture } => {
+                    *texture =
+                        Self::create_offscreen_texture(self.context.device(), &self.surface_config)
+                }
+            }
+
+            // Recreate the depth buffer and HDR texture to match the new window
+            // size.
+            self.depth_pass
+                .resize(self.context.device(), &self.surface_config);
+            self.post_process_stack
+                .resize(self.context.device(), &self.surface_config);
+        }
+    }
 
-            // Recreate the depth buffer to match the new window size.
-            self.depth_pass.resize(&self.device, &self.surface_config);
+    /// Get the maximum number of frames the GPU is allowed to queue up ahead of
+    /// the frame being presented.
+    pub fn max_frame_latency(&self) -> u32 {
+        self.surface_config.desired_maximum_frame_latency
+    }
 
-            // Recreate the camera viewport to match the new window size.
-            self.camera
-                .set_viewport_size(new_width, new_height)
-                .unwrap_or_else(|e| warn!("{e}"))
+    /// Set the maximum number of frames the GPU is allowed to queue up ahead of
+    /// the frame being presented. Lower values reduce input latency, and higher
+    /// values smooth out frames that take an uneven amount of time to draw.
+    /// This is a hint that the graphics backend may not honor exactly.
+    pub fn set_max_frame_latency(&mut self, max_frame_latency: u32) {
+        if max_frame_latency == 0 {
+            warn!("max frame latency must be at least one frame, using one instead");
         }
+
+        self.surface_config.desired_maximum_frame_latency = max_frame_latency.max(1);
+        self.reconfigure_surface();
     }
 
-    pub fn input(&mut self, event: &winit::event::WindowEvent) {
-        self.debug_state.process_input(event);
+    /// Get the effects that are applied to the scene after it is drawn.
+    pub fn post_process_stack(&self) -> &passes::PostProcessStack {
+        &self.post_process_stack
     }
 
-    fn prepare_render(&mut self, scene: &Scene, delta: Duration) {
-        // Update renderer per-frame shader uniforms.
-        self.sys_time_elapsed += delta;
-        self.per_frame_uniforms
-            .set_time_elapsed_seconds(self.sys_time_elapsed);
+    /// Get the effects that are applied to the scene after it is drawn, to add,
+    /// remove or change effects.
+    pub fn post_process_stack_mut(&mut self) -> &mut passes::PostProcessStack {
+        &mut self.post_process_stack
+    }
 
-        self.per_frame_uniforms
-            .set_view_projection(self.camera.view_projection_matrix());
-        self.per_frame_uniforms.set_view_pos(self.camera.eye());
+    /// Get the curve used by the post processing stack's tonemap effect, or
+    /// `None` if the tonemap effect was removed.
+    pub fn tonemapper(&self) -> Option<Tonemapper> {
+        self.post_process_stack
+            .effect::<post_process::TonemapEffect>()
+            .map(|e| e.tonemapper())
+    }
+
+    /// Set the curve used by the post processing stack's tonemap effect. This
+    /// does nothing if the tonemap effect was removed.
+    pub fn set_tonemapper(&mut self, tonemapper: Tonemapper) {
+        if let Some(effect) = self
+            .post_process_stack
+            .effect_mut::<post_process::TonemapEffect>()
+        {
+            effect.set_tonemapper(tonemapper);
+        }
+    }
+
+    /// Get the screen space ambient occlusion settings.
+    pub fn ssao_settings(&self) -> SsaoSettings {
+        self.ssao_pass.settings()
+    }
 
-        // Update renderer per-scene shader uniforms.
-        self.per_frame_uniforms.clear_lights();
+    /// Change the screen space ambient occlusion settings, eg to enable it or
+    /// tune its kernel size and radius.
+    pub fn set_ssao_settings(&mut self, settings: SsaoSettings) {
+        self.ssao_pass.set_settings(settings);
+    }
 
-        for light in &scene.directional_lights {
-            self.per_frame_uniforms.add_directional_light(light);
+    /// Reconfigure the window surface with the current surface configuration.
+    /// Call this when rendering fails because the surface was lost or became
+    /// outdated. Headless renderers have no surface and ignore this call.
+    pub fn reconfigure_surface(&mut self) {
+        if let RenderOutput::Window { surface, .. } = &self.target {
+            surface.configure(self.context.device(), &self.surface_config);
         }
+    }
 
-        for light in &scene.spot_lights {
-            self.per_frame_uniforms.add_spot_light(light);
+    /// Create a new surface for the window (or canvas on the web) and configure
+    /// it to replace the current surface, eg after rendering failed because the
+    /// surface was lost. The surface is configured for the current device, so
+    /// a lost device must be replaced with `recreate_device` instead.
+    pub fn recreate_surface(&mut self) -> anyhow::Result<()> {
+        if let RenderOutput::Window { surface, window } = &mut self.target {
+            let new_surface = self.instance.create_surface(*window)?;
+            new_surface.configure(self.context.device(), &self.surface_config);
+            *surface = new_surface;
         }
 
-        // Update uniforms for each model that will be rendered.
-        for model in scene.models.iter() {
-            let model_sv = &mut self.model_shader_vals[model.model_sv_key];
+        Ok(())
+    }
 
-            // Does the transform matrix need to be updated?
-            if model.is_model_sv_dirty() {
-                model_sv.set_local_to_world(Mat4::from_scale_rotation_translation(
-                    model.scale(),
-                    model.rotation(),
-                    model.translation(),
-                ));
-            }
+    /// Returns true if the GPU device was lost since the last time this method
+    /// was called.
+    pub fn take_device_lost(&self) -> bool {
+        self.device_lost.swap(false, Ordering::AcqRel)
+    }
 
-            // Add lights closest to the model.
-            model_sv.clear_lights();
+    /// Replace a lost GPU device with a new one, and recreate every GPU
+    /// resource owned by the renderer from the state it keeps on the CPU. The
+    /// renderer's settings (eg the tonemapper, SSAO and debug visualizations)
+    /// and the shader values of every model carry over to the new device.
+    /// Post effects other than tonemapping own GPU resources and need to be
+    /// added again.
+    ///
+    /// Meshes, textures and materials belong to the game and must also be
+    /// recreated on the new device, see `GameApp::recreate_gpu_resources`.
+    pub async fn recreate_device(&mut self) -> Result<(), RendererError> {
+        // Windowed renderers keep their surface, which is configured for the
+        // new device.
+        let surface = match &self.target {
+            RenderOutput::Window { surface, .. } => Some(surface),
+            RenderOutput::Offscreen { .. } => None,
+        };
 
-            for light in &scene.point_lights {
-                model_sv.add_point_light(light);
-            }
+        let adapter = self
+            .builder
+            .select_adapter(&self.instance, surface, surface.is_none())
+            .await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
 
-            // Copy the model's shader values to the GPU and then mark its
-            // shader values object as having been updated.
-            model_sv.update_gpu(&self.queue);
-            model.mark_model_sv_updated();
-        }
+        self.install_device(NewDevice {
+            adapter,
+            device,
+            queue,
+            surface: None,
+        })
+    }
 
-        // Let render overlays update resources.
-        self.light_debug_pass.prepare(&self.queue, scene);
+    /// Request a GPU device to replace a lost one without borrowing the
+    /// renderer, so the request can run in the background with `spawn_local`
+    /// since browsers cannot block on it. The device is used once it is passed
+    /// to `install_device`.
+    ///
+    /// The canvas gets a new surface along with the device, because the old
+    /// surface belongs to the browser's lost graphics context.
+    #[cfg(target_arch = "wasm32")]
+    pub fn request_new_device(
+        &self,
+    ) -> Result<
+        impl std::future::Future<Output = Result<NewDevice, RendererError>> + 'static,
+        RendererError,
+    > {
+        use winit::platform::web::WindowExtWebSys;
 
-        // Copy updated per frame uniform values to the GPU.
-        self.per_frame_uniforms.update_gpu(&self.queue);
-    }
+        let instance = self.instance.clone();
+        let builder = self.builder.clone();
+        let surface = match &self.target {
+            RenderOutput::Window { window, .. } => window
+                .canvas()
+                .map(|canvas| instance.create_surface(wgpu::SurfaceTarget::Canvas(canvas)))
+                .transpose()?,
+            RenderOutput::Offscreen { .. } => None,
+        };
 
-    pub fn render(&mut self, scene: &Scene, delta: Duration) -> Result<(), wgpu::SurfaceError> {
-        // Prepare GPU resources for rendering.
-        self.prepare_render(scene, delta);
+        Ok(async move {
+            let adapter = builder
+                .select_adapter(&instance, surface.as_ref(), surface.is_none())
+                .await?;
+            let (device, queue) = Renderer::request_device(&adapter).await?;
 
-        // Start rendering the frame.
-        let backbuffer = self.surface.get_current_texture()?;
-        let view = backbuffer
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut command_encoder =
-            self.device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("Render loop encoder"),
-                });
+            Ok(NewDevice {
+                adapter,
+                device,
+                queue,
+                surface,
+            })
+        })
+    }
 
-        // Draw all models in the scene.
+    /// Replace the lost GPU device with `new_device`, see `recreate_device`.
+    pub fn install_device(&mut self, new_device: NewDevice) -> Result<(), RendererError> {
+        let NewDevice {
+            adapter,
+            device,
+            queue,
+            surface: new_surface,
+        } = new_device;
+
+        if let (Some(new_surface), RenderOutput::Window { surface, .. }) =
+            (new_surface, &mut self.target)
         {
-            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        // Clear the back buffer when rendering.
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        // Write the values from the fragment shader to the back
-                        // buffer.
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: self.depth_pass.depth_texture_view(),
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+            *surface = new_surface;
+        }
 
-            render_pass.set_pipeline(&self.render_pipeline);
+        let surface = match &self.target {
+            RenderOutput::Window { surface, .. } => Some(surface),
+            RenderOutput::Offscreen { .. } => None,
+        };
 
-            debug_assert!(!self.per_frame_uniforms.is_dirty());
-            render_pass.set_bind_group(0, self.per_frame_uniforms.bind_group(), &[]);
+        let mut surface_config = self.surface_config.clone();
 
-            for model in scene.models.iter() {
-                render_pass.draw_model(model, &self.model_shader_vals[model.model_sv_key]);
-            }
+        if let Some(surface) = surface {
+            surface_config = wgpu::SurfaceConfiguration {
+                desired_maximum_frame_latency: surface_config.desired_maximum_frame_latency,
+                ..Self::surface_config(surface, &adapter, self.window_size)?
+            };
+            surface.configure(&device, &surface_config);
         }
 
-        // Debug pass visualization.
-        self.light_debug_pass.draw(
-            &view,
-            self.depth_pass.depth_texture_view(),
-            &self.per_frame_uniforms,
-            &mut command_encoder,
+        // The offscreen texture is created on the new device, and is swapped
+        // for the window below when the renderer has one.
+        let texture = Self::create_offscreen_texture(&device, &surface_config);
+        let mut renderer = Self::from_device(
+            self.instance.clone(),
+            device,
+            queue,
+            surface_config,
+            RenderOutput::Offscreen { texture },
         );
 
-        // Depth pass visualization.
-        if self.debug_state.visualize_depth_pass {
-            self.depth_pass.draw(&view, &mut command_encoder);
+        if let RenderOutput::Window { .. } = &self.target {
+            std::mem::swap(&mut renderer.target, &mut self.target);
         }
 
-        // All done - submit commands for execution.
-        self.queue.submit(std::iter::once(command_encoder.finish()));
-        backbuffer.present();
+        // Carry over the state kept on the CPU.
+        if let Some(tonemapper) = self.tonemapper() {
+            renderer.set_tonemapper(tonemapper);
+        }
+
+        renderer.set_ssao_settings(self.ssao_settings());
+        renderer.set_outline_color(self.outline_pass.color());
+        renderer.set_normals_color(self.normals_color());
+        renderer.set_normals_length(self.normals_length());
+        renderer.per_model_uniforms = self.per_model_uniforms.recreate(
+            renderer.context.device(),
+            renderer.context.bind_group_layouts(),
+        );
+        renderer.model_shader_vals = std::mem::take(&mut self.model_shader_vals);
+        renderer.shared_slots = std::mem::take(&mut self.shared_slots);
+        renderer.selected = std::mem::take(&mut self.selected);
+        renderer.highlighted = self.highlighted;
+        renderer.debug_state = self.debug_state.clone();
+        renderer.debug_draw = std::mem::take(&mut self.debug_draw);
+        renderer.text = std::mem::take(&mut self.text);
+        renderer.sys_time_elapsed = self.sys_time_elapsed;
+        renderer.builder = self.builder.clone();
 
-        self.light_debug_pass.finish_frame();
+        *self = renderer;
+        info!("recreated the GPU device");
 
         Ok(())
     }
 
-    pub fn window_size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.window_size
+    pub fn input(&mut self, event: &winit::event::WindowEvent) {
+        self.debug_state.process_input(event);
+    }
+
+    /// Get the lights in `lights` that the lit shaders can read. Without
+    /// storage buffers only the first `max_uniform_lights` fit in the
+    /// per-frame uniforms, and the renderer warns once when lights are left
+    /// out.
+    fn drawable_lights<'l, T>(&mut self, lights: &'l [T], max_uniform_lights: usize) -> &'l [T] {
+        if self.light_cluster_pass.is_some() || lights.len() <= max_uniform_lights {
+            return lights;
+        }
+
+        if !self.warned_light_limit {
+            warn!("the scene has more lights than fit without storage buffers, some are not drawn");
+            self.warned_light_limit = true;
+        }
+
+        &lights[..max_uniform_lights]
     }
 
-    /// Returns a new model that can be added to a scene and rendered.
-    pub fn create_model(
+    fn prepare_render(
         &mut self,
-        mesh: Rc<Mesh>,
-        translation: Vec3,
-        rotation: Quat,
-        scale: Vec3,
-    ) -> Model {
-        Model::new(
-            self.model_shader_vals.insert(PerModelShaderVals::new(
-                &self.device,
-                &self.bind_group_layouts,
-            )),
-            mesh,
-            translation,
-            rotation,
-            scale,
-        )
+        scene: &Scene,
+        views: &[(Camera, Viewport)],
+        draw_overlays: bool,
+        delta: Duration,
+    ) {
+        // Models move with their entities, so their world transforms are
+        // composed before anything is culled or uploaded.
+        scene.update_transforms();
+        let models = scene.models().collect::<Vec<_>>();
+
+        // Make sure there is a set of per-frame shader uniforms for each
+        // viewport, since each viewport is drawn with a different camera.
+        while self.per_frame_uniforms.len() < views.len() {
+            self.per_frame_uniforms.push(PerFrameShaderVals::new(
+                self.context.device(),
+                self.context.bind_group_layouts(),
+            ));
+        }
+
+        let (directional_lights, spot_lights, point_lights) = (
+            scene.directional_lights(),
+            scene.spot_lights(),
+            scene.point_lights(),
+        );
+        let directional_lights =
+            self.drawable_lights(&directional_lights, lit_shader::MAX_DIRECTIONAL_LIGHTS);
+        let spot_lights = self.drawable_lights(&spot_lights, lit_shader::MAX_SPOT_LIGHTS);
+        let point_lights = self.drawable_lights(&point_lights, lit_shader::MAX_POINT_LIGHTS);
+
+        // Pick the point lights that cast shadows this frame so the lit shader
+        // knows which shadow map each light uses.
+        let point_shadow_maps = self.point_shadow_pass.prepare(
+            self.context.device(),
+            self.context.queue(),
+            point_lights,
+        );
+        let casts_point_shadows = point_shadow_maps.iter().any(Option::is_some);
+        let packed_point_lights = point_lights
+            .iter()
+            .zip(point_shadow_maps)
+            .map(|(light, shadow_map)| PackedPointLight::new(light, shadow_map))
+            .collect::<Vec<_>>();
+
+        let packed_directional_lights = directional_lights
+            .iter()
+            .map(|light| PackedDirectionalLight::from(light.clone()))
+            .collect::<Vec<_>>();
+        let packed_spot_lights = spot_lights
+            .iter()
+            .map(|light| PackedSpotLight::from(light.clone()))
+            .collect::<Vec<_>>();
+
+        self.lighting_resources.write_lights(
+            self.context.device(),
+            self.context.queue(),
+            &packed_directional_lights,
+            &packed_spot_lights,
+        );
+
+        // Bin the point lights into each viewport's clusters.
+        let light_cluster_planes = self.light_cluster_pass.as_mut().map(|pass| {
+            pass.prepare(
+                self.context.device(),
+                self.context.queue(),
+                &packed_point_lights,
+                views.iter().map(|(camera, _)| camera),
+            )
+        });
+
+        // Update renderer per-frame shader uniforms.
+        self.sys_time_elapsed += delta;
+
+        for (view_index, (per_frame_uniforms, (camera, viewport))) in
+            self.per_frame_uniforms.iter_mut().zip(views).enumerate()
+        {
+            per_frame_uniforms.set_time_elapsed_seconds(self.sys_time_elapsed);
+            per_frame_uniforms.set_view_projection(camera.view_projection_matrix());
+            per_frame_uniforms.set_view_pos(camera.eye());
+            per_frame_uniforms.set_view_axes(camera.right(), camera.up());
+
+            // Update renderer per-scene shader uniforms.
+            per_frame_uniforms.clear_lights();
+
+            match &light_cluster_planes {
+                Some(clip_planes) => {
+                    per_frame_uniforms
+                        .set_storage_light_counts(directional_lights.len(), spot_lights.len());
+
+                    let (z_near, z_far) = clip_planes[view_index];
+                    per_frame_uniforms.set_light_clusters(
+                        viewport,
+                        z_near,
+                        z_far,
+                        view_index as u32 * passes::LightClusterPass::CLUSTERS_PER_VIEW,
+                    );
+                }
+                None => {
+                    for light in directional_lights {
+                        per_frame_uniforms.add_directional_light(light);
+                    }
+
+                    for light in spot_lights {
+                        per_frame_uniforms.add_spot_light(light);
+                    }
+
+                    for light in &packed_point_lights {
+                        per_frame_uniforms.add_point_light(*light);
+                    }
+                }
+            }
+
+            // Copy updated per frame uniform values to the GPU.
+            per_frame_uniforms.update_gpu(self.context.queue());
+        }
+
+        self.stats.uploaded_bytes = views.len() as u64 * PerFrameShaderVals::GPU_SIZE;
+
+        // Cull models that are outside of every viewport's frustum.
+        self.visible_models
+            .resize_with(views.len(), VisibleModels::default);
+        let mut in_any_view = vec![false; models.len()];
+        let model_indices = scene
+            .entity_models()
+            .enumerate()
+            .map(|(model_index, (id, _))| (id, model_index))
+            .collect::<SecondaryMap<_, _>>();
+
+        for ((camera, _), visible) in views.iter().zip(&mut self.visible_models) {
+            visible.models.clear();
+
+            scene.query_frustum(&camera.frustum(), |id| {
+                if let Some(&model_index) = model_indices.get(id) {
+                    visible.models.push(model_index);
+                    in_any_view[model_index] = true;
+                }
+            });
+
+            // The tree visits models in no particular order, so sort them to
+            // keep draw order the same from frame to frame.
+            visible.models.sort_unstable();
+        }
+
+        self.stats.models_drawn = self.visible_models[..views.len()]
+            .iter()
+            .map(|visible| visible.models.len())
+            .sum();
+        self.stats.models_culled = views.len() * models.len() - self.stats.models_drawn;
+
+        // Update uniforms for each model that will be rendered. Lights are
+        // shared by all models, so only models that have moved need to be
+        // copied to the GPU. Culled models are left dirty until they are
+        // visible, unless they may cast a shadow into view. Every model's
+        // uniforms live in one buffer, and the models that changed are copied
+        // to the GPU together.
+        self.stats.model_uniform_uploads = 0;
+
+        for (model, in_view) in models.iter().zip(in_any_view) {
+            // Models are assigned shader values the first time they are drawn,
+            // and again if theirs were freed by `destroy_model`.
+            let model_sv_key = match model
+                .model_sv_key()
+                .filter(|key| self.model_shader_vals.contains_key(*key))
+            {
+                Some(key) => key,
+                None => {
+                    let key = self
+                        .model_shader_vals
+                        .insert(self.per_model_uniforms.insert());
+                    model.set_model_sv_key(key);
+                    key
+                }
+            };
+
+            if !in_view && !casts_point_shadows {
+                continue;
+            }
+
+            // Does the transform matrix need to be updated?
+            if model.is_model_sv_dirty() {
+                // A model that moves away from the models it shares shader
+                // values with gets its own, so the others stay where they are.
+                let slot = self.model_shader_vals[model_sv_key];
+
+                if self.shared_slots.contains_key(&slot) {
+                    self.release_slot(slot);
+                    self.model_shader_vals[model_sv_key] = self.per_model_uniforms.insert();
+                }
+
+                self.per_model_uniforms.set_local_to_world(
+                    self.model_shader_vals[model_sv_key],
+                    model.local_to_world(),
+                );
+
+                // Mark the model as updated since its shader values are copied
+                // to the GPU below.
+                model.mark_model_sv_updated();
+                self.stats.model_uniform_uploads += 1;
+            }
+
+            // Copy any material changes to the GPU. Meshes can be shared by
+            // many models but are only uploaded once since uploading clears
+            // the dirty flag.
+            for submesh in model.mesh().submeshes().iter().filter(|s| s.is_dirty()) {
+                submesh.update_gpu(self.context.queue());
+            }
+        }
+
+        self.stats.uploaded_bytes += self.per_model_uniforms.update_gpu(
+            self.context.device(),
+            self.context.queue(),
+            self.context.bind_group_layouts(),
+        );
+
+        // Group each viewport's models by mesh so models sharing a mesh are
+        // drawn with one instanced draw call. Selected models are drawn apart
+        // from unselected ones because they write a different stencil value.
+        let mut visible_models = std::mem::take(&mut self.visible_models);
+        let mut first_instance = 0;
+        self.stats.model_batches = 0;
+
+        for visible in &mut visible_models[..views.len()] {
+            visible.models.sort_by_key(|&model_index| {
+                let model = models[model_index];
+                (Rc::as_ptr(model.mesh()) as usize, self.is_selected(model))
+            });
+
+            visible.first_instance = first_instance;
+            first_instance += visible.models.len() as u32;
+            visible.batches.clear();
+
+            let mut batch_start = 0;
+
+            for (i, pair) in visible.models.windows(2).enumerate() {
+                let (a, b) = (models[pair[0]], models[pair[1]]);
+
+                if !Rc::ptr_eq(a.mesh(), b.mesh()) || self.is_selected(a) != self.is_selected(b) {
+                    visible.batches.push(batch_start..i + 1);
+                    batch_start = i + 1;
+                }
+            }
+
+            if batch_start < visible.models.len() {
+                visible.batches.push(batch_start..visible.models.len());
+            }
+
+            self.stats.model_batches += visible.batches.len();
+        }
+
+        // Opaque submeshes are drawn once per batch and transparent submeshes
+        // once per model, since they are sorted by distance.
+        self.stats.draw_calls = 0;
+        self.stats.triangles = 0;
+
+        for visible in &visible_models[..views.len()] {
+            for batch in &visible.batches {
+                let mesh = models[visible.models[batch.start]].mesh();
+
+                for submesh in mesh.submeshes() {
+                    self.stats.draw_calls += if submesh.is_transparent() {
+                        batch.len()
+                    } else {
+                        1
+                    };
+                    self.stats.triangles += submesh.indices().len() / 3 * batch.len();
+                }
+            }
+        }
+
+        self.model_instances.set_instances(
+            self.context.device(),
+            visible_models[..views.len()]
+                .iter()
+                .flat_map(|visible| &visible.models)
+                .map(|&model_index| ModelInstance::new(models[model_index])),
+        );
+        self.model_instances.update_gpu(self.context.queue());
+        self.stats.uploaded_bytes +=
+            (first_instance as usize * std::mem::size_of::<ModelInstance>()) as u64;
+        self.visible_models = visible_models;
+
+        // Debug shapes, HUD sprites, text and the UI are kept for the next frame
+        // drawn with overlays.
+        if draw_overlays {
+            self.prepare_overlays(scene, views);
+        }
+
+        if let Some((camera, _)) = views.first() {
+            // Billboards are sorted for the first viewport's camera. Other
+            // viewports may blend overlapping billboards in the wrong order.
+            self.billboard_pass.prepare(
+                self.context.device(),
+                self.context.queue(),
+                &scene.billboards,
+                camera,
+            );
+        }
+
+        self.skybox_pass.prepare(
+            self.context.device(),
+            self.context.queue(),
+            scene.skybox.as_ref(),
+            views.iter().map(|(camera, _)| camera),
+        );
+
+        self.ssao_pass.prepare(
+            self.context.device(),
+            self.context.queue(),
+            &self.surface_config,
+            &self.depth_pass,
+            views.iter().map(|(camera, viewport)| (camera, viewport)),
+        );
+
+        // Point shadows, SSAO and light clusters may have replaced the
+        // resources the lit shaders read.
+        self.lighting_resources.update(
+            self.context.device(),
+            self.context.bind_group_layouts(),
+            &self.point_shadow_pass,
+            &self.ssao_pass,
+            self.light_cluster_pass.as_ref(),
+        );
+
+        self.outline_pass.prepare(self.context.queue());
+        self.normals_pass.prepare(self.context.queue());
+        self.post_process_stack.prepare(self.context.queue());
+    }
+
+    /// Prepare the debug shapes and screen space overlays drawn over a frame.
+    fn prepare_overlays(&mut self, scene: &Scene, views: &[(Camera, Viewport)]) {
+        // Outline the highlighted model's bounding box.
+        if let Some(model) = scene
+            .models()
+            .find(|m| m.model_sv_key().is_some() && m.model_sv_key() == self.highlighted)
+        {
+            self.add_bounds_box(model, Self::HIGHLIGHT_BOUNDS_COLOR);
+        }
+
+        // Draw the bounding volumes of models that ask for them, or the boxes
+        // of every model while bounds are visualized.
+        for model in scene.models() {
+            let debug_bounds = model.debug_bounds();
+
+            if debug_bounds.aabb || self.debug_state.visualize_bounds {
+                self.add_bounds_box(model, Self::DEBUG_BOUNDS_COLOR);
+            }
+
+            if debug_bounds.sphere {
+                let bounds = model.world_bounds();
+                self.light_debug_pass.add_wire_sphere(
+                    bounds.center(),
+                    bounds.size().length() * 0.5 * (1.0 + Self::BOUNDS_MARGIN),
+                    Self::DEBUG_BOUNDING_SPHERE_COLOR,
+                );
+            }
+        }
+
+        // Debug overlays are only drawn in the first viewport.
+        self.light_debug_pass
+            .set_lines(self.context.device(), &self.debug_draw);
+
+        if let Some((camera, _)) = views.first() {
+            self.light_debug_pass
+                .prepare(self.context.queue(), scene, camera, &self.debug_state);
+        }
+
+        self.sprite_pass.prepare(
+            self.context.device(),
+            self.context.queue(),
+            &scene.sprites,
+            self.window_size.width,
+            self.window_size.height,
+        );
+        self.text_pass.prepare(
+            self.context.device(),
+            self.context.queue(),
+            &self.text,
+            self.window_size.width,
+            self.window_size.height,
+        );
+        self.ui_pass.prepare(
+            self.context.device(),
+            self.context.queue(),
+            self.window_size.width,
+            self.window_size.height,
+        );
+    }
+
+    /// Render `scene` with its camera into the entire render target.
+    pub fn render(&mut self, scene: &Scene, delta: Duration) -> Result<(), wgpu::SurfaceError> {
+        let viewport = Viewport::full(self.window_size.width, self.window_size.height);
+        self.render_viewports(scene, &[(scene.camera.clone(), viewport)], delta)
+    }
+
+    /// Render `scene` once for each camera and viewport pair, eg to draw two
+    /// player split-screen. The viewports should not overlap.
+    ///
+    /// Each camera's aspect ratio is taken from its viewport rather than the
+    /// size of the render target.
+    pub fn render_viewports(
+        &mut self,
+        scene: &Scene,
+        views: &[(Camera, Viewport)],
+        delta: Duration,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let backbuffer = match &self.target {
+            RenderOutput::Window { surface, .. } => Some(surface.get_current_texture()?),
+            RenderOutput::Offscreen { .. } => None,
+        };
+        let view = match (&self.target, &backbuffer) {
+            (_, Some(backbuffer)) => &backbuffer.texture,
+            (RenderOutput::Offscreen { texture }, None) => texture,
+            (RenderOutput::Window { .. }, None) => unreachable!(),
+        }
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.draw_frame(scene, views, &view, true, delta);
+
+        if let Some(backbuffer) = backbuffer {
+            backbuffer.present();
+        }
+
+        Ok(())
+    }
+
+    /// Create a `width` by `height` texture that scenes can be rendered into
+    /// with `render_to_target`. The target is tonemapped with the renderer's
+    /// current tonemapper, but other post effects are not applied to it.
+    pub fn create_render_target(&self, width: u32, height: u32) -> RenderTarget {
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            format: RenderTarget::TEXTURE_FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            ..self.surface_config.clone()
+        };
+
+        let device = self.context.device();
+        let mut post_process_stack = passes::PostProcessStack::new(device, &surface_config);
+        let mut tonemap =
+            post_process::TonemapEffect::new(device, post_process_stack.input_layout());
+
+        if let Some(tonemapper) = self.tonemapper() {
+            tonemap.set_tonemapper(tonemapper);
+        }
+
+        post_process_stack.push(Box::new(tonemap));
+        RenderTarget::new(device, surface_config, post_process_stack)
+    }
+
+    /// Render `scene` from `camera` into `target` rather than the window. The
+    /// camera's aspect ratio is taken from the target.
+    ///
+    /// Debug shapes, sprites, text and the UI are only drawn over the window,
+    /// so they are kept for the next call to `render`. Call this before
+    /// `render` for models in the window to show what was drawn into the
+    /// target in the same frame.
+    pub fn render_to_target(&mut self, scene: &Scene, camera: &Camera, target: &mut RenderTarget) {
+        let view = target
+            .texture()
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let viewport = Viewport::full(target.size.width, target.size.height);
+
+        // Draw with the target's screen sized textures in place of the
+        // renderer's. Time is not advanced since the frame is not shown.
+        self.swap_frame_textures(target);
+        self.draw_frame(
+            scene,
+            &[(camera.clone(), viewport)],
+            &view,
+            false,
+            Duration::ZERO,
+        );
+        self.swap_frame_textures(target);
+    }
+
+    /// Exchange the renderer's screen sized textures and surface description
+    /// with `target`'s.
+    fn swap_frame_textures(&mut self, target: &mut RenderTarget) {
+        std::mem::swap(&mut self.surface_config, &mut target.surface_config);
+        std::mem::swap(&mut self.window_size, &mut target.size);
+        std::mem::swap(&mut self.depth_pass, &mut target.depth_pass);
+        std::mem::swap(&mut self.post_process_stack, &mut target.post_process_stack);
+        self.ssao_pass.swap_targets(&mut target.ssao_targets);
+    }
+
+    /// Draw `scene` once for each camera and viewport pair into `view`. Debug
+    /// shapes and screen space overlays are drawn over the scene when
+    /// `draw_overlays` is true, and cleared afterwards.
+    fn draw_frame(
+        &mut self,
+        scene: &Scene,
+        views: &[(Camera, Viewport)],
+        view: &wgpu::TextureView,
+        draw_overlays: bool,
+        delta: Duration,
+    ) {
+        // Pick up GPU pass timings from an earlier frame if they are ready.
+        self.gpu_timer
+            .collect_results(self.context.device(), &mut self.stats);
+
+        // Fit each viewport to the render target and match the camera's aspect
+        // ratio to it.
+        let views = views
+            .iter()
+            .map(|(camera, viewport)| {
+                (
+                    camera,
+                    viewport.clamp_to(self.window_size.width, self.window_size.height),
+                )
+            })
+            .filter(|(_, viewport)| !viewport.is_empty())
+            .map(|(camera, viewport)| {
+                let mut camera = camera.clone();
+                camera
+                    .set_viewport_size(viewport.width, viewport.height)
+                    .expect("empty viewports are skipped");
+                (camera, viewport)
+            })
+            .collect::<Vec<_>>();
+
+        // Prepare GPU resources for rendering.
+        self.prepare_render(scene, &views, draw_overlays, delta);
+
+        // Visible models are recorded by their position in the scene's models.
+        let models = scene.models().collect::<Vec<_>>();
+
+        // Start rendering the frame.
+        let mut command_encoder =
+            self.context
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render loop encoder"),
+                });
+
+        // Passes are scheduled by the resources they read and write. Passes
+        // that do not depend on each other run in the order they are added.
+        let mut transient_textures = std::mem::take(&mut self.transient_textures);
+        let mut graph = RenderGraph::new();
+        let mut shadow_maps = graph.import("point shadow maps");
+        let mut light_clusters = graph.import("light clusters");
+        let mut occlusion = graph.import("ambient occlusion");
+        let mut depth = graph.import("depth buffer");
+        let mut hdr = graph.import("scene texture");
+        let mut target = graph.import("render target");
+
+        // Draw the scene into the post processing stack's HDR texture, which is
+        // tonemapped into the render target after the whole scene is drawn.
+        let hdr_view = self.post_process_stack.scene_texture_view();
+        let depth_view = self.depth_pass.depth_texture_view();
+
+        // Draw shadow casting point light shadow maps before the models that
+        // sample them.
+        let mut pass = graph.add_pass("point shadows");
+        shadow_maps = pass.write(shadow_maps);
+        pass.execute(|command_encoder, _| {
+            self.point_shadow_pass.draw(
+                models.iter().map(|&m| (m, self.model_sv(m))),
+                command_encoder,
+            );
+        });
+
+        // Bin point lights into clusters before the models that are lit by them.
+        if let Some(light_cluster_pass) = &self.light_cluster_pass {
+            let mut pass = graph.add_pass("light clusters");
+            light_clusters = pass.write(light_clusters);
+            pass.execute(|command_encoder, _| light_cluster_pass.draw(command_encoder));
+        }
+
+        // Draw ambient occlusion before the models that are darkened by it.
+        if self.ssao_pass.is_enabled() {
+            let raw_occlusion = graph.create_texture(passes::SsaoPass::raw_occlusion_desc(
+                self.surface_config.width,
+                self.surface_config.height,
+            ));
+
+            // The raw occlusion handle is moved into the passes, so borrow
+            // everything else they use.
+            let (renderer, views, models) = (&*self, &views, &models);
+
+            let mut pass = graph.add_pass("ssao");
+            depth = pass.write(depth);
+            let raw_occlusion = pass.write(raw_occlusion);
+            pass.execute(move |command_encoder, textures| {
+                renderer.ssao_pass.draw(
+                    depth_view,
+                    textures.view(raw_occlusion),
+                    views
+                        .iter()
+                        .zip(&renderer.per_frame_uniforms)
+                        .zip(&renderer.visible_models)
+                        .map(|(((_, viewport), per_frame_uniforms), visible)| {
+                            let models = visible.models.iter().map(|&model_index| {
+                                let model = models[model_index];
+                                (model, renderer.model_sv(model))
+                            });
+                            (viewport, per_frame_uniforms, models)
+                        }),
+                    command_encoder,
+                );
+            });
+
+            let mut pass = graph.add_pass("ssao blur");
+            pass.read(raw_occlusion);
+            occlusion = pass.write(occlusion);
+            pass.execute(move |command_encoder, textures| {
+                renderer.ssao_pass.blur(
+                    renderer.context.device(),
+                    textures.view(raw_occlusion),
+                    views.iter().map(|(_, viewport)| viewport),
+                    command_encoder,
+                );
+            });
+        }
+
+        // Draw all models in the scene.
+        let mut pass = graph.add_pass("main");
+        pass.read(shadow_maps);
+        pass.read(light_clusters);
+        pass.read(occlusion);
+        hdr = pass.write(hdr);
+        depth = pass.write(depth);
+        pass.execute(|command_encoder, _| {
+            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: hdr_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Clear the back buffer when rendering. The whole
+                        // target is cleared once rather than per viewport.
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.0,
+                            g: 0.0,
+                            b: 0.0,
+                            a: 1.0,
+                        }),
+                        // Write the values from the fragment shader to the back
+                        // buffer.
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: self.gpu_timer.timestamp_writes(TimedPass::Main),
+            });
+
+            // The depth and stencil buffers were cleared when the pass began, and
+            // the scissor rect keeps each viewport from writing into another.
+            render_pass.set_vertex_buffer(1, self.model_instances.gpu_buffer_slice(..));
+
+            for (((_, viewport), per_frame_uniforms), visible) in views
+                .iter()
+                .zip(&self.per_frame_uniforms)
+                .zip(&self.visible_models)
+            {
+                viewport.apply(&mut render_pass);
+
+                debug_assert!(!per_frame_uniforms.is_dirty());
+                render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+                render_pass.set_bind_group(2, self.lighting_resources.bind_group(), &[]);
+
+                for batch in &visible.batches {
+                    let model = models[visible.models[batch.start]];
+
+                    render_pass.set_stencil_reference(if self.is_selected(model) {
+                        passes::OutlinePass::STENCIL_SELECTED
+                    } else {
+                        0
+                    });
+                    render_pass.draw_mesh_instances(
+                        model.mesh(),
+                        visible.instances(batch.clone()),
+                        &self.render_pipelines,
+                    );
+                }
+            }
+        });
+
+        // Fill in the background behind the models with the skybox.
+        if self.skybox_pass.has_skybox() {
+            let mut pass = graph.add_pass("skybox");
+            pass.read(depth);
+            hdr = pass.write(hdr);
+            pass.execute(|command_encoder, _| {
+                for (view_index, (_, viewport)) in views.iter().enumerate() {
+                    self.skybox_pass.draw(
+                        hdr_view,
+                        depth_view,
+                        view_index,
+                        viewport,
+                        command_encoder,
+                    );
+                }
+            });
+        }
+
+        // Blend transparent submeshes over the opaque models and the skybox.
+        let has_transparent_submeshes = models
+            .iter()
+            .any(|model| model.mesh().submeshes().iter().any(Submesh::is_transparent));
+
+        if has_transparent_submeshes {
+            // Transparent submeshes write the stencil value for outlines.
+            let mut pass = graph.add_pass("transparent");
+            pass.read(shadow_maps);
+            pass.read(light_clusters);
+            pass.read(occlusion);
+            hdr = pass.write(hdr);
+            depth = pass.write(depth);
+            pass.execute(|command_encoder, _| {
+                let mut render_pass =
+                    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("Transparent render pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: hdr_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: depth_view,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            }),
+                        }),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                    });
+
+                render_pass.set_vertex_buffer(1, self.model_instances.gpu_buffer_slice(..));
+
+                for (((camera, viewport), per_frame_uniforms), visible) in views
+                    .iter()
+                    .zip(&self.per_frame_uniforms)
+                    .zip(&self.visible_models)
+                {
+                    viewport.apply(&mut render_pass);
+                    render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+                    render_pass.set_bind_group(2, self.lighting_resources.bind_group(), &[]);
+
+                    // Transparent submeshes do not write depth, so they are drawn
+                    // from the furthest to the closest for nearer submeshes to
+                    // blend over further ones.
+                    let mut sorted = visible
+                        .models
+                        .iter()
+                        .zip(visible.instances(0..visible.models.len()))
+                        .map(|(&model_index, instance)| (models[model_index], instance))
+                        .flat_map(|(model, instance)| {
+                            let distance =
+                                model.world_bounds().center().distance_squared(camera.eye());
+                            model
+                                .mesh()
+                                .submeshes()
+                                .iter()
+                                .filter(|submesh| submesh.is_transparent())
+                                .map(move |submesh| (distance, model, submesh, instance))
+                        })
+                        .collect::<Vec<_>>();
+                    sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+                    for (_, model, submesh, instance) in sorted {
+                        render_pass.set_stencil_reference(if self.is_selected(model) {
+                            passes::OutlinePass::STENCIL_SELECTED
+                        } else {
+                            0
+                        });
+                        render_pass.draw_transparent_submesh(
+                            model.mesh(),
+                            submesh,
+                            instance,
+                            &self.render_pipelines,
+                        );
+                    }
+                }
+            });
+        }
+
+        // Draw billboards over the models with alpha blending.
+        if self.billboard_pass.has_billboards() {
+            let mut pass = graph.add_pass("billboards");
+            pass.read(depth);
+            hdr = pass.write(hdr);
+            pass.execute(|command_encoder, _| {
+                for ((_, viewport), per_frame_uniforms) in
+                    views.iter().zip(&self.per_frame_uniforms)
+                {
+                    self.billboard_pass.draw(
+                        hdr_view,
+                        depth_view,
+                        per_frame_uniforms,
+                        viewport,
+                        command_encoder,
+                    );
+                }
+            });
+        }
+
+        // Outline any selected models.
+        if !self.selected.is_empty() {
+            let mut pass = graph.add_pass("outlines");
+            pass.read(depth);
+            hdr = pass.write(hdr);
+            pass.execute(|command_encoder, _| {
+                for (((_, viewport), per_frame_uniforms), visible) in views
+                    .iter()
+                    .zip(&self.per_frame_uniforms)
+                    .zip(&self.visible_models)
+                {
+                    self.outline_pass.draw(
+                        hdr_view,
+                        depth_view,
+                        per_frame_uniforms,
+                        viewport,
+                        visible
+                            .models
+                            .iter()
+                            .map(|&model_index| models[model_index])
+                            .filter(|m| self.is_selected(m))
+                            .map(|m| (m, self.model_sv(m))),
+                        command_encoder,
+                    );
+                }
+            });
+        }
+
+        // Draw the vertex normals of selected models over the scene.
+        if self.debug_state.visualize_normals && !self.selected.is_empty() {
+            let mut pass = graph.add_pass("normals");
+            pass.read(depth);
+            hdr = pass.write(hdr);
+            pass.execute(|command_encoder, _| {
+                for (((_, viewport), per_frame_uniforms), visible) in views
+                    .iter()
+                    .zip(&self.per_frame_uniforms)
+                    .zip(&self.visible_models)
+                {
+                    self.normals_pass.draw(
+                        hdr_view,
+                        depth_view,
+                        per_frame_uniforms,
+                        viewport,
+                        visible
+                            .models
+                            .iter()
+                            .map(|&model_index| models[model_index])
+                            .filter(|m| self.is_selected(m))
+                            .map(|m| (m, self.model_sv(m))),
+                        command_encoder,
+                    );
+                }
+            });
+        }
+
+        // Debug pass visualization.
+        if let (true, true, Some((_, viewport))) = (
+            draw_overlays,
+            self.light_debug_pass.has_instances(),
+            views.first(),
+        ) {
+            let mut pass = graph.add_pass("light debug");
+            pass.read(depth);
+            hdr = pass.write(hdr);
+            pass.execute(|command_encoder, _| {
+                self.light_debug_pass.draw(
+                    hdr_view,
+                    depth_view,
+                    viewport,
+                    command_encoder,
+                    self.gpu_timer.timestamp_writes(TimedPass::LightDebug),
+                );
+            });
+        }
+
+        // Apply post effects to the scene and copy it to the render target.
+        let mut pass = graph.add_pass("post process");
+        pass.read(hdr);
+        target = pass.write(target);
+        pass.execute(|command_encoder, _| self.post_process_stack.draw(view, command_encoder));
+
+        // Depth pass visualization, which is drawn over the tonemapped scene.
+        if let (true, true, Some((camera, _))) = (
+            draw_overlays,
+            self.debug_state.visualize_depth_pass,
+            views.first(),
+        ) {
+            let mut pass = graph.add_pass("depth visualization");
+            pass.read(depth);
+            target = pass.write(target);
+            pass.execute(|command_encoder, _| {
+                self.depth_pass.draw(
+                    self.context.queue(),
+                    camera,
+                    self.debug_state.depth_quad_placement,
+                    view,
+                    command_encoder,
+                    self.gpu_timer.timestamp_writes(TimedPass::Depth),
+                );
+            });
+        }
+
+        // Sprites are drawn over the tonemapped scene, eg as a HUD.
+        if draw_overlays && self.sprite_pass.has_sprites() {
+            let mut pass = graph.add_pass("sprites");
+            target = pass.write(target);
+            pass.execute(|command_encoder, _| self.sprite_pass.draw(view, command_encoder));
+        }
+
+        // Text is drawn last so it stays readable over every other overlay.
+        if draw_overlays && !self.text.is_empty() {
+            let mut pass = graph.add_pass("text");
+            target = pass.write(target);
+            pass.execute(|command_encoder, _| self.text_pass.draw(view, command_encoder));
+        }
+
+        // The UI is drawn over everything else.
+        if draw_overlays && self.ui_pass.has_ui() {
+            let mut pass = graph.add_pass("ui");
+            pass.write(target);
+            pass.execute(|command_encoder, _| {
+                self.ui_pass.draw(
+                    view,
+                    self.window_size.width,
+                    self.window_size.height,
+                    command_encoder,
+                )
+            });
+        }
+
+        graph
+            .execute(
+                self.context.device(),
+                &mut transient_textures,
+                &mut command_encoder,
+            )
+            .expect("renderer passes do not depend on each other in a cycle");
+        self.transient_textures = transient_textures;
+
+        // Resolve GPU pass timings now that all timed passes are recorded.
+        self.gpu_timer.resolve(&mut command_encoder);
+
+        // All done - submit commands for execution.
+        self.context
+            .queue()
+            .submit(std::iter::once(command_encoder.finish()));
+
+        self.gpu_timer.map_results();
+
+        if draw_overlays {
+            self.light_debug_pass.finish_frame();
+            self.debug_draw.clear();
+            self.text.clear();
+        }
+    }
+
+    pub fn window_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.window_size
+    }
+
+    /// Copy the most recently rendered frame back from the GPU. This is only
+    /// supported by headless renderers.
+    #[allow(dead_code)]
+    pub fn read_pixels(&self) -> anyhow::Result<image::RgbaImage> {
+        let RenderOutput::Offscreen { texture } = &self.target else {
+            bail!("read_pixels is only supported by headless renderers");
+        };
+
+        let (width, height) = (texture.width(), texture.height());
+
+        // Rows copied out of a texture must be padded to a fixed alignment.
+        let unpadded_bytes_per_row = width * 4;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let readback_buffer = self
+            .context
+            .device()
+            .create_buffer(&wgpu::BufferDescriptor {
+                label: Some("read pixels buffer"),
+                size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+
+        let mut command_encoder =
+            self.context
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("read pixels encoder"),
+                });
+
+        command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        self.context
+            .queue()
+            .submit(std::iter::once(command_encoder.finish()));
+
+        // Block until the copy has finished and the buffer is mapped.
+        let (sender, receiver) = mpsc::channel();
+        readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.context.device().poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        // Strip the row padding while copying the pixels into the image.
+        let pixels = {
+            let mapped_range = readback_buffer.slice(..).get_mapped_range();
+            mapped_range
+                .chunks_exact(padded_bytes_per_row as usize)
+                .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        readback_buffer.unmap();
+
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("read back pixels do not match the texture size"))
+    }
+
+    /// Draw a solid colored cube in the next rendered frame. The cube is one
+    /// unit wide before `local_to_world` is applied, and is not lit. This is
+    /// intended for visualizing positions and bounds while debugging a game.
+    pub fn add_debug_cube(&mut self, local_to_world: Mat4, color: Vec3) {
+        self.light_debug_pass.add_cube(local_to_world, color);
+    }
+
+    /// Get the immediate mode lines drawn over the next rendered frame. Lines
+    /// are cleared after each frame, so they must be added again every frame
+    /// they should be visible.
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Draw the tessellated output of an egui frame over the next rendered
+    /// frame, and apply the texture changes egui asked for. The UI stays on
+    /// screen until it is replaced by the next call.
+    pub fn set_ui(
+        &mut self,
+        primitives: Vec<egui::ClippedPrimitive>,
+        textures_delta: egui::TexturesDelta,
+        pixels_per_point: f32,
+    ) {
+        self.ui_pass
+            .set_frame(primitives, textures_delta, pixels_per_point);
+    }
+
+    /// Get the screen space text drawn over the next rendered frame. Like
+    /// `debug_draw`, text is cleared after each frame.
+    pub fn text(&mut self) -> &mut TextRenderer {
+        &mut self.text
+    }
+
+    /// Create a `num_rows` by `num_cols` grid of models that share `mesh`. The
+    /// grid lies on the XZ plane centered on the origin, with models `spacing`
+    /// units apart. Each model is assigned shader values right away rather than
+    /// the first time it is drawn.
+    pub fn create_model_grid(
+        &mut self,
+        mesh: Rc<Mesh>,
+        num_rows: usize,
+        num_cols: usize,
+        spacing: f32,
+    ) -> Vec<Model> {
+        let center = Vec3::new(
+            num_cols.saturating_sub(1) as f32,
+            0.0,
+            num_rows.saturating_sub(1) as f32,
+        ) * spacing
+            * 0.5;
+
+        instancing::grid_positions(num_rows, num_cols, spacing)
+            .map(|position| {
+                let model = Model::new(mesh.clone(), position - center, Quat::IDENTITY, Vec3::ONE);
+                let key = self
+                    .model_shader_vals
+                    .insert(self.per_model_uniforms.insert());
+                model.set_model_sv_key(key);
+                model
+            })
+            .collect()
+    }
+
+    /// Create a model for each mesh in `meshes` that all share one transform,
+    /// eg the separate parts of a static building. The models share a single
+    /// slot in the per-model uniform buffer rather than each getting their
+    /// own, and the shared transform is uploaded to the GPU once here instead
+    /// of when the models are first drawn.
+    ///
+    /// Each model can still be selected, highlighted and destroyed on its own.
+    /// A model that is moved is given its own slot the next time it is drawn,
+    /// so these models save the most memory when used for geometry that never
+    /// moves.
+    #[allow(dead_code)]
+    pub fn create_shared_models(
+        &mut self,
+        meshes: impl IntoIterator<Item = Rc<Mesh>>,
+        translation: Vec3,
+        rotation: Quat,
+        scale: Vec3,
+    ) -> Vec<Model> {
+        let slot = self.per_model_uniforms.insert();
+        self.per_model_uniforms.set_local_to_world(
+            slot,
+            Mat4::from_scale_rotation_translation(scale, rotation, translation),
+        );
+        self.per_model_uniforms.update_gpu(
+            self.context.device(),
+            self.context.queue(),
+            self.context.bind_group_layouts(),
+        );
+
+        let models: Vec<_> = meshes
+            .into_iter()
+            .map(|mesh| {
+                let model = Model::new(mesh, translation, rotation, scale);
+                model.set_model_sv_key(self.model_shader_vals.insert(slot));
+                model.mark_model_sv_updated();
+                model
+            })
+            .collect();
+
+        match models.len() {
+            0 => self.per_model_uniforms.remove(slot),
+            1 => {}
+            count => {
+                self.shared_slots.insert(slot, count);
+            }
+        }
+
+        models
+    }
+
+    /// Free the shader values of `model` so its slot in the per-model uniform
+    /// buffer can be reused. Models that are removed from a scene without being
+    /// destroyed keep their slot for as long as the renderer lives. The model's
+    /// mesh is freed once no other model draws it.
+    ///
+    /// Models created by `create_shared_models` share one slot, which is
+    /// freed once the last of them is destroyed.
+    pub fn destroy_model(&mut self, model: Model) {
+        let Some(key) = model.model_sv_key() else {
+            return;
+        };
+
+        if let Some(slot) = self.model_shader_vals.remove(key) {
+            self.release_slot(slot);
+        }
+
+        self.selected.retain(|selected| *selected != key);
+
+        if self.highlighted == Some(key) {
+            self.highlighted = None;
+        }
+    }
+
+    /// Set which models should be drawn with a selection outline. Any previously
+    /// selected models are unselected.
+    ///
+    /// Models are identified by `Model::model_sv_key`, which is assigned the
+    /// first time a model is rendered.
+    pub fn set_selected(&mut self, models: &[ModelShaderValsKey]) {
+        self.selected.clear();
+        self.selected.extend_from_slice(models);
+    }
+
+    /// Draw the bounding box of `model` each frame, eg to show which model was
+    /// picked. Only one model can be highlighted at a time, and `None` removes
+    /// the highlight.
+    pub fn set_highlighted(&mut self, model: Option<ModelShaderValsKey>) {
+        self.highlighted = model;
+    }
+
+    /// Set the color of the selection outline drawn around selected models.
+    pub fn set_outline_color(&mut self, color: Vec3) {
+        self.outline_pass.set_color(color);
+    }
+
+    /// Get the color of the vertex normals drawn for selected models.
+    pub fn normals_color(&self) -> Vec3 {
+        self.normals_pass.color()
+    }
+
+    /// Set the color of the vertex normals drawn for selected models.
+    pub fn set_normals_color(&mut self, color: Vec3) {
+        self.normals_pass.set_color(color);
+    }
+
+    /// Get the length of the vertex normals drawn for selected models in
+    /// world units.
+    pub fn normals_length(&self) -> f32 {
+        self.normals_pass.length()
+    }
+
+    /// Set the length of the vertex normals drawn for selected models in
+    /// world units.
+    pub fn set_normals_length(&mut self, length: f32) {
+        self.normals_pass.set_length(length);
+    }
+
+    /// Set where on the screen the depth buffer visualization is drawn.
+    #[allow(dead_code)]
+    pub fn set_depth_quad_placement(&mut self, placement: passes::DepthQuadPlacement) {
+        self.debug_state.depth_quad_placement = placement;
+    }
+
+    /// Get statistics collected while rendering recent frames.
+    pub fn stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    /// Record the frame rate measured by the host, and the frame rate it is
+    /// limiting frames to, in the render stats.
+    pub fn set_frame_rate_stats(&mut self, measured_fps: f32, target_fps: Option<u32>) {
+        self.stats.measured_fps = measured_fps;
+        self.stats.target_fps = target_fps;
+    }
+
+    /// Get the optional features that were enabled on the GPU device. Passes
+    /// should check this before using a feature that might not be available.
+    #[allow(dead_code)]
+    pub fn enabled_features(&self) -> wgpu::Features {
+        self.enabled_features
+    }
+
+    /// Get the context that meshes, textures and materials drawn by this
+    /// renderer are created with.
+    pub fn context(&self) -> &RenderContext {
+        &self.context
+    }
+
+    /// Get the shared GPU device and queue used by this renderer.
+    pub fn gpu(&self) -> &GpuContext {
+        self.context.gpu()
+    }
+
+    /// Get the registry of bind group layouts used by this renderer.
+    #[allow(dead_code)]
+    pub fn bind_group_layouts(&self) -> &BindGroupLayouts {
+        self.context.bind_group_layouts()
+    }
+
+    /// Stop a model from using `slot` in the per-model uniform buffer, and
+    /// free the slot unless other models still share it.
+    fn release_slot(&mut self, slot: usize) {
+        match self.shared_slots.get_mut(&slot) {
+            Some(count) if *count > 2 => *count -= 1,
+            Some(_) => {
+                self.shared_slots.remove(&slot);
+            }
+            None => self.per_model_uniforms.remove(slot),
+        }
+    }
+
+    /// Get the shader values for a model that was prepared for rendering.
+    fn model_sv(&self, model: &Model) -> PerModelBinding<'_> {
+        self.per_model_uniforms.binding(
+            self.model_shader_vals[model
+                .model_sv_key()
+                .expect("models are assigned shader values when preparing to render")],
+        )
+    }
+
+    /// Draw `model`'s world space bounding box with the debug pass.
+    fn add_bounds_box(&mut self, model: &Model, color: Vec3) {
+        let bounds = model.world_bounds();
+        self.light_debug_pass.add_wire_cube(
+            Mat4::from_scale_rotation_translation(
+                bounds.size() * (1.0 + Self::BOUNDS_MARGIN),
+                Quat::IDENTITY,
+                bounds.center(),
+            ),
+            color,
+        );
+    }
+
+    /// Check if a model should be drawn with a selection outline.
+    fn is_selected(&self, model: &Model) -> bool {
+        model
+            .model_sv_key()
+            .is_some_and(|key| self.selected.contains(&key))
+    }
+}
+
+/// The scene models drawn in one viewport in a frame.
+#[derive(Default)]
+struct VisibleModels {
+    /// Indices of the scene models inside the viewport's frustum. Models that
+    /// share a mesh are next to each other.
+    models: Vec<usize>,
+    /// Index of the first model's transforms in the model instance buffer. The
+    /// other models' transforms follow in the same order.
+    first_instance: u32,
+    /// Runs of `models` that share a mesh, which are drawn together with one
+    /// instanced draw call per submesh.
+    batches: Vec<Range<usize>>,
+}
+
+impl VisibleModels {
+    /// Get the instances in the model instance buffer holding the transforms
+    /// of `models[range]`.
+    fn instances(&self, range: Range<usize>) -> Range<u32> {
+        self.first_instance + range.start as u32..self.first_instance + range.end as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        camera::Camera,
+        test_support::{test_camera, test_renderer},
+    };
+
+    #[test]
+    fn headless_renderer_clears_to_black() {
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let scene = Scene::new(test_camera(64, 32));
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+
+        assert_eq!(pixels.dimensions(), (64, 32));
+        assert!(pixels.pixels().all(|p| p.0 == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn lit_cube_matches_golden_image() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                image_compare::assert_matches_golden,
+                lighting::DirectionalLight,
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 64) else {
+            return;
+        };
+
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(2.0, 2.0, 3.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            64,
+        ));
+        scene.spawn_light(DirectionalLight {
+            direction: Vec3::new(-0.5, -1.0, -0.3),
+            color: Vec3::ONE,
+            specular: 1.0,
+            ..Default::default()
+        });
+
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::new(0.2, 0.5, 0.8))
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+        scene.spawn_model(Model::new(
+            cube,
+            Vec3::ZERO,
+            Quat::from_rotation_y(0.3),
+            Vec3::ONE,
+        ));
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+
+        // Allow small differences in rounding and edge coverage between
+        // adapters.
+        assert_matches_golden("lit_cube", &renderer.read_pixels().unwrap(), 4, 0.01);
+    }
+
+    #[test]
+    fn only_supported_optional_features_are_enabled() {
+        let adapter_features = wgpu::Features::TIMESTAMP_QUERY
+            | wgpu::Features::POLYGON_MODE_LINE
+            | wgpu::Features::DEPTH_CLIP_CONTROL;
+
+        assert_eq!(
+            wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::POLYGON_MODE_LINE,
+            Renderer::negotiate_features(adapter_features)
+        );
+        assert!(Renderer::negotiate_features(wgpu::Features::empty()).is_empty());
+    }
+
+    #[test]
+    fn viewports_only_draw_inside_their_bounds() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        // An unlit white cube that is visible without any scene lights.
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::ONE)
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        let camera_looking_at = |target: Vec3| {
+            Camera::new(
+                Vec3::new(0.0, 0.0, 5.0),
+                target,
+                Vec3::Y,
+                f32::to_radians(45.0),
+                0.1,
+                100.0,
+                64,
+                32,
+            )
+        };
+
+        let mut scene = Scene::new(camera_looking_at(Vec3::ZERO));
+        scene.spawn_model(Model::new(cube, Vec3::ZERO, Quat::IDENTITY, Vec3::ONE));
+
+        // The left camera sees the cube while the right camera looks away.
+        let [left, right] = Viewport::side_by_side(64, 32);
+        let views = [
+            (camera_looking_at(Vec3::ZERO), left),
+            (camera_looking_at(Vec3::new(0.0, 0.0, 10.0)), right),
+        ];
+
+        renderer
+            .render_viewports(&scene, &views, Duration::ZERO)
+            .unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+
+        let is_black = |x: u32, y: u32| pixels.get_pixel(x, y).0 == [0, 0, 0, 255];
+
+        // The cube is centered in the left viewport rather than the window.
+        assert!(!is_black(16, 16));
+        assert!((32..64).all(|x| (0..32).all(|y| is_black(x, y))));
+    }
+
+    #[test]
+    fn debug_cubes_are_drawn_without_light_visualization() {
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let scene = Scene::new(test_camera(64, 32));
+
+        renderer.debug_state.visualize_lights = false;
+        renderer.add_debug_cube(Mat4::IDENTITY, Vec3::new(0.0, 1.0, 0.0));
+        renderer.render(&scene, Duration::ZERO).unwrap();
+
+        let center = renderer.read_pixels().unwrap().get_pixel(32, 16).0;
+        assert_eq!([0, 255, 0, 255], center);
+
+        // Debug cubes only last for one frame.
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+        assert!(pixels.pixels().all(|p| p.0 == [0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn recreated_devices_keep_the_renderer_settings() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let scene = Scene::new(test_camera(64, 32));
+        renderer.debug_state.visualize_lights = false;
+        renderer.set_tonemapper(Tonemapper::Clamp);
+        renderer.create_shared_models([cube], Vec3::ZERO, Quat::IDENTITY, Vec3::ONE);
+
+        let render_center = |renderer: &mut Renderer| {
+            renderer.add_debug_cube(Mat4::IDENTITY, Vec3::new(0.0, 1.0, 0.0));
+            renderer.render(&scene, Duration::ZERO).unwrap();
+            renderer.read_pixels().unwrap().get_pixel(32, 16).0
+        };
+
+        assert_eq!([0, 255, 0, 255], render_center(&mut renderer));
+
+        pollster::block_on(renderer.recreate_device()).unwrap();
+
+        assert_eq!(Some(Tonemapper::Clamp), renderer.tonemapper());
+        assert!(!renderer.debug_state.visualize_lights);
+        assert_eq!(1, renderer.model_shader_vals.len());
+        assert_eq!([0, 255, 0, 255], render_center(&mut renderer));
+    }
+
+    #[test]
+    fn tonemappers_map_hdr_colors_into_the_display_range() {
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let scene = Scene::new(test_camera(64, 32));
+        renderer.debug_state.visualize_lights = false;
+        assert_eq!(Some(Tonemapper::Aces), renderer.tonemapper());
+
+        let mut render_center = |tonemapper| {
+            renderer.set_tonemapper(tonemapper);
+            renderer.add_debug_cube(Mat4::IDENTITY, Vec3::new(4.0, 1.0, 0.0));
+            renderer.render(&scene, Duration::ZERO).unwrap();
+            renderer.read_pixels().unwrap().get_pixel(32, 16).0
+        };
+
+        // Clamping loses the difference between the over bright red and the
+        // green, while the tone curves keep red brighter than green.
+        assert_eq!([255, 255, 0, 255], render_center(Tonemapper::Clamp));
+
+        for tonemapper in [Tonemapper::Aces, Tonemapper::Reinhard] {
+            let [r, g, b, _] = render_center(tonemapper);
+            assert!(
+                r < 255 && g < r && g > 0 && b == 0,
+                "{tonemapper:?}: {r} {g} {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn post_effects_can_be_added_and_removed_between_frames() {
+        use post_process::{ColorGradingEffect, FxaaEffect, VignetteEffect};
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        // A solid red skybox fills the whole frame.
+        let face = image::DynamicImage::from(image::RgbaImage::from_pixel(
+            4,
+            4,
+            image::Rgba([255, 0, 0, 255]),
+        ));
+        let mut scene = Scene::new(Camera::new(
+            Vec3::ZERO,
+            Vec3::NEG_Z,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        scene.skybox = Some(Rc::new(
+            textures::cube_from_images(
+                renderer.gpu().device(),
+                renderer.gpu().queue(),
+                &vec![face; 6],
+                textures::ColorSpace::Srgb,
+                Some("test skybox"),
+            )
+            .unwrap(),
+        ));
+
+        let render = |renderer: &mut Renderer| {
+            renderer.render(&scene, Duration::ZERO).unwrap();
+            let pixels = renderer.read_pixels().unwrap();
+            (pixels.get_pixel(32, 16).0, pixels.get_pixel(0, 0).0)
+        };
+
+        assert_eq!(([255, 0, 0, 255], [255, 0, 0, 255]), render(&mut renderer));
+
+        // The vignette darkens the corners but not the center.
+        let gpu = renderer.gpu().clone();
+        let stack = renderer.post_process_stack_mut();
+        let mut vignette = VignetteEffect::new(gpu.device(), stack.input_layout());
+        vignette.set_intensity(1.0);
+        stack.push(Box::new(vignette));
+        stack.insert(
+            1,
+            Box::new(FxaaEffect::new(gpu.device(), stack.input_layout())),
+        );
+
+        assert_eq!(
+            vec!["tonemap", "fxaa", "vignette"],
+            renderer
+                .post_process_stack()
+                .effect_names()
+                .collect::<Vec<_>>()
+        );
+
+        let (center, corner) = render(&mut renderer);
+        assert_eq!([255, 0, 0, 255], center);
+        assert!(corner[0] < 64, "{corner:?}");
+
+        // Effects run in order, so grading before the tonemap effect turns the
+        // frame gray.
+        let stack = renderer.post_process_stack_mut();
+        let mut grading = ColorGradingEffect::new(gpu.device(), stack.input_layout());
+        grading.set_saturation(0.0);
+        stack.insert(0, Box::new(grading));
+
+        let ([r, g, b, _], _) = render(&mut renderer);
+        assert!(r > 0 && r < 255 && r == g && g == b, "{r} {g} {b}");
+
+        // Removing effects restores the original frame.
+        let stack = renderer.post_process_stack_mut();
+        assert!(stack.remove(VignetteEffect::NAME).is_some());
+        assert!(stack.remove(ColorGradingEffect::NAME).is_some());
+        assert!(stack.remove(VignetteEffect::NAME).is_none());
+
+        assert_eq!(([255, 0, 0, 255], [255, 0, 0, 255]), render(&mut renderer));
+    }
+
+    #[test]
+    fn highlighted_model_bounds_are_drawn_until_cleared() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::new(0.0, 0.0, 1.0))
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(3.0, 2.0, 4.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        let model = scene.spawn_model(Model::new(
+            cube,
+            Vec3::ZERO,
+            Quat::from_rotation_y(0.5),
+            Vec3::ONE,
+        ));
+
+        let has_highlight = |renderer: &Renderer| {
+            renderer
+                .read_pixels()
+                .unwrap()
+                .pixels()
+                .any(|p| p.0 == [255, 255, 0, 255])
+        };
+
+        // The model is assigned a key the first time it is drawn.
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(!has_highlight(&renderer));
+
+        renderer.set_highlighted(scene.model(model).unwrap().model_sv_key());
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(has_highlight(&renderer));
+
+        renderer.set_highlighted(None);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(!has_highlight(&renderer));
+    }
+
+    #[test]
+    fn debug_draw_lines_are_drawn_for_one_frame() {
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let scene = Scene::new(test_camera(64, 32));
+        let has_line = |renderer: &Renderer| {
+            renderer
+                .read_pixels()
+                .unwrap()
+                .pixels()
+                .any(|p| p.0 == [0, 255, 0, 255])
+        };
+
+        renderer
+            .debug_draw()
+            .line(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::Y);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(has_line(&renderer));
+
+        // Lines are cleared after the frame they were added in.
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(!has_line(&renderer));
+    }
+
+    #[test]
+    fn text_is_drawn_in_screen_space_for_one_frame() {
+        use glam::{Vec2, Vec4};
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let scene = Scene::new(test_camera(64, 32));
+        let text_pixels = |renderer: &Renderer| {
+            let image = renderer.read_pixels().unwrap();
+            image
+                .enumerate_pixels()
+                .filter(|(_, _, p)| p.0 == [255, 0, 255, 255])
+                .map(|(x, y, _)| (x, y))
+                .collect::<Vec<_>>()
+        };
+
+        // An 'I' drawn at twice the font's size is two pixels per font pixel,
+        // with its top bar three font pixels wide.
+        renderer.text().draw_text(
+            Vec2::new(4.0, 2.0),
+            "I",
+            16.0,
+            Vec4::new(1.0, 0.0, 1.0, 1.0),
+        );
+        renderer.render(&scene, Duration::ZERO).unwrap();
+
+        let pixels = text_pixels(&renderer);
+        assert!(pixels.contains(&(6, 2)));
+        assert!(pixels.contains(&(11, 3)));
+        assert!(!pixels.contains(&(4, 2)));
+        assert!(!pixels.contains(&(12, 2)));
+        assert!(pixels
+            .iter()
+            .all(|&(x, y)| (6..12).contains(&x) && (2..16).contains(&y)));
+
+        // Text is cleared after the frame it was added in.
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(text_pixels(&renderer).is_empty());
+    }
+
+    #[test]
+    fn model_bounding_volumes_are_drawn_when_enabled() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+                models::DebugBounds,
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::new(0.0, 0.0, 1.0))
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(3.0, 2.0, 4.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        let model = scene.spawn_model(Model::new(
+            cube,
+            Vec3::ZERO,
+            Quat::from_rotation_y(0.5),
+            Vec3::ONE,
+        ));
+
+        let has_color = |renderer: &mut Renderer, scene: &Scene, color: [u8; 4]| {
+            renderer.render(scene, Duration::ZERO).unwrap();
+            renderer
+                .read_pixels()
+                .unwrap()
+                .pixels()
+                .any(|p| p.0 == color)
+        };
+        let green = [0, 255, 0, 255];
+        let cyan = [0, 255, 255, 255];
+
+        assert!(!has_color(&mut renderer, &scene, green));
+        assert!(!has_color(&mut renderer, &scene, cyan));
+
+        scene
+            .model_mut(model)
+            .unwrap()
+            .set_debug_bounds(DebugBounds {
+                aabb: true,
+                sphere: false,
+            });
+        assert!(has_color(&mut renderer, &scene, green));
+        assert!(!has_color(&mut renderer, &scene, cyan));
+
+        scene
+            .model_mut(model)
+            .unwrap()
+            .set_debug_bounds(DebugBounds {
+                aabb: false,
+                sphere: true,
+            });
+        assert!(!has_color(&mut renderer, &scene, green));
+        assert!(has_color(&mut renderer, &scene, cyan));
+
+        // Every model's box is drawn while bounds are visualized.
+        scene
+            .model_mut(model)
+            .unwrap()
+            .set_debug_bounds(DebugBounds::default());
+        renderer.debug_state.visualize_bounds = true;
+        assert!(has_color(&mut renderer, &scene, green));
+    }
+
+    #[test]
+    fn selected_model_normals_are_drawn_when_visualized() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+        renderer.set_normals_color(Vec3::new(0.0, 1.0, 0.0));
+        renderer.set_normals_length(0.5);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::ONE)
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(3.0, 2.0, 4.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        let model = scene.spawn_model(Model::new(
+            cube,
+            Vec3::ZERO,
+            Quat::from_rotation_y(0.5),
+            Vec3::ONE,
+        ));
+
+        let has_normals = |renderer: &Renderer| {
+            renderer
+                .read_pixels()
+                .unwrap()
+                .pixels()
+                .any(|p| p.0 == [0, 255, 0, 255])
+        };
+
+        // The model is assigned a key the first time it is drawn.
+        renderer.debug_state.visualize_normals = true;
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(!has_normals(&renderer));
+
+        renderer.set_selected(&[scene.model(model).unwrap().model_sv_key().unwrap()]);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(has_normals(&renderer));
+
+        renderer.debug_state.visualize_normals = false;
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert!(!has_normals(&renderer));
+    }
+
+    #[test]
+    fn shared_models_use_one_uniform_slot_until_moved() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let models = renderer.create_shared_models(
+            std::iter::repeat_n(cube, 10),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        );
+        let keys: Vec<_> = models.iter().filter_map(Model::model_sv_key).collect();
+        let slot = renderer.model_shader_vals[keys[0]];
+
+        // Every model has its own key so it can be selected on its own, but
+        // they all use the same slot.
+        assert_eq!(10, renderer.model_shader_vals.len());
+        assert!(keys
+            .iter()
+            .all(|key| renderer.model_shader_vals[*key] == slot));
+        assert_eq!(Some(&10), renderer.shared_slots.get(&slot));
+
+        // The shared transform was already uploaded so drawing the models does
+        // not copy anything to the GPU or create more shader values.
+        let mut scene = Scene::new(test_camera(64, 32));
+        let ids: Vec<_> = models
+            .into_iter()
+            .map(|model| scene.spawn_model(model))
+            .collect();
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert_eq!(0, renderer.stats().model_uniform_uploads);
+
+        // Moving one model gives it its own slot and leaves the others where
+        // they were.
+        scene.entity_mut(ids[0]).unwrap().transform.translation = Vec3::new(-1.0, 0.0, 0.0);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+
+        assert_eq!(1, renderer.stats().model_uniform_uploads);
+        assert_ne!(slot, renderer.model_shader_vals[keys[0]]);
+        assert!(keys[1..]
+            .iter()
+            .all(|key| renderer.model_shader_vals[*key] == slot));
+        assert_eq!(Some(&9), renderer.shared_slots.get(&slot));
+    }
+
+    #[test]
+    fn destroyed_models_free_their_shader_values() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let mut models = renderer.create_model_grid(cube.clone(), 1, 2, 2.0);
+        let freed_key = models[0].model_sv_key().unwrap();
+        let freed_slot = renderer.model_shader_vals[freed_key];
+        renderer.set_selected(&[freed_key]);
+
+        renderer.destroy_model(models.remove(0));
+        assert_eq!(1, renderer.model_shader_vals.len());
+        assert!(renderer.selected.is_empty());
+
+        // The next model reuses the freed slot.
+        models.extend(renderer.create_model_grid(cube.clone(), 1, 1, 2.0));
+        assert_eq!(2, renderer.model_shader_vals.len());
+        assert_eq!(
+            freed_slot,
+            renderer.model_shader_vals[models[1].model_sv_key().unwrap()]
+        );
+
+        // A shared slot is kept until the last model using it is destroyed.
+        let mut shared = renderer.create_shared_models(
+            [cube.clone(), cube.clone()],
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        );
+        let shared_slot = renderer.model_shader_vals[shared[1].model_sv_key().unwrap()];
+        renderer.destroy_model(shared.remove(0));
+
+        assert_eq!(3, renderer.model_shader_vals.len());
+        assert!(renderer.shared_slots.is_empty());
+
+        let more = renderer.create_model_grid(cube, 1, 1, 2.0);
+        assert_ne!(
+            shared_slot,
+            renderer.model_shader_vals[more[0].model_sv_key().unwrap()]
+        );
+        models.extend(more);
+
+        let mut scene = Scene::new(test_camera(64, 32));
+        for model in models.into_iter().chain(shared) {
+            scene.spawn_model(model);
+        }
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert_eq!(4, renderer.model_shader_vals.len());
+    }
+
+    #[test]
+    fn billboards_face_the_camera_and_blend() {
+        use crate::{content::ContentManager, renderer::billboards::Billboard};
+        use glam::{Vec2, Vec4};
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let mut billboard = Billboard::new(
+            Vec3::ZERO,
+            Vec2::ONE,
+            content.default_textures().diffuse_map.clone(),
+        );
+        billboard.color = Vec4::new(1.0, 0.0, 0.0, 1.0);
+
+        // The billboard is visible from the side, where a quad lying in the XY
+        // plane would be edge on.
+        let camera_at = |eye: Vec3| {
+            Camera::new(
+                eye,
+                Vec3::ZERO,
+                Vec3::Y,
+                f32::to_radians(45.0),
+                0.1,
+                100.0,
+                64,
+                32,
+            )
+        };
+
+        for eye in [Vec3::new(0.0, 0.0, 5.0), Vec3::new(5.0, 0.0, 0.0)] {
+            let mut scene = Scene::new(camera_at(eye));
+            scene.billboards.push(billboard.clone());
+
+            renderer.render(&scene, Duration::ZERO).unwrap();
+            let pixels = renderer.read_pixels().unwrap();
+
+            assert_eq!([255, 0, 0, 255], pixels.get_pixel(32, 16).0);
+            assert_eq!([0, 0, 0, 255], pixels.get_pixel(2, 2).0);
+        }
+
+        // Transparent billboards are blended with what is behind them.
+        billboard.color.w = 0.5;
+        let mut scene = Scene::new(camera_at(Vec3::new(0.0, 0.0, 5.0)));
+        scene.billboards.push(billboard);
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let [r, g, b, _] = renderer.read_pixels().unwrap().get_pixel(32, 16).0;
+        assert!(r > 100 && r < 255 && g == 0 && b == 0, "{r} {g} {b}");
+    }
+
+    #[test]
+    fn sprites_are_drawn_in_screen_space_from_their_texture_region() {
+        use crate::renderer::sprites::Sprite;
+        use glam::{Vec2, Vec4};
+        use std::rc::Rc;
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        // A two pixel wide texture with a red left half and a green right half.
+        let image = image::RgbaImage::from_raw(2, 1, vec![255, 0, 0, 255, 0, 255, 0, 255]).unwrap();
+        let texture = Rc::new(textures::from_image(
+            renderer.gpu().device(),
+            renderer.gpu().queue(),
+            image.into(),
+            textures::ColorSpace::Srgb,
+            Some("sprite test texture"),
+        ));
+
+        let mut scene = Scene::new(test_camera(64, 32));
+
+        let mut red = Sprite::new(Vec2::new(4.0, 2.0), Vec2::new(8.0, 8.0), texture.clone());
+        red.uv_max = Vec2::new(0.5, 1.0);
+        let mut green = Sprite::new(Vec2::new(20.0, 2.0), Vec2::new(8.0, 8.0), texture.clone());
+        green.uv_min = Vec2::new(0.5, 0.0);
+        let mut tinted = green.clone();
+        tinted.position = Vec2::new(36.0, 2.0);
+        tinted.color = Vec4::new(0.0, 1.0, 0.0, 0.0);
+        scene.sprites.extend([red, green, tinted]);
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+
+        // Sprites are placed from the top left corner of the render target.
+        // Pixels are checked away from the middle of the texture, where the
+        // two halves are filtered together.
+        assert_eq!([255, 0, 0, 255], pixels.get_pixel(7, 6).0);
+        assert_eq!([0, 255, 0, 255], pixels.get_pixel(24, 6).0);
+        assert_eq!([0, 0, 0, 255], pixels.get_pixel(24, 24).0);
+
+        // The tint's alpha makes the sprite fully transparent.
+        assert_eq!([0, 0, 0, 255], pixels.get_pixel(40, 6).0);
+    }
+
+    #[test]
+    fn skybox_fills_the_background_in_the_view_direction() {
+        use std::rc::Rc;
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        // Give each face of the cube map its own solid color.
+        let face_colors = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 0, 255],
+            [0, 255, 255, 255],
+            [255, 0, 255, 255],
+        ];
+        let faces = face_colors
+            .iter()
+            .map(|color| {
+                image::DynamicImage::from(image::RgbaImage::from_pixel(4, 4, image::Rgba(*color)))
+            })
+            .collect::<Vec<_>>();
+        let skybox = Rc::new(
+            textures::cube_from_images(
+                renderer.gpu().device(),
+                renderer.gpu().queue(),
+                &faces,
+                textures::ColorSpace::Srgb,
+                Some("test skybox"),
+            )
+            .unwrap(),
+        );
+
+        // Moving the camera does not move the skybox, only turning it does.
+        for (look_at, color) in [(Vec3::NEG_Z, face_colors[5]), (Vec3::X, face_colors[0])] {
+            let eye = Vec3::new(3.0, 1.0, 2.0);
+            let mut scene = Scene::new(Camera::new(
+                eye,
+                eye + look_at,
+                Vec3::Y,
+                f32::to_radians(45.0),
+                0.1,
+                100.0,
+                64,
+                32,
+            ));
+            scene.skybox = Some(skybox.clone());
+
+            renderer.render(&scene, Duration::ZERO).unwrap();
+            assert_eq!(color, renderer.read_pixels().unwrap().get_pixel(32, 16).0);
+        }
+    }
+
+    #[test]
+    fn point_lights_cast_shadows_onto_the_floor() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                lighting::{LightAttenuation, PointLight},
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+                scene::Light,
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new()
+                .diffuse_color(Vec3::ONE)
+                .build(content.default_textures()),
+        ));
+
+        // A small cube floats between a light and a wide floor.
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(0.0, 3.0, 4.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        scene.spawn_model(Model::new(
+            cube.clone(),
+            Vec3::new(0.0, -1.1, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(20.0, 0.2, 20.0),
+        ));
+        scene.spawn_model(Model::new(
+            cube,
+            Vec3::new(0.0, 0.5, 0.0),
+            Quat::IDENTITY,
+            Vec3::splat(0.5),
+        ));
+        let light = scene.spawn_light(PointLight {
+            position: Vec3::new(0.0, 2.0, 0.0),
+            color: Vec3::ONE,
+            attenuation: LightAttenuation {
+                constant: 1.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let unshadowed = renderer.read_pixels().unwrap();
+
+        scene
+            .light_mut(light)
+            .and_then(Light::as_point_mut)
+            .unwrap()
+            .casts_shadows = true;
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let shadowed = renderer.read_pixels().unwrap();
+
+        let brightness = |p: &image::Rgba<u8>| p.0[0] as i32;
+        let changes = unshadowed
+            .pixels()
+            .zip(shadowed.pixels())
+            .map(|(before, after)| brightness(before) - brightness(after))
+            .collect::<Vec<_>>();
+
+        // Part of the floor is darkened, and nothing is brightened.
+        assert!(changes.iter().any(|change| *change > 100));
+        assert!(changes.iter().all(|change| *change >= 0));
+    }
+
+    #[test]
+    fn point_lights_past_the_uniform_limit_are_drawn() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                lighting::{LightAttenuation, PointLight},
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        if renderer.light_cluster_pass.is_none() {
+            eprintln!("skipping light cluster test: light clusters are not supported");
+            return;
+        }
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new()
+                .diffuse_color(Vec3::ONE)
+                .build(content.default_textures()),
+        ));
+
+        // A row of small lights hangs just above a floor that is viewed from
+        // straight above.
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(0.0, 5.0, 0.0),
+            Vec3::ZERO,
+            Vec3::NEG_Z,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        scene.spawn_model(Model::new(
+            cube,
+            Vec3::new(0.0, -0.1, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(20.0, 0.2, 20.0),
+        ));
+
+        let light_count = lit_shader::MAX_POINT_LIGHTS + 3;
+
+        let lights = (0..light_count)
+            .map(|i| {
+                scene.spawn_light(PointLight {
+                    position: Vec3::new(i as f32 - 3.0, 0.3, 0.0),
+                    color: Vec3::ONE,
+                    attenuation: LightAttenuation {
+                        constant: 1.0,
+                        ..Default::default()
+                    },
+                    range: 0.6,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Hide the light markers so only the lit floor is drawn.
+        renderer.debug_state.visualize_lights = false;
+        renderer.set_tonemapper(Tonemapper::Clamp);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let all_lights = renderer.read_pixels().unwrap();
+
+        for &light in &lights[lit_shader::MAX_POINT_LIGHTS..] {
+            scene.despawn(light);
+        }
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let first_lights = renderer.read_pixels().unwrap();
+
+        let view_projection = scene.camera.view_projection_matrix();
+        let brightness = |image: &image::RgbaImage, position: Vec3| {
+            let ndc = view_projection.project_point3(Vec3::new(position.x, 0.0, position.z));
+            let x = ((ndc.x + 1.0) * 0.5 * 64.0) as u32;
+            let y = ((1.0 - ndc.y) * 0.5 * 32.0) as u32;
+            image.get_pixel(x, y).0[0]
+        };
+
+        // The floor is lit under every light, including the lights that do
+        // not fit in the per-frame uniforms.
+        for i in 0..light_count {
+            let position = Vec3::new(i as f32 - 3.0, 0.0, 0.0);
+            assert!(
+                brightness(&all_lights, position) > 100,
+                "light {i} is not drawn"
+            );
+
+            if i >= lit_shader::MAX_POINT_LIGHTS {
+                assert_eq!(brightness(&first_lights, position), 0);
+            }
+        }
+
+        // Lights only reach the floor inside their range.
+        assert_eq!(brightness(&all_lights, Vec3::new(0.0, 0.0, 1.5)), 0);
+    }
+
+    #[test]
+    fn scenes_with_dozens_of_lights_are_drawn() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                lighting::DirectionalLight,
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        if renderer.light_cluster_pass.is_none() {
+            eprintln!("skipping storage light test: storage buffers are not supported");
+            return;
+        }
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new()
+                .diffuse_color(Vec3::ONE)
+                .build(content.default_textures()),
+        ));
+
+        // Many dim lights shine straight down on a floor.
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(0.0, 5.0, 0.0),
+            Vec3::ZERO,
+            Vec3::NEG_Z,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        scene.spawn_model(Model::new(
+            cube,
+            Vec3::new(0.0, -0.1, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(20.0, 0.2, 20.0),
+        ));
+
+        let lights = (0..24)
+            .map(|_| {
+                scene.spawn_light(DirectionalLight {
+                    direction: Vec3::NEG_Y,
+                    color: Vec3::splat(0.02),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        renderer.debug_state.visualize_directional_lights = false;
+        renderer.set_tonemapper(Tonemapper::Clamp);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let all_lights = renderer.read_pixels().unwrap().get_pixel(32, 16).0[0];
+
+        for &light in &lights[lit_shader::MAX_DIRECTIONAL_LIGHTS..] {
+            scene.despawn(light);
+        }
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let first_lights = renderer.read_pixels().unwrap().get_pixel(32, 16).0[0];
+
+        // Every light adds to the floor's brightness, not just the lights that
+        // fit in the per-frame uniforms.
+        assert!(first_lights > 0);
+        assert!(
+            u32::from(all_lights) > 2 * u32::from(first_lights),
+            "{all_lights} is not much brighter than {first_lights}"
+        );
+    }
+
+    #[test]
+    fn ssao_darkens_ambient_light_in_corners() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                lighting::DirectionalLight,
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new()
+                .ambient_color(Vec3::ONE)
+                .diffuse_color(Vec3::ONE)
+                .build(content.default_textures()),
+        ));
+
+        // A box sits on a wide floor, and the scene is only lit by ambient
+        // light so every surface starts out equally bright.
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(0.0, 2.0, 4.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        scene.spawn_model(Model::new(
+            cube.clone(),
+            Vec3::new(0.0, -0.1, 0.0),
+            Quat::IDENTITY,
+            Vec3::new(20.0, 0.2, 20.0),
+        ));
+        scene.spawn_model(Model::new(
+            cube,
+            Vec3::new(0.0, 0.5, 0.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+        scene.spawn_light(DirectionalLight {
+            direction: Vec3::Y,
+            color: Vec3::ONE,
+            ambient: 1.0,
+            ..Default::default()
+        });
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let unoccluded = renderer.read_pixels().unwrap();
+
+        renderer.set_ssao_settings(SsaoSettings {
+            enabled: true,
+            ..Default::default()
+        });
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let occluded = renderer.read_pixels().unwrap();
+
+        let changes = unoccluded
+            .pixels()
+            .zip(occluded.pixels())
+            .map(|(before, after)| before.0[0] as i32 - after.0[0] as i32)
+            .collect::<Vec<_>>();
+
+        // The crease around the base of the box is darkened, open floor is not,
+        // and nothing is brightened.
+        assert!(changes.iter().any(|change| *change > 10));
+        assert_eq!(unoccluded.get_pixel(4, 28), occluded.get_pixel(4, 28));
+        assert!(changes.iter().all(|change| *change >= 0));
+
+        // Turning SSAO back off restores the unoccluded image.
+        renderer.set_ssao_settings(SsaoSettings::default());
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert_eq!(unoccluded, renderer.read_pixels().unwrap());
+    }
+
+    #[test]
+    fn submeshes_are_drawn_with_their_material_shading_model() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                lighting::DirectionalLight,
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let mut scene = Scene::new(test_camera(64, 32));
+        scene.spawn_light(DirectionalLight {
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            color: Vec3::ONE,
+            specular: 1.0,
+            ..Default::default()
+        });
+
+        let mut render_cube = |shading| {
+            let material = MaterialBuilder::new()
+                .diffuse_color(Vec3::new(0.8, 0.1, 0.1))
+                .metallic(1.0)
+                .roughness(1.0)
+                .shading(shading)
+                .build(content.default_textures());
+            let cube = Rc::new(builtin_mesh(
+                renderer.gpu().device(),
+                renderer.bind_group_layouts(),
+                BuiltinMesh::Cube,
+                &material,
+            ));
+
+            let model = scene.spawn_model(Model::new(cube, Vec3::ZERO, Quat::IDENTITY, Vec3::ONE));
+            renderer.render(&scene, Duration::ZERO).unwrap();
+            scene.despawn(model);
+            renderer.read_pixels().unwrap().get_pixel(32, 16).0
+        };
+
+        // Phong ignores the metallic value and lights the cube's diffuse color,
+        // while a rough metal facing the light reflects it tinted by its base
+        // color.
+        let [r, g, b, _] = render_cube(ShadingModel::Phong);
+        assert!(r > 200 && g < 100 && b < 100, "{r} {g} {b}");
+
+        let pbr = render_cube(ShadingModel::Pbr);
+        assert!(pbr[0] > pbr[1] && pbr[1] == pbr[2], "{pbr:?}");
+        assert_ne!([r, g, b, 255], pbr);
+    }
+
+    #[test]
+    fn model_grid_is_centered_and_evenly_spaced() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let models = renderer.create_model_grid(cube, 2, 3, 1.5);
+        assert_eq!(6, models.len());
+
+        let positions = models.iter().map(|m| m.translation()).collect::<Vec<_>>();
+        assert_eq!(Vec3::new(-1.5, 0.0, -0.75), positions[0]);
+        assert_eq!(Vec3::new(0.0, 0.0, -0.75), positions[1]);
+        assert_eq!(Vec3::new(1.5, 0.0, 0.75), positions[5]);
+
+        // Every model has its own shader values.
+        let keys = models
+            .iter()
+            .map(|m| m.model_sv_key().unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(6, keys.len());
+    }
+
+    #[test]
+    fn transparent_materials_blend_back_to_front() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = |color: Vec3, blend_mode: BlendMode| {
+            let material = MaterialBuilder::new()
+                .diffuse_color(color)
+                .alpha(0.5)
+                .blend_mode(blend_mode)
+                .unlit(true)
+                .build(content.default_textures());
+            Rc::new(builtin_mesh(
+                renderer.gpu().device(),
+                renderer.bind_group_layouts(),
+                BuiltinMesh::Cube,
+                &material,
+            ))
+        };
+        let opaque_red = cube(Vec3::new(1.0, 0.0, 0.0), BlendMode::Opaque);
+        let transparent_red = cube(Vec3::new(1.0, 0.0, 0.0), BlendMode::AlphaBlend);
+        let transparent_green = cube(Vec3::new(0.0, 1.0, 0.0), BlendMode::AlphaBlend);
+
+        let camera = test_camera(64, 32);
+        let center_pixel = |renderer: &mut Renderer, scene: &Scene| {
+            renderer.render(scene, Duration::ZERO).unwrap();
+            renderer.read_pixels().unwrap().get_pixel(32, 16).0
+        };
+
+        // An opaque material ignores its alpha and hides what is behind it.
+        let mut scene = Scene::new(camera.clone());
+        scene.spawn_model(Model::new(
+            opaque_red,
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+        assert_eq!([255, 0, 0, 255], center_pixel(&mut renderer, &scene));
+
+        // The nearer transparent cube is added first, but the further one is
+        // drawn first so the nearer one contributes more to the final color.
+        let mut scene = Scene::new(camera);
+        scene.spawn_model(Model::new(
+            transparent_red,
+            Vec3::new(0.0, 0.0, 1.0),
+            Quat::IDENTITY,
+            Vec3::splat(0.5),
+        ));
+        scene.spawn_model(Model::new(
+            transparent_green,
+            Vec3::new(0.0, 0.0, -1.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+
+        let [r, g, b, _] = center_pixel(&mut renderer, &scene);
+        assert!(r > g && g > 0 && b == 0, "{r} {g} {b}");
+    }
+
+    #[test]
+    fn models_outside_the_frustum_are_culled() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        // One cube in front of the camera and one behind it.
+        let camera_looking_at = |target: Vec3| {
+            Camera::new(
+                Vec3::ZERO,
+                target,
+                Vec3::Y,
+                f32::to_radians(45.0),
+                0.1,
+                100.0,
+                64,
+                32,
+            )
+        };
+        let mut scene = Scene::new(camera_looking_at(Vec3::NEG_Z));
+        let models = [Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 5.0)].map(|position| {
+            scene.spawn_model(Model::new(
+                cube.clone(),
+                position,
+                Quat::IDENTITY,
+                Vec3::ONE,
+            ))
+        });
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let stats = renderer.stats();
+        assert_eq!(
+            (1, 1, 1),
+            (
+                stats.models_drawn,
+                stats.models_culled,
+                stats.model_uniform_uploads
+            )
+        );
+        assert!(scene.model(models[1]).unwrap().is_model_sv_dirty());
+
+        // The culled cube is uploaded once it comes into view.
+        scene.camera = camera_looking_at(Vec3::Z);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let stats = renderer.stats();
+        assert_eq!(
+            (1, 1, 1),
+            (
+                stats.models_drawn,
+                stats.models_culled,
+                stats.model_uniform_uploads
+            )
+        );
+        assert!(!scene.model(models[1]).unwrap().is_model_sv_dirty());
+    }
+
+    #[test]
+    fn models_sharing_a_mesh_are_drawn_in_one_batch() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::ONE)
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        // Three cubes in a row, each drawn at its own position.
+        let mut scene = Scene::new(Camera::new(
+            Vec3::new(0.0, 0.0, 8.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        ));
+        let models = [-2.0, 0.0, 2.0].map(|x| {
+            scene.spawn_model(Model::new(
+                cube.clone(),
+                Vec3::new(x, 0.0, 0.0),
+                Quat::IDENTITY,
+                Vec3::ONE,
+            ))
+        });
+
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+        let is_black = |x: u32| pixels.get_pixel(x, 16).0 == [0, 0, 0, 255];
+
+        assert_eq!(1, renderer.stats().model_batches);
+        assert_eq!(1, renderer.stats().draw_calls);
+        assert_eq!(3 * 12, renderer.stats().triangles);
+        assert_eq!(
+            [false, true, false, true, false],
+            [22, 27, 32, 37, 42].map(is_black)
+        );
+
+        // Selected models write a different stencil value, so they are drawn
+        // in their own batch.
+        renderer.set_selected(&[scene.model(models[1]).unwrap().model_sv_key().unwrap()]);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        assert_eq!(2, renderer.stats().model_batches);
+        assert_eq!(2, renderer.stats().draw_calls);
+    }
+
+    #[test]
+    fn model_uniforms_are_bound_past_the_initial_buffer_capacity() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(256, 128) else {
+            return;
+        };
+        renderer.set_tonemapper(Tonemapper::Clamp);
+        renderer.set_outline_color(Vec3::new(1.0, 0.0, 0.0));
+
+        let content = ContentManager::new(renderer.context().clone());
+        let material = MaterialBuilder::new()
+            .diffuse_color(Vec3::ONE)
+            .unlit(true)
+            .build(content.default_textures());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &material,
+        ));
+
+        // More models than fit in the per-model uniform buffer when it is
+        // created, spaced so only the last model is in view.
+        let models = renderer.create_model_grid(cube, 10, 10, 10.0);
+        let target = models.last().unwrap().translation();
+        let mut scene = Scene::new(Camera::new(
+            target + Vec3::new(0.0, 0.0, 2.0),
+            target,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            256,
+            128,
+        ));
+        let last = models
+            .into_iter()
+            .map(|model| scene.spawn_model(model))
+            .last()
+            .unwrap();
+
+        // The outline pass reads the selected model's transform from the
+        // per-model uniforms, so the outline is only visible if the last
+        // model's values were bound.
+        renderer.set_selected(&[scene.model(last).unwrap().model_sv_key().unwrap()]);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+
+        assert!(pixels.pixels().any(|p| p.0 == [255, 0, 0, 255]));
+        assert_eq!([255, 255, 255, 255], pixels.get_pixel(128, 64).0);
+    }
+
+    #[test]
+    fn render_targets_can_be_used_as_material_maps() {
+        use crate::{
+            content::ContentManager,
+            renderer::{
+                materials::MaterialBuilder,
+                meshes::{builtin_mesh, BuiltinMesh},
+            },
+        };
+
+        let Some(mut renderer) = test_renderer(64, 32) else {
+            return;
+        };
+
+        renderer.set_tonemapper(Tonemapper::Clamp);
+        let mut target = renderer.create_render_target(16, 16);
+        assert_eq!((16, 16), (target.width(), target.height()));
+
+        let content = ContentManager::new(renderer.context().clone());
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, 2.0),
+            Vec3::ZERO,
+            Vec3::Y,
+            f32::to_radians(45.0),
+            0.1,
+            100.0,
+            64,
+            32,
+        );
+        let cube = |material| {
+            Rc::new(builtin_mesh(
+                renderer.gpu().device(),
+                renderer.bind_group_layouts(),
+                BuiltinMesh::Cube,
+                &material,
+            ))
+        };
+
+        // A red cube filling the target's view.
+        let mut target_scene = Scene::new(camera.clone());
+        target_scene.spawn_model(Model::new(
+            cube(
+                MaterialBuilder::new()
+                    .diffuse_color(Vec3::X)
+                    .unlit(true)
+                    .build(content.default_textures()),
+            ),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+
+        // A white cube showing the target.
+        let mut scene = Scene::new(camera.clone());
+        scene.spawn_model(Model::new(
+            cube(
+                MaterialBuilder::new()
+                    .diffuse_color(Vec3::ONE)
+                    .diffuse_map(target.texture().clone())
+                    .unlit(true)
+                    .build(content.default_textures()),
+            ),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+
+        // Text is left for the window's frame.
+        renderer.text().draw_text(
+            glam::Vec2::ZERO,
+            "#",
+            8.0,
+            glam::Vec4::new(0.0, 1.0, 0.0, 1.0),
+        );
+
+        renderer.render_to_target(&target_scene, &camera, &mut target);
+        renderer.render(&scene, Duration::ZERO).unwrap();
+
+        let pixels = renderer.read_pixels().unwrap();
+        assert_eq!([255, 0, 0, 255], pixels.get_pixel(32, 16).0);
+        assert!(pixels.pixels().any(|p| p.0 == [0, 255, 0, 255]));
+
+        // The window's depth buffer was not resized to the target.
+        assert_eq!(64, renderer.window_size().width);
     }
 }
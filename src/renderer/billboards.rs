@@ -0,0 +1,33 @@
+use std::rc::Rc;
+
+use glam::{Vec2, Vec3, Vec4};
+
+use super::textures::Texture;
+
+/// A textured quad that always faces the camera, eg a particle, an impostor or
+/// an icon floating over the scene. Billboards are drawn after the scene's
+/// models with alpha blending.
+#[derive(Clone, Debug)]
+pub struct Billboard {
+    /// World position of the center of the billboard.
+    pub position: Vec3,
+    /// Width and height of the billboard in world units.
+    pub size: Vec2,
+    /// Color multiplied with the texture. The alpha channel controls the
+    /// billboard's transparency.
+    pub color: Vec4,
+    /// The image drawn on the billboard.
+    pub texture: Rc<Texture>,
+}
+
+impl Billboard {
+    /// Create an untinted billboard that draws `texture`.
+    pub fn new(position: Vec3, size: Vec2, texture: Rc<Texture>) -> Self {
+        Self {
+            position,
+            size,
+            color: Vec4::ONE,
+            texture,
+        }
+    }
+}
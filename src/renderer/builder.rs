@@ -0,0 +1,276 @@
+use tracing::info;
+use winit::window::Window;
+
+use super::{Renderer, RendererError};
+
+/// Creates a `Renderer` with a chosen graphics adapter.
+///
+/// By default the renderer uses whichever adapter the platform suggests,
+/// which on laptops with an integrated and a discrete GPU is not always the
+/// one wanted. The builder can narrow the choice by graphics API (backend),
+/// by power preference, or by a piece of the adapter's name:
+///
+/// ```ignore
+/// let renderer = RendererBuilder::new()
+///     .backends(wgpu::Backends::VULKAN)
+///     .power_preference(wgpu::PowerPreference::HighPerformance)
+///     .adapter_name("nvidia")
+///     .build(&window)
+///     .await?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RendererBuilder {
+    backends: Option<wgpu::Backends>,
+    power_preference: wgpu::PowerPreference,
+    /// Only adapters with a name containing this (ignoring case) are used.
+    adapter_name: Option<String>,
+}
+
+impl RendererBuilder {
+    /// Environment variable holding part of the name of the adapter to use.
+    pub const ADAPTER_NAME_ENV_VAR: &'static str = "WGPU_ADAPTER_NAME";
+
+    /// Create a builder that uses the platform's default adapter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder configured by the same environment variables as wgpu's
+    /// examples:
+    ///  - `WGPU_BACKEND`: comma separated list of backends, eg `vulkan,gl`.
+    ///  - `WGPU_POWER_PREF`: `low` or `high`.
+    ///  - `WGPU_ADAPTER_NAME`: part of the adapter's name, eg `nvidia`.
+    ///
+    /// Variables that are not set keep their default value.
+    pub fn from_env() -> Self {
+        let mut builder = Self::new();
+
+        if let Some(backends) = wgpu::util::backend_bits_from_env() {
+            builder = builder.backends(backends);
+        }
+
+        if let Some(power_preference) = wgpu::util::power_preference_from_env() {
+            builder = builder.power_preference(power_preference);
+        }
+
+        if let Ok(name) = std::env::var(Self::ADAPTER_NAME_ENV_VAR) {
+            builder = builder.adapter_name(name);
+        }
+
+        builder
+    }
+
+    /// Only use adapters from the given graphics APIs, eg
+    /// `wgpu::Backends::VULKAN | wgpu::Backends::GL`.
+    pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = Some(backends);
+        self
+    }
+
+    /// Prefer a low power (integrated) or high performance (discrete) adapter.
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Only use an adapter whose name contains `name`, ignoring case. This
+    /// takes priority over the power preference.
+    pub fn adapter_name(mut self, name: impl Into<String>) -> Self {
+        self.adapter_name = Some(name.into());
+        self
+    }
+
+    /// List every adapter available to the selected backends, eg to let the
+    /// player pick one in a settings menu. Browsers do not allow listing
+    /// adapters, so this is always empty on the web.
+    pub fn enumerate_adapters(&self) -> Vec<wgpu::AdapterInfo> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                Vec::new()
+            } else {
+                self.create_instance()
+                    .enumerate_adapters(self.selected_backends())
+                    .iter()
+                    .map(wgpu::Adapter::get_info)
+                    .collect()
+            }
+        }
+    }
+
+    /// Create a renderer that draws into `window`.
+    pub async fn build(self, window: &Window) -> Result<Renderer<'_>, RendererError> {
+        let instance = self.create_instance();
+        let surface = instance.create_surface(window)?;
+        let adapter = self
+            .select_adapter(&instance, Some(&surface), false)
+            .await?;
+
+        let mut renderer = Renderer::from_surface(instance, surface, window, adapter).await?;
+        renderer.builder = self;
+        Ok(renderer)
+    }
+
+    /// Create a renderer that is not attached to a window, see
+    /// `Renderer::new_headless`. A software adapter is used when no other
+    /// adapter is available, eg when running tests on CI.
+    pub async fn build_headless(
+        self,
+        width: u32,
+        height: u32,
+    ) -> Result<Renderer<'static>, RendererError> {
+        let instance = self.create_instance();
+        let adapter = self.select_adapter(&instance, None, true).await?;
+
+        let mut renderer =
+            Renderer::from_headless_adapter(instance, adapter, width, height).await?;
+        renderer.builder = self;
+        Ok(renderer)
+    }
+
+    fn selected_backends(&self) -> wgpu::Backends {
+        self.backends.unwrap_or(wgpu::Backends::all())
+    }
+
+    fn create_instance(&self) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: self.selected_backends(),
+            ..Default::default()
+        })
+    }
+
+    /// Pick the adapter matching the builder's settings that can draw to
+    /// `surface`, or to offscreen textures when `surface` is `None`. When
+    /// `allow_fallback` is true a software adapter is used if no other adapter
+    /// is found.
+    pub(super) async fn select_adapter(
+        &self,
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+        allow_fallback: bool,
+    ) -> Result<wgpu::Adapter, RendererError> {
+        if let Some(name) = &self.adapter_name {
+            return self.select_adapter_by_name(instance, surface, name);
+        }
+
+        for force_fallback_adapter in [false, true] {
+            if force_fallback_adapter && !allow_fallback {
+                break;
+            }
+
+            if let Some(adapter) = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: self.power_preference,
+                    compatible_surface: surface,
+                    force_fallback_adapter,
+                })
+                .await
+            {
+                let info = adapter.get_info();
+                info!(
+                    "selected graphics adapter {:?} on {:?} with {:?} power preference",
+                    info.name, info.backend, self.power_preference
+                );
+                return Ok(adapter);
+            }
+        }
+
+        Err(RendererError::NoAdapter(self.selected_backends()))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn select_adapter_by_name(
+        &self,
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'_>>,
+        name: &str,
+    ) -> Result<wgpu::Adapter, RendererError> {
+        let adapters = instance.enumerate_adapters(self.selected_backends());
+        let lowercase_name = name.to_lowercase();
+
+        let available = adapters
+            .iter()
+            .map(|adapter| adapter.get_info().name)
+            .collect::<Vec<_>>();
+
+        let adapter = adapters
+            .into_iter()
+            .filter(|adapter| match surface {
+                Some(surface) => adapter.is_surface_supported(surface),
+                None => true,
+            })
+            .find(|adapter| {
+                adapter
+                    .get_info()
+                    .name
+                    .to_lowercase()
+                    .contains(&lowercase_name)
+            })
+            .ok_or_else(|| RendererError::NoAdapterNamed {
+                name: name.to_string(),
+                available,
+            })?;
+
+        let info = adapter.get_info();
+        info!(
+            "selected graphics adapter {:?} on {:?} matching name {name:?}",
+            info.name, info.backend
+        );
+
+        Ok(adapter)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn select_adapter_by_name(
+        &self,
+        _instance: &wgpu::Instance,
+        _surface: Option<&wgpu::Surface<'_>>,
+        name: &str,
+    ) -> Result<wgpu::Adapter, RendererError> {
+        // Browsers do not allow listing adapters.
+        Err(RendererError::NoAdapterNamed {
+            name: name.to_string(),
+            available: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::skip_without_gpu;
+
+    #[test]
+    fn adapters_are_selected_by_name() {
+        let builder = RendererBuilder::new();
+        let Some(adapter) = builder.enumerate_adapters().into_iter().next() else {
+            skip_without_gpu("no graphics adapters were listed");
+            return;
+        };
+
+        // Names are matched by a case insensitive substring. Each renderer is
+        // dropped before building the next, since dropping the GL backend's
+        // unused adapters can invalidate a renderer that is still alive.
+        let name = adapter.name.to_uppercase();
+        let found =
+            pollster::block_on(builder.clone().adapter_name(name).build_headless(4, 4)).map(drop);
+        assert!(found.is_ok(), "{found:?}");
+
+        let missing = pollster::block_on(
+            builder
+                .clone()
+                .adapter_name("no adapter is named this")
+                .build_headless(4, 4),
+        )
+        .map(drop);
+        assert!(
+            matches!(&missing, Err(RendererError::NoAdapterNamed { available, .. }) if available.contains(&adapter.name)),
+            "{missing:?}"
+        );
+
+        let empty = pollster::block_on(builder.build_headless(0, 4)).map(drop);
+        assert!(
+            matches!(empty, Err(RendererError::InvalidSize(0, 4))),
+            "{empty:?}"
+        );
+    }
+}
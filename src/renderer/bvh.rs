@@ -0,0 +1,290 @@
+use slotmap::{Key, SecondaryMap};
+
+use crate::math_utils::{Aabb, Frustum, Ray};
+
+/// A bounding volume hierarchy: a binary tree of bounding boxes over a set of
+/// items, so that frustum and ray queries only need to test the items in the
+/// branches they touch rather than every item.
+///
+/// Each leaf holds one item. Moving an item refits the boxes above its leaf
+/// rather than rebuilding the tree, which keeps the tree correct but can make
+/// it looser as items move far from where they started. `needs_rebuild`
+/// reports when enough items have moved that the tree should be built again.
+pub struct Bvh<K: Key> {
+    nodes: Vec<Node<K>>,
+    /// Index of the leaf node holding each item.
+    leaves: SecondaryMap<K, usize>,
+    /// Number of times an item's bounds changed since the tree was built.
+    refits: usize,
+}
+
+struct Node<K> {
+    bounds: Aabb,
+    parent: Option<usize>,
+    kind: NodeKind<K>,
+}
+
+enum NodeKind<K> {
+    Leaf(K),
+    Branch(usize, usize),
+}
+
+impl<K: Key> Bvh<K> {
+    /// Build a tree over `items`, which are pairs of an item and its bounds.
+    pub fn build(items: impl IntoIterator<Item = (K, Aabb)>) -> Self {
+        let mut items = items.into_iter().collect::<Vec<_>>();
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(items.len() * 2),
+            leaves: SecondaryMap::new(),
+            refits: 0,
+        };
+
+        if !items.is_empty() {
+            bvh.build_node(&mut items, None);
+        }
+
+        bvh
+    }
+
+    /// Add the node for `items` and its descendants, and return its index.
+    fn build_node(&mut self, items: &mut [(K, Aabb)], parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+        let bounds = items
+            .iter()
+            .map(|(_, bounds)| *bounds)
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or_default();
+
+        if let [(key, _)] = items {
+            self.nodes.push(Node {
+                bounds,
+                parent,
+                kind: NodeKind::Leaf(*key),
+            });
+            self.leaves.insert(*key, index);
+            return index;
+        }
+
+        // Split the items in half along the axis their centers are most spread
+        // out on.
+        let centers = Aabb::from_points(items.iter().map(|(_, bounds)| bounds.center()));
+        let size = centers.size();
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+
+        let middle = items.len() / 2;
+        items.select_nth_unstable_by(middle, |(_, a), (_, b)| {
+            a.center()[axis].total_cmp(&b.center()[axis])
+        });
+
+        // The children are filled in once they are built.
+        self.nodes.push(Node {
+            bounds,
+            parent,
+            kind: NodeKind::Branch(0, 0),
+        });
+
+        let (left_items, right_items) = items.split_at_mut(middle);
+        let left = self.build_node(left_items, Some(index));
+        let right = self.build_node(right_items, Some(index));
+        self.nodes[index].kind = NodeKind::Branch(left, right);
+
+        index
+    }
+
+    /// Get the number of items in the tree.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns true if enough items moved since the tree was built that
+    /// building it again would likely speed up queries.
+    pub fn needs_rebuild(&self) -> bool {
+        self.refits > self.len()
+    }
+
+    /// Update the bounds of `key` and the boxes containing it. Returns false
+    /// if `key` is not in the tree, in which case the tree must be built again
+    /// to include it.
+    pub fn update(&mut self, key: K, bounds: Aabb) -> bool {
+        let Some(&leaf) = self.leaves.get(key) else {
+            return false;
+        };
+
+        if self.nodes[leaf].bounds == bounds {
+            return true;
+        }
+
+        self.refits += 1;
+        self.nodes[leaf].bounds = bounds;
+        let mut parent = self.nodes[leaf].parent;
+
+        while let Some(index) = parent {
+            let NodeKind::Branch(left, right) = self.nodes[index].kind else {
+                unreachable!("parents are always branches");
+            };
+
+            let bounds = self.nodes[left].bounds.union(&self.nodes[right].bounds);
+
+            // Boxes further up already contain this one if it did not change.
+            if self.nodes[index].bounds == bounds {
+                break;
+            }
+
+            self.nodes[index].bounds = bounds;
+            parent = self.nodes[index].parent;
+        }
+
+        true
+    }
+
+    /// Call `visit` with every item whose bounds may be inside of `frustum`.
+    pub fn query_frustum(&self, frustum: &Frustum, mut visit: impl FnMut(K)) {
+        let mut stack = Vec::new();
+
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+
+            if !frustum.intersects_aabb(&node.bounds) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf(key) => visit(key),
+                NodeKind::Branch(left, right) => stack.extend([right, left]),
+            }
+        }
+    }
+
+    /// Find the closest item hit by `ray`. `hit` is called with items whose
+    /// bounds are hit by the ray, nearest first, and returns the distance
+    /// along the ray to the item or `None` if the ray misses the item.
+    pub fn raycast(&self, ray: &Ray, mut hit: impl FnMut(K) -> Option<f32>) -> Option<(K, f32)> {
+        let mut closest: Option<(K, f32)> = None;
+        let mut stack = Vec::new();
+
+        if let Some(distance) = self
+            .nodes
+            .first()
+            .and_then(|n| ray.intersect_aabb(&n.bounds))
+        {
+            stack.push((0, distance));
+        }
+
+        while let Some((index, entry_distance)) = stack.pop() {
+            // Nothing in this box can be closer than what was already hit.
+            if closest.is_some_and(|(_, closest)| entry_distance > closest) {
+                continue;
+            }
+
+            match self.nodes[index].kind {
+                NodeKind::Leaf(key) => {
+                    if let Some(distance) = hit(key) {
+                        if !closest.is_some_and(|(_, closest)| closest <= distance) {
+                            closest = Some((key, distance));
+                        }
+                    }
+                }
+                NodeKind::Branch(left, right) => {
+                    let mut children = [left, right]
+                        .map(|child| (child, ray.intersect_aabb(&self.nodes[child].bounds)));
+
+                    // Visit the nearer child first by pushing it last.
+                    children.sort_by(|(_, a), (_, b)| {
+                        b.unwrap_or(f32::INFINITY)
+                            .total_cmp(&a.unwrap_or(f32::INFINITY))
+                    });
+
+                    for (child, distance) in children {
+                        if let Some(distance) = distance {
+                            stack.push((child, distance));
+                        }
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use slotmap::{DefaultKey, SlotMap};
+
+    use super::*;
+
+    fn unit_box_at(center: Vec3) -> Aabb {
+        Aabb::new(center - 0.5, center + 0.5)
+    }
+
+    #[test]
+    fn queries_find_the_same_items_as_testing_each_item() {
+        let mut keys = SlotMap::new();
+        let mut items = (0..64)
+            .map(|i| {
+                let center = Vec3::new((i % 8) as f32 * 3.0 - 10.0, 0.0, (i / 8) as f32 * -3.0);
+                (keys.insert(()), unit_box_at(center))
+            })
+            .collect::<Vec<(DefaultKey, Aabb)>>();
+        let mut bvh = Bvh::build(items.iter().copied());
+        assert_eq!(64, bvh.len());
+
+        // Move a few items, including one far outside of the original bounds.
+        for (i, offset) in [
+            (0, Vec3::new(0.0, 40.0, 0.0)),
+            (9, Vec3::X),
+            (63, Vec3::NEG_Z),
+        ] {
+            items[i].1 = unit_box_at(items[i].1.center() + offset);
+            assert!(bvh.update(items[i].0, items[i].1));
+        }
+
+        assert!(!bvh.update(keys.insert(()), unit_box_at(Vec3::ZERO)));
+        assert!(!bvh.needs_rebuild());
+
+        let view_projection = glam::Mat4::perspective_rh(f32::to_radians(60.0), 1.0, 0.1, 15.0)
+            * glam::Mat4::look_at_rh(Vec3::new(0.0, 2.0, 5.0), Vec3::new(0.0, 0.0, -5.0), Vec3::Y);
+        let frustum = Frustum::from_view_projection(&view_projection);
+
+        let mut found = Vec::new();
+        bvh.query_frustum(&frustum, |key| found.push(key));
+        found.sort();
+
+        let mut expected = items
+            .iter()
+            .filter(|(_, bounds)| frustum.intersects_aabb(bounds))
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert!(!expected.is_empty() && expected.len() < items.len());
+        assert_eq!(expected, found);
+
+        // Rays hit the closest item, including items that were moved.
+        let ray = Ray::new(Vec3::new(items[9].1.center().x, 0.0, 10.0), Vec3::NEG_Z);
+        let (key, distance) = bvh
+            .raycast(&ray, |key| {
+                let (_, bounds) = items.iter().find(|(k, _)| *k == key)?;
+                ray.intersect_aabb(bounds)
+            })
+            .unwrap();
+        assert_eq!(items[9].0, key);
+        assert_eq!(12.5, distance);
+
+        let up = Ray::new(Vec3::new(-10.0, 10.0, 0.0), Vec3::Y);
+        assert_eq!(
+            Some(items[0].0),
+            bvh.raycast(&up, |_| Some(1.0)).map(|(key, _)| key)
+        );
+    }
+}
@@ -1,10 +1,35 @@
 use winit::event::{ElementState, WindowEvent};
 use winit::keyboard::{KeyCode, PhysicalKey};
 
+use super::passes::DepthQuadPlacement;
+
 /// Configurable state useful when debugging/testing the renderer.
-#[derive(Default)]
+#[derive(Clone, Debug)]
 pub struct DebugState {
     pub visualize_depth_pass: bool,
+    pub depth_quad_placement: DepthQuadPlacement,
+    pub visualize_lights: bool,
+    pub visualize_spot_lights: bool,
+    pub visualize_directional_lights: bool,
+    /// Draw the vertex normals of selected models.
+    pub visualize_normals: bool,
+    /// Draw the bounding box of every model, rather than only the models that
+    /// ask for it.
+    pub visualize_bounds: bool,
+}
+
+impl Default for DebugState {
+    fn default() -> Self {
+        Self {
+            visualize_depth_pass: false,
+            depth_quad_placement: Default::default(),
+            visualize_lights: true,
+            visualize_spot_lights: true,
+            visualize_directional_lights: true,
+            visualize_normals: false,
+            visualize_bounds: false,
+        }
+    }
 }
 
 impl DebugState {
@@ -15,8 +40,29 @@ impl DebugState {
         } = event
         {
             if keyboard_input_event.state == ElementState::Released {
-                if let PhysicalKey::Code(KeyCode::KeyZ) = keyboard_input_event.physical_key {
-                    self.visualize_depth_pass = !self.visualize_depth_pass;
+                match keyboard_input_event.physical_key {
+                    PhysicalKey::Code(KeyCode::KeyZ) => {
+                        self.visualize_depth_pass = !self.visualize_depth_pass;
+                    }
+                    PhysicalKey::Code(KeyCode::KeyX) => {
+                        self.depth_quad_placement = self.depth_quad_placement.next();
+                    }
+                    PhysicalKey::Code(KeyCode::KeyL) => {
+                        self.visualize_lights = !self.visualize_lights;
+                    }
+                    PhysicalKey::Code(KeyCode::KeyK) => {
+                        self.visualize_spot_lights = !self.visualize_spot_lights;
+                    }
+                    PhysicalKey::Code(KeyCode::KeyJ) => {
+                        self.visualize_directional_lights = !self.visualize_directional_lights;
+                    }
+                    PhysicalKey::Code(KeyCode::KeyN) => {
+                        self.visualize_normals = !self.visualize_normals;
+                    }
+                    PhysicalKey::Code(KeyCode::KeyB) => {
+                        self.visualize_bounds = !self.visualize_bounds;
+                    }
+                    _ => {}
                 }
             }
         }
@@ -231,3 +277,108 @@ pub const CUBE_INDICES: &[u16] = &[
     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
     26, 27, 28, 29, 30, 31, 32, 33, 34, 35,
 ];
+
+/// Vertices for a square pyramid with its apex at the origin and a base ranging
+/// from [-1, 1] in X and Y at Z = -1.
+pub const PYRAMID_VERTS: &[DebugVertex] = &[
+    DebugVertex {
+        position: [0.0, 0.0, 0.0],
+        tex_coords: [0.5, 0.5],
+    },
+    DebugVertex {
+        position: [-1.0, -1.0, -1.0],
+        tex_coords: [0.0, 1.0],
+    },
+    DebugVertex {
+        position: [1.0, -1.0, -1.0],
+        tex_coords: [1.0, 1.0],
+    },
+    DebugVertex {
+        position: [1.0, 1.0, -1.0],
+        tex_coords: [1.0, 0.0],
+    },
+    DebugVertex {
+        position: [-1.0, 1.0, -1.0],
+        tex_coords: [0.0, 0.0],
+    },
+];
+
+/// Indices for a square pyramid in CCW order.
+pub const PYRAMID_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1, 1, 3, 2, 1, 4, 3];
+
+/// Corners of a cube ranging from [-0.5, 0.5] in all dimensions, for drawing
+/// the cube's edges as lines.
+pub const WIRE_CUBE_VERTS: &[DebugVertex] = &[
+    DebugVertex {
+        position: [-0.5, -0.5, -0.5],
+        tex_coords: [0.0, 0.0],
+    },
+    DebugVertex {
+        position: [0.5, -0.5, -0.5],
+        tex_coords: [1.0, 0.0],
+    },
+    DebugVertex {
+        position: [0.5, 0.5, -0.5],
+        tex_coords: [1.0, 1.0],
+    },
+    DebugVertex {
+        position: [-0.5, 0.5, -0.5],
+        tex_coords: [0.0, 1.0],
+    },
+    DebugVertex {
+        position: [-0.5, -0.5, 0.5],
+        tex_coords: [0.0, 0.0],
+    },
+    DebugVertex {
+        position: [0.5, -0.5, 0.5],
+        tex_coords: [1.0, 0.0],
+    },
+    DebugVertex {
+        position: [0.5, 0.5, 0.5],
+        tex_coords: [1.0, 1.0],
+    },
+    DebugVertex {
+        position: [-0.5, 0.5, 0.5],
+        tex_coords: [0.0, 1.0],
+    },
+];
+
+/// Line list indices for the twelve edges of the wire cube. The back face is
+/// listed first, then the front face, then the edges connecting them.
+pub const WIRE_CUBE_INDICES: &[u16] = &[
+    0, 1, 1, 2, 2, 3, 3, 0, 4, 5, 5, 6, 6, 7, 7, 4, 0, 4, 1, 5, 2, 6, 3, 7,
+];
+
+/// Number of line segments in each circle of the wire sphere.
+const WIRE_SPHERE_SEGMENTS: u16 = 32;
+
+/// Build a sphere with a radius of one out of three circles around the X, Y
+/// and Z axes, for drawing the sphere as lines. Returns the vertices and line
+/// list indices.
+pub fn wire_sphere() -> (Vec<DebugVertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for axis in 0..3 {
+        let first = vertices.len() as u16;
+
+        for segment in 0..WIRE_SPHERE_SEGMENTS {
+            let t = segment as f32 / WIRE_SPHERE_SEGMENTS as f32;
+            let (sin, cos) = (t * std::f32::consts::TAU).sin_cos();
+            let mut position = [0.0; 3];
+            position[(axis + 1) % 3] = cos;
+            position[(axis + 2) % 3] = sin;
+
+            vertices.push(DebugVertex {
+                position,
+                tex_coords: [t, 0.0],
+            });
+            indices.extend([
+                first + segment,
+                first + (segment + 1) % WIRE_SPHERE_SEGMENTS,
+            ]);
+        }
+    }
+
+    (vertices, indices)
+}
@@ -0,0 +1,187 @@
+use glam::{Mat4, Vec3};
+
+use crate::math_utils::Aabb;
+
+/// One end of a line drawn by `DebugDraw`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugLineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl DebugLineVertex {
+    /// Vertex buffer format for `DebugLineVertex`.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+            wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DebugLineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Immediate mode lines for debugging a game, eg to show a ray cast or the
+/// path an enemy is following.
+///
+/// Shapes are added each frame through `Renderer::debug_draw` and are drawn
+/// over the scene by the debug pass in the next rendered frame. Every line
+/// added in a frame is batched into one vertex buffer and drawn with a single
+/// draw call, and the lines are cleared once the frame is drawn.
+#[derive(Debug, Default)]
+pub struct DebugDraw {
+    /// Line list vertices, with two vertices per line.
+    vertices: Vec<DebugLineVertex>,
+}
+
+impl DebugDraw {
+    /// Color of the X axis drawn by `axes`.
+    pub const AXIS_X_COLOR: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    /// Color of the Y axis drawn by `axes`.
+    pub const AXIS_Y_COLOR: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    /// Color of the Z axis drawn by `axes`.
+    pub const AXIS_Z_COLOR: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw a line from `a` to `b` in world space.
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec3) {
+        self.vertices.extend([
+            DebugLineVertex {
+                position: a.into(),
+                color: color.into(),
+            },
+            DebugLineVertex {
+                position: b.into(),
+                color: color.into(),
+            },
+        ]);
+    }
+
+    /// Draw a line starting at `origin` along `direction`. The line is as long
+    /// as `direction`.
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Vec3) {
+        self.line(origin, origin + direction, color);
+    }
+
+    /// Draw the twelve edges of a world space bounding box.
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec3) {
+        let c = aabb.corners();
+
+        // Corners are ordered by their X, then Y, then Z bit, so edges connect
+        // corners whose indices differ in a single bit.
+        for (a, b) in [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ] {
+            self.line(c[a], c[b], color);
+        }
+    }
+
+    /// Draw a square grid on the XZ plane centered on `center`. The grid is
+    /// `size` units wide and split into `divisions` cells along each side.
+    pub fn grid(&mut self, center: Vec3, size: f32, divisions: u32, color: Vec3) {
+        let divisions = divisions.max(1);
+        let half_size = size * 0.5;
+
+        for i in 0..=divisions {
+            let offset = -half_size + size * i as f32 / divisions as f32;
+
+            self.line(
+                center + Vec3::new(offset, 0.0, -half_size),
+                center + Vec3::new(offset, 0.0, half_size),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half_size, 0.0, offset),
+                center + Vec3::new(half_size, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// Draw the X, Y and Z axes of `transform` from its origin, with each axis
+    /// `length` units long before `transform` is applied.
+    pub fn axes(&mut self, transform: Mat4, length: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+
+        for (axis, color) in [
+            (Vec3::X, Self::AXIS_X_COLOR),
+            (Vec3::Y, Self::AXIS_Y_COLOR),
+            (Vec3::Z, Self::AXIS_Z_COLOR),
+        ] {
+            self.line(origin, transform.transform_point3(axis * length), color);
+        }
+    }
+
+    /// Get the line list vertices added this frame.
+    pub fn vertices(&self) -> &[DebugLineVertex] {
+        &self.vertices
+    }
+
+    /// Returns true if nothing was added this frame.
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Remove every line, which the renderer does after each frame.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_are_built_from_lines() {
+        let mut debug_draw = DebugDraw::new();
+
+        debug_draw.aabb(&Aabb::new(Vec3::ZERO, Vec3::ONE), Vec3::ONE);
+        assert_eq!(24, debug_draw.vertices().len());
+
+        // Every edge of the box is one unit long and runs along one axis.
+        for line in debug_draw.vertices().chunks(2) {
+            let a = Vec3::from(line[0].position);
+            let b = Vec3::from(line[1].position);
+            assert_eq!(1.0, (b - a).abs().element_sum());
+        }
+
+        debug_draw.clear();
+        debug_draw.grid(Vec3::ZERO, 4.0, 4, Vec3::ONE);
+        assert_eq!(20, debug_draw.vertices().len());
+
+        debug_draw.clear();
+        debug_draw.axes(Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)), 2.0);
+        assert_eq!(
+            vec![
+                DebugLineVertex {
+                    position: [1.0, 2.0, 3.0],
+                    color: [1.0, 0.0, 0.0],
+                },
+                DebugLineVertex {
+                    position: [3.0, 2.0, 3.0],
+                    color: [1.0, 0.0, 0.0],
+                },
+            ],
+            debug_draw.vertices()[..2]
+        );
+    }
+}
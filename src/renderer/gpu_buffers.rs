@@ -1,4 +1,4 @@
-use std::cell::Cell;
+use std::{cell::Cell, ops::Range};
 
 /// Trait for objects that represent a GPU buffer that can be updated from the
 /// CPU.
@@ -120,6 +120,275 @@ where
     }
 }
 
+/// A uniform buffer holding an array of values that are bound one at a time by
+/// passing a dynamic offset to `set_bind_group`, so many objects can share a
+/// single GPU buffer and bind group rather than each creating their own.
+///
+/// Values that changed are copied to the GPU together by `update_gpu`. The
+/// buffer grows when values are added past its capacity, which replaces the GPU
+/// buffer and bind group.
+#[derive(Debug)]
+pub struct DynamicUniformBuffer<T>
+where
+    T: Clone + Copy + std::fmt::Debug + bytemuck::Pod + bytemuck::Zeroable,
+{
+    label: &'static str,
+    /// A copy of every value in the buffer.
+    values: Vec<T>,
+    /// Distance in bytes between values in the GPU buffer. Dynamic offsets
+    /// must be a multiple of the device's uniform buffer offset alignment.
+    stride: wgpu::BufferAddress,
+    /// The GPU buffer storing a copy of the values.
+    gpu_buffer: wgpu::Buffer,
+    /// Bind group exposing one value of the buffer at binding 0.
+    bind_group: wgpu::BindGroup,
+    /// Range of values that changed since they were last copied to the GPU.
+    dirty: Option<Range<usize>>,
+}
+
+impl<T> DynamicUniformBuffer<T>
+where
+    T: Clone + Copy + std::fmt::Debug + bytemuck::Pod + bytemuck::Zeroable,
+{
+    /// Number of values the GPU buffer is created with.
+    const INITIAL_CAPACITY: usize = 64;
+
+    /// Create a new empty dynamic uniform buffer.
+    ///
+    /// `device`: The wgpu device owning this uniform buffer.
+    /// `label`: Name representing this uniform buffer.
+    /// `bind_group_layout`: A layout with a single uniform buffer at binding 0
+    ///                      that has a dynamic offset.
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stride = (std::mem::size_of::<T>() as wgpu::BufferAddress).next_multiple_of(alignment);
+        let (gpu_buffer, bind_group) = Self::create_buffer(
+            device,
+            label,
+            bind_group_layout,
+            stride,
+            Self::INITIAL_CAPACITY,
+        );
+
+        Self {
+            label,
+            values: Vec::new(),
+            stride,
+            gpu_buffer,
+            bind_group,
+            dirty: None,
+        }
+    }
+
+    /// Create a buffer on `device` holding the same values as this buffer, eg
+    /// to replace a buffer whose device was lost. Every value is copied to the
+    /// GPU by the next `update_gpu`.
+    pub fn recreate(
+        &self,
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let mut buffer = Self::new(device, self.label, bind_group_layout);
+        buffer.values = self.values.clone();
+        buffer.dirty = (!buffer.values.is_empty()).then_some(0..buffer.values.len());
+        buffer
+    }
+
+    /// Add `value` to the end of the buffer and return its index.
+    pub fn push(&mut self, value: T) -> usize {
+        self.values.push(value);
+
+        let index = self.values.len() - 1;
+        self.mark_dirty(index);
+        index
+    }
+
+    /// Access the value at `index` with a mutable ref.
+    ///
+    /// Calling this method marks the value as changed even if it is not.
+    pub fn values_mut(&mut self, index: usize) -> &mut T {
+        self.mark_dirty(index);
+        &mut self.values[index]
+    }
+
+    /// Get the dynamic offset to pass to `set_bind_group` when binding the
+    /// value at `index`.
+    pub fn offset(&self, index: usize) -> u32 {
+        debug_assert!(index < self.values.len());
+        (index as wgpu::BufferAddress * self.stride) as u32
+    }
+
+    /// Copy the values that changed to the GPU with a single write, growing
+    /// the GPU buffer first if values were added past its capacity. Returns the
+    /// number of bytes copied.
+    pub fn update_gpu(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BufferAddress {
+        let Some(mut dirty) = self.dirty.take() else {
+            return 0;
+        };
+
+        if self.values.len() > self.capacity() {
+            (self.gpu_buffer, self.bind_group) = Self::create_buffer(
+                device,
+                self.label,
+                bind_group_layout,
+                self.stride,
+                self.values.len().next_power_of_two(),
+            );
+            dirty = 0..self.values.len();
+        }
+
+        // Pad each value out to the stride so the values land on their
+        // offsets.
+        let mut bytes = vec![0; dirty.len() * self.stride as usize];
+
+        for (value, chunk) in self.values[dirty.clone()]
+            .iter()
+            .zip(bytes.chunks_exact_mut(self.stride as usize))
+        {
+            chunk[..std::mem::size_of::<T>()].copy_from_slice(bytemuck::bytes_of(value));
+        }
+
+        queue.write_buffer(
+            &self.gpu_buffer,
+            dirty.start as wgpu::BufferAddress * self.stride,
+            &bytes,
+        );
+
+        bytes.len() as wgpu::BufferAddress
+    }
+
+    fn capacity(&self) -> usize {
+        (self.gpu_buffer.size() / self.stride) as usize
+    }
+
+    fn mark_dirty(&mut self, index: usize) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(index)..dirty.end.max(index + 1),
+            None => index..index + 1,
+        });
+    }
+
+    fn create_buffer(
+        device: &wgpu::Device,
+        label: &str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        stride: wgpu::BufferAddress,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let gpu_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: stride * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // The binding covers a single value, which the dynamic offset moves
+        // along the buffer.
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &gpu_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+                }),
+            }],
+        });
+
+        (gpu_buffer, bind_group)
+    }
+}
+
+impl<T> UniformBindGroup for DynamicUniformBuffer<T>
+where
+    T: Clone + Copy + std::fmt::Debug + bytemuck::Pod + bytemuck::Zeroable,
+{
+    fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+/// Defines a struct that owns a `GenericUniformBuffer` of packed uniform values,
+/// along with the `DynamicGpuBuffer` and `UniformBindGroup` forwarding impls and
+/// a `bind_group_layout_desc()` describing a single uniform buffer at binding 0.
+///
+/// Constructors and setters are left to the caller since they usually depend on
+/// which bind group layout to use and how values are packed. Structs that need
+/// more than one binding (eg textures) should be written by hand.
+///
+/// ```ignore
+/// uniform_struct! {
+///     /// Doc comment for the generated struct.
+///     #[derive(Debug)]
+///     pub struct PerFrameShaderVals(PerFramePackedUniforms) {
+///         label: "per-frame",
+///         visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+///     }
+/// }
+/// ```
+macro_rules! uniform_struct {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($packed:ty) {
+            label: $label:literal,
+            visibility: $visibility:expr $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            uniforms: $crate::renderer::gpu_buffers::GenericUniformBuffer<$packed>,
+        }
+
+        impl $name {
+            /// Gets the bind group layout describing any instance of this struct.
+            pub fn bind_group_layout_desc() -> wgpu::BindGroupLayoutDescriptor<'static> {
+                wgpu::BindGroupLayoutDescriptor {
+                    label: Some(concat!($label, " bind group layout")),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: $visibility,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                }
+            }
+        }
+
+        impl $crate::renderer::gpu_buffers::UniformBindGroup for $name {
+            fn bind_group(&self) -> &wgpu::BindGroup {
+                $crate::renderer::gpu_buffers::UniformBindGroup::bind_group(&self.uniforms)
+            }
+        }
+
+        impl $crate::renderer::gpu_buffers::DynamicGpuBuffer for $name {
+            fn update_gpu(&self, queue: &wgpu::Queue) {
+                $crate::renderer::gpu_buffers::DynamicGpuBuffer::update_gpu(&self.uniforms, queue)
+            }
+
+            fn is_dirty(&self) -> bool {
+                $crate::renderer::gpu_buffers::DynamicGpuBuffer::is_dirty(&self.uniforms)
+            }
+        }
+    };
+}
+
+pub(crate) use uniform_struct;
+
 /// A utility struct to abstract an array of uniform values when used for
 /// instancing.
 ///
@@ -133,6 +402,9 @@ where
 {
     /// A copy of all the instances in the buffer.
     instances: Vec<T>,
+    /// Name of the GPU buffer, kept so the buffer can be recreated when it
+    /// grows.
+    label: Option<String>,
     /// The GPU buffer storing a copy of this uniform buffer's values.
     gpu_buffer: wgpu::Buffer,
     /// True if `values` has new data that needs to be copied to the GPU.
@@ -160,11 +432,33 @@ where
 
         Self {
             instances,
+            label: label.map(str::to_string),
             gpu_buffer,
             is_dirty: Cell::new(false),
         }
     }
 
+    /// Replace every instance in the buffer with `instances`, growing the GPU
+    /// buffer to the next power of two if they do not fit. The new instances
+    /// are copied to the GPU the next time `update_gpu` is called.
+    pub fn set_instances(&mut self, device: &wgpu::Device, instances: impl IntoIterator<Item = T>) {
+        self.instances.clear();
+        self.instances.extend(instances);
+        self.is_dirty = Cell::new(true);
+
+        let capacity = self.gpu_buffer.size() as usize / std::mem::size_of::<T>();
+
+        if self.instances.len() > capacity {
+            self.gpu_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: self.label.as_deref(),
+                size: (self.instances.len().next_power_of_two() * std::mem::size_of::<T>())
+                    as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        }
+    }
+
     /// Access an instance stored in this instance buffer via const reef.
     #[allow(dead_code)]
     pub fn values(&self, index: usize) -> &T {
@@ -181,7 +475,7 @@ where
     }
 
     /// Get the GPU buffer object used by this instance buffer.
-    pub fn gpu_buffer_slice<S>(&self, bounds: S) -> wgpu::BufferSlice
+    pub fn gpu_buffer_slice<S>(&self, bounds: S) -> wgpu::BufferSlice<'_>
     where
         S: std::ops::RangeBounds<wgpu::BufferAddress>,
     {
@@ -206,3 +500,74 @@ where
         self.is_dirty.get()
     }
 }
+
+/// A GPU storage buffer holding a runtime sized array of values, for shader
+/// inputs that do not fit in a fixed size uniform array.
+///
+/// The buffer grows when more values are written than fit, which replaces the
+/// GPU buffer. Bind groups that use the buffer must be recreated afterwards.
+#[derive(Debug)]
+pub struct StorageBuffer<T>
+where
+    T: Clone + Copy + std::fmt::Debug + bytemuck::Pod + bytemuck::Zeroable,
+{
+    label: &'static str,
+    /// The GPU buffer storing the values.
+    gpu_buffer: wgpu::Buffer,
+    _values: std::marker::PhantomData<T>,
+}
+
+impl<T> StorageBuffer<T>
+where
+    T: Clone + Copy + std::fmt::Debug + bytemuck::Pod + bytemuck::Zeroable,
+{
+    /// Create a new storage buffer with room for `capacity` values.
+    ///
+    /// `device`: The wgpu device owning this storage buffer.
+    /// `label`: Name representing this storage buffer.
+    /// `capacity`: Number of values the buffer can hold before growing.
+    pub fn new(device: &wgpu::Device, label: &'static str, capacity: usize) -> Self {
+        Self {
+            label,
+            gpu_buffer: Self::create_gpu_buffer(device, label, capacity),
+            _values: std::marker::PhantomData,
+        }
+    }
+
+    /// Copy `values` to the GPU, growing the buffer to the next power of two
+    /// if they do not fit. Returns true if the GPU buffer was replaced.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, values: &[T]) -> bool {
+        let replaced = values.len() > self.capacity();
+
+        if replaced {
+            self.gpu_buffer =
+                Self::create_gpu_buffer(device, self.label, values.len().next_power_of_two());
+        }
+
+        if !values.is_empty() {
+            queue.write_buffer(&self.gpu_buffer, 0, bytemuck::cast_slice(values));
+        }
+
+        replaced
+    }
+
+    /// Get the number of values the buffer can hold before growing.
+    pub fn capacity(&self) -> usize {
+        self.gpu_buffer.size() as usize / std::mem::size_of::<T>()
+    }
+
+    /// Get the GPU buffer object used by this storage buffer.
+    pub fn gpu_buffer(&self) -> &wgpu::Buffer {
+        &self.gpu_buffer
+    }
+
+    fn create_gpu_buffer(device: &wgpu::Device, label: &str, capacity: usize) -> wgpu::Buffer {
+        // Empty bindings are not allowed, so there is always room for a value.
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity.max(1) * std::mem::size_of::<T>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
@@ -0,0 +1,32 @@
+use std::rc::Rc;
+
+/// Shared handles to the GPU device and queue.
+///
+/// A `GpuContext` is cheap to clone, which allows systems other than the
+/// renderer (e.g., content loading) to create and update GPU resources without
+/// needing access to the renderer.
+#[derive(Clone, Debug)]
+pub struct GpuContext {
+    device: Rc<wgpu::Device>,
+    queue: Rc<wgpu::Queue>,
+}
+
+impl GpuContext {
+    /// Create a new GPU context that takes ownership of `device` and `queue`.
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+        Self {
+            device: Rc::new(device),
+            queue: Rc::new(queue),
+        }
+    }
+
+    /// Get the device used for creating GPU resources.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// Get the queue used for submitting commands and writing to GPU resources.
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    cell::Cell,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tracing::warn;
+
+use super::stats::RenderStats;
+
+/// Render passes that can be timed on the GPU with timestamp queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimedPass {
+    Main,
+    LightDebug,
+    Depth,
+}
+
+impl TimedPass {
+    /// Number of passes that can be timed.
+    const COUNT: usize = 3;
+    const ALL: [TimedPass; Self::COUNT] = [Self::Main, Self::LightDebug, Self::Depth];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The readback buffer is not in use and can be copied into.
+const READBACK_IDLE: u8 = 0;
+/// A copy into the readback buffer was recorded and needs to be mapped once the
+/// command buffer is submitted.
+const READBACK_COPY_QUEUED: u8 = 1;
+/// The readback buffer is waiting for the GPU to finish mapping it.
+const READBACK_MAP_PENDING: u8 = 2;
+/// The readback buffer is mapped and the timestamps can be read.
+const READBACK_MAPPED: u8 = 3;
+
+/// Measures how much GPU time each render pass takes by writing timestamps at
+/// the beginning and end of each timed pass.
+///
+/// Timestamp queries are an optional WGPU feature. When the device was not
+/// created with `wgpu::Features::TIMESTAMP_QUERY` the timer does nothing and all
+/// pass timings are reported as zero.
+pub struct GpuTimer {
+    queries: Option<TimestampQueries>,
+}
+
+/// GPU resources required to write, resolve and read back timestamp queries.
+struct TimestampQueries {
+    /// Two timestamps (begin and end) for each timed pass.
+    query_set: wgpu::QuerySet,
+    /// Destination for resolved queries. Each pass is resolved to its own
+    /// `QUERY_RESOLVE_BUFFER_ALIGNMENT` aligned slot.
+    resolve_buffer: wgpu::Buffer,
+    /// CPU readable copy of `resolve_buffer`.
+    readback_buffer: wgpu::Buffer,
+    /// Number of nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    /// Passes that have written timestamps in the frame being recorded.
+    written: Cell<[bool; TimedPass::COUNT]>,
+    /// Passes that had written timestamps when `readback_buffer` was copied to.
+    readback_written: Cell<[bool; TimedPass::COUNT]>,
+    /// One of the `READBACK_*` states. Shared with the buffer map callback.
+    readback_state: Arc<AtomicU8>,
+}
+
+impl GpuTimer {
+    /// Byte stride between each pass's resolved timestamps.
+    const PASS_STRIDE: wgpu::BufferAddress = wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT;
+
+    /// Create a new GPU timer. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            warn!("GPU timestamp queries are not supported by this device, GPU pass timings will be reported as zero");
+            return Self { queries: None };
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu timer query set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: (TimedPass::COUNT * 2) as u32,
+        });
+
+        let buffer_size = Self::PASS_STRIDE * TimedPass::COUNT as wgpu::BufferAddress;
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu timer resolve buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu timer readback buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            queries: Some(TimestampQueries {
+                query_set,
+                resolve_buffer,
+                readback_buffer,
+                timestamp_period: queue.get_timestamp_period(),
+                written: Cell::new([false; TimedPass::COUNT]),
+                readback_written: Cell::new([false; TimedPass::COUNT]),
+                readback_state: Arc::new(AtomicU8::new(READBACK_IDLE)),
+            }),
+        }
+    }
+
+    /// Get the timestamp writes to attach to `pass` when beginning the render
+    /// pass, or `None` if timestamp queries are not supported.
+    pub fn timestamp_writes(&self, pass: TimedPass) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.queries.as_ref().map(|q| {
+            let mut written = q.written.get();
+            written[pass.index()] = true;
+            q.written.set(written);
+
+            wgpu::RenderPassTimestampWrites {
+                query_set: &q.query_set,
+                beginning_of_pass_write_index: Some((pass.index() * 2) as u32),
+                end_of_pass_write_index: Some((pass.index() * 2 + 1) as u32),
+            }
+        })
+    }
+
+    /// Resolve the timestamps written this frame and copy them to the readback
+    /// buffer if it is not already in use. This must be called after all timed
+    /// passes have been recorded into `command_encoder`.
+    pub fn resolve(&self, command_encoder: &mut wgpu::CommandEncoder) {
+        let Some(q) = &self.queries else {
+            return;
+        };
+
+        // Only resolve the passes that were actually recorded because resolving
+        // a query that was never written is not portable.
+        let written = q.written.replace([false; TimedPass::COUNT]);
+
+        for pass in TimedPass::ALL.iter().filter(|p| written[p.index()]) {
+            let first_query = (pass.index() * 2) as u32;
+            command_encoder.resolve_query_set(
+                &q.query_set,
+                first_query..(first_query + 2),
+                &q.resolve_buffer,
+                pass.index() as wgpu::BufferAddress * Self::PASS_STRIDE,
+            );
+        }
+
+        // The previous frame's results may still be waiting to be read back. In
+        // that case skip copying this frame's results.
+        if q.readback_state.load(Ordering::Acquire) == READBACK_IDLE {
+            command_encoder.copy_buffer_to_buffer(
+                &q.resolve_buffer,
+                0,
+                &q.readback_buffer,
+                0,
+                q.readback_buffer.size(),
+            );
+
+            q.readback_written.set(written);
+            q.readback_state
+                .store(READBACK_COPY_QUEUED, Ordering::Release);
+        }
+    }
+
+    /// Request the readback buffer be mapped for reading. This must be called
+    /// after the command buffer containing `resolve` has been submitted.
+    pub fn map_results(&self) {
+        let Some(q) = &self.queries else {
+            return;
+        };
+
+        if q.readback_state.load(Ordering::Acquire) != READBACK_COPY_QUEUED {
+            return;
+        }
+
+        q.readback_state
+            .store(READBACK_MAP_PENDING, Ordering::Release);
+
+        let readback_state = q.readback_state.clone();
+        q.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                readback_state.store(
+                    if result.is_ok() {
+                        READBACK_MAPPED
+                    } else {
+                        READBACK_IDLE
+                    },
+                    Ordering::Release,
+                );
+            });
+    }
+
+    /// Copy the most recently read back pass timings into `stats`. Stats are
+    /// left untouched if no new timings are available yet.
+    pub fn collect_results(&self, device: &wgpu::Device, stats: &mut RenderStats) {
+        let Some(q) = &self.queries else {
+            return;
+        };
+
+        // Give the device a chance to invoke any pending map callbacks.
+        device.poll(wgpu::Maintain::Poll);
+
+        if q.readback_state.load(Ordering::Acquire) != READBACK_MAPPED {
+            return;
+        }
+
+        {
+            let mapped_range = q.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&mapped_range);
+            let written = q.readback_written.get();
+
+            for pass in TimedPass::ALL {
+                let elapsed = if written[pass.index()] {
+                    let first =
+                        (pass.index() as wgpu::BufferAddress * Self::PASS_STRIDE / 8) as usize;
+                    let ticks = timestamps[first + 1].saturating_sub(timestamps[first]);
+                    Duration::from_nanos((ticks as f64 * q.timestamp_period as f64) as u64)
+                } else {
+                    Duration::ZERO
+                };
+
+                match pass {
+                    TimedPass::Main => stats.main_pass_gpu_time = elapsed,
+                    TimedPass::LightDebug => stats.light_debug_pass_gpu_time = elapsed,
+                    TimedPass::Depth => stats.depth_pass_gpu_time = elapsed,
+                }
+            }
+        }
+
+        q.readback_buffer.unmap();
+        q.readback_state.store(READBACK_IDLE, Ordering::Release);
+    }
+}
@@ -0,0 +1,134 @@
+//! Pixel-wise comparison of rendered frames against reference ("golden")
+//! images, so tests running on a headless renderer can catch visual
+//! regressions.
+use anyhow::bail;
+
+/// How much two images differ.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// Number of pixels with a channel that differs by more than the allowed
+    /// tolerance.
+    pub differing_pixels: usize,
+    /// The largest difference of any channel of any pixel.
+    pub max_difference: u8,
+}
+
+impl ImageDiff {
+    /// Get the fraction of pixels in a `width` by `height` image that differ.
+    #[allow(dead_code)]
+    pub fn differing_fraction(&self, width: u32, height: u32) -> f32 {
+        self.differing_pixels as f32 / (width * height).max(1) as f32
+    }
+}
+
+/// Compare `actual` to `expected` pixel by pixel. Channels that differ by at
+/// most `tolerance` are treated as equal, since GPUs and software adapters
+/// can round colors and rasterize edges slightly differently.
+#[allow(dead_code)]
+pub fn compare_images(
+    actual: &image::RgbaImage,
+    expected: &image::RgbaImage,
+    tolerance: u8,
+) -> anyhow::Result<ImageDiff> {
+    if actual.dimensions() != expected.dimensions() {
+        bail!(
+            "cannot compare a {:?} image to a {:?} image",
+            actual.dimensions(),
+            expected.dimensions()
+        );
+    }
+
+    let mut diff = ImageDiff::default();
+
+    for (a, b) in actual.pixels().zip(expected.pixels()) {
+        let difference =
+            a.0.iter()
+                .zip(b.0)
+                .map(|(&a, b)| a.abs_diff(b))
+                .max()
+                .unwrap_or_default();
+
+        diff.max_difference = diff.max_difference.max(difference);
+
+        if difference > tolerance {
+            diff.differing_pixels += 1;
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Check that `actual` matches the golden image `tests/golden/<name>.png`.
+/// Up to `max_differing_fraction` of the pixels may differ by more than
+/// `tolerance`.
+///
+/// Golden images are written instead of compared when the
+/// `SQUIRREL_UPDATE_GOLDEN` environment variable is set. When the images do
+/// not match, `actual` is saved next to the build output for inspection.
+#[cfg(test)]
+pub fn assert_matches_golden(
+    name: &str,
+    actual: &image::RgbaImage,
+    tolerance: u8,
+    max_differing_fraction: f32,
+) {
+    use std::path::Path;
+
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.png"));
+
+    if std::env::var_os("SQUIRREL_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+
+    let expected = image::open(&golden_path)
+        .unwrap_or_else(|e| {
+            panic!(
+                "failed to load golden image {golden_path:?}, set SQUIRREL_UPDATE_GOLDEN to create it: {e}"
+            )
+        })
+        .to_rgba8();
+    let diff = compare_images(actual, &expected, tolerance).unwrap();
+
+    if diff.differing_fraction(actual.width(), actual.height()) > max_differing_fraction {
+        let actual_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join("golden")
+            .join(format!("{name}.png"));
+        std::fs::create_dir_all(actual_path.parent().unwrap()).unwrap();
+        actual.save(&actual_path).unwrap();
+
+        panic!("rendered image does not match golden image {name:?}: {diff:?}, saved the rendered image to {actual_path:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn differences_past_the_tolerance_are_counted() {
+        let expected = image::RgbaImage::from_pixel(4, 2, image::Rgba([10, 20, 30, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, image::Rgba([12, 20, 30, 255]));
+        actual.put_pixel(3, 1, image::Rgba([10, 20, 90, 255]));
+
+        let diff = compare_images(&actual, &expected, 2).unwrap();
+        assert_eq!(
+            ImageDiff {
+                differing_pixels: 1,
+                max_difference: 60,
+            },
+            diff
+        );
+        assert_eq!(0.125, diff.differing_fraction(4, 2));
+
+        let smaller = image::RgbaImage::new(2, 2);
+        assert!(compare_images(&smaller, &expected, 0).is_err());
+    }
+}
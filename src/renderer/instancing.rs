@@ -152,23 +152,29 @@ pub fn spawn_object_instances_as_grid(
     displacement: Vec3,
     angle_radians: f32,
 ) -> Vec<ModelInstance> {
-    (0..num_rows)
-        .flat_map(|z| {
-            (0..instances_per_row).map(move |x| {
-                let position: Vec3 = Vec3 {
-                    x: x as f32 * 2.0,
-                    y: 0.0,
-                    z: z as f32 * 2.0,
-                } - displacement;
-
-                let rotation = if position == Vec3::ZERO {
-                    Quat::from_axis_angle(Vec3::Z, 0.0)
-                } else {
-                    Quat::from_axis_angle(position.normalize(), angle_radians)
-                };
-
-                ModelInstance { position, rotation }
-            })
+    grid_positions(num_rows, instances_per_row, 2.0)
+        .map(|position| {
+            let position = position - displacement;
+            let rotation = if position == Vec3::ZERO {
+                Quat::from_axis_angle(Vec3::Z, 0.0)
+            } else {
+                Quat::from_axis_angle(position.normalize(), angle_radians)
+            };
+
+            ModelInstance { position, rotation }
         })
         .collect::<Vec<_>>()
 }
+
+/// Get the position of each cell in a `num_rows` by `num_cols` grid on the XZ
+/// plane. Cells are `spacing` units apart with the first cell at the origin,
+/// columns along +X and rows along +Z. Positions are returned one row at a time.
+pub fn grid_positions(
+    num_rows: usize,
+    num_cols: usize,
+    spacing: f32,
+) -> impl Iterator<Item = Vec3> {
+    (0..num_rows).flat_map(move |z| {
+        (0..num_cols).map(move |x| Vec3::new(x as f32 * spacing, 0.0, z as f32 * spacing))
+    })
+}
@@ -1,23 +1,56 @@
-use glam::Vec3;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The default brightness multiplier of a light.
+pub const DEFAULT_LIGHT_INTENSITY: f32 = 1.0;
+/// The default range of point and spot lights, which is large enough that the
+/// light is only faded out by its attenuation.
+pub const DEFAULT_LIGHT_RANGE: f32 = f32::MAX;
+
+/// Point light shadows are only cast this far from the light, even when the
+/// light's range is larger.
+pub const MAX_POINT_SHADOW_DISTANCE: f32 = 100.0;
+
+/// Color of the sun when it is on the horizon at sunrise and sunset.
+const SUN_HORIZON_COLOR: Vec3 = Vec3::new(1.0, 0.45, 0.15);
+/// Color of the sun when it is high in the sky.
+const SUN_OVERHEAD_COLOR: Vec3 = Vec3::new(1.0, 0.97, 0.92);
+/// Sun elevation (the sine of the angle above the horizon) at which the sun has
+/// fully changed from its horizon color to its overhead color.
+const SUN_WHITE_ELEVATION: f32 = 0.5;
+/// How far below the horizon the sun can be before it stops lighting the scene.
+/// Lets the light fade out after sunset rather than switching off.
+const SUN_TWILIGHT_ELEVATION: f32 = 0.1;
 
 /// Point light.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PointLight {
     /// The world position of the light.
     pub position: Vec3,
     /// The color of the light.
     pub color: Vec3,
+    /// Brightness multiplier applied to the light's color.
+    pub intensity: f32,
     /// Attenuation terms.
     pub attenuation: LightAttenuation,
+    /// Distance from the light at which it is smoothly faded out to nothing.
+    pub range: f32,
     /// Modifies the amount of color that is applied to the ambient term when
     /// shading.
     pub ambient: f32,
     /// Modifies the amount of white color that is applied to the specular term
     /// when shading.
     pub specular: f32,
+    /// Whether models block this light and cast shadows. Shadows are rendered
+    /// into a depth cube map every frame, so only a few lights should cast
+    /// them.
+    pub casts_shadows: bool,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LightAttenuation {
     pub constant: f32,
     pub linear: f32,
@@ -25,12 +58,15 @@ pub struct LightAttenuation {
 }
 
 /// Directional light.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DirectionalLight {
     /// The direction of the light pointing _away_ from the light source.
     pub direction: Vec3,
     /// The color of the light.
     pub color: Vec3,
+    /// Brightness multiplier applied to the light's color.
+    pub intensity: f32,
     /// Modifies the amount of color that is applied to the ambient term when
     /// shading.
     pub ambient: f32,
@@ -40,7 +76,8 @@ pub struct DirectionalLight {
 }
 
 /// A spot light.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SpotLight {
     /// The world position of the light.
     pub position: Vec3,
@@ -52,8 +89,12 @@ pub struct SpotLight {
     pub outer_cutoff_radians: f32,
     /// The color of the light.
     pub color: Vec3,
+    /// Brightness multiplier applied to the light's color.
+    pub intensity: f32,
     /// Attenuation terms.
     pub attenuation: LightAttenuation,
+    /// Distance from the light at which it is smoothly faded out to nothing.
+    pub range: f32,
     /// Modifies the amount of color that is applied to the ambient term when
     /// shading.
     pub ambient: f32,
@@ -61,3 +102,201 @@ pub struct SpotLight {
     /// when shading.
     pub specular: f32,
 }
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            color: Vec3::ZERO,
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            attenuation: LightAttenuation::default(),
+            range: DEFAULT_LIGHT_RANGE,
+            ambient: 0.0,
+            specular: 0.0,
+            casts_shadows: false,
+        }
+    }
+}
+
+impl PointLight {
+    /// Get how far from the light models can cast shadows.
+    pub fn shadow_distance(&self) -> f32 {
+        self.range.min(MAX_POINT_SHADOW_DISTANCE)
+    }
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::ZERO,
+            color: Vec3::ZERO,
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            ambient: 0.0,
+            specular: 0.0,
+        }
+    }
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            direction: Vec3::ZERO,
+            cutoff_radians: 0.0,
+            outer_cutoff_radians: 0.0,
+            color: Vec3::ZERO,
+            intensity: DEFAULT_LIGHT_INTENSITY,
+            attenuation: LightAttenuation::default(),
+            range: DEFAULT_LIGHT_RANGE,
+            ambient: 0.0,
+            specular: 0.0,
+        }
+    }
+}
+
+impl SpotLight {
+    /// Create a spot light with the given inner and outer cone angles in
+    /// radians. All other properties are left at their default values.
+    ///
+    /// The angles must satisfy `0 < cutoff <= outer_cutoff < π/2`.
+    pub fn from_radians(
+        cutoff_radians: f32,
+        outer_cutoff_radians: f32,
+    ) -> Result<Self, InvalidSpotLightCutoff> {
+        let mut light = Self::default();
+        light.set_cutoff_radians(cutoff_radians, outer_cutoff_radians)?;
+        Ok(light)
+    }
+
+    /// Create a spot light with the given inner and outer cone angles in
+    /// degrees. All other properties are left at their default values.
+    ///
+    /// The angles must satisfy `0 < cutoff <= outer_cutoff < 90`.
+    pub fn from_degrees(
+        cutoff_degrees: f32,
+        outer_cutoff_degrees: f32,
+    ) -> Result<Self, InvalidSpotLightCutoff> {
+        Self::from_radians(
+            cutoff_degrees.to_radians(),
+            outer_cutoff_degrees.to_radians(),
+        )
+    }
+
+    /// Set the inner and outer cone angles in radians, leaving the light
+    /// unchanged if they are not valid.
+    pub fn set_cutoff_radians(
+        &mut self,
+        cutoff_radians: f32,
+        outer_cutoff_radians: f32,
+    ) -> Result<(), InvalidSpotLightCutoff> {
+        if !is_valid_cutoff(cutoff_radians, outer_cutoff_radians) {
+            return Err(InvalidSpotLightCutoff(cutoff_radians, outer_cutoff_radians));
+        }
+
+        self.cutoff_radians = cutoff_radians;
+        self.outer_cutoff_radians = outer_cutoff_radians;
+
+        Ok(())
+    }
+
+    /// Returns true if the inner cone angle is inside of the outer cone angle.
+    pub fn has_valid_cutoff(&self) -> bool {
+        is_valid_cutoff(self.cutoff_radians, self.outer_cutoff_radians)
+    }
+}
+
+/// Get a directional light for the sun at `time_of_day`, where 0 is midnight,
+/// 0.25 is sunrise, 0.5 is noon and 0.75 is sunset. Times outside of `[0, 1)`
+/// wrap around to the same time on another day.
+///
+/// The sun rises in the east (+X), sets in the west (-X), and at noon is tilted
+/// towards the south (+Z) by `latitude_degrees`. The sun is white when high in
+/// the sky, warms to orange near the horizon and fades out once it has set.
+pub fn sun_light(time_of_day: f32, latitude_degrees: f32) -> DirectionalLight {
+    // Angle of the sun along its arc, measured from the eastern horizon.
+    let arc_angle = (time_of_day.rem_euclid(1.0) - 0.25) * std::f32::consts::TAU;
+    let sun_position = Quat::from_rotation_x(latitude_degrees.to_radians())
+        * Vec3::new(arc_angle.cos(), arc_angle.sin(), 0.0);
+
+    let elevation = sun_position.y;
+    let brightness =
+        ((elevation + SUN_TWILIGHT_ELEVATION) / SUN_TWILIGHT_ELEVATION).clamp(0.0, 1.0);
+    let whiteness = (elevation / SUN_WHITE_ELEVATION).clamp(0.0, 1.0);
+
+    DirectionalLight {
+        direction: -sun_position,
+        color: SUN_HORIZON_COLOR.lerp(SUN_OVERHEAD_COLOR, whiteness),
+        intensity: DEFAULT_LIGHT_INTENSITY * brightness,
+        ambient: 0.05 * brightness,
+        specular: 0.5 * brightness,
+    }
+}
+
+/// Check that spot light cone angles satisfy `0 < cutoff <= outer < π/2`.
+fn is_valid_cutoff(cutoff_radians: f32, outer_cutoff_radians: f32) -> bool {
+    0.0 < cutoff_radians
+        && cutoff_radians <= outer_cutoff_radians
+        && outer_cutoff_radians < std::f32::consts::FRAC_PI_2
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("spot light cutoff angles must satisfy 0 < cutoff <= outer cutoff < π/2 but cutoff was {} and outer cutoff was {} radians", .0, .1)]
+pub struct InvalidSpotLightCutoff(f32, f32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noon_sun_points_down() {
+        let light = sun_light(0.5, 0.0);
+        assert!(light.direction.abs_diff_eq(Vec3::NEG_Y, 1e-5));
+        assert_eq!(DEFAULT_LIGHT_INTENSITY, light.intensity);
+
+        // Away from the equator the noon sun is tilted towards the south.
+        let light = sun_light(0.5, 40.0);
+        assert!((light.direction.angle_between(Vec3::NEG_Y).to_degrees() - 40.0).abs() < 1e-3);
+        assert!(light.direction.z < 0.0);
+
+        // Times wrap around to the next day.
+        assert!(sun_light(1.5, 0.0).direction.abs_diff_eq(Vec3::NEG_Y, 1e-5));
+    }
+
+    #[test]
+    fn sun_is_warmer_near_the_horizon() {
+        let warmth = |light: DirectionalLight| light.color.x - light.color.z;
+
+        let sunrise = sun_light(0.26, 0.0);
+        let noon = sun_light(0.5, 0.0);
+        assert!(sunrise.direction.x < 0.0, "the sun rises in the east");
+        assert!(warmth(sunrise) > warmth(noon));
+
+        // The sun does not light the scene at night.
+        assert_eq!(0.0, sun_light(0.0, 0.0).intensity);
+    }
+
+    #[test]
+    fn spot_light_from_degrees_converts_to_radians() {
+        let light = SpotLight::from_degrees(12.5, 17.5).unwrap();
+
+        assert!((light.cutoff_radians - 0.2181662).abs() < 1e-6);
+        assert!((light.outer_cutoff_radians - 0.3054326).abs() < 1e-6);
+        assert!(light.has_valid_cutoff());
+    }
+
+    #[test]
+    fn spot_light_cutoff_must_be_inside_outer_cutoff() {
+        assert_eq!(
+            SpotLight::from_degrees(20.0, 10.0).unwrap_err(),
+            InvalidSpotLightCutoff(20.0_f32.to_radians(), 10.0_f32.to_radians())
+        );
+        assert!(SpotLight::from_degrees(0.0, 10.0).is_err());
+        assert!(SpotLight::from_degrees(10.0, 90.0).is_err());
+        assert!(SpotLight::from_degrees(10.0, 10.0).is_ok());
+
+        // A rejected update leaves the previous angles in place.
+        let mut light = SpotLight::from_degrees(10.0, 20.0).unwrap();
+        assert!(light.set_cutoff_radians(0.5, 0.25).is_err());
+        assert_eq!(light.cutoff_radians, 10.0_f32.to_radians());
+    }
+}
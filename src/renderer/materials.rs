@@ -1,25 +1,180 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
-use glam::Vec3;
+use glam::{Vec2, Vec3};
+use serde::Deserialize;
 
-use crate::content::DefaultTextures;
+use crate::{content::DefaultTextures, renderer::textures::Texture};
 
 /// A render material that is compatible with the standard lighting shader
-/// with phong lighting properties.
+/// with phong lighting properties, or the physically based lighting shader.
 ///
 /// A material can set both a constant color and a texture map for the ambient,
 /// diffuse and specular values. When both a constant and a texture map are set
 /// the values are multiplied together. The ambient color is ambient color
 /// multiplied by the diffuse texture.
+///
+/// Materials using `ShadingModel::Pbr` use the diffuse color as their base
+/// color, and the metallic, roughness and ambient occlusion values in place of
+/// the ambient and specular values.
 #[derive(Clone, Debug)]
 pub struct Material {
     pub ambient_color: Vec3,
     pub diffuse_color: Vec3,
-    pub diffuse_map: Rc<wgpu::Texture>,
+    pub diffuse_map: Rc<Texture>,
     pub specular_color: Vec3,
-    pub specular_map: Rc<wgpu::Texture>,
+    pub specular_map: Rc<Texture>,
     pub specular_power: f32,
-    pub emissive_map: Rc<wgpu::Texture>,
+    pub emissive_color: Vec3,
+    pub emissive_map: Rc<Texture>,
+    /// How metallic the surface is, from 0 for dielectrics to 1 for metals.
+    /// Multiplied by the red channel of `metallic_map`.
+    pub metallic: f32,
+    pub metallic_map: Rc<Texture>,
+    /// How rough the surface is, from 0 for a perfect mirror to 1 for a fully
+    /// diffuse surface. Multiplied by the red channel of `roughness_map`.
+    pub roughness: f32,
+    pub roughness_map: Rc<Texture>,
+    /// Ambient occlusion map. The red channel darkens ambient light reaching
+    /// creases and cavities in the surface.
+    pub ao_map: Rc<Texture>,
+    /// Texture coordinates are multiplied by `uv_scale` before texture maps are
+    /// sampled. Values larger than one will repeat the texture.
+    pub uv_scale: Vec2,
+    /// Texture coordinates are offset by `uv_offset` after being scaled.
+    pub uv_offset: Vec2,
+    /// Which triangle faces are culled when drawing with this material.
+    pub cull_mode: CullMode,
+    /// Unlit materials ignore scene lights and are drawn with their diffuse
+    /// and emissive colors, e.g. for lamps and other self-illuminated objects.
+    pub unlit: bool,
+    /// The lighting model used to shade the material.
+    pub shading: ShadingModel,
+    /// Opacity of the material from 0 (invisible) to 1 (opaque), multiplied by
+    /// the diffuse map's alpha. Only used by `BlendMode::AlphaBlend`.
+    pub alpha: f32,
+    /// How the material is combined with what was drawn behind it.
+    pub blend_mode: BlendMode,
+}
+
+impl Material {
+    /// Returns true if this material scales or offsets texture coordinates.
+    pub fn has_uv_transform(&self) -> bool {
+        self.uv_scale != MaterialBuilder::DEFAULT_UV_SCALE
+            || self.uv_offset != MaterialBuilder::DEFAULT_UV_OFFSET
+    }
+}
+
+/// Controls which triangle faces are culled (not drawn) when rendering a
+/// material.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CullMode {
+    /// Cull back facing triangles.
+    #[default]
+    Back,
+    /// Cull front facing triangles.
+    Front,
+    /// Draw both sides of every triangle, e.g. for foliage and other thin
+    /// surfaces.
+    None,
+}
+
+impl CullMode {
+    pub const ALL: [CullMode; 3] = [CullMode::Back, CullMode::Front, CullMode::None];
+
+    /// Get the equivalent WGPU face culling value.
+    pub fn to_wgpu_face(self) -> Option<wgpu::Face> {
+        match self {
+            CullMode::Back => Some(wgpu::Face::Back),
+            CullMode::Front => Some(wgpu::Face::Front),
+            CullMode::None => None,
+        }
+    }
+}
+
+/// The lighting model a material is shaded with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShadingModel {
+    /// Blinn-Phong lighting with ambient, diffuse and specular colors.
+    #[default]
+    Phong,
+    /// Physically based lighting with metallic and roughness values.
+    Pbr,
+}
+
+impl ShadingModel {
+    pub const ALL: [ShadingModel; 2] = [ShadingModel::Phong, ShadingModel::Pbr];
+}
+
+/// How a material's fragments are combined with what was already drawn behind
+/// them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Fragments replace what is behind them, and the material's alpha is
+    /// ignored.
+    #[default]
+    Opaque,
+    /// Fragments are blended over what is behind them by the material's alpha.
+    /// Transparent submeshes are drawn after every opaque submesh, sorted back
+    /// to front, and do not write to the depth buffer.
+    AlphaBlend,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 2] = [BlendMode::Opaque, BlendMode::AlphaBlend];
+
+    /// Returns true if materials with this blend mode let what is behind them
+    /// show through.
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+}
+
+/// A small cache of render pipelines, one for each material shading model,
+/// cull mode and blend mode.
+///
+/// The shader, cull mode and blend mode are fixed when a render pipeline is
+/// created, so materials that differ in any of them must be drawn with
+/// different pipelines.
+pub struct MaterialPipelines {
+    pipelines: HashMap<(ShadingModel, CullMode, BlendMode), wgpu::RenderPipeline>,
+}
+
+impl MaterialPipelines {
+    /// Create a render pipeline for each shading model, cull mode and blend
+    /// mode with `create_pipeline`.
+    pub fn new(
+        mut create_pipeline: impl FnMut(ShadingModel, CullMode, BlendMode) -> wgpu::RenderPipeline,
+    ) -> Self {
+        Self {
+            pipelines: ShadingModel::ALL
+                .iter()
+                .flat_map(|shading| {
+                    CullMode::ALL
+                        .iter()
+                        .map(move |cull_mode| (*shading, *cull_mode))
+                })
+                .flat_map(|(shading, cull_mode)| {
+                    BlendMode::ALL
+                        .iter()
+                        .map(move |blend_mode| (shading, cull_mode, *blend_mode))
+                })
+                .map(|key| (key, create_pipeline(key.0, key.1, key.2)))
+                .collect(),
+        }
+    }
+
+    /// Get the render pipeline for materials using `shading`, `cull_mode` and
+    /// `blend_mode`.
+    pub fn get(
+        &self,
+        shading: ShadingModel,
+        cull_mode: CullMode,
+        blend_mode: BlendMode,
+    ) -> &wgpu::RenderPipeline {
+        &self.pipelines[&(shading, cull_mode, blend_mode)]
+    }
 }
 
 /// A fluent builder for creating Materials without having to specify every
@@ -34,9 +189,22 @@ pub struct MaterialBuilder {
     diffuse_color: Option<Vec3>,
     specular_color: Option<Vec3>,
     specular_power: Option<f32>,
-    diffuse_map: Option<Rc<wgpu::Texture>>,
-    specular_map: Option<Rc<wgpu::Texture>>,
-    emissive_map: Option<Rc<wgpu::Texture>>,
+    diffuse_map: Option<Rc<Texture>>,
+    specular_map: Option<Rc<Texture>>,
+    emissive_color: Option<Vec3>,
+    emissive_map: Option<Rc<Texture>>,
+    metallic: Option<f32>,
+    metallic_map: Option<Rc<Texture>>,
+    roughness: Option<f32>,
+    roughness_map: Option<Rc<Texture>>,
+    ao_map: Option<Rc<Texture>>,
+    uv_scale: Option<Vec2>,
+    uv_offset: Option<Vec2>,
+    cull_mode: Option<CullMode>,
+    unlit: Option<bool>,
+    shading: Option<ShadingModel>,
+    alpha: Option<f32>,
+    blend_mode: Option<BlendMode>,
 }
 
 impl MaterialBuilder {
@@ -44,6 +212,12 @@ impl MaterialBuilder {
     pub const DEFAULT_DIFFUSE_COLOR: Vec3 = Vec3::new(1.0, 1.0, 1.0);
     pub const DEFAULT_SPECULAR_COLOR: Vec3 = Vec3::new(0.0, 0.0, 0.0);
     pub const DEFAULT_SPECULAR_POWER: f32 = 0.0;
+    pub const DEFAULT_EMISSIVE_COLOR: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const DEFAULT_METALLIC: f32 = 0.0;
+    pub const DEFAULT_ROUGHNESS: f32 = 0.5;
+    pub const DEFAULT_UV_SCALE: Vec2 = Vec2::ONE;
+    pub const DEFAULT_UV_OFFSET: Vec2 = Vec2::ZERO;
+    pub const DEFAULT_ALPHA: f32 = 1.0;
 
     /// Create a new material builder.
     pub fn new() -> Self {
@@ -54,7 +228,20 @@ impl MaterialBuilder {
             specular_power: None,
             diffuse_map: None,
             specular_map: None,
+            emissive_color: None,
             emissive_map: None,
+            metallic: None,
+            metallic_map: None,
+            roughness: None,
+            roughness_map: None,
+            ao_map: None,
+            uv_scale: None,
+            uv_offset: None,
+            cull_mode: None,
+            unlit: None,
+            shading: None,
+            alpha: None,
+            blend_mode: None,
         }
     }
 
@@ -85,29 +272,115 @@ impl MaterialBuilder {
     }
 
     /// Set the material's diffuse texture map.
-    pub fn diffuse_map(mut self, texture: Rc<wgpu::Texture>) -> Self {
+    pub fn diffuse_map(mut self, texture: Rc<Texture>) -> Self {
         self.diffuse_map = Some(texture);
         self
     }
 
     /// Set the material's specular texture map.
-    pub fn specular_map(mut self, texture: Rc<wgpu::Texture>) -> Self {
+    pub fn specular_map(mut self, texture: Rc<Texture>) -> Self {
         self.specular_map = Some(texture);
         self
     }
 
-    /// Set the material's emissive texture map.
-    #[allow(dead_code)]
-    pub fn emissive_map(mut self, texture: Rc<wgpu::Texture>) -> Self {
+    /// Set the material's emissive color to a constant value.
+    pub fn emissive_color(mut self, color: Vec3) -> Self {
+        self.emissive_color = Some(color);
+        self
+    }
+
+    /// Set the material's emissive texture map. The emissive color defaults to
+    /// white when a map is set so the map is used as is.
+    pub fn emissive_map(mut self, texture: Rc<Texture>) -> Self {
         self.emissive_map = Some(texture);
         self
     }
 
+    /// Set how metallic the material is when using PBR shading.
+    pub fn metallic(mut self, metallic: f32) -> Self {
+        self.metallic = Some(metallic);
+        self
+    }
+
+    /// Set the material's metallic texture map.
+    pub fn metallic_map(mut self, texture: Rc<Texture>) -> Self {
+        self.metallic_map = Some(texture);
+        self
+    }
+
+    /// Set how rough the material is when using PBR shading.
+    pub fn roughness(mut self, roughness: f32) -> Self {
+        self.roughness = Some(roughness);
+        self
+    }
+
+    /// Set the material's roughness texture map.
+    pub fn roughness_map(mut self, texture: Rc<Texture>) -> Self {
+        self.roughness_map = Some(texture);
+        self
+    }
+
+    /// Set the material's ambient occlusion texture map.
+    pub fn ao_map(mut self, texture: Rc<Texture>) -> Self {
+        self.ao_map = Some(texture);
+        self
+    }
+
+    /// Set how many times the material's texture maps repeat across the mesh.
+    pub fn uv_scale(mut self, scale: Vec2) -> Self {
+        self.uv_scale = Some(scale);
+        self
+    }
+
+    /// Set the offset applied to texture coordinates after they are scaled.
+    pub fn uv_offset(mut self, offset: Vec2) -> Self {
+        self.uv_offset = Some(offset);
+        self
+    }
+
+    /// Set which triangle faces are culled when drawing the material. Use
+    /// `CullMode::None` for two-sided materials.
+    pub fn cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = Some(cull_mode);
+        self
+    }
+
+    /// Set if the material ignores scene lighting.
+    pub fn unlit(mut self, unlit: bool) -> Self {
+        self.unlit = Some(unlit);
+        self
+    }
+
+    /// Set the lighting model used to shade the material.
+    pub fn shading(mut self, shading: ShadingModel) -> Self {
+        self.shading = Some(shading);
+        self
+    }
+
+    /// Set the opacity of the material from 0 (invisible) to 1 (opaque). This
+    /// is only used when the blend mode is `BlendMode::AlphaBlend`.
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.alpha = Some(alpha.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Set how the material is combined with what was drawn behind it.
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = Some(blend_mode);
+        self
+    }
+
     /// Use the properties of this material builder to construct a new material.
     ///
     /// An appropriate default texture from `default_textures` is used when a
     /// texture map is not specified.
     pub fn build(self, default_textures: &DefaultTextures) -> Material {
+        let default_emissive_color = if self.emissive_map.is_some() {
+            Vec3::ONE
+        } else {
+            Self::DEFAULT_EMISSIVE_COLOR
+        };
+
         Material {
             ambient_color: self.ambient_color.unwrap_or(Self::DEFAULT_AMBIENT_COLOR),
             diffuse_color: self.diffuse_color.unwrap_or(Self::DEFAULT_DIFFUSE_COLOR),
@@ -119,9 +392,26 @@ impl MaterialBuilder {
             specular_map: self
                 .specular_map
                 .unwrap_or(default_textures.specular_map.clone()),
+            emissive_color: self.emissive_color.unwrap_or(default_emissive_color),
             emissive_map: self
                 .emissive_map
                 .unwrap_or(default_textures.emissive_map.clone()),
+            metallic: self.metallic.unwrap_or(Self::DEFAULT_METALLIC),
+            metallic_map: self
+                .metallic_map
+                .unwrap_or(default_textures.metallic_map.clone()),
+            roughness: self.roughness.unwrap_or(Self::DEFAULT_ROUGHNESS),
+            roughness_map: self
+                .roughness_map
+                .unwrap_or(default_textures.roughness_map.clone()),
+            ao_map: self.ao_map.unwrap_or(default_textures.ao_map.clone()),
+            uv_scale: self.uv_scale.unwrap_or(Self::DEFAULT_UV_SCALE),
+            uv_offset: self.uv_offset.unwrap_or(Self::DEFAULT_UV_OFFSET),
+            cull_mode: self.cull_mode.unwrap_or_default(),
+            unlit: self.unlit.unwrap_or_default(),
+            shading: self.shading.unwrap_or_default(),
+            alpha: self.alpha.unwrap_or(Self::DEFAULT_ALPHA),
+            blend_mode: self.blend_mode.unwrap_or_default(),
         }
     }
 }
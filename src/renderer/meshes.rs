@@ -1,6 +1,7 @@
 //! NOTES:
 //! Meshes vertex winding order is CCW.
 //! Builtin meshes are ordered bottom left to bottom right.
+use glam::Vec3;
 use wgpu::util::DeviceExt;
 
 use super::{
@@ -12,6 +13,7 @@ use super::{
 /// A list of meshes that can be constructed by the engine without needing to
 /// load a model externally.
 #[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BuiltinMesh {
     Triangle,
     Rect,
@@ -19,6 +21,23 @@ pub enum BuiltinMesh {
     Cube,
 }
 
+impl BuiltinMesh {
+    /// Get a name for the mesh that is used to label its GPU buffers.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinMesh::Triangle => "Triangle",
+            BuiltinMesh::Rect => "Rect",
+            BuiltinMesh::Pentagon => "Pentagon",
+            BuiltinMesh::Cube => "Cube",
+        }
+    }
+
+    /// Returns true if the mesh is a flat shape lying on the XY plane.
+    pub fn is_flat(&self) -> bool {
+        !matches!(self, BuiltinMesh::Cube)
+    }
+}
+
 /// Generates a new `Mesh` object for the given builtin mesh.
 pub fn builtin_mesh(
     device: &wgpu::Device,
@@ -26,29 +45,98 @@ pub fn builtin_mesh(
     mesh_type: BuiltinMesh,
     material: &Material,
 ) -> Mesh {
-    let (vertices, indices) = builtin_mesh_verts(mesh_type);
-
-    Mesh::new(
-        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Cube Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        }),
-        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Cube Index Buffer"),
-            contents: bytemuck::cast_slice(indices),
-            usage: wgpu::BufferUsages::INDEX,
-        }),
-        indices.len() as u32,
-        wgpu::IndexFormat::Uint16,
-        vec![Submesh::new(
-            device,
-            layouts,
-            0..indices.len() as u32,
-            0,
-            material,
-        )],
-    )
+    let (vertices, indices) = builtin_mesh_geometry(mesh_type);
+    let submeshes = vec![Submesh::new(
+        device,
+        layouts,
+        0..indices.len() as u32,
+        0,
+        material,
+    )];
+
+    Mesh::new(device, mesh_type.name(), vertices, indices, submeshes)
+}
+
+/// Get the vertices and indices of a builtin mesh ready to be copied to the
+/// GPU.
+///
+/// Flat meshes only have faces pointing towards +Z in `builtin_mesh_verts`, so
+/// a copy of each face pointing towards -Z is added. This keeps flat meshes
+/// visible and correctly lit from behind without turning off back face culling.
+pub fn builtin_mesh_geometry(mesh_type: BuiltinMesh) -> (Vec<Vertex>, Vec<u32>) {
+    let is_flat = mesh_type.is_flat();
+    let (front_vertices, front_indices) = builtin_mesh_verts(mesh_type);
+
+    let mut vertices = front_vertices.to_vec();
+    let mut indices: Vec<u32> = front_indices.iter().map(|i| *i as u32).collect();
+
+    if is_flat {
+        let base_vertex = vertices.len() as u32;
+
+        // Back faces have a flipped normal, and mirrored texture coordinates so
+        // textures are not drawn backwards when viewed from behind.
+        vertices.extend(front_vertices.iter().map(|v| Vertex {
+            normal: (-Vec3::from(v.normal)).into(),
+            tex_coords: [1.0 - v.tex_coords[0], v.tex_coords[1]],
+            ..*v
+        }));
+
+        // Reverse the winding order so back faces are front facing from -Z.
+        indices.extend(
+            front_indices
+                .chunks_exact(3)
+                .flat_map(|t| [t[0], t[2], t[1]])
+                .map(|i| base_vertex + i as u32),
+        );
+    }
+
+    (vertices, indices)
+}
+
+/// Creates an index buffer for a mesh with `vertex_count` vertices.
+///
+/// 16-bit indices are used when every vertex can be addressed by one, otherwise
+/// 32-bit indices are used. The chosen format is returned alongside the buffer.
+pub fn create_index_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    vertex_count: usize,
+    indices: &[u32],
+) -> (wgpu::Buffer, wgpu::IndexFormat) {
+    let (index_format, contents) = pack_indices(vertex_count, indices);
+
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: &contents,
+        usage: wgpu::BufferUsages::INDEX,
+    });
+
+    (index_buffer, index_format)
+}
+
+/// Get the smallest index format that can address `vertex_count` vertices.
+pub fn index_format_for(vertex_count: usize) -> wgpu::IndexFormat {
+    if vertex_count <= u16::MAX as usize {
+        wgpu::IndexFormat::Uint16
+    } else {
+        wgpu::IndexFormat::Uint32
+    }
+}
+
+/// Convert `indices` to the bytes of an index buffer using the index format
+/// picked by `index_format_for`.
+fn pack_indices(vertex_count: usize, indices: &[u32]) -> (wgpu::IndexFormat, Vec<u8>) {
+    let index_format = index_format_for(vertex_count);
+
+    let contents = match index_format {
+        wgpu::IndexFormat::Uint16 => {
+            let indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            bytemuck::cast_slice(&indices).to_vec()
+        }
+        wgpu::IndexFormat::Uint32 => bytemuck::cast_slice(indices).to_vec(),
+    };
+
+    (index_format, contents)
 }
 
 /// Gets a vertex buffer representing a builtin mesh for use in rendering. All
@@ -333,3 +421,104 @@ pub const CUBE_INDICES: &[u16] = &[
     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
     26, 27, 28, 29, 30, 31, 32, 33, 34, 35,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_BUILTIN_MESHES: [BuiltinMesh; 4] = [
+        BuiltinMesh::Triangle,
+        BuiltinMesh::Rect,
+        BuiltinMesh::Pentagon,
+        BuiltinMesh::Cube,
+    ];
+
+    #[test]
+    fn builtin_mesh_normals_match_winding_order() {
+        for mesh_type in ALL_BUILTIN_MESHES {
+            let (vertices, indices) = builtin_mesh_geometry(mesh_type);
+            assert!(!indices.is_empty(), "{mesh_type:?} has no triangles");
+
+            // The normal of a counter clockwise triangle points out of its
+            // front face.
+            for triangle in indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+                let face_normal = (Vec3::from(b.position) - Vec3::from(a.position))
+                    .cross(Vec3::from(c.position) - Vec3::from(a.position))
+                    .normalize();
+
+                for vertex in [a, b, c] {
+                    assert!(
+                        face_normal.abs_diff_eq(Vec3::from(vertex.normal), 1e-4),
+                        "{mesh_type:?} triangle {triangle:?} faces {face_normal} but has normal {:?}",
+                        vertex.normal
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_builtin_mesh_builds_a_mesh() {
+        use crate::{
+            content::DefaultTextures, renderer::materials::MaterialBuilder,
+            test_support::test_renderer,
+        };
+
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let device = renderer.gpu().device();
+        let default_textures = DefaultTextures::new(device, renderer.gpu().queue());
+        let material = MaterialBuilder::new().build(&default_textures);
+
+        for mesh_type in ALL_BUILTIN_MESHES {
+            let mesh = builtin_mesh(device, renderer.bind_group_layouts(), mesh_type, &material);
+            let submesh_indices = mesh.submeshes()[0].indices();
+
+            assert!(!submesh_indices.is_empty(), "{mesh_type:?} mesh is empty");
+            assert!(mesh.bounds().max.x > mesh.bounds().min.x);
+        }
+    }
+
+    #[test]
+    fn flat_builtin_meshes_have_back_faces() {
+        let (front_vertices, front_indices) = builtin_mesh_verts(BuiltinMesh::Rect);
+        let (vertices, indices) = builtin_mesh_geometry(BuiltinMesh::Rect);
+
+        assert_eq!(front_vertices.len() * 2, vertices.len());
+        assert_eq!(front_indices.len() * 2, indices.len());
+        assert!(vertices.iter().any(|v| v.normal == [0.0, 0.0, -1.0]));
+    }
+
+    #[test]
+    fn unit_cube_bounds() {
+        let (vertices, _) = builtin_mesh_verts(BuiltinMesh::Cube);
+        let bounds = Mesh::compute_bounds(vertices);
+
+        assert!(bounds.min.abs_diff_eq(Vec3::splat(-0.5), 0.0001));
+        assert!(bounds.max.abs_diff_eq(Vec3::splat(0.5), 0.0001));
+    }
+
+    #[test]
+    fn small_meshes_use_16_bit_indices() {
+        let (index_format, contents) = pack_indices(CUBE_VERTS.len(), &[0, 1, 2]);
+
+        assert_eq!(wgpu::IndexFormat::Uint16, index_format);
+        assert_eq!(3 * std::mem::size_of::<u16>(), contents.len());
+    }
+
+    #[test]
+    fn large_meshes_use_32_bit_indices() {
+        let vertex_count = u16::MAX as usize + 10;
+        let indices: Vec<u32> = (0..vertex_count as u32).collect();
+        let (index_format, contents) = pack_indices(vertex_count, &indices);
+
+        assert_eq!(wgpu::IndexFormat::Uint32, index_format);
+        assert_eq!(
+            vertex_count as u32 - 1,
+            bytemuck::cast_slice::<u8, u32>(&contents)[vertex_count - 1]
+        );
+    }
+}
@@ -1,12 +1,18 @@
 use std::{cell::Cell, ops::Range, rc::Rc};
 
-use glam::{Quat, Vec3};
+use glam::{Mat4, Quat, Vec3};
+use wgpu::util::DeviceExt;
 
-use crate::renderer::gpu_buffers::UniformBindGroup;
+use crate::{
+    math_utils::Aabb,
+    renderer::gpu_buffers::{DynamicGpuBuffer, UniformBindGroup},
+};
 
 use super::{
-    materials::Material,
-    shaders::{BindGroupLayouts, PerModelShaderVals, PerSubmeshShaderVals, VertexLayout},
+    materials::{BlendMode, CullMode, Material, MaterialPipelines, ShadingModel},
+    meshes::create_index_buffer,
+    recreated_resources::RecreatedResources,
+    shaders::{BindGroupLayouts, PerModelBinding, PerSubmeshShaderVals, VertexLayout},
     ModelShaderValsKey,
 };
 
@@ -23,55 +29,142 @@ pub struct Model {
     rotation: Quat,
     /// The scale of this model.
     scale: Vec3,
+    /// World transform of the scene entity that owns this model. The model's
+    /// translation, rotation and scale are relative to the entity. Kept up to
+    /// date by `Scene::update_transforms`.
+    entity_to_world: Cell<Mat4>,
     /// Shader uniform values associated with this model. The uniforms must be
     /// uploaded to the GPU after changes to position, rotation etc. This update
     /// must happen prior to drawing.
-    pub model_sv_key: ModelShaderValsKey,
+    ///
+    /// The renderer assigns shader values the first time the model is drawn.
+    model_sv_key: Cell<Option<ModelShaderValsKey>>,
     /// Specifies if the translation, rotation or scale of the model has changed
     /// since the last time those values were copied to the shader_vals instance
     /// backing this model.
     model_sv_dirty: Cell<bool>,
     /// Reference to the shared mesh that this model will draw.
     mesh: Rc<Mesh>,
+    /// Bounding volumes drawn around this model by the debug pass.
+    debug_bounds: DebugBounds,
+}
+
+/// Selects the bounding volumes drawn around a model, which helps when checking
+/// why a model was culled or picked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugBounds {
+    /// Draw the model's world space bounding box.
+    pub aabb: bool,
+    /// Draw a sphere containing the model's world space bounding box.
+    pub sphere: bool,
 }
 
 impl Model {
     /// Create a new model.
-    pub fn new(
-        model_shader_vals: ModelShaderValsKey,
-        mesh: Rc<Mesh>,
-        translation: Vec3,
-        rotation: Quat,
-        scale: Vec3,
-    ) -> Self {
+    pub fn new(mesh: Rc<Mesh>, translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
         let mut m = Self {
             translation: Default::default(),
             rotation: Default::default(),
             scale: Default::default(),
-            model_sv_key: model_shader_vals,
+            entity_to_world: Cell::new(Mat4::IDENTITY),
+            model_sv_key: Cell::new(None),
             model_sv_dirty: Cell::new(true), // Force an initial update.
             mesh,
+            debug_bounds: Default::default(),
         };
 
         m.set_scale_rotation_translation(scale, rotation, translation);
         m
     }
 
-    /// Model translation offset.
+    /// Set the world transform of the entity that owns this model, and mark
+    /// the model's shader values as out of date if it changed. This should only
+    /// be called by the scene for dirty entities, which may not have moved
+    /// (eg when only their light was changed).
+    pub(super) fn set_entity_to_world(&self, entity_to_world: Mat4) {
+        if self.entity_to_world.get() != entity_to_world {
+            self.entity_to_world.set(entity_to_world);
+            self.model_sv_dirty.replace(true);
+        }
+    }
+
+    /// Model translation offset, relative to the scene entity that owns the
+    /// model.
+    #[allow(dead_code)]
     pub fn translation(&self) -> Vec3 {
         self.translation
     }
 
     /// Model rotation.
+    #[allow(dead_code)]
     pub fn rotation(&self) -> Quat {
         self.rotation
     }
 
     /// Model scale.
+    #[allow(dead_code)]
     pub fn scale(&self) -> Vec3 {
         self.scale
     }
 
+    /// Get the key of the shader values associated with this model, or `None`
+    /// if the model has not been drawn yet.
+    pub fn model_sv_key(&self) -> Option<ModelShaderValsKey> {
+        self.model_sv_key.get()
+    }
+
+    /// Associate shader values with this model. The model's transform is
+    /// copied to them the next time it is drawn. This should only be called by
+    /// the renderer.
+    pub fn set_model_sv_key(&self, key: ModelShaderValsKey) {
+        self.model_sv_key.set(Some(key));
+        self.model_sv_dirty.replace(true);
+    }
+
+    /// Get the mesh drawn by this model.
+    pub fn mesh(&self) -> &Rc<Mesh> {
+        &self.mesh
+    }
+
+    /// Get the model's mesh for modification, or `None` if the mesh is shared
+    /// with other models.
+    pub fn mesh_mut(&mut self) -> Option<&mut Mesh> {
+        Rc::get_mut(&mut self.mesh)
+    }
+
+    /// Draw `mesh` in place of the model's current mesh, eg a copy of the
+    /// mesh that was recreated on a new GPU device.
+    pub fn set_mesh(&mut self, mesh: Rc<Mesh>) {
+        self.mesh = mesh;
+    }
+
+    /// Get the transform from this model's local space to the space of the
+    /// scene entity that owns it.
+    pub fn local_to_entity(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Get the transform from this model's local space to world space, which
+    /// includes the transforms of the entity that owns it and its ancestors.
+    pub fn local_to_world(&self) -> Mat4 {
+        self.entity_to_world.get() * self.local_to_entity()
+    }
+
+    /// Get a bounding box containing this model in world space.
+    pub fn world_bounds(&self) -> Aabb {
+        self.mesh.bounds().transformed(&self.local_to_world())
+    }
+
+    /// Get the bounding volumes drawn around this model for debugging.
+    pub fn debug_bounds(&self) -> DebugBounds {
+        self.debug_bounds
+    }
+
+    /// Set the bounding volumes drawn around this model for debugging.
+    pub fn set_debug_bounds(&mut self, debug_bounds: DebugBounds) {
+        self.debug_bounds = debug_bounds;
+    }
+
     /// Returns true if the values stored in this model (eg translation,
     /// rotation or scale) are out of date with respect to the values stored in
     /// the model's shader values uniform object.
@@ -124,7 +217,13 @@ impl Model {
 }
 
 /// Mesh definition that is shared among one or more instances of model.
+///
+/// The mesh keeps a copy of its vertices, indices and materials on the CPU so
+/// rays can be tested against its triangles, and so it can be recreated on a
+/// new GPU device after the old one was lost.
 pub struct Mesh {
+    /// Name used to label the mesh's GPU buffers.
+    name: String,
     /// A buffer storing this mesh's vertices.
     vertex_buffer: wgpu::Buffer,
     /// A buffer storing this mesh's indices.
@@ -133,18 +232,28 @@ pub struct Mesh {
     index_format: wgpu::IndexFormat,
     /// Submeshes that draw a portion of the total mesh.
     submeshes: Vec<Submesh>,
+    /// Bounding box containing all of the mesh's vertices in model space.
+    bounds: Aabb,
+    /// Copy of the vertices in `vertex_buffer`.
+    vertices: Vec<Vertex>,
+    /// Copy of the indices in `index_buffer`.
+    indices: Vec<u32>,
+    /// The mesh's triangles in model space for picking.
+    triangles: Vec<[Vec3; 3]>,
 }
 
 impl Mesh {
+    /// Create a mesh that draws `submeshes` out of `vertices` and `indices`,
+    /// which are copied into GPU buffers labelled with `name`.
     pub fn new(
-        vertex_buffer: wgpu::Buffer,
-        index_buffer: wgpu::Buffer,
-        index_count: u32,
-        index_format: wgpu::IndexFormat,
+        device: &wgpu::Device,
+        name: &str,
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
         submeshes: Vec<Submesh>,
     ) -> Self {
         assert!(
-            index_count
+            indices.len() as u32
                 >= submeshes
                     .iter()
                     .map(|m| m.indices.end)
@@ -153,17 +262,100 @@ impl Mesh {
             "at least one submesh has index offsets larger than the associated index buffer"
         );
 
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{name} vertex buffer")),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // Small meshes are stored with 16-bit indices.
+        let (index_buffer, index_format) = create_index_buffer(
+            device,
+            &format!("{name} index buffer"),
+            vertices.len(),
+            &indices,
+        );
+
+        let triangles = submeshes
+            .iter()
+            .flat_map(|submesh| {
+                indices[submesh.indices.start as usize..submesh.indices.end as usize]
+                    .chunks_exact(3)
+                    .map(|triangle| {
+                        [triangle[0], triangle[1], triangle[2]].map(|index| {
+                            let index = (index as i32 + submesh.base_vertex) as usize;
+                            Vec3::from(vertices[index].position)
+                        })
+                    })
+            })
+            .collect();
+
         Self {
+            name: name.to_string(),
             vertex_buffer,
             index_buffer,
             index_format,
             submeshes,
+            bounds: Self::compute_bounds(&vertices),
+            vertices,
+            indices,
+            triangles,
         }
     }
 
+    /// Create a copy of this mesh with the GPU device of `resources` from the
+    /// copies of its vertices, indices and materials kept on the CPU.
+    pub fn recreate(&self, resources: &mut RecreatedResources) -> Self {
+        let submeshes = self
+            .submeshes
+            .iter()
+            .map(|submesh| submesh.recreate(resources))
+            .collect();
+
+        Self::new(
+            resources.context().device(),
+            &self.name,
+            self.vertices.clone(),
+            self.indices.clone(),
+            submeshes,
+        )
+    }
+
+    /// Get the mesh's triangles in model space.
+    pub fn triangles(&self) -> &[[Vec3; 3]] {
+        &self.triangles
+    }
+
+    /// Calculate a bounding box that contains all of the positions in
+    /// `vertices`.
+    pub fn compute_bounds(vertices: &[Vertex]) -> Aabb {
+        Aabb::from_points(vertices.iter().map(|v| Vec3::from(v.position)))
+    }
+
     pub fn index_format(&self) -> wgpu::IndexFormat {
         self.index_format
     }
+
+    /// Get a bounding box containing the mesh in model space.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// Get the number of vertices in the mesh's vertex buffer.
+    pub fn vertex_count(&self) -> u32 {
+        (self.vertex_buffer.size() / std::mem::size_of::<Vertex>() as u64) as u32
+    }
+
+    /// Get the submeshes that make up this mesh.
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    /// Get a mutable reference to the submesh at `index`, or `None` if there is
+    /// no submesh at that index.
+    pub fn submesh_mut(&mut self, index: usize) -> Option<&mut Submesh> {
+        self.submeshes.get_mut(index)
+    }
 }
 
 /// A subpart of a larger mesh which has its own shader uniforms.
@@ -174,6 +366,14 @@ pub struct Submesh {
     indices: Range<u32>,
     /// Base vertex used when rendering this submesh.
     base_vertex: i32,
+    /// Cull mode of the submesh's material.
+    cull_mode: CullMode,
+    /// Shading model of the submesh's material.
+    shading: ShadingModel,
+    /// Blend mode of the submesh's material.
+    blend_mode: BlendMode,
+    /// The material the submesh is drawn with.
+    material: Material,
 }
 
 impl Submesh {
@@ -189,39 +389,171 @@ impl Submesh {
             submesh_shader_vals: uniforms,
             indices,
             base_vertex,
+            cull_mode: material.cull_mode,
+            shading: material.shading,
+            blend_mode: material.blend_mode,
+            material: material.clone(),
         }
     }
+
+    /// Create a copy of this submesh with the GPU device of `resources`.
+    fn recreate(&self, resources: &mut RecreatedResources) -> Self {
+        let material = resources.material(&self.material);
+        let context = resources.context();
+
+        Self::new(
+            context.device(),
+            context.bind_group_layouts(),
+            self.indices.clone(),
+            self.base_vertex,
+            &material,
+        )
+    }
+
+    /// Update this submesh's material constants, cull mode, shading model and
+    /// blend mode to match `material`. The new values are copied to the GPU
+    /// the next time the submesh is rendered.
+    pub fn set_material_constants(&mut self, material: &Material) {
+        self.submesh_shader_vals.set_material_constants(material);
+        self.cull_mode = material.cull_mode;
+        self.shading = material.shading;
+        self.blend_mode = material.blend_mode;
+
+        // The submesh keeps drawing with the textures it was created with.
+        self.material = Material {
+            diffuse_map: self.material.diffuse_map.clone(),
+            specular_map: self.material.specular_map.clone(),
+            emissive_map: self.material.emissive_map.clone(),
+            metallic_map: self.material.metallic_map.clone(),
+            roughness_map: self.material.roughness_map.clone(),
+            ao_map: self.material.ao_map.clone(),
+            ..material.clone()
+        };
+    }
+
+    /// Returns true if the submesh's material lets what is behind it show
+    /// through, which means it must be drawn after opaque submeshes.
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode.is_transparent()
+    }
+
+    /// Returns true if this submesh's shader values need to be copied to the
+    /// GPU.
+    pub fn is_dirty(&self) -> bool {
+        self.submesh_shader_vals.is_dirty()
+    }
+
+    /// Copy this submesh's shader values to the GPU.
+    pub fn update_gpu(&self, queue: &wgpu::Queue) {
+        self.submesh_shader_vals.update_gpu(queue);
+    }
+
+    /// Get the range of the mesh's index buffer drawn by this submesh.
+    pub fn indices(&self) -> std::ops::Range<u32> {
+        self.indices.clone()
+    }
 }
 
 /// A trait for types that are capable of rendering models and meshes.
 pub trait DrawModel<'a> {
-    fn draw_model(&mut self, model: &'a Model, model_sv: &'a PerModelShaderVals);
-    fn draw_mesh(&mut self, mesh: &'a Mesh);
+    /// Draw the mesh's opaque submeshes once for each instance in `instances`,
+    /// with one draw call per submesh. The `ModelInstance` buffer must already
+    /// be bound to vertex buffer slot 1.
+    ///
+    /// Transparent submeshes are skipped and should be drawn with
+    /// `draw_transparent_submesh` after every opaque submesh in the scene.
+    fn draw_mesh_instances(
+        &mut self,
+        mesh: &'a Mesh,
+        instances: Range<u32>,
+        pipelines: &'a MaterialPipelines,
+    );
+    /// Draw one of the mesh's transparent submeshes with the model transform
+    /// stored at `instance` in the bound `ModelInstance` buffer.
+    fn draw_transparent_submesh(
+        &mut self,
+        mesh: &'a Mesh,
+        submesh: &'a Submesh,
+        instance: u32,
+        pipelines: &'a MaterialPipelines,
+    );
+    /// Draw the model's mesh without binding any per-submesh values.
+    fn draw_model_geometry(&mut self, model: &'a Model, model_sv: PerModelBinding<'a>);
+    /// Draw two vertices for each vertex in the model's mesh, with the mesh's
+    /// vertex buffer bound to slot 0 and stepped once per instance.
+    fn draw_vertex_normals(&mut self, model: &'a Model, model_sv: PerModelBinding<'a>);
 }
 
 impl<'rpass, 'a> DrawModel<'a> for wgpu::RenderPass<'rpass>
 where
     'a: 'rpass,
 {
-    fn draw_model(&mut self, model: &'a Model, model_sv: &'a PerModelShaderVals) {
-        // Bind the per-model uniforms for this model before drawing the mesh.
-        debug_assert!(!model.is_model_sv_dirty());
+    fn draw_mesh_instances(
+        &mut self,
+        mesh: &'a Mesh,
+        instances: Range<u32>,
+        pipelines: &'a MaterialPipelines,
+    ) {
+        // Bind the mesh's vertex and index buffers.
+        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format());
 
-        self.set_bind_group(1, model_sv.bind_group(), &[]);
-        self.draw_mesh(&model.mesh);
+        // Draw each opaque sub-mesh in the mesh.
+        // Each submesh's material selects the pipeline it is drawn with.
+        for submesh in mesh.submeshes.iter().filter(|s| !s.is_transparent()) {
+            self.set_pipeline(pipelines.get(
+                submesh.shading,
+                submesh.cull_mode,
+                submesh.blend_mode,
+            ));
+            self.set_bind_group(1, submesh.submesh_shader_vals.bind_group(), &[]);
+            self.draw_indexed(
+                submesh.indices.clone(),
+                submesh.base_vertex,
+                instances.clone(),
+            );
+        }
     }
 
-    fn draw_mesh(&mut self, mesh: &'a Mesh) {
-        // Bind the mesh's vertex and index buffers.
+    fn draw_transparent_submesh(
+        &mut self,
+        mesh: &'a Mesh,
+        submesh: &'a Submesh,
+        instance: u32,
+        pipelines: &'a MaterialPipelines,
+    ) {
+        debug_assert!(submesh.is_transparent());
+
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
         self.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format());
+        self.set_pipeline(pipelines.get(submesh.shading, submesh.cull_mode, submesh.blend_mode));
+        self.set_bind_group(1, submesh.submesh_shader_vals.bind_group(), &[]);
+        self.draw_indexed(
+            submesh.indices.clone(),
+            submesh.base_vertex,
+            instance..instance + 1,
+        );
+    }
+
+    fn draw_model_geometry(&mut self, model: &'a Model, model_sv: PerModelBinding<'a>) {
+        debug_assert!(!model.is_model_sv_dirty());
+
+        self.set_bind_group(1, model_sv.bind_group, &[model_sv.offset]);
+        self.set_vertex_buffer(0, model.mesh.vertex_buffer.slice(..));
+        self.set_index_buffer(model.mesh.index_buffer.slice(..), model.mesh.index_format());
 
-        // Draw each sub-mesh in the mesh.
-        for submesh in &mesh.submeshes {
-            self.set_bind_group(2, submesh.submesh_shader_vals.bind_group(), &[]);
+        for submesh in &model.mesh.submeshes {
             self.draw_indexed(submesh.indices.clone(), submesh.base_vertex, 0..1);
         }
     }
+
+    fn draw_vertex_normals(&mut self, model: &'a Model, model_sv: PerModelBinding<'a>) {
+        debug_assert!(!model.is_model_sv_dirty());
+
+        self.set_bind_group(1, model_sv.bind_group, &[model_sv.offset]);
+        self.set_vertex_buffer(0, model.mesh.vertex_buffer.slice(..));
+        self.draw(0..2, 0..model.mesh.vertex_count());
+    }
 }
 
 /// Vertex format used by model meshes.
@@ -233,6 +565,51 @@ pub struct Vertex {
     pub tex_coords: [f32; 2],
 }
 
+/// Per-instance values read by the lit shaders, holding the transforms of one
+/// model. Models that share a mesh are drawn together by storing their
+/// instances next to each other in an instance buffer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelInstance {
+    pub local_to_world: Mat4,
+    pub world_to_local: Mat4,
+}
+
+impl ModelInstance {
+    /// Create an instance holding `model`'s current transforms.
+    pub fn new(model: &Model) -> Self {
+        let local_to_world = model.local_to_world();
+
+        Self {
+            local_to_world,
+            world_to_local: local_to_world.inverse(),
+        }
+    }
+}
+
+impl VertexLayout for ModelInstance {
+    /// Get a description of the instance layout for wgpu. Each matrix is
+    /// passed as four column vectors following the `Vertex` attributes.
+    fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+            3 => Float32x4,
+            4 => Float32x4,
+            5 => Float32x4,
+            6 => Float32x4,
+            7 => Float32x4,
+            8 => Float32x4,
+            9 => Float32x4,
+            10 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
 impl VertexLayout for Vertex {
     /// Get a description of the vertex layout for wgpu.
     fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
@@ -260,3 +637,57 @@ impl VertexLayout for Vertex {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::DefaultTextures,
+        renderer::{
+            materials::MaterialBuilder,
+            meshes::{CUBE_INDICES, CUBE_VERTS},
+        },
+        test_support::test_renderer,
+    };
+
+    #[test]
+    fn changing_material_only_dirties_that_submesh() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let device = renderer.gpu().device();
+        let layouts = renderer.bind_group_layouts();
+        let default_textures = DefaultTextures::new(device, renderer.gpu().queue());
+        let material = MaterialBuilder::new().build(&default_textures);
+
+        let indices: Vec<u32> = CUBE_INDICES.iter().map(|i| *i as u32).collect();
+        let half = indices.len() as u32 / 2;
+        let index_count = indices.len() as u32;
+
+        let mut mesh = Mesh::new(
+            device,
+            "test",
+            CUBE_VERTS.to_vec(),
+            indices,
+            vec![
+                Submesh::new(device, layouts, 0..half, 0, &material),
+                Submesh::new(device, layouts, half..index_count, 0, &material),
+            ],
+        );
+
+        assert!(mesh.submeshes().iter().all(|s| !s.is_dirty()));
+
+        let mut shiny_material = material.clone();
+        shiny_material.specular_power = 32.0;
+        mesh.submesh_mut(1)
+            .unwrap()
+            .set_material_constants(&shiny_material);
+
+        assert!(!mesh.submeshes()[0].is_dirty());
+        assert!(mesh.submeshes()[1].is_dirty());
+
+        mesh.submeshes()[1].update_gpu(renderer.gpu().queue());
+        assert!(!mesh.submeshes()[1].is_dirty());
+    }
+}
@@ -1,5 +1,27 @@
+mod billboard_pass;
 mod depth_pass;
+mod light_cluster_pass;
 mod light_debug_pass;
+mod normals_pass;
+mod outline_pass;
+mod point_shadow_pass;
+pub mod post_process;
+mod skybox_pass;
+mod sprite_pass;
+mod ssao_pass;
+mod text_pass;
+mod ui_pass;
 
-pub use depth_pass::DepthPass;
+pub use billboard_pass::BillboardPass;
+pub use depth_pass::{DepthPass, DepthQuadPlacement};
+pub use light_cluster_pass::LightClusterPass;
 pub use light_debug_pass::LightDebugPass;
+pub use normals_pass::NormalsPass;
+pub use outline_pass::OutlinePass;
+pub use point_shadow_pass::PointShadowPass;
+pub use post_process::PostProcessStack;
+pub use skybox_pass::SkyboxPass;
+pub use sprite_pass::SpritePass;
+pub use ssao_pass::{SsaoPass, SsaoSettings, SsaoTargets};
+pub use text_pass::TextPass;
+pub use ui_pass::UiPass;
@@ -0,0 +1,290 @@
+use std::{ops::Range, rc::Rc};
+
+use crate::{
+    camera::Camera,
+    renderer::{
+        billboards::Billboard,
+        gpu_buffers::{DynamicGpuBuffer, InstanceBuffer, UniformBindGroup},
+        shaders::{BindGroupLayouts, PerFrameShaderVals},
+        textures,
+        viewport::Viewport,
+    },
+};
+
+/// Draws the scene's billboards as camera facing quads.
+///
+/// Billboards are sorted from back to front so they blend correctly with each
+/// other, and are then drawn in batches of consecutive billboards that share a
+/// texture.
+pub struct BillboardPass {
+    render_pipeline: wgpu::RenderPipeline,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Bind groups for textures drawn in recent frames. Each entry holds on to
+    /// its texture, and is dropped once nothing else holds the texture.
+    textures: Vec<(Rc<textures::Texture>, wgpu::BindGroup)>,
+    instances: InstanceBuffer<BillboardPackedInstance>,
+    /// Ranges of instances to draw this frame and the index of the texture
+    /// each range is drawn with.
+    batches: Vec<(Range<u32>, usize)>,
+}
+
+impl BillboardPass {
+    const SHADER: &'static str = include_str!("billboard_pass.wgsl");
+    /// Maximum number of billboards drawn in one frame. The billboards furthest
+    /// from the camera are dropped when there are more.
+    const MAX_BILLBOARDS: usize = 1024;
+    /// Number of vertices drawn for each billboard quad.
+    const VERTICES_PER_QUAD: u32 = 6;
+
+    /// Create a new billboard pass. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("billboard texture layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("billboard pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("billboard pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("billboard pass pipeline layout"),
+                    bind_group_layouts: &[&layouts.per_frame_layout, &texture_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[BillboardPackedInstance::vertex_layout()],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // Billboards are hidden behind models but do not hide each other,
+            // since they are blended together from back to front.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: super::DepthPass::DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: super::PostProcessStack::HDR_TEXTURE_FORMAT,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            render_pipeline,
+            texture_layout,
+            sampler: textures::create_default_sampler(device),
+            textures: Vec::new(),
+            instances: InstanceBuffer::new(
+                device,
+                Some("billboard instance buffer"),
+                vec![Default::default(); Self::MAX_BILLBOARDS],
+            ),
+            batches: Vec::new(),
+        }
+    }
+
+    /// Prepare `billboards` for drawing by sorting them from back to front as
+    /// seen by `camera`, and copying them to the GPU.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        billboards: &[Billboard],
+        camera: &Camera,
+    ) {
+        self.batches.clear();
+        self.textures
+            .retain(|(texture, _)| Rc::strong_count(texture) > 1);
+
+        let distance_to_camera = |b: &Billboard| b.position.distance_squared(camera.eye());
+        let mut sorted = billboards.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| distance_to_camera(b).total_cmp(&distance_to_camera(a)));
+
+        let skipped = sorted.len().saturating_sub(Self::MAX_BILLBOARDS);
+
+        for (index, billboard) in sorted.into_iter().skip(skipped).enumerate() {
+            *self.instances.values_mut(index) = BillboardPackedInstance {
+                position: billboard.position.to_array(),
+                size: billboard.size.to_array(),
+                color: billboard.color.to_array(),
+            };
+
+            let texture_index = self.texture_index(device, &billboard.texture);
+            let index = index as u32;
+
+            // Extend the previous batch when the texture does not change.
+            match self.batches.last_mut() {
+                Some((instances, batch_texture)) if *batch_texture == texture_index => {
+                    instances.end = index + 1;
+                }
+                _ => self.batches.push((index..index + 1, texture_index)),
+            }
+        }
+
+        if self.instances.is_dirty() {
+            self.instances.update_gpu(queue);
+        }
+    }
+
+    /// Returns true if there are billboards to draw this frame.
+    pub fn has_billboards(&self) -> bool {
+        !self.batches.is_empty()
+    }
+
+    /// Draw the billboards prepared for this frame into `viewport`.
+    pub fn draw(
+        &self,
+        output_view: &wgpu::TextureView,
+        depth_stencil_view: &wgpu::TextureView,
+        per_frame_uniforms: &PerFrameShaderVals,
+        viewport: &Viewport,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("billboard render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_stencil_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        viewport.apply(&mut render_pass);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+        render_pass.set_vertex_buffer(0, self.instances.gpu_buffer_slice(..));
+
+        for (instances, texture_index) in &self.batches {
+            render_pass.set_bind_group(1, &self.textures[*texture_index].1, &[]);
+            render_pass.draw(0..Self::VERTICES_PER_QUAD, instances.clone());
+        }
+    }
+
+    /// Get the index of the bind group for `texture`, creating it if the
+    /// texture has not been drawn recently.
+    fn texture_index(&mut self, device: &wgpu::Device, texture: &Rc<textures::Texture>) -> usize {
+        if let Some(index) = self
+            .textures
+            .iter()
+            .position(|(t, _)| Rc::ptr_eq(t, texture))
+        {
+            return index;
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("billboard texture bind group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        self.textures.push((texture.clone(), bind_group));
+        self.textures.len() - 1
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct BillboardPackedInstance {
+    pub position: [f32; 3],
+    pub size: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl BillboardPackedInstance {
+    fn vertex_layout() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<BillboardPackedInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                // position: vec3
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // size: vec2
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // color: vec4
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
@@ -0,0 +1,65 @@
+/// Only the camera values at the start of the per-frame uniforms are needed.
+struct PerFrameUniforms {
+    view_projection: mat4x4<f32>,
+    view_pos: vec4<f32>,
+    view_right: vec4<f32>,
+    view_up: vec4<f32>,
+};
+
+struct InstanceInput {
+    @location(0) position: vec3<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> per_frame: PerFrameUniforms;
+
+@group(1) @binding(0)
+var billboard_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var billboard_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    // The two triangles making up the quad, with corners ranging from -0.5 to
+    // 0.5 along the camera's right and up axes.
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+    );
+    let corner = corners[vertex_index];
+
+    let offset_ws = per_frame.view_right.xyz * (corner.x * instance.size.x)
+        + per_frame.view_up.xyz * (corner.y * instance.size.y);
+
+    var v: VertexOutput;
+    v.position_cs = per_frame.view_projection * vec4<f32>(instance.position + offset_ws, 1.0);
+    v.tex_coords = vec2<f32>(corner.x + 0.5, 0.5 - corner.y);
+    v.color = instance.color;
+
+    return v;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(billboard_texture, billboard_sampler, in.tex_coords) * in.color;
+
+    // Fully transparent texels are skipped so they do not hide billboards
+    // drawn behind them.
+    if (color.a <= 0.0) {
+        discard;
+    }
+
+    return color;
+}
@@ -1,21 +1,69 @@
+use glam::Vec2;
 use wgpu::util::DeviceExt;
 
-use crate::renderer::debug::{DebugVertex, QUAD_INDICES, QUAD_VERTS};
+use crate::{
+    camera::Camera,
+    renderer::debug::{DebugVertex, QUAD_INDICES, QUAD_VERTS},
+};
 
-// TODO: Pass projection zNear/zFar values to depth shader.
-// TODO: Pass quad location (eg full screen, or NE,NW,SW,SE corner)
+/// Controls where on the screen the depth buffer visualization is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthQuadPlacement {
+    /// Cover the entire screen.
+    FullScreen,
+    /// Quarter size overlay in the top left corner of the screen.
+    TopLeft,
+    /// Quarter size overlay in the top right corner of the screen.
+    TopRight,
+    /// Quarter size overlay in the bottom left corner of the screen.
+    BottomLeft,
+    /// Quarter size overlay in the bottom right corner of the screen.
+    #[default]
+    BottomRight,
+}
+
+impl DepthQuadPlacement {
+    /// Get the next placement, wrapping back around to the first placement
+    /// after the last one.
+    pub fn next(self) -> Self {
+        match self {
+            Self::FullScreen => Self::TopLeft,
+            Self::TopLeft => Self::TopRight,
+            Self::TopRight => Self::BottomRight,
+            Self::BottomRight => Self::BottomLeft,
+            Self::BottomLeft => Self::FullScreen,
+        }
+    }
+
+    /// Get the scale and offset (in clip space) that is applied to the full
+    /// screen quad vertices to move them into this placement.
+    fn scale_and_offset(self) -> (Vec2, Vec2) {
+        match self {
+            Self::FullScreen => (Vec2::ONE, Vec2::ZERO),
+            Self::TopLeft => (Vec2::splat(0.5), Vec2::new(-0.5, 0.5)),
+            Self::TopRight => (Vec2::splat(0.5), Vec2::new(0.5, 0.5)),
+            Self::BottomLeft => (Vec2::splat(0.5), Vec2::new(-0.5, -0.5)),
+            Self::BottomRight => (Vec2::splat(0.5), Vec2::new(0.5, -0.5)),
+        }
+    }
+}
 
 /// Provides both the texture for the depth pass as well as an optional
-/// render pipeline for visualizing the pass as a full screen quad.
+/// render pipeline for visualizing the pass as a screen aligned quad.
 pub struct DepthPass {
     /// The depth buffer written to by the GPU.
     depth_texture: wgpu::Texture,
-    /// A view into the depth texture. Required by the renderer for writing into
-    /// the depth buffer, and by the debug visualizer for displaying the depth
-    /// buffer.
+    /// A view into the depth and stencil texture. Required by the renderer for
+    /// writing into the depth and stencil buffer.
     depth_texture_view: wgpu::TextureView,
+    /// A view of only the depth aspect of the depth texture. Required by the
+    /// debug visualizer for displaying the depth buffer.
+    depth_sample_view: wgpu::TextureView,
     /// Sampler required for reading from the depth buffer for visualization.
     depth_sampler: wgpu::Sampler,
+    /// Uniforms (camera near and far plane, quad placement) required to draw
+    /// the linearized depth buffer for visualization.
+    uniforms_buffer: wgpu::Buffer,
     /// Bind group layout required by depth buffer visualization shader.
     bind_group_layout: wgpu::BindGroupLayout,
     /// Bind group (texture view, sampler and uniforms) required by depth buffer
@@ -30,17 +78,42 @@ pub struct DepthPass {
 }
 
 impl DepthPass {
-    pub const DEPTH_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    pub const DEPTH_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
     const SHADER: &'static str = include_str!("depth_pass.wgsl");
 
     /// Create a new depth pass. Only one instance is needed per renderer.
     pub fn new(device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) -> Self {
-        let (depth_texture, depth_texture_view, depth_sampler) =
+        let (depth_texture, depth_texture_view, depth_sample_view) =
             Self::create_depth_texture(device, surface_config);
 
+        // The sampler for the depth texture can optionally be used for
+        // visualizing the depth buffer. It does not depend on the size of the
+        // depth texture so it is created once and kept across resizes.
+        let depth_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: None,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        // Uniforms for the camera's near and far planes, which are needed to
+        // convert depth values back into linear distances, and the placement of
+        // the visualization quad on screen.
+        let uniforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("depth pass uniforms"),
+            contents: bytemuck::bytes_of(&DepthPassUniforms::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
         // This bind group is used to render the depth buffer to the screen for
-        // visualization. It only requirs the texture view and sampler, no other
-        // uniforms are needed (e.g., view transform).
+        // visualization. It requires the texture view and sampler, as well as
+        // the camera's near and far plane distances.
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("depth pass layout"),
             entries: &[
@@ -62,26 +135,28 @@ impl DepthPass {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
                     visibility: wgpu::ShaderStages::FRAGMENT,
                 },
-            ],
-        });
-
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("depth pass bind group"),
-            layout: &bind_group_layout,
-            entries: &[
-                // Slot 0: depth buffer texture view.
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&depth_texture_view),
-                },
-                // Slot 1: depth buffer texture sampler.
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&depth_sampler),
+                // Slot 2: depth pass uniforms.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    count: None,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                 },
             ],
         });
 
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &depth_sample_view,
+            &depth_sampler,
+            &uniforms_buffer,
+        );
+
         // Create a unique vertex and index buffer for a full screen quad that
         // will render the depth pass (if visualization is requested).
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -151,7 +226,9 @@ impl DepthPass {
         Self {
             depth_texture,
             depth_texture_view,
+            depth_sample_view,
             depth_sampler,
+            uniforms_buffer,
             bind_group_layout,
             bind_group,
             vertex_buffer,
@@ -166,43 +243,69 @@ impl DepthPass {
         &self.depth_texture_view
     }
 
+    /// Get a view of only the depth aspect of the depth buffer, which is
+    /// required for reading depth values in a shader.
+    pub fn depth_sample_view(&self) -> &wgpu::TextureView {
+        &self.depth_sample_view
+    }
+
     /// Resize the depth buffer to match the new window size. This must be called
     /// when the window is resized and only after `surface_config` is resized.
+    ///
+    /// The depth texture must be exactly the same size as the color attachment
+    /// it is paired with, so it is recreated whenever the size changes. The
+    /// sampler, uniforms and pipeline do not depend on the size and are kept.
     pub fn resize(&mut self, device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) {
-        // Recreate the depth buffer texture, view and sampler when resized.
-        // TODO: Is there any way to re-use these existing resources?
-        let (depth_texture, depth_texture_view, depth_sampler) =
+        let size = self.depth_texture.size();
+
+        if size.width == surface_config.width.max(1) && size.height == surface_config.height.max(1)
+        {
+            return;
+        }
+
+        let (depth_texture, depth_texture_view, depth_sample_view) =
             Self::create_depth_texture(device, surface_config);
 
         self.depth_texture = depth_texture;
         self.depth_texture_view = depth_texture_view;
-        self.depth_sampler = depth_sampler;
+        self.depth_sample_view = depth_sample_view;
 
-        self.bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("depth pass bind group"),
-            layout: &self.bind_group_layout,
-            entries: &[
-                // Slot 0: depth buffer texture view.
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&self.depth_texture_view),
-                },
-                // Slot 1: depth buffer texture sampler.
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&self.depth_sampler),
-                },
-            ],
-        });
+        // The bind group references the old sample view and must be rebuilt.
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.depth_sample_view,
+            &self.depth_sampler,
+            &self.uniforms_buffer,
+        );
     }
 
     /// Draw the contents of the depth buffer to the screen for visualization
-    /// purposes.
+    /// purposes. `camera` must be the camera used when the depth buffer was
+    /// written to, otherwise depth values will not be linearized correctly.
     pub fn draw(
         &self,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        placement: DepthQuadPlacement,
         output_view: &wgpu::TextureView,
         command_encoder: &mut wgpu::CommandEncoder,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
     ) {
+        let (quad_scale, quad_offset) = placement.scale_and_offset();
+
+        queue.write_buffer(
+            &self.uniforms_buffer,
+            0,
+            bytemuck::bytes_of(&DepthPassUniforms {
+                z_near: camera.z_near(),
+                z_far: camera.z_far(),
+                quad_scale,
+                quad_offset,
+                _padding: Default::default(),
+            }),
+        );
+
         let mut depth_render_pass =
             command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("depth buffer visualization render pass"),
@@ -215,7 +318,7 @@ impl DepthPass {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes,
                 occlusion_query_set: None,
             });
 
@@ -227,11 +330,11 @@ impl DepthPass {
     }
 
     /// Helper method that creates the depth texture as well as its associated
-    /// view and sampler objects.
+    /// views.
     fn create_depth_texture(
         device: &wgpu::Device,
         surface_config: &wgpu::SurfaceConfiguration,
-    ) -> (wgpu::Texture, wgpu::TextureView, wgpu::Sampler) {
+    ) -> (wgpu::Texture, wgpu::TextureView, wgpu::TextureView) {
         // Create the GPU backing texture for the depth buffer. Including
         // `TextureUsages::RENDER_ATTACHMENT` in the usage flags ensures depth
         // information can be written to this texture.
@@ -252,21 +355,56 @@ impl DepthPass {
 
         let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        // The sampler for this depth texture can optionally be used for
-        // visualizing the depth buffer.
-        let depth_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            compare: None,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 100.0,
+        // Shaders can only sample the depth aspect of a depth stencil texture.
+        let depth_sample_view = depth_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("depth buffer sample view"),
+            aspect: wgpu::TextureAspect::DepthOnly,
             ..Default::default()
         });
 
-        (depth_texture, depth_texture_view, depth_sampler)
+        (depth_texture, depth_texture_view, depth_sample_view)
+    }
+
+    /// Helper method that creates the bind group used by the depth buffer
+    /// visualization shader.
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        depth_sample_view: &wgpu::TextureView,
+        depth_sampler: &wgpu::Sampler,
+        uniforms_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("depth pass bind group"),
+            layout,
+            entries: &[
+                // Slot 0: depth buffer texture view.
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_sample_view),
+                },
+                // Slot 1: depth buffer texture sampler.
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(depth_sampler),
+                },
+                // Slot 2: depth pass uniforms.
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniforms_buffer.as_entire_binding(),
+                },
+            ],
+        })
     }
 }
+
+/// Uniform values used by the depth visualization shader.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct DepthPassUniforms {
+    pub z_near: f32,
+    pub z_far: f32,
+    pub quad_scale: Vec2,
+    pub quad_offset: Vec2,
+    pub _padding: [f32; 2],
+}
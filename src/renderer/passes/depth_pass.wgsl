@@ -9,26 +9,47 @@ struct VertexOutput {
     @location(0) tex_coords: vec2<f32>,
 }
 
+struct DepthPassUniforms {
+    /// Camera near plane distance.
+    z_near: f32,
+    /// Camera far plane distance.
+    z_far: f32,
+    /// Scale applied to the full screen quad vertices.
+    quad_scale: vec2<f32>,
+    /// Clip space offset applied to the full screen quad after scaling.
+    quad_offset: vec2<f32>,
+}
+
 @group(0) @binding(0)
 var depth_texture: texture_2d<f32>;
 @group(0) @binding(1)
 var depth_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> uniforms: DepthPassUniforms;
 
 @vertex
 fn vs_main(model: VertexInput,) -> VertexOutput {
     var out: VertexOutput;
 
     out.tex_coords = model.tex_coords;
-    out.position_cs = vec4<f32>(model.position, 1.0);
+    out.position_cs = vec4<f32>(
+        model.position.xy * uniforms.quad_scale + uniforms.quad_offset,
+        model.position.z,
+        1.0
+    );
     
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let near = 0.1; // TODO: Replace with zNear of perspective projection.
-    let far = 100.0; // TODO: Replace with zFar of perspective projection.
+    let near = uniforms.z_near;
+    let far = uniforms.z_far;
     let depth = textureSample(depth_texture, depth_sampler, in.tex_coords).x;
-    let r = (2.0 * near) / (far + near - depth * (far - near));
+
+    // Convert the non-linear [0, 1] depth value back into a view space distance
+    // and then rescale it so the near plane is black and the far plane is white.
+    let view_distance = (near * far) / (far - depth * (far - near));
+    let r = (view_distance - near) / (far - near);
     return vec4<f32>(r, r, r, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,313 @@
+use glam::Mat4;
+
+use crate::{
+    camera::Camera,
+    renderer::{
+        gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, StorageBuffer, UniformBindGroup},
+        shaders::PackedPointLight,
+    },
+};
+
+/// Bins point lights into clusters so the lit shaders only shade each fragment
+/// with the lights that can reach it, rather than every light in the scene.
+///
+/// Each viewport is split into a grid of screen space tiles, and each tile is
+/// split again into depth slices that grow exponentially with distance from
+/// the camera. A compute shader tests every light's range against the bounds of
+/// each cluster and writes the indices of the lights that touch it. The lit
+/// shaders find the cluster a fragment is in and loop over its lights.
+///
+/// Light clusters need compute shaders and storage buffers, which WebGL2 does
+/// not support. Use `LightClusterPass::is_supported` to check the device first.
+pub struct LightClusterPass {
+    compute_pipeline: wgpu::ComputePipeline,
+    view_layout: wgpu::BindGroupLayout,
+    clusters_layout: wgpu::BindGroupLayout,
+    /// Uniforms for each viewport drawn in a frame.
+    view_uniforms: Vec<GenericUniformBuffer<LightClusterPackedUniforms>>,
+    /// Number of viewports prepared this frame.
+    view_count: usize,
+    point_lights: StorageBuffer<PackedPointLight>,
+    cluster_light_counts_buffer: wgpu::Buffer,
+    cluster_light_indices_buffer: wgpu::Buffer,
+    clusters_bind_group: wgpu::BindGroup,
+}
+
+impl LightClusterPass {
+    /// Number of screen space tiles across each viewport.
+    pub const CLUSTERS_X: u32 = 16;
+    /// Number of screen space tiles down each viewport.
+    pub const CLUSTERS_Y: u32 = 8;
+    /// Number of depth slices between the camera's near and far planes.
+    pub const CLUSTERS_Z: u32 = 24;
+    pub const CLUSTERS_PER_VIEW: u32 = Self::CLUSTERS_X * Self::CLUSTERS_Y * Self::CLUSTERS_Z;
+    /// The most lights that can shine on a single cluster. Lights past this
+    /// are ignored by the fragments in that cluster.
+    pub const MAX_LIGHTS_PER_CLUSTER: u32 = 64;
+    /// Closest distance to the camera that depth slices start at, which keeps
+    /// the slices usable when the camera's near plane is at zero.
+    const MIN_Z_NEAR: f32 = 0.01;
+    const SHADER: &'static str = include_str!("light_cluster_pass.wgsl");
+
+    /// Returns true if `device` supports the compute shaders and storage
+    /// buffers used to build light clusters. The lit shaders read lights and
+    /// clusters from five storage buffers.
+    pub fn is_supported(device: &wgpu::Device) -> bool {
+        let limits = device.limits();
+
+        limits.max_storage_buffers_per_shader_stage >= 5
+            && limits.max_compute_invocations_per_workgroup >= Self::CLUSTERS_X * Self::CLUSTERS_Y
+            && limits.max_compute_workgroup_size_x >= Self::CLUSTERS_X
+            && limits.max_compute_workgroup_size_y >= Self::CLUSTERS_Y
+    }
+
+    /// Create a new light cluster pass. Only one instance is needed per
+    /// renderer.
+    pub fn new(device: &wgpu::Device) -> Self {
+        let view_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("light cluster view layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let clusters_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("light clusters layout"),
+            entries: &[
+                storage_entry(0, true),
+                storage_entry(1, false),
+                storage_entry(2, false),
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("light cluster shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("light cluster compute pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("light cluster pipeline layout"),
+                    bind_group_layouts: &[&view_layout, &clusters_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let point_lights = StorageBuffer::new(device, "light cluster point lights", 1);
+        let (cluster_light_counts_buffer, cluster_light_indices_buffer) =
+            Self::create_cluster_buffers(device, 1);
+        let clusters_bind_group = Self::create_clusters_bind_group(
+            device,
+            &clusters_layout,
+            point_lights.gpu_buffer(),
+            &cluster_light_counts_buffer,
+            &cluster_light_indices_buffer,
+        );
+
+        Self {
+            compute_pipeline,
+            view_layout,
+            clusters_layout,
+            view_uniforms: Vec::new(),
+            view_count: 0,
+            point_lights,
+            cluster_light_counts_buffer,
+            cluster_light_indices_buffer,
+            clusters_bind_group,
+        }
+    }
+
+    /// Copy `point_lights` and each viewport's camera to the GPU, growing the
+    /// light and cluster buffers if they are too small. Returns the clip
+    /// planes the clusters were sliced between for each camera, which the lit
+    /// shaders need to find a fragment's cluster.
+    pub fn prepare<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        point_lights: &[PackedPointLight],
+        cameras: impl Iterator<Item = &'a Camera>,
+    ) -> Vec<(f32, f32)> {
+        let mut clip_planes = Vec::new();
+
+        for (view_index, camera) in cameras.enumerate() {
+            if view_index == self.view_uniforms.len() {
+                self.view_uniforms.push(GenericUniformBuffer::new(
+                    device,
+                    Some("light cluster view uniforms"),
+                    bytemuck::Zeroable::zeroed(),
+                    &self.view_layout,
+                ));
+            }
+
+            let (z_near, z_far) = Self::clip_planes(camera);
+
+            *self.view_uniforms[view_index].values_mut() = LightClusterPackedUniforms {
+                inverse_projection: camera.inverse_projection_matrix(),
+                view: camera.view_matrix(),
+                z_near,
+                z_far,
+                first_cluster: view_index as u32 * Self::CLUSTERS_PER_VIEW,
+                light_count: point_lights.len() as u32,
+            };
+            self.view_uniforms[view_index].update_gpu(queue);
+
+            clip_planes.push((z_near, z_far));
+        }
+
+        self.view_count = clip_planes.len();
+
+        // Buffers are only replaced when they grow, and the bind group is only
+        // recreated when a buffer is replaced.
+        let mut replaced = self.point_lights.write(device, queue, point_lights);
+        let view_capacity = self.cluster_light_counts_buffer.size() as usize
+            / (std::mem::size_of::<u32>() * Self::CLUSTERS_PER_VIEW as usize);
+
+        if self.view_count > view_capacity {
+            (
+                self.cluster_light_counts_buffer,
+                self.cluster_light_indices_buffer,
+            ) = Self::create_cluster_buffers(device, self.view_count);
+            replaced = true;
+        }
+
+        if replaced {
+            self.clusters_bind_group = Self::create_clusters_bind_group(
+                device,
+                &self.clusters_layout,
+                self.point_lights.gpu_buffer(),
+                &self.cluster_light_counts_buffer,
+                &self.cluster_light_indices_buffer,
+            );
+        }
+
+        clip_planes
+    }
+
+    /// Bin the lights prepared this frame into each viewport's clusters. This
+    /// must run before the lit shaders read the clusters.
+    pub fn draw(&self, command_encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("light cluster compute pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(1, &self.clusters_bind_group, &[]);
+
+        // Each workgroup covers every tile in one depth slice.
+        for view_uniforms in self.view_uniforms.iter().take(self.view_count) {
+            compute_pass.set_bind_group(0, view_uniforms.bind_group(), &[]);
+            compute_pass.dispatch_workgroups(1, 1, Self::CLUSTERS_Z);
+        }
+    }
+
+    /// Get the buffer holding every point light in the scene.
+    pub fn point_lights_buffer(&self) -> &wgpu::Buffer {
+        self.point_lights.gpu_buffer()
+    }
+
+    /// Get the buffer holding the number of lights in each cluster.
+    pub fn cluster_light_counts_buffer(&self) -> &wgpu::Buffer {
+        &self.cluster_light_counts_buffer
+    }
+
+    /// Get the buffer holding the indices of the lights in each cluster, with
+    /// `MAX_LIGHTS_PER_CLUSTER` entries per cluster.
+    pub fn cluster_light_indices_buffer(&self) -> &wgpu::Buffer {
+        &self.cluster_light_indices_buffer
+    }
+
+    /// Get the near and far distances that `camera`'s clusters are sliced
+    /// between.
+    fn clip_planes(camera: &Camera) -> (f32, f32) {
+        let z_far = camera.z_far();
+        let z_near = camera.z_near().max(Self::MIN_Z_NEAR.min(z_far * 0.5));
+
+        (z_near, z_far)
+    }
+
+    fn create_cluster_buffers(
+        device: &wgpu::Device,
+        view_count: usize,
+    ) -> (wgpu::Buffer, wgpu::Buffer) {
+        let cluster_count = (view_count * Self::CLUSTERS_PER_VIEW as usize) as u64;
+        let create_buffer = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        };
+
+        (
+            create_buffer("cluster light counts", cluster_count * 4),
+            create_buffer(
+                "cluster light indices",
+                cluster_count * Self::MAX_LIGHTS_PER_CLUSTER as u64 * 4,
+            ),
+        )
+    }
+
+    fn create_clusters_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        point_lights_buffer: &wgpu::Buffer,
+        cluster_light_counts_buffer: &wgpu::Buffer,
+        cluster_light_indices_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light clusters bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: point_lights_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: cluster_light_counts_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: cluster_light_indices_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightClusterPackedUniforms {
+    pub inverse_projection: Mat4,
+    pub view: Mat4,
+    pub z_near: f32,
+    pub z_far: f32,
+    pub first_cluster: u32,
+    pub light_count: u32,
+}
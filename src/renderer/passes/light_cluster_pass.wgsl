@@ -0,0 +1,110 @@
+// These must match the constants in `LightClusterPass`.
+const CLUSTERS_X: u32 = 16u;
+const CLUSTERS_Y: u32 = 8u;
+const CLUSTERS_Z: u32 = 24u;
+const MAX_LIGHTS_PER_CLUSTER: u32 = 64u;
+
+struct LightClusterUniforms {
+    /// Clip space -> view space transform.
+    inverse_projection: mat4x4<f32>,
+    /// World space -> view space transform.
+    view: mat4x4<f32>,
+    /// Distance to the camera where the first depth slice starts.
+    z_near: f32,
+    /// Distance to the camera where the last depth slice ends.
+    z_far: f32,
+    /// Index of the viewport's first cluster.
+    first_cluster: u32,
+    light_count: u32,
+}
+
+/// Must match the `PackedPointLight` struct in the lit shader.
+struct PackedPointLight {
+    pos: vec4<f32>,
+    color: vec4<f32>,
+    attenuation: vec4<f32>,
+    /// `x` is the distance where the light fades out to nothing.
+    range: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> view: LightClusterUniforms;
+
+@group(1) @binding(0)
+var<storage, read> point_lights: array<PackedPointLight>;
+
+@group(1) @binding(1)
+var<storage, read_write> cluster_light_counts: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read_write> cluster_light_indices: array<u32>;
+
+/// Each invocation finds the lights touching one cluster, with one workgroup
+/// per depth slice.
+@compute @workgroup_size(16, 8, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let cluster = view.first_cluster
+        + id.x
+        + id.y * CLUSTERS_X
+        + id.z * CLUSTERS_X * CLUSTERS_Y;
+
+    // Depth slices are spaced exponentially so clusters far from the camera
+    // are not much longer than they are wide.
+    let depth_ratio = view.z_far / view.z_near;
+    let near = view.z_near * pow(depth_ratio, f32(id.z) / f32(CLUSTERS_Z));
+    let far = view.z_near * pow(depth_ratio, f32(id.z + 1u) / f32(CLUSTERS_Z));
+
+    // Tiles are numbered from the top left corner of the viewport.
+    let tile_size = vec2<f32>(2.0 / f32(CLUSTERS_X), 2.0 / f32(CLUSTERS_Y));
+    let ndc_min = vec2<f32>(
+        -1.0 + f32(id.x) * tile_size.x,
+        1.0 - f32(id.y + 1u) * tile_size.y);
+    let ndc_max = ndc_min + tile_size;
+
+    // Bound the frustum between the tile's corners and the slice's depths in
+    // view space.
+    var aabb_min = vec3<f32>(3.4e38);
+    var aabb_max = vec3<f32>(-3.4e38);
+
+    for (var corner = 0u; corner < 4u; corner++) {
+        let ndc = select(ndc_min, ndc_max, vec2<bool>((corner & 1u) != 0u, (corner & 2u) != 0u));
+
+        for (var i = 0u; i < 2u; i++) {
+            let p = view_point(ndc, select(near, far, i == 1u));
+            aabb_min = min(aabb_min, p);
+            aabb_max = max(aabb_max, p);
+        }
+    }
+
+    // Keep every light whose range overlaps the cluster's bounds.
+    var count = 0u;
+
+    for (var i = 0u; i < view.light_count && count < MAX_LIGHTS_PER_CLUSTER; i++) {
+        let light = point_lights[i];
+        let center = (view.view * vec4<f32>(light.pos.xyz, 1.0)).xyz;
+        let offset = clamp(center, aabb_min, aabb_max) - center;
+
+        if (dot(offset, offset) <= light.range.x * light.range.x) {
+            cluster_light_indices[cluster * MAX_LIGHTS_PER_CLUSTER + count] = i;
+            count++;
+        }
+    }
+
+    cluster_light_counts[cluster] = count;
+}
+
+/// Get the view space point `depth` units in front of the camera through a
+/// point in normalized device coordinates.
+fn view_point(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let p = view.inverse_projection * vec4<f32>(ndc, 0.0, 1.0);
+    let position = p.xyz / p.w;
+
+    // Orthographic projections do not divide by depth, so their inverse leaves
+    // `w` alone and the view is the same width at every depth.
+    if (view.inverse_projection[2][3] == 0.0) {
+        return vec3<f32>(position.xy, -depth);
+    }
+
+    let direction = position / -position.z;
+    return direction * depth;
+}
@@ -1,42 +1,105 @@
 use glam::{Mat4, Quat, Vec3};
 use wgpu::util::DeviceExt;
 
-// TODO: Draw spot light as a pyramid mesh.
 // TODO: Use model instancing for rendering the meshes.
 // TODO: Re-use the existing cube mesh, just update the shader to ignore
 //       unneeded attributes like normal.
-// TODO: Add debug state to `DebugState`, then pass to here ::update + ::draw
-
-use crate::renderer::{
-    debug::{DebugVertex, CUBE_INDICES, CUBE_VERTS},
-    gpu_buffers::{DynamicGpuBuffer, InstanceBuffer, UniformBindGroup},
-    lighting::PointLight,
-    scene::Scene,
-    shaders::{BindGroupLayouts, PerFrameShaderVals},
+
+use crate::{
+    camera::Camera,
+    renderer::{
+        debug::{
+            self, DebugState, DebugVertex, CUBE_INDICES, CUBE_VERTS, PYRAMID_INDICES,
+            PYRAMID_VERTS, WIRE_CUBE_INDICES, WIRE_CUBE_VERTS,
+        },
+        debug_draw::{DebugDraw, DebugLineVertex},
+        gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, InstanceBuffer, UniformBindGroup},
+        lighting::{DirectionalLight, PointLight, SpotLight},
+        scene::Scene,
+        viewport::Viewport,
+    },
 };
 
 /// Provides a debug visualization layer to the renderer.
 ///
 /// Lighting information must be specified every frame as the information is not
-/// retained between frames.
+/// retained between frames. Other colored debug cubes can be added with
+/// `add_cube`, and outlined boxes and spheres with `add_wire_cube` and
+/// `add_wire_sphere`. Lines from `DebugDraw` are copied in with `set_lines`.
+/// These are drawn even when light visualization is turned off.
 pub struct LightDebugPass {
     /// Render pipeline for the debug overlay.
     render_pipeline: wgpu::RenderPipeline,
+    /// Render pipeline for debug meshes drawn as lines.
+    line_render_pipeline: wgpu::RenderPipeline,
+    /// Render pipeline for world space lines from `DebugDraw`.
+    debug_draw_pipeline: wgpu::RenderPipeline,
+    /// Camera values copied each frame in `prepare`.
+    uniforms: GenericUniformBuffer<LightDebugPackedUniforms>,
     cube_vertex_buffer: wgpu::Buffer,
     cube_index_buffer: wgpu::Buffer,
-    lamp_instances: DebugMeshInstanceBuffer,
-    lamp_count: usize,
+    /// Cube instances for point light lamps and directional light arrow shafts.
+    cube_instances: DebugMeshInstanceBuffer,
+    cube_count: usize,
+    pyramid_vertex_buffer: wgpu::Buffer,
+    pyramid_index_buffer: wgpu::Buffer,
+    /// Pyramid instances for spot light cones and directional light arrow heads.
+    pyramid_instances: DebugMeshInstanceBuffer,
+    pyramid_count: usize,
+    wire_cube_vertex_buffer: wgpu::Buffer,
+    wire_cube_index_buffer: wgpu::Buffer,
+    /// Wire cube instances for outlined boxes (eg bounding boxes).
+    wire_cube_instances: DebugMeshInstanceBuffer,
+    wire_cube_count: usize,
+    wire_sphere_vertex_buffer: wgpu::Buffer,
+    wire_sphere_index_buffer: wgpu::Buffer,
+    wire_sphere_index_count: u32,
+    /// Wire sphere instances for outlined spheres (eg bounding spheres).
+    wire_sphere_instances: DebugMeshInstanceBuffer,
+    wire_sphere_count: usize,
+    /// Line list vertices from `DebugDraw`, which grows to fit the lines drawn
+    /// in a frame.
+    debug_draw_vertices: InstanceBuffer<DebugLineVertex>,
+    debug_draw_vertex_count: u32,
 }
 
 impl LightDebugPass {
     const SHADER: &'static str = include_str!("debug_shader.wgsl");
+    /// Length of the cone drawn for each spot light.
+    const SPOT_LIGHT_RANGE: f32 = 1.0;
+    /// World position that directional light arrows are centered on.
+    const DIRECTIONAL_LIGHT_ANCHOR: Vec3 = Vec3::new(0.0, 3.0, 0.0);
+    /// Length of the arrow drawn for each directional light.
+    const DIRECTIONAL_LIGHT_ARROW_LENGTH: f32 = 1.0;
+    /// Maximum number of instances of each debug mesh drawn in one frame. Any
+    /// more are dropped.
+    const MAX_INSTANCES: usize = 100;
 
     /// Create a new debug pass. Only one instance is needed per renderer.
-    pub fn new(
-        device: &wgpu::Device,
-        surface_config: &wgpu::SurfaceConfiguration,
-        layouts: &BindGroupLayouts,
-    ) -> Self {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("light debug pass layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniforms = GenericUniformBuffer::<LightDebugPackedUniforms>::new(
+            device,
+            Some("light debug pass uniforms"),
+            LightDebugPackedUniforms {
+                view_projection: Mat4::IDENTITY,
+            },
+            &bind_group_layout,
+        );
+
         // Load the cube debug mesh and generate N instances for rendering.
         let cube_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Debug Cube Vertex Buffer"),
@@ -50,100 +113,296 @@ impl LightDebugPass {
             usage: wgpu::BufferUsages::INDEX,
         });
 
+        // Load the pyramid debug mesh used for spot lights and arrow heads.
+        let pyramid_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Debug Pyramid Vertex Buffer"),
+            contents: bytemuck::cast_slice(PYRAMID_VERTS),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let pyramid_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Debug Pyramid Index Buffer"),
+            contents: bytemuck::cast_slice(PYRAMID_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // Load the wire cube debug mesh used for outlined boxes.
+        let wire_cube_vertex_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Debug Wire Cube Vertex Buffer"),
+                contents: bytemuck::cast_slice(WIRE_CUBE_VERTS),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let wire_cube_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Debug Wire Cube Index Buffer"),
+            contents: bytemuck::cast_slice(WIRE_CUBE_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // Build the wire sphere debug mesh used for outlined spheres.
+        let (wire_sphere_verts, wire_sphere_indices) = debug::wire_sphere();
+        let wire_sphere_vertex_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Debug Wire Sphere Vertex Buffer"),
+                contents: bytemuck::cast_slice(&wire_sphere_verts),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let wire_sphere_index_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Debug Wire Sphere Index Buffer"),
+                contents: bytemuck::cast_slice(&wire_sphere_indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
         // Load the shader used to render debug meshes.
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
             source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
         });
 
-        // Create a render pipeline for rendering the debug layer.
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("debug pass render pipeline"),
-            layout: Some(
-                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("debug pass pipeline layout"),
-                    bind_group_layouts: &[&layouts.per_frame_layout],
-                    push_constant_ranges: &[],
-                }),
-            ),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    DebugVertex::desc(),
-                    DebugMeshInstanceBuffer::vertex_layout(),
-                ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: super::DepthPass::DEPTH_TEXTURE_FORMAT,
-                depth_write_enabled: false,
-                depth_compare: wgpu::CompareFunction::Less, // Fragments drawn front to back.
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
+        // Create render pipelines for rendering the debug layer. Solid meshes,
+        // line meshes and `DebugDraw` lines share everything except their
+        // vertices and how primitives are drawn.
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("debug pass pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
         });
 
+        let create_pipeline = |label, entry_point, buffers, topology, cull_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point,
+                    buffers,
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: super::DepthPass::DEPTH_TEXTURE_FORMAT,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::Less, // Fragments drawn front to back.
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: super::PostProcessStack::HDR_TEXTURE_FORMAT,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent::REPLACE,
+                            alpha: wgpu::BlendComponent::REPLACE,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+
+        let mesh_buffers = &[
+            DebugVertex::desc(),
+            DebugMeshInstanceBuffer::vertex_layout(),
+        ];
+        let render_pipeline = create_pipeline(
+            "debug pass render pipeline",
+            "vs_main",
+            mesh_buffers,
+            wgpu::PrimitiveTopology::TriangleList,
+            Some(wgpu::Face::Back),
+        );
+        let line_render_pipeline = create_pipeline(
+            "debug pass line render pipeline",
+            "vs_main",
+            mesh_buffers,
+            wgpu::PrimitiveTopology::LineList,
+            None,
+        );
+        let debug_draw_pipeline = create_pipeline(
+            "debug draw line render pipeline",
+            "vs_line",
+            &[DebugLineVertex::desc()],
+            wgpu::PrimitiveTopology::LineList,
+            None,
+        );
+
         Self {
             render_pipeline,
+            line_render_pipeline,
+            debug_draw_pipeline,
+            uniforms,
             cube_vertex_buffer,
             cube_index_buffer,
-            lamp_instances: DebugMeshInstanceBuffer::new(device),
-            lamp_count: 0,
+            cube_instances: DebugMeshInstanceBuffer::new(device),
+            cube_count: 0,
+            pyramid_vertex_buffer,
+            pyramid_index_buffer,
+            pyramid_instances: DebugMeshInstanceBuffer::new(device),
+            pyramid_count: 0,
+            wire_cube_vertex_buffer,
+            wire_cube_index_buffer,
+            wire_cube_instances: DebugMeshInstanceBuffer::new(device),
+            wire_cube_count: 0,
+            wire_sphere_vertex_buffer,
+            wire_sphere_index_buffer,
+            wire_sphere_index_count: wire_sphere_indices.len() as u32,
+            wire_sphere_instances: DebugMeshInstanceBuffer::new(device),
+            wire_sphere_count: 0,
+            debug_draw_vertices: InstanceBuffer::new(
+                device,
+                Some("debug draw vertex buffer"),
+                vec![Default::default(); 2],
+            ),
+            debug_draw_vertex_count: 0,
         }
     }
 
     /// Set the world position of the scene light.
     pub fn add_point_light(&mut self, light: &PointLight) {
-        self.lamp_instances
-            .set_color_tint(self.lamp_count, light.color);
-        self.lamp_instances.set_local_to_world(
-            self.lamp_count,
+        self.add_cube(
             Mat4::from_scale_rotation_translation(
                 Vec3::new(0.2, 0.2, 0.2),
                 Quat::IDENTITY,
                 light.position,
             ),
+            light.color,
+        );
+    }
+
+    /// Draw a cone from the spot light's position pointing in the direction of
+    /// the light, with the cone's width matching the outer cutoff angle.
+    pub fn add_spot_light(&mut self, light: &SpotLight) {
+        let Some(direction) = light.direction.try_normalize() else {
+            return;
+        };
+
+        // The pyramid mesh points down -Z with a base that is two units wide.
+        let radius = Self::SPOT_LIGHT_RANGE * light.outer_cutoff_radians.tan();
+
+        self.add_pyramid(
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(radius, radius, Self::SPOT_LIGHT_RANGE),
+                Quat::from_rotation_arc(Vec3::NEG_Z, direction),
+                light.position,
+            ),
+            light.color,
+        );
+    }
+
+    /// Draw an arrow at a fixed location in the scene pointing in the direction
+    /// of the light. `index` is used to keep multiple directional light arrows
+    /// from overlapping.
+    pub fn add_directional_light(&mut self, index: usize, light: &DirectionalLight) {
+        let Some(direction) = light.direction.try_normalize() else {
+            return;
+        };
+
+        let length = Self::DIRECTIONAL_LIGHT_ARROW_LENGTH;
+        let head_length = length * 0.25;
+        let center = Self::DIRECTIONAL_LIGHT_ANCHOR + Vec3::X * index as f32;
+        let tip = center + direction * (length * 0.5);
+
+        // Arrow shaft runs from the tail to the base of the arrow head.
+        self.add_cube(
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(0.05, 0.05, length - head_length),
+                Quat::from_rotation_arc(Vec3::Z, direction),
+                center - direction * (head_length * 0.5),
+            ),
+            light.color,
+        );
+
+        // The pyramid's apex is the tip of the arrow, and its base points back
+        // towards the shaft.
+        self.add_pyramid(
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(0.1, 0.1, head_length),
+                Quat::from_rotation_arc(Vec3::Z, direction),
+                tip,
+            ),
+            light.color,
         );
+    }
+
+    /// Copy the lines added to `debug_draw` this frame into the pass's vertex
+    /// buffer, growing the buffer if they do not fit.
+    pub fn set_lines(&mut self, device: &wgpu::Device, debug_draw: &DebugDraw) {
+        self.debug_draw_vertex_count = debug_draw.vertices().len() as u32;
 
-        self.lamp_count += 1;
+        if !debug_draw.is_empty() {
+            self.debug_draw_vertices
+                .set_instances(device, debug_draw.vertices().iter().copied());
+        }
     }
 
     /// Prepare for rendering by creating and updating all resources used during
     /// rendering.
-    pub fn prepare(&mut self, queue: &wgpu::Queue, scene: &Scene) {
-        for light in &scene.point_lights {
-            self.add_point_light(light);
+    pub fn prepare(
+        &mut self,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        camera: &Camera,
+        debug_state: &DebugState,
+    ) {
+        self.uniforms.values_mut().view_projection = camera.view_projection_matrix();
+
+        if debug_state.visualize_lights {
+            for light in &scene.point_lights() {
+                self.add_point_light(light);
+            }
+
+            if debug_state.visualize_spot_lights {
+                for light in &scene.spot_lights() {
+                    self.add_spot_light(light);
+                }
+            }
+
+            if debug_state.visualize_directional_lights {
+                for (index, light) in scene.directional_lights().iter().enumerate() {
+                    self.add_directional_light(index, light);
+                }
+            }
+        }
+
+        if self.uniforms.is_dirty() {
+            self.uniforms.update_gpu(queue);
+        }
+
+        if self.cube_instances.is_dirty() {
+            self.cube_instances.update_gpu(queue)
+        }
+
+        if self.pyramid_instances.is_dirty() {
+            self.pyramid_instances.update_gpu(queue)
         }
 
-        if self.lamp_instances.is_dirty() {
-            self.lamp_instances.update_gpu(queue)
+        if self.wire_cube_instances.is_dirty() {
+            self.wire_cube_instances.update_gpu(queue)
+        }
+
+        if self.wire_sphere_instances.is_dirty() {
+            self.wire_sphere_instances.update_gpu(queue)
+        }
+
+        if self.debug_draw_vertices.is_dirty() {
+            self.debug_draw_vertices.update_gpu(queue)
         }
     }
 
@@ -152,8 +411,9 @@ impl LightDebugPass {
         &self,
         output_view: &wgpu::TextureView,
         depth_buffer: &wgpu::TextureView,
-        per_frame_uniforms: &PerFrameShaderVals, // TODO: Don't pass, move values to `prepare`.
+        viewport: &Viewport,
         command_encoder: &mut wgpu::CommandEncoder,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
     ) {
         let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("debug render pass"),
@@ -173,25 +433,146 @@ impl LightDebugPass {
                 }),
                 stencil_ops: None,
             }),
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
+        viewport.apply(&mut render_pass);
         render_pass.set_pipeline(&self.render_pipeline);
 
-        render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+        render_pass.set_bind_group(0, self.uniforms.bind_group(), &[]);
+
         render_pass.set_vertex_buffer(0, self.cube_vertex_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, self.lamp_instances.gpu_buffer_slice(..));
+        render_pass.set_vertex_buffer(1, self.cube_instances.gpu_buffer_slice(..));
         render_pass.set_index_buffer(self.cube_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..(self.cube_count as u32));
+
+        render_pass.set_vertex_buffer(0, self.pyramid_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.pyramid_instances.gpu_buffer_slice(..));
+        render_pass.set_index_buffer(
+            self.pyramid_index_buffer.slice(..),
+            wgpu::IndexFormat::Uint16,
+        );
+        render_pass.draw_indexed(
+            0..PYRAMID_INDICES.len() as u32,
+            0,
+            0..(self.pyramid_count as u32),
+        );
+
+        render_pass.set_pipeline(&self.line_render_pipeline);
+        render_pass.set_vertex_buffer(0, self.wire_cube_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.wire_cube_instances.gpu_buffer_slice(..));
+        render_pass.set_index_buffer(
+            self.wire_cube_index_buffer.slice(..),
+            wgpu::IndexFormat::Uint16,
+        );
+        render_pass.draw_indexed(
+            0..WIRE_CUBE_INDICES.len() as u32,
+            0,
+            0..(self.wire_cube_count as u32),
+        );
+
+        render_pass.set_vertex_buffer(0, self.wire_sphere_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.wire_sphere_instances.gpu_buffer_slice(..));
+        render_pass.set_index_buffer(
+            self.wire_sphere_index_buffer.slice(..),
+            wgpu::IndexFormat::Uint16,
+        );
+        render_pass.draw_indexed(
+            0..self.wire_sphere_index_count,
+            0,
+            0..(self.wire_sphere_count as u32),
+        );
 
-        render_pass.draw_indexed(0..CUBE_INDICES.len() as u32, 0, 0..(self.lamp_count as u32));
+        if self.debug_draw_vertex_count > 0 {
+            render_pass.set_pipeline(&self.debug_draw_pipeline);
+            render_pass.set_vertex_buffer(0, self.debug_draw_vertices.gpu_buffer_slice(..));
+            render_pass.draw(0..self.debug_draw_vertex_count, 0..1);
+        }
+    }
+
+    /// Returns true if there is anything to draw this frame.
+    pub fn has_instances(&self) -> bool {
+        self.cube_count > 0
+            || self.pyramid_count > 0
+            || self.wire_cube_count > 0
+            || self.wire_sphere_count > 0
+            || self.debug_draw_vertex_count > 0
     }
 
     pub fn finish_frame(&mut self) {
-        self.lamp_count = 0
+        self.cube_count = 0;
+        self.pyramid_count = 0;
+        self.wire_cube_count = 0;
+        self.wire_sphere_count = 0;
+        self.debug_draw_vertex_count = 0;
+    }
+
+    /// Add a unit cube instance to be drawn this frame.
+    pub fn add_cube(&mut self, local_to_world: Mat4, color: Vec3) {
+        if self.cube_count >= Self::MAX_INSTANCES {
+            return;
+        }
+
+        self.cube_instances.set_color_tint(self.cube_count, color);
+        self.cube_instances
+            .set_local_to_world(self.cube_count, local_to_world);
+
+        self.cube_count += 1;
+    }
+
+    /// Add the edges of a unit cube to be drawn as lines this frame.
+    pub fn add_wire_cube(&mut self, local_to_world: Mat4, color: Vec3) {
+        if self.wire_cube_count >= Self::MAX_INSTANCES {
+            return;
+        }
+
+        self.wire_cube_instances
+            .set_color_tint(self.wire_cube_count, color);
+        self.wire_cube_instances
+            .set_local_to_world(self.wire_cube_count, local_to_world);
+
+        self.wire_cube_count += 1;
+    }
+
+    /// Add the outline of a sphere centered on `center` to be drawn as lines
+    /// this frame.
+    pub fn add_wire_sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+        if self.wire_sphere_count >= Self::MAX_INSTANCES {
+            return;
+        }
+
+        self.wire_sphere_instances
+            .set_color_tint(self.wire_sphere_count, color);
+        self.wire_sphere_instances.set_local_to_world(
+            self.wire_sphere_count,
+            Mat4::from_scale_rotation_translation(Vec3::splat(radius), Quat::IDENTITY, center),
+        );
+
+        self.wire_sphere_count += 1;
+    }
+
+    /// Add a pyramid instance to be drawn this frame.
+    fn add_pyramid(&mut self, local_to_world: Mat4, color: Vec3) {
+        if self.pyramid_count >= Self::MAX_INSTANCES {
+            return;
+        }
+
+        self.pyramid_instances
+            .set_color_tint(self.pyramid_count, color);
+        self.pyramid_instances
+            .set_local_to_world(self.pyramid_count, local_to_world);
+
+        self.pyramid_count += 1;
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LightDebugPackedUniforms {
+    pub view_projection: Mat4,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct DebugMeshPackedInstance {
@@ -218,7 +599,7 @@ impl DebugMeshInstanceBuffer {
                         color_tint: Vec3::ONE,
                         _padding_1: Default::default(),
                     };
-                    100
+                    LightDebugPass::MAX_INSTANCES
                 ],
             ),
         }
@@ -235,7 +616,7 @@ impl DebugMeshInstanceBuffer {
     }
 
     /// Get the GPU buffer object used by this instance buffer.
-    pub fn gpu_buffer_slice<S>(&self, bounds: S) -> wgpu::BufferSlice
+    pub fn gpu_buffer_slice<S>(&self, bounds: S) -> wgpu::BufferSlice<'_>
     where
         S: std::ops::RangeBounds<wgpu::BufferAddress>,
     {
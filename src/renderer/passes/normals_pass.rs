@@ -0,0 +1,199 @@
+use glam::{Vec3, Vec4};
+
+use crate::renderer::{
+    gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup},
+    models::{self, DrawModel, Model},
+    shaders::{BindGroupLayouts, PerFrameShaderVals, PerModelBinding, VertexLayout},
+    viewport::Viewport,
+};
+
+/// Draws a line from each vertex of the selected models along the vertex's
+/// normal, for checking that normals were imported or generated correctly.
+///
+/// Lines are built on the GPU from the mesh's vertex buffer, which is read once
+/// per instance so every instance draws one line. Vertices do not have tangents
+/// yet, so only normals are drawn.
+pub struct NormalsPass {
+    /// Render pipeline for drawing normals as a line list.
+    render_pipeline: wgpu::RenderPipeline,
+    /// Line color and length shared by all models.
+    uniforms: GenericUniformBuffer<NormalsPackedUniforms>,
+}
+
+impl NormalsPass {
+    pub const DEFAULT_COLOR: Vec3 = Vec3::new(0.2, 0.4, 1.0);
+    pub const DEFAULT_LENGTH: f32 = 0.2;
+    const SHADER: &'static str = include_str!("normals_pass.wgsl");
+
+    /// Create a new normals pass. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("normals pass layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniforms = GenericUniformBuffer::<NormalsPackedUniforms>::new(
+            device,
+            Some("normals pass uniforms"),
+            NormalsPackedUniforms {
+                color: Self::DEFAULT_COLOR.extend(1.0),
+                length: Self::DEFAULT_LENGTH,
+                _padding: Default::default(),
+            },
+            &bind_group_layout,
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("normals pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("normals pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("normals pass pipeline layout"),
+                    bind_group_layouts: &[
+                        &layouts.per_frame_layout,
+                        &layouts.per_model_layout,
+                        &bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                // Each mesh vertex is one instance of a two vertex line.
+                buffers: &[wgpu::VertexBufferLayout {
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    ..models::Vertex::vertex_buffer_layout()
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // Lines start on the model's surface, so they are depth tested to
+            // hide normals on the far side of the model.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: super::DepthPass::DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: super::PostProcessStack::HDR_TEXTURE_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            render_pipeline,
+            uniforms,
+        }
+    }
+
+    /// Get the color of the normal lines.
+    pub fn color(&self) -> Vec3 {
+        self.uniforms.values().color.truncate()
+    }
+
+    /// Set the color of the normal lines.
+    pub fn set_color(&mut self, color: Vec3) {
+        self.uniforms.values_mut().color = color.extend(1.0);
+    }
+
+    /// Get the length of the normal lines in world units.
+    pub fn length(&self) -> f32 {
+        self.uniforms.values().length
+    }
+
+    /// Set the length of the normal lines in world units.
+    pub fn set_length(&mut self, length: f32) {
+        self.uniforms.values_mut().length = length;
+    }
+
+    /// Prepare for rendering by copying any changed line values to the GPU.
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uniforms.is_dirty() {
+            self.uniforms.update_gpu(queue);
+        }
+    }
+
+    /// Draw the vertex normals of each of `models` as seen in `viewport`.
+    pub fn draw<'a>(
+        &'a self,
+        output_view: &wgpu::TextureView,
+        depth_stencil_view: &wgpu::TextureView,
+        per_frame_uniforms: &'a PerFrameShaderVals,
+        viewport: &Viewport,
+        models: impl Iterator<Item = (&'a Model, PerModelBinding<'a>)>,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("normals render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_stencil_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        viewport.apply(&mut render_pass);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+        render_pass.set_bind_group(2, self.uniforms.bind_group(), &[]);
+
+        for (model, model_sv) in models {
+            render_pass.draw_vertex_normals(model, model_sv);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct NormalsPackedUniforms {
+    pub color: Vec4,
+    pub length: f32,
+    pub _padding: [u32; 3],
+}
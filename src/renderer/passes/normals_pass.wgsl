@@ -0,0 +1,55 @@
+struct PerFrameUniforms {
+    view_projection: mat4x4<f32>,
+};
+
+struct PerModelUniforms {
+    local_to_world: mat4x4<f32>,
+    world_to_local: mat4x4<f32>,
+};
+
+struct NormalsUniforms {
+    /// Line color, .w is unused.
+    color: vec4<f32>,
+    /// Length of each line in world units.
+    length: f32,
+};
+
+/// One mesh vertex, which is stepped per instance so each instance draws a
+/// single line.
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> per_frame: PerFrameUniforms;
+
+@group(1) @binding(0)
+var<uniform> per_model: PerModelUniforms;
+
+@group(2) @binding(0)
+var<uniform> normals: NormalsUniforms;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, v_in: VertexInput) -> VertexOutput {
+    var v_out: VertexOutput;
+
+    // The first vertex of the line is on the surface and the second is at the
+    // tip of the normal.
+    let position_ws = (per_model.local_to_world * vec4<f32>(v_in.position, 1.0)).xyz;
+    let normal_ws = normalize((transpose(per_model.world_to_local) * vec4<f32>(v_in.normal, 0.0)).xyz);
+    let tip = select(0.0, normals.length, vertex_index == 1u);
+
+    v_out.position_cs = per_frame.view_projection * vec4<f32>(position_ws + normal_ws * tip, 1.0);
+
+    return v_out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normals.color.xyz, 1.0);
+}
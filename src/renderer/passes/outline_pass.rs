@@ -0,0 +1,207 @@
+use glam::{Vec3, Vec4};
+
+use crate::renderer::{
+    gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup},
+    models::{self, DrawModel, Model},
+    shaders::{BindGroupLayouts, PerFrameShaderVals, PerModelBinding, VertexLayout},
+    viewport::Viewport,
+};
+
+/// Draws a colored outline around selected models.
+///
+/// Selected models are expected to have written `STENCIL_SELECTED` into the
+/// stencil buffer when they were drawn by the main pass. This pass draws a
+/// slightly scaled up copy of each selected model, and only keeps the fragments
+/// that fall outside of the model's original stencil footprint.
+pub struct OutlinePass {
+    /// Render pipeline for drawing the scaled up outline meshes.
+    render_pipeline: wgpu::RenderPipeline,
+    /// Outline color and scale shared by all outlined models.
+    uniforms: GenericUniformBuffer<OutlinePackedUniforms>,
+}
+
+impl OutlinePass {
+    /// Stencil value written by the main pass for selected models.
+    pub const STENCIL_SELECTED: u32 = 1;
+    pub const DEFAULT_COLOR: Vec3 = Vec3::new(1.0, 0.6, 0.0);
+    pub const DEFAULT_SCALE: f32 = 1.05;
+    const SHADER: &'static str = include_str!("outline_pass.wgsl");
+
+    /// Create a new outline pass. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("outline pass layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniforms = GenericUniformBuffer::<OutlinePackedUniforms>::new(
+            device,
+            Some("outline pass uniforms"),
+            OutlinePackedUniforms {
+                color: Self::DEFAULT_COLOR.extend(1.0),
+                scale: Self::DEFAULT_SCALE,
+                _padding: Default::default(),
+            },
+            &bind_group_layout,
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("outline pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        // Only draw outline fragments where the selected model was not drawn,
+        // and never write back to the stencil buffer.
+        let stencil_face = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::NotEqual,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Keep,
+        };
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("outline pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("outline pass pipeline layout"),
+                    bind_group_layouts: &[
+                        &layouts.per_frame_layout,
+                        &layouts.per_model_layout,
+                        &bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[models::Vertex::vertex_buffer_layout()],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // The outline is drawn on top of everything so selected models can
+            // be found even when they are hidden behind other models.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: super::DepthPass::DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: stencil_face,
+                    back: stencil_face,
+                    read_mask: 0xff,
+                    write_mask: 0,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: super::PostProcessStack::HDR_TEXTURE_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            render_pipeline,
+            uniforms,
+        }
+    }
+
+    /// Get the color of the outline.
+    pub fn color(&self) -> Vec3 {
+        self.uniforms.values().color.truncate()
+    }
+
+    /// Set the color of the outline.
+    pub fn set_color(&mut self, color: Vec3) {
+        self.uniforms.values_mut().color = color.extend(1.0);
+    }
+
+    /// Prepare for rendering by copying any changed outline values to the GPU.
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uniforms.is_dirty() {
+            self.uniforms.update_gpu(queue);
+        }
+    }
+
+    /// Draw an outline around each of the selected `models` as seen in
+    /// `viewport`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw<'a>(
+        &'a self,
+        output_view: &wgpu::TextureView,
+        depth_stencil_view: &wgpu::TextureView,
+        per_frame_uniforms: &'a PerFrameShaderVals,
+        viewport: &Viewport,
+        models: impl Iterator<Item = (&'a Model, PerModelBinding<'a>)>,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("outline render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_stencil_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        viewport.apply(&mut render_pass);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_stencil_reference(Self::STENCIL_SELECTED);
+        render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+        render_pass.set_bind_group(2, self.uniforms.bind_group(), &[]);
+
+        for (model, model_sv) in models {
+            render_pass.draw_model_geometry(model, model_sv);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutlinePackedUniforms {
+    pub color: Vec4,
+    pub scale: f32,
+    pub _padding: [u32; 3],
+}
@@ -0,0 +1,47 @@
+struct PerFrameUniforms {
+    view_projection: mat4x4<f32>,
+};
+
+struct PerModelUniforms {
+    local_to_world: mat4x4<f32>,
+};
+
+struct OutlineUniforms {
+    /// Outline color, .w is unused.
+    color: vec4<f32>,
+    /// Amount to scale the mesh by when drawing the outline.
+    scale: f32,
+};
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> per_frame: PerFrameUniforms;
+
+@group(1) @binding(0)
+var<uniform> per_model: PerModelUniforms;
+
+@group(2) @binding(0)
+var<uniform> outline: OutlineUniforms;
+
+@vertex
+fn vs_main(v_in: VertexInput) -> VertexOutput {
+    var v_out: VertexOutput;
+
+    v_out.position_cs = per_frame.view_projection
+        * per_model.local_to_world
+        * vec4<f32>(v_in.position * outline.scale, 1.0);
+
+    return v_out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(outline.color.xyz, 1.0);
+}
@@ -0,0 +1,333 @@
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::renderer::{
+    gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup},
+    lighting::PointLight,
+    models::{self, DrawModel, Model},
+    shaders::{lit_shader, BindGroupLayouts, PerModelBinding, VertexLayout},
+};
+
+/// A depth cube map holding the distance from a point light to the nearest
+/// model in every direction.
+struct ShadowCubeMap {
+    _texture: wgpu::Texture,
+    /// Views of each face for rendering, in `PointShadowPass::FACES` order.
+    face_views: Vec<wgpu::TextureView>,
+    /// View of the entire cube for sampling in the lit shader.
+    cube_view: wgpu::TextureView,
+}
+
+impl ShadowCubeMap {
+    fn new(device: &wgpu::Device, size: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("point shadow cube map"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: PointShadowPass::DEPTH_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let face_views = (0..6)
+            .map(|face| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("point shadow cube map face"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: face,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let cube_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("point shadow cube map"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        Self {
+            _texture: texture,
+            face_views,
+            cube_view,
+        }
+    }
+}
+
+/// Draws the scene into a depth cube map around each point light that casts
+/// shadows, which the lit shader compares against to find shadowed fragments.
+///
+/// Cube maps store the distance from the light divided by the light's shadow
+/// distance rather than the projected depth. Cube maps are allocated the first
+/// time they are needed and kept for later frames.
+pub struct PointShadowPass {
+    render_pipeline: wgpu::RenderPipeline,
+    /// View projection and light position for each face of each cube map.
+    face_uniforms: Vec<GenericUniformBuffer<PointShadowFacePackedUniforms>>,
+    /// Cube maps for the shadow casting lights, in shadow map index order.
+    cube_maps: Vec<ShadowCubeMap>,
+    /// Bound in place of cube maps that have not been allocated.
+    empty_cube_map: ShadowCubeMap,
+    sampler: wgpu::Sampler,
+    /// Number of cube maps drawn this frame.
+    active_lights: usize,
+}
+
+impl PointShadowPass {
+    pub const DEPTH_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    /// Width and height of each cube map face in texels.
+    pub const SHADOW_MAP_SIZE: u32 = 512;
+    /// Models closer to the light than this do not cast shadows.
+    const NEAR_PLANE: f32 = 0.05;
+    /// Direction and up vector of each cube map face, in the order expected by
+    /// cube map sampling (+X, -X, +Y, -Y, +Z, -Z).
+    const FACES: [(Vec3, Vec3); 6] = [
+        (Vec3::X, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Y),
+        (Vec3::Y, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::Z),
+        (Vec3::Z, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y),
+    ];
+    const SHADER: &'static str = include_str!("point_shadow_pass.wgsl");
+
+    /// Create a new point shadow pass. Only one instance is needed per
+    /// renderer.
+    pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
+        let face_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("point shadow face layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let face_uniforms = (0..lit_shader::MAX_SHADOWED_POINT_LIGHTS * 6)
+            .map(|_| {
+                GenericUniformBuffer::new(
+                    device,
+                    Some("point shadow face uniforms"),
+                    PointShadowFacePackedUniforms::default(),
+                    &face_layout,
+                )
+            })
+            .collect();
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("point shadow pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("point shadow pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("point shadow pass pipeline layout"),
+                    bind_group_layouts: &[&face_layout, &layouts.per_model_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[models::Vertex::vertex_buffer_layout()],
+            },
+            // Both sides of every triangle cast shadows, so open meshes and
+            // meshes drawn without culling block light too.
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Self::DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[],
+            }),
+            multiview: None,
+        });
+
+        // Fragments further from the light than the stored distance are in
+        // shadow. Linear filtering blends the comparison of neighboring texels
+        // to soften shadow edges.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("point shadow sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self {
+            render_pipeline,
+            face_uniforms,
+            cube_maps: Vec::new(),
+            empty_cube_map: ShadowCubeMap::new(device, 1),
+            sampler,
+            active_lights: 0,
+        }
+    }
+
+    /// Get the view projection matrices looking out of each cube map face from
+    /// `light`.
+    pub fn face_view_projections(light: &PointLight) -> [Mat4; 6] {
+        // Cube map faces are laid out as if seen from inside the cube, which
+        // mirrors them compared to a right handed view.
+        let projection = Mat4::perspective_lh(
+            std::f32::consts::FRAC_PI_2,
+            1.0,
+            Self::NEAR_PLANE,
+            light.shadow_distance(),
+        );
+
+        Self::FACES
+            .map(|(direction, up)| projection * Mat4::look_to_lh(light.position, direction, up))
+    }
+
+    /// Pick which of `lights` cast shadows this frame and prepare their cube
+    /// maps. Returns the shadow map index of each light, or `None` for lights
+    /// that do not cast shadows.
+    ///
+    /// Only the first `lit_shader::MAX_SHADOWED_POINT_LIGHTS` shadow casting
+    /// lights that the lit shader can draw get shadows.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lights: &[PointLight],
+    ) -> Vec<Option<usize>> {
+        self.active_lights = 0;
+
+        let shadow_maps = lights
+            .iter()
+            .map(|light| {
+                if !light.casts_shadows
+                    || self.active_lights == lit_shader::MAX_SHADOWED_POINT_LIGHTS
+                {
+                    return None;
+                }
+
+                let shadow_map = self.active_lights;
+                self.active_lights += 1;
+
+                for (face, view_projection) in
+                    Self::face_view_projections(light).into_iter().enumerate()
+                {
+                    *self.face_uniforms[shadow_map * 6 + face].values_mut() =
+                        PointShadowFacePackedUniforms {
+                            view_projection,
+                            light_pos: light.position.extend(light.shadow_distance()),
+                        };
+                }
+
+                Some(shadow_map)
+            })
+            .collect();
+
+        for face_uniforms in self.face_uniforms.iter().filter(|u| u.is_dirty()) {
+            face_uniforms.update_gpu(queue);
+        }
+
+        // Allocate cube maps for lights that have not cast shadows before.
+        while self.cube_maps.len() < self.active_lights {
+            self.cube_maps
+                .push(ShadowCubeMap::new(device, Self::SHADOW_MAP_SIZE));
+        }
+
+        shadow_maps
+    }
+
+    /// Get the comparison sampler the lit shader samples shadow cube maps
+    /// with.
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// Get the cube map for shadow map index `shadow_map`. An empty cube map is
+    /// returned for shadow maps that have not been allocated yet.
+    pub fn cube_map_view(&self, shadow_map: usize) -> &wgpu::TextureView {
+        &self
+            .cube_maps
+            .get(shadow_map)
+            .unwrap_or(&self.empty_cube_map)
+            .cube_view
+    }
+
+    /// Draw `models` into the cube map of every shadow casting light prepared
+    /// for this frame.
+    pub fn draw<'a>(
+        &'a self,
+        models: impl Iterator<Item = (&'a Model, PerModelBinding<'a>)> + Clone,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        for (shadow_map, cube_map) in self.cube_maps[..self.active_lights].iter().enumerate() {
+            for (face, face_view) in cube_map.face_views.iter().enumerate() {
+                let mut render_pass =
+                    command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("point shadow render pass"),
+                        color_attachments: &[],
+                        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                            view: face_view,
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: wgpu::StoreOp::Store,
+                            }),
+                            stencil_ops: None,
+                        }),
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+                render_pass.set_pipeline(&self.render_pipeline);
+                render_pass.set_bind_group(
+                    0,
+                    self.face_uniforms[shadow_map * 6 + face].bind_group(),
+                    &[],
+                );
+
+                for (model, model_sv) in models.clone() {
+                    render_pass.draw_model_geometry(model, model_sv);
+                }
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointShadowFacePackedUniforms {
+    pub view_projection: Mat4,
+    pub light_pos: Vec4,
+}
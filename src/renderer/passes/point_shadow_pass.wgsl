@@ -0,0 +1,43 @@
+struct ShadowFaceUniforms {
+    /// View projection looking out of one face of the shadow cube map.
+    view_projection: mat4x4<f32>,
+    /// World space position of the light. (`w` is the shadow distance).
+    light_pos: vec4<f32>,
+};
+
+struct PerModelUniforms {
+    local_to_world: mat4x4<f32>,
+};
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    @location(0) position_ws: vec3<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> face: ShadowFaceUniforms;
+
+@group(1) @binding(0)
+var<uniform> per_model: PerModelUniforms;
+
+@vertex
+fn vs_main(v_in: VertexInput) -> VertexOutput {
+    var v_out: VertexOutput;
+
+    let position_ws = per_model.local_to_world * vec4<f32>(v_in.position, 1.0);
+    v_out.position_cs = face.view_projection * position_ws;
+    v_out.position_ws = position_ws.xyz;
+
+    return v_out;
+}
+
+/// Store the distance from the light rather than the projected depth, so the
+/// lit shader can compare against it without knowing which face it samples.
+@fragment
+fn fs_main(v_in: VertexOutput) -> @builtin(frag_depth) f32 {
+    return length(v_in.position_ws - face.light_pos.xyz) / face.light_pos.w;
+}
@@ -0,0 +1,414 @@
+//! Full screen effects that are applied to the scene after it is drawn.
+
+use std::any::Any;
+
+mod color_grading;
+mod fxaa;
+mod tonemap;
+mod vignette;
+
+pub use color_grading::ColorGradingEffect;
+pub use fxaa::FxaaEffect;
+pub use tonemap::{TonemapEffect, Tonemapper};
+pub use vignette::VignetteEffect;
+
+/// Shader code shared by all post effects, which each effect's own shader code
+/// is appended to.
+const SHARED_SHADER: &str = include_str!("post_process.wgsl");
+
+/// A full screen effect that is run by a `PostProcessStack`.
+///
+/// Effects read the output of the previous effect from the input texture bound
+/// at group 0 (see `post_process.wgsl`), and write their result to the render
+/// target of the render pass they are drawn in. Effects are free to use any
+/// other bind groups for their own resources.
+pub trait PostEffect: Any {
+    /// Name used to find the effect in a stack. Names should be unique within
+    /// a stack.
+    fn name(&self) -> &str;
+
+    /// Copy any changed effect settings to the GPU before the effect is drawn.
+    fn prepare(&mut self, _queue: &wgpu::Queue) {}
+
+    /// Draw the effect into `render_pass`, reading from the input texture in
+    /// `input_bind_group`.
+    fn draw<'a>(
+        &'a self,
+        input_bind_group: &'a wgpu::BindGroup,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    );
+}
+
+/// A texture that post effects read from or write into.
+struct PostProcessTarget {
+    view: wgpu::TextureView,
+    /// Bind group for reading this texture as the input of an effect.
+    bind_group: wgpu::BindGroup,
+}
+
+impl PostProcessTarget {
+    fn new(
+        device: &wgpu::Device,
+        input_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        surface_config: &wgpu::SurfaceConfiguration,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: surface_config.width.max(1),
+                height: surface_config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: PostProcessStack::HDR_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: input_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        Self { view, bind_group }
+    }
+}
+
+/// The scene is drawn into a floating point HDR texture owned by the stack,
+/// and then each post effect is run in order with the output of one effect
+/// used as the input of the next. Effects ping-pong between two offscreen
+/// textures, and the output of the last effect is copied to the render target.
+///
+/// Effects can be added and removed between frames. This is the only pass that
+/// writes to the display's render target, and the only pass that needs to know
+/// if that target is sRGB.
+pub struct PostProcessStack {
+    effects: Vec<Box<dyn PostEffect>>,
+    input_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Texture the scene is drawn into.
+    scene_target: PostProcessTarget,
+    /// Textures that effects alternate writing to.
+    ping_pong_targets: [PostProcessTarget; 2],
+    /// Copies the final output to the render target.
+    present_pipeline: wgpu::RenderPipeline,
+}
+
+impl PostProcessStack {
+    /// Color format of the texture that the scene is drawn into, and of the
+    /// textures that post effects write to.
+    pub const HDR_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    const PRESENT_SHADER: &'static str = include_str!("post_process/present.wgsl");
+
+    /// Create a new post processing stack without any effects. Only one
+    /// instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) -> Self {
+        let input_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post effect input layout"),
+            entries: &[
+                // Slot 0: output of the previous effect.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Slot 1: sampler for the previous effect's output.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // Effects like FXAA sample between texels, so both filters are linear.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post effect input sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let (scene_target, ping_pong_targets) =
+            Self::create_targets(device, &input_layout, &sampler, surface_config);
+
+        // The present shader converts colors to sRGB itself when the render
+        // target will not do it.
+        let present_pipeline = create_effect_pipeline_for_target(
+            device,
+            &input_layout,
+            "post process present",
+            Self::PRESENT_SHADER,
+            &[],
+            surface_config.format,
+            if surface_config.format.is_srgb() {
+                "fs_main"
+            } else {
+                "fs_main_linear_target"
+            },
+        );
+
+        Self {
+            effects: Vec::new(),
+            input_layout,
+            sampler,
+            scene_target,
+            ping_pong_targets,
+            present_pipeline,
+        }
+    }
+
+    /// Get the layout of the input texture bind group that effects read from.
+    /// This is needed to create effects.
+    pub fn input_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.input_layout
+    }
+
+    /// Get the texture view that the scene should be drawn into.
+    pub fn scene_texture_view(&self) -> &wgpu::TextureView {
+        &self.scene_target.view
+    }
+
+    /// Add an effect that runs after all of the current effects.
+    pub fn push(&mut self, effect: Box<dyn PostEffect>) {
+        self.effects.push(effect);
+    }
+
+    /// Add an effect that runs at position `index`, shifting the effects after
+    /// it back by one.
+    ///
+    /// Panics if `index` is greater than the number of effects.
+    pub fn insert(&mut self, index: usize, effect: Box<dyn PostEffect>) {
+        self.effects.insert(index, effect);
+    }
+
+    /// Remove the first effect named `name` and return it, or `None` if there
+    /// is no effect with that name.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn PostEffect>> {
+        let index = self.effects.iter().position(|e| e.name() == name)?;
+        Some(self.effects.remove(index))
+    }
+
+    /// Get the names of the effects in the order they run.
+    pub fn effect_names(&self) -> impl Iterator<Item = &str> {
+        self.effects.iter().map(|e| e.name())
+    }
+
+    /// Get the first effect of type `T` so its settings can be changed.
+    pub fn effect_mut<T: PostEffect>(&mut self) -> Option<&mut T> {
+        self.effects
+            .iter_mut()
+            .find_map(|e| (e.as_mut() as &mut dyn Any).downcast_mut::<T>())
+    }
+
+    /// Get the first effect of type `T`.
+    pub fn effect<T: PostEffect>(&self) -> Option<&T> {
+        self.effects
+            .iter()
+            .find_map(|e| (e.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    /// Recreate the offscreen textures to match a new render target size.
+    pub fn resize(&mut self, device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) {
+        (self.scene_target, self.ping_pong_targets) =
+            Self::create_targets(device, &self.input_layout, &self.sampler, surface_config);
+    }
+
+    /// Copy changed effect settings to the GPU.
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        for effect in &mut self.effects {
+            effect.prepare(queue);
+        }
+    }
+
+    /// Run each effect over the scene texture and write the result to
+    /// `output_view`, replacing its contents.
+    pub fn draw(
+        &self,
+        output_view: &wgpu::TextureView,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut input = &self.scene_target;
+
+        for (index, effect) in self.effects.iter().enumerate() {
+            let output = &self.ping_pong_targets[index % 2];
+            let mut render_pass = Self::begin_render_pass(command_encoder, &output.view);
+            effect.draw(&input.bind_group, &mut render_pass);
+
+            input = output;
+        }
+
+        let mut render_pass = Self::begin_render_pass(command_encoder, output_view);
+        render_pass.set_pipeline(&self.present_pipeline);
+        render_pass.set_bind_group(0, &input.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn begin_render_pass<'a>(
+        command_encoder: &'a mut wgpu::CommandEncoder,
+        output_view: &'a wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
+        command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post process render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // Every pixel is overwritten so the old contents are not
+                    // needed.
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    fn create_targets(
+        device: &wgpu::Device,
+        input_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        surface_config: &wgpu::SurfaceConfiguration,
+    ) -> (PostProcessTarget, [PostProcessTarget; 2]) {
+        let target =
+            |label| PostProcessTarget::new(device, input_layout, sampler, surface_config, label);
+
+        (
+            target("hdr scene texture"),
+            [
+                target("post process texture a"),
+                target("post process texture b"),
+            ],
+        )
+    }
+}
+
+/// Create the render pipeline for a post effect that writes to the stack's
+/// offscreen textures.
+///
+/// `shader_code` is appended to the shared post effect shader and must have a
+/// `fs_main` fragment entry point. `effect_layouts` are bound after the input
+/// texture, starting at group 1.
+pub fn create_effect_pipeline(
+    device: &wgpu::Device,
+    input_layout: &wgpu::BindGroupLayout,
+    label: &str,
+    shader_code: &str,
+    effect_layouts: &[&wgpu::BindGroupLayout],
+) -> wgpu::RenderPipeline {
+    create_effect_pipeline_for_target(
+        device,
+        input_layout,
+        label,
+        shader_code,
+        effect_layouts,
+        PostProcessStack::HDR_TEXTURE_FORMAT,
+        "fs_main",
+    )
+}
+
+/// Create the bind group layout for an effect's uniform buffer, which is bound
+/// at binding 0.
+pub fn create_effect_uniforms_layout(device: &wgpu::Device, label: &str) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
+
+fn create_effect_pipeline_for_target(
+    device: &wgpu::Device,
+    input_layout: &wgpu::BindGroupLayout,
+    label: &str,
+    shader_code: &str,
+    effect_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    fragment_entry_point: &str,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(format!("{SHARED_SHADER}\n{shader_code}").into()),
+    });
+
+    let bind_group_layouts = std::iter::once(input_layout)
+        .chain(effect_layouts.iter().copied())
+        .collect::<Vec<_>>();
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(
+            &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &bind_group_layouts,
+                push_constant_ranges: &[],
+            }),
+        ),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
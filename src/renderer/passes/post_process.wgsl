@@ -0,0 +1,34 @@
+//============================================================================//
+// Shared by every post effect. Each effect's shader is appended to this one
+// and only needs to provide a `fs_main` fragment entry point.
+//============================================================================//
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    /// Texture coordinates of the input texture, with (0, 0) at the top left.
+    @location(0) tex_coords: vec2<f32>,
+};
+
+/// Output of the previous effect, or the scene for the first effect.
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var input_sampler: sampler;
+
+/// Draws a single triangle that covers the entire render target.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var v_out: VertexOutput;
+    v_out.position_cs = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    v_out.tex_coords = uv;
+
+    return v_out;
+}
+
+/// Relative luminance of a linear color.
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,120 @@
+use glam::Vec3;
+
+use crate::renderer::gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup};
+
+use super::PostEffect;
+
+/// Adjusts the exposure, contrast, saturation and tint of the scene. This works
+/// best before the tonemap effect, while colors are still in HDR.
+pub struct ColorGradingEffect {
+    uniforms: GenericUniformBuffer<ColorGradingPackedUniforms>,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl ColorGradingEffect {
+    pub const NAME: &'static str = "color grading";
+    const SHADER: &'static str = include_str!("color_grading.wgsl");
+
+    /// Create a color grading effect that leaves colors unchanged until its
+    /// settings are changed.
+    pub fn new(device: &wgpu::Device, input_layout: &wgpu::BindGroupLayout) -> Self {
+        let uniforms_layout =
+            super::create_effect_uniforms_layout(device, "color grading effect layout");
+
+        Self {
+            uniforms: GenericUniformBuffer::new(
+                device,
+                Some("color grading effect uniforms"),
+                ColorGradingPackedUniforms {
+                    color_filter: Vec3::ONE.extend(1.0),
+                    exposure: 0.0,
+                    contrast: 1.0,
+                    saturation: 1.0,
+                    _padding: Default::default(),
+                },
+                &uniforms_layout,
+            ),
+            render_pipeline: super::create_effect_pipeline(
+                device,
+                input_layout,
+                "color grading effect",
+                Self::SHADER,
+                &[&uniforms_layout],
+            ),
+        }
+    }
+
+    /// Get the exposure adjustment in stops.
+    pub fn exposure(&self) -> f32 {
+        self.uniforms.values().exposure
+    }
+
+    /// Set the exposure adjustment in stops, where each stop doubles (or
+    /// halves when negative) the brightness of the scene.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.uniforms.values_mut().exposure = exposure;
+    }
+
+    /// Get the contrast around middle gray, where 1 is unchanged.
+    pub fn contrast(&self) -> f32 {
+        self.uniforms.values().contrast
+    }
+
+    /// Set the contrast around middle gray, where 1 is unchanged.
+    pub fn set_contrast(&mut self, contrast: f32) {
+        self.uniforms.values_mut().contrast = contrast.max(0.0);
+    }
+
+    /// Get the color saturation, where 0 is grayscale and 1 is unchanged.
+    pub fn saturation(&self) -> f32 {
+        self.uniforms.values().saturation
+    }
+
+    /// Set the color saturation, where 0 is grayscale and 1 is unchanged.
+    pub fn set_saturation(&mut self, saturation: f32) {
+        self.uniforms.values_mut().saturation = saturation.max(0.0);
+    }
+
+    /// Get the color that the scene is multiplied with to tint it.
+    pub fn color_filter(&self) -> Vec3 {
+        self.uniforms.values().color_filter.truncate()
+    }
+
+    /// Set a color that the scene is multiplied with to tint it.
+    pub fn set_color_filter(&mut self, color_filter: Vec3) {
+        self.uniforms.values_mut().color_filter = color_filter.extend(1.0);
+    }
+}
+
+impl PostEffect for ColorGradingEffect {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uniforms.is_dirty() {
+            self.uniforms.update_gpu(queue);
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        input_bind_group: &'a wgpu::BindGroup,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, input_bind_group, &[]);
+        render_pass.set_bind_group(1, self.uniforms.bind_group(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColorGradingPackedUniforms {
+    pub color_filter: glam::Vec4,
+    pub exposure: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    pub _padding: u32,
+}
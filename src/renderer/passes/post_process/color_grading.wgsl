@@ -0,0 +1,27 @@
+struct ColorGradingUniforms {
+    /// Color multiplied with the scene, .w is unused.
+    color_filter: vec4<f32>,
+    /// Exposure adjustment in stops.
+    exposure: f32,
+    /// Contrast around middle gray, where 1 is unchanged.
+    contrast: f32,
+    /// Color saturation, where 0 is grayscale and 1 is unchanged.
+    saturation: f32,
+};
+
+/// Linear middle gray, which contrast changes are centered on.
+const MIDDLE_GRAY: f32 = 0.18;
+
+@group(1) @binding(0)
+var<uniform> grading: ColorGradingUniforms;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = textureSample(input_texture, input_sampler, in.tex_coords).rgb;
+
+    color *= exp2(grading.exposure) * grading.color_filter.rgb;
+    color = (color - vec3<f32>(MIDDLE_GRAY)) * grading.contrast + vec3<f32>(MIDDLE_GRAY);
+    color = mix(vec3<f32>(luminance(color)), color, grading.saturation);
+
+    return vec4<f32>(max(color, vec3<f32>(0.0)), 1.0);
+}
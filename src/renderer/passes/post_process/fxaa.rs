@@ -0,0 +1,40 @@
+use super::PostEffect;
+
+/// Smooths jagged edges with fast approximate anti-aliasing. This expects
+/// colors in the [0, 1] range, so it should run after the tonemap effect.
+pub struct FxaaEffect {
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl FxaaEffect {
+    pub const NAME: &'static str = "fxaa";
+    const SHADER: &'static str = include_str!("fxaa.wgsl");
+
+    pub fn new(device: &wgpu::Device, input_layout: &wgpu::BindGroupLayout) -> Self {
+        Self {
+            render_pipeline: super::create_effect_pipeline(
+                device,
+                input_layout,
+                "fxaa effect",
+                Self::SHADER,
+                &[],
+            ),
+        }
+    }
+}
+
+impl PostEffect for FxaaEffect {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn draw<'a>(
+        &'a self,
+        input_bind_group: &'a wgpu::BindGroup,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, input_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
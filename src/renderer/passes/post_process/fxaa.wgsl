@@ -0,0 +1,71 @@
+// Fast approximate anti-aliasing, based on the FXAA console algorithm by
+// Timothy Lottes.
+//
+// Edges are found from the luminance of the texels around each pixel, and the
+// pixel is blurred along the edge rather than across it.
+
+/// Smallest amount that the edge direction is reduced by, which keeps the blur
+/// from growing too large in dark areas.
+const FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+/// How much the edge direction is reduced by relative to the local luminance.
+const FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+/// Maximum distance in texels that a pixel is blurred along an edge.
+const FXAA_SPAN_MAX: f32 = 8.0;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel_size = 1.0 / vec2<f32>(textureDimensions(input_texture));
+    let uv = in.tex_coords;
+
+    let rgb_nw = textureSample(input_texture, input_sampler, uv + vec2<f32>(-1.0, -1.0) * texel_size).rgb;
+    let rgb_ne = textureSample(input_texture, input_sampler, uv + vec2<f32>(1.0, -1.0) * texel_size).rgb;
+    let rgb_sw = textureSample(input_texture, input_sampler, uv + vec2<f32>(-1.0, 1.0) * texel_size).rgb;
+    let rgb_se = textureSample(input_texture, input_sampler, uv + vec2<f32>(1.0, 1.0) * texel_size).rgb;
+    let rgb_m = textureSample(input_texture, input_sampler, uv).rgb;
+
+    let luma_nw = luminance(rgb_nw);
+    let luma_ne = luminance(rgb_ne);
+    let luma_sw = luminance(rgb_sw);
+    let luma_se = luminance(rgb_se);
+    let luma_m = luminance(rgb_m);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // The direction along the edge, which is perpendicular to the luminance
+    // gradient.
+    var direction = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+
+    let direction_reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * (0.25 * FXAA_REDUCE_MUL),
+        FXAA_REDUCE_MIN,
+    );
+    let inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+
+    direction = clamp(
+        direction * inverse_direction_min,
+        vec2<f32>(-FXAA_SPAN_MAX),
+        vec2<f32>(FXAA_SPAN_MAX),
+    ) * texel_size;
+
+    // Blur with two samples close to the pixel, and with two more samples
+    // further along the edge.
+    let rgb_a = 0.5 * (
+        textureSample(input_texture, input_sampler, uv + direction * (1.0 / 3.0 - 0.5)).rgb
+        + textureSample(input_texture, input_sampler, uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let rgb_b = rgb_a * 0.5 + 0.25 * (
+        textureSample(input_texture, input_sampler, uv + direction * -0.5).rgb
+        + textureSample(input_texture, input_sampler, uv + direction * 0.5).rgb
+    );
+
+    // The wider blur went past the end of the edge if it picked up colors that
+    // are not in the pixel's neighborhood.
+    let luma_b = luminance(rgb_b);
+    let outside_edge = luma_b < luma_min || luma_b > luma_max;
+
+    return vec4<f32>(select(rgb_b, rgb_a, outside_edge), 1.0);
+}
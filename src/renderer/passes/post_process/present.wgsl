@@ -0,0 +1,35 @@
+/// Fragment entry point used when the render target converts colors to sRGB.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.tex_coords).rgb;
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0);
+}
+
+/// Fragment entry point used when the render target does not convert colors
+/// to sRGB itself.
+@fragment
+fn fs_main_linear_target(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.tex_coords).rgb;
+    return vec4<f32>(from_linear_rgb(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0))), 1.0);
+}
+
+// linear -> srgb
+// https://en.wikipedia.org/wiki/SRGB
+fn from_linear_color(x: f32) -> f32 {
+    var y = 12.92 * x;
+
+    if (x > 0.0031308) {
+        let a = 0.055;
+        y = (1.0 + a) * pow(x, 1.0/2.4) - a;
+    }
+
+    return y;
+}
+
+fn from_linear_rgb(c: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        from_linear_color(c.r),
+        from_linear_color(c.g),
+        from_linear_color(c.b),
+    );
+}
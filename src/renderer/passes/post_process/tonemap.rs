@@ -0,0 +1,107 @@
+use crate::renderer::gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup};
+
+use super::PostEffect;
+
+/// Curves that map the scene's HDR colors into the range a display can show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Filmic curve that keeps contrast in the midtones and rolls off bright
+    /// highlights.
+    #[default]
+    Aces,
+    /// Simple `c / (1 + c)` curve that never quite reaches white.
+    Reinhard,
+    /// Clamp colors to [0, 1] without a curve, which looks the same as drawing
+    /// the scene directly to the display.
+    Clamp,
+}
+
+impl Tonemapper {
+    /// Value of the matching `TONEMAPPER_*` constant in the tonemap shader.
+    fn shader_value(self) -> u32 {
+        match self {
+            Self::Aces => 0,
+            Self::Reinhard => 1,
+            Self::Clamp => 2,
+        }
+    }
+}
+
+/// Maps HDR colors into the [0, 1] range with a `Tonemapper` curve. Effects
+/// that expect displayable colors (eg FXAA) should run after this one.
+pub struct TonemapEffect {
+    tonemapper: Tonemapper,
+    uniforms: GenericUniformBuffer<TonemapPackedUniforms>,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl TonemapEffect {
+    pub const NAME: &'static str = "tonemap";
+    const SHADER: &'static str = include_str!("tonemap.wgsl");
+
+    pub fn new(device: &wgpu::Device, input_layout: &wgpu::BindGroupLayout) -> Self {
+        let uniforms_layout = super::create_effect_uniforms_layout(device, "tonemap effect layout");
+        let tonemapper = Tonemapper::default();
+
+        Self {
+            tonemapper,
+            uniforms: GenericUniformBuffer::new(
+                device,
+                Some("tonemap effect uniforms"),
+                TonemapPackedUniforms {
+                    tonemapper: tonemapper.shader_value(),
+                    _padding: Default::default(),
+                },
+                &uniforms_layout,
+            ),
+            render_pipeline: super::create_effect_pipeline(
+                device,
+                input_layout,
+                "tonemap effect",
+                Self::SHADER,
+                &[&uniforms_layout],
+            ),
+        }
+    }
+
+    /// Get the curve used to map HDR colors into the [0, 1] range.
+    pub fn tonemapper(&self) -> Tonemapper {
+        self.tonemapper
+    }
+
+    /// Set the curve used to map HDR colors into the [0, 1] range.
+    pub fn set_tonemapper(&mut self, tonemapper: Tonemapper) {
+        self.tonemapper = tonemapper;
+        self.uniforms.values_mut().tonemapper = tonemapper.shader_value();
+    }
+}
+
+impl PostEffect for TonemapEffect {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uniforms.is_dirty() {
+            self.uniforms.update_gpu(queue);
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        input_bind_group: &'a wgpu::BindGroup,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, input_bind_group, &[]);
+        render_pass.set_bind_group(1, self.uniforms.bind_group(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapPackedUniforms {
+    pub tonemapper: u32,
+    pub _padding: [u32; 3],
+}
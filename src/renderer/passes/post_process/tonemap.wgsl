@@ -0,0 +1,43 @@
+struct TonemapUniforms {
+    /// Which `TONEMAPPER_*` curve maps HDR colors into the displayable range.
+    tonemapper: u32,
+};
+
+const TONEMAPPER_ACES: u32 = 0u;
+const TONEMAPPER_REINHARD: u32 = 1u;
+const TONEMAPPER_CLAMP: u32 = 2u;
+
+@group(1) @binding(0)
+var<uniform> tonemap: TonemapUniforms;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = max(textureSample(input_texture, input_sampler, in.tex_coords).rgb, vec3<f32>(0.0));
+    var color: vec3<f32>;
+
+    switch tonemap.tonemapper {
+        case TONEMAPPER_ACES: {
+            color = aces_film(hdr);
+        }
+        case TONEMAPPER_REINHARD: {
+            color = hdr / (hdr + vec3<f32>(1.0));
+        }
+        default: {
+            color = clamp(hdr, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+
+    return vec4<f32>(color, 1.0);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+// https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+fn aces_film(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
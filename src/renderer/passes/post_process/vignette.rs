@@ -0,0 +1,103 @@
+use crate::renderer::gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup};
+
+use super::PostEffect;
+
+/// Darkens the edges of the screen.
+pub struct VignetteEffect {
+    uniforms: GenericUniformBuffer<VignettePackedUniforms>,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl VignetteEffect {
+    pub const NAME: &'static str = "vignette";
+    pub const DEFAULT_INTENSITY: f32 = 0.5;
+    pub const DEFAULT_RADIUS: f32 = 1.0;
+    pub const DEFAULT_SOFTNESS: f32 = 0.6;
+    const SHADER: &'static str = include_str!("vignette.wgsl");
+
+    pub fn new(device: &wgpu::Device, input_layout: &wgpu::BindGroupLayout) -> Self {
+        let uniforms_layout =
+            super::create_effect_uniforms_layout(device, "vignette effect layout");
+
+        Self {
+            uniforms: GenericUniformBuffer::new(
+                device,
+                Some("vignette effect uniforms"),
+                VignettePackedUniforms {
+                    intensity: Self::DEFAULT_INTENSITY,
+                    radius: Self::DEFAULT_RADIUS,
+                    softness: Self::DEFAULT_SOFTNESS,
+                    _padding: Default::default(),
+                },
+                &uniforms_layout,
+            ),
+            render_pipeline: super::create_effect_pipeline(
+                device,
+                input_layout,
+                "vignette effect",
+                Self::SHADER,
+                &[&uniforms_layout],
+            ),
+        }
+    }
+
+    /// Get how much the edges of the screen are darkened, from 0 (not at all)
+    /// to 1 (black).
+    pub fn intensity(&self) -> f32 {
+        self.uniforms.values().intensity
+    }
+
+    /// Set how much the edges of the screen are darkened, from 0 (not at all)
+    /// to 1 (black).
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.uniforms.values_mut().intensity = intensity.clamp(0.0, 1.0);
+    }
+
+    /// Get the distance from the center of the screen where the vignette is
+    /// strongest, and the distance over which it fades in before that.
+    pub fn radius(&self) -> (f32, f32) {
+        let uniforms = self.uniforms.values();
+        (uniforms.radius, uniforms.softness)
+    }
+
+    /// Set the distance from the center of the screen where the vignette is
+    /// strongest, with 1 being the corners, and the distance over which it
+    /// fades in before that.
+    pub fn set_radius(&mut self, radius: f32, softness: f32) {
+        let uniforms = self.uniforms.values_mut();
+        uniforms.radius = radius;
+        uniforms.softness = softness.max(f32::EPSILON);
+    }
+}
+
+impl PostEffect for VignetteEffect {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn prepare(&mut self, queue: &wgpu::Queue) {
+        if self.uniforms.is_dirty() {
+            self.uniforms.update_gpu(queue);
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        input_bind_group: &'a wgpu::BindGroup,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, input_bind_group, &[]);
+        render_pass.set_bind_group(1, self.uniforms.bind_group(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct VignettePackedUniforms {
+    pub intensity: f32,
+    pub radius: f32,
+    pub softness: f32,
+    pub _padding: u32,
+}
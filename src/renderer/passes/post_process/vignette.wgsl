@@ -0,0 +1,24 @@
+struct VignetteUniforms {
+    /// How much the edges of the screen are darkened, from 0 (not at all) to 1
+    /// (black).
+    intensity: f32,
+    /// Distance from the center of the screen where the vignette is strongest,
+    /// with 1 being the corners.
+    radius: f32,
+    /// Distance over which the vignette fades in before `radius`.
+    softness: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> vignette: VignetteUniforms;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(input_texture, input_sampler, in.tex_coords).rgb;
+
+    // Scale the distance so the corners are 1 unit from the center.
+    let distance = length(in.tex_coords - vec2<f32>(0.5)) * sqrt(2.0);
+    let edge = smoothstep(vignette.radius - vignette.softness, vignette.radius, distance);
+
+    return vec4<f32>(color * (1.0 - vignette.intensity * edge), 1.0);
+}
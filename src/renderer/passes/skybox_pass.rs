@@ -0,0 +1,252 @@
+use std::rc::Rc;
+
+use glam::{Mat3, Mat4};
+
+use crate::{
+    camera::Camera,
+    renderer::{
+        gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup},
+        textures,
+        viewport::Viewport,
+    },
+};
+
+/// Draws a cube map behind everything else in the scene.
+///
+/// The skybox is drawn as a single triangle covering the viewport on the far
+/// plane after the main pass, so only the pixels that no model was drawn over
+/// sample the cube map.
+pub struct SkyboxPass {
+    render_pipeline: wgpu::RenderPipeline,
+    view_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Uniforms for each viewport drawn in a frame.
+    view_uniforms: Vec<GenericUniformBuffer<SkyboxPackedUniforms>>,
+    /// The skybox drawn this frame and the bind group for sampling it.
+    skybox: Option<(Rc<textures::Texture>, wgpu::BindGroup)>,
+}
+
+impl SkyboxPass {
+    const SHADER: &'static str = include_str!("skybox_pass.wgsl");
+
+    /// Create a new skybox pass. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device) -> Self {
+        let view_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("skybox view layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("skybox texture layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("skybox pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("skybox pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("skybox pass pipeline layout"),
+                    bind_group_layouts: &[&view_layout, &texture_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            // The triangle lies on the far plane, which the depth buffer is
+            // cleared to, so it only passes where nothing else was drawn.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: super::DepthPass::DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: super::PostProcessStack::HDR_TEXTURE_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            render_pipeline,
+            view_layout,
+            texture_layout,
+            sampler: textures::create_default_sampler(device),
+            view_uniforms: Vec::new(),
+            skybox: None,
+        }
+    }
+
+    /// Prepare to draw `skybox` as seen by each of `cameras`, or nothing if
+    /// `skybox` is `None`. The skybox must be a cube map texture.
+    pub fn prepare<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        skybox: Option<&Rc<textures::Texture>>,
+        cameras: impl Iterator<Item = &'a Camera>,
+    ) {
+        // Only rebuild the bind group when the skybox texture changes.
+        self.skybox = match (skybox, self.skybox.take()) {
+            (None, _) => None,
+            (Some(texture), Some(current)) if Rc::ptr_eq(texture, &current.0) => Some(current),
+            (Some(texture), _) => Some((texture.clone(), self.create_bind_group(device, texture))),
+        };
+
+        if self.skybox.is_none() {
+            return;
+        }
+
+        for (index, camera) in cameras.enumerate() {
+            if index == self.view_uniforms.len() {
+                self.view_uniforms.push(GenericUniformBuffer::new(
+                    device,
+                    Some("skybox view uniforms"),
+                    SkyboxPackedUniforms::default(),
+                    &self.view_layout,
+                ));
+            }
+
+            // The skybox is infinitely far away, so moving the camera does not
+            // change how it looks.
+            let view_rotation = Mat4::from_mat3(Mat3::from_mat4(camera.view_matrix()));
+            let view_uniforms = &mut self.view_uniforms[index];
+
+            view_uniforms.values_mut().inverse_view_projection =
+                (camera.projection_matrix() * view_rotation).inverse();
+            view_uniforms.update_gpu(queue);
+        }
+    }
+
+    /// Returns true if there is a skybox to draw this frame.
+    pub fn has_skybox(&self) -> bool {
+        self.skybox.is_some()
+    }
+
+    /// Draw the skybox into `viewport`, as seen by the camera at `view_index`
+    /// in the cameras given to `prepare`.
+    pub fn draw(
+        &self,
+        output_view: &wgpu::TextureView,
+        depth_stencil_view: &wgpu::TextureView,
+        view_index: usize,
+        viewport: &Viewport,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let Some((_, texture_bind_group)) = &self.skybox else {
+            return;
+        };
+
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("skybox render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_stencil_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        viewport.apply(&mut render_pass);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, self.view_uniforms[view_index].bind_group(), &[]);
+        render_pass.set_bind_group(1, texture_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_bind_group(&self, device: &wgpu::Device, texture: &wgpu::Texture) -> wgpu::BindGroup {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("skybox view"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("skybox texture bind group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyboxPackedUniforms {
+    pub inverse_view_projection: Mat4,
+}
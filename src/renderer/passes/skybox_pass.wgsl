@@ -0,0 +1,43 @@
+struct SkyboxUniforms {
+    /// Inverse of the camera's view projection without its translation, which
+    /// turns a clip space position into a world space view direction.
+    inverse_view_projection: mat4x4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    /// Clip space position, interpolated across the screen.
+    @location(0) position_ndc: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> skybox: SkyboxUniforms;
+
+@group(1) @binding(0)
+var skybox_texture: texture_cube<f32>;
+
+@group(1) @binding(1)
+var skybox_sampler: sampler;
+
+/// Draws a single triangle that covers the entire viewport on the far plane,
+/// so the skybox is only visible where no model was drawn.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let position = vec2<f32>(
+        f32(i32(vertex_index & 1u) * 4 - 1),
+        f32(i32(vertex_index >> 1u) * 4 - 1),
+    );
+
+    var v_out: VertexOutput;
+    v_out.position_cs = vec4<f32>(position, 1.0, 1.0);
+    v_out.position_ndc = position;
+
+    return v_out;
+}
+
+@fragment
+fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = skybox.inverse_view_projection * vec4<f32>(v_in.position_ndc, 1.0, 1.0);
+    let color = textureSample(skybox_texture, skybox_sampler, direction.xyz / direction.w);
+    return vec4<f32>(color.rgb, 1.0);
+}
@@ -0,0 +1,305 @@
+use std::{ops::Range, rc::Rc};
+
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::renderer::{
+    gpu_buffers::{DynamicGpuBuffer, InstanceBuffer},
+    sprites::Sprite,
+    textures,
+};
+
+/// Draws the scene's sprites as screen space quads over the render target.
+///
+/// Sprites are positioned in pixels with an orthographic projection that
+/// matches the size of the render target. They are drawn in the order they are
+/// listed, in batches of consecutive sprites that share a texture, so sprites
+/// drawn from one atlas texture are drawn with a single instanced draw.
+pub struct SpritePass {
+    render_pipeline: wgpu::RenderPipeline,
+    /// Projection from pixels into clip space, copied each frame in `prepare`.
+    uniforms_buffer: wgpu::Buffer,
+    uniforms_bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Bind groups for textures drawn in recent frames. Each entry holds on to
+    /// its texture, and is dropped once nothing else holds the texture.
+    textures: Vec<(Rc<textures::Texture>, wgpu::BindGroup)>,
+    instances: InstanceBuffer<SpritePackedInstance>,
+    /// Ranges of instances to draw this frame and the index of the texture
+    /// each range is drawn with.
+    batches: Vec<(Range<u32>, usize)>,
+}
+
+impl SpritePass {
+    const SHADER: &'static str = include_str!("sprite_pass.wgsl");
+    /// Number of vertices drawn for each sprite quad.
+    const VERTICES_PER_QUAD: u32 = 6;
+
+    /// Create a new sprite pass that draws into render targets of
+    /// `target_format`. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let uniforms_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite uniforms layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("sprite pass uniforms"),
+            contents: bytemuck::bytes_of(&Mat4::IDENTITY),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniforms_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sprite uniforms bind group"),
+            layout: &uniforms_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms_buffer.as_entire_binding(),
+            }],
+        });
+
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite texture layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("sprite pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sprite pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sprite pass pipeline layout"),
+                    bind_group_layouts: &[&uniforms_layout, &texture_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[SpritePackedInstance::vertex_layout()],
+            },
+            // Flipped sprites have the opposite winding, so nothing is culled.
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            render_pipeline,
+            uniforms_buffer,
+            uniforms_bind_group,
+            texture_layout,
+            sampler: textures::create_default_sampler(device),
+            textures: Vec::new(),
+            instances: InstanceBuffer::new(
+                device,
+                Some("sprite instance buffer"),
+                vec![Default::default()],
+            ),
+            batches: Vec::new(),
+        }
+    }
+
+    /// Prepare `sprites` for drawing into a render target that is
+    /// `target_width` by `target_height` pixels, and copy them to the GPU.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sprites: &[Sprite],
+        target_width: u32,
+        target_height: u32,
+    ) {
+        self.batches.clear();
+        self.textures
+            .retain(|(texture, _)| Rc::strong_count(texture) > 1);
+
+        if sprites.is_empty() {
+            return;
+        }
+
+        for (index, sprite) in sprites.iter().enumerate() {
+            let texture_index = self.texture_index(device, &sprite.texture);
+            let index = index as u32;
+
+            // Extend the previous batch when the texture does not change.
+            match self.batches.last_mut() {
+                Some((instances, batch_texture)) if *batch_texture == texture_index => {
+                    instances.end = index + 1;
+                }
+                _ => self.batches.push((index..index + 1, texture_index)),
+            }
+        }
+
+        self.instances.set_instances(
+            device,
+            sprites.iter().map(|sprite| SpritePackedInstance {
+                position: sprite.position.to_array(),
+                size: sprite.size.to_array(),
+                uv_min: sprite.uv_min.to_array(),
+                uv_max: sprite.uv_max.to_array(),
+                color: sprite.color.to_array(),
+            }),
+        );
+        self.instances.update_gpu(queue);
+
+        // Pixels are measured from the top left corner with Y pointing down.
+        let projection = Mat4::orthographic_rh(
+            0.0,
+            target_width as f32,
+            target_height as f32,
+            0.0,
+            -1.0,
+            1.0,
+        );
+        queue.write_buffer(&self.uniforms_buffer, 0, bytemuck::bytes_of(&projection));
+    }
+
+    /// Returns true if there are sprites to draw this frame.
+    pub fn has_sprites(&self) -> bool {
+        !self.batches.is_empty()
+    }
+
+    /// Draw the sprites prepared for this frame over `output_view`.
+    pub fn draw(
+        &self,
+        output_view: &wgpu::TextureView,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("sprite render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instances.gpu_buffer_slice(..));
+
+        for (instances, texture_index) in &self.batches {
+            render_pass.set_bind_group(1, &self.textures[*texture_index].1, &[]);
+            render_pass.draw(0..Self::VERTICES_PER_QUAD, instances.clone());
+        }
+    }
+
+    /// Get the index of the bind group for `texture`, creating it if the
+    /// texture has not been drawn recently.
+    fn texture_index(&mut self, device: &wgpu::Device, texture: &Rc<textures::Texture>) -> usize {
+        if let Some(index) = self
+            .textures
+            .iter()
+            .position(|(t, _)| Rc::ptr_eq(t, texture))
+        {
+            return index;
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sprite texture bind group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        self.textures.push((texture.clone(), bind_group));
+        self.textures.len() - 1
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpritePackedInstance {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl SpritePackedInstance {
+    fn vertex_layout() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x2,
+            2 => Float32x2,
+            3 => Float32x2,
+            4 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpritePackedInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
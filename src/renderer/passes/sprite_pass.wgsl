@@ -0,0 +1,54 @@
+struct SpriteUniforms {
+    /// Orthographic projection from pixels into clip space.
+    projection: mat4x4<f32>,
+}
+
+struct InstanceInput {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) uv_min: vec2<f32>,
+    @location(3) uv_max: vec2<f32>,
+    @location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: SpriteUniforms;
+
+@group(1) @binding(0)
+var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var sprite_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    // The two triangles making up the quad, with corners ranging from the top
+    // left (0, 0) to the bottom right (1, 1).
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+    );
+    let corner = corners[vertex_index];
+    let position_px = instance.position + corner * instance.size;
+
+    var v: VertexOutput;
+    v.position_cs = uniforms.projection * vec4<f32>(position_px, 0.0, 1.0);
+    v.tex_coords = mix(instance.uv_min, instance.uv_max, corner);
+    v.color = instance.color;
+
+    return v;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(sprite_texture, sprite_sampler, in.tex_coords) * in.color;
+}
@@ -0,0 +1,34 @@
+/// Half the width of the square of texels averaged around each pixel.
+const BLUR_RADIUS: i32 = 2;
+
+@group(0) @binding(0)
+var occlusion_texture: texture_2d<f32>;
+
+/// Draws a single triangle that covers the entire viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let position = vec2<f32>(
+        f32(i32(vertex_index & 1u) * 4 - 1),
+        f32(i32(vertex_index >> 1u) * 4 - 1),
+    );
+
+    return vec4<f32>(position, 0.0, 1.0);
+}
+
+/// Average the occlusion around each pixel to remove the noise from rotating
+/// the SSAO kernel.
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let max_pixel = vec2<i32>(textureDimensions(occlusion_texture)) - 1;
+    var occlusion = 0.0;
+
+    for (var y = -BLUR_RADIUS; y < BLUR_RADIUS; y++) {
+        for (var x = -BLUR_RADIUS; x < BLUR_RADIUS; x++) {
+            let texel = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), max_pixel);
+            occlusion += textureLoad(occlusion_texture, texel, 0).x;
+        }
+    }
+
+    return vec4<f32>(occlusion / f32(4 * BLUR_RADIUS * BLUR_RADIUS));
+}
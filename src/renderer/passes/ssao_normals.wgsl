@@ -0,0 +1,44 @@
+struct PerFrameUniforms {
+    view_projection: mat4x4<f32>,
+};
+
+struct PerModelUniforms {
+    local_to_world: mat4x4<f32>,
+    world_to_local: mat4x4<f32>,
+};
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    @location(0) normal_ws: vec3<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> per_frame: PerFrameUniforms;
+
+@group(1) @binding(0)
+var<uniform> per_model: PerModelUniforms;
+
+@vertex
+fn vs_main(v_in: VertexInput) -> VertexOutput {
+    var v_out: VertexOutput;
+
+    v_out.position_cs = per_frame.view_projection
+        * per_model.local_to_world
+        * vec4<f32>(v_in.position, 1.0);
+    v_out.normal_ws = (transpose(per_model.world_to_local) * vec4<f32>(v_in.normal, 0.0)).xyz;
+
+    return v_out;
+}
+
+/// Store the world space normal of the side facing the camera. `w` marks the
+/// pixel as covered by a model.
+@fragment
+fn fs_main(v_in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    let normal = normalize(v_in.normal_ws) * select(-1.0, 1.0, front_facing);
+    return vec4<f32>(normal, 1.0);
+}
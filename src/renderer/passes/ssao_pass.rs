@@ -0,0 +1,672 @@
+use glam::{Mat4, Vec3, Vec4};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera,
+    renderer::{
+        gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup},
+        models::{self, DrawModel, Model},
+        passes::DepthPass,
+        render_graph::TransientTextureDesc,
+        shaders::{BindGroupLayouts, PerFrameShaderVals, PerModelBinding, VertexLayout},
+        viewport::Viewport,
+    },
+};
+
+/// Tunable values for screen space ambient occlusion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsaoSettings {
+    /// Draw ambient occlusion each frame. Ambient lighting is not darkened
+    /// when this is false.
+    pub enabled: bool,
+    /// Number of samples taken around each pixel, up to
+    /// `SsaoPass::MAX_KERNEL_SIZE`. More samples give smoother occlusion at a
+    /// higher cost.
+    pub kernel_size: u32,
+    /// Distance in world units around each pixel that is checked for
+    /// occluding geometry.
+    pub radius: f32,
+    /// Depth difference in world units that is ignored when checking for
+    /// occluders, which stops flat surfaces from occluding themselves.
+    pub bias: f32,
+    /// How much fully occluded pixels are darkened, from 0 (not at all) to 1
+    /// (no ambient light).
+    pub intensity: f32,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            kernel_size: 16,
+            radius: 0.5,
+            bias: 0.025,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Screen sized textures drawn by the SSAO pass.
+pub struct SsaoTargets {
+    /// World space normals of the nearest model at each pixel. `w` is zero for
+    /// pixels that no model was drawn over.
+    normal_view: wgpu::TextureView,
+    /// Blurred occlusion sampled by the lit shaders.
+    occlusion_view: wgpu::TextureView,
+    /// Depth and normal textures read when drawing occlusion.
+    occlusion_bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+    /// Depth buffer view that `occlusion_bind_group` reads from.
+    depth_view_id: wgpu::Id<wgpu::TextureView>,
+}
+
+/// Darkens ambient lighting in creases and corners that are partly hidden
+/// from the rest of the scene.
+///
+/// Models are first drawn into the depth buffer and a normal texture. Every
+/// pixel then checks a hemisphere of points around itself against the depth
+/// buffer, and the fraction of points that are behind other geometry is its
+/// occlusion. The occlusion is blurred to hide the per pixel noise before the
+/// lit shaders sample it.
+pub struct SsaoPass {
+    settings: SsaoSettings,
+    /// Sample points in a unit hemisphere around +Z for `settings`.
+    kernel: [Vec4; Self::MAX_KERNEL_SIZE],
+    normals_pipeline: wgpu::RenderPipeline,
+    occlusion_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    view_layout: wgpu::BindGroupLayout,
+    occlusion_layout: wgpu::BindGroupLayout,
+    blur_layout: wgpu::BindGroupLayout,
+    /// Uniforms for each viewport drawn in a frame.
+    view_uniforms: Vec<GenericUniformBuffer<SsaoPackedUniforms>>,
+    /// Allocated while ambient occlusion is enabled.
+    targets: Option<SsaoTargets>,
+    /// Unoccluded texture sampled by the lit shaders while ambient occlusion
+    /// is disabled.
+    empty_occlusion_view: wgpu::TextureView,
+}
+
+impl SsaoPass {
+    /// The largest `SsaoSettings::kernel_size` the SSAO shader supports.
+    pub const MAX_KERNEL_SIZE: usize = 64;
+    pub const NORMAL_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    pub const OCCLUSION_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+    const NORMALS_SHADER: &'static str = include_str!("ssao_normals.wgsl");
+    const OCCLUSION_SHADER: &'static str = include_str!("ssao_pass.wgsl");
+    const BLUR_SHADER: &'static str = include_str!("ssao_blur.wgsl");
+
+    /// Create a new SSAO pass. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, layouts: &BindGroupLayouts) -> Self {
+        let view_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ssao view layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let occlusion_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ssao occlusion layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    // Depth is read as a float texture because depth textures
+                    // can only be sampled with a sampler on the GL backend.
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let blur_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ssao blur layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        // Models are drawn with both sides visible, since materials can turn
+        // off culling and the normal of the nearest side is what matters.
+        let normals_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ssao normals shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::NORMALS_SHADER.into()),
+        });
+
+        let normals_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ssao normals render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("ssao normals pipeline layout"),
+                    bind_group_layouts: &[&layouts.per_frame_layout, &layouts.per_model_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &normals_shader,
+                entry_point: "vs_main",
+                buffers: &[models::Vertex::vertex_buffer_layout()],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthPass::DEPTH_TEXTURE_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &normals_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Self::NORMAL_TEXTURE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let occlusion_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            "ssao occlusion",
+            Self::OCCLUSION_SHADER,
+            &[&view_layout, &occlusion_layout],
+        );
+        let blur_pipeline = Self::create_fullscreen_pipeline(
+            device,
+            "ssao blur",
+            Self::BLUR_SHADER,
+            &[&blur_layout],
+        );
+
+        let empty_occlusion_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("empty ssao occlusion texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::OCCLUSION_TEXTURE_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &[255],
+        );
+
+        let settings = SsaoSettings::default();
+
+        Self {
+            settings,
+            kernel: Self::kernel(settings.kernel_size),
+            normals_pipeline,
+            occlusion_pipeline,
+            blur_pipeline,
+            view_layout,
+            occlusion_layout,
+            blur_layout,
+            view_uniforms: Vec::new(),
+            targets: None,
+            empty_occlusion_view: empty_occlusion_texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        }
+    }
+
+    /// Get the current ambient occlusion settings.
+    pub fn settings(&self) -> SsaoSettings {
+        self.settings
+    }
+
+    /// Change the ambient occlusion settings, which take effect on the next
+    /// frame.
+    pub fn set_settings(&mut self, settings: SsaoSettings) {
+        let settings = SsaoSettings {
+            kernel_size: settings.kernel_size.clamp(1, Self::MAX_KERNEL_SIZE as u32),
+            radius: settings.radius.max(f32::EPSILON),
+            bias: settings.bias.max(0.0),
+            intensity: settings.intensity.clamp(0.0, 1.0),
+            ..settings
+        };
+
+        if settings.kernel_size != self.settings.kernel_size {
+            self.kernel = Self::kernel(settings.kernel_size);
+        }
+
+        self.settings = settings;
+    }
+
+    /// Returns true if ambient occlusion is drawn this frame.
+    pub fn is_enabled(&self) -> bool {
+        self.settings.enabled
+    }
+
+    /// Get the occlusion texture sampled by the lit shaders, where 1 is
+    /// unoccluded. This is a single unoccluded texel when ambient occlusion is
+    /// disabled.
+    pub fn occlusion_view(&self) -> &wgpu::TextureView {
+        self.targets
+            .as_ref()
+            .map_or(&self.empty_occlusion_view, |t| &t.occlusion_view)
+    }
+
+    /// Exchange the pass's textures with `targets`, so that a render target
+    /// with a different size than the window keeps its own textures rather
+    /// than the pass recreating them each time it switches between the two.
+    pub fn swap_targets(&mut self, targets: &mut Option<SsaoTargets>) {
+        std::mem::swap(&mut self.targets, targets);
+    }
+
+    /// Prepare the textures and per-viewport uniforms for drawing ambient
+    /// occlusion with `depth_pass`'s depth buffer. Textures are freed while
+    /// ambient occlusion is disabled.
+    pub fn prepare<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_config: &wgpu::SurfaceConfiguration,
+        depth_pass: &DepthPass,
+        views: impl Iterator<Item = (&'a Camera, &'a Viewport)>,
+    ) {
+        if !self.settings.enabled {
+            self.targets = None;
+            return;
+        }
+
+        let size = (surface_config.width.max(1), surface_config.height.max(1));
+        let depth_view = depth_pass.depth_sample_view();
+
+        if !self
+            .targets
+            .as_ref()
+            .is_some_and(|t| t.size == size && t.depth_view_id == depth_view.global_id())
+        {
+            self.targets = Some(self.create_targets(device, size, depth_view));
+        }
+
+        for (view_index, (camera, viewport)) in views.enumerate() {
+            if view_index == self.view_uniforms.len() {
+                self.view_uniforms.push(GenericUniformBuffer::new(
+                    device,
+                    Some("ssao view uniforms"),
+                    bytemuck::Zeroable::zeroed(),
+                    &self.view_layout,
+                ));
+            }
+
+            *self.view_uniforms[view_index].values_mut() = SsaoPackedUniforms {
+                projection: camera.projection_matrix(),
+                inverse_projection: camera.inverse_projection_matrix(),
+                view: camera.view_matrix(),
+                viewport: Vec4::new(
+                    viewport.x as f32,
+                    viewport.y as f32,
+                    viewport.width as f32,
+                    viewport.height as f32,
+                ),
+                kernel_size: self.settings.kernel_size,
+                radius: self.settings.radius,
+                bias: self.settings.bias,
+                intensity: self.settings.intensity,
+                kernel: self.kernel,
+            };
+            self.view_uniforms[view_index].update_gpu(queue);
+        }
+    }
+
+    /// Get the description of the texture that noisy occlusion is drawn into
+    /// before it is blurred, for a `width` by `height` render target. The
+    /// texture is only needed while drawing, so it is allocated by the render
+    /// graph.
+    pub fn raw_occlusion_desc(width: u32, height: u32) -> TransientTextureDesc {
+        TransientTextureDesc {
+            label: "ssao raw occlusion texture",
+            width,
+            height,
+            format: Self::OCCLUSION_TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    /// Draw noisy ambient occlusion for each viewport into
+    /// `raw_occlusion_view`, occluded by the models visible in that viewport.
+    /// `depth_view` is the depth buffer that models are drawn into, which the
+    /// main pass must clear afterwards. Call `blur` next to smooth it out.
+    pub fn draw<'a, M>(
+        &'a self,
+        depth_view: &wgpu::TextureView,
+        raw_occlusion_view: &wgpu::TextureView,
+        views: impl Iterator<Item = (&'a Viewport, &'a PerFrameShaderVals, M)> + Clone,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) where
+        M: Iterator<Item = (&'a Model, PerModelBinding<'a>)>,
+    {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+
+        // Draw the depth and normals of the nearest model at each pixel.
+        {
+            let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ssao normals render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.normal_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            render_pass.set_pipeline(&self.normals_pipeline);
+
+            for (viewport, per_frame_uniforms, models) in views.clone() {
+                viewport.apply(&mut render_pass);
+                render_pass.set_bind_group(0, per_frame_uniforms.bind_group(), &[]);
+
+                for (model, model_sv) in models {
+                    render_pass.draw_model_geometry(model, model_sv);
+                }
+            }
+        }
+
+        // Find how occluded each pixel is.
+        {
+            let mut render_pass = Self::begin_occlusion_pass(
+                command_encoder,
+                "ssao occlusion render pass",
+                raw_occlusion_view,
+            );
+
+            render_pass.set_pipeline(&self.occlusion_pipeline);
+            render_pass.set_bind_group(1, &targets.occlusion_bind_group, &[]);
+
+            for ((viewport, _, _), view_uniforms) in views.clone().zip(&self.view_uniforms) {
+                viewport.apply(&mut render_pass);
+                render_pass.set_bind_group(0, view_uniforms.bind_group(), &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    /// Blur away the noise in `raw_occlusion_view` from rotating the kernel at
+    /// each pixel, and write the result to the occlusion texture sampled by
+    /// the lit shaders.
+    pub fn blur<'a>(
+        &self,
+        device: &wgpu::Device,
+        raw_occlusion_view: &wgpu::TextureView,
+        viewports: impl Iterator<Item = &'a Viewport>,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+
+        let blur_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssao blur bind group"),
+            layout: &self.blur_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(raw_occlusion_view),
+            }],
+        });
+
+        let mut render_pass = Self::begin_occlusion_pass(
+            command_encoder,
+            "ssao blur render pass",
+            &targets.occlusion_view,
+        );
+
+        render_pass.set_pipeline(&self.blur_pipeline);
+        render_pass.set_bind_group(0, &blur_bind_group, &[]);
+
+        for viewport in viewports {
+            viewport.apply(&mut render_pass);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Generate `kernel_size` sample points in a unit hemisphere around +Z.
+    /// Points are spread with low discrepancy sequences rather than randomly,
+    /// and are packed closer to the center so nearby geometry counts for more.
+    fn kernel(kernel_size: u32) -> [Vec4; Self::MAX_KERNEL_SIZE] {
+        let mut kernel = [Vec4::ZERO; Self::MAX_KERNEL_SIZE];
+
+        for (i, sample) in kernel.iter_mut().take(kernel_size as usize).enumerate() {
+            let phi = std::f32::consts::TAU * halton(i as u32 + 1, 2);
+            // Keep points off the surface's tangent plane, where every sample
+            // would be occluded by the surface itself.
+            let z = 0.1 + 0.9 * halton(i as u32 + 1, 3);
+            let r = (1.0 - z * z).sqrt();
+
+            let t = (i as f32 + halton(i as u32 + 1, 5)) / kernel_size as f32;
+            let scale = 0.1 + 0.9 * t * t;
+
+            *sample = (Vec3::new(r * phi.cos(), r * phi.sin(), z) * scale).extend(0.0);
+        }
+
+        kernel
+    }
+
+    fn create_targets(
+        &self,
+        device: &wgpu::Device,
+        size: (u32, u32),
+        depth_view: &wgpu::TextureView,
+    ) -> SsaoTargets {
+        let create_view = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.0,
+                        height: size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+
+        let normal_view = create_view("ssao normal texture", Self::NORMAL_TEXTURE_FORMAT);
+        let occlusion_view = create_view("ssao occlusion texture", Self::OCCLUSION_TEXTURE_FORMAT);
+
+        let occlusion_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ssao occlusion bind group"),
+            layout: &self.occlusion_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&normal_view),
+                },
+            ],
+        });
+
+        SsaoTargets {
+            normal_view,
+            occlusion_view,
+            occlusion_bind_group,
+            size,
+            depth_view_id: depth_view.global_id(),
+        }
+    }
+
+    fn create_fullscreen_pipeline(
+        device: &wgpu::Device,
+        label: &str,
+        shader_code: &str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{label} shader")),
+            source: wgpu::ShaderSource::Wgsl(shader_code.into()),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{label} render pipeline")),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(&format!("{label} pipeline layout")),
+                    bind_group_layouts,
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Self::OCCLUSION_TEXTURE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+    }
+
+    /// Begin a render pass into an occlusion texture, which is cleared to
+    /// unoccluded outside of the viewports.
+    fn begin_occlusion_pass<'e>(
+        command_encoder: &'e mut wgpu::CommandEncoder,
+        label: &str,
+        view: &'e wgpu::TextureView,
+    ) -> wgpu::RenderPass<'e> {
+        command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+}
+
+/// Get the `index`th value of the Halton sequence with `base`, which fills
+/// [0, 1) evenly without repeating.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoPackedUniforms {
+    pub projection: Mat4,
+    pub inverse_projection: Mat4,
+    pub view: Mat4,
+    /// Position and size of the viewport in pixels.
+    pub viewport: Vec4,
+    pub kernel_size: u32,
+    pub radius: f32,
+    pub bias: f32,
+    pub intensity: f32,
+    pub kernel: [Vec4; SsaoPass::MAX_KERNEL_SIZE],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_samples_stay_inside_the_hemisphere() {
+        let kernel = SsaoPass::kernel(16);
+
+        for sample in &kernel[..16] {
+            assert!(sample.z > 0.0);
+            assert!(sample.truncate().length() <= 1.0);
+        }
+
+        assert!(kernel[16..].iter().all(|sample| *sample == Vec4::ZERO));
+    }
+}
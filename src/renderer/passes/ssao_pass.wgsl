@@ -0,0 +1,122 @@
+const MAX_KERNEL_SIZE: u32 = 64u;
+
+struct SsaoUniforms {
+    /// Camera projection, which moves view space points into clip space.
+    projection: mat4x4<f32>,
+    /// Turns clip space positions back into view space.
+    inverse_projection: mat4x4<f32>,
+    /// Camera view matrix, which turns world space normals into view space.
+    view: mat4x4<f32>,
+    /// Position (`xy`) and size (`zw`) of the viewport in pixels.
+    viewport: vec4<f32>,
+    kernel_size: u32,
+    radius: f32,
+    bias: f32,
+    intensity: f32,
+    /// Sample points in a unit hemisphere around +Z. `w` is unused.
+    kernel: array<vec4<f32>, MAX_KERNEL_SIZE>,
+};
+
+@group(0) @binding(0)
+var<uniform> ssao: SsaoUniforms;
+
+@group(1) @binding(0)
+var depth_texture: texture_2d<f32>;
+
+@group(1) @binding(1)
+var normal_texture: texture_2d<f32>;
+
+/// Draws a single triangle that covers the entire viewport.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let position = vec2<f32>(
+        f32(i32(vertex_index & 1u) * 4 - 1),
+        f32(i32(vertex_index >> 1u) * 4 - 1),
+    );
+
+    return vec4<f32>(position, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let normal_ws = textureLoad(normal_texture, pixel, 0);
+
+    // Pixels that no model was drawn over are never occluded.
+    if (normal_ws.w == 0.0) {
+        return vec4<f32>(1.0);
+    }
+
+    let origin = view_position(pixel);
+    let normal = normalize((ssao.view * vec4<f32>(normal_ws.xyz, 0.0)).xyz);
+
+    // Rotate the kernel around the normal by a different angle at each pixel,
+    // which trades banding for noise that the blur pass removes.
+    let basis = orthonormal_basis(normal);
+    let angle = 6.2831853 * interleaved_gradient_noise(position.xy);
+    let tangent = basis[0] * cos(angle) + basis[1] * sin(angle);
+    let bitangent = cross(normal, tangent);
+
+    var occlusion = 0.0;
+
+    for (var i: u32 = 0u; i < ssao.kernel_size; i++) {
+        let k = ssao.kernel[i].xyz;
+        let sample_pos = origin + (tangent * k.x + bitangent * k.y + normal * k.z) * ssao.radius;
+
+        // Find the nearest geometry in the direction of the sample point.
+        let sample_cs = ssao.projection * vec4<f32>(sample_pos, 1.0);
+        let sample_ndc = sample_cs.xy / sample_cs.w;
+        let sample_uv = vec2<f32>(sample_ndc.x * 0.5 + 0.5, 0.5 - sample_ndc.y * 0.5);
+        let sample_pixel = clamp(
+            ssao.viewport.xy + sample_uv * ssao.viewport.zw,
+            ssao.viewport.xy,
+            ssao.viewport.xy + ssao.viewport.zw - 1.0,
+        );
+        let scene_pos = view_position(vec2<i32>(sample_pixel));
+
+        // The sample point is occluded when it is behind that geometry. The
+        // camera looks down -Z so nearer geometry has a larger z. Geometry must
+        // also rise above the pixel's surface, otherwise surfaces seen at
+        // grazing angles occlude themselves between neighboring pixels.
+        let is_behind = scene_pos.z >= sample_pos.z + ssao.bias;
+        let is_above = dot(scene_pos - origin, normal) > ssao.bias;
+
+        // Geometry far outside of the radius is in front of the pixel rather
+        // than next to it, and fades out so it does not darken the pixel.
+        let in_range = smoothstep(0.0, 1.0, ssao.radius / abs(origin.z - scene_pos.z));
+        occlusion += select(0.0, 1.0, is_behind && is_above) * in_range;
+    }
+
+    let ambient = 1.0 - occlusion / f32(ssao.kernel_size) * ssao.intensity;
+    return vec4<f32>(clamp(ambient, 0.0, 1.0));
+}
+
+/// Get the view space position of the nearest geometry at `pixel`.
+fn view_position(pixel: vec2<i32>) -> vec3<f32> {
+    let depth = textureLoad(depth_texture, pixel, 0).x;
+    let uv = (vec2<f32>(pixel) + 0.5 - ssao.viewport.xy) / ssao.viewport.zw;
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = ssao.inverse_projection * ndc;
+
+    return position.xyz / position.w;
+}
+
+/// Build two axes perpendicular to unit vector `n` and each other, using the
+/// branchless method from "Building an Orthonormal Basis, Revisited" (Duff et
+/// al. 2017).
+fn orthonormal_basis(n: vec3<f32>) -> array<vec3<f32>, 2> {
+    let s = select(-1.0, 1.0, n.z >= 0.0);
+    let a = -1.0 / (s + n.z);
+    let b = n.x * n.y * a;
+
+    return array<vec3<f32>, 2>(
+        vec3<f32>(1.0 + s * n.x * n.x * a, s * b, -s * n.x),
+        vec3<f32>(b, s + n.y * n.y * a, -n.y),
+    );
+}
+
+/// Noise in [0, 1) that varies smoothly enough between neighboring pixels to
+/// be hidden by a small blur (Jimenez 2014).
+fn interleaved_gradient_noise(pixel: vec2<f32>) -> f32 {
+    return fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
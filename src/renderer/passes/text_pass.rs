@@ -0,0 +1,258 @@
+use glam::Vec2;
+use wgpu::util::DeviceExt;
+
+use crate::renderer::{
+    gpu_buffers::{DynamicGpuBuffer, InstanceBuffer},
+    text::{BitmapFont, GlyphInstance, TextRenderer},
+};
+
+/// Draws the screen space text added to a `TextRenderer` over the render
+/// target, after the scene is tonemapped so text colors are not changed by
+/// post processing.
+pub struct TextPass {
+    /// Render pipeline for drawing alpha blended glyph quads.
+    render_pipeline: wgpu::RenderPipeline,
+    /// Render target size, copied each frame in `prepare`.
+    uniforms_buffer: wgpu::Buffer,
+    /// Bind group (uniforms, font atlas and sampler) used by the text shader.
+    bind_group: wgpu::BindGroup,
+    /// A quad for each character drawn this frame.
+    glyph_instances: InstanceBuffer<GlyphInstance>,
+    glyph_count: u32,
+}
+
+impl TextPass {
+    const FONT_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+    const SHADER: &'static str = include_str!("text_pass.wgsl");
+    const VERTICES_PER_QUAD: u32 = 6;
+
+    /// Create a new text pass that draws into render targets of
+    /// `target_format`. Only one instance is needed per renderer.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target_format: wgpu::TextureFormat,
+    ) -> Self {
+        let font_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("text pass font atlas"),
+                size: wgpu::Extent3d {
+                    width: BitmapFont::ATLAS_WIDTH,
+                    height: BitmapFont::ATLAS_HEIGHT,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::FONT_TEXTURE_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &BitmapFont::atlas_pixels(),
+        );
+
+        // The font is a pixel font, so it is sampled without filtering to keep
+        // glyph edges sharp when scaled up.
+        let font_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("text pass font sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("text pass uniforms"),
+            contents: bytemuck::bytes_of(&TextPackedUniforms::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("text pass layout"),
+            entries: &[
+                // Slot 0: text pass uniforms.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Slot 1: font atlas texture view.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Slot 2: font atlas sampler.
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("text pass bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                // Slot 0: text pass uniforms.
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms_buffer.as_entire_binding(),
+                },
+                // Slot 1: font atlas texture view.
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &font_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                // Slot 2: font atlas sampler.
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&font_sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("text pass shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("text pass render pipeline"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("text pass pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[GlyphInstance::desc()],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let glyph_instances = InstanceBuffer::new(
+            device,
+            Some("text pass glyph instances"),
+            vec![GlyphInstance::default()],
+        );
+
+        Self {
+            render_pipeline,
+            uniforms_buffer,
+            bind_group,
+            glyph_instances,
+            glyph_count: 0,
+        }
+    }
+
+    /// Prepare for rendering by copying the text added this frame and the size
+    /// of the render target to the GPU.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text: &TextRenderer,
+        target_width: u32,
+        target_height: u32,
+    ) {
+        self.glyph_count = text.glyphs().len() as u32;
+
+        if text.is_empty() {
+            return;
+        }
+
+        self.glyph_instances
+            .set_instances(device, text.glyphs().iter().copied());
+        self.glyph_instances.update_gpu(queue);
+
+        queue.write_buffer(
+            &self.uniforms_buffer,
+            0,
+            bytemuck::bytes_of(&TextPackedUniforms {
+                screen_size: Vec2::new(target_width as f32, target_height as f32),
+                _padding: Default::default(),
+            }),
+        );
+    }
+
+    /// Draw the text prepared this frame over `output_view`.
+    pub fn draw(
+        &self,
+        output_view: &wgpu::TextureView,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("text render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.glyph_instances.gpu_buffer_slice(..));
+        render_pass.draw(0..Self::VERTICES_PER_QUAD, 0..self.glyph_count);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct TextPackedUniforms {
+    pub screen_size: Vec2,
+    pub _padding: [f32; 2],
+}
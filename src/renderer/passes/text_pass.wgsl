@@ -0,0 +1,62 @@
+struct TextUniforms {
+    /// Width and height of the render target in pixels.
+    screen_size: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) uv_min: vec2<f32>,
+    @location(3) uv_max: vec2<f32>,
+    @location(4) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position_cs: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: TextUniforms;
+@group(0) @binding(1)
+var font_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var font_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    // The two triangles making up the quad, with corners ranging from the top
+    // left (0, 0) to the bottom right (1, 1).
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+    );
+    let corner = corners[vertex_index];
+
+    // Convert from pixels with Y pointing down into clip space.
+    let position_px = instance.position + corner * instance.size;
+    let position_ndc = position_px / uniforms.screen_size * 2.0 - 1.0;
+
+    var v: VertexOutput;
+    v.position_cs = vec4<f32>(position_ndc.x, -position_ndc.y, 0.0, 1.0);
+    v.tex_coords = mix(instance.uv_min, instance.uv_max, corner);
+    v.color = instance.color;
+
+    return v;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(font_texture, font_sampler, in.tex_coords).r;
+
+    if (coverage <= 0.0) {
+        discard;
+    }
+
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,140 @@
+/// Draws an egui user interface over the render target, eg tool windows with
+/// sliders for tweaking a demo while it runs.
+///
+/// The UI is built and tessellated by the owner of the `egui::Context`, which
+/// hands the output to `set_frame` each frame. The UI is drawn after every
+/// other pass so it is not affected by post processing or hidden by overlays.
+pub struct UiPass {
+    /// Draws egui's triangles and owns the textures egui asks for.
+    egui_renderer: egui_wgpu::Renderer,
+    /// Triangles for the UI drawn in the next frame.
+    primitives: Vec<egui::ClippedPrimitive>,
+    /// Texture changes egui requested for the next frame.
+    textures_delta: egui::TexturesDelta,
+    /// Textures that egui no longer needs. They are freed once the frame that
+    /// last used them is drawn.
+    textures_to_free: Vec<egui::TextureId>,
+    /// Number of physical pixels for each egui point in the next frame.
+    pixels_per_point: f32,
+}
+
+impl UiPass {
+    /// Create a new UI pass that draws into render targets of
+    /// `target_format`. Only one instance is needed per renderer.
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        Self {
+            egui_renderer: egui_wgpu::Renderer::new(device, target_format, None, 1),
+            primitives: Vec::new(),
+            textures_delta: Default::default(),
+            textures_to_free: Vec::new(),
+            pixels_per_point: 1.0,
+        }
+    }
+
+    /// Replace the UI drawn in the next frame with `primitives`, and queue the
+    /// texture changes in `textures_delta`.
+    ///
+    /// Texture changes are kept until the next frame is prepared, so the
+    /// changes from several calls are all applied if no frame is drawn between
+    /// them.
+    pub fn set_frame(
+        &mut self,
+        primitives: Vec<egui::ClippedPrimitive>,
+        textures_delta: egui::TexturesDelta,
+        pixels_per_point: f32,
+    ) {
+        self.primitives = primitives;
+        self.textures_delta.append(textures_delta);
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    /// Returns true if there is any UI to draw this frame.
+    pub fn has_ui(&self) -> bool {
+        !self.primitives.is_empty()
+    }
+
+    /// Prepare for rendering into a `target_width` by `target_height` render
+    /// target by updating egui's textures and copying the UI's triangles to the
+    /// GPU.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target_width: u32,
+        target_height: u32,
+    ) {
+        for id in self.textures_to_free.drain(..) {
+            self.egui_renderer.free_texture(&id);
+        }
+
+        let textures_delta = std::mem::take(&mut self.textures_delta);
+
+        for (id, image_delta) in &textures_delta.set {
+            self.egui_renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+
+        self.textures_to_free = textures_delta.free;
+
+        if self.primitives.is_empty() {
+            return;
+        }
+
+        // egui copies vertices with the queue, and only records commands for
+        // paint callbacks which are not used by the renderer.
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("ui upload encoder"),
+        });
+        let callback_commands = self.egui_renderer.update_buffers(
+            device,
+            queue,
+            &mut command_encoder,
+            &self.primitives,
+            &self.screen_descriptor(target_width, target_height),
+        );
+
+        queue.submit(
+            callback_commands
+                .into_iter()
+                .chain(std::iter::once(command_encoder.finish())),
+        );
+    }
+
+    /// Draw the UI prepared this frame over `output_view`, which must be
+    /// `target_width` by `target_height` pixels.
+    pub fn draw(
+        &self,
+        output_view: &wgpu::TextureView,
+        target_width: u32,
+        target_height: u32,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ui render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        self.egui_renderer.render(
+            &mut render_pass,
+            &self.primitives,
+            &self.screen_descriptor(target_width, target_height),
+        );
+    }
+
+    fn screen_descriptor(&self, width: u32, height: u32) -> egui_wgpu::ScreenDescriptor {
+        egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [width, height],
+            pixels_per_point: self.pixels_per_point,
+        }
+    }
+}
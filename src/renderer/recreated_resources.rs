@@ -0,0 +1,75 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::{materials::Material, models::Mesh, render_context::RenderContext, textures::Texture};
+
+/// Recreates meshes and textures on a new GPU device from the copies they keep
+/// on the CPU, after the device they were created with was lost.
+///
+/// Each mesh and texture is recreated once no matter how many models,
+/// materials or games share it, so sharing is kept on the new device.
+pub struct RecreatedResources {
+    context: RenderContext,
+    /// Recreated textures keyed by the address of the texture they replace.
+    /// The replaced texture is kept alive so its address is not reused.
+    textures: HashMap<*const Texture, (Rc<Texture>, Rc<Texture>)>,
+    /// Recreated meshes keyed by the address of the mesh they replace. The
+    /// replaced mesh is kept alive so its address is not reused.
+    meshes: HashMap<*const Mesh, (Rc<Mesh>, Rc<Mesh>)>,
+}
+
+impl RecreatedResources {
+    /// Recreate resources with the device, bind group layouts and default
+    /// textures of `context`.
+    pub fn new(context: RenderContext) -> Self {
+        Self {
+            context,
+            textures: Default::default(),
+            meshes: Default::default(),
+        }
+    }
+
+    /// Get the render context resources are recreated with.
+    pub fn context(&self) -> &RenderContext {
+        &self.context
+    }
+
+    /// Get the copy of `texture` on the new device.
+    pub fn texture(&mut self, texture: &Rc<Texture>) -> Rc<Texture> {
+        let context = &self.context;
+
+        self.textures
+            .entry(Rc::as_ptr(texture))
+            .or_insert_with(|| {
+                let recreated = texture.recreate(context.device(), context.queue());
+                (texture.clone(), Rc::new(recreated))
+            })
+            .1
+            .clone()
+    }
+
+    /// Get a copy of `material` whose texture maps are on the new device.
+    pub fn material(&mut self, material: &Material) -> Material {
+        Material {
+            diffuse_map: self.texture(&material.diffuse_map),
+            specular_map: self.texture(&material.specular_map),
+            emissive_map: self.texture(&material.emissive_map),
+            metallic_map: self.texture(&material.metallic_map),
+            roughness_map: self.texture(&material.roughness_map),
+            ao_map: self.texture(&material.ao_map),
+            ..material.clone()
+        }
+    }
+
+    /// Get the copy of `mesh` on the new device.
+    pub fn mesh(&mut self, mesh: &Rc<Mesh>) -> Rc<Mesh> {
+        if let Some((_, recreated)) = self.meshes.get(&Rc::as_ptr(mesh)) {
+            return recreated.clone();
+        }
+
+        let recreated = Rc::new(mesh.recreate(self));
+        self.meshes
+            .insert(Rc::as_ptr(mesh), (mesh.clone(), recreated.clone()));
+
+        recreated
+    }
+}
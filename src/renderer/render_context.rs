@@ -0,0 +1,55 @@
+use std::rc::Rc;
+
+use crate::content::DefaultTextures;
+
+use super::{gpu_context::GpuContext, shaders::BindGroupLayouts};
+
+/// Everything needed to create meshes, textures and materials for a renderer.
+///
+/// Like `GpuContext` a `RenderContext` is cheap to clone, so content loading
+/// and games can keep their own copy and create GPU resources that the
+/// renderer can draw without needing access to the renderer itself.
+#[derive(Clone, Debug)]
+pub struct RenderContext {
+    gpu: GpuContext,
+    bind_group_layouts: Rc<BindGroupLayouts>,
+    default_textures: Rc<DefaultTextures>,
+}
+
+impl RenderContext {
+    /// Create a render context for `gpu` with new bind group layouts and
+    /// default textures.
+    pub fn new(gpu: GpuContext) -> Self {
+        Self {
+            bind_group_layouts: Rc::new(BindGroupLayouts::new(gpu.device())),
+            default_textures: Rc::new(DefaultTextures::new(gpu.device(), gpu.queue())),
+            gpu,
+        }
+    }
+
+    /// Get the shared GPU device and queue.
+    pub fn gpu(&self) -> &GpuContext {
+        &self.gpu
+    }
+
+    /// Get the device used for creating GPU resources.
+    pub fn device(&self) -> &wgpu::Device {
+        self.gpu.device()
+    }
+
+    /// Get the queue used for submitting commands and writing to GPU resources.
+    pub fn queue(&self) -> &wgpu::Queue {
+        self.gpu.queue()
+    }
+
+    /// Get the registry of bind group layouts that meshes and materials must
+    /// be created with.
+    pub fn bind_group_layouts(&self) -> &BindGroupLayouts {
+        &self.bind_group_layouts
+    }
+
+    /// Get the textures used when a material does not specify its own.
+    pub fn default_textures(&self) -> &DefaultTextures {
+        &self.default_textures
+    }
+}
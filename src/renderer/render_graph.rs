@@ -0,0 +1,624 @@
+//! Schedules the passes drawn each frame from the resources they read and
+//! write.
+//!
+//! Passes are added to a `RenderGraph` along with the resources they read and
+//! write. Writing a resource creates a new version of it, and a pass that reads
+//! a version runs after the pass that wrote it and before any pass that writes
+//! the next version. The graph sorts passes by these dependencies, skips passes
+//! whose output is never used, and allocates the transient textures passes
+//! draw into from a pool that is kept across frames.
+use thiserror::Error;
+
+/// Handle to one version of a resource in a `RenderGraph`. Passes that write a
+/// resource are given a handle to the new version for later passes to read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphResource {
+    index: usize,
+    version: usize,
+}
+
+/// Describes a texture that only lives for the frame the graph is executed in.
+/// Textures with matching descriptions and non overlapping lifetimes share the
+/// same GPU texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransientTextureDesc {
+    pub label: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+}
+
+impl TransientTextureDesc {
+    /// Returns true if a texture created for `other` can be used for this one.
+    fn is_compatible(&self, other: &TransientTextureDesc) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.format == other.format
+            && self.usage == other.usage
+    }
+}
+
+/// Textures that are reused by each frame's render graph. Textures are created
+/// the first time a graph needs them and freed after a frame that does not use
+/// them, eg after the window is resized.
+#[derive(Default)]
+pub struct TransientTexturePool {
+    textures: Vec<PooledTexture>,
+}
+
+impl TransientTexturePool {
+    /// Number of GPU textures held by the pool.
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// Get the index of an unused texture matching `desc`, creating one if
+    /// none is free.
+    fn acquire(&mut self, device: &wgpu::Device, desc: &TransientTextureDesc) -> usize {
+        if let Some(index) = self
+            .textures
+            .iter()
+            .position(|t| !t.in_use && t.desc.is_compatible(desc))
+        {
+            self.textures[index].in_use = true;
+            self.textures[index].used_this_frame = true;
+            return index;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(desc.label),
+            size: wgpu::Extent3d {
+                width: desc.width.max(1),
+                height: desc.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: desc.format,
+            usage: desc.usage,
+            view_formats: &[],
+        });
+
+        self.textures.push(PooledTexture {
+            desc: *desc,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            in_use: true,
+            used_this_frame: true,
+        });
+
+        self.textures.len() - 1
+    }
+
+    /// Free the textures that were not used this frame and get the rest ready
+    /// for the next frame.
+    fn end_frame(&mut self) {
+        self.textures.retain(|t| t.used_this_frame);
+
+        for texture in &mut self.textures {
+            texture.in_use = false;
+            texture.used_this_frame = false;
+        }
+    }
+}
+
+struct PooledTexture {
+    desc: TransientTextureDesc,
+    view: wgpu::TextureView,
+    /// Assigned to a transient texture that is still alive.
+    in_use: bool,
+    used_this_frame: bool,
+}
+
+/// The transient textures allocated for the graph being executed.
+pub struct GraphTextures<'p> {
+    pool: &'p TransientTexturePool,
+    /// Index of the pooled texture assigned to each graph resource.
+    assigned: Vec<Option<usize>>,
+}
+
+impl GraphTextures<'_> {
+    /// Get the view of the transient texture `resource`.
+    ///
+    /// Panics if `resource` was imported rather than created by the graph.
+    pub fn view(&self, resource: GraphResource) -> &wgpu::TextureView {
+        let index = self.assigned[resource.index]
+            .expect("only transient textures are allocated by the render graph");
+        &self.pool.textures[index].view
+    }
+}
+
+type PassFn<'a> = Box<dyn FnOnce(&mut wgpu::CommandEncoder, &GraphTextures) + 'a>;
+
+struct PassNode<'a> {
+    name: String,
+    reads: Vec<GraphResource>,
+    writes: Vec<GraphResource>,
+    run: PassFn<'a>,
+}
+
+struct ResourceNode {
+    name: String,
+    /// Transient textures are allocated by the graph. Imported resources are
+    /// owned elsewhere and are never culled.
+    transient: Option<TransientTextureDesc>,
+    /// Pass that wrote each version of the resource. The first version holds
+    /// the resource's contents before the graph is executed.
+    writers: Vec<Option<usize>>,
+}
+
+/// Errors from scheduling the passes in a `RenderGraph`.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RenderGraphError {
+    #[error("render graph passes depend on each other in a cycle: {}", .0.join(", "))]
+    Cycle(Vec<String>),
+}
+
+/// Passes drawn in one frame and the resources they share. A new graph is
+/// built for each frame.
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    resources: Vec<ResourceNode>,
+    passes: Vec<PassNode<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a resource that is owned outside of the graph, eg the render target
+    /// or a texture held by a pass. Passes that write imported resources are
+    /// always executed.
+    pub fn import(&mut self, name: &str) -> GraphResource {
+        self.add_resource(name, None)
+    }
+
+    /// Add a texture that is allocated by the graph while it is executed.
+    /// Passes that only write transient textures nothing reads are skipped.
+    pub fn create_texture(&mut self, desc: TransientTextureDesc) -> GraphResource {
+        self.add_resource(desc.label, Some(desc))
+    }
+
+    /// Start adding a pass named `name`. The pass is added to the graph once
+    /// `PassBuilder::execute` is called.
+    pub fn add_pass(&mut self, name: &str) -> PassBuilder<'_, 'a> {
+        PassBuilder {
+            graph: self,
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Run every pass that contributes to an imported resource in dependency
+    /// order, recording their commands into `command_encoder`. Transient
+    /// textures are taken from `pool` for as long as the passes using them
+    /// need them.
+    pub fn execute(
+        self,
+        device: &wgpu::Device,
+        pool: &mut TransientTexturePool,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) -> Result<(), RenderGraphError> {
+        let order = self.schedule()?;
+        let assigned = self.allocate(device, pool, &order);
+        let textures = GraphTextures { pool, assigned };
+
+        let mut passes = self.passes.into_iter().map(Some).collect::<Vec<_>>();
+
+        for pass_index in order {
+            let pass = passes[pass_index]
+                .take()
+                .expect("passes are scheduled once");
+            (pass.run)(command_encoder, &textures);
+        }
+
+        pool.end_frame();
+        Ok(())
+    }
+
+    fn add_resource(
+        &mut self,
+        name: &str,
+        transient: Option<TransientTextureDesc>,
+    ) -> GraphResource {
+        self.resources.push(ResourceNode {
+            name: name.to_string(),
+            transient,
+            writers: vec![None],
+        });
+
+        GraphResource {
+            index: self.resources.len() - 1,
+            version: 0,
+        }
+    }
+
+    /// Get the indices of the passes to execute in the order they must run.
+    /// Passes that do not depend on each other keep the order they were added.
+    fn schedule(&self) -> Result<Vec<usize>, RenderGraphError> {
+        // Readers of each version of each resource.
+        let mut readers = self
+            .resources
+            .iter()
+            .map(|r| vec![Vec::new(); r.writers.len()])
+            .collect::<Vec<_>>();
+
+        for (pass_index, pass) in self.passes.iter().enumerate() {
+            for read in &pass.reads {
+                readers[read.index][read.version].push(pass_index);
+            }
+        }
+
+        // Mark passes that write an imported resource, and then every pass
+        // writing a version that a marked pass reads or overwrites.
+        let mut needed = vec![false; self.passes.len()];
+        let mut stack = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| {
+                pass.writes
+                    .iter()
+                    .any(|w| self.resources[w.index].transient.is_none())
+            })
+            .map(|(pass_index, _)| pass_index)
+            .collect::<Vec<_>>();
+
+        while let Some(pass_index) = stack.pop() {
+            if std::mem::replace(&mut needed[pass_index], true) {
+                continue;
+            }
+
+            let pass = &self.passes[pass_index];
+            let written = pass.writes.iter().map(|w| GraphResource {
+                index: w.index,
+                version: w.version - 1,
+            });
+
+            for source in pass.reads.iter().copied().chain(written) {
+                if let Some(writer) = self.resources[source.index].writers[source.version] {
+                    stack.push(writer);
+                }
+            }
+        }
+
+        // Edges from each pass to the passes that must run after it.
+        let mut dependents = vec![Vec::new(); self.passes.len()];
+        let mut dependency_counts = vec![0; self.passes.len()];
+        let mut add_edge = |from: usize, to: usize| {
+            if from != to && needed[from] && needed[to] && !dependents[from].contains(&to) {
+                dependents[from].push(to);
+                dependency_counts[to] += 1;
+            }
+        };
+
+        for (resource, readers) in self.resources.iter().zip(&readers) {
+            for (version, version_readers) in readers.iter().enumerate() {
+                let writer = resource.writers[version];
+                let next_writer = resource.writers.get(version + 1).copied().flatten();
+
+                for &reader in version_readers {
+                    if let Some(writer) = writer {
+                        add_edge(writer, reader);
+                    }
+                    if let Some(next_writer) = next_writer {
+                        add_edge(reader, next_writer);
+                    }
+                }
+
+                if let (Some(writer), Some(next_writer)) = (writer, next_writer) {
+                    add_edge(writer, next_writer);
+                }
+            }
+        }
+
+        // Repeatedly run the earliest added pass that has nothing left to wait
+        // on.
+        let mut ready = (0..self.passes.len())
+            .filter(|&i| needed[i] && dependency_counts[i] == 0)
+            .collect::<std::collections::BTreeSet<_>>();
+        let mut order = Vec::new();
+
+        while let Some(pass_index) = ready.pop_first() {
+            order.push(pass_index);
+
+            for &dependent in &dependents[pass_index] {
+                dependency_counts[dependent] -= 1;
+
+                if dependency_counts[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if order.len() < needed.iter().filter(|&&n| n).count() {
+            return Err(RenderGraphError::Cycle(
+                (0..self.passes.len())
+                    .filter(|&i| needed[i] && dependency_counts[i] > 0)
+                    .map(|i| self.passes[i].name.clone())
+                    .collect(),
+            ));
+        }
+
+        Ok(order)
+    }
+
+    /// Assign a pooled texture to each transient texture used by the passes in
+    /// `order`. A pooled texture is shared by transient textures that are not
+    /// used by any of the same passes.
+    fn allocate(
+        &self,
+        device: &wgpu::Device,
+        pool: &mut TransientTexturePool,
+        order: &[usize],
+    ) -> Vec<Option<usize>> {
+        // First and last step of `order` that uses each resource.
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.resources.len()];
+
+        for (step, &pass_index) in order.iter().enumerate() {
+            let pass = &self.passes[pass_index];
+
+            for resource in pass.reads.iter().chain(&pass.writes) {
+                let lifetime = &mut lifetimes[resource.index];
+                *lifetime = Some(lifetime.map_or((step, step), |(first, _)| (first, step)));
+            }
+        }
+
+        let mut assigned = vec![None; self.resources.len()];
+
+        for step in 0..order.len() {
+            for (index, resource) in self.resources.iter().enumerate() {
+                if let (Some(desc), Some((first, _))) = (&resource.transient, lifetimes[index]) {
+                    if first == step {
+                        assigned[index] = Some(pool.acquire(device, desc));
+                    }
+                }
+            }
+
+            // Textures can be reused by passes after the last one using them.
+            for (index, lifetime) in lifetimes.iter().enumerate() {
+                if let (Some(pooled), Some((_, last))) = (assigned[index], lifetime) {
+                    if *last == step {
+                        pool.textures[pooled].in_use = false;
+                    }
+                }
+            }
+        }
+
+        assigned
+    }
+}
+
+/// Declares the resources a pass reads and writes before adding it to a
+/// `RenderGraph`.
+pub struct PassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    name: String,
+    reads: Vec<GraphResource>,
+    writes: Vec<GraphResource>,
+}
+
+impl<'a> PassBuilder<'_, 'a> {
+    /// Declare that the pass reads `resource`, so it runs after the pass that
+    /// wrote that version of the resource.
+    pub fn read(&mut self, resource: GraphResource) -> &mut Self {
+        self.reads.push(resource);
+        self
+    }
+
+    /// Declare that the pass writes `resource`, returning the new version of
+    /// the resource for later passes to read. Passes that load the existing
+    /// contents of a resource only need to write it.
+    ///
+    /// Panics if `resource` is not the latest version, because another pass
+    /// already wrote over it.
+    pub fn write(&mut self, resource: GraphResource) -> GraphResource {
+        let node = &mut self.graph.resources[resource.index];
+
+        assert!(
+            resource.version + 1 == node.writers.len(),
+            "pass `{}` writes an old version of `{}`",
+            self.name,
+            node.name
+        );
+
+        // The writer is filled in when the pass is added to the graph.
+        node.writers.push(None);
+
+        let written = GraphResource {
+            index: resource.index,
+            version: resource.version + 1,
+        };
+        self.writes.push(written);
+        written
+    }
+
+    /// Add the pass to the graph, recording its commands with `run` when the
+    /// graph is executed.
+    pub fn execute(self, run: impl FnOnce(&mut wgpu::CommandEncoder, &GraphTextures) + 'a) {
+        let pass_index = self.graph.passes.len();
+
+        for written in &self.writes {
+            self.graph.resources[written.index].writers[written.version] = Some(pass_index);
+        }
+
+        self.graph.passes.push(PassNode {
+            name: self.name,
+            reads: self.reads,
+            writes: self.writes,
+            run: Box::new(run),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_renderer;
+
+    fn pass_names(graph: &RenderGraph) -> Vec<String> {
+        graph
+            .schedule()
+            .unwrap()
+            .into_iter()
+            .map(|i| graph.passes[i].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn passes_reading_an_old_version_run_before_it_is_overwritten() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import("target");
+        let depth = graph.import("depth");
+
+        let mut main = graph.add_pass("main");
+        let depth = main.write(depth);
+        main.execute(|_, _| {});
+
+        let mut transparent = graph.add_pass("transparent");
+        transparent.write(depth);
+        transparent.execute(|_, _| {});
+
+        // Reads the depth written by the main pass, so it must run before the
+        // transparent pass writes over it.
+        let mut visualize = graph.add_pass("visualize depth");
+        visualize.read(depth);
+        visualize.write(target);
+        visualize.execute(|_, _| {});
+
+        assert_eq!(
+            vec!["main", "visualize depth", "transparent"],
+            pass_names(&graph)
+        );
+    }
+
+    #[test]
+    fn passes_only_writing_unread_transient_textures_are_skipped() {
+        let mut graph = RenderGraph::new();
+        let target = graph.import("target");
+        let desc = TransientTextureDesc {
+            label: "scratch",
+            width: 4,
+            height: 4,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
+        let used = graph.create_texture(desc);
+        let unused = graph.create_texture(desc);
+
+        let mut pass = graph.add_pass("unused");
+        pass.write(unused);
+        pass.execute(|_, _| {});
+
+        let mut pass = graph.add_pass("used");
+        let used = pass.write(used);
+        pass.execute(|_, _| {});
+
+        let mut pass = graph.add_pass("present");
+        pass.read(used);
+        pass.write(target);
+        pass.execute(|_, _| {});
+
+        assert_eq!(vec!["used", "present"], pass_names(&graph));
+    }
+
+    #[test]
+    fn passes_that_depend_on_each_other_are_a_cycle() {
+        let mut graph = RenderGraph::new();
+        let a = graph.import("a");
+        let b = graph.import("b");
+
+        let mut first = graph.add_pass("first");
+        let a_old = first.write(a);
+        first.execute(|_, _| {});
+
+        let mut second = graph.add_pass("second");
+        second.write(a_old);
+        let b = second.write(b);
+        second.execute(|_, _| {});
+
+        // Reads a version of `a` that the second pass overwrites, but also
+        // reads the second pass's output.
+        let mut third = graph.add_pass("third");
+        third.read(a_old);
+        third.read(b);
+        third.write(b);
+        third.execute(|_, _| {});
+
+        assert_eq!(
+            Err(RenderGraphError::Cycle(vec![
+                "second".to_string(),
+                "third".to_string()
+            ])),
+            graph.schedule()
+        );
+    }
+
+    #[test]
+    fn transient_textures_with_separate_lifetimes_share_a_texture() {
+        let Some(renderer) = test_renderer(8, 8) else {
+            return;
+        };
+        let device = renderer.gpu().device();
+        let desc = TransientTextureDesc {
+            label: "scratch",
+            width: 8,
+            height: 8,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        };
+        let mut pool = TransientTexturePool::default();
+
+        // `first` is last used by the pass that writes `second`, so the pass
+        // writing `third` can reuse its texture.
+        let build = |pool: &mut TransientTexturePool| {
+            let mut graph = RenderGraph::new();
+            let target = graph.import("target");
+            let first = graph.create_texture(desc);
+            let second = graph.create_texture(desc);
+            let third = graph.create_texture(desc);
+
+            let mut pass = graph.add_pass("first");
+            let first = pass.write(first);
+            pass.execute(|_, _| {});
+
+            let mut pass = graph.add_pass("second");
+            pass.read(first);
+            let second = pass.write(second);
+            pass.execute(|_, _| {});
+
+            let mut pass = graph.add_pass("third");
+            pass.read(second);
+            let third = pass.write(third);
+            pass.execute(move |_, textures| {
+                assert_eq!(
+                    textures.view(first).global_id(),
+                    textures.view(third).global_id()
+                );
+                assert_ne!(
+                    textures.view(first).global_id(),
+                    textures.view(second).global_id()
+                );
+            });
+
+            let mut pass = graph.add_pass("present");
+            pass.read(third);
+            pass.write(target);
+            pass.execute(|_, _| {});
+
+            let mut encoder = device.create_command_encoder(&Default::default());
+            graph.execute(device, pool, &mut encoder).unwrap();
+        };
+
+        build(&mut pool);
+        assert_eq!(2, pool.len());
+
+        // Textures are kept for the next frame rather than recreated.
+        build(&mut pool);
+        assert_eq!(2, pool.len());
+    }
+}
@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+use super::{
+    passes::{DepthPass, PostProcessStack, SsaoTargets},
+    textures::Texture,
+};
+
+/// A texture that a scene can be rendered into with
+/// `Renderer::render_to_target`, eg to draw a mirror, a portal, a minimap or
+/// a thumbnail of a model.
+///
+/// The texture can be used as a material map (eg `MaterialBuilder::diffuse_map`)
+/// to show what was rendered on a model in the next frame. A model must not
+/// show the texture while it is being rendered into, so hide such models from
+/// the scene drawn into the target.
+///
+/// Targets are created with `Renderer::create_render_target` and keep their
+/// own depth buffer, post processing and ambient occlusion textures so that
+/// rendering into them does not disturb the textures sized to the window.
+pub struct RenderTarget {
+    /// The color texture that the scene is drawn into.
+    texture: Rc<Texture>,
+    /// Describes `texture` in the same way that the renderer describes its
+    /// window surface, and is swapped with the renderer's configuration while
+    /// drawing into the target.
+    pub(super) surface_config: wgpu::SurfaceConfiguration,
+    pub(super) size: winit::dpi::PhysicalSize<u32>,
+    pub(super) depth_pass: DepthPass,
+    pub(super) post_process_stack: PostProcessStack,
+    pub(super) ssao_targets: Option<SsaoTargets>,
+}
+
+impl RenderTarget {
+    /// Color format of render target textures. This matches the format that
+    /// images are loaded as, so render targets can be used in place of them.
+    pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Create a `width` by `height` render target. `post_process_stack` is the
+    /// stack applied to scenes drawn into the target, and should be created
+    /// for a surface with `TEXTURE_FORMAT`.
+    pub(super) fn new(
+        device: &wgpu::Device,
+        surface_config: wgpu::SurfaceConfiguration,
+        post_process_stack: PostProcessStack,
+    ) -> Self {
+        let texture = Texture::new_drawable(
+            device,
+            surface_config.format,
+            surface_config.width,
+            surface_config.height,
+            surface_config.usage,
+            Some("render target texture"),
+        );
+
+        Self {
+            texture: Rc::new(texture),
+            size: winit::dpi::PhysicalSize::new(surface_config.width, surface_config.height),
+            depth_pass: DepthPass::new(device, &surface_config),
+            post_process_stack,
+            ssao_targets: None,
+            surface_config,
+        }
+    }
+
+    /// Get the texture that scenes are rendered into, eg to use as a material
+    /// map.
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    /// Get the width of the target in pixels.
+    pub fn width(&self) -> u32 {
+        self.size.width
+    }
+
+    /// Get the height of the target in pixels.
+    pub fn height(&self) -> u32 {
+        self.size.height
+    }
+}
@@ -1,16 +1,793 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use slotmap::{new_key_type, SlotMap};
+use thiserror::Error;
+use tracing::warn;
+
 use super::{
+    billboards::Billboard,
+    bvh::Bvh,
     lighting::{DirectionalLight, PointLight, SpotLight},
     models::Model,
+    recreated_resources::RecreatedResources,
+    sprites::Sprite,
+    textures::Texture,
+};
+use crate::{
+    camera::Camera,
+    math_utils::{Frustum, Ray},
 };
 
-/// A set of models and associated properties that can be drawn with the
+new_key_type! {
+    /// Identifies an entity in a scene. Ids stay valid until the entity is
+    /// despawned, so they can be kept to refer to an entity later, eg after
+    /// picking it or from a script.
+    pub struct EntityId;
+}
+
+/// Position, orientation and scale of an entity relative to its parent, or to
+/// the world when the entity has no parent.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    /// The transform that leaves everything where it is.
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    /// Create a transform that only moves things by `translation`.
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Get the matrix that applies this transform.
+    pub fn to_matrix(self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// The light component of an entity. The light's position and direction are
+/// relative to the entity.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    /// Get the point light, or `None` if this is a different kind of light.
+    pub fn as_point_mut(&mut self) -> Option<&mut PointLight> {
+        match self {
+            Self::Point(light) => Some(light),
+            _ => None,
+        }
+    }
+
+    /// Get the spot light, or `None` if this is a different kind of light.
+    pub fn as_spot_mut(&mut self) -> Option<&mut SpotLight> {
+        match self {
+            Self::Spot(light) => Some(light),
+            _ => None,
+        }
+    }
+
+    /// Get the directional light, or `None` if this is a different kind of
+    /// light.
+    pub fn as_directional_mut(&mut self) -> Option<&mut DirectionalLight> {
+        match self {
+            Self::Directional(light) => Some(light),
+            _ => None,
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Self::Point(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Self::Spot(light)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(light: DirectionalLight) -> Self {
+        Self::Directional(light)
+    }
+}
+
+/// Something placed in a scene. Entities have a transform, and optionally a
+/// model that is drawn at the entity (its mesh renderer) and a light.
+pub struct Entity {
+    /// Name used to find the entity, eg when reloading a scene file.
+    pub name: Option<String>,
+    pub transform: Transform,
+    /// The model drawn for this entity. The model's own translation, rotation
+    /// and scale offset it from the entity.
+    pub model: Option<Model>,
+    pub light: Option<Light>,
+    /// Entity that this entity is attached to, see `Scene::attach`.
+    parent: Option<EntityId>,
+    /// Entities attached to this entity.
+    children: Vec<EntityId>,
+    /// Transform from this entity's space to world space, as of the last call
+    /// to `Scene::update_transforms`.
+    world: Cell<Mat4>,
+    /// Set when the entity or one of its ancestors may have moved since the
+    /// last call to `Scene::update_transforms`. Every descendant of a dirty
+    /// entity is dirty too.
+    dirty: Cell<bool>,
+}
+
+impl Entity {
+    /// Create an entity with no model or light.
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            name: None,
+            transform,
+            model: None,
+            light: None,
+            parent: None,
+            children: Vec::new(),
+            world: Cell::new(Mat4::IDENTITY),
+            dirty: Cell::new(true),
+        }
+    }
+
+    /// Give the entity a name that it can be found by.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Give the entity a model to draw.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Give the entity a light.
+    pub fn with_light(mut self, light: impl Into<Light>) -> Self {
+        self.light = Some(light.into());
+        self
+    }
+
+    /// Get the entity this entity is attached to, or `None` if the entity is
+    /// not attached to another entity.
+    pub fn parent(&self) -> Option<EntityId> {
+        self.parent
+    }
+}
+
+/// A set of entities and associated properties that can be drawn with the
 /// renderer.
 ///
-/// A `Scene` is not a scene graph!
-#[derive(Default)]
+/// Entities can be attached to other entities with `attach`, after which they
+/// move with their parent.
+///
+/// The scene keeps a bounding volume hierarchy over its models so culling and
+/// picking stay fast in scenes with many models. It is refit to the models
+/// that moved in `update_transforms`.
+///
+/// Entities are marked dirty when they are modified with `entity_mut`, and
+/// attached or detached. Only dirty entities and their descendants have their
+/// world transforms computed again by `update_transforms`.
 pub struct Scene {
-    pub point_lights: Vec<PointLight>,
-    pub directional_lights: Vec<DirectionalLight>,
-    pub spot_lights: Vec<SpotLight>,
-    pub models: Vec<Model>,
+    /// The camera used when rendering the scene.
+    pub camera: Camera,
+    entities: SlotMap<EntityId, Entity>,
+    /// Tree of the world bounds of every model, as of the last call to
+    /// `update_transforms`.
+    bvh: RefCell<Bvh<EntityId>>,
+    /// Camera facing quads drawn after the models.
+    pub billboards: Vec<Billboard>,
+    /// Screen space quads drawn over the scene after it is tonemapped.
+    pub sprites: Vec<Sprite>,
+    /// Cube map drawn behind everything else in the scene.
+    pub skybox: Option<Rc<Texture>>,
+}
+
+impl Scene {
+    /// Create a new empty scene that is viewed through `camera`.
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            entities: SlotMap::with_key(),
+            bvh: RefCell::new(Bvh::build([])),
+            billboards: Vec::new(),
+            sprites: Vec::new(),
+            skybox: None,
+        }
+    }
+
+    /// Add `entity` to the scene and return its id.
+    pub fn spawn(&mut self, entity: Entity) -> EntityId {
+        self.entities.insert(entity)
+    }
+
+    /// Add an entity that draws `model` at the origin. The model's translation,
+    /// rotation and scale place it in the world until the entity is moved.
+    pub fn spawn_model(&mut self, model: Model) -> EntityId {
+        self.spawn(Entity::new(Transform::IDENTITY).with_model(model))
+    }
+
+    /// Add an entity with `light` at the origin, so the light's position and
+    /// direction are in world space until the entity is moved.
+    pub fn spawn_light(&mut self, light: impl Into<Light>) -> EntityId {
+        self.spawn(Entity::new(Transform::IDENTITY).with_light(light))
+    }
+
+    /// Remove the entity `id` from the scene and return it. Entities attached
+    /// to it are detached, so their transforms become relative to the world.
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity> {
+        self.detach(id);
+        let mut entity = self.entities.remove(id)?;
+
+        for child in std::mem::take(&mut entity.children) {
+            self.entities[child].parent = None;
+            self.mark_dirty(child);
+        }
+
+        Some(entity)
+    }
+
+    /// Get the entity `id`, or `None` if it was despawned.
+    pub fn entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(id)
+    }
+
+    /// Get the entity `id` for modification, or `None` if it was despawned.
+    /// The entity may be moved, so it is marked dirty.
+    pub fn entity_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.mark_dirty(id);
+        self.entities.get_mut(id)
+    }
+
+    /// Find the first entity named `name`.
+    pub fn find_entity(&self, name: &str) -> Option<EntityId> {
+        self.entities
+            .iter()
+            .find(|(_, entity)| entity.name.as_deref() == Some(name))
+            .map(|(id, _)| id)
+    }
+
+    /// Iterate over every entity in the scene.
+    pub fn entities(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities.iter()
+    }
+
+    /// Get the model drawn for entity `id`, or `None` if the entity does not
+    /// exist or has no model.
+    pub fn model(&self, id: EntityId) -> Option<&Model> {
+        self.entity(id).and_then(|entity| entity.model.as_ref())
+    }
+
+    /// Get the model drawn for entity `id` for modification.
+    pub fn model_mut(&mut self, id: EntityId) -> Option<&mut Model> {
+        self.entity_mut(id).and_then(|entity| entity.model.as_mut())
+    }
+
+    /// Get the light of entity `id` for modification, or `None` if the entity
+    /// does not exist or has no light.
+    pub fn light_mut(&mut self, id: EntityId) -> Option<&mut Light> {
+        self.entity_mut(id).and_then(|entity| entity.light.as_mut())
+    }
+
+    /// Iterate over the models of every entity that has one.
+    pub fn models(&self) -> impl Iterator<Item = &Model> {
+        self.entity_models().map(|(_, model)| model)
+    }
+
+    /// Iterate over the models of every entity that has one, along with the
+    /// id of the entity. Models are in the same order as `models`.
+    pub fn entity_models(&self) -> impl Iterator<Item = (EntityId, &Model)> {
+        self.entities
+            .iter()
+            .filter_map(|(id, entity)| Some((id, entity.model.as_ref()?)))
+    }
+
+    /// Call `visit` with the id of every entity whose model may be visible
+    /// inside of `frustum`. Models are tested where they were at the last call
+    /// to `update_transforms`.
+    pub fn query_frustum(&self, frustum: &Frustum, visit: impl FnMut(EntityId)) {
+        self.bvh.borrow().query_frustum(frustum, visit);
+    }
+
+    /// Attach entity `child` to entity `parent`. The child's transform becomes
+    /// relative to the parent, and the child moves with the parent from then
+    /// on.
+    pub fn attach(&mut self, child: EntityId, parent: EntityId) -> Result<(), AttachError> {
+        for id in [child, parent] {
+            if !self.entities.contains_key(id) {
+                return Err(AttachError::NoSuchEntity(id));
+            }
+        }
+
+        // Attaching an entity to one of its descendants would create a cycle.
+        let mut ancestor = Some(parent);
+
+        while let Some(id) = ancestor {
+            if id == child {
+                return Err(AttachError::Cycle { child, parent });
+            }
+
+            ancestor = self.entities[id].parent;
+        }
+
+        self.detach(child);
+        self.entities[child].parent = Some(parent);
+        self.entities[parent].children.push(child);
+        self.mark_dirty(child);
+
+        Ok(())
+    }
+
+    /// Detach entity `child` from its parent. The child's transform becomes
+    /// relative to the world again.
+    pub fn detach(&mut self, child: EntityId) {
+        let Some(parent) = self.entities.get_mut(child).and_then(|e| e.parent.take()) else {
+            return;
+        };
+
+        self.entities[parent].children.retain(|id| *id != child);
+        self.mark_dirty(child);
+    }
+
+    /// Get the entities attached directly to entity `parent`.
+    pub fn children(&self, parent: EntityId) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities
+            .get(parent)
+            .into_iter()
+            .flat_map(|entity| entity.children.iter().copied())
+    }
+
+    /// Get the transform from entity `id`'s space to world space, which
+    /// includes the transforms of the entity's ancestors. Returns `None` if
+    /// the entity does not exist.
+    pub fn world_transform(&self, id: EntityId) -> Option<Mat4> {
+        let entity = self.entities.get(id)?;
+
+        if !entity.dirty.get() {
+            return Some(entity.world.get());
+        }
+
+        let parent_world = entity
+            .parent
+            .and_then(|parent| self.world_transform(parent))
+            .unwrap_or(Mat4::IDENTITY);
+
+        Some(parent_world * entity.transform.to_matrix())
+    }
+
+    /// Mark entity `id` and its descendants as needing their world transforms
+    /// computed again.
+    fn mark_dirty(&mut self, id: EntityId) {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let Some(entity) = self.entities.get_mut(id) else {
+                continue;
+            };
+
+            // Descendants of an entity that is already dirty are dirty too.
+            if !entity.dirty.replace(true) {
+                stack.extend_from_slice(&entity.children);
+            }
+        }
+    }
+
+    /// Find the closest model whose bounding box is hit by `ray`.
+    pub fn pick(&self, ray: &Ray) -> Option<PickHit> {
+        self.pick_models(ray, false, |_| true)
+    }
+
+    /// Find the closest model whose triangles are hit by `ray`. Models with
+    /// meshes that have no triangles are tested against their bounding box
+    /// instead.
+    pub fn pick_triangles(&self, ray: &Ray) -> Option<PickHit> {
+        self.pick_models(ray, true, |_| true)
+    }
+
+    /// Like `pick_triangles`, but only entities for which `filter` returns
+    /// true can be hit. Useful to ignore the entity a ray starts from.
+    pub fn pick_triangles_filtered(
+        &self,
+        ray: &Ray,
+        filter: impl Fn(EntityId) -> bool,
+    ) -> Option<PickHit> {
+        self.pick_models(ray, true, filter)
+    }
+
+    fn pick_models(
+        &self,
+        ray: &Ray,
+        test_triangles: bool,
+        filter: impl Fn(EntityId) -> bool,
+    ) -> Option<PickHit> {
+        self.update_transforms();
+
+        // Only models whose bounds are hit by the ray are tested, and the
+        // distance to the bounds is never more than the distance to the
+        // triangles inside of them.
+        let hit = self.bvh.borrow().raycast(ray, |id| {
+            if !filter(id) {
+                return None;
+            }
+
+            let model = self.model(id)?;
+            let bounds_distance = ray.intersect_aabb(&model.world_bounds())?;
+            let triangles = model.mesh().triangles();
+
+            if test_triangles && !triangles.is_empty() {
+                // Distances along the ray are unchanged by moving it into the
+                // model's local space.
+                let local_ray = ray.transformed(&model.local_to_world().inverse());
+                triangles
+                    .iter()
+                    .filter_map(|triangle| local_ray.intersect_triangle(*triangle))
+                    .min_by(f32::total_cmp)
+            } else {
+                Some(bounds_distance)
+            }
+        });
+
+        hit.map(|(entity, distance)| PickHit { entity, distance })
+    }
+
+    /// Compute the world transforms of dirty entities and copy them to their
+    /// models, which marks the models as needing their shader values updated.
+    /// The renderer calls this before drawing the scene.
+    ///
+    /// The bounding volume hierarchy is refit to models that moved, or built
+    /// again when models were spawned or despawned.
+    pub fn update_transforms(&self) {
+        let mut bvh = self.bvh.borrow_mut();
+        let mut rebuild = bvh.needs_rebuild();
+        let mut model_count = 0;
+
+        for (id, entity) in &self.entities {
+            model_count += usize::from(entity.model.is_some());
+
+            // Dirty subtrees are updated from their topmost entity, since the
+            // descendants of a dirty entity are dirty too.
+            let parent = entity.parent.map(|parent| &self.entities[parent]);
+
+            if entity.dirty.get() && !parent.is_some_and(|parent| parent.dirty.get()) {
+                let parent_world = parent.map_or(Mat4::IDENTITY, |parent| parent.world.get());
+                self.update_subtree(id, parent_world, &mut bvh, &mut rebuild);
+            }
+        }
+
+        if rebuild || model_count != bvh.len() {
+            *bvh = Bvh::build(
+                self.entity_models()
+                    .map(|(id, model)| (id, model.world_bounds())),
+            );
+        }
+    }
+
+    /// Compute the world transform of entity `id` and its descendants from the
+    /// world transform of its parent, and refit the bounds of their models.
+    fn update_subtree(
+        &self,
+        id: EntityId,
+        parent_world: Mat4,
+        bvh: &mut Bvh<EntityId>,
+        rebuild: &mut bool,
+    ) {
+        let entity = &self.entities[id];
+        let world = parent_world * entity.transform.to_matrix();
+        entity.world.set(world);
+        entity.dirty.set(false);
+
+        if let Some(model) = &entity.model {
+            model.set_entity_to_world(world);
+
+            if !*rebuild {
+                *rebuild = !bvh.update(id, model.world_bounds());
+            }
+        }
+
+        for child in &entity.children {
+            self.update_subtree(*child, world, bvh, rebuild);
+        }
+    }
+
+    /// Get a copy of every point light in the scene, moved to world space.
+    pub fn point_lights(&self) -> Vec<PointLight> {
+        self.world_lights(|light, world| match light {
+            Light::Point(light) => Some(PointLight {
+                position: world.transform_point3(light.position),
+                ..light.clone()
+            }),
+            _ => None,
+        })
+    }
+
+    /// Get a copy of every spot light in the scene, moved to world space.
+    pub fn spot_lights(&self) -> Vec<SpotLight> {
+        self.world_lights(|light, world| match light {
+            Light::Spot(light) => Some(SpotLight {
+                position: world.transform_point3(light.position),
+                direction: world_direction(world, light.direction),
+                ..light.clone()
+            }),
+            _ => None,
+        })
+    }
+
+    /// Get a copy of every directional light in the scene, rotated to world
+    /// space.
+    pub fn directional_lights(&self) -> Vec<DirectionalLight> {
+        self.world_lights(|light, world| match light {
+            Light::Directional(light) => Some(DirectionalLight {
+                direction: world_direction(world, light.direction),
+                ..light.clone()
+            }),
+            _ => None,
+        })
+    }
+
+    fn world_lights<T>(&self, to_world: impl Fn(&Light, Mat4) -> Option<T>) -> Vec<T> {
+        self.entities
+            .iter()
+            .filter_map(|(id, entity)| {
+                let world = self.world_transform(id).unwrap_or(Mat4::IDENTITY);
+                to_world(entity.light.as_ref()?, world)
+            })
+            .collect()
+    }
+
+    /// Swap the meshes and textures drawn by the scene for their copies on the
+    /// new GPU device of `resources`, after the old device was lost. Entities,
+    /// their transforms and everything else kept on the CPU are unchanged.
+    pub fn recreate_gpu_resources(&mut self, resources: &mut RecreatedResources) {
+        for (_, entity) in &mut self.entities {
+            if let Some(model) = &mut entity.model {
+                model.set_mesh(resources.mesh(model.mesh()));
+            }
+        }
+
+        for billboard in &mut self.billboards {
+            billboard.texture = resources.texture(&billboard.texture);
+        }
+
+        for sprite in &mut self.sprites {
+            sprite.texture = resources.texture(&sprite.texture);
+        }
+
+        if let Some(skybox) = &mut self.skybox {
+            *skybox = resources.texture(skybox);
+        }
+    }
+
+    /// Notify the scene that the size of the viewport it is rendered into has
+    /// changed. This should be called whenever the render window is resized.
+    pub fn set_viewport_size(&mut self, new_width: u32, new_height: u32) {
+        self.camera
+            .set_viewport_size(new_width, new_height)
+            .unwrap_or_else(|e| warn!("{e}"))
+    }
+}
+
+/// The closest model hit by a ray, see `Scene::pick`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickHit {
+    /// The entity that owns the model.
+    pub entity: EntityId,
+    /// Distance along the ray to where it hit the model.
+    pub distance: f32,
+}
+
+/// Rotate `direction` into world space. Lights on entities that are not
+/// transformed keep their exact direction, rather than one that was multiplied
+/// by the identity matrix and normalized again.
+fn world_direction(entity_to_world: Mat4, direction: Vec3) -> Vec3 {
+    if entity_to_world == Mat4::IDENTITY {
+        direction
+    } else {
+        entity_to_world
+            .transform_vector3(direction)
+            .normalize_or_zero()
+    }
+}
+
+/// Reasons that an entity could not be attached to another entity.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AttachError {
+    #[error("there is no scene entity {0:?}")]
+    NoSuchEntity(EntityId),
+    #[error("cannot attach entity {child:?} to entity {parent:?} because {parent:?} is already attached to {child:?} or its descendants")]
+    Cycle { child: EntityId, parent: EntityId },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        content::ContentManager,
+        renderer::{
+            materials::MaterialBuilder,
+            meshes::{builtin_mesh, BuiltinMesh},
+        },
+        test_support::{test_camera, test_renderer},
+    };
+
+    #[test]
+    fn attached_entities_move_with_their_parents() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let mut scene = Scene::new(test_camera(4, 4));
+
+        // The grandchild is spawned first to check that parents spawned later
+        // are still applied before their children.
+        let offset = Transform::from_translation(Vec3::new(1.0, 0.0, 0.0));
+        let [grandchild, child, root] = [(); 3].map(|_| {
+            scene.spawn(Entity::new(offset).with_model(Model::new(
+                cube.clone(),
+                Vec3::ZERO,
+                Quat::IDENTITY,
+                Vec3::ONE,
+            )))
+        });
+
+        scene.attach(child, root).unwrap();
+        scene.attach(grandchild, child).unwrap();
+        assert_eq!(
+            Err(AttachError::Cycle {
+                child: root,
+                parent: grandchild
+            }),
+            scene.attach(root, grandchild)
+        );
+        assert_eq!(vec![grandchild], scene.children(child).collect::<Vec<_>>());
+
+        let light = scene.spawn(Entity::new(Transform::IDENTITY).with_light(SpotLight {
+            position: Vec3::ZERO,
+            direction: Vec3::NEG_Z,
+            ..Default::default()
+        }));
+        scene.attach(light, grandchild).unwrap();
+
+        scene.update_transforms();
+        let world_translation =
+            |scene: &Scene, id| scene.model(id).unwrap().local_to_world().w_axis.truncate();
+        assert_eq!(
+            Vec3::new(3.0, 0.0, 0.0),
+            world_translation(&scene, grandchild)
+        );
+        assert_eq!(Vec3::new(3.0, 0.0, 0.0), scene.spot_lights()[0].position);
+
+        // Entities that did not move are not updated again, and moving a
+        // child leaves its parent where it is.
+        let mark_models_updated = |scene: &Scene| {
+            for model in scene.models() {
+                model.mark_model_sv_updated();
+            }
+        };
+
+        mark_models_updated(&scene);
+        scene.update_transforms();
+        assert!(!scene.models().any(Model::is_model_sv_dirty));
+
+        scene.entity_mut(grandchild).unwrap().transform.scale = Vec3::splat(2.0);
+        scene.update_transforms();
+        assert!(scene.model(grandchild).unwrap().is_model_sv_dirty());
+        assert!(!scene.model(child).unwrap().is_model_sv_dirty());
+        assert!(!scene.model(root).unwrap().is_model_sv_dirty());
+        scene.entity_mut(grandchild).unwrap().transform = offset;
+
+        // Moving the root marks every descendant as needing an update, and
+        // turns the lights attached to them.
+        mark_models_updated(&scene);
+
+        scene.entity_mut(root).unwrap().transform = Transform {
+            rotation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            ..Transform::IDENTITY
+        };
+        scene.update_transforms();
+        assert!(scene.models().all(Model::is_model_sv_dirty));
+        assert!(world_translation(&scene, grandchild).abs_diff_eq(Vec3::new(0.0, 0.0, -2.0), 1e-5));
+        assert!(scene.spot_lights()[0]
+            .direction
+            .abs_diff_eq(Vec3::NEG_X, 1e-5));
+
+        // Despawning the middle entity detaches its child, and the ids of the
+        // other entities stay valid.
+        assert!(scene.despawn(child).is_some());
+        assert!(scene.despawn(child).is_none());
+        assert_eq!(
+            Err(AttachError::NoSuchEntity(child)),
+            scene.attach(grandchild, child)
+        );
+        scene.update_transforms();
+        assert_eq!(None, scene.entity(grandchild).unwrap().parent());
+        assert_eq!(offset.translation, world_translation(&scene, grandchild));
+        assert_eq!(0, scene.children(root).count());
+    }
+
+    #[test]
+    fn rays_pick_the_closest_model() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let content = ContentManager::new(renderer.context().clone());
+        let cube = Rc::new(builtin_mesh(
+            renderer.gpu().device(),
+            renderer.bind_group_layouts(),
+            BuiltinMesh::Cube,
+            &MaterialBuilder::new().build(content.default_textures()),
+        ));
+
+        let mut scene = Scene::new(test_camera(4, 4));
+
+        // A cube turned to a diamond in front of another cube.
+        let far = scene.spawn_model(Model::new(
+            cube.clone(),
+            Vec3::new(0.0, 0.0, -3.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+        let near = scene.spawn_model(Model::new(
+            cube,
+            Vec3::ZERO,
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+            Vec3::ONE,
+        ));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        let hit = scene.pick_triangles(&ray).unwrap();
+        assert_eq!(near, hit.entity);
+        assert!((hit.distance - 4.5).abs() < 1e-5, "{hit:?}");
+
+        // Near the diamond's corner the ray is inside its bounding box but
+        // passes beside its triangles.
+        let corner_ray = Ray::new(Vec3::new(0.45, 0.45, 5.0), Vec3::NEG_Z);
+        assert_eq!(Some(near), scene.pick(&corner_ray).map(|hit| hit.entity));
+        assert_eq!(
+            Some(far),
+            scene.pick_triangles(&corner_ray).map(|hit| hit.entity)
+        );
+
+        let miss = Ray::new(Vec3::new(5.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert_eq!(None, scene.pick_triangles(&miss));
+    }
 }
@@ -6,34 +6,99 @@
 //! a memory layout that exactly matches the shader uniform buffer. In particular
 //! all fields must be aligned to a 16 byte (eg `Vec4`) padding as this is a
 //! WebGPU requirement.
+
+/// Fails the build if the size of a packed uniform struct is not a multiple of
+/// 16 bytes. Use this on every struct that is copied to a uniform buffer.
+macro_rules! assert_uniform_aligned {
+    ($t:ty) => {
+        const _: () = assert!(
+            ::std::mem::size_of::<$t>() % 16 == 0,
+            concat!(
+                "`",
+                stringify!($t),
+                "` must be padded to a multiple of 16 bytes to be used in a uniform buffer"
+            )
+        );
+    };
+}
+
 mod packed_structs;
 
 use glam::Vec4;
-use packed_structs::{
-    PackedDirectionalLight, PackedMaterialConstants, PackedPointLight, PackedSpotLight,
-};
+use packed_structs::PackedMaterialConstants;
+
+pub use packed_structs::{PackedDirectionalLight, PackedPointLight, PackedSpotLight};
 
 use super::{
-    gpu_buffers::{DynamicGpuBuffer, GenericUniformBuffer, UniformBindGroup},
-    lighting::{DirectionalLight, PointLight, SpotLight},
+    gpu_buffers::{
+        uniform_struct, DynamicGpuBuffer, DynamicUniformBuffer, GenericUniformBuffer,
+        StorageBuffer, UniformBindGroup,
+    },
+    lighting::{DirectionalLight, SpotLight},
     materials::Material,
+    passes::{LightClusterPass, PointShadowPass, SsaoPass},
     textures,
+    viewport::Viewport,
 };
 
-// TODO(scott): Use a derive! macro to eliminate the copy-paste in these
-//              `per-frame-*` structs.
-
 /// The standard lighting shader used to render objects with Phong lighting.
 ///
-/// NOTE: The following constants _must_ be kept in sync with the lit shader:
+/// NOTE: The following constants _must_ be kept in sync with
+/// lighting_common.wgsl:
 ///  `MAX_POINT_LIGHTS`
+///  `MAX_SHADOWED_POINT_LIGHTS`
 pub mod lit_shader {
     /// The shader source code.
     pub const SHADER_CODE: &str = include_str!("shaders/lit_shader.wgsl");
-    /// The maximum number of point lights that can be specified per model.
+    /// The maximum number of lights of each type that can be specified per
+    /// frame when the device does not support storage buffers (eg WebGL2).
+    /// There is no limit when lights are read from storage buffers.
     pub const MAX_POINT_LIGHTS: usize = 4;
     pub const MAX_DIRECTIONAL_LIGHTS: usize = 3;
     pub const MAX_SPOT_LIGHTS: usize = 2;
+    /// The maximum number of point lights that can cast shadows per frame.
+    pub const MAX_SHADOWED_POINT_LIGHTS: usize = 2;
+}
+
+/// The physically based lighting shader used to render materials with
+/// `ShadingModel::Pbr`. It takes the same inputs as the lit shader.
+pub mod pbr_shader {
+    /// The shader source code.
+    pub const SHADER_CODE: &str = include_str!("shaders/pbr_shader.wgsl");
+}
+
+/// Declarations prepended to the lit and PBR shaders, such as the uniform
+/// buffers, bindings, vertex shader and point light shadows.
+pub mod lighting_common {
+    /// The shader source code.
+    pub const SHADER_CODE: &str = include_str!("shaders/lighting_common.wgsl");
+}
+
+/// Code appended to the lit and PBR shaders that finds the lights shining on a
+/// fragment. Both versions define the same `fragment_*_light` functions.
+pub mod light_lookup {
+    /// Reads scene lights from storage buffers, and only the point lights in
+    /// the fragment's cluster (see `LightClusterPass`).
+    pub const STORAGE_SHADER_CODE: &str = include_str!("shaders/storage_lights.wgsl");
+    /// Reads every light from the per-frame uniforms, for devices that do not
+    /// support storage buffers.
+    pub const UNIFORM_SHADER_CODE: &str = include_str!("shaders/uniform_lights.wgsl");
+
+    /// Get the source code of a lighting shader (eg `lit_shader::SHADER_CODE`)
+    /// with the shared lighting declarations prepended and the matching light
+    /// lookup appended.
+    pub fn shader_code(lighting_shader_code: &str, storage_lights: bool) -> String {
+        let lookup_code = if storage_lights {
+            STORAGE_SHADER_CODE
+        } else {
+            UNIFORM_SHADER_CODE
+        };
+
+        format!(
+            "{}\n{lighting_shader_code}\n{lookup_code}",
+            super::lighting_common::SHADER_CODE
+        )
+    }
 }
 
 /// Per-frame shader uniforms used by the standard shader model.
@@ -42,19 +107,39 @@ pub mod lit_shader {
 struct PerFramePackedUniforms {
     pub view_projection: glam::Mat4,
     pub view_pos: glam::Vec4,
+    pub view_right: glam::Vec4,
+    pub view_up: glam::Vec4,
     pub directional_lights: [PackedDirectionalLight; lit_shader::MAX_DIRECTIONAL_LIGHTS],
     pub spot_lights: [PackedSpotLight; lit_shader::MAX_SPOT_LIGHTS],
+    pub point_lights: [PackedPointLight; lit_shader::MAX_POINT_LIGHTS],
     pub directional_light_count: u32,
     pub spot_light_count: u32,
-    pub output_is_srgb: u32,
+    pub point_light_count: u32,
     pub time_elapsed_seconds: f32,
+    /// Position and size of the viewport in pixels.
+    pub viewport: glam::Vec4,
+    pub z_near: f32,
+    pub z_far: f32,
+    /// Index of the viewport's first light cluster.
+    pub first_light_cluster: u32,
+    pub _padding: u32,
 }
 
-pub struct PerFrameShaderVals {
-    uniforms: GenericUniformBuffer<PerFramePackedUniforms>,
+assert_uniform_aligned!(PerFramePackedUniforms);
+
+uniform_struct! {
+    /// Stores per-frame shader values such as the camera and scene lights.
+    pub struct PerFrameShaderVals(PerFramePackedUniforms) {
+        label: "per-frame",
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+    }
 }
 
 impl PerFrameShaderVals {
+    /// Number of bytes copied to the GPU by each call to `update_gpu`.
+    pub const GPU_SIZE: wgpu::BufferAddress =
+        std::mem::size_of::<PerFramePackedUniforms>() as wgpu::BufferAddress;
+
     /// Create a new per frame shader values struct. Only one instance is needed
     /// per renderer.
     pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
@@ -78,10 +163,19 @@ impl PerFrameShaderVals {
         self.uniforms.values_mut().view_pos = Vec4::new(view_pos.x, view_pos.y, view_pos.z, 1.0);
     }
 
+    /// Set the world space right and up axes of the camera, which are used to
+    /// turn billboards towards the camera.
+    pub fn set_view_axes(&mut self, right: glam::Vec3, up: glam::Vec3) {
+        let uniforms = self.uniforms.values_mut();
+        uniforms.view_right = right.extend(0.0);
+        uniforms.view_up = up.extend(0.0);
+    }
+
     /// Clear all lighting information.
     pub fn clear_lights(&mut self) {
         self.uniforms.values_mut().directional_light_count = 0;
         self.uniforms.values_mut().spot_light_count = 0;
+        self.uniforms.values_mut().point_light_count = 0;
     }
 
     /// Add directional light to the scene.
@@ -109,47 +203,51 @@ impl PerFrameShaderVals {
         }
     }
 
-    /// Set time elapsed in seconds.
-    pub fn set_time_elapsed_seconds(&mut self, time_elapsed: std::time::Duration) {
-        self.uniforms.values_mut().time_elapsed_seconds = time_elapsed.as_secs_f32();
+    /// Set the number of directional and spot lights in the scene when lights
+    /// are read from storage buffers rather than the per-frame uniforms.
+    pub fn set_storage_light_counts(&mut self, directional_lights: usize, spot_lights: usize) {
+        let uniforms = self.uniforms.values_mut();
+        uniforms.directional_light_count = directional_lights as u32;
+        uniforms.spot_light_count = spot_lights as u32;
     }
 
-    /// Set if the output backbuffer format is SRGB or not.
-    pub fn set_output_is_srgb(&mut self, is_srgb: bool) {
-        self.uniforms.values_mut().output_is_srgb = if is_srgb { 1 } else { 0 };
-    }
+    /// Add a point light to the scene. Point lights are only read from the
+    /// per-frame uniforms when the device does not support light clusters.
+    pub fn add_point_light(&mut self, light: PackedPointLight) {
+        let uniforms = self.uniforms.values_mut();
 
-    /// Gets the bind group layout describing any instance of `PerFrameUniforms`.
-    pub fn bind_group_layout_desc() -> wgpu::BindGroupLayoutDescriptor<'static> {
-        wgpu::BindGroupLayoutDescriptor {
-            label: Some("per-frame bind group layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        }
-    }
-}
+        debug_assert!(uniforms.point_light_count < lit_shader::MAX_POINT_LIGHTS as u32);
 
-impl UniformBindGroup for PerFrameShaderVals {
-    fn bind_group(&self) -> &wgpu::BindGroup {
-        self.uniforms.bind_group()
+        if uniforms.point_light_count < lit_shader::MAX_POINT_LIGHTS as u32 {
+            uniforms.point_lights[uniforms.point_light_count as usize] = light;
+            uniforms.point_light_count += 1;
+        }
     }
-}
 
-impl DynamicGpuBuffer for PerFrameShaderVals {
-    fn update_gpu(&self, queue: &wgpu::Queue) {
-        self.uniforms.update_gpu(queue)
+    /// Set the viewport and camera clip planes used to find each fragment's
+    /// light cluster, and the index of the viewport's first light cluster.
+    pub fn set_light_clusters(
+        &mut self,
+        viewport: &Viewport,
+        z_near: f32,
+        z_far: f32,
+        first_light_cluster: u32,
+    ) {
+        let uniforms = self.uniforms.values_mut();
+        uniforms.viewport = Vec4::new(
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+        );
+        uniforms.z_near = z_near;
+        uniforms.z_far = z_far;
+        uniforms.first_light_cluster = first_light_cluster;
     }
 
-    fn is_dirty(&self) -> bool {
-        self.uniforms.is_dirty()
+    /// Set time elapsed in seconds.
+    pub fn set_time_elapsed_seconds(&mut self, time_elapsed: std::time::Duration) {
+        self.uniforms.values_mut().time_elapsed_seconds = time_elapsed.as_secs_f32();
     }
 }
 
@@ -159,91 +257,118 @@ impl DynamicGpuBuffer for PerFrameShaderVals {
 struct PerModelPackedUniforms {
     pub local_to_world: glam::Mat4,
     pub world_to_local: glam::Mat4,
-    pub point_light: [PackedPointLight; lit_shader::MAX_POINT_LIGHTS],
-    pub point_light_count: u32,
-    pub _padding: [u32; 3],
 }
 
-/// Stores per-model shader values that are copied to the GPU prior to rendering
-/// a model.
+assert_uniform_aligned!(PerModelPackedUniforms);
+
+/// Stores the per-model shader values of every model in a single uniform
+/// buffer. Each model is given a slot in the buffer, and is drawn by binding
+/// the buffer with the slot's dynamic offset. One instance per renderer.
 #[derive(Debug)]
 pub struct PerModelShaderVals {
-    uniforms: GenericUniformBuffer<PerModelPackedUniforms>,
+    uniforms: DynamicUniformBuffer<PerModelPackedUniforms>,
+    /// Slots freed by `remove` that are reused before adding new slots.
+    free_slots: Vec<usize>,
 }
 
 impl PerModelShaderVals {
-    /// Create a new PerModelShaderVals object. One instance per model.
+    /// Create a new PerModelShaderVals object with no model slots.
     pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
         Self {
-            uniforms: GenericUniformBuffer::<PerModelPackedUniforms>::new(
+            uniforms: DynamicUniformBuffer::new(
                 device,
-                Some("per-model shader vals"),
-                Default::default(),
+                "per-model shader vals",
                 &layouts.per_model_layout,
             ),
+            free_slots: Vec::new(),
         }
     }
 
-    /// Set local to world transform matrix.
-    #[allow(dead_code)]
-    pub fn set_local_to_world(&mut self, local_to_world: glam::Mat4) {
-        self.uniforms.values_mut().local_to_world = local_to_world;
-        self.uniforms.values_mut().world_to_local = local_to_world.inverse();
-        debug_assert!(!self.uniforms.values().world_to_local.is_nan());
-    }
-
-    /// Clear all lighting information.
-    pub fn clear_lights(&mut self) {
-        self.uniforms.values_mut().point_light_count = 0;
-    }
-
-    /// Add point light to the model.
-    pub fn add_point_light(&mut self, light: &PointLight) {
-        debug_assert!(light.ambient >= 0.0 && light.ambient <= 1.0);
-        debug_assert!(light.specular >= 0.0 && light.specular <= 1.0);
-
-        let uniforms = self.uniforms.values_mut();
-
-        if uniforms.point_light_count < lit_shader::MAX_POINT_LIGHTS as u32 {
-            debug_assert!(uniforms.point_light_count < lit_shader::MAX_POINT_LIGHTS as u32);
-
-            uniforms.point_light[uniforms.point_light_count as usize] = light.clone().into();
-            uniforms.point_light_count += 1;
+    /// Create shader values on `device` for the same model slots as these
+    /// values, eg after the GPU device was lost. Every slot is copied to the
+    /// GPU by the next `update_gpu`.
+    pub fn recreate(&self, device: &wgpu::Device, layouts: &BindGroupLayouts) -> Self {
+        Self {
+            uniforms: self.uniforms.recreate(device, &layouts.per_model_layout),
+            free_slots: self.free_slots.clone(),
         }
     }
 
-    /// Gets the bind group layout describing any instance of `PerModelUniforms`.
+    /// Gets the bind group layout describing a single model's values, which are
+    /// selected with a dynamic offset.
     pub fn bind_group_layout_desc() -> wgpu::BindGroupLayoutDescriptor<'static> {
+        const ENTRIES: &[wgpu::BindGroupLayoutEntry] = &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<PerModelPackedUniforms>() as u64,
+                ),
+            },
+            count: None,
+        }];
+
         wgpu::BindGroupLayoutDescriptor {
             label: Some("per-model bind group layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            entries: ENTRIES,
         }
     }
-}
 
-impl DynamicGpuBuffer for PerModelShaderVals {
-    fn update_gpu(&self, queue: &wgpu::Queue) {
-        self.uniforms.update_gpu(queue)
+    /// Add a slot for a new model and return its index. Slots freed by
+    /// `remove` are reused first.
+    pub fn insert(&mut self) -> usize {
+        match self.free_slots.pop() {
+            Some(slot) => {
+                *self.uniforms.values_mut(slot) = Default::default();
+                slot
+            }
+            None => self.uniforms.push(Default::default()),
+        }
     }
 
-    fn is_dirty(&self) -> bool {
-        self.uniforms.is_dirty()
+    /// Free `slot` so it can be reused by a later `insert`.
+    pub fn remove(&mut self, slot: usize) {
+        debug_assert!(!self.free_slots.contains(&slot));
+        self.free_slots.push(slot);
     }
-}
 
-impl UniformBindGroup for PerModelShaderVals {
-    fn bind_group(&self) -> &wgpu::BindGroup {
-        self.uniforms.bind_group()
+    /// Set the local to world transform matrix of the model in `slot`.
+    pub fn set_local_to_world(&mut self, slot: usize, local_to_world: glam::Mat4) {
+        let values = self.uniforms.values_mut(slot);
+        values.local_to_world = local_to_world;
+        values.world_to_local = local_to_world.inverse();
+        debug_assert!(!values.world_to_local.is_nan());
+    }
+
+    /// Get the bind group and dynamic offset that bind the model in `slot`.
+    pub fn binding(&self, slot: usize) -> PerModelBinding<'_> {
+        PerModelBinding {
+            bind_group: self.uniforms.bind_group(),
+            offset: self.uniforms.offset(slot),
+        }
     }
+
+    /// Copy every model's changed values to the GPU with a single write, and
+    /// return the number of bytes copied.
+    pub fn update_gpu(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &BindGroupLayouts,
+    ) -> wgpu::BufferAddress {
+        self.uniforms
+            .update_gpu(device, queue, &layouts.per_model_layout)
+    }
+}
+
+/// The per-model shader values of one model, which are bound by passing
+/// `offset` as the dynamic offset of `bind_group`.
+#[derive(Clone, Copy, Debug)]
+pub struct PerModelBinding<'a> {
+    pub bind_group: &'a wgpu::BindGroup,
+    pub offset: u32,
 }
 
 /// Per-submesh uniform values that are used by the standard shader model.
@@ -253,6 +378,8 @@ struct PerSubmeshPackedUniforms {
     pub material: PackedMaterialConstants,
 }
 
+assert_uniform_aligned!(PerSubmeshPackedUniforms);
+
 /// Responsible for storing per-submesh shader values used during a submesh
 /// rendering pass.
 #[derive(Debug)]
@@ -261,6 +388,9 @@ pub struct PerSubmeshShaderVals {
     _diffuse_view: wgpu::TextureView,
     _specular_view: wgpu::TextureView,
     _emissive_view: wgpu::TextureView,
+    _metallic_view: wgpu::TextureView,
+    _roughness_view: wgpu::TextureView,
+    _ao_view: wgpu::TextureView,
     uniforms: PerSubmeshPackedUniforms,
     gpu_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
@@ -273,11 +403,20 @@ impl PerSubmeshShaderVals {
     pub const DIFFUSE_VIEW_BINDING_SLOT: u32 = 2;
     pub const SPECULAR_VIEW_BINDING_SLOT: u32 = 3;
     pub const EMISSIVE_VIEW_BINDING_SLOT: u32 = 4;
+    pub const METALLIC_VIEW_BINDING_SLOT: u32 = 5;
+    pub const ROUGHNESS_VIEW_BINDING_SLOT: u32 = 6;
+    pub const AO_VIEW_BINDING_SLOT: u32 = 7;
 
     pub fn new(device: &wgpu::Device, layouts: &BindGroupLayouts, material: &Material) -> Self {
         // TODO: How to move this into the GenericUniformBuffer type when we have
         // additional bind group entries for the textures?
-        let tex_sampler = textures::create_default_sampler(device);
+        // Scaled or offset texture coordinates are expected to repeat the
+        // texture rather than stretching the edge texels.
+        let tex_sampler = if material.has_uv_transform() {
+            textures::create_repeating_sampler(device)
+        } else {
+            textures::create_default_sampler(device)
+        };
         let diffuse_view = material
             .diffuse_map
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -287,6 +426,15 @@ impl PerSubmeshShaderVals {
         let emissive_view = material
             .emissive_map
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let metallic_view = material
+            .metallic_map
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let roughness_view = material
+            .roughness_map
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let ao_view = material
+            .ao_map
+            .create_view(&wgpu::TextureViewDescriptor::default());
 
         let values = PerSubmeshPackedUniforms {
             material: material.clone().into(),
@@ -325,6 +473,18 @@ impl PerSubmeshShaderVals {
                     binding: Self::EMISSIVE_VIEW_BINDING_SLOT,
                     resource: wgpu::BindingResource::TextureView(&emissive_view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: Self::METALLIC_VIEW_BINDING_SLOT,
+                    resource: wgpu::BindingResource::TextureView(&metallic_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: Self::ROUGHNESS_VIEW_BINDING_SLOT,
+                    resource: wgpu::BindingResource::TextureView(&roughness_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: Self::AO_VIEW_BINDING_SLOT,
+                    resource: wgpu::BindingResource::TextureView(&ao_view),
+                },
             ],
         });
 
@@ -333,6 +493,9 @@ impl PerSubmeshShaderVals {
             _diffuse_view: diffuse_view,
             _specular_view: specular_view,
             _emissive_view: emissive_view,
+            _metallic_view: metallic_view,
+            _roughness_view: roughness_view,
+            _ao_view: ao_view,
             uniforms: values,
             gpu_buffer,
             bind_group,
@@ -340,6 +503,14 @@ impl PerSubmeshShaderVals {
         }
     }
 
+    /// Replace the material constants (colors, specular power, metallic,
+    /// roughness, uv transform and flags) with values from `material`. Texture maps are bound when the
+    /// shader values are created and are not changed by this method.
+    pub fn set_material_constants(&mut self, material: &Material) {
+        self.uniforms.material = material.clone().into();
+        self.is_dirty.set(true);
+    }
+
     /// Gets the bind group layout describing any instance of `PerMeshUniforms`.
     ///
     /// Expected bind group inputs:
@@ -348,6 +519,9 @@ impl PerSubmeshShaderVals {
     ///  2 - diffuse texture
     ///  3 - specular texture
     ///  4 - emissive texture
+    ///  5 - metallic texture
+    ///  6 - roughness texture
+    ///  7 - ambient occlusion texture
     pub fn bind_group_layout_desc() -> wgpu::BindGroupLayoutDescriptor<'static> {
         wgpu::BindGroupLayoutDescriptor {
             label: Some("per-mesh bind group layout"),
@@ -398,6 +572,36 @@ impl PerSubmeshShaderVals {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: Self::METALLIC_VIEW_BINDING_SLOT,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: Self::ROUGHNESS_VIEW_BINDING_SLOT,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: Self::AO_VIEW_BINDING_SLOT,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
             ],
         }
     }
@@ -420,12 +624,285 @@ impl UniformBindGroup for PerSubmeshShaderVals {
     }
 }
 
+/// Scene lights that the lit shaders read from storage buffers, which lets a
+/// scene have any number of lights.
+struct SceneLightBuffers {
+    directional_lights: StorageBuffer<PackedDirectionalLight>,
+    spot_lights: StorageBuffer<PackedSpotLight>,
+}
+
+/// Textures and buffers filled earlier in the frame that the lit shaders read
+/// when shading, such as point light shadow maps, ambient occlusion, light
+/// clusters and scene lights.
+///
+/// Lights are read from storage buffers when the device supports light
+/// clusters, and from the per-frame uniforms otherwise.
+///
+/// The bind group is only recreated when one of these resources is replaced,
+/// eg after allocating a new shadow map, when the window is resized or when
+/// more lights are added than fit in the storage buffers.
+pub struct LightingResources {
+    bind_group: wgpu::BindGroup,
+    /// Texture views that `bind_group` was created with.
+    view_ids: [wgpu::Id<wgpu::TextureView>; 3],
+    /// Storage buffers that `bind_group` was created with.
+    buffer_ids: Option<[wgpu::Id<wgpu::Buffer>; 5]>,
+    /// Allocated when lights are read from storage buffers.
+    scene_lights: Option<SceneLightBuffers>,
+}
+
+impl LightingResources {
+    const SHADOW_SAMPLER_BINDING_SLOT: u32 = 0;
+    const FIRST_SHADOW_MAP_BINDING_SLOT: u32 = 1;
+    const AMBIENT_OCCLUSION_BINDING_SLOT: u32 = 3;
+    const FIRST_LIGHT_CLUSTER_BINDING_SLOT: u32 = 4;
+    const FIRST_SCENE_LIGHT_BINDING_SLOT: u32 = 7;
+    /// Bindings used by every device, followed by the storage buffer bindings
+    /// that are only used when the device supports light clusters.
+    const LAYOUT_ENTRIES: [wgpu::BindGroupLayoutEntry; 9] = {
+        const CUBE_MAP_ENTRY: wgpu::BindingType = wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            multisampled: false,
+        };
+        const STORAGE_ENTRY: wgpu::BindingType = wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        const fn entry(binding: u32, ty: wgpu::BindingType) -> wgpu::BindGroupLayoutEntry {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty,
+                count: None,
+            }
+        }
+
+        // The lit shader declares one cube map binding per shadowed light.
+        assert!(lit_shader::MAX_SHADOWED_POINT_LIGHTS == 2);
+
+        [
+            entry(
+                Self::SHADOW_SAMPLER_BINDING_SLOT,
+                wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+            ),
+            entry(Self::FIRST_SHADOW_MAP_BINDING_SLOT, CUBE_MAP_ENTRY),
+            entry(Self::FIRST_SHADOW_MAP_BINDING_SLOT + 1, CUBE_MAP_ENTRY),
+            entry(
+                Self::AMBIENT_OCCLUSION_BINDING_SLOT,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            ),
+            entry(Self::FIRST_LIGHT_CLUSTER_BINDING_SLOT, STORAGE_ENTRY),
+            entry(Self::FIRST_LIGHT_CLUSTER_BINDING_SLOT + 1, STORAGE_ENTRY),
+            entry(Self::FIRST_LIGHT_CLUSTER_BINDING_SLOT + 2, STORAGE_ENTRY),
+            entry(Self::FIRST_SCENE_LIGHT_BINDING_SLOT, STORAGE_ENTRY),
+            entry(Self::FIRST_SCENE_LIGHT_BINDING_SLOT + 1, STORAGE_ENTRY),
+        ]
+    };
+
+    /// Create a new lighting resources bind group. Only one instance is needed
+    /// per renderer. `light_cluster_pass` must be `None` if the device does
+    /// not support light clusters, in which case lights are read from the
+    /// per-frame uniforms.
+    pub fn new(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        point_shadow_pass: &PointShadowPass,
+        ssao_pass: &SsaoPass,
+        light_cluster_pass: Option<&LightClusterPass>,
+    ) -> Self {
+        let scene_lights = light_cluster_pass.map(|_| SceneLightBuffers {
+            directional_lights: StorageBuffer::new(device, "directional lights", 1),
+            spot_lights: StorageBuffer::new(device, "spot lights", 1),
+        });
+
+        let mut resources = Self {
+            bind_group: Self::create_bind_group(
+                device,
+                layouts,
+                point_shadow_pass,
+                ssao_pass,
+                light_cluster_pass.zip(scene_lights.as_ref()),
+            ),
+            view_ids: Self::views(point_shadow_pass, ssao_pass).map(|view| view.global_id()),
+            buffer_ids: None,
+            scene_lights,
+        };
+
+        resources.buffer_ids = resources.buffer_ids(light_cluster_pass);
+        resources
+    }
+
+    /// Copy the scene's directional and spot lights to the GPU when lights are
+    /// read from storage buffers. Does nothing otherwise.
+    pub fn write_lights(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        directional_lights: &[PackedDirectionalLight],
+        spot_lights: &[PackedSpotLight],
+    ) {
+        if let Some(scene_lights) = &mut self.scene_lights {
+            scene_lights
+                .directional_lights
+                .write(device, queue, directional_lights);
+            scene_lights.spot_lights.write(device, queue, spot_lights);
+        }
+    }
+
+    /// Recreate the bind group if any of the resources filled by
+    /// `point_shadow_pass`, `ssao_pass`, `light_cluster_pass` or
+    /// `write_lights` were replaced since it was created.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        point_shadow_pass: &PointShadowPass,
+        ssao_pass: &SsaoPass,
+        light_cluster_pass: Option<&LightClusterPass>,
+    ) {
+        let view_ids = Self::views(point_shadow_pass, ssao_pass).map(|view| view.global_id());
+        let buffer_ids = self.buffer_ids(light_cluster_pass);
+
+        if view_ids != self.view_ids || buffer_ids != self.buffer_ids {
+            self.bind_group = Self::create_bind_group(
+                device,
+                layouts,
+                point_shadow_pass,
+                ssao_pass,
+                light_cluster_pass.zip(self.scene_lights.as_ref()),
+            );
+            self.view_ids = view_ids;
+            self.buffer_ids = buffer_ids;
+        }
+    }
+
+    /// Get the bind group the lit shaders read lighting resources from.
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Gets the bind group layout used by the lit shaders to read lighting
+    /// resources. The light cluster bindings are only included when
+    /// `clustered` is true.
+    ///
+    /// Expected bind group inputs:
+    ///  0 - point shadow comparison sampler
+    ///  1..=2 - one depth cube map per shadow casting point light
+    ///  3 - screen space ambient occlusion
+    ///  4 - point lights (clustered only)
+    ///  5 - number of lights in each cluster (clustered only)
+    ///  6 - indices of the lights in each cluster (clustered only)
+    ///  7 - directional lights (clustered only)
+    ///  8 - spot lights (clustered only)
+    pub fn bind_group_layout_desc(clustered: bool) -> wgpu::BindGroupLayoutDescriptor<'static> {
+        let entry_count = if clustered {
+            Self::LAYOUT_ENTRIES.len()
+        } else {
+            Self::FIRST_LIGHT_CLUSTER_BINDING_SLOT as usize
+        };
+
+        wgpu::BindGroupLayoutDescriptor {
+            label: Some("lighting resources bind group layout"),
+            entries: &Self::LAYOUT_ENTRIES[..entry_count],
+        }
+    }
+
+    fn views<'a>(
+        point_shadow_pass: &'a PointShadowPass,
+        ssao_pass: &'a SsaoPass,
+    ) -> [&'a wgpu::TextureView; 3] {
+        [
+            point_shadow_pass.cube_map_view(0),
+            point_shadow_pass.cube_map_view(1),
+            ssao_pass.occlusion_view(),
+        ]
+    }
+
+    fn buffers<'a>(
+        light_cluster_pass: &'a LightClusterPass,
+        scene_lights: &'a SceneLightBuffers,
+    ) -> [&'a wgpu::Buffer; 5] {
+        [
+            light_cluster_pass.point_lights_buffer(),
+            light_cluster_pass.cluster_light_counts_buffer(),
+            light_cluster_pass.cluster_light_indices_buffer(),
+            scene_lights.directional_lights.gpu_buffer(),
+            scene_lights.spot_lights.gpu_buffer(),
+        ]
+    }
+
+    fn buffer_ids(
+        &self,
+        light_cluster_pass: Option<&LightClusterPass>,
+    ) -> Option<[wgpu::Id<wgpu::Buffer>; 5]> {
+        light_cluster_pass
+            .zip(self.scene_lights.as_ref())
+            .map(|(pass, scene_lights)| {
+                Self::buffers(pass, scene_lights).map(|buffer| buffer.global_id())
+            })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layouts: &BindGroupLayouts,
+        point_shadow_pass: &PointShadowPass,
+        ssao_pass: &SsaoPass,
+        storage: Option<(&LightClusterPass, &SceneLightBuffers)>,
+    ) -> wgpu::BindGroup {
+        let views = Self::views(point_shadow_pass, ssao_pass);
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: Self::SHADOW_SAMPLER_BINDING_SLOT,
+                resource: wgpu::BindingResource::Sampler(point_shadow_pass.sampler()),
+            },
+            wgpu::BindGroupEntry {
+                binding: Self::FIRST_SHADOW_MAP_BINDING_SLOT,
+                resource: wgpu::BindingResource::TextureView(views[0]),
+            },
+            wgpu::BindGroupEntry {
+                binding: Self::FIRST_SHADOW_MAP_BINDING_SLOT + 1,
+                resource: wgpu::BindingResource::TextureView(views[1]),
+            },
+            wgpu::BindGroupEntry {
+                binding: Self::AMBIENT_OCCLUSION_BINDING_SLOT,
+                resource: wgpu::BindingResource::TextureView(views[2]),
+            },
+        ];
+
+        // Storage buffers follow the textures in binding order.
+        if let Some((light_cluster_pass, scene_lights)) = storage {
+            entries.extend(
+                Self::buffers(light_cluster_pass, scene_lights)
+                    .iter()
+                    .zip(Self::FIRST_LIGHT_CLUSTER_BINDING_SLOT..)
+                    .map(|(buffer, binding)| wgpu::BindGroupEntry {
+                        binding,
+                        resource: buffer.as_entire_binding(),
+                    }),
+            );
+        }
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("lighting resources bind group"),
+            layout: &layouts.lighting_resources_layout,
+            entries: &entries,
+        })
+    }
+}
+
 /// A registry of bind group layouts used by this renderer.
 #[derive(Debug)]
 pub struct BindGroupLayouts {
     pub per_frame_layout: wgpu::BindGroupLayout,
     pub per_model_layout: wgpu::BindGroupLayout,
     pub per_submesh_layout: wgpu::BindGroupLayout,
+    pub lighting_resources_layout: wgpu::BindGroupLayout,
 }
 
 impl BindGroupLayouts {
@@ -438,6 +915,9 @@ impl BindGroupLayouts {
                 .create_bind_group_layout(&PerModelShaderVals::bind_group_layout_desc()),
             per_submesh_layout: device
                 .create_bind_group_layout(&PerSubmeshShaderVals::bind_group_layout_desc()),
+            lighting_resources_layout: device.create_bind_group_layout(
+                &LightingResources::bind_group_layout_desc(LightClusterPass::is_supported(device)),
+            ),
         }
     }
 }
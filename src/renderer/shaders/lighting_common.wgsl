@@ -0,0 +1,285 @@
+//============================================================================//
+// Shared lighting declarations                                               //
+//============================================================================//
+// Prepended to the lit and PBR shaders so both lighting paths read the same
+// uniforms, vertex inputs and shadow maps.
+//
+// NOTE: The light limits _must_ be kept in sync with `lit_shader` in
+// shaders.rs.
+const MAX_POINT_LIGHTS: u32 = 4;
+const MAX_DIRECTIONAL_LIGHTS: u32 = 3;
+const MAX_SPOT_LIGHTS: u32 = 2;
+const MAX_SHADOWED_POINT_LIGHTS: u32 = 2;
+
+/// Distance in world units that fragments are moved towards a point light
+/// before comparing against its shadow map, so surfaces do not shadow
+/// themselves.
+const POINT_SHADOW_BIAS: f32 = 0.02;
+/// Distance fragments are moved along their normal before sampling a point
+/// light shadow map, scaled by the distance to the light since shadow map
+/// texels cover more of the scene further from the light.
+const POINT_SHADOW_NORMAL_OFFSET: f32 = 0.01;
+
+/// The material is not lit by scene lights.
+const MATERIAL_FLAG_UNLIT: u32 = 1u;
+/// The material is blended over what is behind it by its alpha.
+const MATERIAL_FLAG_ALPHA_BLEND: u32 = 2u;
+
+//============================================================================//
+// Uniform Buffers                                                            //
+//============================================================================//
+// TODO: Consider using structs to represent the packed lighting data, and
+// structs to represent unpacked lights/materials. Refactor the functions to
+// take those parameters which should make this all a lot less confusing.
+struct PerFrameUniforms {
+    /// Camera view projection.
+    view_projection: mat4x4<f32>,
+    /// Camera world space position.
+    view_pos: vec4<f32>,
+    /// Camera world space right axis.
+    view_right: vec4<f32>,
+    /// Camera world space up axis.
+    view_up: vec4<f32>,
+    directional_light: array<PackedDirectionalLight, MAX_DIRECTIONAL_LIGHTS>,
+    spot_light: array<PackedSpotLight, MAX_SPOT_LIGHTS>,
+    point_light: array<PackedPointLight, MAX_POINT_LIGHTS>,
+    directional_light_count: u32,
+    spot_light_count: u32,
+    point_light_count: u32,
+    time_elapsed_seconds: f32,
+    /// Viewport position and size in pixels.
+    viewport: vec4<f32>,
+    /// Distance to the camera where the first light cluster slice starts.
+    z_near: f32,
+    /// Distance to the camera where the last light cluster slice ends.
+    z_far: f32,
+    /// Index of the viewport's first light cluster.
+    first_light_cluster: u32,
+    _padding: u32,
+};
+
+struct PerSubmeshUniforms {
+    material: PackedMaterialConstants
+}
+
+struct PackedMaterialConstants {
+    ambient_color: vec4<f32>,  // .w is unused. Unused by PBR shading.
+    diffuse_color: vec4<f32>,  // Diffuse or PBR base color, .w is alpha.
+    specular_color: vec4<f32>, // .w is power. Unused by PBR shading.
+    uv_transform: vec4<f32>,   // .xy is uv scale, .zw is uv offset.
+    emissive_color: vec4<f32>, // .w is unused.
+    pbr: vec4<f32>,            // .x is metallic, .y is roughness, .zw is unused.
+    flags: u32,                // `MATERIAL_FLAG_*` bits.
+    padding_0: u32,
+    padding_1: u32,
+    padding_2: u32,
+}
+
+struct PackedDirectionalLight {
+    /// Direction from light to source.
+    ///   .xyz is normalized
+    ///   .w is ambient contribution.
+    direction: vec4<f32>,
+    /// Color
+    ///   .w is specular contribution.
+    color: vec4<f32>,
+}
+
+struct PackedPointLight {
+    /// Point light world space position. (`w` is the ambient term).
+    pos: vec4<f32>,
+    /// Point light color. (`w` is the specular term).
+    color: vec4<f32>,
+    /// Point light attenuation.
+    ///  `x`: constant term.
+    ///  `y`: linear term.
+    ///  `z`: quadratic term.
+    ///  `w`: unused.
+    attenuation: vec4<f32>,
+    /// Point light range and shadows.
+    ///  `x`: distance where the light fades out to nothing.
+    ///  `y`: shadow map index, or -1 if the light does not cast shadows.
+    ///  `z`: distance from the light that shadows are cast.
+    ///  `w`: unused.
+    range: vec4<f32>,
+}
+
+struct PackedSpotLight {
+    /// Point light world space position. (`w` is the precomputed cutoff angle).
+    pos: vec4<f32>,
+    /// Normalized direction pointing away from the light.
+    ///   .xyz is normalized
+    ///   .w is ambient contribution.
+    direction: vec4<f32>,
+    /// Color
+    ///   .w is specular contribution.
+    color: vec4<f32>,
+    /// Attenuation
+    ///   .x is constant term, .y is linear term and .z is quadratic term.
+    ///   .w is the precomputed outer cutoff angle.
+    attenuation: vec4<f32>,
+    /// Distance where the light fades out to nothing. (`yzw` are unused).
+    range: vec4<f32>,
+}
+
+//============================================================================//
+// Shader inputs                                                              //
+//============================================================================//
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+}
+
+/// Transforms of the model being drawn, which must match `ModelInstance`.
+struct InstanceInput {
+    /// Model -> world transform.
+    @location(3) local_to_world_0: vec4<f32>,
+    @location(4) local_to_world_1: vec4<f32>,
+    @location(5) local_to_world_2: vec4<f32>,
+    @location(6) local_to_world_3: vec4<f32>,
+    /// World -> model transform.
+    @location(7) world_to_local_0: vec4<f32>,
+    @location(8) world_to_local_1: vec4<f32>,
+    @location(9) world_to_local_2: vec4<f32>,
+    @location(10) world_to_local_3: vec4<f32>,
+}
+
+struct VertexOutput {
+    /// Vertex output in "clip space" which can be visualized as:
+    ///  (.u must be set to 1.0).
+    ///
+    ///  <----------X---------->
+    /// ^
+    /// |          +1
+    /// |
+    /// Y    -1     .     +1
+    /// |
+    /// |          -1
+    /// v
+    ///
+    /// See: https://webgpufundamentals.org/webgpu/lessons/webgpu-fundamentals.html
+    @builtin(position) position_cs: vec4<f32>,
+    /// Vertex position in world space (rather than clip space) to allow world
+    /// space lighting calculations in the fragment shader.
+    @location(0) position_ws: vec3<f32>,
+    /// Normal vector from the vertex.
+    @location(1) normal: vec3<f32>,
+    /// UV texture coordinates of the vertex.
+    @location(2) tex_coords: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> per_frame: PerFrameUniforms;
+
+@group(1) @binding(0)
+var<uniform> per_submesh: PerSubmeshUniforms;
+
+@group(1) @binding(1)
+var tex_sampler: sampler;
+
+// Bindings 2 and 3 are the diffuse and specular maps of the lit shader, or the
+// base color map of the PBR shader. Bindings 5 to 7 are only read by the PBR
+// shader.
+
+@group(1) @binding(4)
+var emissive_texture: texture_2d<f32>;
+
+@group(2) @binding(0)
+var point_shadow_sampler: sampler_comparison;
+
+@group(2) @binding(1)
+var point_shadow_map_0: texture_depth_cube;
+
+@group(2) @binding(2)
+var point_shadow_map_1: texture_depth_cube;
+
+/// Screen space ambient occlusion, where 1 is unoccluded. This is a single
+/// unoccluded texel when ambient occlusion is disabled.
+@group(2) @binding(3)
+var ambient_occlusion_map: texture_2d<f32>;
+
+//============================================================================//
+// Vertex shader                                                              //
+//============================================================================//
+@vertex
+fn vs_main(v_in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var v_out: VertexOutput;
+
+    let local_to_world = mat4x4<f32>(
+        instance.local_to_world_0,
+        instance.local_to_world_1,
+        instance.local_to_world_2,
+        instance.local_to_world_3);
+    let world_to_local = mat4x4<f32>(
+        instance.world_to_local_0,
+        instance.world_to_local_1,
+        instance.world_to_local_2,
+        instance.world_to_local_3);
+
+    v_out.position_cs = per_frame.view_projection
+        * local_to_world
+        * vec4<f32>(v_in.position, 1.0);
+    v_out.position_ws = (local_to_world * vec4<f32>(v_in.position, 1.0)).xyz;
+    v_out.normal = (transpose(world_to_local) * vec4<f32>(v_in.normal, 1.0)).xyz;
+    v_out.tex_coords = v_in.tex_coords;
+
+    return v_out;
+}
+
+//============================================================================//
+// Shared lighting functions                                                  //
+//============================================================================//
+
+/// Calculate how much of a point light reaches a fragment, from 0 when the
+/// fragment is in shadow to 1 when it is fully lit.
+///
+///  `frag_pos`:  Fragment world space position.
+///  `frag_normal`: Fragment normal vector direction (normalized).
+///  `light_pos`: World space position of the light.
+///  `shadow_map`: The light's shadow map index, or -1 if it casts no shadows.
+///  `shadow_distance`: Distance from the light that shadows are cast.
+fn point_light_shadow(
+        frag_pos: vec3<f32>,
+        frag_normal: vec3<f32>,
+        light_pos: vec3<f32>,
+        shadow_map: i32,
+        shadow_distance: f32,
+) -> f32 {
+    if (shadow_map < 0) {
+        return 1.0;
+    }
+
+    let light_distance = length(frag_pos - light_pos);
+    let offset_pos = frag_pos + frag_normal * light_distance * POINT_SHADOW_NORMAL_OFFSET;
+    let light_to_frag = offset_pos - light_pos;
+    let depth = (length(light_to_frag) - POINT_SHADOW_BIAS) / shadow_distance;
+
+    // Nothing casts shadows past the shadow distance.
+    if (depth >= 1.0) {
+        return 1.0;
+    }
+
+    // Bindings can not be indexed, so each shadow map is sampled separately.
+    if (shadow_map == 0) {
+        return textureSampleCompareLevel(
+            point_shadow_map_0, point_shadow_sampler, light_to_frag, depth);
+    } else {
+        return textureSampleCompareLevel(
+            point_shadow_map_1, point_shadow_sampler, light_to_frag, depth);
+    }
+}
+
+/// Smoothly fades a light from full strength to nothing as `distance` reaches
+/// the light's `range`, so lights do not have an infinitely long tail.
+fn range_falloff(distance: f32, range: f32) -> f32 {
+    let ratio = distance / range;
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window;
+}
+
+/// Get the screen space ambient occlusion at the fragment at `position_cs`.
+fn ambient_occlusion(position_cs: vec4<f32>) -> f32 {
+    let max_pixel = vec2<i32>(textureDimensions(ambient_occlusion_map)) - 1;
+    return textureLoad(ambient_occlusion_map, min(vec2<i32>(position_cs.xy), max_pixel), 0).x;
+}
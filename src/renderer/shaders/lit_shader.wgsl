@@ -1,128 +1,32 @@
-const MAX_POINT_LIGHTS: u32 = 4;
-const MAX_DIRECTIONAL_LIGHTS: u32 = 3;
-const MAX_SPOT_LIGHTS: u32 = 2;
+// Blinn-Phong lighting. Declarations shared with the PBR shader are prepended
+// from lighting_common.wgsl.
 
-//============================================================================//
-// Uniform Buffers                                                            //
-//============================================================================//
-// TODO: Consider using structs to represent the packed lighting data, and 
-// structs to represent unpacked lights/materials. Refactor the functions to
-// take those parameters which should make this all a lot less confusing.
-struct PerFrameUniforms {
-    /// Camera view projection.
-    view_projection: mat4x4<f32>,
-    /// Camera world space position.
-    view_pos: vec4<f32>,
-    directional_light: array<PackedDirectionalLight, MAX_DIRECTIONAL_LIGHTS>,
-    spot_light: array<PackedSpotLight, MAX_SPOT_LIGHTS>,
-    directional_light_count: u32,
-    spot_light_count: u32,
-    output_is_srgb: u32, // TODO(scott): Pack bit flags in here.
-    time_elapsed_seconds: f32,
-};
-
-struct PerModelUniforms {
-    /// Model -> world transform.
-    local_to_world: mat4x4<f32>,
-    /// World -> model transform.
-    world_to_local: mat4x4<f32>,
-    /// Point lights.
-    point_light: array<PackedPointLight, MAX_POINT_LIGHTS>,
-    /// Number of point lights.
-    point_light_count: u32,
-    padding_0: u32,
-    padding_1: u32,
-    padding_2: u32,
-}
-
-struct PerSubmeshUniforms {
-    material: PackedMaterialConstants
-}
-
-//============================================================================//
-// Shader inputs                                                              //
-//============================================================================//
-struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) normal: vec3<f32>,
-    @location(2) tex_coords: vec2<f32>,
-}
-
-struct VertexOutput {
-    /// Vertex output in "clip space" which can be visualized as:
-    ///  (.u must be set to 1.0).
-    ///
-    ///  <----------X---------->
-    /// ^ 
-    /// |          +1
-    /// |           
-    /// Y    -1     .     +1
-    /// | 
-    /// |          -1
-    /// v
-    ///
-    /// See: https://webgpufundamentals.org/webgpu/lessons/webgpu-fundamentals.html
-    @builtin(position) position_cs: vec4<f32>,
-    /// Vertex position in world space (rather than clip space) to allow world
-    /// space lighting calculations in the fragment shader.
-    @location(0) position_ws: vec3<f32>,
-    /// Normal vector from the vertex.
-    @location(1) normal: vec3<f32>,
-    /// UV texture coordinates of the vertex.
-    @location(2) tex_coords: vec2<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> per_frame: PerFrameUniforms;
-
-@group(1) @binding(0)
-var<uniform> per_model: PerModelUniforms;
-
-@group(2) @binding(0)
-var<uniform> per_submesh: PerSubmeshUniforms;
-
-@group(2) @binding(1)
-var tex_sampler: sampler;
-
-@group(2) @binding(2)
+@group(1) @binding(2)
 var diffuse_texture: texture_2d<f32>;
 
-@group(2) @binding(3)
+@group(1) @binding(3)
 var specular_texture: texture_2d<f32>;
 
-@group(2) @binding(4)
-var emissive_texture: texture_2d<f32>;
-
-//============================================================================//
-// Vertex shader                                                              //
-//============================================================================//
-@vertex
-fn vs_main(v_in: VertexInput) -> VertexOutput {
-    var v_out: VertexOutput;
-
-    v_out.position_cs = per_frame.view_projection
-        * per_model.local_to_world
-        * vec4<f32>(v_in.position, 1.0);
-    v_out.position_ws = (per_model.local_to_world * vec4<f32>(v_in.position, 1.0)).xyz;
-    v_out.normal = (transpose(per_model.world_to_local) * vec4<f32>(v_in.normal, 1.0)).xyz;
-    v_out.tex_coords = v_in.tex_coords;
-
-    return v_out;
-}
-
 //============================================================================//
 // Pixel shader                                                               //
 //============================================================================//
 @fragment
 fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
     let frag_normal = normalize(v_in.normal);
-    let material = unpack_material(
+    let uv_transform = per_submesh.material.uv_transform;
+    var material = unpack_material(
             per_submesh.material,
-            v_in.tex_coords,
+            v_in.tex_coords * uv_transform.xy + uv_transform.zw,
             tex_sampler,
             diffuse_texture,
             specular_texture,
             emissive_texture);
+    material.ambient_color *= ambient_occlusion(v_in.position_cs);
+
+    // Unlit materials skip lighting and use their diffuse color as is.
+    if ((per_submesh.material.flags & MATERIAL_FLAG_UNLIT) != 0u) {
+        return vec4(material.diffuse_color + material.emissive_color, material.alpha);
+    }
 
     // Directional lighting.
     var frag_color = vec3<f32>(0);
@@ -132,7 +36,7 @@ fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
             v_in.position_ws,        // fragment world space position
             frag_normal,             // fragment normal direction (normalized)
             per_frame.view_pos.xyz,  // camera world space position
-            unpack_directional_light(per_frame.directional_light[i]),
+            unpack_directional_light(fragment_directional_light(i)),
             material
         );
     }
@@ -143,19 +47,21 @@ fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
             v_in.position_ws,        // fragment world space position
             frag_normal,             // fragment normal direction (normalized)
             per_frame.view_pos.xyz,  // camera world space position
-            unpack_spot_light(per_frame.spot_light[i]),
+            unpack_spot_light(fragment_spot_light(i)),
             material,
         );
     }
     
 
     // Point lighting.
-    for (var i: u32 = 0; i < per_model.point_light_count; i++) {
+    let point_lights = fragment_point_lights(v_in.position_cs, v_in.position_ws);
+
+    for (var i: u32 = 0; i < point_lights.count; i++) {
         frag_color += point_light(
             v_in.position_ws,        // fragment world space position
             frag_normal,             // fragment normal direction (normalized)
             per_frame.view_pos.xyz,  // camera world space position
-            unpack_point_light(per_model.point_light[i]),
+            unpack_point_light(fragment_point_light(point_lights, i)),
             material,
         );
     }
@@ -163,30 +69,20 @@ fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
     // Mix in emissive light.
     frag_color += material.emissive_color;
 
-    // Should the color be converted from linear to sRGB in the pixel shader?
-    // Otherwise simply return it in lienar space.
-    if (per_frame.output_is_srgb == 0) {
-        return from_linear_rgb(vec4(frag_color, 1.0));
-    } else {
-        return vec4(frag_color, 1.0);
-    }
+    return vec4(frag_color, material.alpha);
 }
 
 //============================================================================//
 // Shared types and functions                                                 //
 //============================================================================//
-struct PackedMaterialConstants {
-    ambient_color: vec4<f32>,  // .w is unused.
-    diffuse_color: vec4<f32>,  // .w is unused.
-    specular_color: vec4<f32>, // .w is power.
-}
-
 struct Material {
     ambient_color: vec3<f32>,
     diffuse_color: vec3<f32>,
     specular_color: vec3<f32>,
     specular_shininess: f32,
     emissive_color: vec3<f32>,
+    /// Opacity, which is always 1 unless the material is alpha blended.
+    alpha: f32,
 };
 
 fn unpack_material(
@@ -201,9 +97,10 @@ fn unpack_material(
     // then either use a 1x1 white pixel to let the constant color through or
     // use a 1x1 black pixel to disable that contribution.
     //
-    // A sane default is probably white = 1 for the diffuse texture map, and a
-    // black = 0 for the specular and emissive texture map.
-    let diffuse_tex_color = textureSample(diffuse_map, tex_sampler, tex_uv).xyz;
+    // A sane default is probably white = 1 for the diffuse and emissive texture
+    // maps, and a black = 0 for the specular texture map. Materials without an
+    // emissive color have an emissive constant of black.
+    let diffuse_tex_color = textureSample(diffuse_map, tex_sampler, tex_uv);
     let specular_tex_color = textureSample(specular_map, tex_sampler, tex_uv).xyz;
     let emissive_tex_color = textureSample(emissive_map, tex_sampler, tex_uv).xyz;
 
@@ -211,24 +108,25 @@ fn unpack_material(
     // returning the material.
     var m: Material;
 
-    m.ambient_color = material_constants.ambient_color.xyz * diffuse_tex_color;
-    m.diffuse_color = material_constants.diffuse_color.xyz * diffuse_tex_color;
+    m.ambient_color = material_constants.ambient_color.xyz * diffuse_tex_color.xyz;
+    m.diffuse_color = material_constants.diffuse_color.xyz * diffuse_tex_color.xyz;
     m.specular_color = material_constants.specular_color.xyz * specular_tex_color;
-    m.emissive_color = emissive_tex_color.xyz;
+    m.emissive_color = material_constants.emissive_color.xyz * emissive_tex_color;
 
     m.specular_shininess = material_constants.specular_color.w;
+    m.alpha = material_alpha(material_constants, diffuse_tex_color.w);
 
     return m;
 }
 
-struct PackedDirectionalLight {
-    /// Direction from light to source.
-    ///   .xyz is normalized
-    ///   .w is ambient contribution.
-    direction: vec4<f32>,
-    /// Color
-    ///   .w is specular contribution.
-    color: vec4<f32>,
+/// Get the opacity of a material from its constant alpha and the alpha of its
+/// diffuse texture. Materials that are not alpha blended are always opaque.
+fn material_alpha(material_constants: PackedMaterialConstants, diffuse_tex_alpha: f32) -> f32 {
+    if ((material_constants.flags & MATERIAL_FLAG_ALPHA_BLEND) == 0u) {
+        return 1.0;
+    }
+
+    return material_constants.diffuse_color.w * diffuse_tex_alpha;
 }
 
 struct DirectionalLight {
@@ -309,20 +207,6 @@ fn directional_light(
         + specular_color;
 }
 
-struct PackedPointLight {
-    /// Point light world space position. (`w` is the ambient term).
-    pos: vec4<f32>, 
-    /// Point light color. (`w` is the specular term).
-    color: vec4<f32>,
-    /// Point light attenuation.
-    ///  `x`: constant term.
-    ///  `y`: linear term.
-    ///  `z`: quadratic term.
-    ///  `w`: unused.
-    attenuation: vec4<f32>,
-    padding: vec4<f32>,
-}
-
 struct PointLight {
     pos: vec3<f32>,
     color: vec3<f32>,
@@ -330,6 +214,9 @@ struct PointLight {
     diffuse_contrib: f32,
     specular_contrib: f32,
     attenuation: vec3<f32>,
+    range: f32,
+    shadow_map: i32,
+    shadow_distance: f32,
 }
 
 fn unpack_point_light(packed_light: PackedPointLight) -> PointLight {
@@ -341,6 +228,9 @@ fn unpack_point_light(packed_light: PackedPointLight) -> PointLight {
     p.diffuse_contrib = 1.0;
     p.specular_contrib = packed_light.color.w;
     p.attenuation = packed_light.attenuation.xyz;
+    p.range = packed_light.range.x;
+    p.shadow_map = i32(packed_light.range.y);
+    p.shadow_distance = packed_light.range.z;
 
     return p;
 }
@@ -396,33 +286,20 @@ fn point_light(
     // Attenuation.
     // TODO: Insert check for when attenuation tries to divide by zero.
     let distance = length(light.pos - frag_pos);
-    let attenuation = 1.0 / (
+    let attenuation = range_falloff(distance, light.range) / (
         light.attenuation.x +
         light.attenuation.y * distance +
         light.attenuation.z * distance * distance
     );
 
+    // Shadows block diffuse and specular light but not ambient light.
+    let shadow = point_light_shadow(
+        frag_pos, frag_normal, light.pos, light.shadow_map, light.shadow_distance);
+
     // Final color is an additive combination of ambient, diffuse and specular.
     return ambient_color * attenuation
-        + diffuse_color * attenuation
-        + specular_color * attenuation;
-}
-
-struct PackedSpotLight {
-    /// Point light world space position. (`w` is the precomputed cutoff angle).
-    pos: vec4<f32>, 
-    /// Normalized direction pointing away from the light.
-    ///   .xyz is normalized
-    ///   .w is ambient contribution.
-    direction: vec4<f32>,
-    /// Color
-    ///   .w is specular contribution.
-    color: vec4<f32>,
-    /// Attenuation
-    ///   .x is constant term, .y is linear term and .z is quadratic term.
-    ///   .w is the precomputed outer cutoff angle.
-    attenuation: vec4<f32>,
-
+        + diffuse_color * attenuation * shadow
+        + specular_color * attenuation * shadow;
 }
 
 struct SpotLight {
@@ -435,6 +312,7 @@ struct SpotLight {
     outer_cutoff: f32,
     color: vec3<f32>,
     attenuation: vec3<f32>,
+    range: f32,
     ambient_contrib: f32,
     diffuse_contrib: f32,
     specular_contrib: f32,
@@ -449,6 +327,7 @@ fn unpack_spot_light(packed_light: PackedSpotLight) -> SpotLight {
     s.outer_cutoff = packed_light.attenuation.w;
     s.color = packed_light.color.xyz;
     s.attenuation = packed_light.attenuation.xyz;
+    s.range = packed_light.range.x;
     s.ambient_contrib = packed_light.direction.w;
     s.diffuse_contrib = 1.0;
     s.specular_contrib = packed_light.color.w;
@@ -465,6 +344,9 @@ fn spot_light_as_point_light(s: SpotLight, intensity: f32) -> PointLight {
     p.diffuse_contrib = s.diffuse_contrib * intensity;
     p.specular_contrib = s.specular_contrib * intensity;
     p.attenuation = s.attenuation;
+    p.range = s.range;
+    p.shadow_map = -1;
+    p.shadow_distance = 0.0;
 
     return p;
 }
@@ -535,42 +417,3 @@ fn light_specular(
         * specular_amount
         * mat_color;
 }
-
-//============================================================================//
-// Shared utility functions.
-// TODO(scott): Move these to a utility functions library.
-//============================================================================//
-
-// linear -> srgb
-// https://en.wikipedia.org/wiki/SRGB
-fn from_linear_color(x: f32) -> f32 {
-    var y = 12.92 * x;
-
-    if (x > 0.0031308) {
-        let a = 0.055;
-        y = (1.0 + a) * pow(x, 1.0/2.4) - a;
-    }
-
-    return y;
-}
-
-fn from_linear_rgb(c: vec4<f32>) -> vec4<f32> {
-    return vec4<f32>(
-        from_linear_color(c.r),
-        from_linear_color(c.g),
-        from_linear_color(c.b),
-        c.a
-    );
-}
-
-/*
-// TODO(scott): Get this optimized solution to work from GLSL
-// https://gamedev.stackexchange.com/questions/92015/optimized-linear-to-srgb-glsl
-fn from_linear_rgb(linear_rgb: vec4<f32>) -> vec4<f32> {
-    let cutoff: vec4<bool> = lessThan(linear_rgb.rgb, vec3<f32>(0.0031308));
-    let higher = vec3<f32>(1.055) * pow(linear_rgb.rgb, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
-    let lower = linear_rgb.rgb * vec3<f32>(12.2);
-
-    return vec4<f32>(mix(higher, lower, cutoff), linear_rgb.a);
-}
-*/
\ No newline at end of file
@@ -31,16 +31,43 @@ use crate::renderer::{
 #[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct PackedMaterialConstants {
     pub ambient_color: Vec4,  // .w is unused.
-    pub diffuse_color: Vec4,  // .w is unused.
+    pub diffuse_color: Vec4,  // .w is alpha.
     pub specular_color: Vec4, // .w is specular power.
+    pub uv_transform: Vec4,   // .xy is uv scale, .zw is uv offset.
+    pub emissive_color: Vec4, // .w is unused.
+    pub pbr: Vec4,            // xyzw: (metallic, roughness, unused, unused).
+    pub flags: u32,           // `MATERIAL_FLAG_*` bits.
+    pub _padding: [u32; 3],
 }
 
+assert_uniform_aligned!(PackedMaterialConstants);
+
+/// The material is not lit by scene lights.
+pub const MATERIAL_FLAG_UNLIT: u32 = 1;
+/// The material is blended over what is behind it by its alpha.
+pub const MATERIAL_FLAG_ALPHA_BLEND: u32 = 2;
+
 impl From<Material> for PackedMaterialConstants {
     fn from(val: Material) -> Self {
         Self {
             ambient_color: vec3_w(val.ambient_color, 0.0),
-            diffuse_color: vec3_w(val.diffuse_color, 0.0),
+            diffuse_color: vec3_w(val.diffuse_color, val.alpha),
             specular_color: vec3_w(val.specular_color, val.specular_power),
+            uv_transform: Vec4::new(
+                val.uv_scale.x,
+                val.uv_scale.y,
+                val.uv_offset.x,
+                val.uv_offset.y,
+            ),
+            emissive_color: vec3_w(val.emissive_color, 0.0),
+            pbr: Vec4::new(val.metallic, val.roughness, 0.0, 0.0),
+            flags: if val.unlit { MATERIAL_FLAG_UNLIT } else { 0 }
+                | if val.blend_mode.is_transparent() {
+                    MATERIAL_FLAG_ALPHA_BLEND
+                } else {
+                    0
+                },
+            _padding: Default::default(),
         }
     }
 }
@@ -54,11 +81,13 @@ pub struct PackedDirectionalLight {
     pub color: Vec4,     // directional light, .w is specular amount.
 }
 
+assert_uniform_aligned!(PackedDirectionalLight);
+
 impl From<DirectionalLight> for PackedDirectionalLight {
     fn from(val: DirectionalLight) -> Self {
         Self {
             direction: vec3_w(val.direction.normalize(), val.ambient),
-            color: vec3_w(val.color, val.specular),
+            color: vec3_w(val.color * val.intensity, val.specular),
         }
     }
 }
@@ -71,21 +100,40 @@ pub struct PackedPointLight {
     pub position: Vec4,    // .w is ambient amount.
     pub color: Vec4,       // .w is specular amount.
     pub attenuation: Vec4, // xyzw: (constant, linear, quadratic, unused).
-    pub padding: Vec4,
+    pub range: Vec4,       // xyzw: (range, shadow map, shadow distance, unused).
+}
+
+assert_uniform_aligned!(PackedPointLight);
+
+impl PackedPointLight {
+    /// Value of `range.y` when the light does not have a shadow map.
+    pub const NO_SHADOW_MAP: f32 = -1.0;
+
+    /// Pack `light` along with the index of the point light shadow map its
+    /// shadows were drawn into, or `None` if the light does not cast shadows.
+    pub fn new(light: &PointLight, shadow_map: Option<usize>) -> Self {
+        let mut packed_light = Self::from(light.clone());
+
+        if let Some(shadow_map) = shadow_map {
+            packed_light.range.y = shadow_map as f32;
+        }
+
+        packed_light
+    }
 }
 
 impl From<PointLight> for PackedPointLight {
     fn from(val: PointLight) -> Self {
         Self {
             position: vec3_w(val.position, val.ambient),
-            color: vec3_w(val.color, val.specular),
+            color: vec3_w(val.color * val.intensity, val.specular),
             attenuation: Vec4::new(
                 val.attenuation.constant,
                 val.attenuation.linear,
                 val.attenuation.quadratic,
                 0.0,
             ),
-            padding: Vec4::ZERO,
+            range: Vec4::new(val.range, Self::NO_SHADOW_MAP, val.shadow_distance(), 0.0),
         }
     }
 }
@@ -99,20 +147,33 @@ pub struct PackedSpotLight {
     pub direction: Vec4,   // .w is ambient amount.
     pub color: Vec4,       // .w is specular amount.
     pub attenuation: Vec4, // .w is the outer precomputed cutoff angle.
+    pub range: Vec4,       // .x is range, .yzw are unused.
 }
 
+assert_uniform_aligned!(PackedSpotLight);
+
 impl From<SpotLight> for PackedSpotLight {
     fn from(val: SpotLight) -> Self {
+        // An inner cone that is wider than the outer cone lights the scene
+        // inside out.
+        debug_assert!(
+            val.cutoff_radians <= val.outer_cutoff_radians,
+            "spot light cutoff {} must not be larger than the outer cutoff {}",
+            val.cutoff_radians,
+            val.outer_cutoff_radians
+        );
+
         Self {
             position: vec3_w(val.position, f32::cos(val.cutoff_radians)),
             direction: vec3_w(val.direction.normalize(), val.ambient),
-            color: vec3_w(val.color, val.specular),
+            color: vec3_w(val.color * val.intensity, val.specular),
             attenuation: Vec4::new(
                 val.attenuation.constant,
                 val.attenuation.linear,
                 val.attenuation.quadratic,
                 f32::cos(val.outer_cutoff_radians),
             ),
+            range: Vec4::new(val.range, 0.0, 0.0, 0.0),
         }
     }
 }
@@ -0,0 +1,196 @@
+// Physically based lighting. Declarations shared with the lit shader are
+// prepended from lighting_common.wgsl.
+
+const PI: f32 = 3.14159265359;
+/// Reflectance of dielectric surfaces when viewed head on.
+const DIELECTRIC_F0: f32 = 0.04;
+/// Roughness is kept above this value so highlights from point lights do not
+/// become infinitely small and bright.
+const MIN_ROUGHNESS: f32 = 0.04;
+
+@group(1) @binding(2)
+var base_color_texture: texture_2d<f32>;
+
+@group(1) @binding(5)
+var metallic_texture: texture_2d<f32>;
+
+@group(1) @binding(6)
+var roughness_texture: texture_2d<f32>;
+
+@group(1) @binding(7)
+var ao_texture: texture_2d<f32>;
+
+//============================================================================//
+// Pixel shader                                                               //
+//============================================================================//
+struct Material {
+    base_color: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    ao: f32,
+    emissive_color: vec3<f32>,
+    /// Reflectance when viewed head on.
+    f0: vec3<f32>,
+    /// Opacity, which is always 1 unless the material is alpha blended.
+    alpha: f32,
+}
+
+@fragment
+fn fs_main(v_in: VertexOutput) -> @location(0) vec4<f32> {
+    let constants = per_submesh.material;
+    let uv = v_in.tex_coords * constants.uv_transform.xy + constants.uv_transform.zw;
+
+    let base_color_tex = textureSample(base_color_texture, tex_sampler, uv);
+
+    var m: Material;
+    m.base_color = constants.diffuse_color.xyz * base_color_tex.xyz;
+    m.emissive_color = constants.emissive_color.xyz
+        * textureSample(emissive_texture, tex_sampler, uv).xyz;
+    m.metallic = clamp(
+        constants.pbr.x * textureSample(metallic_texture, tex_sampler, uv).x, 0.0, 1.0);
+    m.roughness = clamp(
+        constants.pbr.y * textureSample(roughness_texture, tex_sampler, uv).x,
+        MIN_ROUGHNESS,
+        1.0);
+    m.ao = textureSample(ao_texture, tex_sampler, uv).x * ambient_occlusion(v_in.position_cs);
+    m.f0 = mix(vec3<f32>(DIELECTRIC_F0), m.base_color, m.metallic);
+    m.alpha = select(
+        1.0,
+        constants.diffuse_color.w * base_color_tex.w,
+        (constants.flags & MATERIAL_FLAG_ALPHA_BLEND) != 0u);
+
+    // Unlit materials skip lighting and use their base color as is.
+    if ((constants.flags & MATERIAL_FLAG_UNLIT) != 0u) {
+        return vec4(m.base_color + m.emissive_color, m.alpha);
+    }
+
+    let n = normalize(v_in.normal);
+    let v = normalize(per_frame.view_pos.xyz - v_in.position_ws);
+    var frag_color = vec3<f32>(0);
+
+    for (var i: u32 = 0; i < per_frame.directional_light_count; i++) {
+        let light = fragment_directional_light(i);
+        let l = normalize(-light.direction.xyz);
+
+        frag_color += ambient(light.color.xyz, light.direction.w, m)
+            + light_brdf(n, v, l, light.color.xyz, light.color.w, m);
+    }
+
+    for (var i: u32 = 0; i < per_frame.spot_light_count; i++) {
+        let light = fragment_spot_light(i);
+        let l = normalize(light.pos.xyz - v_in.position_ws);
+
+        let theta = dot(l, -light.direction.xyz);
+        let epsilon = light.pos.w - light.attenuation.w;
+        let intensity = clamp((theta - light.attenuation.w) / epsilon, 0.0, 1.0);
+        let attenuation = light_attenuation(
+            length(light.pos.xyz - v_in.position_ws),
+            light.attenuation.xyz,
+            light.range.x);
+
+        frag_color += (ambient(light.color.xyz, light.direction.w, m)
+            + light_brdf(n, v, l, light.color.xyz, light.color.w, m) * intensity)
+            * attenuation;
+    }
+
+    let point_lights = fragment_point_lights(v_in.position_cs, v_in.position_ws);
+
+    for (var i: u32 = 0; i < point_lights.count; i++) {
+        let light = fragment_point_light(point_lights, i);
+        let l = normalize(light.pos.xyz - v_in.position_ws);
+
+        let attenuation = light_attenuation(
+            length(light.pos.xyz - v_in.position_ws),
+            light.attenuation.xyz,
+            light.range.x);
+        let shadow = point_light_shadow(
+            v_in.position_ws, n, light.pos.xyz, i32(light.range.y), light.range.z);
+
+        frag_color += (ambient(light.color.xyz, light.pos.w, m)
+            + light_brdf(n, v, l, light.color.xyz, light.color.w, m) * shadow)
+            * attenuation;
+    }
+
+    frag_color += m.emissive_color;
+
+    return vec4(frag_color, m.alpha);
+}
+
+//============================================================================//
+// Lighting functions                                                         //
+//============================================================================//
+
+/// Calculate the ambient light contributed by a light.
+fn ambient(light_color: vec3<f32>, ambient_contrib: f32, m: Material) -> vec3<f32> {
+    return light_color * ambient_contrib * m.base_color * m.ao;
+}
+
+/// Calculate the light reflected towards the viewer from a light using the
+/// Cook-Torrance BRDF.
+///
+/// Lights are scaled by π so that a light shining straight onto a white
+/// diffuse surface is as bright as it is with Phong shading.
+///
+///  `n`: Fragment normal vector direction (normalized).
+///  `v`: Direction from the fragment towards the camera (normalized).
+///  `l`: Direction from the fragment towards the light (normalized).
+///  `light_color`: Color of the light.
+///  `specular_contrib`: Specular lighting modifier [0 = none, 1 = full].
+///  `m`: The fragment's material.
+fn light_brdf(
+        n: vec3<f32>,
+        v: vec3<f32>,
+        l: vec3<f32>,
+        light_color: vec3<f32>,
+        specular_contrib: f32,
+        m: Material,
+) -> vec3<f32> {
+    let h = normalize(v + l);
+    let n_dot_l = max(dot(n, l), 0.0);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    let n_dot_h = max(dot(n, h), 0.0);
+
+    let f = fresnel_schlick(max(dot(h, v), 0.0), m.f0);
+    let d = distribution_ggx(n_dot_h, m.roughness);
+    let g = geometry_smith(n_dot_v, n_dot_l, m.roughness);
+    let specular = (d * g * f) / (4.0 * n_dot_v * n_dot_l + 0.0001);
+
+    // Light that is not reflected is refracted and diffused, except by metals
+    // which absorb it.
+    let k_d = (vec3<f32>(1.0) - f) * (1.0 - m.metallic);
+    let diffuse = k_d * m.base_color / PI;
+
+    return (diffuse + specular * specular_contrib) * light_color * n_dot_l * PI;
+}
+
+/// Trowbridge-Reitz GGX normal distribution function.
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+/// Smith's method combining Schlick-GGX geometry terms for the view and light
+/// directions.
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = (r * r) / 8.0;
+    let g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+/// Schlick's approximation of the Fresnel reflectance.
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+/// Calculate how much a point or spot light is attenuated at `distance`.
+fn light_attenuation(distance: f32, terms: vec3<f32>, range: f32) -> f32 {
+    return range_falloff(distance, range) / (
+        terms.x +
+        terms.y * distance +
+        terms.z * distance * distance
+    );
+}
@@ -0,0 +1,70 @@
+//============================================================================//
+// Storage buffer lights                                                      //
+//============================================================================//
+// Appended to the lit shaders when the device supports storage buffers, so a
+// scene can have any number of lights. Point lights are binned into clusters
+// by `LightClusterPass` and each fragment only reads the lights in its own
+// cluster.
+//
+// These must match the constants in `LightClusterPass`.
+const CLUSTERS_X: u32 = 16u;
+const CLUSTERS_Y: u32 = 8u;
+const CLUSTERS_Z: u32 = 24u;
+const MAX_LIGHTS_PER_CLUSTER: u32 = 64u;
+
+@group(2) @binding(4)
+var<storage, read> point_lights: array<PackedPointLight>;
+
+@group(2) @binding(5)
+var<storage, read> cluster_light_counts: array<u32>;
+
+@group(2) @binding(6)
+var<storage, read> cluster_light_indices: array<u32>;
+
+@group(2) @binding(7)
+var<storage, read> directional_lights: array<PackedDirectionalLight>;
+
+@group(2) @binding(8)
+var<storage, read> spot_lights: array<PackedSpotLight>;
+
+/// Get the `i`th directional light, up to `per_frame.directional_light_count`.
+fn fragment_directional_light(i: u32) -> PackedDirectionalLight {
+    return directional_lights[i];
+}
+
+/// Get the `i`th spot light, up to `per_frame.spot_light_count`.
+fn fragment_spot_light(i: u32) -> PackedSpotLight {
+    return spot_lights[i];
+}
+
+/// The point lights that shine on a fragment.
+struct PointLightList {
+    cluster: u32,
+    count: u32,
+}
+
+/// Find the point lights that shine on the fragment at `position_cs` (its
+/// framebuffer position) and `position_ws`.
+fn fragment_point_lights(position_cs: vec4<f32>, position_ws: vec3<f32>) -> PointLightList {
+    let clusters = vec2<u32>(CLUSTERS_X, CLUSTERS_Y);
+    let tile_uv = (position_cs.xy - per_frame.viewport.xy) / per_frame.viewport.zw;
+    let tile = min(vec2<u32>(max(tile_uv, vec2<f32>(0.0)) * vec2<f32>(clusters)), clusters - 1u);
+
+    let forward = cross(per_frame.view_up.xyz, per_frame.view_right.xyz);
+    let depth = max(dot(position_ws - per_frame.view_pos.xyz, forward), per_frame.z_near);
+    let slice = min(
+        u32(log(depth / per_frame.z_near) / log(per_frame.z_far / per_frame.z_near) * f32(CLUSTERS_Z)),
+        CLUSTERS_Z - 1u);
+
+    let cluster = per_frame.first_light_cluster
+        + tile.x
+        + tile.y * CLUSTERS_X
+        + slice * CLUSTERS_X * CLUSTERS_Y;
+
+    return PointLightList(cluster, cluster_light_counts[cluster]);
+}
+
+/// Get the `i`th point light in `lights`.
+fn fragment_point_light(lights: PointLightList, i: u32) -> PackedPointLight {
+    return point_lights[cluster_light_indices[lights.cluster * MAX_LIGHTS_PER_CLUSTER + i]];
+}
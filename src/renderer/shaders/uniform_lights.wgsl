@@ -0,0 +1,31 @@
+//============================================================================//
+// Uniform lights                                                             //
+//============================================================================//
+// Appended to the lit shaders when the device does not support storage
+// buffers. Every fragment reads all of the lights in the per-frame uniforms.
+
+/// Get the `i`th directional light, up to `per_frame.directional_light_count`.
+fn fragment_directional_light(i: u32) -> PackedDirectionalLight {
+    return per_frame.directional_light[i];
+}
+
+/// Get the `i`th spot light, up to `per_frame.spot_light_count`.
+fn fragment_spot_light(i: u32) -> PackedSpotLight {
+    return per_frame.spot_light[i];
+}
+
+/// The point lights that shine on a fragment.
+struct PointLightList {
+    count: u32,
+}
+
+/// Find the point lights that shine on the fragment at `position_cs` (its
+/// framebuffer position) and `position_ws`.
+fn fragment_point_lights(position_cs: vec4<f32>, position_ws: vec3<f32>) -> PointLightList {
+    return PointLightList(per_frame.point_light_count);
+}
+
+/// Get the `i`th point light in `lights`.
+fn fragment_point_light(lights: PointLightList, i: u32) -> PackedPointLight {
+    return per_frame.point_light[i];
+}
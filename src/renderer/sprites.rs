@@ -0,0 +1,44 @@
+use std::rc::Rc;
+
+use glam::{Vec2, Vec4};
+
+use super::textures::Texture;
+
+/// A textured quad drawn in screen space over the scene, eg a HUD element or
+/// a character in a 2D game. Sprites are drawn after the scene is tonemapped,
+/// in the order they are listed.
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    /// Top left corner of the sprite in pixels, measured from the top left of
+    /// the render target.
+    pub position: Vec2,
+    /// Width and height of the sprite in pixels. A negative width or height
+    /// flips the sprite.
+    pub size: Vec2,
+    /// Color multiplied with the texture. The alpha channel controls the
+    /// sprite's transparency.
+    pub color: Vec4,
+    /// Top left corner of the area of `texture` drawn on the sprite, in texture
+    /// coordinates.
+    pub uv_min: Vec2,
+    /// Bottom right corner of the area of `texture` drawn on the sprite, in
+    /// texture coordinates.
+    pub uv_max: Vec2,
+    /// The image drawn on the sprite. Sprites sharing a texture (eg frames
+    /// packed into one atlas) are drawn together in one draw call.
+    pub texture: Rc<Texture>,
+}
+
+impl Sprite {
+    /// Create an untinted sprite that draws all of `texture`.
+    pub fn new(position: Vec2, size: Vec2, texture: Rc<Texture>) -> Self {
+        Self {
+            position,
+            size,
+            color: Vec4::ONE,
+            uv_min: Vec2::ZERO,
+            uv_max: Vec2::ONE,
+            texture,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// Statistics gathered by the renderer while drawing a frame.
+///
+/// GPU pass timings are only available when the adapter supports timestamp
+/// queries, otherwise they are always reported as zero. Timings lag behind the
+/// current frame by at least one frame because the results have to be copied
+/// back from the GPU.
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats {
+    /// GPU time spent drawing the scene's models.
+    pub main_pass_gpu_time: Duration,
+    /// GPU time spent in the light debug visualization pass.
+    pub light_debug_pass_gpu_time: Duration,
+    /// GPU time spent in the depth buffer visualization pass.
+    pub depth_pass_gpu_time: Duration,
+    /// Number of per-model uniform buffers copied to the GPU in the last frame.
+    /// Only models that moved since the previous frame are uploaded.
+    pub model_uniform_uploads: usize,
+    /// Number of models drawn in the last frame, counted once for each
+    /// viewport they were drawn in.
+    pub models_drawn: usize,
+    /// Number of models skipped in the last frame because they were outside of
+    /// a viewport's frustum, counted once for each viewport.
+    pub models_culled: usize,
+    /// Number of groups of models sharing a mesh drawn in the last frame,
+    /// counted once for each viewport. Each group is drawn with one instanced
+    /// draw call per submesh.
+    pub model_batches: usize,
+    /// Number of draw calls issued for the scene's models in the main and
+    /// transparent passes in the last frame. Shadow, debug, overlay and post
+    /// processing draws are not counted.
+    pub draw_calls: usize,
+    /// Number of triangles drawn by `draw_calls`.
+    pub triangles: usize,
+    /// Bytes of per-frame, per-model and model instance values copied to the
+    /// GPU in the last frame.
+    pub uploaded_bytes: u64,
+    /// Frame rate measured by the host over recent frames.
+    pub measured_fps: f32,
+    /// Frame rate the host is limiting frames to, or `None` if frames are not
+    /// limited.
+    pub target_fps: Option<u32>,
+}
+
+impl RenderStats {
+    /// Get the total GPU time spent in the timed render passes.
+    pub fn gpu_time(&self) -> Duration {
+        self.main_pass_gpu_time + self.light_debug_pass_gpu_time + self.depth_pass_gpu_time
+    }
+}
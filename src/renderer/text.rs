@@ -0,0 +1,350 @@
+use glam::{Vec2, Vec4};
+
+/// One character drawn by the text pass, as a quad in screen space that is
+/// textured with the character's cell in the font atlas.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphInstance {
+    /// Top left corner of the quad in pixels, measured from the top left of
+    /// the render target.
+    pub position: [f32; 2],
+    /// Width and height of the quad in pixels.
+    pub size: [f32; 2],
+    /// Top left corner of the glyph's cell in the font atlas.
+    pub uv_min: [f32; 2],
+    /// Bottom right corner of the glyph's cell in the font atlas.
+    pub uv_max: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl GlyphInstance {
+    /// Vertex buffer format for `GlyphInstance`, with one instance per quad.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x2,
+            2 => Float32x2,
+            3 => Float32x2,
+            4 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GlyphInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Immediate mode screen space text, eg a frame rate counter, debug values or
+/// instructions for a demo.
+///
+/// Text is added each frame through `Renderer::text` and is drawn over the
+/// tonemapped scene by the text pass in the next rendered frame. Characters are
+/// drawn from the built in `BitmapFont`, with every character added in a frame
+/// batched into a single draw call. Text is cleared once the frame is drawn.
+#[derive(Debug, Default)]
+pub struct TextRenderer {
+    /// Quads for every visible character added this frame.
+    glyphs: Vec<GlyphInstance>,
+}
+
+impl TextRenderer {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw `text` with the top left corner of its first line at `position`,
+    /// which is in pixels from the top left of the render target. `size` is the
+    /// height of a line in pixels, and should be a multiple of
+    /// `BitmapFont::CELL_HEIGHT` for the sharpest text.
+    ///
+    /// Lines are split on `\n`. Characters missing from the font are drawn as
+    /// `?`.
+    pub fn draw_text(&mut self, position: Vec2, text: &str, size: f32, color: Vec4) {
+        let scale = size / BitmapFont::CELL_HEIGHT as f32;
+        let cell_size = Vec2::new(
+            BitmapFont::CELL_WIDTH as f32 * scale,
+            BitmapFont::CELL_HEIGHT as f32 * scale,
+        );
+
+        for (row, line) in text.split('\n').enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+
+                let (uv_min, uv_max) = BitmapFont::glyph_uv(c)
+                    .or_else(|| BitmapFont::glyph_uv('?'))
+                    .expect("the font has a glyph for '?'");
+
+                self.glyphs.push(GlyphInstance {
+                    position: (position + cell_size * Vec2::new(column as f32, row as f32)).into(),
+                    size: cell_size.into(),
+                    uv_min: uv_min.into(),
+                    uv_max: uv_max.into(),
+                    color: color.into(),
+                });
+            }
+        }
+    }
+
+    /// Get the width and height in pixels of `text` when drawn with `size`,
+    /// eg for right aligning text or drawing a background behind it.
+    pub fn measure_text(text: &str, size: f32) -> Vec2 {
+        let scale = size / BitmapFont::CELL_HEIGHT as f32;
+        let columns = text
+            .split('\n')
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or_default();
+        let rows = text.split('\n').count();
+
+        Vec2::new(
+            (columns * BitmapFont::CELL_WIDTH as usize) as f32 * scale,
+            (rows * BitmapFont::CELL_HEIGHT as usize) as f32 * scale,
+        )
+    }
+
+    /// Get the quads for every character added this frame.
+    pub fn glyphs(&self) -> &[GlyphInstance] {
+        &self.glyphs
+    }
+
+    /// Returns true if no text was added this frame.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Remove all text, which the renderer does after each frame.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+    }
+}
+
+/// A fixed width 5x7 pixel font covering printable ASCII, which is built into
+/// the renderer so text can be drawn without loading any font files.
+///
+/// Each glyph sits in the top left corner of a 6x8 cell, leaving a blank column
+/// and row between neighboring characters and lines. Cells are packed into a
+/// single channel atlas texture in character order.
+pub struct BitmapFont;
+
+impl BitmapFont {
+    /// Width of a character cell in the atlas, including spacing.
+    pub const CELL_WIDTH: u32 = 6;
+    /// Height of a character cell in the atlas, including spacing.
+    pub const CELL_HEIGHT: u32 = 8;
+    /// Number of character cells in each row of the atlas.
+    const ATLAS_COLUMNS: u32 = 16;
+    /// Number of character cell rows in the atlas.
+    const ATLAS_ROWS: u32 = (GLYPHS.len() as u32).div_ceil(Self::ATLAS_COLUMNS);
+    /// Width of the atlas texture in pixels.
+    pub const ATLAS_WIDTH: u32 = Self::ATLAS_COLUMNS * Self::CELL_WIDTH;
+    /// Height of the atlas texture in pixels.
+    pub const ATLAS_HEIGHT: u32 = Self::ATLAS_ROWS * Self::CELL_HEIGHT;
+    /// The character drawn by the first glyph in the font.
+    const FIRST_CHAR: char = ' ';
+
+    /// Get the pixels of the font atlas, one byte per pixel in row order. Glyph
+    /// pixels are 255 and everything else is 0.
+    pub fn atlas_pixels() -> Vec<u8> {
+        let mut pixels = vec![0; (Self::ATLAS_WIDTH * Self::ATLAS_HEIGHT) as usize];
+
+        for (index, glyph) in GLYPHS.iter().enumerate() {
+            let cell_x = index as u32 % Self::ATLAS_COLUMNS * Self::CELL_WIDTH;
+            let cell_y = index as u32 / Self::ATLAS_COLUMNS * Self::CELL_HEIGHT;
+
+            for (y, row) in glyph.iter().enumerate() {
+                for x in 0..5 {
+                    // The leftmost pixel of each row is stored in bit 4.
+                    if row & (0x10 >> x) != 0 {
+                        let offset = (cell_y + y as u32) * Self::ATLAS_WIDTH + cell_x + x;
+                        pixels[offset as usize] = 255;
+                    }
+                }
+            }
+        }
+
+        pixels
+    }
+
+    /// Get the top left and bottom right texture coordinates of the cell
+    /// holding `c` in the font atlas, or `None` if the font has no glyph for
+    /// `c`.
+    pub fn glyph_uv(c: char) -> Option<(Vec2, Vec2)> {
+        let index = (c as u32).checked_sub(Self::FIRST_CHAR as u32)?;
+
+        if index as usize >= GLYPHS.len() {
+            return None;
+        }
+
+        let atlas_size = Vec2::new(Self::ATLAS_WIDTH as f32, Self::ATLAS_HEIGHT as f32);
+        let cell_size = Vec2::new(Self::CELL_WIDTH as f32, Self::CELL_HEIGHT as f32);
+        let cell = Vec2::new(
+            (index % Self::ATLAS_COLUMNS) as f32,
+            (index / Self::ATLAS_COLUMNS) as f32,
+        );
+
+        Some((
+            cell * cell_size / atlas_size,
+            (cell + Vec2::ONE) * cell_size / atlas_size,
+        ))
+    }
+}
+
+/// Rows of each printable ASCII character from space to `~`, top row first.
+const GLYPHS: [[u8; 7]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // !
+    [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00], // "
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a], // #
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04], // $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // %
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d], // &
+    [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00], // '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // )
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00], // *
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08], // ,
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c], // .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // /
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e], // 0
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e], // 1
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f], // 2
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e], // 3
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02], // 4
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e], // 5
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e], // 6
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // 7
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e], // 8
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c], // 9
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00], // :
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08], // ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // <
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00], // =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // >
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // ?
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e], // @
+    [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11], // A
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e], // B
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e], // C
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c], // D
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f], // E
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10], // F
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f], // G
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // H
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c], // J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f], // L
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11], // M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // N
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // O
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10], // P
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d], // Q
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11], // R
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e], // S
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04], // V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a], // W
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11], // X
+    [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04], // Y
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f], // Z
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e], // [
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // \
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e], // ]
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f], // _
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f], // a
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e], // b
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e], // c
+    [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f], // d
+    [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e], // e
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08], // f
+    [0x00, 0x0f, 0x11, 0x11, 0x0f, 0x01, 0x0e], // g
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11], // h
+    [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e], // i
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c], // j
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12], // k
+    [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // l
+    [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11], // m
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11], // n
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e], // o
+    [0x00, 0x00, 0x1e, 0x11, 0x1e, 0x10, 0x10], // p
+    [0x00, 0x00, 0x0d, 0x13, 0x0f, 0x01, 0x01], // q
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10], // r
+    [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e], // s
+    [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06], // t
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d], // u
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04], // v
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a], // w
+    [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11], // x
+    [0x00, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e], // y
+    [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f], // z
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02], // {
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // |
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08], // }
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00], // ~
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_laid_out_in_character_cells() {
+        let mut text = TextRenderer::new();
+        text.draw_text(Vec2::new(10.0, 20.0), "a b\nc", 16.0, Vec4::ONE);
+
+        // Spaces advance the cursor without adding a quad.
+        let positions = text
+            .glyphs()
+            .iter()
+            .map(|glyph| Vec2::from(glyph.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Vec2::new(10.0, 20.0),
+                Vec2::new(34.0, 20.0),
+                Vec2::new(10.0, 36.0),
+            ],
+            positions
+        );
+        assert_eq!([12.0, 16.0], text.glyphs()[0].size);
+        assert_eq!(
+            Vec2::new(36.0, 32.0),
+            TextRenderer::measure_text("a b\nc", 16.0)
+        );
+
+        // Characters missing from the font are drawn as '?'.
+        text.clear();
+        text.draw_text(Vec2::ZERO, "é", 8.0, Vec4::ONE);
+        assert_eq!(
+            BitmapFont::glyph_uv('?').unwrap().0,
+            Vec2::from(text.glyphs()[0].uv_min)
+        );
+    }
+
+    #[test]
+    fn atlas_cells_hold_their_glyph() {
+        let pixels = BitmapFont::atlas_pixels();
+        let (uv_min, _) = BitmapFont::glyph_uv('|').unwrap();
+        let x = (uv_min.x * BitmapFont::ATLAS_WIDTH as f32) as u32;
+        let y = (uv_min.y * BitmapFont::ATLAS_HEIGHT as f32) as u32;
+
+        // '|' is a vertical line through the middle column of the glyph, and
+        // the spacing row below it is blank.
+        let column = (0..BitmapFont::CELL_HEIGHT)
+            .map(|row| pixels[((y + row) * BitmapFont::ATLAS_WIDTH + x + 2) as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(vec![255, 255, 255, 255, 255, 255, 255, 0], column);
+
+        assert_eq!(None, BitmapFont::glyph_uv('\u{7f}'));
+    }
+}
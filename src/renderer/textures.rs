@@ -1,5 +1,11 @@
+mod cube_maps;
+mod dds;
+#[cfg(feature = "ktx2")]
+mod ktx;
+
 use anyhow::*;
 use image::{GenericImageView, Rgba, RgbaImage};
+use tracing::debug;
 
 // TODO: Allow customization of texture parameters.
 // TODO: Create a high level sharable texture type that can be updated at runtime
@@ -7,7 +13,7 @@ use image::{GenericImageView, Rgba, RgbaImage};
 
 /// Color space encoding for an image. SRGB refers to gamma encoded images that
 /// are typically diffuse, albedo or similiar texture maps.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ColorSpace {
     /// Gamma encoded color space.
     Srgb,
@@ -15,6 +21,143 @@ pub enum ColorSpace {
     Linear,
 }
 
+/// How textures made from images are used: they are copied to once and then
+/// sampled by shaders.
+const SAMPLED_USAGE: wgpu::TextureUsages =
+    wgpu::TextureUsages::TEXTURE_BINDING.union(wgpu::TextureUsages::COPY_DST);
+
+/// A texture on the GPU along with a copy of its texels on the CPU, so that it
+/// can be uploaded again to a new device after the GPU device was lost.
+///
+/// A `Texture` derefs to the `wgpu::Texture` it holds.
+#[derive(Debug)]
+pub struct Texture {
+    texture: wgpu::Texture,
+    source: TextureSource,
+}
+
+/// Everything needed to create a texture and fill it with texels.
+#[derive(Clone, Debug)]
+struct TextureSource {
+    label: Option<String>,
+    format: wgpu::TextureFormat,
+    /// Size of the first mip level. The depth is the number of layers, eg six
+    /// for cube maps.
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+    usage: wgpu::TextureUsages,
+    /// The texels of each layer one after another. Each layer holds every mip
+    /// level in the layout described by `MipLayout`. Empty for textures that
+    /// are drawn into by the GPU.
+    data: Vec<u8>,
+}
+
+impl Texture {
+    /// Create a texture on `device` that is filled with the texels in `source`.
+    fn upload(device: &wgpu::Device, queue: &wgpu::Queue, source: TextureSource) -> Self {
+        let texture = Self::create_gpu_texture(device, &source);
+        let mut offset = 0;
+
+        // Textures drawn into by the GPU have no texels to copy.
+        let layer_count = if source.data.is_empty() {
+            0
+        } else {
+            source.size.depth_or_array_layers
+        };
+
+        for layer in 0..layer_count {
+            for mip_level in 0..source.mip_level_count {
+                let layout = MipLayout::new(source.format, source.size, mip_level);
+
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level,
+                        origin: wgpu::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &source.data[offset..offset + layout.byte_len()],
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(layout.bytes_per_row),
+                        rows_per_image: Some(layout.rows),
+                    },
+                    wgpu::Extent3d {
+                        depth_or_array_layers: 1,
+                        ..layout.size
+                    },
+                );
+
+                offset += layout.byte_len();
+            }
+        }
+
+        Self { texture, source }
+    }
+
+    /// Create an empty texture on `device` described by `source`.
+    fn create_gpu_texture(device: &wgpu::Device, source: &TextureSource) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: source.label.as_deref(),
+            size: source.size,
+            mip_level_count: source.mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: source.format,
+            usage: source.usage,
+            view_formats: &[],
+        })
+    }
+
+    /// Create an empty `width` by `height` texture that the GPU draws into,
+    /// eg a render target. Recreating the texture gives another empty texture
+    /// since its texels are not kept on the CPU.
+    pub fn new_drawable(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        usage: wgpu::TextureUsages,
+        label: Option<&str>,
+    ) -> Self {
+        let source = TextureSource {
+            label: label.map(String::from),
+            format,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            usage,
+            data: Vec::new(),
+        };
+
+        Self {
+            texture: Self::create_gpu_texture(device, &source),
+            source,
+        }
+    }
+
+    /// Create a copy of this texture on `device` from the texels kept on the
+    /// CPU, eg after the GPU device this texture was created on was lost.
+    pub fn recreate(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self::upload(device, queue, self.source.clone())
+    }
+}
+
+impl std::ops::Deref for Texture {
+    type Target = wgpu::Texture;
+
+    fn deref(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+}
+
 /// Creates a new 1x1 texture with the given pixel color. `pixel` is an RGB
 /// triplet with 0 being none, and 255 being maximum.
 ///
@@ -26,14 +169,19 @@ pub fn new_1x1(
     pixel: [u8; 3],
     color_space: ColorSpace,
     label: Option<&str>,
-) -> wgpu::Texture {
+) -> Texture {
     let mut image = RgbaImage::new(1, 1);
     image.put_pixel(0, 0, Rgba([pixel[0], pixel[1], pixel[2], 255]));
     from_image(device, queue, image.into(), color_space, label)
 }
 
 /// Construct a texture represented by `image_bytes` which must be a JPEG, PNG
-/// or DDS image.
+/// or DDS image, or a KTX2 file when the `ktx2` feature is enabled.
+///
+/// Block compressed DDS images are uploaded without decompressing them when
+/// the device can sample the compressed format. Otherwise they are decoded to
+/// RGBA like any other image. KTX2 files are loaded with `from_ktx2_bytes`, and
+/// keep the color space stored in the file.
 #[allow(dead_code)]
 pub fn from_image_bytes(
     device: &wgpu::Device,
@@ -41,14 +189,34 @@ pub fn from_image_bytes(
     image_bytes: &[u8],
     color_space: ColorSpace,
     label: Option<&str>,
-) -> Result<wgpu::Texture> {
+) -> Result<Texture> {
+    #[cfg(feature = "ktx2")]
+    if ktx::is_ktx2(image_bytes) {
+        return from_ktx2_bytes(device, queue, image_bytes, label);
+    }
+
+    if dds::is_dds(image_bytes) {
+        match dds::parse(image_bytes, color_space) {
+            Result::Ok(image) if can_sample_compressed(device, &image) => {
+                return from_compressed_dds(device, queue, &image, label);
+            }
+            Result::Ok(image) => {
+                debug!(
+                    "decoding {:?} DDS texture {label:?} because the device cannot sample it",
+                    image.format
+                );
+            }
+            Err(e) => debug!("decoding DDS texture {label:?}: {e}"),
+        }
+    }
+
     let image = image::load_from_memory(image_bytes)?;
     Ok(from_image(device, queue, image, color_space, label))
 }
 
-/// Create a wgpu texture object from a `DynamicImage`.`
+/// Create a texture from a `DynamicImage`.`
 ///
-/// To get a texture view from the wgpu texture object use the following code:
+/// To get a texture view from the texture use the following code:
 /// `texture.create_view(&wgpu::TextureViewDescriptor::default())`
 pub fn from_image(
     device: &wgpu::Device,
@@ -56,47 +224,292 @@ pub fn from_image(
     image: image::DynamicImage,
     color_space: ColorSpace,
     label: Option<&str>,
-) -> wgpu::Texture {
-    let rgba = image.to_rgba8();
-    let dims = image.dimensions();
+) -> Texture {
+    let (width, height) = image.dimensions();
 
-    let size = wgpu::Extent3d {
-        width: dims.0,
-        height: dims.1,
-        depth_or_array_layers: 1,
-    };
+    Texture::upload(
+        device,
+        queue,
+        TextureSource {
+            label: label.map(String::from),
+            format: match color_space {
+                ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+                ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+            },
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            usage: SAMPLED_USAGE,
+            data: image.to_rgba8().into_raw(),
+        },
+    )
+}
 
-    let texture = device.create_texture(&wgpu::TextureDescriptor {
-        label,
-        size,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: match color_space {
+/// Create a cube map texture from six square images of the same size, given in
+/// cube map face order (+X, -X, +Y, -Y, +Z, -Z).
+///
+/// To sample the cube map create a view with a `Cube` view dimension.
+pub fn cube_from_images(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    faces: &[image::DynamicImage],
+    color_space: ColorSpace,
+    label: Option<&str>,
+) -> Result<Texture> {
+    ensure!(
+        faces.len() == cube_maps::FACE_COUNT,
+        "cube map {label:?} needs {} face images but got {}",
+        cube_maps::FACE_COUNT,
+        faces.len()
+    );
+
+    let (width, height) = faces[0].dimensions();
+
+    ensure!(
+        width == height,
+        "cube map {label:?} faces must be square but are {width}x{height}"
+    );
+    ensure!(
+        faces
+            .iter()
+            .all(|face| face.dimensions() == (width, height)),
+        "cube map {label:?} faces must all be the same size"
+    );
+
+    let faces = faces
+        .iter()
+        .map(|face| face.to_rgba8().into_raw())
+        .collect::<Vec<_>>();
+
+    Ok(cube_from_face_bytes(
+        device,
+        queue,
+        match color_space {
             ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
             ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
         },
-        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-        view_formats: &[],
-    });
-
-    queue.write_texture(
-        wgpu::ImageCopyTexture {
-            texture: &texture,
-            mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
-            aspect: wgpu::TextureAspect::All,
-        },
-        &rgba,
-        wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: Some(4 * dims.0),
-            rows_per_image: Some(dims.1),
+        width,
+        &faces,
+        label,
+    ))
+}
+
+/// Create a cube map texture by projecting an equirectangular (latitude and
+/// longitude) panorama, such as an HDR environment map, onto the cube's faces.
+///
+/// The cube map keeps the panorama's high dynamic range by storing it as half
+/// precision floats. Each face is a quarter of the panorama's width.
+pub fn cube_from_equirect(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    panorama: &image::DynamicImage,
+    label: Option<&str>,
+) -> Texture {
+    let face_size = (panorama.width() / 4).max(1);
+    let faces = cube_maps::equirect_to_faces(&panorama.to_rgba32f(), face_size)
+        .into_iter()
+        .map(|face| {
+            let texels = face
+                .into_raw()
+                .into_iter()
+                .map(|c| half::f16::from_f32(c).to_bits())
+                .collect::<Vec<_>>();
+            bytemuck::cast_slice(&texels).to_vec()
+        })
+        .collect::<Vec<_>>();
+
+    cube_from_face_bytes(
+        device,
+        queue,
+        wgpu::TextureFormat::Rgba16Float,
+        face_size,
+        &faces,
+        label,
+    )
+}
+
+/// Create a cube map texture from the texels of each face.
+fn cube_from_face_bytes(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+    face_size: u32,
+    faces: &[Vec<u8>],
+    label: Option<&str>,
+) -> Texture {
+    Texture::upload(
+        device,
+        queue,
+        TextureSource {
+            label: label.map(String::from),
+            format,
+            size: wgpu::Extent3d {
+                width: face_size,
+                height: face_size,
+                depth_or_array_layers: cube_maps::FACE_COUNT as u32,
+            },
+            mip_level_count: 1,
+            usage: SAMPLED_USAGE,
+            data: faces.concat(),
         },
-        size,
+    )
+}
+
+/// Construct a texture from a KTX2 file.
+///
+/// The texture is uploaded in the format stored in the file. An error is
+/// returned if `device` cannot sample that format, or if the file holds a Basis
+/// Universal payload, which is not supported (see the `ktx` module).
+#[cfg(feature = "ktx2")]
+pub fn from_ktx2_bytes(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    ktx2_bytes: &[u8],
+    label: Option<&str>,
+) -> Result<Texture> {
+    let image = ktx::parse(ktx2_bytes)?;
+
+    ensure!(
+        can_sample_format(device, image.format, image.width, image.height),
+        "KTX2 texture {label:?} is stored as {:?} which this device cannot sample",
+        image.format
     );
 
-    texture
+    let size = wgpu::Extent3d {
+        width: image.width,
+        height: image.height,
+        depth_or_array_layers: 1,
+    };
+
+    from_mip_levels(device, queue, image.format, size, &image.mips(), label)
+}
+
+/// Returns true if `image` can be uploaded to `device` without decompressing
+/// it first.
+fn can_sample_compressed(device: &wgpu::Device, image: &dds::DdsImage) -> bool {
+    can_sample_format(device, image.format, image.width, image.height)
+}
+
+/// Returns true if `device` can sample a `width` by `height` texture stored as
+/// `format`. Block compressed textures must be a whole number of blocks.
+fn can_sample_format(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> bool {
+    let (block_width, block_height) = format.block_dimensions();
+
+    device.features().contains(format.required_features())
+        && width.is_multiple_of(block_width)
+        && height.is_multiple_of(block_height)
+}
+
+/// Create a wgpu texture object from a block compressed DDS image, copying the
+/// compressed blocks of every mip level as is.
+fn from_compressed_dds(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &dds::DdsImage,
+    label: Option<&str>,
+) -> Result<Texture> {
+    let size = wgpu::Extent3d {
+        width: image.width,
+        height: image.height,
+        depth_or_array_layers: 1,
+    };
+
+    // Mip levels are stored one after another with no padding in between.
+    let mut mips = Vec::with_capacity(image.mip_level_count as usize);
+    let mut offset = 0;
+
+    for mip_level in 0..image.mip_level_count {
+        let mip_bytes = MipLayout::new(image.format, size, mip_level).byte_len();
+
+        let Some(mip_data) = image.data.get(offset..offset + mip_bytes) else {
+            bail!("DDS texture {label:?} is missing data for mip level {mip_level}");
+        };
+
+        mips.push(mip_data);
+        offset += mip_bytes;
+    }
+
+    from_mip_levels(device, queue, image.format, size, &mips, label)
+}
+
+/// The memory layout of a single texture mip level. Compressed formats are
+/// measured in blocks rather than texels, and mip levels smaller than a block
+/// still take up an entire block.
+struct MipLayout {
+    /// Size of the mip level rounded up to a whole number of blocks.
+    size: wgpu::Extent3d,
+    bytes_per_row: u32,
+    rows: u32,
+}
+
+impl MipLayout {
+    fn new(format: wgpu::TextureFormat, base_size: wgpu::Extent3d, mip_level: u32) -> Self {
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format
+            .block_copy_size(None)
+            .expect("color formats have a block size");
+        let size = base_size
+            .mip_level_size(mip_level, wgpu::TextureDimension::D2)
+            .physical_size(format);
+
+        Self {
+            size,
+            bytes_per_row: size.width / block_width * block_size,
+            rows: size.height / block_height,
+        }
+    }
+
+    fn byte_len(&self) -> usize {
+        (self.bytes_per_row * self.rows) as usize
+    }
+}
+
+/// Create a texture with one mip level for each entry in `mips`. Each entry
+/// holds the texel (or block) data for that mip level in the layout described
+/// by `MipLayout`.
+fn from_mip_levels(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    mips: &[&[u8]],
+    label: Option<&str>,
+) -> Result<Texture> {
+    let mut data = Vec::new();
+
+    for (mip_level, mip_data) in mips.iter().enumerate() {
+        let layout = MipLayout::new(format, size, mip_level as u32);
+
+        ensure!(
+            mip_data.len() >= layout.byte_len(),
+            "texture {label:?} mip level {mip_level} has {} bytes but needs {}",
+            mip_data.len(),
+            layout.byte_len()
+        );
+
+        data.extend_from_slice(&mip_data[..layout.byte_len()]);
+    }
+
+    Ok(Texture::upload(
+        device,
+        queue,
+        TextureSource {
+            label: label.map(String::from),
+            format,
+            size,
+            mip_level_count: mips.len() as u32,
+            usage: SAMPLED_USAGE,
+            data,
+        },
+    ))
 }
 
 /// Create a default texture sampler with sane defaults.
@@ -111,3 +524,57 @@ pub fn create_default_sampler(device: &wgpu::Device) -> wgpu::Sampler {
         ..Default::default()
     })
 }
+
+/// Create a texture sampler that repeats the texture when sampled outside of
+/// the [0, 1] range.
+pub fn create_repeating_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        address_mode_w: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_renderer;
+
+    #[test]
+    fn crate_dds_textures_stay_compressed() {
+        let Some(renderer) = test_renderer(4, 4) else {
+            return;
+        };
+
+        let device = renderer.gpu().device();
+        let expected_format = if device
+            .features()
+            .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+        {
+            wgpu::TextureFormat::Bc1RgbaUnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        };
+
+        for image_bytes in [
+            include_bytes!("../../content/crate_diffuse.dds").as_slice(),
+            include_bytes!("../../content/crate_specular.dds").as_slice(),
+            include_bytes!("../../content/matrix_emissive.dds").as_slice(),
+        ] {
+            let texture = from_image_bytes(
+                device,
+                renderer.gpu().queue(),
+                image_bytes,
+                ColorSpace::Srgb,
+                None,
+            )
+            .unwrap();
+
+            assert_eq!(texture.format(), expected_format);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Converts images into the six faces of a cube map.
+use std::f32::consts::{PI, TAU};
+
+use glam::{Vec3, Vec4};
+use image::{Rgba, Rgba32FImage};
+
+/// Number of faces in a cube map.
+pub const FACE_COUNT: usize = 6;
+
+/// Get the direction from the center of a cube map through a point on one of
+/// its faces.
+///
+/// Faces are in cube map layer order (+X, -X, +Y, -Y, +Z, -Z). `u` and `v` are
+/// texture coordinates on the face in the range [0, 1], with `v` increasing
+/// from the top row of the face to the bottom row.
+pub fn face_direction(face: usize, u: f32, v: f32) -> Vec3 {
+    let s = 2.0 * u - 1.0;
+    let t = 2.0 * v - 1.0;
+
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        5 => Vec3::new(-s, -t, -1.0),
+        _ => panic!("cube maps only have {FACE_COUNT} faces but face {face} was requested"),
+    }
+    .normalize()
+}
+
+/// Project an equirectangular (latitude/longitude) panorama onto the six faces
+/// of a cube map that are each `face_size` texels wide.
+///
+/// The center of the panorama faces -Z, and its top row is straight up.
+pub fn equirect_to_faces(panorama: &Rgba32FImage, face_size: u32) -> Vec<Rgba32FImage> {
+    (0..FACE_COUNT)
+        .map(|face| {
+            Rgba32FImage::from_fn(face_size, face_size, |x, y| {
+                let direction = face_direction(
+                    face,
+                    (x as f32 + 0.5) / face_size as f32,
+                    (y as f32 + 0.5) / face_size as f32,
+                );
+
+                let u = 0.5 + direction.x.atan2(-direction.z) / TAU;
+                let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+
+                Rgba(sample_bilinear(panorama, u, v).to_array())
+            })
+        })
+        .collect()
+}
+
+/// Sample `image` at texture coordinates `u` and `v`, wrapping horizontally
+/// and clamping vertically.
+fn sample_bilinear(image: &Rgba32FImage, u: f32, v: f32) -> Vec4 {
+    let (width, height) = image.dimensions();
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let texel = |x: f32, y: f32| {
+        let x = (x as i64).rem_euclid(width as i64) as u32;
+        let y = (y as u32).min(height - 1);
+        Vec4::from(image.get_pixel(x, y).0)
+    };
+
+    let top = texel(x0, y0).lerp(texel(x0 + 1.0, y0), fx);
+    let bottom = texel(x0, y0 + 1.0).lerp(texel(x0 + 1.0, y0 + 1.0), fx);
+    top.lerp(bottom, fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_centers_point_along_their_axis() {
+        let axes = [
+            Vec3::X,
+            Vec3::NEG_X,
+            Vec3::Y,
+            Vec3::NEG_Y,
+            Vec3::Z,
+            Vec3::NEG_Z,
+        ];
+
+        for (face, axis) in axes.into_iter().enumerate() {
+            assert_eq!(axis, face_direction(face, 0.5, 0.5));
+        }
+
+        // The top row of the side faces looks up.
+        assert!(face_direction(4, 0.5, 0.0).y > 0.0);
+    }
+
+    #[test]
+    fn equirect_sky_and_ground_map_to_top_and_bottom_faces() {
+        let sky = Rgba([0.2, 0.4, 1.0, 1.0]);
+        let ground = Rgba([0.3, 0.2, 0.1, 1.0]);
+        let panorama = Rgba32FImage::from_fn(16, 8, |_, y| if y < 4 { sky } else { ground });
+
+        let faces = equirect_to_faces(&panorama, 4);
+
+        assert_eq!(FACE_COUNT, faces.len());
+        assert!(faces[2].pixels().all(|p| *p == sky));
+        assert!(faces[3].pixels().all(|p| *p == ground));
+        assert_eq!(sky, *faces[5].get_pixel(2, 0));
+        assert_eq!(ground, *faces[5].get_pixel(2, 3));
+    }
+}
@@ -0,0 +1,185 @@
+//! A minimal DDS reader for block compressed (BCn) images.
+//!
+//! Only the parts of the DDS format needed to upload compressed blocks directly
+//! to the GPU are read. Uncompressed DDS images are left to the `image` crate.
+use thiserror::Error;
+
+use super::ColorSpace;
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 124;
+const DX10_HEADER_SIZE: usize = 20;
+
+/// Header flag set when the mip map count field is valid.
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+/// Pixel format flag set when the four character code is valid.
+const DDPF_FOURCC: u32 = 0x4;
+
+/// A block compressed DDS image.
+#[derive(Debug)]
+pub struct DdsImage<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub mip_level_count: u32,
+    pub format: wgpu::TextureFormat,
+    /// Compressed blocks for each mip level, stored one after another starting
+    /// with the largest mip level.
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Error)]
+pub enum DdsError {
+    #[error("image data is not a DDS file")]
+    NotDds,
+    #[error("DDS file is truncated")]
+    Truncated,
+    #[error("DDS pixel format {0} is not block compressed or not supported")]
+    UnsupportedFormat(String),
+}
+
+/// Returns true if `bytes` starts with the DDS magic number.
+pub fn is_dds(bytes: &[u8]) -> bool {
+    bytes.starts_with(DDS_MAGIC)
+}
+
+/// Read the header of a block compressed DDS image. `color_space` picks between
+/// the sRGB and linear variant of formats that have both.
+pub fn parse(bytes: &[u8], color_space: ColorSpace) -> Result<DdsImage<'_>, DdsError> {
+    if !is_dds(bytes) {
+        return Err(DdsError::NotDds);
+    }
+
+    let read_u32 = |offset: usize| -> Result<u32, DdsError> {
+        bytes
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or(DdsError::Truncated)
+    };
+
+    // Header fields are offset by the four byte magic number.
+    let flags = read_u32(8)?;
+    let height = read_u32(12)?;
+    let width = read_u32(16)?;
+    let mip_level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        read_u32(28)?.max(1)
+    } else {
+        1
+    };
+    let pixel_format_flags = read_u32(80)?;
+    let four_cc = bytes.get(84..88).ok_or(DdsError::Truncated)?;
+
+    if pixel_format_flags & DDPF_FOURCC == 0 {
+        return Err(DdsError::UnsupportedFormat("uncompressed".to_string()));
+    }
+
+    let srgb = color_space == ColorSpace::Srgb;
+    let mut data_offset = 4 + HEADER_SIZE;
+
+    let format = match four_cc {
+        b"DXT1" => bc1(srgb),
+        b"DXT3" => bc2(srgb),
+        b"DXT5" => bc3(srgb),
+        b"ATI1" | b"BC4U" => wgpu::TextureFormat::Bc4RUnorm,
+        b"ATI2" | b"BC5U" => wgpu::TextureFormat::Bc5RgUnorm,
+        b"DX10" => {
+            data_offset += DX10_HEADER_SIZE;
+            dxgi_format(read_u32(4 + HEADER_SIZE)?, srgb)?
+        }
+        other => {
+            return Err(DdsError::UnsupportedFormat(
+                String::from_utf8_lossy(other).into_owned(),
+            ))
+        }
+    };
+
+    Ok(DdsImage {
+        width,
+        height,
+        mip_level_count,
+        format,
+        data: bytes.get(data_offset..).ok_or(DdsError::Truncated)?,
+    })
+}
+
+fn bc1(srgb: bool) -> wgpu::TextureFormat {
+    if srgb {
+        wgpu::TextureFormat::Bc1RgbaUnormSrgb
+    } else {
+        wgpu::TextureFormat::Bc1RgbaUnorm
+    }
+}
+
+fn bc2(srgb: bool) -> wgpu::TextureFormat {
+    if srgb {
+        wgpu::TextureFormat::Bc2RgbaUnormSrgb
+    } else {
+        wgpu::TextureFormat::Bc2RgbaUnorm
+    }
+}
+
+fn bc3(srgb: bool) -> wgpu::TextureFormat {
+    if srgb {
+        wgpu::TextureFormat::Bc3RgbaUnormSrgb
+    } else {
+        wgpu::TextureFormat::Bc3RgbaUnorm
+    }
+}
+
+fn bc7(srgb: bool) -> wgpu::TextureFormat {
+    if srgb {
+        wgpu::TextureFormat::Bc7RgbaUnormSrgb
+    } else {
+        wgpu::TextureFormat::Bc7RgbaUnorm
+    }
+}
+
+/// Map a `DXGI_FORMAT` value from a DX10 header to a texture format. The caller's
+/// color space is used for typeless formats.
+fn dxgi_format(dxgi_format: u32, srgb: bool) -> Result<wgpu::TextureFormat, DdsError> {
+    Ok(match dxgi_format {
+        70 => bc1(srgb),
+        71 => wgpu::TextureFormat::Bc1RgbaUnorm,
+        72 => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+        73 => bc2(srgb),
+        74 => wgpu::TextureFormat::Bc2RgbaUnorm,
+        75 => wgpu::TextureFormat::Bc2RgbaUnormSrgb,
+        76 => bc3(srgb),
+        77 => wgpu::TextureFormat::Bc3RgbaUnorm,
+        78 => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+        79 | 80 => wgpu::TextureFormat::Bc4RUnorm,
+        81 => wgpu::TextureFormat::Bc4RSnorm,
+        82 | 83 => wgpu::TextureFormat::Bc5RgUnorm,
+        84 => wgpu::TextureFormat::Bc5RgSnorm,
+        97 => bc7(srgb),
+        98 => wgpu::TextureFormat::Bc7RgbaUnorm,
+        99 => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+        other => return Err(DdsError::UnsupportedFormat(format!("DXGI {other}"))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_dxt1_and_dx10_headers() {
+        let crate_diffuse = parse(
+            include_bytes!("../../../content/crate_diffuse.dds"),
+            ColorSpace::Srgb,
+        )
+        .unwrap();
+
+        assert_eq!(crate_diffuse.format, wgpu::TextureFormat::Bc1RgbaUnormSrgb);
+        assert_eq!((crate_diffuse.width, crate_diffuse.height), (500, 500));
+        assert_eq!(crate_diffuse.data.len(), 125 * 125 * 8);
+
+        let emissive = parse(
+            include_bytes!("../../../content/matrix_emissive.dds"),
+            ColorSpace::Linear,
+        )
+        .unwrap();
+
+        assert_eq!(emissive.format, wgpu::TextureFormat::Bc1RgbaUnormSrgb);
+        assert_eq!(emissive.mip_level_count, 9);
+    }
+}
@@ -0,0 +1,176 @@
+//! Loads textures stored in KTX2 containers.
+//!
+//! KTX2 textures that are already stored in a GPU format (eg BCn, ETC2 or ASTC)
+//! are uploaded as is when the device can sample that format.
+//!
+//! Basis Universal payloads (ETC1S and UASTC) are not supported, and are
+//! reported as an error asking for the file to be re-encoded. Transcoding them
+//! needs the C++ Basis Universal transcoder, which the `basis-universal` crate
+//! builds from source, and that cannot be built for the wasm32 target the demos
+//! also run on. Textures should instead be encoded to the GPU formats they are
+//! shipped in, eg with `toktx --encode astc`.
+use anyhow::*;
+use ktx2::{Format, SupercompressionScheme};
+
+/// Bytes every KTX2 file starts with.
+const KTX2_MAGIC: &[u8] = &[
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Returns true if `bytes` starts with the KTX2 file identifier.
+pub fn is_ktx2(bytes: &[u8]) -> bool {
+    bytes.starts_with(KTX2_MAGIC)
+}
+
+/// Read the texture format, size and mip levels of a KTX2 file.
+pub fn parse(bytes: &[u8]) -> Result<Ktx2Image<'_>> {
+    let reader = ktx2::Reader::new(bytes).map_err(|e| anyhow!("invalid KTX2 file: {e:?}"))?;
+    let header = reader.header();
+
+    if let Some(scheme) = header.supercompression_scheme {
+        if scheme == SupercompressionScheme::BasisLZ {
+            bail!("KTX2 file is Basis Universal (ETC1S) encoded, which is not supported. Re-encode it to a GPU format such as BC7, ETC2 or ASTC");
+        }
+
+        bail!("KTX2 supercompression scheme {scheme:?} is not supported");
+    }
+
+    let Some(format) = header.format else {
+        bail!(
+            "KTX2 file has no texture format, which means it is Basis Universal (UASTC) encoded and is not supported. Re-encode it to a GPU format such as BC7, ETC2 or ASTC"
+        );
+    };
+
+    ensure!(
+        header.pixel_depth <= 1 && header.layer_count <= 1 && header.face_count == 1,
+        "only 2D KTX2 textures are supported"
+    );
+
+    Ok(Ktx2Image {
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        format: texture_format(format)?,
+        reader,
+    })
+}
+
+/// A KTX2 image stored in a format that can be uploaded to the GPU as is.
+pub struct Ktx2Image<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    reader: ktx2::Reader<&'a [u8]>,
+}
+
+impl Ktx2Image<'_> {
+    /// Get the texel (or block) data for each mip level starting with the
+    /// largest.
+    pub fn mips(&self) -> Vec<&[u8]> {
+        self.reader.levels().map(|level| level.data).collect()
+    }
+}
+
+/// Get the texture format matching a KTX2 (Vulkan) texture format.
+fn texture_format(format: Format) -> Result<wgpu::TextureFormat> {
+    use wgpu::{AstcBlock, AstcChannel, TextureFormat as T};
+
+    let astc = |block, channel| T::Astc { block, channel };
+
+    Ok(match format {
+        Format::R8G8B8A8_UNORM => T::Rgba8Unorm,
+        Format::R8G8B8A8_SRGB => T::Rgba8UnormSrgb,
+        Format::BC1_RGBA_UNORM_BLOCK => T::Bc1RgbaUnorm,
+        Format::BC1_RGBA_SRGB_BLOCK => T::Bc1RgbaUnormSrgb,
+        Format::BC2_UNORM_BLOCK => T::Bc2RgbaUnorm,
+        Format::BC2_SRGB_BLOCK => T::Bc2RgbaUnormSrgb,
+        Format::BC3_UNORM_BLOCK => T::Bc3RgbaUnorm,
+        Format::BC3_SRGB_BLOCK => T::Bc3RgbaUnormSrgb,
+        Format::BC4_UNORM_BLOCK => T::Bc4RUnorm,
+        Format::BC4_SNORM_BLOCK => T::Bc4RSnorm,
+        Format::BC5_UNORM_BLOCK => T::Bc5RgUnorm,
+        Format::BC5_SNORM_BLOCK => T::Bc5RgSnorm,
+        Format::BC6H_UFLOAT_BLOCK => T::Bc6hRgbUfloat,
+        Format::BC6H_SFLOAT_BLOCK => T::Bc6hRgbFloat,
+        Format::BC7_UNORM_BLOCK => T::Bc7RgbaUnorm,
+        Format::BC7_SRGB_BLOCK => T::Bc7RgbaUnormSrgb,
+        Format::ETC2_R8G8B8_UNORM_BLOCK => T::Etc2Rgb8Unorm,
+        Format::ETC2_R8G8B8_SRGB_BLOCK => T::Etc2Rgb8UnormSrgb,
+        Format::ETC2_R8G8B8A1_UNORM_BLOCK => T::Etc2Rgb8A1Unorm,
+        Format::ETC2_R8G8B8A1_SRGB_BLOCK => T::Etc2Rgb8A1UnormSrgb,
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK => T::Etc2Rgba8Unorm,
+        Format::ETC2_R8G8B8A8_SRGB_BLOCK => T::Etc2Rgba8UnormSrgb,
+        Format::EAC_R11_UNORM_BLOCK => T::EacR11Unorm,
+        Format::EAC_R11_SNORM_BLOCK => T::EacR11Snorm,
+        Format::EAC_R11G11_UNORM_BLOCK => T::EacRg11Unorm,
+        Format::EAC_R11G11_SNORM_BLOCK => T::EacRg11Snorm,
+        Format::ASTC_4x4_UNORM_BLOCK => astc(AstcBlock::B4x4, AstcChannel::Unorm),
+        Format::ASTC_4x4_SRGB_BLOCK => astc(AstcBlock::B4x4, AstcChannel::UnormSrgb),
+        Format::ASTC_6x6_UNORM_BLOCK => astc(AstcBlock::B6x6, AstcChannel::Unorm),
+        Format::ASTC_6x6_SRGB_BLOCK => astc(AstcBlock::B6x6, AstcChannel::UnormSrgb),
+        Format::ASTC_8x8_UNORM_BLOCK => astc(AstcBlock::B8x8, AstcChannel::Unorm),
+        Format::ASTC_8x8_SRGB_BLOCK => astc(AstcBlock::B8x8, AstcChannel::UnormSrgb),
+        other => bail!("KTX2 texture format {other:?} is not supported"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a minimal 2D KTX2 file with a single mip level.
+    fn ktx2_file(vk_format: u32, supercompression: u32, width: u32, data: &[u8]) -> Vec<u8> {
+        const HEADER_LEN: u32 = 80;
+        const LEVEL_INDEX_LEN: u32 = 24;
+        const DFD_LEN: u32 = 4;
+
+        let dfd_offset = HEADER_LEN + LEVEL_INDEX_LEN;
+        let data_offset = dfd_offset + DFD_LEN;
+
+        let mut bytes = KTX2_MAGIC.to_vec();
+
+        // vkFormat, typeSize, width, height, depth, layers, faces, levels and
+        // supercompression scheme.
+        for value in [vk_format, 1, width, width, 0, 0, 1, 1, supercompression] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // The DFD only holds its own length. There is no key/value or global
+        // data, so those point at the start of the level data.
+        for value in [dfd_offset, DFD_LEN, data_offset, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for value in [data_offset as u64, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for value in [data_offset as u64, data.len() as u64, data.len() as u64] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&DFD_LEN.to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn reads_bc1_texture() {
+        let file = ktx2_file(Format::BC1_RGBA_SRGB_BLOCK.value(), 0, 8, &[0; 32]);
+        assert!(is_ktx2(&file));
+        let image = parse(&file).unwrap();
+
+        assert_eq!(image.format, wgpu::TextureFormat::Bc1RgbaUnormSrgb);
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(image.mips().len(), 1);
+        assert_eq!(image.mips()[0].len(), 32);
+    }
+
+    #[test]
+    fn basis_payloads_are_an_error() {
+        let etc1s = ktx2_file(0, SupercompressionScheme::BasisLZ.value(), 4, &[0; 16]);
+        assert!(parse(&etc1s).is_err());
+
+        let uastc = ktx2_file(0, 0, 4, &[0; 16]);
+        assert!(parse(&uastc).is_err());
+    }
+}
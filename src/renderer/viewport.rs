@@ -0,0 +1,86 @@
+/// A rectangular region of the render target that a camera draws into. The
+/// origin is the top left corner of the render target, and all values are in
+/// physical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// A viewport that covers an entire `width` by `height` render target.
+    pub fn full(width: u32, height: u32) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    /// Split a `width` by `height` render target into a left and right
+    /// viewport for two player split-screen. The right viewport gets the extra
+    /// column when `width` is odd.
+    pub fn side_by_side(width: u32, height: u32) -> [Self; 2] {
+        let left_width = width / 2;
+
+        [
+            Self::new(0, 0, left_width, height),
+            Self::new(left_width, 0, width - left_width, height),
+        ]
+    }
+
+    /// Returns true if the viewport does not cover any pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Clamp the viewport to fit inside of a `width` by `height` render target.
+    /// The render pass rejects scissor rects that extend past the target.
+    pub fn clamp_to(&self, width: u32, height: u32) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+
+        Self::new(x, y, self.width.min(width - x), self.height.min(height - y))
+    }
+
+    /// Restrict drawing in `render_pass` to this viewport.
+    pub fn apply(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_viewport(
+            self.x as f32,
+            self.y as f32,
+            self.width as f32,
+            self.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(self.x, self.y, self.width, self.height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_by_side_viewports_cover_the_target() {
+        let [left, right] = Viewport::side_by_side(101, 50);
+
+        assert_eq!(left, Viewport::new(0, 0, 50, 50));
+        assert_eq!(right, Viewport::new(50, 0, 51, 50));
+    }
+
+    #[test]
+    fn clamped_viewport_stays_inside_the_target() {
+        let viewport = Viewport::new(60, 10, 100, 100).clamp_to(64, 32);
+        assert_eq!(viewport, Viewport::new(60, 10, 4, 22));
+
+        assert!(Viewport::new(80, 0, 10, 10).clamp_to(64, 32).is_empty());
+    }
+}
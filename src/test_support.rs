@@ -0,0 +1,55 @@
+//! Helpers shared by tests that draw with a graphics adapter.
+use std::io::Write;
+
+use glam::Vec3;
+
+use crate::{camera::Camera, renderer::Renderer};
+
+/// Environment variable that makes tests fail instead of being skipped when no
+/// graphics adapter is found, eg on CI machines that are expected to have one.
+const REQUIRE_GPU_VAR: &str = "SQUIRREL_REQUIRE_GPU";
+
+/// Create a `width` by `height` headless renderer, or `None` when there is no
+/// graphics adapter and the test should return early. See `skip_without_gpu`.
+pub fn test_renderer(width: u32, height: u32) -> Option<Renderer<'static>> {
+    match pollster::block_on(Renderer::new_headless(width, height)) {
+        Ok(renderer) => Some(renderer),
+        Err(e) => {
+            skip_without_gpu(format!("{e:#}"));
+            None
+        }
+    }
+}
+
+/// Report that the running test is skipped since no graphics adapter was found
+/// because of `reason`, or fail it if `SQUIRREL_REQUIRE_GPU` is set.
+///
+/// Skipped tests still pass, so the skip is written straight to stderr where
+/// the test harness does not capture it.
+pub fn skip_without_gpu(reason: impl std::fmt::Display) {
+    if std::env::var_os(REQUIRE_GPU_VAR).is_some() {
+        panic!("{REQUIRE_GPU_VAR} is set but no graphics adapter was found: {reason}");
+    }
+
+    let thread = std::thread::current();
+    let test = thread.name().unwrap_or("test");
+    let _ = writeln!(
+        std::io::stderr(),
+        "skipping {test}, no graphics adapter was found: {reason}"
+    );
+}
+
+/// Create a camera at +Z looking at the origin with a `width` by `height`
+/// viewport.
+pub fn test_camera(width: u32, height: u32) -> Camera {
+    Camera::new(
+        Vec3::new(0.0, 0.0, 5.0),
+        Vec3::ZERO,
+        Vec3::Y,
+        f32::to_radians(45.0),
+        0.1,
+        100.0,
+        width,
+        height,
+    )
+}
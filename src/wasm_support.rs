@@ -1,11 +1,62 @@
+use std::{cell::Cell, rc::Rc};
+
 use console_error_panic_hook;
 use tracing::info;
 use tracing_wasm;
-use winit::window::Window;
+use wasm_bindgen::{closure::Closure, JsCast};
+use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Window};
+
+/// Id of the page element that the render canvas is placed in.
+const CONTAINER_ELEMENT_ID: &str = "wasm-container";
+
+/// Name of the URL query parameter that overrides the default log filter, eg
+/// `index.html?log=wgpu=debug,squirrel=trace`.
+const LOG_FILTER_QUERY_PARAM: &str = "log";
+/// Name of a JavaScript global that overrides the default log filter when it is
+/// set before the wasm module starts. The URL query parameter takes priority.
+const LOG_FILTER_GLOBAL: &str = "SQUIRREL_LOG";
+/// Name of the URL query parameter that picks the demo that runs first, eg
+/// `index.html?demo=lighting`.
+const DEMO_QUERY_PARAM: &str = "demo";
 
 pub fn logging_init() {
+    use tracing_subscriber::layer::SubscriberExt;
+
     console_error_panic_hook::set_once();
-    tracing_wasm::set_as_global_default();
+
+    crate::logging::init(
+        tracing_subscriber::registry().with(tracing_wasm::WASMLayer::default()),
+        log_filter_override().as_deref(),
+    );
+}
+
+/// Get the value of the page URL's query parameter called `name`, if any.
+fn query_param(name: &str) -> Option<String> {
+    web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|search| {
+            search
+                .trim_start_matches('?')
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == name)
+                .and_then(|(_, value)| js_sys::decode_uri_component(value).ok())
+                .map(String::from)
+        })
+}
+
+/// Get the log filter directives set by the page, if any.
+fn log_filter_override() -> Option<String> {
+    query_param(LOG_FILTER_QUERY_PARAM).or_else(|| {
+        js_sys::Reflect::get(&js_sys::global(), &LOG_FILTER_GLOBAL.into())
+            .ok()
+            .and_then(|value| value.as_string())
+    })
+}
+
+/// Get the name of the demo that the page asked to run first, if any.
+pub fn demo_name() -> Option<String> {
+    query_param(DEMO_QUERY_PARAM)
 }
 
 pub fn create_canvas(window: &Window) {
@@ -13,10 +64,134 @@ pub fn create_canvas(window: &Window) {
     web_sys::window()
         .and_then(|w| w.document())
         .and_then(|d| {
-            let element = d.get_element_by_id("wasm-container")?;
+            let element = d.get_element_by_id(CONTAINER_ELEMENT_ID)?;
             let canvas = web_sys::Element::from(window.canvas().unwrap());
             element.append_child(&canvas).ok()?;
             Some(())
         })
         .expect("failed to append canvas to document body.");
 }
+
+/// Replace the contents of the canvas container element with `message`, eg to
+/// explain why the game could not start.
+pub fn show_error(message: &str) {
+    if let Some(element) = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(CONTAINER_ELEMENT_ID))
+    {
+        element.set_text_content(Some(message));
+    }
+}
+
+/// Keeps the render canvas the same size as its container element.
+///
+/// The browser does not resize the canvas when the container is resized by the
+/// page layout, so the container is watched with a `ResizeObserver`. When its
+/// size changes the canvas is resized to match and the event loop is woken up
+/// with a user event. Call `take_resize` when the user event arrives to get the
+/// new physical size of the canvas.
+pub struct CanvasResizeObserver {
+    observer: web_sys::ResizeObserver,
+    _callback: Closure<dyn FnMut(js_sys::Array, web_sys::ResizeObserver)>,
+    pending_size: Rc<Cell<Option<PhysicalSize<u32>>>>,
+}
+
+impl CanvasResizeObserver {
+    /// Start resizing `window`'s canvas to fill the canvas container element.
+    pub fn new(window: &Window, event_loop_proxy: EventLoopProxy<()>) -> Self {
+        use winit::platform::web::WindowExtWebSys;
+
+        let container = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id(CONTAINER_ELEMENT_ID))
+            .expect("canvas container element is missing from the document");
+        let canvas = window.canvas().expect("window does not have a canvas");
+        let pending_size = Rc::new(Cell::new(None));
+
+        let callback = {
+            let container = container.clone();
+            let pending_size = pending_size.clone();
+
+            Closure::new(move |_: js_sys::Array, _: web_sys::ResizeObserver| {
+                // Client sizes are in CSS pixels, so scale them by the device
+                // pixel ratio to render crisply on high-DPI displays.
+                let (css_width, css_height) = (container.client_width(), container.client_height());
+                let pixel_ratio = web_sys::window().map_or(1.0, |w| w.device_pixel_ratio());
+                let new_size = PhysicalSize::new(
+                    (css_width as f64 * pixel_ratio).round() as u32,
+                    (css_height as f64 * pixel_ratio).round() as u32,
+                );
+
+                if new_size.width == 0
+                    || new_size.height == 0
+                    || new_size == PhysicalSize::new(canvas.width(), canvas.height())
+                {
+                    return;
+                }
+
+                let style = canvas.style();
+                let _ = style.set_property("width", &format!("{css_width}px"));
+                let _ = style.set_property("height", &format!("{css_height}px"));
+                canvas.set_width(new_size.width);
+                canvas.set_height(new_size.height);
+
+                info!("canvas container resized to {new_size:?}");
+                pending_size.set(Some(new_size));
+                let _ = event_loop_proxy.send_event(());
+            })
+        };
+
+        let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref())
+            .expect("failed to create canvas container resize observer");
+        observer.observe(&container);
+
+        Self {
+            observer,
+            _callback: callback,
+            pending_size,
+        }
+    }
+
+    /// Returns the new physical size of the canvas if the container was resized
+    /// since the last time this method was called.
+    pub fn take_resize(&self) -> Option<PhysicalSize<u32>> {
+        self.pending_size.take()
+    }
+}
+
+impl Drop for CanvasResizeObserver {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
+/// Ask the browser to lock the mouse pointer to `window`'s canvas. Browsers
+/// only grant the lock in response to a user gesture (eg a click), and it is
+/// granted asynchronously so use `is_pointer_locked` to check if it worked.
+pub fn request_pointer_lock(window: &Window) -> bool {
+    use winit::platform::web::WindowExtWebSys;
+
+    match window.canvas() {
+        Some(canvas) => {
+            canvas.request_pointer_lock();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Release a pointer lock held by the page.
+pub fn exit_pointer_lock() {
+    if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+        document.exit_pointer_lock();
+    }
+}
+
+/// Returns true if the page currently holds the pointer lock. The browser
+/// releases the lock without notifying the page when escape is pressed.
+pub fn is_pointer_locked() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.pointer_lock_element())
+        .is_some()
+}